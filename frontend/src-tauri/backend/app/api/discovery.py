@@ -6,7 +6,7 @@ API routes for server discovery
 """
 
 from fastapi import APIRouter, HTTPException
-from typing import Dict, Any
+from typing import Dict, Any, List, Optional
 import logging
 import os
 import asyncio
@@ -34,6 +34,72 @@ GPU_FALLBACK_NAMES = {
     "10de:20f1": "A100 SXM4 80GB",
 }
 
+# AI workloads the wizard can pre-select, with the VRAM one GPU needs for
+# each and the lowest compute capability that runs it. Budgets are for the
+# usual containers, not the bare model weights.
+GPU_WORKLOADS = [
+    {"id": "embeddings", "min_memory_gb": 4, "min_compute_cap": 7.0},
+    {"id": "llm-small", "min_memory_gb": 8, "min_compute_cap": 7.0},
+    {"id": "image-generation", "min_memory_gb": 12, "min_compute_cap": 7.0},
+    {"id": "llm-medium", "min_memory_gb": 20, "min_compute_cap": 7.5},
+    {"id": "fine-tuning", "min_memory_gb": 24, "min_compute_cap": 8.0},
+    {"id": "llm-large", "min_memory_gb": 48, "min_compute_cap": 8.0},
+]
+
+
+def parse_nvidia_smi_gpus(lines: List[str], memory_bytes: int) -> List[Dict[str, Any]]:
+    """Per-GPU facts from `nvidia-smi --query-gpu=index,name,memory.total,compute_cap,mig.mode.current`
+
+    Older drivers know neither compute_cap nor MIG, so those may be missing;
+    GPUs without MIG support report "[N/A]". So do GPUs sharing system
+    memory, like the GB10, which get the node's memory instead.
+    """
+    def known(value: str) -> Optional[str]:
+        return value if value and not value.startswith("[") else None
+
+    gpus = []
+    for line in lines:
+        parts = [p.strip() for p in line.split(",")]
+        if len(parts) < 3:
+            continue
+        unified_memory = parts[2].startswith("[")
+        try:
+            memory_mib = memory_bytes // (1024 * 1024) if unified_memory else int(float(parts[2]))
+        except ValueError:
+            memory_mib = None
+        mig = known(parts[4]) if len(parts) >= 5 else None
+        gpus.append({
+            "index": int(parts[0]) if parts[0].isdigit() else len(gpus),
+            "name": parts[1],
+            "memory_mib": memory_mib,
+            "unified_memory": unified_memory,
+            "compute_cap": known(parts[3]) if len(parts) >= 4 else None,
+            "mig_mode": mig.lower() if mig else None,
+        })
+    return gpus
+
+
+def feasible_workloads(gpus: List[Dict[str, Any]]) -> List[str]:
+    """IDs of the GPU_WORKLOADS some GPU on the node has room for
+
+    A GPU with MIG enabled is split into smaller instances, so it doesn't
+    count; neither does one whose compute capability is unknown. Cards
+    report a little under their nominal size, hence the 5% slack.
+    """
+    feasible = []
+    for workload in GPU_WORKLOADS:
+        for gpu in gpus:
+            if gpu["mig_mode"] == "enabled" or not gpu["memory_mib"] or not gpu["compute_cap"]:
+                continue
+            try:
+                compute_cap = float(gpu["compute_cap"])
+            except ValueError:
+                continue
+            if gpu["memory_mib"] >= workload["min_memory_gb"] * 1024 * 0.95 and compute_cap >= workload["min_compute_cap"]:
+                feasible.append(workload["id"])
+                break
+    return feasible
+
 
 async def detect_lvm_status(
     ip_address: str,
@@ -196,6 +262,27 @@ else
 fi
 echo '",'
 
+# Per-GPU memory (MiB), compute capability and MIG mode. Drivers older
+# than 510 reject the whole query over compute_cap, so retry without it.
+echo -n '"nvidia_smi_gpus": ['
+if command -v nvidia-smi >/dev/null 2>&1; then
+    smi=$(nvidia-smi --query-gpu=index,name,memory.total,compute_cap,mig.mode.current --format=csv,noheader,nounits 2>/dev/null) \
+        || smi=$(nvidia-smi --query-gpu=index,name,memory.total --format=csv,noheader,nounits 2>/dev/null) \
+        || smi=""
+    first=true
+    printf '%s\n' "$smi" | grep -v '^ *$' | while IFS= read -r line; do
+        if [ "$first" = true ]; then
+            first=false
+        else
+            echo -n ","
+        fi
+        echo -n '"'
+        echo -n "$line" | sed 's/"/\\"/g' | tr -d '\n'
+        echo -n '"'
+    done
+fi
+echo "],"
+
 # VFIO info
 echo -n '"vfio_info": "'
 lspci -k 2>/dev/null | grep -A 3 -i nvidia | tr '\n' ' ' | tr '\t' ' ' | sed 's/"/\\"/g' | sed 's/  */ /g' | tr -d '\n' || echo -n ""
@@ -516,6 +603,20 @@ echo "}"
             
             logger.info(f"GPU Summary: {visible_count} visible, {vfio_count} VFIO-bound, {total_gpu_count} total")
 
+        # Per-GPU VRAM, compute capability and MIG mode, for workload planning
+        gpus = parse_nvidia_smi_gpus(
+            raw_data.get("nvidia_smi_gpus", []), int(raw_data.get("memory_bytes", 0) or 0)
+        )
+        hardware_info["gpus"] = gpus
+        hardware_info["gpu_memory_mib"] = sum(gpu["memory_mib"] or 0 for gpu in gpus)
+        hardware_info["feasible_workloads"] = feasible_workloads(gpus)
+        compute_caps = [gpu["compute_cap"] for gpu in gpus if gpu["compute_cap"]]
+        if compute_caps:
+            hardware_info["compute_cap"] = min(compute_caps, key=float)
+            hardware_info["gpu_supported"] = float(hardware_info["compute_cap"]) >= 7.0
+        if gpus:
+            logger.info(f"GPUs on {ip_address}: {gpus}, feasible workloads: {hardware_info['feasible_workloads']}")
+
         # Determine driver status based on GPU detection and driver version
         if total_gpu_count > 0:
             # GPU detected - check driver status
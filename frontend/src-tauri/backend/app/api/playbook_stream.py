@@ -174,7 +174,8 @@ async def stream_playbook_execution(websocket: WebSocket, playbook_name: str):
         
         # Create a temporary vars file for authentication
         import tempfile
-        temp_vars_fd, temp_vars_path = tempfile.mkstemp(suffix='.yml', prefix='ansible-vars-')
+        from app.shared import workspace_dir
+        temp_vars_fd, temp_vars_path = tempfile.mkstemp(suffix='.yml', prefix='ansible-vars-', dir=workspace_dir())
         try:
             with os.fdopen(temp_vars_fd, 'w') as f:
                 import yaml
@@ -218,14 +219,21 @@ async def stream_playbook_execution(websocket: WebSocket, playbook_name: str):
             "-e", f"@{temp_vars_path}",
         ]
 
-        # Add verbosity only for normal mode (profiling uses callbacks for timing)
-        if not PROFILER_ENABLED:
+        # Dry run: report what would change without changing anything
+        if os.environ.get("THINKUBE_DRY_RUN") == "1":
+            cmd.extend(["--check", "--diff"])
+
+        # Add verbosity only for normal mode (profiling uses callbacks for timing);
+        # ANSIBLE_VERBOSITY from the shell's log level takes over when set
+        if not PROFILER_ENABLED and not os.environ.get("ANSIBLE_VERBOSITY"):
             cmd.append("-v")  # Normal verbose
             
         # Set up environment with Ansible specific settings for real-time output
         env = os.environ.copy()
         # Filter out None values — JS null serializes to Python None and breaks subprocess
         env.update({k: str(v) for k, v in environment.items() if v is not None})
+        if PROFILER_ENABLED:
+            env.pop('ANSIBLE_VERBOSITY', None)
         
         # Add venv to PATH if it exists
         if user_venv.exists():
@@ -882,7 +882,8 @@ async def run_setup_script(sudo_password: str):
         if sudo_password:
             # Create a temporary askpass script for sudo
             import tempfile
-            askpass_fd, askpass_path = tempfile.mkstemp()
+            from app.shared import workspace_dir
+            askpass_fd, askpass_path = tempfile.mkstemp(dir=workspace_dir())
             try:
                 with os.fdopen(askpass_fd, 'w') as f:
                     f.write(f'#!/bin/sh\necho "{sudo_password}"\n')
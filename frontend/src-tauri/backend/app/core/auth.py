@@ -0,0 +1,70 @@
+# Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+# SPDX-License-Identifier: Apache-2.0
+
+"""
+Shared-secret authentication for the installer API
+
+The Rust shell generates a random token at startup, passes it to the backend
+as THINKUBE_API_TOKEN and hands it to the webview through the
+`get_api_token` command. Every HTTP request must carry it as
+`Authorization: Bearer <token>`; WebSockets, which can't set headers from a
+browser, pass it as the `token` query parameter.
+"""
+
+import hmac
+import logging
+import os
+from urllib.parse import parse_qs
+
+logger = logging.getLogger(__name__)
+
+# Reachable without the token: the health check identifies the backend to the
+# shell, and shutdown checks the instance token itself
+PUBLIC_PATHS = {"/", "/api/health", "/api/shutdown"}
+
+
+class ApiTokenMiddleware:
+    """Reject HTTP requests and WebSockets that don't present the API token"""
+
+    def __init__(self, app):
+        self.app = app
+        self.token = os.environ.get("THINKUBE_API_TOKEN")
+        if not self.token:
+            logger.warning(
+                "THINKUBE_API_TOKEN is not set; the API is open to every local process. "
+                "This is only expected when running the backend by hand for development."
+            )
+
+    def _presented(self, scope) -> str:
+        if scope["type"] == "websocket":
+            query = parse_qs(scope.get("query_string", b"").decode())
+            return (query.get("token") or [""])[0]
+        for name, value in scope.get("headers", []):
+            if name == b"authorization":
+                scheme, _, credentials = value.decode().partition(" ")
+                if scheme.lower() == "bearer":
+                    return credentials.strip()
+        return ""
+
+    async def __call__(self, scope, receive, send):
+        if (
+            not self.token
+            or scope["type"] not in ("http", "websocket")
+            or scope["path"] in PUBLIC_PATHS
+            # CORS preflights never carry credentials
+            or (scope["type"] == "http" and scope["method"] == "OPTIONS")
+            or hmac.compare_digest(self.token.encode(), self._presented(scope).encode())
+        ):
+            await self.app(scope, receive, send)
+            return
+
+        logger.warning(f"Rejected unauthenticated {scope['type']} request to {scope['path']}")
+        if scope["type"] == "websocket":
+            await send({"type": "websocket.close", "code": 4401})
+            return
+        await send({
+            "type": "http.response.start",
+            "status": 401,
+            "headers": [(b"content-type", b"application/json")],
+        })
+        await send({"type": "http.response.body", "body": b'{"detail": "Missing or invalid API token"}'})
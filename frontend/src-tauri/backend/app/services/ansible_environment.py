@@ -31,6 +31,8 @@ class AnsibleEnvironment:
         self.thinkube_repo_url = "https://github.com/thinkube/thinkube.git"
         self.thinkube_branch = os.environ.get("THINKUBE_BRANCH", "main")
         self.thinkube_clone_dir = None  # Will be set during initialization
+        # Checkout kept by the installer shell (pinned ref, works offline)
+        self.managed_checkout = False
 
     def is_initialized(self) -> bool:
         """Check if Ansible environment is already set up"""
@@ -114,8 +116,33 @@ class AnsibleEnvironment:
 
             logger.info("Installing Ansible")
 
+            # The installer hands over the signed, hash-pinned lock it
+            # verified; a release build never installs without it
+            lock = os.environ.get("THINKUBE_ANSIBLE_LOCK")
+            if lock:
+                if progress_callback:
+                    progress_callback("Installing Ansible from the pinned lock...", 50)
+                logger.info(f"Installing Ansible from {lock}")
+                process = await asyncio.create_subprocess_exec(
+                    str(self.pip_bin), "install", "--require-hashes", "-r", lock,
+                    stdout=asyncio.subprocess.PIPE,
+                    stderr=asyncio.subprocess.PIPE
+                )
+                stdout, stderr = await process.communicate()
+                if process.returncode != 0:
+                    error_msg = stderr.decode() if stderr else "Unknown error"
+                    logger.error(f"Failed to install from {lock}: {error_msg}")
+                    if progress_callback:
+                        progress_callback("Error installing Ansible", 0)
+                    return False
+            elif os.environ.get("THINKUBE_ANSIBLE_LOCK_REQUIRED"):
+                logger.error("No verified Ansible lock; refusing to install unpinned packages")
+                if progress_callback:
+                    progress_callback("Error: the Ansible requirements could not be verified", 0)
+                return False
+
             # Install specific versions for stability
-            packages = [
+            packages = [] if lock else [
                 "ansible-core>=2.16,<2.17",
                 "ansible>=9.0,<10.0",
                 "jinja2>=3.1.0",
@@ -195,6 +222,15 @@ class AnsibleEnvironment:
             True if successful, False otherwise
         """
         try:
+            managed_dir = os.environ.get("THINKUBE_PLAYBOOKS_DIR")
+            if managed_dir and (Path(managed_dir) / "ansible").is_dir():
+                logger.info(f"Using the installer's thinkube checkout at {managed_dir}")
+                self.thinkube_clone_dir = Path(managed_dir)
+                self.managed_checkout = True
+                if progress_callback:
+                    progress_callback("Using the pinned thinkube checkout", 100)
+                return True
+
             logger.info("Cloning thinkube repository...")
 
             # Always get fresh code - delete existing clone if present
@@ -274,6 +310,11 @@ class AnsibleEnvironment:
 
     def cleanup_thinkube_clone(self):
         """Clean up the temporary thinkube clone"""
+        if self.managed_checkout:
+            # The shell's checkout outlives the run; only forget it
+            self.thinkube_clone_dir = None
+            self.managed_checkout = False
+            return
         if self.thinkube_clone_dir and self.thinkube_clone_dir.exists():
             try:
                 import shutil
@@ -115,6 +115,10 @@ class AnsibleExecutor:
             ansible_playbook = ansible_environment.get_ansible_playbook_command()
             cmd = [ansible_playbook, str(playbook_path)]
             
+            # Dry run: report what would change without changing anything
+            if os.environ.get("THINKUBE_DRY_RUN") == "1":
+                cmd.extend(["--check", "--diff"])
+
             # Add extra vars if provided
             if extra_vars:
                 for key, value in extra_vars.items():
@@ -5,11 +5,23 @@
 Shared state and utilities for the installer backend
 """
 import logging
-from typing import List
+import os
+from typing import List, Optional
 from fastapi import WebSocket
 
 logger = logging.getLogger(__name__)
 
+def workspace_dir() -> Optional[str]:
+    """Private per-run work directory created by the Tauri shell (0700).
+
+    Returns None when the backend runs standalone, in which case callers
+    fall back to the system temp directory.
+    """
+    path = os.environ.get("THINKUBE_WORKSPACE")
+    if path and os.path.isdir(path):
+        return path
+    return None
+
 # Global state - shared across all modules
 class AppState:
     installation_status = {
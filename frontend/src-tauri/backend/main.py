@@ -12,12 +12,14 @@ Refactored modular version
 
 import os
 import sys
+import hmac
+import signal
 import asyncio
 import logging
 from pathlib import Path
-from typing import List
+from typing import List, Optional
 
-from fastapi import FastAPI, WebSocket, WebSocketDisconnect
+from fastapi import FastAPI, Header, HTTPException, WebSocket, WebSocketDisconnect
 from fastapi.middleware.cors import CORSMiddleware
 from fastapi.responses import FileResponse
 import uvicorn
@@ -39,13 +41,14 @@ from app.api.logs import router as logs_router
 
 # Configure logging
 logging.basicConfig(
-    level=logging.INFO,
+    level=getattr(logging, os.environ.get("LOG_LEVEL", "INFO").upper(), logging.INFO),
     format='%(asctime)s - %(name)s - %(levelname)s - %(message)s'
 )
 logger = logging.getLogger(__name__)
 
 # Import shared state
 from app.shared import app_state, broadcast_status
+from app.core.auth import ApiTokenMiddleware
 
 # Initialize FastAPI app
 app = FastAPI(
@@ -54,6 +57,9 @@ app = FastAPI(
     version="1.0.0"
 )
 
+# Added before CORS so CORS wraps it and rejections still carry CORS headers
+app.add_middleware(ApiTokenMiddleware)
+
 # Configure CORS
 app.add_middleware(
     CORSMiddleware,
@@ -87,8 +93,31 @@ async def root():
 
 @app.get("/api/health")
 async def api_health():
-    """API health check endpoint"""
-    return {"status": "healthy", "service": "thinkube-installer-backend"}
+    """API health check endpoint
+
+    Also reports which installer started this backend, so a new installer
+    can tell its own orphaned backend apart from anything else on the port.
+    """
+    return {
+        "status": "healthy",
+        "service": "thinkube-installer-backend",
+        "version": os.environ.get("THINKUBE_INSTALLER_VERSION"),
+        "instance": os.environ.get("THINKUBE_INSTANCE_ID"),
+    }
+
+
+@app.post("/api/shutdown")
+async def api_shutdown(x_thinkube_instance_token: Optional[str] = Header(default=None)):
+    """Stop this backend; only the installer that started it knows the token"""
+    expected = os.environ.get("THINKUBE_INSTANCE_TOKEN")
+    if not expected or not x_thinkube_instance_token \
+            or not hmac.compare_digest(expected, x_thinkube_instance_token):
+        raise HTTPException(status_code=403, detail="Invalid instance token")
+
+    logger.info("Shutdown requested by the installer")
+    # Answer first, then let uvicorn shut down gracefully on SIGTERM
+    asyncio.get_running_loop().call_later(0.2, os.kill, os.getpid(), signal.SIGTERM)
+    return {"status": "shutting-down"}
 
 
 @app.get("/api/current-user")
@@ -153,7 +182,7 @@ if __name__ == "__main__":
     import argparse
     
     parser = argparse.ArgumentParser(description="thinkube Installer Backend")
-    parser.add_argument("--host", default="0.0.0.0", help="Host to bind to")
+    parser.add_argument("--host", default="127.0.0.1", help="Host to bind to")
     parser.add_argument("--port", type=int, default=8000, help="Port to bind to")
     parser.add_argument("--reload", action="store_true", help="Enable auto-reload")
     
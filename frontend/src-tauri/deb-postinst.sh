@@ -40,6 +40,18 @@ if [ ! -f "$BACKEND_DIR/requirements.txt" ]; then
     exit 1
 fi
 
+# Bundled wheels, if any, are per architecture
+ARCH="$(dpkg --print-architecture)"
+FIND_LINKS=""
+if [ -d "$BACKEND_DIR/wheels" ]; then
+    if [ ! -d "$BACKEND_DIR/wheels/$ARCH" ]; then
+        echo "ERROR: This package has no Python wheels for $ARCH"
+        echo "Available: $(ls "$BACKEND_DIR/wheels" | tr '\n' ' ')"
+        exit 1
+    fi
+    FIND_LINKS="--find-links $BACKEND_DIR/wheels/$ARCH"
+fi
+
 # Create virtual environment
 echo "Creating Python virtual environment..."
 python3 -m venv "$VENV_DIR"
@@ -47,7 +59,12 @@ python3 -m venv "$VENV_DIR"
 # Activate and install dependencies
 echo "Installing backend dependencies..."
 "$VENV_DIR/bin/pip" install --quiet --upgrade pip
-"$VENV_DIR/bin/pip" install --quiet -r "$BACKEND_DIR/requirements.txt"
+# The hash-pinned lock when the package ships one
+if [ -f "$BACKEND_DIR/requirements.lock" ]; then
+    "$VENV_DIR/bin/pip" install --quiet $FIND_LINKS --require-hashes -r "$BACKEND_DIR/requirements.lock"
+else
+    "$VENV_DIR/bin/pip" install --quiet $FIND_LINKS -r "$BACKEND_DIR/requirements.txt"
+fi
 
 echo "Backend environment setup complete"
 
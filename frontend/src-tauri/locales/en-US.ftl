@@ -0,0 +1,132 @@
+# Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+# SPDX-License-Identifier: Apache-2.0
+#
+# Native UI strings of the Tauri shell. The wizard's own copy lives in the
+# frontend; only text produced on the Rust side belongs here.
+
+## Host OS compatibility
+
+os-supported = { $os } is supported
+os-untested-ubuntu = { $os } has not been tested with this installer; Ubuntu 24.04 LTS is recommended
+os-untested-derivative = { $os } is an Ubuntu derivative and has not been tested; Ubuntu 24.04 LTS is recommended
+os-unsupported-linux = { $os } is not supported; this installer requires Ubuntu 24.04 LTS
+os-unknown-linux = This Linux distribution
+os-macos-too-old = { $os } is too old; macOS { $min } or newer is required
+os-macos-unknown = Could not determine the macOS version
+os-unsupported = { $os } is not supported by this installer
+
+## Host environment
+
+env-bare-metal = Running on bare metal with KVM available
+env-no-kvm = KVM is not available; enable virtualization (VT-x/AMD-V) in the firmware so LXD can run VMs
+env-vm-nested = Running in a { $platform } VM with nested virtualization
+env-vm-no-kvm = Running in a { $platform } VM without nested virtualization; this machine can be a Kubernetes node but LXD cannot run VMs on it
+env-container = Running inside a { $platform } container; this machine can drive the install over SSH but cannot be a cluster node
+env-wsl = Running inside WSL; this machine can drive the install over SSH but cannot be a cluster node
+env-controller-only = This machine drives the install over SSH; the cluster runs on the Ubuntu nodes
+env-unknown-platform = unknown
+
+## Power source
+
+power-battery = This machine is running on battery; plug it in before starting, as the install takes hours
+power-battery-charge = This machine is running on battery ({ $percent }%); plug it in before starting, as the install takes hours
+power-battery-blocked = This machine is running on battery. Plug it in to start the deployment
+
+## Close confirmation
+
+close-guard-title = An installation step is running
+close-guard-body =
+    { $step } is still running. Closing the installer stops the backend and leaves the nodes half-configured.
+
+    Hide the installer to the tray to let the step finish; "Show installer" in the tray brings it back.
+close-guard-minimize = Hide to tray
+close-guard-quit = Stop and quit
+close-guard-keep-open = Keep open
+
+## Desktop notifications
+
+notify-phase-complete-title = Installation phase finished
+notify-phase-complete-body = The { $phase } phase finished; the installer is moving on.
+notify-install-complete-title = Thinkube is installed
+notify-install-complete-body = All steps finished successfully.
+notify-input-required-title = The installer needs you
+notify-failed-title = { $step } failed
+notify-failed-body = The installation is paused. Open the installer to retry or roll back.
+
+## File dialogs
+
+export-kubeconfig-title = Save kubeconfig
+export-credentials-title = Save credentials
+export-filter-kubeconfig = Kubeconfig
+export-filter-text = Text file
+pick-ssh-key-title = Choose an SSH private key
+pick-kubeconfig-title = Choose a kubeconfig
+pick-config-title = Choose an installer configuration
+pick-filter-config = Installer configuration
+summary-save-title = Save cluster summary
+summary-window-title = Cluster summary
+bundle-save-title = Save offline bundle
+bundle-open-title = Choose an offline bundle
+bundle-filter = Offline bundle
+
+## Platform compatibility
+
+compat-compatible = Installer { $version } is supported by this platform release
+compat-untested = Installer { $version } is newer than this platform release was tested with (up to { $max })
+compat-too-old = Installer { $version } is too old for this platform release; version { $min } or newer is required
+compat-blocked = Installer { $version } is known not to work with this platform release
+compat-blocked-reason = Installer { $version } is known not to work with this platform release: { $reason }
+compat-unknown-version = Could not compare installer version { $version } with the platform's supported versions
+compat-no-manifest = This platform repository doesn't publish supported installer versions
+compat-fetch-failed = Could not check which installer versions the platform supports: { $error }
+
+## Scheduled installation
+
+schedule-blocked-title = The scheduled installation did not start
+schedule-preflight-error = The preflight checks could not run: { $error }
+schedule-preflight-failed = { $count } preflight checks did not pass, so the installation was not started.
+schedule-started-title = The scheduled installation has started
+schedule-started-body = Preflight checks passed on { $nodes } nodes; the installer is deploying.
+
+## Service health
+
+health-down-title = A Thinkube service is down
+health-down-body = Not responding anymore: { $names }
+
+## Crash reports
+
+crash-dialog-title = Thinkube Installer closed unexpectedly
+crash-dialog-body =
+    The installer crashed the last time it ran. A crash report was saved to:
+
+    { $path }
+
+    Please attach this file when reporting the problem.
+
+## Windows and tray
+
+logs-window-title = Thinkube Installer — Logs
+tray-show-installer = Show installer
+tray-show-logs = Show logs
+tray-quit = Quit
+tray-tooltip-progress = Thinkube Installer: { $percent }% installed
+tray-tooltip-failed = Thinkube Installer: a step failed
+tray-tooltip-scheduled = Thinkube Installer: installation scheduled
+tray-tooltip-support = Thinkube Installer: support session open
+
+## Splash window
+
+splash-starting-backend = Starting backend…
+splash-installing-dependencies = Installing dependencies… (first run only)
+splash-waiting-backend = Waiting for backend…
+splash-retrying-backend = Backend did not start, retrying (attempt { $attempt } of { $max })…
+
+## Backend watchdog
+
+watchdog-dialog-title = The installer backend is not responding
+watchdog-dialog-body =
+    The backend has not answered for { $seconds } seconds. It may be stuck on a playbook step.
+
+    Restarting it stops whatever it is running.
+watchdog-restart = Restart backend
+watchdog-wait = Keep waiting
@@ -0,0 +1,129 @@
+# Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+# SPDX-License-Identifier: Apache-2.0
+
+## Host OS compatibility
+
+os-supported = { $os } está soportado
+os-untested-ubuntu = { $os } no se ha probado con este instalador; se recomienda Ubuntu 24.04 LTS
+os-untested-derivative = { $os } es un derivado de Ubuntu y no se ha probado; se recomienda Ubuntu 24.04 LTS
+os-unsupported-linux = { $os } no está soportado; este instalador requiere Ubuntu 24.04 LTS
+os-unknown-linux = Esta distribución de Linux
+os-macos-too-old = { $os } es demasiado antiguo; se requiere macOS { $min } o posterior
+os-macos-unknown = No se pudo determinar la versión de macOS
+os-unsupported = { $os } no está soportado por este instalador
+
+## Host environment
+
+env-bare-metal = Ejecutándose en hardware físico con KVM disponible
+env-no-kvm = KVM no está disponible; active la virtualización (VT-x/AMD-V) en el firmware para que LXD pueda ejecutar máquinas virtuales
+env-vm-nested = Ejecutándose en una máquina virtual { $platform } con virtualización anidada
+env-vm-no-kvm = Ejecutándose en una máquina virtual { $platform } sin virtualización anidada; esta máquina puede ser un nodo de Kubernetes pero LXD no puede ejecutar máquinas virtuales en ella
+env-container = Ejecutándose dentro de un contenedor { $platform }; esta máquina puede dirigir la instalación por SSH pero no puede ser un nodo del clúster
+env-wsl = Ejecutándose dentro de WSL; esta máquina puede dirigir la instalación por SSH pero no puede ser un nodo del clúster
+env-controller-only = Esta máquina dirige la instalación por SSH; el clúster se ejecuta en los nodos Ubuntu
+env-unknown-platform = desconocida
+
+## Power source
+
+power-battery = Esta máquina funciona con batería; conéctela antes de empezar, la instalación tarda horas
+power-battery-charge = Esta máquina funciona con batería ({ $percent }%); conéctela antes de empezar, la instalación tarda horas
+power-battery-blocked = Esta máquina funciona con batería. Conéctela para iniciar el despliegue
+
+## Close confirmation
+
+close-guard-title = Hay un paso de la instalación en curso
+close-guard-body =
+    { $step } sigue en curso. Cerrar el instalador detiene el backend y deja los nodos a medio configurar.
+
+    Oculte el instalador en la bandeja para que el paso termine; «Mostrar instalador» en la bandeja lo vuelve a abrir.
+close-guard-minimize = Ocultar en la bandeja
+close-guard-quit = Detener y salir
+close-guard-keep-open = Mantener abierto
+
+## Desktop notifications
+
+notify-phase-complete-title = Fase de la instalación terminada
+notify-phase-complete-body = La fase { $phase } ha terminado; el instalador continúa.
+notify-install-complete-title = Thinkube está instalado
+notify-install-complete-body = Todos los pasos han terminado correctamente.
+notify-input-required-title = El instalador le necesita
+notify-failed-title = { $step } ha fallado
+notify-failed-body = La instalación está en pausa. Abra el instalador para reintentar o revertir.
+
+## File dialogs
+
+export-kubeconfig-title = Guardar kubeconfig
+export-credentials-title = Guardar credenciales
+export-filter-kubeconfig = Kubeconfig
+export-filter-text = Archivo de texto
+pick-ssh-key-title = Elija una clave privada SSH
+pick-kubeconfig-title = Elija un kubeconfig
+pick-config-title = Elija una configuración del instalador
+pick-filter-config = Configuración del instalador
+summary-save-title = Guardar el resumen del clúster
+summary-window-title = Resumen del clúster
+bundle-save-title = Guardar paquete sin conexión
+bundle-open-title = Elegir un paquete sin conexión
+bundle-filter = Paquete sin conexión
+
+## Platform compatibility
+
+compat-compatible = Esta versión de la plataforma admite el instalador { $version }
+compat-untested = El instalador { $version } es más reciente que las versiones con las que se probó esta versión de la plataforma (hasta { $max })
+compat-too-old = El instalador { $version } es demasiado antiguo para esta versión de la plataforma; se requiere la versión { $min } o posterior
+compat-blocked = Se sabe que el instalador { $version } no funciona con esta versión de la plataforma
+compat-blocked-reason = Se sabe que el instalador { $version } no funciona con esta versión de la plataforma: { $reason }
+compat-unknown-version = No se pudo comparar la versión { $version } del instalador con las versiones que admite la plataforma
+compat-no-manifest = Este repositorio de la plataforma no publica las versiones del instalador que admite
+compat-fetch-failed = No se pudo comprobar qué versiones del instalador admite la plataforma: { $error }
+
+## Scheduled installation
+
+schedule-blocked-title = La instalación programada no se inició
+schedule-preflight-error = No se pudieron ejecutar las comprobaciones previas: { $error }
+schedule-preflight-failed = { $count } comprobaciones previas no se superaron, así que la instalación no se inició.
+schedule-started-title = La instalación programada ha comenzado
+schedule-started-body = Las comprobaciones previas se superaron en { $nodes } nodos; el instalador está desplegando.
+
+## Service health
+
+health-down-title = Un servicio de Thinkube no responde
+health-down-body = Han dejado de responder: { $names }
+
+## Crash reports
+
+crash-dialog-title = Thinkube Installer se cerró inesperadamente
+crash-dialog-body =
+    El instalador falló la última vez que se ejecutó. Se guardó un informe de error en:
+
+    { $path }
+
+    Adjunta este archivo cuando informes del problema.
+
+## Windows and tray
+
+logs-window-title = Thinkube Installer — Registros
+tray-show-installer = Mostrar instalador
+tray-show-logs = Mostrar registros
+tray-quit = Salir
+tray-tooltip-progress = Instalador de Thinkube: { $percent }% instalado
+tray-tooltip-failed = Instalador de Thinkube: ha fallado un paso
+tray-tooltip-scheduled = Instalador de Thinkube: instalación programada
+tray-tooltip-support = Instalador de Thinkube: sesión de soporte abierta
+
+## Splash window
+
+splash-starting-backend = Iniciando el backend…
+splash-installing-dependencies = Instalando dependencias… (solo la primera vez)
+splash-waiting-backend = Esperando al backend…
+splash-retrying-backend = El backend no arrancó, reintentando (intento { $attempt } de { $max })…
+
+## Backend watchdog
+
+watchdog-dialog-title = El backend del instalador no responde
+watchdog-dialog-body =
+    El backend no ha respondido durante { $seconds } segundos. Puede estar bloqueado en un paso de un playbook.
+
+    Reiniciarlo detiene lo que esté ejecutando.
+watchdog-restart = Reiniciar el backend
+watchdog-wait = Seguir esperando
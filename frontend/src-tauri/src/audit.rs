@@ -0,0 +1,301 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Audit log of what the installer did with elevated rights.
+//!
+//! Every command and script sent to a node, every interactive shell opened
+//! on one, every file uploaded to one and every sudo run on this machine is
+//! appended to `audit.jsonl` in the profile's data directory, one JSON
+//! entry per line, before it runs. Entries are hash-chained: each carries
+//! the SHA-256 of the previous one and its own, computed over all its other
+//! fields, so an edited, removed or reordered entry breaks the chain from
+//! there on. Cutting entries off the end can only be told from an export
+//! taken earlier, whose head hash no longer appears. Commands are masked by
+//! [`crate::redact`] first; what goes to a command's stdin, such as a sudo
+//! password, is never recorded.
+//!
+//! The file is only ever opened for appending. `verify_audit_log` walks the
+//! chain and `export_audit_log` copies a verified log elsewhere with its
+//! head hash.
+
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::AppHandle;
+use tracing::{info, warn};
+
+use crate::workspace::create_private_dir;
+use crate::{profiles, redact};
+
+const FILE_NAME: &str = "audit.jsonl";
+/// `prev` of the first entry
+const GENESIS: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+const LOCALHOST: &str = "localhost";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Kind {
+  /// A command or script run over SSH
+  RemoteCommand,
+  /// An interactive shell on a node
+  RemoteShell,
+  /// A file uploaded to a node
+  FileWrite,
+  /// sudo on this machine
+  LocalSudo,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+  pub seq: u64,
+  /// Seconds since the Unix epoch
+  pub time: u64,
+  /// Run workspace the entry was made in
+  pub run_id: String,
+  pub kind: Kind,
+  /// The node, or `localhost` for this machine
+  pub host: String,
+  pub user: String,
+  /// Runs something through sudo
+  pub elevated: bool,
+  /// The command or script, or the path written and its size
+  pub detail: String,
+  pub prev: String,
+  pub hash: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditVerification {
+  pub path: String,
+  pub entries: u64,
+  /// Hash of the last entry; changes with every entry appended
+  pub head: Option<String>,
+  pub valid: bool,
+  /// First entry that doesn't chain to the one before
+  pub broken_at: Option<u64>,
+  pub error: Option<String>,
+}
+
+struct Chain {
+  path: PathBuf,
+  run_id: String,
+  seq: u64,
+  last: String,
+}
+
+static CHAIN: Mutex<Option<Chain>> = Mutex::new(None);
+
+fn now() -> u64 {
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|d| d.as_secs())
+    .unwrap_or_default()
+}
+
+fn log_path(app: &AppHandle) -> Result<PathBuf, String> {
+  Ok(profiles::data_dir(app)?.join(FILE_NAME))
+}
+
+/// SHA-256 over the entry with an empty `hash`.
+fn digest(entry: &AuditEntry) -> String {
+  let unhashed = AuditEntry {
+    hash: String::new(),
+    ..entry.clone()
+  };
+  let json = serde_json::to_string(&unhashed).unwrap_or_default();
+  ring::digest::digest(&ring::digest::SHA256, json.as_bytes())
+    .as_ref()
+    .iter()
+    .map(|b| format!("{:02x}", b))
+    .collect()
+}
+
+/// Walk the chain in `path`.
+fn verify(path: &Path) -> AuditVerification {
+  let mut report = AuditVerification {
+    path: path.display().to_string(),
+    entries: 0,
+    head: None,
+    valid: true,
+    broken_at: None,
+    error: None,
+  };
+  let contents = match std::fs::read_to_string(path) {
+    Ok(contents) => contents,
+    Err(e) if e.kind() == std::io::ErrorKind::NotFound => return report,
+    Err(e) => {
+      report.valid = false;
+      report.error = Some(format!("Failed to read {}: {}", path.display(), e));
+      return report;
+    }
+  };
+  let mut prev = GENESIS.to_string();
+  for (index, line) in contents.lines().enumerate() {
+    let seq = index as u64 + 1;
+    let problem = match serde_json::from_str::<AuditEntry>(line) {
+      Err(e) => Some(format!("Entry {} is not valid: {}", seq, e)),
+      Ok(entry) if entry.seq != seq || entry.prev != prev => {
+        Some(format!("Entry {} doesn't follow entry {}", seq, seq - 1))
+      }
+      Ok(entry) if digest(&entry) != entry.hash => Some(format!("Entry {} was changed", seq)),
+      Ok(entry) => {
+        prev = entry.hash;
+        None
+      }
+    };
+    if let Some(problem) = problem {
+      report.valid = false;
+      report.broken_at = Some(seq);
+      report.error = Some(problem);
+      break;
+    }
+    report.entries = seq;
+  }
+  report.head = (report.entries > 0).then_some(prev);
+  report
+}
+
+/// Pick up the chain where the log on disk ends; called once at startup.
+pub fn init(app: &AppHandle, run_id: &str) {
+  let path = match log_path(app) {
+    Ok(path) => path,
+    Err(e) => {
+      warn!("Audit log disabled: {}", e);
+      return;
+    }
+  };
+  let report = verify(&path);
+  if !report.valid {
+    warn!(
+      "Audit log {} is broken: {}",
+      path.display(),
+      report.error.as_deref().unwrap_or("unknown error")
+    );
+  }
+  // Keep appending after the last good entry; the break stays visible
+  let last = match std::fs::read_to_string(&path) {
+    Ok(contents) => contents
+      .lines()
+      .last()
+      .and_then(|line| serde_json::from_str::<AuditEntry>(line).ok()),
+    Err(_) => None,
+  };
+  let chain = Chain {
+    path,
+    run_id: run_id.to_string(),
+    seq: last.as_ref().map_or(0, |e| e.seq),
+    last: last.map_or_else(|| GENESIS.to_string(), |e| e.hash),
+  };
+  if let Ok(mut slot) = CHAIN.lock() {
+    *slot = Some(chain);
+  }
+}
+
+fn append(kind: Kind, host: &str, user: &str, elevated: bool, detail: &str) {
+  let Ok(mut slot) = CHAIN.lock() else { return };
+  let Some(chain) = slot.as_mut() else { return };
+  let mut entry = AuditEntry {
+    seq: chain.seq + 1,
+    time: now(),
+    run_id: chain.run_id.clone(),
+    kind,
+    host: host.to_string(),
+    user: user.to_string(),
+    elevated,
+    detail: redact::redact(detail).into_owned(),
+    prev: chain.last.clone(),
+    hash: String::new(),
+  };
+  entry.hash = digest(&entry);
+
+  let result = (|| {
+    if let Some(dir) = chain.path.parent() {
+      create_private_dir(dir)?;
+    }
+    let mut options = std::fs::OpenOptions::new();
+    options.append(true).create(true);
+    #[cfg(unix)]
+    {
+      use std::os::unix::fs::OpenOptionsExt;
+      options.mode(0o600);
+    }
+    let mut file = options.open(&chain.path).map_err(|e| e.to_string())?;
+    let line = serde_json::to_string(&entry).map_err(|e| e.to_string())?;
+    writeln!(file, "{}", line).map_err(|e| e.to_string())
+  })();
+  match result {
+    Ok(()) => {
+      chain.seq = entry.seq;
+      chain.last = entry.hash;
+    }
+    Err(e) => warn!("Failed to write audit log {}: {}", chain.path.display(), e),
+  }
+}
+
+/// Whether `command` calls sudo anywhere.
+fn uses_sudo(command: &str) -> bool {
+  command
+    .split(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '-'))
+    .any(|word| word == "sudo")
+}
+
+fn local_user() -> String {
+  std::env::var("USER")
+    .or_else(|_| std::env::var("LOGNAME"))
+    .unwrap_or_default()
+}
+
+/// A command or script about to run on `host` as `user`.
+pub fn remote_command(host: &str, user: &str, command: &str) {
+  append(Kind::RemoteCommand, host, user, uses_sudo(command), command);
+}
+
+pub fn remote_shell(host: &str, user: &str) {
+  append(Kind::RemoteShell, host, user, false, "interactive shell");
+}
+
+/// A file uploaded to `path` on `host`, with its mode if one was set.
+pub fn file_write(host: &str, user: &str, path: &str, bytes: u64, mode: Option<u32>) {
+  let detail = match mode {
+    Some(mode) => format!("{} ({} bytes, mode {:o})", path, bytes, mode),
+    None => format!("{} ({} bytes)", path, bytes),
+  };
+  append(Kind::FileWrite, host, user, false, &detail);
+}
+
+/// sudo with `args` about to run on this machine.
+pub fn local_sudo(args: &[&str]) {
+  append(Kind::LocalSudo, LOCALHOST, &local_user(), true, &args.join(" "));
+}
+
+#[tauri::command]
+pub fn verify_audit_log(app: AppHandle) -> Result<AuditVerification, String> {
+  Ok(verify(&log_path(&app)?))
+}
+
+/// Copy the audit log to `path` after checking its chain; the head hash in
+/// the result is what a later export must still contain.
+#[tauri::command]
+pub fn export_audit_log(app: AppHandle, path: String) -> Result<AuditVerification, String> {
+  let source = log_path(&app)?;
+  let report = verify(&source);
+  if report.entries == 0 && report.valid {
+    return Err("The audit log is empty".to_string());
+  }
+  let destination = PathBuf::from(&path);
+  std::fs::copy(&source, &destination).map_err(|e| format!("Failed to write {}: {}", destination.display(), e))?;
+  info!(
+    "Exported {} audit entries to {} (head {})",
+    report.entries,
+    destination.display(),
+    report.head.as_deref().unwrap_or("none")
+  );
+  Ok(AuditVerification {
+    path: destination.display().to_string(),
+    ..report
+  })
+}
@@ -0,0 +1,85 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Keeping the backend on the loopback interface.
+//!
+//! The backend runs privileged operations, so it's started with an explicit
+//! `--host 127.0.0.1` and, once it answers, every other address of this
+//! machine is tried on the backend port. A backend that can be reached on
+//! any of them is stopped and the start fails, whatever made it bind there.
+
+use std::net::{IpAddr, SocketAddr, TcpStream};
+use std::process::Command;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::platform::find_program;
+
+pub const HOST: &str = "127.0.0.1";
+pub const PORT: u16 = 8000;
+
+const PROBE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Addresses of this machine's interfaces, loopback and link-local IPv6
+/// (which needs a scope to connect to) excluded.
+fn interface_addresses() -> Result<Vec<IpAddr>, String> {
+  let output = match find_program("ip", &["/usr/sbin/ip", "/sbin/ip"]) {
+    Some(ip) if cfg!(target_os = "linux") => Command::new(ip).args(["-o", "addr", "show"]).output(),
+    _ => Command::new(find_program("ifconfig", &["/sbin/ifconfig"]).ok_or("Neither ip nor ifconfig is available")?)
+      .output(),
+  }
+  .map_err(|e| format!("Failed to list network interfaces: {}", e))?;
+
+  let text = String::from_utf8_lossy(&output.stdout);
+  let mut addresses = Vec::new();
+  for line in text.lines() {
+    let mut words = line.split_whitespace();
+    while let Some(word) = words.next() {
+      if word != "inet" && word != "inet6" {
+        continue;
+      }
+      // `ip` prints `addr/prefix`, ifconfig `addr%scope` for IPv6
+      let Some(addr) = words
+        .next()
+        .and_then(|a| a.split(['/', '%']).next())
+        .and_then(|a| a.parse::<IpAddr>().ok())
+      else {
+        continue;
+      };
+      let link_local = matches!(addr, IpAddr::V6(v6) if v6.segments()[0] & 0xffc0 == 0xfe80);
+      if !addr.is_loopback() && !link_local {
+        addresses.push(addr);
+      }
+    }
+  }
+  Ok(addresses)
+}
+
+/// Fail if the backend port accepts connections on anything but loopback.
+pub fn verify_loopback_only() -> Result<(), String> {
+  let addresses = match interface_addresses() {
+    Ok(addresses) => addresses,
+    Err(e) => {
+      warn!("Cannot verify the backend is loopback-only: {}", e);
+      return Ok(());
+    }
+  };
+  let exposed: Vec<String> = addresses
+    .into_iter()
+    .filter(|addr| TcpStream::connect_timeout(&SocketAddr::new(*addr, PORT), PROBE_TIMEOUT).is_ok())
+    .map(|addr| addr.to_string())
+    .collect();
+  if !exposed.is_empty() {
+    return Err(format!(
+      "The backend is reachable from the network on {} port {}; it must only listen on {}. \
+       It has been stopped.",
+      exposed.join(", "),
+      PORT,
+      HOST
+    ));
+  }
+  info!("Verified backend only listens on {}:{}", HOST, PORT);
+  Ok(())
+}
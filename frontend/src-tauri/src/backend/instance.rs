@@ -0,0 +1,152 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Telling our own backend apart from whatever else holds the port.
+//!
+//! Every spawned backend gets a public instance id (reported by
+//! `/api/health`) and a secret token (required by `/api/shutdown`). Both are
+//! recorded in `backend-instance.json` in the app data dir. If a previous
+//! installer crashed and left its backend running, the next start finds the
+//! recorded id on the health endpoint and shuts that backend down with the
+//! token. An orphan isn't adopted: its output and run workspace belong to
+//! the run that died. Anything else answering on the port is left alone and
+//! reported.
+
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+use tracing::info;
+
+use super::BACKEND_URL;
+use crate::workspace::write_private_file;
+
+const SERVICE: &str = "thinkube-installer-backend";
+pub const TOKEN_HEADER: &str = "X-Thinkube-Instance-Token";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Instance {
+  pub id: String,
+  pub token: String,
+  pub version: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Health {
+  service: Option<String>,
+  version: Option<String>,
+  instance: Option<String>,
+}
+
+impl Instance {
+  pub fn new(app: &AppHandle) -> Result<Self, String> {
+    let token = random_hex(32)?;
+    crate::redact::register(&token);
+    Ok(Self {
+      id: random_hex(8)?,
+      token,
+      version: app.package_info().version.to_string(),
+    })
+  }
+}
+
+pub fn random_hex(len: usize) -> Result<String, String> {
+  let mut bytes = vec![0u8; len];
+  std::fs::File::open("/dev/urandom")
+    .and_then(|mut f| f.read_exact(&mut bytes))
+    .map_err(|e| format!("Failed to read /dev/urandom: {}", e))?;
+  Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+fn record_path(app: &AppHandle) -> Result<PathBuf, String> {
+  app
+    .path()
+    .app_data_dir()
+    .map(|dir| dir.join("backend-instance.json"))
+    .map_err(|e| format!("Cannot resolve app data directory: {}", e))
+}
+
+/// Remember the instance we're about to spawn.
+pub fn record(app: &AppHandle, instance: &Instance) -> Result<(), String> {
+  let json = serde_json::to_vec_pretty(instance).map_err(|e| e.to_string())?;
+  write_private_file(&record_path(app)?, &json)
+}
+
+pub fn clear_record(app: &AppHandle) {
+  if let Ok(path) = record_path(app) {
+    let _ = std::fs::remove_file(path);
+  }
+}
+
+fn load_record(app: &AppHandle) -> Option<Instance> {
+  let contents = std::fs::read_to_string(record_path(app).ok()?).ok()?;
+  serde_json::from_str(&contents).ok()
+}
+
+/// `None` if nothing answers on the backend port.
+fn probe() -> Option<Result<Health, String>> {
+  match ureq::get(&format!("{}/api/health", BACKEND_URL))
+    .timeout(Duration::from_secs(2))
+    .call()
+  {
+    Ok(response) => Some(
+      response
+        .into_json::<Health>()
+        .map_err(|e| format!("unexpected health response: {}", e)),
+    ),
+    Err(ureq::Error::Status(code, _)) => Some(Err(format!("HTTP {}", code))),
+    Err(ureq::Error::Transport(_)) => None,
+  }
+}
+
+/// Make sure the backend port is free before spawning, shutting down an
+/// orphaned backend from a previous run if that's what holds it.
+pub fn reclaim_port(app: &AppHandle) -> Result<(), String> {
+  let health = match probe() {
+    None => return Ok(()),
+    Some(Ok(health)) => health,
+    Some(Err(e)) => {
+      return Err(format!(
+        "Port 8000 is already in use by another program ({}). Stop it and try again.",
+        e
+      ))
+    }
+  };
+
+  if health.service.as_deref() != Some(SERVICE) {
+    return Err("Port 8000 is already in use by another program. Stop it and try again.".to_string());
+  }
+
+  let orphan = load_record(app).filter(|r| health.instance.as_deref() == Some(r.id.as_str()));
+  let Some(orphan) = orphan else {
+    return Err(format!(
+      "Another Thinkube Installer backend (version {}) is already running on port 8000. \
+       Close the other installer and try again.",
+      health.version.as_deref().unwrap_or("unknown")
+    ));
+  };
+
+  info!(
+    "Found orphaned backend {} (version {}) from a previous run, shutting it down...",
+    orphan.id, orphan.version
+  );
+  ureq::post(&format!("{}/api/shutdown", BACKEND_URL))
+    .set(TOKEN_HEADER, &orphan.token)
+    .timeout(Duration::from_secs(2))
+    .call()
+    .map_err(|e| format!("Failed to shut down the orphaned backend: {}", e))?;
+
+  let deadline = Instant::now() + Duration::from_secs(10);
+  while Instant::now() < deadline {
+    if probe().is_none() {
+      clear_record(app);
+      info!("Orphaned backend stopped");
+      return Ok(());
+    }
+    std::thread::sleep(Duration::from_millis(250));
+  }
+  Err("The orphaned backend from a previous run did not shut down within 10s".to_string())
+}
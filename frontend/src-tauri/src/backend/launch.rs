@@ -0,0 +1,249 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Locating, bootstrapping and spawning the backend.
+//!
+//! Everything here blocks (pip can take minutes on a first macOS run), so it
+//! is only ever called from the background task started by
+//! [`super::start`]. pip installs from the signed, hash-pinned
+//! `requirements.lock` and `ansible-requirements.lock` next to the
+//! sources; only a build without signing keys falls back to the unpinned
+//! requirements.
+
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use tauri::{AppHandle, Manager};
+use tracing::{info, warn};
+
+use super::instance::Instance;
+use super::BackendStatus;
+use crate::platform::arch::{self, Arch};
+use crate::workspace::Workspace;
+
+/// Hash-pinned `requirements.txt`, signed like any other artifact
+pub const REQUIREMENTS_LOCK: &str = "requirements.lock";
+/// Hash-pinned packages of the Ansible venv the backend creates
+pub const ANSIBLE_LOCK: &str = "ansible-requirements.lock";
+
+/// Where the backend sources live and which venv to activate.
+pub struct Location {
+  pub backend_dir: PathBuf,
+  pub venv_dir: &'static str,
+  pub arch: Arch,
+  /// Bundled wheels for this architecture, when the bundle has any
+  pub wheels_dir: Option<PathBuf>,
+  /// Bundled helper binaries for this architecture, put on the backend's PATH
+  pub bin_dir: Option<PathBuf>,
+}
+
+impl Location {
+  fn new(backend_dir: PathBuf, venv_dir: &'static str) -> Result<Self, String> {
+    let arch = arch::detect()?;
+    Ok(Self {
+      wheels_dir: arch::artifact_dir(&backend_dir.join("wheels"), arch, "Python wheels")?,
+      bin_dir: arch::artifact_dir(&backend_dir.join("bin"), arch, "helper binaries")?,
+      backend_dir,
+      venv_dir,
+      arch,
+    })
+  }
+}
+
+/// In dev mode cargo runs from frontend/src-tauri/, so the backend is just
+/// ./backend; release builds use the copy bundled in the resources.
+pub fn locate(app: &AppHandle) -> Result<Location, String> {
+  #[cfg(debug_assertions)]
+  {
+    let _ = app;
+    let cwd = std::env::current_dir()
+      .map_err(|e| format!("Cannot resolve current directory: {}", e))?;
+    Location::new(cwd.join("backend"), "venv-test")
+  }
+
+  #[cfg(not(debug_assertions))]
+  {
+    let resource_path = app
+      .path()
+      .resource_dir()
+      .map_err(|e| format!("Cannot access app resources: {}", e))?;
+    let backend_dir = resource_path.join("backend");
+    info!("Resource directory: {}", resource_path.display());
+
+    if !backend_dir.exists() {
+      tracing::error!("Backend directory not found at: {}", backend_dir.display());
+      tracing::error!("Resource directory contents:");
+      if let Ok(entries) = std::fs::read_dir(&resource_path) {
+        for entry in entries.flatten() {
+          tracing::error!("  - {}", entry.path().display());
+        }
+      }
+      return Err(format!(
+        "Backend directory not found in app bundle: {}",
+        backend_dir.display()
+      ));
+    }
+
+    Location::new(backend_dir, ".venv")
+  }
+}
+
+/// Create the backend venv on first run. Only needed on macOS, where the
+/// bundle format has no post-install script to do it.
+pub fn bootstrap(app: &AppHandle, location: &Location) -> Result<(), String> {
+  let venv_path = location.backend_dir.join(location.venv_dir);
+  if !cfg!(all(target_os = "macos", not(debug_assertions))) || venv_path.exists() {
+    return Ok(());
+  }
+
+  info!("First run on macOS: Creating backend virtual environment...");
+  super::set_status(app, BackendStatus::InstallingDependencies);
+
+  run_step(
+    Command::new("python3").arg("-m").arg("venv").arg(&venv_path),
+    "create the Python virtual environment",
+  )?;
+
+  info!("Installing backend dependencies for {}...", location.arch.name());
+  let mut pip = Command::new(venv_path.join("bin").join("pip"));
+  pip.args(["install", "-q"]);
+  // Prefer the bundled wheels, so first start needs no compiler or network
+  // for anything they cover
+  if let Some(wheels) = &location.wheels_dir {
+    pip.arg("--find-links").arg(wheels);
+  }
+  match crate::verify::signed_lock(&location.backend_dir.join(REQUIREMENTS_LOCK))? {
+    Some(lock) => pip.arg("--require-hashes").arg("-r").arg(lock),
+    None => {
+      warn!("This build has no signing keys; installing the backend's requirements unpinned");
+      pip.arg("-r").arg(location.backend_dir.join("requirements.txt"))
+    }
+  };
+  run_step(&mut pip, "install backend dependencies")?;
+
+  info!("Backend environment setup complete");
+  Ok(())
+}
+
+fn run_step(cmd: &mut Command, what: &str) -> Result<(), String> {
+  let status = cmd
+    .status()
+    .map_err(|e| format!("Failed to {}: {}", what, e))?;
+  if !status.success() {
+    return Err(format!("Failed to {}: exited with {}", what, status));
+  }
+  Ok(())
+}
+
+/// Spawn `python3 main.py` inside the venv with piped output.
+///
+/// Branch bake-in:
+///   If the build was invoked as `scripts/build.sh --branch <name>`
+///   then THINKUBE_BUILD_BRANCH is set at compile time. We forward
+///   it to the Python backend as THINKUBE_BRANCH so the produced
+///   binary defaults to that branch when launched from the .desktop
+///   menu (where env vars from the user shell don't propagate).
+///   A user who launches from a terminal with THINKUBE_BRANCH set
+///   wins — Command::env only sets the var if we don't see it
+///   already in our own env.
+///
+///   Same shape for THINKUBE_REPO_URL and THINKUBE_METADATA_REPO
+///   so a fork-pinned deb is also buildable.
+pub fn spawn(app: &AppHandle, location: &Location, instance: &Instance) -> Result<Child, String> {
+  info!("Backend directory: {}", location.backend_dir.display());
+
+  let script = format!(
+    "cd {} && source {}/bin/activate && python3 main.py --host {} --port {}",
+    location.backend_dir.display(),
+    location.venv_dir,
+    super::bind::HOST,
+    super::bind::PORT
+  );
+  // Bounded, so a runaway pip or ansible can't take the machine with it
+  let limits = super::limits::configured(app);
+  let (mut cmd, scoped) = super::limits::command(&script, &limits);
+
+  // Forward baked-in defaults unless the user has overridden them.
+  for (compile_env, runtime_env) in [
+    (option_env!("THINKUBE_BUILD_BRANCH"),         "THINKUBE_BRANCH"),
+    (option_env!("THINKUBE_BUILD_REPO_URL"),       "THINKUBE_REPO_URL"),
+    (option_env!("THINKUBE_BUILD_METADATA_REPO"),  "THINKUBE_METADATA_REPO"),
+  ] {
+    if let Some(baked) = compile_env {
+      if !baked.is_empty() && std::env::var(runtime_env).is_err() {
+        cmd.env(runtime_env, baked);
+        info!("Baked-in {}: {}", runtime_env, baked);
+      }
+    }
+  }
+
+  // The shell's pinned checkout, which the backend uses instead of cloning
+  if let Ok(dir) = crate::playbooks::checkout_dir(app) {
+    cmd.env("THINKUBE_PLAYBOOKS_DIR", dir);
+  }
+  // An imported offline bundle replaces the package indexes
+  for (name, value) in crate::bundle::backend_env(app) {
+    info!("{}={}", name, value.to_string_lossy());
+    cmd.env(name, value);
+  }
+
+  // The Ansible venv is only installed from the verified lock, unless the
+  // build has no keys to verify it with
+  if crate::verify::has_keys() {
+    cmd.env("THINKUBE_ANSIBLE_LOCK_REQUIRED", "1");
+  }
+  match crate::verify::signed_lock(&location.backend_dir.join(ANSIBLE_LOCK)) {
+    Ok(Some(lock)) => {
+      cmd.env("THINKUBE_ANSIBLE_LOCK", lock);
+    }
+    Ok(None) => {}
+    Err(e) => warn!("The Ansible venv can't be installed: {}", e),
+  }
+
+  // The installer host's architecture, which the nodes' needn't match
+  cmd.env("THINKUBE_HOST_ARCH", location.arch.name());
+  if let Some(bin_dir) = &location.bin_dir {
+    let path = std::env::var_os("PATH").unwrap_or_default();
+    let paths = std::iter::once(bin_dir.clone()).chain(std::env::split_paths(&path));
+    if let Ok(joined) = std::env::join_paths(paths) {
+      cmd.env("PATH", joined);
+    }
+  }
+
+  // Lets the next installer recognise (and stop) this backend if we crash
+  cmd.env("THINKUBE_INSTALLER_VERSION", &instance.version)
+    .env("THINKUBE_INSTANCE_ID", &instance.id)
+    .env("THINKUBE_INSTANCE_TOKEN", &instance.token);
+  if let Some(token) = app.try_state::<super::ApiToken>() {
+    cmd.env("THINKUBE_API_TOKEN", &token.0);
+  }
+
+  // Generated artifacts belong in the private per-run workspace
+  if let Some(workspace) = app.try_state::<Workspace>() {
+    cmd.env("THINKUBE_WORKSPACE", workspace.root());
+  }
+
+  crate::log_level::apply_to_backend(app, &mut cmd);
+
+  // Playbooks run with --check while the shell only simulates changes
+  if crate::dry_run::is_enabled(app) {
+    cmd.env(crate::dry_run::ENV, "1");
+  }
+
+  // Own process group, so stopping the backend also stops python3 and
+  // anything it started rather than just bash
+  super::process::isolate(&mut cmd);
+
+  // Piped so the output can be forwarded to the log viewer
+  cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+
+  let mut child = cmd
+    .spawn()
+    .map_err(|e| format!("Failed to start backend: {}", e))?;
+  super::output::capture(app, &mut child);
+  if scoped {
+    super::limits::watch(app, child.id(), limits);
+  }
+  Ok(child)
+}
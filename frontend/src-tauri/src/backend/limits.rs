@@ -0,0 +1,256 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Resource limits for the backend and everything it starts.
+//!
+//! A runaway pip or ansible run shouldn't take the rest of the desktop down
+//! with it. On Linux with a systemd user session the backend is started in
+//! a transient scope (`systemd-run --user --scope`), so memory, CPU and
+//! process limits apply to the whole tree, and the scope's `memory.events`
+//! and `pids.events` are polled to notice when a limit is hit. Elsewhere a
+//! memory limit set in the settings falls back to `ulimit -v`, which caps
+//! each process's address space on its own, and hitting it is recognised
+//! from the allocation errors in the backend's output. The default limit is
+//! not applied that way: threads and forks reserve far more address space
+//! than they use, so it would fail ordinary allocations.
+//!
+//! Limits come from settings.json: `backend_memory_limit_mib` (defaults to
+//! three quarters of RAM, 0 disables), `backend_cpu_percent` (100 per core,
+//! unlimited by default) and `backend_max_tasks`.
+
+use serde::Serialize;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+use tracing::{info, warn};
+
+use crate::platform::find_program;
+use crate::settings::SettingsStore;
+
+pub const LIMIT_EVENT: &str = "backend-resource-limit";
+
+const DEFAULT_MAX_TASKS: u32 = 1024;
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Output that means an allocation failed under `ulimit -v`
+const ALLOCATION_ERRORS: &[&str] = &["MemoryError", "Cannot allocate memory", "std::bad_alloc"];
+
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct Limits {
+  pub memory_bytes: Option<u64>,
+  /// `memory_bytes` was set in the settings rather than defaulted
+  pub memory_explicit: bool,
+  pub cpu_percent: Option<u32>,
+  pub max_tasks: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Resource {
+  Memory,
+  Tasks,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LimitHit {
+  pub resource: Resource,
+  /// Configured limit (bytes or tasks), if known
+  pub limit: Option<u64>,
+  /// What happened, e.g. the number of processes killed by the OOM killer
+  pub message: String,
+}
+
+/// When the ulimit fallback last reported a hit, so a burst of errors from
+/// one failure is one event
+static LAST_OUTPUT_HIT: AtomicU64 = AtomicU64::new(0);
+
+fn total_memory_bytes() -> Option<u64> {
+  let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+  let kib: u64 = meminfo
+    .lines()
+    .find_map(|l| l.strip_prefix("MemTotal:"))?
+    .split_whitespace()
+    .next()?
+    .parse()
+    .ok()?;
+  Some(kib * 1024)
+}
+
+pub fn configured(app: &AppHandle) -> Limits {
+  let settings = app.try_state::<SettingsStore>().map(|s| s.get()).unwrap_or_default();
+  let memory_bytes = match settings.backend_memory_limit_mib {
+    Some(0) => None,
+    Some(mib) => Some(mib * 1024 * 1024),
+    None => total_memory_bytes().map(|total| total / 4 * 3),
+  };
+  Limits {
+    memory_bytes,
+    memory_explicit: settings.backend_memory_limit_mib.is_some(),
+    cpu_percent: settings.backend_cpu_percent.filter(|p| *p > 0),
+    max_tasks: Some(settings.backend_max_tasks.unwrap_or(DEFAULT_MAX_TASKS)).filter(|t| *t > 0),
+  }
+}
+
+/// Whether transient user scopes can be created in this session.
+fn systemd_run() -> Option<PathBuf> {
+  if !cfg!(target_os = "linux") {
+    return None;
+  }
+  let systemd_run = find_program("systemd-run", &["/usr/bin/systemd-run"])?;
+  let user_manager = find_program("systemctl", &["/usr/bin/systemctl"])
+    .and_then(|systemctl| {
+      Command::new(systemctl)
+        .args(["--user", "show-environment"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .ok()
+    })
+    .is_some_and(|status| status.success());
+  user_manager.then_some(systemd_run)
+}
+
+/// The command that runs `script` under `limits`, and whether it runs in a
+/// scope of its own that [`watch`] can follow.
+pub fn command(script: &str, limits: &Limits) -> (Command, bool) {
+  if let Some(systemd_run) = systemd_run() {
+    // The scope execs the command in place, so the child's pid and process
+    // group are the backend's own
+    let mut cmd = Command::new(systemd_run);
+    cmd.args(["--user", "--scope", "--quiet", "--collect"]);
+    if let Some(bytes) = limits.memory_bytes {
+      cmd.arg("-p").arg(format!("MemoryMax={}", bytes));
+      // Without this the tree just thrashes swap instead of hitting the limit
+      cmd.arg("-p").arg(format!("MemorySwapMax={}", bytes / 4));
+    }
+    if let Some(percent) = limits.cpu_percent {
+      cmd.arg("-p").arg(format!("CPUQuota={}%", percent));
+    }
+    if let Some(tasks) = limits.max_tasks {
+      cmd.arg("-p").arg(format!("TasksMax={}", tasks));
+    }
+    cmd.args(["bash", "-c", script]);
+    info!("Backend limits (systemd scope): {:?}", limits);
+    return (cmd, true);
+  }
+
+  let mut cmd = Command::new("bash");
+  match limits.memory_bytes.filter(|_| limits.memory_explicit) {
+    Some(bytes) => {
+      // Start anyway if the shell won't set it
+      cmd.arg("-c").arg(format!("ulimit -v {} 2>/dev/null; {}", bytes / 1024, script));
+      info!("Backend memory limit (per process): {} MiB", bytes / 1024 / 1024);
+    }
+    None => {
+      cmd.arg("-c").arg(script);
+    }
+  }
+  (cmd, false)
+}
+
+fn emit(app: &AppHandle, hit: LimitHit) {
+  warn!("Backend hit its {:?} limit: {}", hit.resource, hit.message);
+  let _ = app.emit(LIMIT_EVENT, hit);
+}
+
+/// Counter `key` in a cgroup `*.events` file.
+fn event_count(path: &std::path::Path, key: &str) -> Option<u64> {
+  std::fs::read_to_string(path)
+    .ok()?
+    .lines()
+    .find_map(|l| l.strip_prefix(key)?.trim().parse().ok())
+}
+
+/// The unified-hierarchy cgroup directory of `pid`.
+fn cgroup_of(pid: u32) -> Option<PathBuf> {
+  std::fs::read_to_string(format!("/proc/{}/cgroup", pid))
+    .ok()?
+    .lines()
+    .find_map(|l| l.strip_prefix("0::"))
+    .map(|path| PathBuf::from("/sys/fs/cgroup").join(path.trim_start_matches('/')))
+    .filter(|dir| dir.join("memory.events").exists())
+}
+
+/// Poll the scope of the backend started as `pid` until it goes away.
+pub fn watch(app: &AppHandle, pid: u32, limits: Limits) {
+  let app = app.clone();
+  std::thread::spawn(move || {
+    // systemd-run moves itself into the scope before it execs the backend
+    std::thread::sleep(Duration::from_secs(1));
+    let Some(cgroup) = cgroup_of(pid).filter(|dir| dir.to_string_lossy().ends_with(".scope")) else {
+      warn!("Cannot find the backend's cgroup; resource limit hits won't be reported");
+      return;
+    };
+    let memory = cgroup.join("memory.events");
+    let pids = cgroup.join("pids.events");
+    let mut oom_kills = event_count(&memory, "oom_kill").unwrap_or(0);
+    let mut memory_max = event_count(&memory, "max").unwrap_or(0);
+    let mut tasks_max = event_count(&pids, "max").unwrap_or(0);
+
+    while cgroup.exists() {
+      std::thread::sleep(POLL_INTERVAL);
+      let kills = event_count(&memory, "oom_kill").unwrap_or(oom_kills);
+      let max = event_count(&memory, "max").unwrap_or(memory_max);
+      if kills > oom_kills {
+        emit(
+          &app,
+          LimitHit {
+            resource: Resource::Memory,
+            limit: limits.memory_bytes,
+            message: format!("{} process(es) killed for running out of memory", kills - oom_kills),
+          },
+        );
+      } else if max > memory_max {
+        emit(
+          &app,
+          LimitHit {
+            resource: Resource::Memory,
+            limit: limits.memory_bytes,
+            message: "Memory use reached the limit; the backend is being throttled".to_string(),
+          },
+        );
+      }
+      oom_kills = kills;
+      memory_max = max;
+
+      let tasks = event_count(&pids, "max").unwrap_or(tasks_max);
+      if tasks > tasks_max {
+        emit(
+          &app,
+          LimitHit {
+            resource: Resource::Tasks,
+            limit: limits.max_tasks.map(u64::from),
+            message: format!("{} process creation(s) refused", tasks - tasks_max),
+          },
+        );
+      }
+      tasks_max = tasks;
+    }
+  });
+}
+
+/// Recognise an allocation failure in a line of backend output.
+pub fn check_line(app: &AppHandle, line: &str) {
+  if !ALLOCATION_ERRORS.iter().any(|e| line.contains(e)) {
+    return;
+  }
+  let now = std::time::SystemTime::now()
+    .duration_since(std::time::UNIX_EPOCH)
+    .map(|d| d.as_secs())
+    .unwrap_or_default();
+  if now.saturating_sub(LAST_OUTPUT_HIT.swap(now, Ordering::SeqCst)) < POLL_INTERVAL.as_secs() {
+    return;
+  }
+  emit(
+    app,
+    LimitHit {
+      resource: Resource::Memory,
+      limit: configured(app).memory_bytes,
+      message: line.trim().to_string(),
+    },
+  );
+}
@@ -0,0 +1,223 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Resource usage of the backend and everything it started.
+//!
+//! A sampler thread walks the process table every few seconds, sums CPU
+//! time, resident memory and open files over the backend's process tree and
+//! keeps the samples in a bounded history, so a slow leak over a long
+//! install shows up as a trend. `get_backend_metrics` returns the latest
+//! sample with that history.
+
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::process::Command;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager, State};
+use tracing::warn;
+
+use super::Backend;
+
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(5);
+/// Samples kept: an hour at the sampling interval.
+const HISTORY_SAMPLES: usize = 720;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Sample {
+  /// Milliseconds since the Unix epoch
+  pub timestamp: u64,
+  pub pid: u32,
+  /// CPU use since the previous sample, 100 per fully used core
+  pub cpu_percent: f64,
+  pub rss_bytes: u64,
+  pub open_files: u64,
+  /// Processes in the tree besides the backend itself
+  pub child_processes: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MetricsReport {
+  /// `None` while the backend isn't running
+  pub latest: Option<Sample>,
+  pub history: Vec<Sample>,
+}
+
+#[derive(Default)]
+pub struct BackendMetrics(Mutex<MetricsState>);
+
+#[derive(Default)]
+struct MetricsState {
+  latest: Option<Sample>,
+  history: VecDeque<Sample>,
+}
+
+struct ProcessInfo {
+  ppid: u32,
+  rss_kib: u64,
+  cpu_secs: f64,
+}
+
+/// `ps` cumulative CPU time: `[dd-]hh:mm:ss` on Linux, `mm:ss.cc` on macOS.
+fn parse_cpu_time(text: &str) -> Option<f64> {
+  let (days, clock) = match text.split_once('-') {
+    Some((days, clock)) => (days.parse::<f64>().ok()?, clock),
+    None => (0.0, text),
+  };
+  let mut secs = 0.0;
+  for part in clock.split(':') {
+    secs = secs * 60.0 + part.parse::<f64>().ok()?;
+  }
+  Some(days * 86400.0 + secs)
+}
+
+fn process_table() -> Result<HashMap<u32, ProcessInfo>, String> {
+  let output = Command::new("ps")
+    .args(["-A", "-o", "pid=,ppid=,rss=,time="])
+    .output()
+    .map_err(|e| format!("Failed to run ps: {}", e))?;
+  if !output.status.success() {
+    return Err(format!("ps exited with {}", output.status));
+  }
+  let mut table = HashMap::new();
+  for line in String::from_utf8_lossy(&output.stdout).lines() {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    let [pid, ppid, rss, time] = fields[..] else { continue };
+    let (Ok(pid), Ok(ppid), Ok(rss_kib), Some(cpu_secs)) =
+      (pid.parse(), ppid.parse(), rss.parse(), parse_cpu_time(time))
+    else {
+      continue;
+    };
+    table.insert(
+      pid,
+      ProcessInfo {
+        ppid,
+        rss_kib,
+        cpu_secs,
+      },
+    );
+  }
+  Ok(table)
+}
+
+/// `root` and all its descendants that are still in `table`.
+fn tree(table: &HashMap<u32, ProcessInfo>, root: u32) -> Vec<u32> {
+  let mut pids = vec![root];
+  let mut i = 0;
+  while i < pids.len() {
+    let parent = pids[i];
+    pids.extend(table.iter().filter(|(_, p)| p.ppid == parent).map(|(pid, _)| *pid));
+    i += 1;
+  }
+  pids.retain(|pid| table.contains_key(pid));
+  pids
+}
+
+fn open_files(pids: &[u32]) -> u64 {
+  if cfg!(target_os = "linux") {
+    return pids
+      .iter()
+      .filter_map(|pid| std::fs::read_dir(format!("/proc/{}/fd", pid)).ok())
+      .map(|entries| entries.count() as u64)
+      .sum();
+  }
+  let list: Vec<String> = pids.iter().map(|p| p.to_string()).collect();
+  let Ok(output) = Command::new("lsof")
+    .args(["-n", "-P", "-a", "-p", &list.join(",")])
+    .output()
+  else {
+    return 0;
+  };
+  // Only numbered descriptors; cwd, txt and mapped files aren't open files
+  String::from_utf8_lossy(&output.stdout)
+    .lines()
+    .skip(1)
+    .filter(|l| {
+      l.split_whitespace()
+        .nth(3)
+        .is_some_and(|fd| fd.starts_with(|c: char| c.is_ascii_digit()))
+    })
+    .count() as u64
+}
+
+impl BackendMetrics {
+  fn record(&self, sample: Option<Sample>) {
+    let Ok(mut state) = self.0.lock() else { return };
+    if let Some(sample) = &sample {
+      if state.history.len() == HISTORY_SAMPLES {
+        state.history.pop_front();
+      }
+      state.history.push_back(sample.clone());
+    }
+    state.latest = sample;
+  }
+}
+
+/// Sample the backend tree for as long as the app runs.
+pub fn start_sampling(app: &AppHandle) {
+  let app = app.clone();
+  std::thread::spawn(move || {
+    // CPU seconds of the tree at the previous sample, for the rate
+    let mut previous: Option<(u32, f64, Instant)> = None;
+    loop {
+      std::thread::sleep(SAMPLE_INTERVAL);
+      let Some(metrics) = app.try_state::<BackendMetrics>() else {
+        return;
+      };
+      let Some(pid) = app.try_state::<Backend>().and_then(|b| b.pid()) else {
+        previous = None;
+        metrics.record(None);
+        continue;
+      };
+      let table = match process_table() {
+        Ok(table) => table,
+        Err(e) => {
+          warn!("Cannot sample backend metrics: {}", e);
+          continue;
+        }
+      };
+      let pids = tree(&table, pid);
+      if pids.is_empty() {
+        previous = None;
+        metrics.record(None);
+        continue;
+      }
+
+      let cpu_secs: f64 = pids.iter().map(|p| table[p].cpu_secs).sum();
+      let now = Instant::now();
+      // Children that exited take their CPU time with them, hence the clamp
+      let cpu_percent = match previous {
+        Some((prev_pid, prev_secs, at)) if prev_pid == pid => {
+          ((cpu_secs - prev_secs) / now.duration_since(at).as_secs_f64() * 100.0).max(0.0)
+        }
+        _ => 0.0,
+      };
+      previous = Some((pid, cpu_secs, now));
+
+      metrics.record(Some(Sample {
+        timestamp: SystemTime::now()
+          .duration_since(UNIX_EPOCH)
+          .map(|d| d.as_millis() as u64)
+          .unwrap_or_default(),
+        pid,
+        cpu_percent: (cpu_percent * 10.0).round() / 10.0,
+        rss_bytes: pids.iter().map(|p| table[p].rss_kib * 1024).sum(),
+        open_files: open_files(&pids),
+        child_processes: pids.len() as u64 - 1,
+      }));
+    }
+  });
+}
+
+#[tauri::command]
+pub fn get_backend_metrics(metrics: State<'_, BackendMetrics>) -> MetricsReport {
+  let Ok(state) = metrics.0.lock() else {
+    return MetricsReport::default();
+  };
+  MetricsReport {
+    latest: state.latest.clone(),
+    history: state.history.iter().cloned().collect(),
+  }
+}
@@ -0,0 +1,398 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! A stand-in for the backend, for working on the UI without Python.
+//!
+//! `--mock-backend` makes [`super::start`] serve canned answers on the
+//! backend's address instead of bootstrapping the venv: the responses in
+//! `mock_responses.json`, merged with those of the file given as
+//! `--mock-backend=<file>`. A file has the same shape as the built-in one:
+//! `routes` maps `METHOD /path` to a response, with a trailing `*` matching
+//! any path with that prefix, and `WS /path` to the messages a WebSocket
+//! sends, `message_delay_ms` apart, before it closes. A response gives its
+//! `status` (200), `body` and `delay_ms`; a list of them is answered in
+//! turn, the last one repeating, to script e.g. a check that fails once.
+//! Anything else gets a 404 naming the route, so gaps are easy to spot.
+//! The API token isn't checked.
+
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{info, warn};
+
+use super::bind::{HOST, PORT};
+
+const DEFAULTS: &str = include_str!("mock_responses.json");
+const MAX_HEADER_BYTES: usize = 8192;
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+const DEFAULT_MESSAGE_DELAY_MS: u64 = 250;
+/// How long a WebSocket waits for the client's first message, which
+/// carries the playbook parameters, before sending anyway
+const FIRST_MESSAGE_TIMEOUT: Duration = Duration::from_secs(5);
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// Given with `--mock-backend`: `Some(None)` for the built-in responses,
+/// `Some(Some(file))` to merge a script over them.
+static REQUESTED: Mutex<Option<Option<PathBuf>>> = Mutex::new(None);
+static SERVING: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Deserialize)]
+struct MockResponse {
+  #[serde(default = "default_status")]
+  status: u16,
+  #[serde(default)]
+  body: Value,
+  #[serde(default)]
+  delay_ms: u64,
+  /// For `WS` routes
+  #[serde(default)]
+  messages: Vec<Value>,
+}
+
+fn default_status() -> u16 {
+  200
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum Scripted {
+  Once(MockResponse),
+  InTurn(Vec<MockResponse>),
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Script {
+  message_delay_ms: Option<u64>,
+  #[serde(default)]
+  routes: HashMap<String, Scripted>,
+}
+
+struct Mock {
+  routes: HashMap<String, Vec<MockResponse>>,
+  message_delay: Duration,
+  /// How often each route was answered, for the ones scripted in turn
+  calls: Mutex<HashMap<String, usize>>,
+}
+
+impl Mock {
+  fn load(file: Option<&PathBuf>) -> Result<Self, String> {
+    let mut script: Script =
+      serde_json::from_str(DEFAULTS).map_err(|e| format!("Invalid built-in mock responses: {}", e))?;
+    if let Some(file) = file {
+      let text = std::fs::read_to_string(file).map_err(|e| format!("Failed to read {}: {}", file.display(), e))?;
+      let overrides: Script = serde_json::from_str(&text).map_err(|e| format!("Invalid {}: {}", file.display(), e))?;
+      script.routes.extend(overrides.routes);
+      script.message_delay_ms = overrides.message_delay_ms.or(script.message_delay_ms);
+    }
+    let routes = script
+      .routes
+      .into_iter()
+      .filter_map(|(route, scripted)| {
+        let responses = match scripted {
+          Scripted::Once(response) => vec![response],
+          Scripted::InTurn(responses) => responses,
+        };
+        (!responses.is_empty()).then_some((route, responses))
+      })
+      .collect();
+    Ok(Self {
+      routes,
+      message_delay: Duration::from_millis(script.message_delay_ms.unwrap_or(DEFAULT_MESSAGE_DELAY_MS)),
+      calls: Mutex::new(HashMap::new()),
+    })
+  }
+
+  /// The response for `route`, an exact match before the longest prefix.
+  fn answer(&self, route: &str) -> Option<MockResponse> {
+    let key = if self.routes.contains_key(route) {
+      route
+    } else {
+      self
+        .routes
+        .keys()
+        .filter_map(|k| Some((k, k.strip_suffix('*')?)))
+        .filter(|(_, prefix)| route.starts_with(prefix))
+        .max_by_key(|(_, prefix)| prefix.len())
+        .map(|(k, _)| k.as_str())?
+    };
+    let responses = self.routes.get(key)?;
+    let mut calls = self.calls.lock().ok()?;
+    let call = calls.entry(key.to_string()).or_insert(0);
+    let response = responses[(*call).min(responses.len() - 1)].clone();
+    *call += 1;
+    Some(response)
+  }
+}
+
+/// Look for `--mock-backend[=<file>]` on the command line.
+pub fn parse_args() {
+  for arg in std::env::args().skip(1) {
+    let requested = match arg.strip_prefix("--mock-backend") {
+      Some("") => None,
+      Some(rest) => match rest.strip_prefix('=') {
+        Some(file) => Some(PathBuf::from(file)),
+        None => continue,
+      },
+      None => continue,
+    };
+    if let Ok(mut slot) = REQUESTED.lock() {
+      *slot = Some(requested);
+    }
+  }
+}
+
+pub fn enabled() -> bool {
+  REQUESTED.lock().map(|r| r.is_some()).unwrap_or(false)
+}
+
+/// Serve the mock backend for the rest of the process. Starting it again,
+/// e.g. from a backend restart, keeps the one that is running.
+pub fn start() -> Result<(), String> {
+  if SERVING.load(Ordering::SeqCst) {
+    return Ok(());
+  }
+  let file = REQUESTED.lock().ok().and_then(|r| r.clone()).flatten();
+  let mock = Arc::new(Mock::load(file.as_ref())?);
+  let listener = TcpListener::bind((HOST, PORT)).map_err(|e| {
+    format!(
+      "Cannot serve the mock backend on {}:{}: {}; is another backend running?",
+      HOST, PORT, e
+    )
+  })?;
+  SERVING.store(true, Ordering::SeqCst);
+  info!(
+    "Serving the mock backend on {}:{} with {} routes{}",
+    HOST,
+    PORT,
+    mock.routes.len(),
+    file.map(|f| format!(" (script {})", f.display())).unwrap_or_default()
+  );
+  std::thread::spawn(move || {
+    for stream in listener.incoming() {
+      let Ok(stream) = stream else {
+        continue;
+      };
+      let mock = mock.clone();
+      std::thread::spawn(move || {
+        if let Err(e) = handle(stream, &mock) {
+          warn!("Mock backend: {}", e);
+        }
+      });
+    }
+  });
+  Ok(())
+}
+
+struct Request {
+  method: String,
+  path: String,
+  headers: HashMap<String, String>,
+}
+
+fn read_request(reader: &mut BufReader<TcpStream>) -> std::io::Result<Request> {
+  let mut request_line = String::new();
+  reader.read_line(&mut request_line)?;
+  let mut headers = HashMap::new();
+  let mut header_bytes = 0;
+  loop {
+    let mut line = String::new();
+    let n = reader.read_line(&mut line)?;
+    header_bytes += n;
+    if n == 0 || line == "\r\n" || line == "\n" || header_bytes > MAX_HEADER_BYTES {
+      break;
+    }
+    if let Some((name, value)) = line.split_once(':') {
+      headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+    }
+  }
+  // Drained so the client sees the response rather than a reset
+  let length = headers
+    .get("content-length")
+    .and_then(|l| l.parse::<usize>().ok())
+    .unwrap_or(0)
+    .min(MAX_BODY_BYTES);
+  let mut body = vec![0u8; length];
+  reader.read_exact(&mut body)?;
+
+  let mut parts = request_line.split_whitespace();
+  Ok(Request {
+    method: parts.next().unwrap_or("").to_string(),
+    path: parts.next().unwrap_or("").split('?').next().unwrap_or("").to_string(),
+    headers,
+  })
+}
+
+fn reason(status: u16) -> &'static str {
+  match status {
+    200 => "OK",
+    204 => "No Content",
+    400 => "Bad Request",
+    401 => "Unauthorized",
+    404 => "Not Found",
+    500 => "Internal Server Error",
+    503 => "Service Unavailable",
+    _ => "Mock",
+  }
+}
+
+/// The webview's origin differs from the backend's, so every answer
+/// allows it, as the real backend's CORS middleware does.
+fn cors(request: &Request) -> String {
+  format!(
+    "Access-Control-Allow-Origin: {}\r\nAccess-Control-Allow-Credentials: true\r\n\
+     Access-Control-Allow-Methods: GET, POST, PUT, DELETE, OPTIONS\r\nAccess-Control-Allow-Headers: {}\r\n",
+    request.headers.get("origin").map(String::as_str).unwrap_or("*"),
+    request
+      .headers
+      .get("access-control-request-headers")
+      .map(String::as_str)
+      .unwrap_or("*")
+  )
+}
+
+fn respond(stream: &mut TcpStream, request: &Request, status: u16, body: &Value) -> std::io::Result<()> {
+  let body = if status == 204 { String::new() } else { body.to_string() };
+  write!(
+    stream,
+    "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n{}Connection: close\r\n\r\n{}",
+    status,
+    reason(status),
+    body.len(),
+    cors(request),
+    body
+  )
+}
+
+fn handle(stream: TcpStream, mock: &Mock) -> std::io::Result<()> {
+  stream.set_read_timeout(Some(Duration::from_secs(10)))?;
+  let mut reader = BufReader::new(stream.try_clone()?);
+  let mut stream = stream;
+  let request = read_request(&mut reader)?;
+  if request.method == "OPTIONS" {
+    return respond(&mut stream, &request, 204, &Value::Null);
+  }
+  let websocket = request
+    .headers
+    .get("upgrade")
+    .is_some_and(|u| u.eq_ignore_ascii_case("websocket"));
+  let route = format!("{} {}", if websocket { "WS" } else { &request.method }, request.path);
+  info!("Mock backend: {}", route);
+
+  let Some(response) = mock.answer(&route) else {
+    let body = serde_json::json!({ "detail": format!("Not mocked: {}", route) });
+    return respond(&mut stream, &request, 404, &body);
+  };
+  std::thread::sleep(Duration::from_millis(response.delay_ms));
+  if websocket {
+    websocket_session(&mut stream, &mut reader, &request, &response, mock.message_delay)
+  } else {
+    respond(&mut stream, &request, response.status, &response.body)
+  }
+}
+
+fn websocket_accept(key: &str) -> String {
+  use base64::Engine;
+  let digest = ring::digest::digest(
+    &ring::digest::SHA1_FOR_LEGACY_USE_ONLY,
+    format!("{}{}", key, WEBSOCKET_GUID).as_bytes(),
+  );
+  base64::engine::general_purpose::STANDARD.encode(digest.as_ref())
+}
+
+/// One unfragmented frame from the client, unmasked.
+fn read_frame(reader: &mut BufReader<TcpStream>) -> std::io::Result<(u8, Vec<u8>)> {
+  let mut head = [0u8; 2];
+  reader.read_exact(&mut head)?;
+  let opcode = head[0] & 0x0f;
+  let masked = head[1] & 0x80 != 0;
+  let length = match head[1] & 0x7f {
+    126 => {
+      let mut bytes = [0u8; 2];
+      reader.read_exact(&mut bytes)?;
+      u64::from(u16::from_be_bytes(bytes))
+    }
+    127 => {
+      let mut bytes = [0u8; 8];
+      reader.read_exact(&mut bytes)?;
+      u64::from_be_bytes(bytes)
+    }
+    n => u64::from(n),
+  };
+  if length > MAX_BODY_BYTES as u64 {
+    return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "frame too large"));
+  }
+  let mut mask = [0u8; 4];
+  if masked {
+    reader.read_exact(&mut mask)?;
+  }
+  let mut payload = vec![0u8; length as usize];
+  reader.read_exact(&mut payload)?;
+  if masked {
+    for (i, byte) in payload.iter_mut().enumerate() {
+      *byte ^= mask[i % 4];
+    }
+  }
+  Ok((opcode, payload))
+}
+
+fn write_frame(stream: &mut TcpStream, opcode: u8, payload: &[u8]) -> std::io::Result<()> {
+  let mut frame = vec![0x80 | opcode];
+  match payload.len() {
+    n if n < 126 => frame.push(n as u8),
+    n if n <= usize::from(u16::MAX) => {
+      frame.push(126);
+      frame.extend_from_slice(&(n as u16).to_be_bytes());
+    }
+    n => {
+      frame.push(127);
+      frame.extend_from_slice(&(n as u64).to_be_bytes());
+    }
+  }
+  frame.extend_from_slice(payload);
+  stream.write_all(&frame)
+}
+
+fn websocket_session(
+  stream: &mut TcpStream,
+  reader: &mut BufReader<TcpStream>,
+  request: &Request,
+  response: &MockResponse,
+  delay: Duration,
+) -> std::io::Result<()> {
+  let Some(key) = request.headers.get("sec-websocket-key") else {
+    return respond(
+      stream,
+      request,
+      400,
+      &serde_json::json!({ "detail": "Missing Sec-WebSocket-Key" }),
+    );
+  };
+  write!(
+    stream,
+    "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+    websocket_accept(key)
+  )?;
+
+  stream.set_read_timeout(Some(FIRST_MESSAGE_TIMEOUT))?;
+  if let Ok((_, payload)) = read_frame(reader) {
+    info!(
+      "Mock backend: {} got {}",
+      request.path,
+      String::from_utf8_lossy(&payload)
+    );
+  }
+  for message in &response.messages {
+    std::thread::sleep(delay);
+    write_frame(stream, 0x1, message.to_string().as_bytes())?;
+  }
+  // Normal closure
+  write_frame(stream, 0x8, &1000u16.to_be_bytes())
+}
@@ -0,0 +1,371 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! The FastAPI backend child process.
+//!
+//! [`start`] locates, bootstraps and spawns the backend on a background task
+//! so the event loop (and the splash window) keep running meanwhile. Each
+//! phase is published as a `backend-status` event and kept in [`Backend`] so
+//! a window that loads late can ask for it with `get_backend_status`.
+//! With `--mock-backend`, [`mock`] answers in its place.
+
+mod bind;
+mod instance;
+mod launch;
+mod limits;
+pub mod metrics;
+pub mod mock;
+pub mod output;
+mod pidfile;
+pub mod process;
+pub mod watchdog;
+
+use serde::Serialize;
+use std::process::Child;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tracing::{error, info, info_span, warn};
+
+use crate::settings::SettingsStore;
+use crate::{deep_link, i18n, render_fallback, windows};
+
+pub use instance::random_hex;
+
+pub const STATUS_EVENT: &str = "backend-status";
+
+/// Start attempts unless `backend_start_attempts` is set in settings.json.
+const DEFAULT_START_ATTEMPTS: u32 = 4;
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(8);
+/// How long a single attempt waits for the health endpoint.
+const HEALTH_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Where the backend listens ([`bind::HOST`] and [`bind::PORT`]); the
+/// frontend's axios and WebSocket bases use the same address, as
+/// `localhost` may resolve to `::1` first.
+pub const BACKEND_URL: &str = "http://127.0.0.1:8000";
+
+/// Whether the backend answers its health endpoint.
+pub fn is_healthy(timeout: Duration) -> bool {
+  ureq::get(&format!("{}/api/health", BACKEND_URL))
+    .timeout(timeout)
+    .call()
+    .map(|response| response.status() == 200)
+    .unwrap_or(false)
+}
+
+/// `bin/` of the backend's venv, where the ansible the playbooks run with is.
+pub fn venv_bin(app: &AppHandle) -> Option<std::path::PathBuf> {
+  let location = launch::locate(app).ok()?;
+  Some(location.backend_dir.join(location.venv_dir).join("bin"))
+}
+
+/// The backend's `requirements.txt`.
+pub fn requirements(app: &AppHandle) -> Option<std::path::PathBuf> {
+  let location = launch::locate(app).ok()?;
+  Some(location.backend_dir.join("requirements.txt"))
+}
+
+/// The backend's and the Ansible venv's hash-pinned requirements, once
+/// their signatures check out; see [`crate::verify::signed_lock`].
+pub fn locks(app: &AppHandle) -> Result<Option<(std::path::PathBuf, std::path::PathBuf)>, String> {
+  let location = launch::locate(app)?;
+  let backend = crate::verify::signed_lock(&location.backend_dir.join(launch::REQUIREMENTS_LOCK))?;
+  let ansible = crate::verify::signed_lock(&location.backend_dir.join(launch::ANSIBLE_LOCK))?;
+  Ok(backend.zip(ansible))
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(tag = "phase", rename_all = "snake_case")]
+pub enum BackendStatus {
+  #[default]
+  Starting,
+  InstallingDependencies,
+  WaitingForHealth,
+  /// A start attempt failed; another one follows after `retry_in_ms`
+  Retrying {
+    attempt: u32,
+    max_attempts: u32,
+    error: String,
+    retry_in_ms: u64,
+  },
+  Ready,
+  /// Running, but the health endpoint stopped answering
+  Unresponsive { failures: u32 },
+  Failed { error: String },
+}
+
+impl BackendStatus {
+  fn splash_text(&self) -> Option<String> {
+    match self {
+      BackendStatus::Starting => Some(i18n::t("splash-starting-backend")),
+      BackendStatus::InstallingDependencies => Some(i18n::t("splash-installing-dependencies")),
+      BackendStatus::WaitingForHealth => Some(i18n::t("splash-waiting-backend")),
+      BackendStatus::Retrying { attempt, max_attempts, .. } => Some(i18n::t_args(
+        "splash-retrying-backend",
+        &[
+          ("attempt", &(attempt + 1).to_string()),
+          ("max", &max_attempts.to_string()),
+        ],
+      )),
+      BackendStatus::Ready | BackendStatus::Unresponsive { .. } | BackendStatus::Failed { .. } => None,
+    }
+  }
+}
+
+/// Shared secret every API call from the webview must carry. Generated once
+/// per launch, so it stays valid across backend restarts.
+pub struct ApiToken(String);
+
+impl ApiToken {
+  pub fn generate() -> Result<Self, String> {
+    let token = instance::random_hex(32)?;
+    crate::redact::register(&token);
+    Ok(Self(token))
+  }
+}
+
+/// The running backend child and its last published status.
+#[derive(Default)]
+pub struct Backend {
+  child: Mutex<Option<Child>>,
+  status: Mutex<BackendStatus>,
+}
+
+impl Backend {
+  /// PID of the running child, if any.
+  pub fn pid(&self) -> Option<u32> {
+    self.child.lock().ok()?.as_ref().map(|child| child.id())
+  }
+
+  /// The child's exit status if it has already exited; the slot is cleared
+  /// so a later `stop` doesn't signal a reused PID.
+  fn exit_status(&self) -> Option<std::process::ExitStatus> {
+    let mut slot = self.child.lock().ok()?;
+    let status = slot.as_mut()?.try_wait().ok()??;
+    *slot = None;
+    Some(status)
+  }
+
+  /// Terminate the backend's whole process group if it's running.
+  pub fn stop(&self) {
+    let Some(mut child) = self.child.lock().ok().and_then(|mut slot| slot.take()) else {
+      return;
+    };
+    process::terminate_group(child.id(), || matches!(child.try_wait(), Ok(Some(_))));
+    // Fallback for a group that couldn't be signalled, then reap the zombie
+    let _ = child.kill();
+    let _ = child.wait();
+    info!("Backend process stopped");
+  }
+}
+
+/// Stop the backend on a clean exit and forget its instance record.
+pub fn shutdown(app: &AppHandle) {
+  if let Some(backend) = app.try_state::<Backend>() {
+    backend.stop();
+  }
+  pidfile::remove(app);
+  instance::clear_record(app);
+}
+
+fn set_status(app: &AppHandle, status: BackendStatus) {
+  if let Some(text) = status.splash_text() {
+    windows::set_splash_status(app, &text);
+  }
+  if let Some(backend) = app.try_state::<Backend>() {
+    if let Ok(mut current) = backend.status.lock() {
+      *current = status.clone();
+    }
+  }
+  let _ = app.emit(STATUS_EVENT, status);
+}
+
+/// Bootstrap and spawn the backend off the main thread, then swap the splash
+/// for the main window once it answers (or fails).
+pub fn start(app: &AppHandle) {
+  let app = app.clone();
+  tauri::async_runtime::spawn_blocking(move || {
+    boot(&app);
+
+    // The main window opens on failure too, so the wizard can show the
+    // error instead of leaving the user with a frozen splash.
+    windows::close_splash(&app);
+    info!("Showing main window...");
+    windows::restore_main_geometry(&app);
+    windows::restore_zoom(&app, windows::MAIN_WINDOW);
+    let _ = windows::reveal_main(&app);
+    deep_link::deliver_pending(&app);
+    render_fallback::arm(&app);
+  });
+}
+
+/// Held while the backend is stopped or started, so restarts asked for
+/// at the same time run one after the other instead of racing.
+static LIFECYCLE: Mutex<()> = Mutex::new(());
+
+/// Bring the backend up and publish how that went.
+fn boot(app: &AppHandle) {
+  let _lifecycle = LIFECYCLE.lock().unwrap_or_else(|e| e.into_inner());
+  boot_locked(app);
+}
+
+fn boot_locked(app: &AppHandle) {
+  let _span = info_span!("backend_start").entered();
+  info!("Starting FastAPI backend...");
+  set_status(app, BackendStatus::Starting);
+
+  let started = if mock::enabled() {
+    mock::start()
+  } else {
+    launch_and_wait(app)
+  };
+  let status = match started {
+    Ok(()) => BackendStatus::Ready,
+    Err(error) => {
+      error!("{}", error);
+      BackendStatus::Failed { error }
+    }
+  };
+  set_status(app, status);
+}
+
+/// Stop the backend and start it again, e.g. after a change to the
+/// environment it is started with. The windows are left as they are.
+pub fn restart(app: &AppHandle) {
+  let app = app.clone();
+  // Stopping waits for the process group to exit
+  tauri::async_runtime::spawn_blocking(move || {
+    let _lifecycle = LIFECYCLE.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(backend) = app.try_state::<Backend>() {
+      backend.stop();
+    }
+    boot_locked(&app);
+  });
+}
+
+/// Restart the backend on request, e.g. after the watchdog found it stuck.
+#[tauri::command]
+pub fn restart_backend(app: AppHandle) {
+  info!("Restarting the backend on request");
+  restart(&app);
+}
+
+fn launch_and_wait(app: &AppHandle) -> Result<(), String> {
+  let location = launch::locate(app)?;
+  info_span!("bootstrap").in_scope(|| launch::bootstrap(app, &location))?;
+  pidfile::reap_orphan(app);
+  instance::reclaim_port(app)?;
+
+  let instance = instance::Instance::new(app)?;
+  instance::record(app, &instance)?;
+
+  let max_attempts = app
+    .try_state::<SettingsStore>()
+    .and_then(|settings| settings.get().backend_start_attempts)
+    .unwrap_or(DEFAULT_START_ATTEMPTS)
+    .max(1);
+  let mut backoff = INITIAL_BACKOFF;
+
+  for attempt in 1..=max_attempts {
+    let _attempt = info_span!("attempt", attempt, max_attempts).entered();
+    let error = match start_once(app, &location, &instance) {
+      // A backend exposed to the network is not retried: it would bind the
+      // same way again
+      Ok(()) => {
+        return bind::verify_loopback_only().map_err(|e| {
+          app.state::<Backend>().stop();
+          e
+        })
+      }
+      Err(error) => error,
+    };
+    if attempt == max_attempts {
+      return Err(format!(
+        "Backend failed to start after {} attempts: {}",
+        max_attempts, error
+      ));
+    }
+
+    let delay = with_jitter(backoff);
+    warn!(
+      "Backend start attempt {}/{} failed: {} (retrying in {}ms)",
+      attempt,
+      max_attempts,
+      error,
+      delay.as_millis()
+    );
+    set_status(
+      app,
+      BackendStatus::Retrying {
+        attempt,
+        max_attempts,
+        error,
+        retry_in_ms: delay.as_millis() as u64,
+      },
+    );
+    std::thread::sleep(delay);
+    backoff = (backoff * 2).min(MAX_BACKOFF);
+  }
+  unreachable!("max_attempts is at least 1")
+}
+
+/// Spawn the backend once and wait for its health check. A child that exits
+/// early fails the attempt straight away instead of waiting out the timeout.
+fn start_once(
+  app: &AppHandle,
+  location: &launch::Location,
+  instance: &instance::Instance,
+) -> Result<(), String> {
+  let child = launch::spawn(app, location, instance)?;
+  if let Err(e) = pidfile::write(app, child.id()) {
+    warn!("{}", e);
+  }
+  let backend = app.state::<Backend>();
+  if let Ok(mut slot) = backend.child.lock() {
+    *slot = Some(child);
+  }
+
+  set_status(app, BackendStatus::WaitingForHealth);
+  let deadline = Instant::now() + HEALTH_TIMEOUT;
+  while Instant::now() < deadline {
+    if is_healthy(Duration::from_secs(1)) {
+      return Ok(());
+    }
+    if let Some(status) = backend.exit_status() {
+      return Err(format!("Backend exited during startup with {}", status));
+    }
+    std::thread::sleep(Duration::from_millis(250));
+  }
+
+  backend.stop();
+  Err(format!(
+    "Backend did not answer its health check within {}s",
+    HEALTH_TIMEOUT.as_secs()
+  ))
+}
+
+/// Spread retries over [delay/2, delay) so several installers started at
+/// once (e.g. by a test harness) don't retry in lockstep.
+fn with_jitter(delay: Duration) -> Duration {
+  let nanos = SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|d| d.subsec_nanos())
+    .unwrap_or(0);
+  let half = delay / 2;
+  half + half.mul_f64(f64::from(nanos % 1000) / 1000.0)
+}
+
+/// For the webview's API client; see `backend/app/core/auth.py`.
+#[tauri::command]
+pub fn get_api_token(token: State<'_, ApiToken>) -> String {
+  token.0.clone()
+}
+
+#[tauri::command]
+pub fn get_backend_status(backend: State<'_, Backend>) -> BackendStatus {
+  backend.status.lock().map(|s| s.clone()).unwrap_or_default()
+}
@@ -0,0 +1,101 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Capture of the backend's stdout/stderr.
+//!
+//! Each line is logged with the `backend` target (so it shows in the
+//! terminal and the shell log file), kept in a bounded ring buffer, and
+//! emitted to every window as a `backend-log` event. Windows opened later
+//! (the log viewer) backfill from the buffer with `get_backend_log`.
+
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Read};
+use std::process::Child;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tracing::info;
+
+use crate::redact::redact;
+use crate::shell_log::BACKEND_TARGET;
+
+pub const BACKEND_LOG_EVENT: &str = "backend-log";
+
+/// Lines kept for late subscribers.
+const BUFFER_LINES: usize = 5000;
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Stream {
+  Stdout,
+  Stderr,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LogLine {
+  pub stream: Stream,
+  pub line: String,
+  /// Milliseconds since the Unix epoch
+  pub timestamp: u64,
+}
+
+#[derive(Default)]
+pub struct BackendLog(Mutex<VecDeque<LogLine>>);
+
+impl BackendLog {
+  fn push(&self, line: LogLine) {
+    if let Ok(mut buffer) = self.0.lock() {
+      if buffer.len() == BUFFER_LINES {
+        buffer.pop_front();
+      }
+      buffer.push_back(line);
+    }
+  }
+}
+
+/// Take the child's piped stdout/stderr and forward them on reader threads.
+pub fn capture(app: &AppHandle, child: &mut Child) {
+  if let Some(stdout) = child.stdout.take() {
+    spawn_reader(app.clone(), stdout, Stream::Stdout);
+  }
+  if let Some(stderr) = child.stderr.take() {
+    spawn_reader(app.clone(), stderr, Stream::Stderr);
+  }
+}
+
+fn spawn_reader<R: Read + Send + 'static>(app: AppHandle, source: R, stream: Stream) {
+  std::thread::spawn(move || {
+    let reader = BufReader::new(source);
+    // split() rather than lines() so non-UTF-8 output doesn't end the stream
+    for chunk in reader.split(b'\n') {
+      let Ok(bytes) = chunk else { break };
+      let text = String::from_utf8_lossy(&bytes).trim_end_matches('\r').to_string();
+      // Before the line is logged, buffered or sent anywhere
+      let text = redact(&text).into_owned();
+
+      info!(target: BACKEND_TARGET, stream = ?stream, "{}", text);
+      super::limits::check_line(&app, &text);
+
+      let line = LogLine {
+        stream,
+        line: text,
+        timestamp: SystemTime::now()
+          .duration_since(UNIX_EPOCH)
+          .map(|d| d.as_millis() as u64)
+          .unwrap_or_default(),
+      };
+      if let Some(log) = app.try_state::<BackendLog>() {
+        log.push(line.clone());
+      }
+      let _ = app.emit(BACKEND_LOG_EVENT, line);
+    }
+  });
+}
+
+#[tauri::command]
+pub fn get_backend_log(log: State<'_, BackendLog>) -> Vec<LogLine> {
+  log.0.lock().map(|b| b.iter().cloned().collect()).unwrap_or_default()
+}
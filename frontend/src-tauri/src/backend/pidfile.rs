@@ -0,0 +1,76 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! `backend.pid` in the app data dir.
+//!
+//! Holds the backend's PID and the process start time as reported by
+//! `ps -o lstart=`. The start time guards against PID reuse: after a reboot
+//! or a long gap the recorded PID may belong to something unrelated, and we
+//! only ever signal a process whose start time still matches.
+
+use std::path::PathBuf;
+use std::process::Command;
+use tauri::{AppHandle, Manager};
+use tracing::info;
+
+use crate::workspace::write_private_file;
+
+fn path(app: &AppHandle) -> Result<PathBuf, String> {
+  app
+    .path()
+    .app_data_dir()
+    .map(|dir| dir.join("backend.pid"))
+    .map_err(|e| format!("Cannot resolve app data directory: {}", e))
+}
+
+/// Start time of `pid`, or `None` if no such process exists.
+fn start_time(pid: u32) -> Option<String> {
+  let output = Command::new("ps")
+    .args(["-o", "lstart=", "-p", &pid.to_string()])
+    .output()
+    .ok()?;
+  let started = String::from_utf8_lossy(&output.stdout).trim().to_string();
+  if output.status.success() && !started.is_empty() {
+    Some(started)
+  } else {
+    None
+  }
+}
+
+/// Record a freshly spawned backend.
+pub fn write(app: &AppHandle, pid: u32) -> Result<(), String> {
+  let started = start_time(pid).unwrap_or_default();
+  write_private_file(&path(app)?, format!("{}\n{}\n", pid, started).as_bytes())
+}
+
+pub fn remove(app: &AppHandle) {
+  if let Ok(path) = path(app) {
+    let _ = std::fs::remove_file(path);
+  }
+}
+
+/// Terminate the backend recorded by a previous run if it's still alive, and
+/// drop the PID file either way.
+pub fn reap_orphan(app: &AppHandle) {
+  let Ok(path) = path(app) else { return };
+  let Ok(contents) = std::fs::read_to_string(&path) else { return };
+
+  let mut lines = contents.lines();
+  let pid = lines.next().and_then(|l| l.trim().parse::<u32>().ok());
+  let recorded = lines.next().unwrap_or("").trim().to_string();
+
+  if let Some(pid) = pid.filter(|&pid| pid > 1) {
+    match start_time(pid) {
+      Some(started) if !recorded.is_empty() && started == recorded => {
+        info!("Reaping orphaned backend process group {} (started {})", pid, started);
+        // The recorded PID leads the backend's process group
+        super::process::terminate_group(pid, || start_time(pid).is_none());
+      }
+      Some(_) => info!("PID {} from backend.pid now belongs to another process, leaving it alone", pid),
+      None => {}
+    }
+  }
+  let _ = std::fs::remove_file(&path);
+}
@@ -0,0 +1,58 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Signalling the backend's process group.
+//!
+//! The backend is `bash -c '... && python3 main.py'`, so the PID we get
+//! back is bash's and python is its child (uvicorn may add more). The child
+//! is spawned as the leader of a new process group, which lets us signal
+//! the whole tree at once with `kill -- -<pgid>`. `cancel` starts the tools
+//! it runs for a step the same way, to stop them as a whole too.
+
+use std::process::Command;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// How long the group gets to exit after SIGTERM before SIGKILL.
+const GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+/// Make `cmd` start a new process group led by the child.
+pub fn isolate(cmd: &mut Command) {
+  #[cfg(unix)]
+  {
+    use std::os::unix::process::CommandExt;
+    cmd.process_group(0);
+  }
+}
+
+fn signal_group(pgid: u32, name: &str) -> bool {
+  Command::new("kill")
+    .args([&format!("-{}", name), "--", &format!("-{}", pgid)])
+    .status()
+    .map(|status| status.success())
+    .unwrap_or(false)
+}
+
+/// Whether any process of the group led by `pgid` is left.
+pub fn group_exists(pgid: u32) -> bool {
+  signal_group(pgid, "0")
+}
+
+/// SIGTERM the group led by `pgid`, escalating to SIGKILL if `exited` still
+/// reports it running after the grace period.
+pub fn terminate_group<F: FnMut() -> bool>(pgid: u32, mut exited: F) {
+  if pgid <= 1 || !signal_group(pgid, "TERM") {
+    return;
+  }
+  let deadline = Instant::now() + GRACE_PERIOD;
+  while Instant::now() < deadline {
+    if exited() {
+      return;
+    }
+    std::thread::sleep(Duration::from_millis(100));
+  }
+  warn!("Process group {} ignored SIGTERM, sending SIGKILL", pgid);
+  signal_group(pgid, "KILL");
+}
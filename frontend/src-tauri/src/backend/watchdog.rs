@@ -0,0 +1,100 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Noticing a backend that is alive but stuck.
+//!
+//! A deadlocked Ansible callback leaves python3 running with nothing
+//! answering, which the exit checks never see. Once the backend is ready the
+//! health endpoint is probed every few seconds; after
+//! `backend_watchdog_failures` consecutive timeouts (settings.json, 0
+//! disables) the status becomes `unresponsive` and the user is offered a
+//! restart. A backend that answers again goes back to `ready`.
+
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+use tracing::{error, info, warn};
+
+use super::{is_healthy, set_status, Backend, BackendStatus};
+use crate::i18n;
+use crate::settings::SettingsStore;
+
+const PROBE_INTERVAL: Duration = Duration::from_secs(10);
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+/// Consecutive failed probes unless `backend_watchdog_failures` is set.
+const DEFAULT_FAILURES: u32 = 3;
+
+fn current_status(app: &AppHandle) -> Option<BackendStatus> {
+  let backend = app.try_state::<Backend>()?;
+  let status = backend.status.lock().ok()?.clone();
+  Some(status)
+}
+
+fn offer_restart(app: &AppHandle, failures: u32) {
+  let seconds = (PROBE_INTERVAL + PROBE_TIMEOUT).as_secs() * u64::from(failures);
+  let app_handle = app.clone();
+  app
+    .dialog()
+    .message(i18n::t_args(
+      "watchdog-dialog-body",
+      &[("seconds", &seconds.to_string())],
+    ))
+    .title(i18n::t("watchdog-dialog-title"))
+    .kind(MessageDialogKind::Warning)
+    .buttons(MessageDialogButtons::OkCancelCustom(
+      i18n::t("watchdog-restart"),
+      i18n::t("watchdog-wait"),
+    ))
+    .show(move |restart| {
+      if restart {
+        info!("Restarting the unresponsive backend");
+        super::restart(&app_handle);
+      }
+    });
+}
+
+/// Probe the backend for as long as the app runs.
+pub fn start(app: &AppHandle) {
+  let app = app.clone();
+  std::thread::spawn(move || {
+    let mut failures = 0;
+    loop {
+      std::thread::sleep(PROBE_INTERVAL);
+      let threshold = app
+        .try_state::<SettingsStore>()
+        .and_then(|settings| settings.get().backend_watchdog_failures)
+        .unwrap_or(DEFAULT_FAILURES);
+      // Starting, retrying or failed backends are the start loop's business
+      let unresponsive = match current_status(&app) {
+        Some(BackendStatus::Ready) => false,
+        Some(BackendStatus::Unresponsive { .. }) => true,
+        _ => {
+          failures = 0;
+          continue;
+        }
+      };
+      if threshold == 0 {
+        continue;
+      }
+
+      if is_healthy(PROBE_TIMEOUT) {
+        if unresponsive {
+          info!("Backend is responding again");
+          set_status(&app, BackendStatus::Ready);
+        }
+        failures = 0;
+        continue;
+      }
+
+      failures += 1;
+      warn!("Backend health check timed out ({}/{})", failures, threshold);
+      if failures == threshold {
+        error!("Backend is not responding");
+        set_status(&app, BackendStatus::Unresponsive { failures });
+        offer_restart(&app, failures);
+      }
+    }
+  });
+}
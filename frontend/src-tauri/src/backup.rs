@@ -0,0 +1,447 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Encrypted backups of the installer's state.
+//!
+//! `backup_state` gathers what this machine knows about the cluster into
+//! one file: the active profile's data and config dirs (settings,
+//! transcript, journal, markers, saved configuration), the generated
+//! `~/thinkube/inventory/inventory.yaml`, `~/.thinkube-installer` without
+//! its logs, and the `~/.ssh/thinkube*` keys. The playbooks checkout and
+//! offline bundle are left out, since they can be downloaded or imported
+//! again, and so are secrets kept in the system keyring. The archive is
+//! encrypted with AES-256-GCM under a key derived from the passphrase with
+//! PBKDF2, so the file can be carried around without exposing the keys.
+//!
+//! `restore_state` on this or another machine decrypts it, checks the
+//! manifest and copies everything back over the current files, then
+//! restarts the shell so the restored state is loaded; a dry run stops
+//! after the checks and lists what it would overwrite. Like switching
+//! profiles, restoring is refused while a playbook runs. The inventory is only
+//! put back into an existing checkout, because a `~/thinkube` holding
+//! nothing but the inventory would keep the playbooks from being cloned.
+
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::pbkdf2;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, State};
+use tracing::{info, warn};
+
+use crate::dry_run;
+use crate::install_guard::InstallGuard;
+use crate::platform::find_program;
+use crate::profiles;
+use crate::workspace::{create_private_dir, write_private_file};
+
+const FORMAT: &str = "thinkube-state-backup";
+const VERSION: u32 = 1;
+const MANIFEST: &str = "manifest.json";
+const MAGIC: &[u8; 8] = b"TKSTATE1";
+const SALT_LEN: usize = 16;
+/// Magic, iterations, salt and nonce, authenticated along with the data
+const HEADER_LEN: usize = MAGIC.len() + 4 + SALT_LEN + NONCE_LEN;
+const ITERATIONS: u32 = 600_000;
+/// Fewer would mean a file that wasn't written by this installer
+const MIN_ITERATIONS: u32 = 100_000;
+const MIN_PASSPHRASE: usize = 8;
+const MAX_BACKUP_BYTES: u64 = 512 * 1024 * 1024;
+/// Parts of the data dir that are downloaded or imported again: the
+/// playbooks checkout, the offline bundle and the other profiles
+const DATA_SKIP: &[&str] = &["thinkube", "offline", "profiles"];
+const INSTALLER_SKIP: &[&str] = &["logs"];
+const INVENTORY: &str = "thinkube/inventory/inventory.yaml";
+const KEY_PREFIX: &str = "thinkube";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupManifest {
+  pub format: String,
+  pub version: u32,
+  pub created_at: u64,
+  pub installer_version: String,
+  pub profile: String,
+  pub files: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BackupSummary {
+  pub path: String,
+  pub files: u32,
+  pub bytes: u64,
+}
+
+/// What a dry-run restore would put back.
+#[derive(Debug, Clone, Serialize)]
+pub struct RestorePlan {
+  pub manifest: BackupManifest,
+  /// Files that would be overwritten or created
+  pub files: Vec<String>,
+}
+
+fn home() -> Result<PathBuf, String> {
+  std::env::var_os("HOME")
+    .map(PathBuf::from)
+    .ok_or_else(|| "HOME is not set".to_string())
+}
+
+fn tar() -> Result<PathBuf, String> {
+  find_program("tar", &[]).ok_or_else(|| "tar is not installed".to_string())
+}
+
+fn run(cmd: &mut Command, what: &str) -> Result<(), String> {
+  let output = cmd.output().map_err(|e| format!("Failed to {}: {}", what, e))?;
+  if !output.status.success() {
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    return Err(format!(
+      "Failed to {}: {}",
+      what,
+      stderr.lines().last().unwrap_or("").trim()
+    ));
+  }
+  Ok(())
+}
+
+/// Copy the regular files below `from` to `to`, leaving out the top-level
+/// entries named in `skip` and any symlinks. Returns how many were copied.
+fn copy_tree(from: &Path, to: &Path, skip: &[&str]) -> Result<u32, String> {
+  let Ok(entries) = std::fs::read_dir(from) else {
+    return Ok(0);
+  };
+  create_private_dir(to)?;
+  let mut copied = 0;
+  for entry in entries.flatten() {
+    let name = entry.file_name();
+    if skip.iter().any(|s| name == *s) {
+      continue;
+    }
+    let Ok(kind) = entry.file_type() else {
+      continue;
+    };
+    let source = entry.path();
+    if kind.is_dir() {
+      copied += copy_tree(&source, &to.join(&name), &[])?;
+    } else if kind.is_file() {
+      copy_file(&source, &to.join(&name))?;
+      copied += 1;
+    }
+  }
+  Ok(copied)
+}
+
+/// `std::fs::copy` keeps the mode, so private files stay private.
+fn copy_file(from: &Path, to: &Path) -> Result<(), String> {
+  if let Some(parent) = to.parent() {
+    create_private_dir(parent)?;
+  }
+  std::fs::copy(from, to)
+    .map(|_| ())
+    .map_err(|e| format!("Failed to copy {} to {}: {}", from.display(), to.display(), e))
+}
+
+fn key(passphrase: &str, salt: &[u8], iterations: u32) -> Result<LessSafeKey, String> {
+  let iterations = NonZeroU32::new(iterations).ok_or("Invalid backup header")?;
+  let mut key = [0u8; 32];
+  pbkdf2::derive(
+    pbkdf2::PBKDF2_HMAC_SHA256,
+    iterations,
+    salt,
+    passphrase.as_bytes(),
+    &mut key,
+  );
+  let key = UnboundKey::new(&AES_256_GCM, &key).map_err(|_| "Failed to set up encryption".to_string())?;
+  Ok(LessSafeKey::new(key))
+}
+
+fn encrypt(passphrase: &str, mut data: Vec<u8>) -> Result<Vec<u8>, String> {
+  let rng = SystemRandom::new();
+  let mut salt = [0u8; SALT_LEN];
+  let mut nonce = [0u8; NONCE_LEN];
+  rng
+    .fill(&mut salt)
+    .and_then(|_| rng.fill(&mut nonce))
+    .map_err(|_| "Failed to generate random numbers".to_string())?;
+
+  let mut header = Vec::with_capacity(HEADER_LEN);
+  header.extend_from_slice(MAGIC);
+  header.extend_from_slice(&ITERATIONS.to_be_bytes());
+  header.extend_from_slice(&salt);
+  header.extend_from_slice(&nonce);
+
+  key(passphrase, &salt, ITERATIONS)?
+    .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::from(&header[..]), &mut data)
+    .map_err(|_| "Failed to encrypt the backup".to_string())?;
+  header.append(&mut data);
+  Ok(header)
+}
+
+fn decrypt(passphrase: &str, mut contents: Vec<u8>) -> Result<Vec<u8>, String> {
+  if contents.len() < HEADER_LEN || &contents[..MAGIC.len()] != MAGIC {
+    return Err("This is not an installer state backup".to_string());
+  }
+  let mut data = contents.split_off(HEADER_LEN);
+  let header = contents;
+  let mut iterations = [0u8; 4];
+  iterations.copy_from_slice(&header[MAGIC.len()..MAGIC.len() + 4]);
+  let iterations = u32::from_be_bytes(iterations);
+  if !(MIN_ITERATIONS..=ITERATIONS * 10).contains(&iterations) {
+    return Err("Invalid backup header".to_string());
+  }
+  let salt = &header[MAGIC.len() + 4..MAGIC.len() + 4 + SALT_LEN];
+  let nonce = Nonce::try_assume_unique_for_key(&header[HEADER_LEN - NONCE_LEN..])
+    .map_err(|_| "Invalid backup header".to_string())?;
+
+  let plain_len = key(passphrase, salt, iterations)?
+    .open_in_place(nonce, Aad::from(&header[..]), &mut data)
+    .map_err(|_| "Wrong passphrase or corrupted backup".to_string())?
+    .len();
+  data.truncate(plain_len);
+  Ok(data)
+}
+
+fn stage(app: &AppHandle, tree: &Path) -> Result<u32, String> {
+  let home = home()?;
+  let mut files = copy_tree(&profiles::data_dir(app)?, &tree.join("data"), DATA_SKIP)?;
+  files += copy_tree(&profiles::config_dir(app)?, &tree.join("config"), &["profiles"])?;
+  files += copy_tree(
+    &home.join(".thinkube-installer"),
+    &tree.join("home/.thinkube-installer"),
+    INSTALLER_SKIP,
+  )?;
+  let inventory = home.join(INVENTORY);
+  if inventory.is_file() {
+    copy_file(&inventory, &tree.join("home").join(INVENTORY))?;
+    files += 1;
+  }
+  if let Ok(entries) = std::fs::read_dir(home.join(".ssh")) {
+    for entry in entries.flatten() {
+      let name = entry.file_name();
+      if name.to_string_lossy().starts_with(KEY_PREFIX) && entry.file_type().is_ok_and(|t| t.is_file()) {
+        copy_file(&entry.path(), &tree.join("home/.ssh").join(&name))?;
+        files += 1;
+      }
+    }
+  }
+  Ok(files)
+}
+
+fn check_passphrase(passphrase: &str) -> Result<(), String> {
+  if passphrase.chars().count() < MIN_PASSPHRASE {
+    return Err(format!("The passphrase needs at least {} characters", MIN_PASSPHRASE));
+  }
+  Ok(())
+}
+
+fn with_staging<T>(app: &AppHandle, f: impl FnOnce(&Path) -> Result<T, String>) -> Result<T, String> {
+  let staging = profiles::cache_dir(app)?.join("state-backup");
+  let _ = std::fs::remove_dir_all(&staging);
+  create_private_dir(&staging)?;
+  let result = f(&staging);
+  let _ = std::fs::remove_dir_all(&staging);
+  result
+}
+
+/// Archive the installer's state, inventory and keys into `path`,
+/// encrypted with `passphrase`.
+#[tauri::command]
+pub async fn backup_state(app: AppHandle, path: String, passphrase: String) -> Result<BackupSummary, String> {
+  check_passphrase(&passphrase)?;
+  let dest = PathBuf::from(&path);
+  if dest.is_dir() {
+    return Err(format!("{} is a directory", path));
+  }
+  tauri::async_runtime::spawn_blocking(move || {
+    with_staging(&app, |staging| {
+      let tree = staging.join("tree");
+      let files = stage(&app, &tree)?;
+      let manifest = BackupManifest {
+        format: FORMAT.to_string(),
+        version: VERSION,
+        created_at: SystemTime::now()
+          .duration_since(UNIX_EPOCH)
+          .map(|d| d.as_secs())
+          .unwrap_or_default(),
+        installer_version: app.package_info().version.to_string(),
+        profile: profiles::active(&app),
+        files,
+      };
+      let json = serde_json::to_string_pretty(&manifest).map_err(|e| e.to_string())?;
+      write_private_file(&tree.join(MANIFEST), json.as_bytes())?;
+
+      let archive = staging.join("state.tar.gz");
+      run(
+        Command::new(tar()?)
+          .arg("-czf")
+          .arg(&archive)
+          .arg("-C")
+          .arg(&tree)
+          .arg("."),
+        "archive the state",
+      )?;
+      let data = std::fs::read(&archive).map_err(|e| format!("Failed to read {}: {}", archive.display(), e))?;
+      let encrypted = encrypt(&passphrase, data)?;
+      write_private_file(&dest, &encrypted)?;
+      info!("Backed up {} files of the installer state to {}", files, dest.display());
+      Ok(BackupSummary {
+        path: dest.display().to_string(),
+        files,
+        bytes: encrypted.len() as u64,
+      })
+    })
+  })
+  .await
+  .map_err(|e| e.to_string())?
+}
+
+fn check(tree: &Path) -> Result<BackupManifest, String> {
+  let text =
+    std::fs::read_to_string(tree.join(MANIFEST)).map_err(|_| "This is not an installer state backup".to_string())?;
+  let manifest: BackupManifest = serde_json::from_str(&text).map_err(|e| format!("Invalid {}: {}", MANIFEST, e))?;
+  if manifest.format != FORMAT {
+    return Err("This is not an installer state backup".to_string());
+  }
+  if manifest.version > VERSION {
+    return Err(format!(
+      "The backup is version {}; this installer reads up to {}",
+      manifest.version, VERSION
+    ));
+  }
+  Ok(manifest)
+}
+
+/// Each directory of the unpacked backup and where it goes back to.
+fn destinations(app: &AppHandle, tree: &Path) -> Result<Vec<(PathBuf, PathBuf)>, String> {
+  let home = home()?;
+  Ok(vec![
+    (tree.join("data"), profiles::data_dir(app)?),
+    (tree.join("config"), profiles::config_dir(app)?),
+    (tree.join("home/.thinkube-installer"), home.join(".thinkube-installer")),
+    (tree.join("home/.ssh"), home.join(".ssh")),
+  ])
+}
+
+/// The backed-up inventory and where it goes, if there's a checkout to
+/// put it in.
+fn inventory(tree: &Path) -> Result<Option<(PathBuf, PathBuf)>, String> {
+  let inventory = tree.join("home").join(INVENTORY);
+  if !inventory.is_file() {
+    return Ok(None);
+  }
+  let dest = home()?.join(INVENTORY);
+  if !dest.parent().is_some_and(Path::is_dir) {
+    warn!(
+      "Not restoring {}: there is no playbooks checkout to put it in",
+      dest.display()
+    );
+    return Ok(None);
+  }
+  Ok(Some((inventory, dest)))
+}
+
+fn put_back(app: &AppHandle, tree: &Path) -> Result<u32, String> {
+  let mut files = 0;
+  for (from, to) in destinations(app, tree)? {
+    files += copy_tree(&from, &to, &[])?;
+  }
+  if let Some((inventory, dest)) = inventory(tree)? {
+    copy_file(&inventory, &dest)?;
+    files += 1;
+  }
+  Ok(files)
+}
+
+/// The files [`copy_tree`] would write, by where they'd go.
+fn list_tree(from: &Path, to: &Path, files: &mut Vec<String>) {
+  let Ok(entries) = std::fs::read_dir(from) else {
+    return;
+  };
+  for entry in entries.flatten() {
+    let Ok(kind) = entry.file_type() else {
+      continue;
+    };
+    let dest = to.join(entry.file_name());
+    if kind.is_dir() {
+      list_tree(&entry.path(), &dest, files);
+    } else if kind.is_file() {
+      files.push(dest.display().to_string());
+    }
+  }
+}
+
+/// What [`put_back`] would overwrite, for a dry run.
+fn plan(app: &AppHandle, tree: &Path) -> Result<Vec<String>, String> {
+  let mut files = Vec::new();
+  for (from, to) in destinations(app, tree)? {
+    list_tree(&from, &to, &mut files);
+  }
+  if let Some((_, dest)) = inventory(tree)? {
+    files.push(dest.display().to_string());
+  }
+  files.sort();
+  Ok(files)
+}
+
+/// Restore a backup made with `backup_state` over the current state and
+/// restart the shell to load it. In a dry run the backup is only
+/// decrypted and checked, and the files it would restore come back
+/// instead.
+#[tauri::command]
+pub async fn restore_state(
+  app: AppHandle,
+  guard: State<'_, InstallGuard>,
+  path: String,
+  passphrase: String,
+) -> Result<RestorePlan, String> {
+  if let Some(step) = guard.running_step() {
+    return Err(format!("Cannot restore a backup while {} is running", step));
+  }
+  let source = PathBuf::from(&path);
+  let size = std::fs::metadata(&source)
+    .map_err(|e| format!("Failed to read {}: {}", path, e))?
+    .len();
+  if size > MAX_BACKUP_BYTES {
+    return Err(format!("{} is too large to be an installer state backup", path));
+  }
+  let dry_run = dry_run::is_enabled(&app);
+  let handle = app.clone();
+  let plan = tauri::async_runtime::spawn_blocking(move || {
+    let app = handle;
+    let contents = std::fs::read(&source).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let data = decrypt(&passphrase, contents)?;
+    with_staging(&app, |staging| {
+      let archive = staging.join("state.tar.gz");
+      write_private_file(&archive, &data)?;
+      let tree = staging.join("tree");
+      create_private_dir(&tree)?;
+      run(
+        Command::new(tar()?).arg("-xzf").arg(&archive).arg("-C").arg(&tree),
+        "unpack the backup",
+      )?;
+      let manifest = check(&tree)?;
+      if dry_run {
+        let files = plan(&app, &tree)?;
+        info!("Dry run: would restore {} files from {}", files.len(), path);
+        return Ok(Some(RestorePlan { manifest, files }));
+      }
+      let files = put_back(&app, &tree)?;
+      info!(
+        "Restored {} files from the {} profile's backup of {}",
+        files, manifest.profile, manifest.created_at
+      );
+      Ok(None)
+    })
+  })
+  .await
+  .map_err(|e| e.to_string())??;
+  if let Some(plan) = plan {
+    return Ok(plan);
+  }
+
+  info!("Restored the installer state from a backup; restarting");
+  app.restart()
+}
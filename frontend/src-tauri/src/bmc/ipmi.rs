@@ -0,0 +1,78 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! `ipmitool` over IPMI v2 (lanplus), for BMCs without Redfish. The password
+//! is passed through `IPMI_PASSWORD` (`-E`) rather than on the command line.
+
+use std::path::PathBuf;
+use std::process::Command;
+
+use super::{Credentials, PowerAction, PowerState};
+use crate::platform::find_program;
+
+pub struct Client {
+  binary: PathBuf,
+  host: String,
+  username: String,
+  password: String,
+}
+
+impl Client {
+  pub fn new(host: &str, creds: &Credentials) -> Result<Self, String> {
+    let binary = find_program("ipmitool", &["/usr/bin/ipmitool", "/opt/homebrew/bin/ipmitool"])
+      .ok_or("ipmitool is not installed")?;
+    Ok(Self {
+      binary,
+      host: host.to_string(),
+      username: creds.username.clone(),
+      password: creds.password.clone(),
+    })
+  }
+
+  fn run(&self, args: &[&str]) -> Result<String, String> {
+    let output = Command::new(&self.binary)
+      .args(["-I", "lanplus", "-H", &self.host, "-U", &self.username, "-E"])
+      .args(args)
+      .env("IPMI_PASSWORD", &self.password)
+      .output()
+      .map_err(|e| format!("Failed to run ipmitool: {}", e))?;
+    if !output.status.success() {
+      return Err(format!(
+        "ipmitool {} failed: {}",
+        args.join(" "),
+        String::from_utf8_lossy(&output.stderr).trim()
+      ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+  }
+
+  pub fn power_state(&self) -> Result<PowerState, String> {
+    // "Chassis Power is on"
+    let output = self.run(&["chassis", "power", "status"])?.to_ascii_lowercase();
+    Ok(if output.contains(" is on") {
+      PowerState::On
+    } else if output.contains(" is off") {
+      PowerState::Off
+    } else {
+      PowerState::Unknown
+    })
+  }
+
+  pub fn power(&self, action: PowerAction) -> Result<(), String> {
+    let verb = match action {
+      PowerAction::On => "on",
+      PowerAction::Off => "off",
+      PowerAction::GracefulShutdown => "soft",
+      PowerAction::Reset => "reset",
+      PowerAction::PowerCycle => "cycle",
+    };
+    self.run(&["chassis", "power", verb]).map(|_| ())
+  }
+
+  /// `bootdev` without `options=persistent` only applies to the next boot.
+  pub fn pxe_boot_once(&self) -> Result<(), String> {
+    self.run(&["chassis", "bootdev", "pxe"]).map(|_| ())
+  }
+}
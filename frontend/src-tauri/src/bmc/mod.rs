@@ -0,0 +1,183 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Out-of-band power management for rack servers.
+//!
+//! Nodes with a BMC can be powered on, off or reset and told to PXE boot
+//! once, either over Redfish or, for older BMCs, with `ipmitool`. With no
+//! protocol chosen Redfish is tried first and ipmitool second. Credentials
+//! live in the OS keyring under `bmc:<host>` and are stored with
+//! `store_bmc_credentials` before any other command is used.
+
+mod ipmi;
+mod redfish;
+
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+use tracing::info;
+
+use crate::{dry_run, keyring, validation};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Protocol {
+  Redfish,
+  Ipmi,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BmcTarget {
+  pub host: String,
+  /// `None` tries Redfish, then ipmitool
+  #[serde(default)]
+  pub protocol: Option<Protocol>,
+  /// Accept the BMC's self-signed certificate (Redfish only)
+  #[serde(default)]
+  pub insecure_tls: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PowerAction {
+  On,
+  Off,
+  GracefulShutdown,
+  Reset,
+  PowerCycle,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PowerState {
+  On,
+  Off,
+  PoweringOn,
+  PoweringOff,
+  Unknown,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PowerStatus {
+  pub state: PowerState,
+  /// Which protocol answered
+  pub protocol: Protocol,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Credentials {
+  username: String,
+  password: String,
+}
+
+fn account(host: &str) -> String {
+  format!("bmc:{}", host.to_ascii_lowercase())
+}
+
+fn credentials(host: &str) -> Result<Credentials, String> {
+  let secret = keyring::get(&account(host))?
+    .ok_or_else(|| format!("No BMC credentials stored for {}", host))?;
+  serde_json::from_str(&secret).map_err(|e| format!("Stored BMC credentials for {} are invalid: {}", host, e))
+}
+
+fn check_host(host: &str) -> Result<(), String> {
+  if validation::is_valid_host(host) {
+    Ok(())
+  } else {
+    Err(format!("Invalid BMC host: {}", host))
+  }
+}
+
+/// Run `redfish_op` or `ipmi_op` according to the target's protocol, falling
+/// back from Redfish to ipmitool when none was chosen.
+fn with_protocol<T>(
+  target: &BmcTarget,
+  redfish_op: impl FnOnce(&redfish::Client) -> Result<T, String>,
+  ipmi_op: impl FnOnce(&ipmi::Client) -> Result<T, String>,
+) -> Result<(T, Protocol), String> {
+  check_host(&target.host)?;
+  let creds = credentials(&target.host)?;
+
+  let redfish_client = || redfish::Client::new(&target.host, &creds, target.insecure_tls);
+  let ipmi_client = || ipmi::Client::new(&target.host, &creds);
+
+  match target.protocol {
+    Some(Protocol::Redfish) => redfish_op(&redfish_client()?).map(|v| (v, Protocol::Redfish)),
+    Some(Protocol::Ipmi) => ipmi_op(&ipmi_client()?).map(|v| (v, Protocol::Ipmi)),
+    None => match redfish_client().and_then(|client| redfish_op(&client)) {
+      Ok(v) => Ok((v, Protocol::Redfish)),
+      Err(redfish_error) => match ipmi_client() {
+        Ok(client) => ipmi_op(&client)
+          .map(|v| (v, Protocol::Ipmi))
+          .map_err(|ipmi_error| format!("Redfish: {}; IPMI: {}", redfish_error, ipmi_error)),
+        Err(_) => Err(redfish_error),
+      },
+    },
+  }
+}
+
+async fn blocking<T: Send + 'static>(f: impl FnOnce() -> Result<T, String> + Send + 'static) -> Result<T, String> {
+  tauri::async_runtime::spawn_blocking(f)
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// In a dry run, read the power state instead of changing anything, which
+/// still proves the BMC answers and finds the protocol.
+async fn simulate(target: BmcTarget) -> Result<Protocol, String> {
+  info!("Dry run: only querying the BMC at {}", target.host);
+  blocking(move || with_protocol(&target, |c| c.power_state(), |c| c.power_state()).map(|(_, protocol)| protocol)).await
+}
+
+#[tauri::command]
+pub fn store_bmc_credentials(host: String, username: String, password: String) -> Result<(), String> {
+  let host = host.trim();
+  check_host(host)?;
+  if username.trim().is_empty() {
+    return Err("BMC username must not be empty".to_string());
+  }
+  let creds = Credentials {
+    username: username.trim().to_string(),
+    password,
+  };
+  let secret = serde_json::to_string(&creds).map_err(|e| e.to_string())?;
+  keyring::set(&account(host), &secret)
+}
+
+#[tauri::command]
+pub fn delete_bmc_credentials(host: String) -> Result<(), String> {
+  keyring::delete(&account(host.trim()))
+}
+
+#[tauri::command]
+pub async fn bmc_power_status(target: BmcTarget) -> Result<PowerStatus, String> {
+  blocking(move || {
+    with_protocol(&target, |c| c.power_state(), |c| c.power_state())
+      .map(|(state, protocol)| PowerStatus { state, protocol })
+  })
+  .await
+}
+
+#[tauri::command]
+pub async fn bmc_power_action(app: AppHandle, target: BmcTarget, action: PowerAction) -> Result<Protocol, String> {
+  if dry_run::is_enabled(&app) {
+    return simulate(target).await;
+  }
+  blocking(move || {
+    with_protocol(&target, |c| c.power(action), |c| c.power(action)).map(|((), protocol)| protocol)
+  })
+  .await
+}
+
+/// Boot from the network on the next start only.
+#[tauri::command]
+pub async fn bmc_set_pxe_boot_once(app: AppHandle, target: BmcTarget) -> Result<Protocol, String> {
+  if dry_run::is_enabled(&app) {
+    return simulate(target).await;
+  }
+  blocking(move || {
+    with_protocol(&target, |c| c.pxe_boot_once(), |c| c.pxe_boot_once()).map(|((), protocol)| protocol)
+  })
+  .await
+}
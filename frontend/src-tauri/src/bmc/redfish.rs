@@ -0,0 +1,119 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Minimal Redfish client: the first ComputerSystem's power state, its
+//! Reset action and a one-time boot override.
+
+use base64::Engine;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::{Credentials, PowerAction, PowerState};
+use crate::net::tls;
+
+pub struct Client {
+  agent: ureq::Agent,
+  base: String,
+  authorization: String,
+  system: String,
+}
+
+impl Client {
+  pub fn new(host: &str, creds: &Credentials, insecure_tls: bool) -> Result<Self, String> {
+    let mut builder = ureq::AgentBuilder::new().timeout(Duration::from_secs(15));
+    if insecure_tls {
+      builder = builder.tls_config(Arc::new(tls::accept_any_certificate()?));
+    }
+    let host_part = if host.contains(':') { format!("[{}]", host) } else { host.to_string() };
+
+    let mut client = Self {
+      agent: builder.build(),
+      base: format!("https://{}", host_part),
+      authorization: format!(
+        "Basic {}",
+        base64::engine::general_purpose::STANDARD.encode(format!("{}:{}", creds.username, creds.password))
+      ),
+      system: String::new(),
+    };
+
+    let systems = client.get("/redfish/v1/Systems")?;
+    client.system = systems["Members"][0]["@odata.id"]
+      .as_str()
+      .ok_or("Redfish service lists no ComputerSystem")?
+      .to_string();
+    Ok(client)
+  }
+
+  fn request(&self, method: &str, path: &str) -> ureq::Request {
+    self
+      .agent
+      .request(method, &format!("{}{}", self.base, path))
+      .set("Authorization", &self.authorization)
+      .set("Accept", "application/json")
+  }
+
+  fn get(&self, path: &str) -> Result<Value, String> {
+    self
+      .request("GET", path)
+      .call()
+      .map_err(describe)?
+      .into_json()
+      .map_err(|e| format!("Unexpected Redfish response from {}: {}", path, e))
+  }
+
+  fn send(&self, method: &str, path: &str, body: Value) -> Result<(), String> {
+    self
+      .request(method, path)
+      .send_json(body)
+      .map(|_| ())
+      .map_err(describe)
+  }
+
+  pub fn power_state(&self) -> Result<PowerState, String> {
+    let system = self.get(&self.system)?;
+    Ok(match system["PowerState"].as_str() {
+      Some("On") => PowerState::On,
+      Some("Off") => PowerState::Off,
+      Some("PoweringOn") => PowerState::PoweringOn,
+      Some("PoweringOff") => PowerState::PoweringOff,
+      _ => PowerState::Unknown,
+    })
+  }
+
+  pub fn power(&self, action: PowerAction) -> Result<(), String> {
+    let reset_type = match action {
+      PowerAction::On => "On",
+      PowerAction::Off => "ForceOff",
+      PowerAction::GracefulShutdown => "GracefulShutdown",
+      PowerAction::Reset => "ForceRestart",
+      PowerAction::PowerCycle => "PowerCycle",
+    };
+    self.send(
+      "POST",
+      &format!("{}/Actions/ComputerSystem.Reset", self.system),
+      json!({ "ResetType": reset_type }),
+    )
+  }
+
+  pub fn pxe_boot_once(&self) -> Result<(), String> {
+    self.send(
+      "PATCH",
+      &self.system,
+      json!({ "Boot": { "BootSourceOverrideTarget": "Pxe", "BootSourceOverrideEnabled": "Once" } }),
+    )
+  }
+}
+
+fn describe(error: ureq::Error) -> String {
+  match error {
+    ureq::Error::Status(401, _) | ureq::Error::Status(403, _) => "BMC rejected the credentials".to_string(),
+    ureq::Error::Status(code, response) => {
+      let body = response.into_string().unwrap_or_default();
+      format!("Redfish request failed with HTTP {}: {}", code, body.trim())
+    }
+    ureq::Error::Transport(transport) => format!("Cannot reach Redfish service: {}", transport),
+  }
+}
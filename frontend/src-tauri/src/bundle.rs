@@ -0,0 +1,530 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Offline bundles for installing without a network.
+//!
+//! On a connected machine `create_offline_bundle` gathers what an install
+//! would otherwise download into one `.tar.gz`: the playbooks checkout as
+//! a git bundle, Python wheels for the backend and the Ansible venv, the
+//! snaps the nodes need, and the list of container images to mirror; a
+//! `manifest.json` records each file's SHA-256. `import_offline_bundle`
+//! only takes an archive with a detached `<archive>.minisig` from one of
+//! the release keys (see [`verify`](crate::verify)), so a bundle made here
+//! has to be signed with `minisign -Sm` before another machine accepts
+//! it. The archive is unpacked next to the profile's current bundle and
+//! every file checked against the manifest, with anything the manifest
+//! doesn't list refused; only then does it replace the old bundle, install
+//! the playbooks as the checkout and get remembered as `offline_bundle`
+//! in settings.json. A bundle that fails any of this leaves the one in use
+//! untouched.
+//! From then on the backend starts with pip pointed at the wheels and no
+//! index, and `THINKUBE_OFFLINE_BUNDLE` tells the playbooks where the snaps
+//! and image list are. The wheels are for the architecture and Python the
+//! bundle was made with, so import refuses a bundle for another
+//! architecture.
+
+use serde::{Deserialize, Serialize};
+use std::ffi::OsString;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_dialog::DialogExt;
+use tracing::{info, warn};
+
+use crate::cancel;
+use crate::i18n;
+use crate::install_guard::InstallGuard;
+use crate::platform::{arch, find_program};
+use crate::playbooks::{self, PlaybooksCheckout};
+use crate::profiles;
+use crate::settings::SettingsStore;
+use crate::verify;
+use crate::windows::MAIN_WINDOW;
+
+pub const PROGRESS_EVENT: &str = "offline-bundle-progress";
+const FORMAT: &str = "thinkube-offline-bundle";
+const VERSION: u32 = 1;
+const MANIFEST: &str = "manifest.json";
+const PLAYBOOKS: &str = "playbooks.bundle";
+const IMAGES: &str = "images.txt";
+/// Snaps the nodes install when none are asked for
+pub const DEFAULT_SNAPS: &[&str] = &["lxd", "k8s"];
+/// Same as ansible_environment.py's, which the backend installs into the
+/// Ansible venv
+const ANSIBLE_PACKAGES: &[&str] = &[
+  "ansible-core>=2.16,<2.17",
+  "ansible>=9.0,<10.0",
+  "jinja2>=3.1.0",
+  "pyyaml>=6.0",
+];
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct BundleRequest {
+  /// Snap names; [`DEFAULT_SNAPS`] when unset
+  pub snaps: Option<Vec<String>>,
+  /// Container images the cluster needs, written to `images.txt`
+  #[serde(default)]
+  pub images: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleFile {
+  /// Relative to the bundle root
+  pub path: String,
+  pub size: u64,
+  pub sha256: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleManifest {
+  pub format: String,
+  pub version: u32,
+  pub created_at: u64,
+  pub installer_version: String,
+  pub arch: String,
+  pub python: Option<String>,
+  /// Commit of the bundled playbooks
+  pub playbooks_commit: String,
+  pub snaps: Vec<String>,
+  pub images: Vec<String>,
+  pub files: Vec<BundleFile>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportedBundle {
+  pub path: String,
+  pub manifest: BundleManifest,
+  pub playbooks: PlaybooksCheckout,
+}
+
+fn progress(app: &AppHandle, step: &str) {
+  info!("Offline bundle: {}", step);
+  let _ = app.emit(PROGRESS_EVENT, step);
+}
+
+fn run(app: &AppHandle, cmd: &mut Command, what: &str) -> Result<(), String> {
+  let output = cancel::begin(app, what)
+    .output(cmd)
+    .map_err(|e| format!("Failed to {}: {}", what, e))?;
+  if !output.status.success() {
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    return Err(format!(
+      "Failed to {}: {}",
+      what,
+      stderr.lines().last().unwrap_or("").trim()
+    ));
+  }
+  Ok(())
+}
+
+fn sha256(path: &Path) -> Result<(u64, String), String> {
+  let mut file = std::fs::File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+  let mut context = ring::digest::Context::new(&ring::digest::SHA256);
+  let mut buf = [0u8; 64 * 1024];
+  let mut size = 0u64;
+  loop {
+    let n = file
+      .read(&mut buf)
+      .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    if n == 0 {
+      break;
+    }
+    size += n as u64;
+    context.update(&buf[..n]);
+  }
+  Ok((
+    size,
+    context.finish().as_ref().iter().map(|b| format!("{:02x}", b)).collect(),
+  ))
+}
+
+/// Every file under `root`, relative to it, in a stable order.
+fn list_files(root: &Path, dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), String> {
+  let entries = std::fs::read_dir(dir).map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?;
+  for entry in entries.flatten() {
+    let path = entry.path();
+    if entry.file_type().is_ok_and(|t| t.is_dir()) {
+      list_files(root, &path, files)?;
+    } else if let Ok(relative) = path.strip_prefix(root) {
+      files.push(relative.to_path_buf());
+    }
+  }
+  files.sort();
+  Ok(())
+}
+
+/// pip from the backend's venv, which is the Python the wheels must fit.
+fn pip(app: &AppHandle) -> Result<PathBuf, String> {
+  crate::backend::venv_bin(app)
+    .map(|bin| bin.join("pip"))
+    .filter(|pip| pip.is_file())
+    .or_else(|| find_program("pip3", &[]))
+    .ok_or_else(|| "pip is not installed".to_string())
+}
+
+fn python_version(pip: &Path) -> Option<String> {
+  let python = pip.with_file_name("python3");
+  let program = if python.is_file() {
+    python
+  } else {
+    PathBuf::from("python3")
+  };
+  let output = Command::new(program).arg("--version").output().ok()?;
+  output
+    .status
+    .success()
+    .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn stage(app: &AppHandle, request: &BundleRequest, staging: &Path) -> Result<BundleManifest, String> {
+  progress(app, "playbooks");
+  let playbooks_commit = playbooks::write_bundle(app, &staging.join(PLAYBOOKS))?;
+
+  progress(app, "wheels");
+  let pip = pip(app)?;
+  let wheels = staging.join("wheels");
+  let mut download = Command::new(&pip);
+  download.args(["download", "--quiet", "--dest"]).arg(&wheels);
+  // The exact wheels the locks pin, which is all an offline install accepts
+  if let Some((backend, ansible)) = crate::backend::locks(app)? {
+    download.arg("--require-hashes").arg("-r").arg(backend).arg("-r").arg(ansible);
+  } else {
+    if let Some(requirements) = crate::backend::requirements(app).filter(|r| r.is_file()) {
+      download.arg("-r").arg(requirements);
+    }
+    download.args(ANSIBLE_PACKAGES);
+  }
+  run(app, &mut download, "download the Python wheels")?;
+
+  let snaps: Vec<String> = request
+    .snaps
+    .clone()
+    .unwrap_or_else(|| DEFAULT_SNAPS.iter().map(|s| s.to_string()).collect());
+  if !snaps.is_empty() {
+    progress(app, "snaps");
+    let snap = find_program("snap", &[]).ok_or("snap is not installed, so the snaps can't be downloaded")?;
+    let dir = staging.join("snaps");
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    for name in &snaps {
+      if name.starts_with('-') || name.contains('/') {
+        return Err(format!("Invalid snap name: {}", name));
+      }
+      run(
+        app,
+        Command::new(&snap)
+          .arg("download")
+          .arg(format!("--target-directory={}", dir.display()))
+          .arg(name),
+        &format!("download the {} snap", name),
+      )?;
+    }
+  }
+
+  let images: Vec<String> = request
+    .images
+    .iter()
+    .map(|i| i.trim().to_string())
+    .filter(|i| !i.is_empty())
+    .collect();
+  let mut list = images.join("\n");
+  list.push('\n');
+  std::fs::write(staging.join(IMAGES), list).map_err(|e| e.to_string())?;
+
+  progress(app, "manifest");
+  let mut paths = Vec::new();
+  list_files(staging, staging, &mut paths)?;
+  let mut files = Vec::new();
+  for relative in paths {
+    let (size, sha256) = sha256(&staging.join(&relative))?;
+    files.push(BundleFile {
+      path: relative.to_string_lossy().replace('\\', "/"),
+      size,
+      sha256,
+    });
+  }
+  let manifest = BundleManifest {
+    format: FORMAT.to_string(),
+    version: VERSION,
+    created_at: SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .map(|d| d.as_secs())
+      .unwrap_or_default(),
+    installer_version: app.package_info().version.to_string(),
+    arch: arch::detect()?.name().to_string(),
+    python: python_version(&pip),
+    playbooks_commit,
+    snaps,
+    images,
+    files,
+  };
+  let json = serde_json::to_string_pretty(&manifest).map_err(|e| e.to_string())?;
+  std::fs::write(staging.join(MANIFEST), json).map_err(|e| e.to_string())?;
+  Ok(manifest)
+}
+
+fn tar() -> Result<PathBuf, String> {
+  find_program("tar", &[]).ok_or_else(|| "tar is not installed".to_string())
+}
+
+/// Gather everything an install downloads and save it as one archive.
+/// Returns the archive's path, or `None` when the dialog was cancelled.
+#[tauri::command]
+pub async fn create_offline_bundle(app: AppHandle, request: Option<BundleRequest>) -> Result<Option<String>, String> {
+  tauri::async_runtime::spawn_blocking(move || {
+    let mut dialog = app
+      .dialog()
+      .file()
+      .set_title(i18n::t("bundle-save-title"))
+      .set_file_name("thinkube-offline.tar.gz")
+      .add_filter(i18n::t("bundle-filter"), &["gz", "tgz"]);
+    if let Some(window) = app.get_webview_window(MAIN_WINDOW) {
+      dialog = dialog.set_parent(&window);
+    }
+    if let Ok(home) = app.path().home_dir() {
+      dialog = dialog.set_directory(home);
+    }
+    let Some(chosen) = dialog.blocking_save_file() else {
+      return Ok(None);
+    };
+    let dest = chosen.into_path().map_err(|e| e.to_string())?;
+
+    let staging = profiles::cache_dir(&app)?.join("offline-bundle");
+    let _ = std::fs::remove_dir_all(&staging);
+    std::fs::create_dir_all(&staging).map_err(|e| format!("Failed to create {}: {}", staging.display(), e))?;
+    let result = stage(&app, &request.unwrap_or_default(), &staging).and_then(|manifest| {
+      progress(&app, "archive");
+      run(
+        &app,
+        Command::new(tar()?)
+          .arg("-czf")
+          .arg(&dest)
+          .arg("-C")
+          .arg(&staging)
+          .arg("."),
+        "write the bundle",
+      )?;
+      Ok(manifest)
+    });
+    let _ = std::fs::remove_dir_all(&staging);
+    let manifest = result?;
+    info!(
+      "Offline bundle with {} files at {}",
+      manifest.files.len(),
+      dest.display()
+    );
+    Ok(Some(dest.display().to_string()))
+  })
+  .await
+  .map_err(|e| e.to_string())?
+}
+
+fn bundle_dir(app: &AppHandle) -> Result<PathBuf, String> {
+  Ok(profiles::data_dir(app)?.join("offline"))
+}
+
+fn check(dir: &Path) -> Result<BundleManifest, String> {
+  let text = std::fs::read_to_string(dir.join(MANIFEST)).map_err(|_| "This is not an offline bundle".to_string())?;
+  let manifest: BundleManifest = serde_json::from_str(&text).map_err(|e| format!("Invalid {}: {}", MANIFEST, e))?;
+  if manifest.format != FORMAT {
+    return Err("This is not an offline bundle".to_string());
+  }
+  if manifest.version > VERSION {
+    return Err(format!(
+      "The bundle is version {}; this installer reads up to {}",
+      manifest.version, VERSION
+    ));
+  }
+  let arch = arch::detect()?.name();
+  if manifest.arch != arch {
+    return Err(format!(
+      "The bundle was made for {} machines; this one is {}",
+      manifest.arch, arch
+    ));
+  }
+  for file in &manifest.files {
+    if file.path.split('/').any(|part| part == "..") {
+      return Err(format!("Invalid path in the bundle: {}", file.path));
+    }
+    let (size, sha256) = sha256(&dir.join(&file.path)).map_err(|_| format!("The bundle is missing {}", file.path))?;
+    if size != file.size || sha256 != file.sha256 {
+      return Err(format!("{} in the bundle is corrupted", file.path));
+    }
+  }
+  // Only what the manifest lists may come along, and none of it as a link
+  let mut paths = Vec::new();
+  list_files(dir, dir, &mut paths)?;
+  for relative in paths {
+    let name = relative.to_string_lossy().replace('\\', "/");
+    let listed = name == MANIFEST || manifest.files.iter().any(|f| f.path == name);
+    let link = std::fs::symlink_metadata(dir.join(&relative)).map_or(true, |m| m.file_type().is_symlink());
+    if !listed || link {
+      return Err(format!("The bundle has {}, which its manifest doesn't list", name));
+    }
+  }
+  Ok(manifest)
+}
+
+/// `dir` with `suffix` appended to its name.
+fn sibling(dir: &Path, suffix: &str) -> PathBuf {
+  let mut name = dir.as_os_str().to_owned();
+  name.push(suffix);
+  PathBuf::from(name)
+}
+
+/// Put the unpacked bundle at `staging` where the one in use was.
+fn replace(staging: &Path, dir: &Path) -> Result<(), String> {
+  let old = sibling(dir, ".old");
+  let _ = std::fs::remove_dir_all(&old);
+  if dir.exists() {
+    std::fs::rename(dir, &old).map_err(|e| format!("Failed to move {} aside: {}", dir.display(), e))?;
+  }
+  if let Err(e) = std::fs::rename(staging, dir) {
+    let _ = std::fs::rename(&old, dir);
+    return Err(format!("Failed to move the bundle to {}: {}", dir.display(), e));
+  }
+  let _ = std::fs::remove_dir_all(&old);
+  Ok(())
+}
+
+/// Unpack an offline bundle, picked in a dialog unless `path` is given,
+/// and use it instead of the network. `None` when the dialog was
+/// cancelled.
+#[tauri::command]
+pub async fn import_offline_bundle(
+  app: AppHandle,
+  settings: State<'_, SettingsStore>,
+  guard: State<'_, InstallGuard>,
+  path: Option<String>,
+) -> Result<Option<ImportedBundle>, String> {
+  if let Some(step) = guard.running_step() {
+    return Err(format!("Cannot import an offline bundle while {} is running", step));
+  }
+  let handle = app.clone();
+  let imported = tauri::async_runtime::spawn_blocking(move || {
+    let app = handle;
+    let archive = match path {
+      Some(path) => PathBuf::from(path),
+      None => {
+        let mut dialog = app
+          .dialog()
+          .file()
+          .set_title(i18n::t("bundle-open-title"))
+          .add_filter(i18n::t("bundle-filter"), &["gz", "tgz"]);
+        if let Some(window) = app.get_webview_window(MAIN_WINDOW) {
+          dialog = dialog.set_parent(&window);
+        }
+        let Some(chosen) = dialog.blocking_pick_file() else {
+          return Ok(None);
+        };
+        chosen.into_path().map_err(|e| e.to_string())?
+      }
+    };
+
+    progress(&app, "signature");
+    let signed = verify::verify(&archive).map_err(|e| {
+      warn!("Refusing to import {}: {}", archive.display(), e);
+      e
+    })?;
+    info!("{} is signed with key {}", archive.display(), signed.key_id);
+
+    let dir = bundle_dir(&app)?;
+    let staging = sibling(&dir, ".new");
+    let _ = std::fs::remove_dir_all(&staging);
+    std::fs::create_dir_all(&staging).map_err(|e| format!("Failed to create {}: {}", staging.display(), e))?;
+    progress(&app, "extract");
+    let result = run(
+      &app,
+      Command::new(tar()?).arg("-xzf").arg(&archive).arg("-C").arg(&staging),
+      "unpack the bundle",
+    )
+    .and_then(|_| {
+      progress(&app, "verify");
+      check(&staging)
+    })
+    .and_then(|manifest| {
+      progress(&app, "playbooks");
+      let checkout = playbooks::install_bundle(&app, &staging.join(PLAYBOOKS), &manifest.playbooks_commit)?;
+      replace(&staging, &dir)?;
+      Ok((manifest, checkout))
+    });
+    match result {
+      Ok((manifest, playbooks)) => Ok(Some(ImportedBundle {
+        path: dir.display().to_string(),
+        manifest,
+        playbooks,
+      })),
+      Err(e) => {
+        warn!("Failed to import {}: {}", archive.display(), e);
+        let _ = std::fs::remove_dir_all(&staging);
+        Err(e)
+      }
+    }
+  })
+  .await
+  .map_err(|e| e.to_string())??;
+
+  if let Some(imported) = &imported {
+    let dir = imported.path.clone();
+    let commit = imported.manifest.playbooks_commit.clone();
+    settings.update(|s| {
+      s.offline_bundle = Some(dir);
+      s.playbooks_ref = Some(commit);
+    })?;
+    info!("Using the offline bundle at {}", imported.path);
+    // The backend's environment is set when it starts
+    crate::backend::restart(&app);
+  }
+  Ok(imported)
+}
+
+/// The imported bundle's manifest, if one is in use.
+#[tauri::command]
+pub fn get_offline_bundle(settings: State<'_, SettingsStore>) -> Option<BundleManifest> {
+  let dir = settings.get().offline_bundle?;
+  let text = std::fs::read_to_string(Path::new(&dir).join(MANIFEST)).ok()?;
+  serde_json::from_str(&text).ok()
+}
+
+/// Go back to downloading from the network and delete the unpacked bundle,
+/// unpinning the playbooks from the bundle's commit.
+#[tauri::command]
+pub fn clear_offline_bundle(
+  app: AppHandle,
+  settings: State<'_, SettingsStore>,
+  guard: State<'_, InstallGuard>,
+) -> Result<(), String> {
+  let Some(dir) = settings.get().offline_bundle else {
+    return Ok(());
+  };
+  if let Some(step) = guard.running_step() {
+    return Err(format!("Cannot remove the offline bundle while {} is running", step));
+  }
+  let _ = std::fs::remove_dir_all(&dir);
+  settings.update(|s| {
+    s.offline_bundle = None;
+    s.playbooks_ref = None;
+  })?;
+  crate::backend::restart(&app);
+  Ok(())
+}
+
+/// The wheels and snaps of the bundle in use, for the backend's environment.
+pub fn backend_env(app: &AppHandle) -> Vec<(&'static str, OsString)> {
+  let Some(dir) = app
+    .try_state::<SettingsStore>()
+    .and_then(|s| s.get().offline_bundle)
+    .map(PathBuf::from)
+    .filter(|dir| dir.join(MANIFEST).is_file())
+  else {
+    return Vec::new();
+  };
+  vec![
+    ("THINKUBE_OFFLINE_BUNDLE", dir.clone().into_os_string()),
+    ("PIP_FIND_LINKS", dir.join("wheels").into_os_string()),
+    ("PIP_NO_INDEX", OsString::from("1")),
+  ]
+}
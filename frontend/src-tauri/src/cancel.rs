@@ -0,0 +1,204 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Stopping the step that's running, cleanly.
+//!
+//! Every long operation the shell runs for a step registers itself with
+//! [`begin`] (or [`watch`], when it already has a cancel flag of its own)
+//! and holds on to the returned [`Operation`] until it's done: remote
+//! commands and task graphs, SFTP transfers, USB writes, downloads, and the
+//! tools it runs locally (git, pip, snap, tar, the ISO builders). Those
+//! tools are started through the operation, each as the leader of its own
+//! process group. `cancel_current_step` raises every registered flag, which
+//! the operations check between chunks of work, and terminates the
+//! process groups (SIGTERM, then SIGKILL after a grace period), which also
+//! takes down ssh clients and with them their channels. The running step
+//! is then reported as cancelled: the close guard stops treating it as
+//! running, the transcript records it, and a `step-cancelled` event tells
+//! the wizard.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io;
+use std::process::{Child, Command, Output, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager};
+use tracing::info;
+
+use crate::backend::process;
+use crate::install_guard::InstallGuard;
+use crate::report::{StepRecord, Transcript};
+use crate::telemetry::Outcome;
+
+pub const EVENT: &str = "step-cancelled";
+
+struct Registered {
+  what: String,
+  cancel: Arc<AtomicBool>,
+  /// Process groups started for it, by leader pid
+  groups: Vec<u32>,
+}
+
+#[derive(Default)]
+pub struct Cancellation {
+  operations: Mutex<HashMap<u64, Registered>>,
+  next_id: AtomicU64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CancelledStep {
+  /// The playbook step that was running, if any
+  pub step: Option<String>,
+  /// What was stopped
+  pub operations: Vec<String>,
+  pub processes: usize,
+}
+
+/// A registered operation; dropping it unregisters it.
+pub struct Operation {
+  app: AppHandle,
+  id: u64,
+  cancel: Arc<AtomicBool>,
+}
+
+impl Operation {
+  pub fn is_cancelled(&self) -> bool {
+    self.cancel.load(Ordering::SeqCst)
+  }
+
+  /// Start `cmd` in a process group of its own that cancelling kills.
+  pub fn spawn(&self, cmd: &mut Command) -> io::Result<Child> {
+    if self.is_cancelled() {
+      return Err(io::Error::new(io::ErrorKind::Interrupted, "cancelled"));
+    }
+    process::isolate(cmd);
+    let child = cmd.spawn()?;
+    if let Some(cancellation) = self.app.try_state::<Cancellation>() {
+      if let Ok(mut operations) = cancellation.operations.lock() {
+        if let Some(operation) = operations.get_mut(&self.id) {
+          operation.groups.push(child.id());
+        }
+      }
+    }
+    Ok(child)
+  }
+
+  /// Like [`Command::output`], but stopped by cancelling, which then shows
+  /// as an `Interrupted` error.
+  pub fn output(&self, cmd: &mut Command) -> io::Result<Output> {
+    let child = self.spawn(cmd.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped()))?;
+    let pid = child.id();
+    let output = child.wait_with_output();
+    self.forget(pid);
+    if self.is_cancelled() {
+      return Err(io::Error::new(io::ErrorKind::Interrupted, "cancelled"));
+    }
+    output
+  }
+
+  /// The group led by `pid` has been reaped; don't signal it, as the pid
+  /// may be reused.
+  pub fn forget(&self, pid: u32) {
+    if let Some(cancellation) = self.app.try_state::<Cancellation>() {
+      if let Ok(mut operations) = cancellation.operations.lock() {
+        if let Some(operation) = operations.get_mut(&self.id) {
+          operation.groups.retain(|&g| g != pid);
+        }
+      }
+    }
+  }
+}
+
+impl Drop for Operation {
+  fn drop(&mut self) {
+    if let Some(cancellation) = self.app.try_state::<Cancellation>() {
+      if let Ok(mut operations) = cancellation.operations.lock() {
+        operations.remove(&self.id);
+      }
+    }
+  }
+}
+
+/// Register an operation with the flag it already checks.
+pub fn watch(app: &AppHandle, what: &str, cancel: Arc<AtomicBool>) -> Operation {
+  let mut id = 0;
+  if let Some(cancellation) = app.try_state::<Cancellation>() {
+    id = cancellation.next_id.fetch_add(1, Ordering::SeqCst) + 1;
+    if let Ok(mut operations) = cancellation.operations.lock() {
+      operations.insert(
+        id,
+        Registered {
+          what: what.to_string(),
+          cancel: cancel.clone(),
+          groups: Vec::new(),
+        },
+      );
+    }
+  }
+  Operation {
+    app: app.clone(),
+    id,
+    cancel,
+  }
+}
+
+/// Register an operation with a fresh flag.
+pub fn begin(app: &AppHandle, what: &str) -> Operation {
+  watch(app, what, Arc::new(AtomicBool::new(false)))
+}
+
+/// Abort whatever is running: raise every cancel flag, kill the process
+/// trees and mark the step cancelled.
+#[tauri::command]
+pub fn cancel_current_step(app: AppHandle) -> CancelledStep {
+  let mut stopped = Vec::new();
+  let mut groups = Vec::new();
+  if let Some(cancellation) = app.try_state::<Cancellation>() {
+    if let Ok(operations) = cancellation.operations.lock() {
+      for operation in operations.values() {
+        operation.cancel.store(true, Ordering::SeqCst);
+        stopped.push(operation.what.clone());
+        groups.extend(operation.groups.iter().copied());
+      }
+    }
+  }
+  for &pgid in &groups {
+    std::thread::spawn(move || process::terminate_group(pgid, || !process::group_exists(pgid)));
+  }
+
+  let step = app
+    .try_state::<InstallGuard>()
+    .and_then(|guard| guard.take_running_step());
+  if let Some(step) = &step {
+    let started_at = SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .map(|d| d.as_millis() as u64)
+      .unwrap_or_default();
+    let _ = app.state::<Transcript>().record(StepRecord {
+      step: step.clone(),
+      title: step.clone(),
+      started_at,
+      duration_ms: 0,
+      outcome: Outcome::Cancelled,
+      message: Some("Cancelled by the user".to_string()),
+      outputs: Default::default(),
+    });
+  }
+  info!(
+    "Cancelled step {:?}: {} operations, {} process groups",
+    step,
+    stopped.len(),
+    groups.len()
+  );
+  let cancelled = CancelledStep {
+    step,
+    operations: stopped,
+    processes: groups.len(),
+  };
+  let _ = app.emit(EVENT, cancelled.clone());
+  cancelled
+}
@@ -0,0 +1,299 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! TLS certificates for the cluster domain.
+//!
+//! `generate_bootstrap_certs` creates a throwaway CA and a wildcard
+//! certificate for the domain in the run workspace, for bootstrapping
+//! ingress before real certificates exist. `inspect_certificate` loads a
+//! certificate chain from a PEM file, pasted PEM or a live TLS endpoint and
+//! reports expiry, SANs, whether the domain is covered and whether the chain
+//! verifies against the public web roots (plus an optional extra CA), so
+//! problems show up in the wizard rather than during ingress deployment.
+
+use base64::Engine;
+use rcgen::{
+  BasicConstraints, CertificateParams, DnType, ExtendedKeyUsagePurpose, IsCa, KeyPair, KeyUsagePurpose,
+};
+use rustls::client::danger::ServerCertVerifier;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use serde::{Deserialize, Serialize};
+use std::net::TcpStream;
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use time::OffsetDateTime;
+use tracing::info;
+
+use crate::net::tls;
+use crate::validation;
+use crate::workspace::{write_private_file, Workspace};
+
+const DEFAULT_VALIDITY_DAYS: u32 = 90;
+const MAX_VALIDITY_DAYS: u32 = 825;
+/// Certificates expiring sooner than this are flagged
+const EXPIRY_WARNING_DAYS: i64 = 21;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BootstrapCerts {
+  pub ca_cert_path: String,
+  pub ca_key_path: String,
+  pub cert_path: String,
+  pub key_path: String,
+  pub not_after: i64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CertSource {
+  File { path: String },
+  Pem { pem: String },
+  Remote { host: String, port: Option<u16> },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CertReport {
+  pub subject: String,
+  pub issuer: String,
+  pub self_signed: bool,
+  /// Seconds since the Unix epoch
+  pub not_before: i64,
+  pub not_after: i64,
+  pub days_remaining: i64,
+  pub expired: bool,
+  pub expiring_soon: bool,
+  pub sans: Vec<String>,
+  /// `None` when no domain was given to check against
+  pub covers_domain: Option<bool>,
+  pub chain_length: usize,
+  pub chain_valid: bool,
+  pub chain_error: Option<String>,
+}
+
+fn generate(workspace: &Workspace, domain: &str, days: u32) -> Result<BootstrapCerts, String> {
+  let err = |e: rcgen::Error| format!("Failed to generate certificate: {}", e);
+  let now = OffsetDateTime::now_utc();
+  let not_after = now + time::Duration::days(i64::from(days));
+
+  let mut ca_params = CertificateParams::new(Vec::<String>::new()).map_err(err)?;
+  ca_params
+    .distinguished_name
+    .push(DnType::CommonName, format!("Thinkube Bootstrap CA ({})", domain));
+  ca_params.is_ca = IsCa::Ca(BasicConstraints::Constrained(0));
+  ca_params.key_usages = vec![KeyUsagePurpose::KeyCertSign, KeyUsagePurpose::CrlSign];
+  ca_params.not_before = now - time::Duration::hours(1);
+  ca_params.not_after = not_after;
+  let ca_key = KeyPair::generate().map_err(err)?;
+  let ca_cert = ca_params.self_signed(&ca_key).map_err(err)?;
+
+  let mut params = CertificateParams::new(vec![domain.to_string(), format!("*.{}", domain)]).map_err(err)?;
+  params.distinguished_name.push(DnType::CommonName, domain);
+  params.key_usages = vec![KeyUsagePurpose::DigitalSignature, KeyUsagePurpose::KeyEncipherment];
+  params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ServerAuth];
+  params.not_before = now - time::Duration::hours(1);
+  params.not_after = not_after;
+  let key = KeyPair::generate().map_err(err)?;
+  let cert = params.signed_by(&key, &ca_cert, &ca_key).map_err(err)?;
+
+  let paths = [
+    ("certs/ca.crt", ca_cert.pem()),
+    ("certs/ca.key", ca_key.serialize_pem()),
+    ("certs/tls.crt", format!("{}{}", cert.pem(), ca_cert.pem())),
+    ("certs/tls.key", key.serialize_pem()),
+  ];
+  let mut written = Vec::new();
+  for (relative, contents) in paths {
+    let path = workspace.resolve(relative)?;
+    write_private_file(&path, contents.as_bytes())?;
+    written.push(path.display().to_string());
+  }
+  info!("Generated bootstrap certificates for {} (valid {} days)", domain, days);
+
+  let mut written = written.into_iter();
+  Ok(BootstrapCerts {
+    ca_cert_path: written.next().unwrap_or_default(),
+    ca_key_path: written.next().unwrap_or_default(),
+    cert_path: written.next().unwrap_or_default(),
+    key_path: written.next().unwrap_or_default(),
+    not_after: not_after.unix_timestamp(),
+  })
+}
+
+pub fn parse_pem(pem: &[u8]) -> Result<Vec<CertificateDer<'static>>, String> {
+  let chain = CertificateDer::pem_slice_iter(pem)
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(|e| format!("Invalid PEM: {}", e))?;
+  if chain.is_empty() {
+    return Err("No certificates found in PEM data".to_string());
+  }
+  Ok(chain)
+}
+
+/// `der` as a PEM certificate block, without a trailing newline.
+pub fn to_pem(der: &[u8]) -> String {
+  let encoded = base64::engine::general_purpose::STANDARD.encode(der);
+  let lines: Vec<&str> = encoded
+    .as_bytes()
+    .chunks(64)
+    .filter_map(|line| std::str::from_utf8(line).ok())
+    .collect();
+  format!(
+    "-----BEGIN CERTIFICATE-----\n{}\n-----END CERTIFICATE-----",
+    lines.join("\n")
+  )
+}
+
+/// The chain a server presents, fetched without judging it.
+pub fn fetch_remote(host: &str, port: u16) -> Result<Vec<CertificateDer<'static>>, String> {
+  let name = ServerName::try_from(host.to_string()).map_err(|e| format!("Invalid host {}: {}", host, e))?;
+  let config = Arc::new(tls::accept_any_certificate()?);
+  let mut conn = rustls::ClientConnection::new(config, name).map_err(|e| e.to_string())?;
+
+  let mut sock = TcpStream::connect((host, port)).map_err(|e| format!("Cannot connect to {}:{}: {}", host, port, e))?;
+  sock.set_read_timeout(Some(Duration::from_secs(10))).map_err(|e| e.to_string())?;
+  sock.set_write_timeout(Some(Duration::from_secs(10))).map_err(|e| e.to_string())?;
+  while conn.is_handshaking() {
+    conn
+      .complete_io(&mut sock)
+      .map_err(|e| format!("TLS handshake with {}:{} failed: {}", host, port, e))?;
+  }
+  conn
+    .peer_certificates()
+    .map(|certs| certs.iter().map(|c| c.clone().into_owned()).collect())
+    .filter(|certs: &Vec<_>| !certs.is_empty())
+    .ok_or_else(|| format!("{}:{} presented no certificate", host, port))
+}
+
+/// RFC 6125 matching, including a single left-most wildcard label.
+fn name_matches(pattern: &str, name: &str) -> bool {
+  let pattern = pattern.to_ascii_lowercase();
+  let name = name.to_ascii_lowercase();
+  match pattern.strip_prefix("*.") {
+    Some(suffix) => name
+      .split_once('.')
+      .map(|(label, rest)| !label.is_empty() && rest == suffix)
+      .unwrap_or(false),
+    None => pattern == name,
+  }
+}
+
+/// The public web roots, plus `extra_ca` (PEM) if given.
+pub fn roots(extra_ca: Option<&str>) -> Result<rustls::RootCertStore, String> {
+  let mut roots = rustls::RootCertStore::empty();
+  roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+  if let Some(pem) = extra_ca {
+    for ca in parse_pem(pem.as_bytes())? {
+      roots.add(ca).map_err(|e| format!("Invalid CA certificate: {}", e))?;
+    }
+  }
+  Ok(roots)
+}
+
+pub fn verify_chain(chain: &[CertificateDer<'static>], name: &str, extra_ca: Option<&str>) -> Result<(), String> {
+  let roots = roots(extra_ca)?;
+  let verifier = rustls::client::WebPkiServerVerifier::builder_with_provider(Arc::new(roots), tls::provider())
+    .build()
+    .map_err(|e| e.to_string())?;
+  let server_name = ServerName::try_from(name.to_string()).map_err(|e| format!("Invalid name {}: {}", name, e))?;
+  verifier
+    .verify_server_cert(&chain[0], &chain[1..], &server_name, &[], UnixTime::now())
+    .map(|_| ())
+    .map_err(|e| e.to_string())
+}
+
+fn inspect(chain: &[CertificateDer<'static>], domain: Option<&str>, extra_ca: Option<&str>) -> Result<CertReport, String> {
+  let (_, cert) = x509_parser::parse_x509_certificate(&chain[0]).map_err(|e| format!("Invalid certificate: {}", e))?;
+
+  let mut sans = Vec::new();
+  if let Ok(Some(ext)) = cert.subject_alternative_name() {
+    for name in &ext.value.general_names {
+      match name {
+        x509_parser::extensions::GeneralName::DNSName(dns) => sans.push(dns.to_string()),
+        x509_parser::extensions::GeneralName::IPAddress(bytes) => match bytes.len() {
+          4 => sans.push(std::net::Ipv4Addr::new(bytes[0], bytes[1], bytes[2], bytes[3]).to_string()),
+          16 => {
+            let mut octets = [0u8; 16];
+            octets.copy_from_slice(bytes);
+            sans.push(std::net::Ipv6Addr::from(octets).to_string());
+          }
+          _ => {}
+        },
+        _ => {}
+      }
+    }
+  }
+
+  let now = OffsetDateTime::now_utc().unix_timestamp();
+  let not_before = cert.validity().not_before.timestamp();
+  let not_after = cert.validity().not_after.timestamp();
+  let days_remaining = (not_after - now).div_euclid(86_400);
+
+  let check_name = domain.map(str::to_string).or_else(|| sans.iter().find(|s| !s.starts_with("*.")).cloned());
+  let (chain_valid, chain_error) = match &check_name {
+    Some(name) => match verify_chain(chain, name, extra_ca) {
+      Ok(()) => (true, None),
+      Err(e) => (false, Some(e)),
+    },
+    None => (false, Some("Certificate names no host to verify against".to_string())),
+  };
+
+  Ok(CertReport {
+    subject: cert.subject().to_string(),
+    issuer: cert.issuer().to_string(),
+    self_signed: cert.subject() == cert.issuer(),
+    not_before,
+    not_after,
+    days_remaining,
+    expired: now > not_after,
+    expiring_soon: days_remaining < EXPIRY_WARNING_DAYS,
+    covers_domain: domain.map(|d| sans.iter().any(|san| name_matches(san, d))),
+    sans,
+    chain_length: chain.len(),
+    chain_valid,
+    chain_error,
+  })
+}
+
+#[tauri::command]
+pub fn generate_bootstrap_certs(app: AppHandle, domain: String, days: Option<u32>) -> Result<BootstrapCerts, String> {
+  let domain = domain.trim().to_ascii_lowercase();
+  if !validation::is_valid_domain(&domain) {
+    return Err(format!("Invalid domain: {}", domain));
+  }
+  let days = days.unwrap_or(DEFAULT_VALIDITY_DAYS).clamp(1, MAX_VALIDITY_DAYS);
+  generate(&app.state::<Workspace>(), &domain, days)
+}
+
+/// Inspect a certificate chain. `domain` is checked against the SANs and used
+/// for chain verification; `extra_ca` (PEM) is trusted in addition to the
+/// public roots, e.g. the bootstrap CA.
+#[tauri::command]
+pub async fn inspect_certificate(
+  source: CertSource,
+  domain: Option<String>,
+  extra_ca: Option<String>,
+) -> Result<CertReport, String> {
+  tauri::async_runtime::spawn_blocking(move || {
+    let chain = match source {
+      CertSource::File { path } => {
+        let pem = std::fs::read(&path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        parse_pem(&pem)?
+      }
+      CertSource::Pem { pem } => parse_pem(pem.as_bytes())?,
+      CertSource::Remote { host, port } => {
+        let host = host.trim().to_string();
+        if !validation::is_valid_host(&host) {
+          return Err(format!("Invalid host: {}", host));
+        }
+        fetch_remote(&host, port.unwrap_or(443))?
+      }
+    };
+    let domain = domain.map(|d| d.trim().to_ascii_lowercase()).filter(|d| !d.is_empty());
+    inspect(&chain, domain.as_deref(), extra_ca.as_deref())
+  })
+  .await
+  .map_err(|e| e.to_string())?
+}
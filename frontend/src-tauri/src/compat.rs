@@ -0,0 +1,228 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Whether this installer release works with the platform it deploys.
+//!
+//! The playbooks come from the thinkube repository at `THINKUBE_BRANCH` or
+//! the pinned `playbooks_ref`, which move independently of the installer.
+//! That ref publishes `installer-compat.json` with the installer versions
+//! it supports, and the shell fetches it once at startup and compares it
+//! with its own version. An installer older than `min_installer`, or one
+//! listed in `blocked`, is unsupported and the wizard blocks; one newer
+//! than `max_tested_installer` is untested and the wizard warns. A manifest
+//! that can't be fetched leaves the verdict unknown, which also only warns,
+//! so an offline or forked setup isn't stopped by it.
+
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tracing::{info, warn};
+
+use crate::i18n;
+use crate::playbooks;
+use crate::settings::SettingsStore;
+
+pub const EVENT: &str = "platform-compatibility";
+const MANIFEST: &str = "installer-compat.json";
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Deserialize)]
+struct Manifest {
+  platform_version: Option<String>,
+  min_installer: Option<String>,
+  max_tested_installer: Option<String>,
+  #[serde(default)]
+  blocked: Vec<BlockedVersion>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct BlockedVersion {
+  version: String,
+  reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Verdict {
+  /// Within the range the platform was tested with
+  Compatible,
+  /// Newer than the platform was tested with; the wizard warns
+  Untested,
+  /// Too old or blocked; the wizard should block
+  Unsupported,
+  /// The manifest couldn't be fetched or read
+  Unknown,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlatformCompatibility {
+  pub installer_version: String,
+  pub platform_version: Option<String>,
+  pub branch: String,
+  pub manifest_url: Option<String>,
+  pub verdict: Verdict,
+  pub message: String,
+}
+
+#[derive(Default)]
+pub struct Compatibility(Mutex<Option<PlatformCompatibility>>);
+
+/// The raw file URL of the manifest; GitHub repositories only.
+fn manifest_url(repo: &str, branch: &str) -> Option<String> {
+  let path = repo
+    .trim()
+    .strip_prefix("https://github.com/")
+    .or_else(|| repo.trim().strip_prefix("git@github.com:"))?;
+  let path = path.trim_end_matches('/').trim_end_matches(".git");
+  let (owner, name) = path.split_once('/')?;
+  if owner.is_empty() || name.is_empty() || name.contains('/') {
+    return None;
+  }
+  Some(format!(
+    "https://raw.githubusercontent.com/{}/{}/{}/{}",
+    owner, name, branch, MANIFEST
+  ))
+}
+
+/// `major.minor.patch`, ignoring a leading `v` and any pre-release or
+/// build suffix; missing parts count as zero.
+fn parse_version(version: &str) -> Option<(u64, u64, u64)> {
+  let core = version.trim().trim_start_matches('v').split(['-', '+']).next()?;
+  let mut parts = core.split('.').map(|p| p.parse::<u64>());
+  let major = parts.next()?.ok()?;
+  let minor = parts.next().unwrap_or(Ok(0)).ok()?;
+  let patch = parts.next().unwrap_or(Ok(0)).ok()?;
+  parts.next().is_none().then_some((major, minor, patch))
+}
+
+fn fetch(url: &str) -> Result<Manifest, String> {
+  let agent = ureq::AgentBuilder::new().timeout(TIMEOUT).build();
+  agent
+    .get(url)
+    .call()
+    .map_err(|e| e.to_string())?
+    .into_json::<Manifest>()
+    .map_err(|e| format!("Invalid {}: {}", MANIFEST, e))
+}
+
+fn judge(installer: &str, manifest: &Manifest) -> (Verdict, String) {
+  let Some(current) = parse_version(installer) else {
+    return (
+      Verdict::Unknown,
+      i18n::t_args("compat-unknown-version", &[("version", installer)]),
+    );
+  };
+  if let Some(blocked) = manifest
+    .blocked
+    .iter()
+    .find(|b| parse_version(&b.version) == Some(current))
+  {
+    let message = match &blocked.reason {
+      Some(reason) => i18n::t_args("compat-blocked-reason", &[("version", installer), ("reason", reason)]),
+      None => i18n::t_args("compat-blocked", &[("version", installer)]),
+    };
+    return (Verdict::Unsupported, message);
+  }
+  if let Some(min) = manifest.min_installer.as_deref() {
+    if parse_version(min).is_some_and(|min| current < min) {
+      return (
+        Verdict::Unsupported,
+        i18n::t_args("compat-too-old", &[("version", installer), ("min", min)]),
+      );
+    }
+  }
+  if let Some(max) = manifest.max_tested_installer.as_deref() {
+    if parse_version(max).is_some_and(|max| current > max) {
+      return (
+        Verdict::Untested,
+        i18n::t_args("compat-untested", &[("version", installer), ("max", max)]),
+      );
+    }
+  }
+  (
+    Verdict::Compatible,
+    i18n::t_args("compat-compatible", &[("version", installer)]),
+  )
+}
+
+fn check(app: &AppHandle) -> PlatformCompatibility {
+  let installer_version = app.package_info().version.to_string();
+  // The pinned checkout, when there is one, is what the playbooks run from
+  let branch = app
+    .try_state::<SettingsStore>()
+    .and_then(|s| s.get().playbooks_ref)
+    .unwrap_or_else(playbooks::default_branch);
+  let repo = playbooks::repo_url();
+  let manifest_url = manifest_url(&repo, &branch);
+  let mut result = PlatformCompatibility {
+    installer_version: installer_version.clone(),
+    platform_version: None,
+    branch: branch.clone(),
+    manifest_url: manifest_url.clone(),
+    verdict: Verdict::Unknown,
+    message: i18n::t("compat-no-manifest"),
+  };
+  let Some(url) = manifest_url else {
+    info!("No compatibility manifest for {}; skipping the version check", repo);
+    return result;
+  };
+  match fetch(&url) {
+    Ok(manifest) => {
+      let (verdict, message) = judge(&installer_version, &manifest);
+      info!(
+        "Installer {} with platform {} ({}): {:?}",
+        installer_version,
+        manifest.platform_version.as_deref().unwrap_or("unknown"),
+        branch,
+        verdict
+      );
+      result.platform_version = manifest.platform_version;
+      result.verdict = verdict;
+      result.message = message;
+    }
+    Err(e) => {
+      warn!("Could not fetch {}: {}", url, e);
+      result.message = i18n::t_args("compat-fetch-failed", &[("error", &e)]);
+    }
+  }
+  result
+}
+
+/// Run the check in the background at startup; the result arrives as an
+/// [`EVENT`] and through `get_platform_compatibility`.
+pub fn start(app: &AppHandle) {
+  let app = app.clone();
+  std::thread::spawn(move || {
+    let result = check(&app);
+    if let Some(state) = app.try_state::<Compatibility>() {
+      if let Ok(mut current) = state.0.lock() {
+        *current = Some(result.clone());
+      }
+    }
+    let _ = app.emit(EVENT, result);
+  });
+}
+
+/// The startup verdict, or a fresh one with `refresh` (or before the
+/// startup check has finished).
+#[tauri::command]
+pub async fn get_platform_compatibility(
+  app: AppHandle,
+  state: State<'_, Compatibility>,
+  refresh: Option<bool>,
+) -> Result<PlatformCompatibility, String> {
+  if !refresh.unwrap_or(false) {
+    if let Some(result) = state.0.lock().map_err(|e| e.to_string())?.clone() {
+      return Ok(result);
+    }
+  }
+  let handle = app.clone();
+  let result = tauri::async_runtime::spawn_blocking(move || check(&handle))
+    .await
+    .map_err(|e| e.to_string())?;
+  *state.0.lock().map_err(|e| e.to_string())? = Some(result.clone());
+  Ok(result)
+}
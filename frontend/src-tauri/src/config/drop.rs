@@ -0,0 +1,49 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Importing a config file by dropping it onto the main window.
+
+use serde::Serialize;
+use tauri::{AppHandle, DragDropEvent, Emitter, WindowEvent};
+use tracing::{info, warn};
+
+use super::migrate::Migration;
+use super::ConfigDocument;
+
+pub const CONFIG_IMPORTED_EVENT: &str = "config-imported";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub enum ImportResult {
+  Ok {
+    path: String,
+    document: Box<ConfigDocument>,
+    migration: Migration,
+  },
+  Error { path: String, error: String },
+}
+
+/// Window event hook: parse dropped files and report each one.
+pub fn handle_window_event(app: &AppHandle, event: &WindowEvent) {
+  let WindowEvent::DragDrop(DragDropEvent::Drop { paths, .. }) = event else { return };
+
+  for path in paths {
+    let result = match super::read_file(path) {
+      Ok((document, migration)) => {
+        info!("Imported config from {}", path.display());
+        ImportResult::Ok {
+          path: path.display().to_string(),
+          document: Box::new(document),
+          migration,
+        }
+      }
+      Err(error) => {
+        warn!("Rejected dropped file {}: {}", path.display(), error);
+        ImportResult::Error { path: path.display().to_string(), error }
+      }
+    };
+    let _ = app.emit(CONFIG_IMPORTED_EVENT, result);
+  }
+}
@@ -0,0 +1,85 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Upgrading config files written by older installers.
+//!
+//! Each step takes the raw JSON of version N and returns version N + 1, so
+//! a file of any age is brought forward one step at a time before it is
+//! deserialized into the current `ConfigDocument`.
+
+use serde_json::{json, Map, Value};
+
+use super::{CURRENT_VERSION, FORMAT};
+
+/// Result of bringing a file up to `CURRENT_VERSION`.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct Migration {
+  /// Version the file had on disk
+  pub from_version: u32,
+  pub to_version: u32,
+  /// Human-readable description of each change applied
+  pub notes: Vec<String>,
+}
+
+/// Version of a raw file. Files without a `format` envelope are the bare
+/// `thinkube-config` objects that early installers told users to copy out of
+/// local storage; they are treated as version 0.
+fn detect_version(raw: &Map<String, Value>) -> Result<u32, String> {
+  match raw.get("format").and_then(Value::as_str) {
+    Some(FORMAT) => raw
+      .get("version")
+      .and_then(Value::as_u64)
+      .map(|v| v as u32)
+      .ok_or_else(|| "Config file has no valid \"version\"".to_string()),
+    Some(other) => Err(format!("Unknown config format: {}", other)),
+    None if raw.contains_key("domainName") || raw.contains_key("overlayProvider") => Ok(0),
+    None => Err("Not a Thinkube installer config (missing \"format\")".to_string()),
+  }
+}
+
+/// v0 → v1: wrap a bare `thinkube-config` object in the envelope.
+fn v0_to_v1(raw: Map<String, Value>, notes: &mut Vec<String>) -> Map<String, Value> {
+  notes.push("Wrapped bare wizard settings in a versioned config envelope".to_string());
+  let mut envelope = Map::new();
+  envelope.insert("format".to_string(), json!(FORMAT));
+  envelope.insert("version".to_string(), json!(1));
+  envelope.insert("config".to_string(), Value::Object(raw));
+  envelope
+}
+
+/// Bring `raw` up to `CURRENT_VERSION`.
+pub fn upgrade(raw: Value) -> Result<(Value, Migration), String> {
+  let mut object = match raw {
+    Value::Object(object) => object,
+    _ => return Err("Config file must contain a JSON object".to_string()),
+  };
+
+  let from_version = detect_version(&object)?;
+  if from_version > CURRENT_VERSION {
+    return Err(format!(
+      "Config file version {} was written by a newer installer (this one supports up to {})",
+      from_version, CURRENT_VERSION
+    ));
+  }
+
+  let mut notes = Vec::new();
+  let mut version = from_version;
+  while version < CURRENT_VERSION {
+    object = match version {
+      0 => v0_to_v1(object, &mut notes),
+      _ => unreachable!("missing config migration from version {}", version),
+    };
+    version += 1;
+  }
+
+  Ok((
+    Value::Object(object),
+    Migration {
+      from_version,
+      to_version: CURRENT_VERSION,
+      notes,
+    },
+  ))
+}
@@ -0,0 +1,196 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Exported installer configuration files.
+//!
+//! A config file wraps what the wizard keeps in web storage (the
+//! `thinkube-config` object, network plan, cluster nodes, GPU assignments)
+//! in a versioned envelope so it can be replayed on another machine. The
+//! wizard owns the inner shapes; this module checks the envelope and the
+//! handful of fields every install depends on.
+
+pub mod drop;
+pub mod migrate;
+pub mod rules;
+pub mod schema;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::AppHandle;
+use tracing::info;
+
+use crate::validation;
+use crate::workspace::write_private_file;
+
+/// Value of the `format` field identifying our files.
+pub const FORMAT: &str = "thinkube-installer-config";
+
+/// Envelope version written by this installer.
+pub const CURRENT_VERSION: u32 = 1;
+
+/// Largest file we attempt to parse; real configs are a few KiB.
+const MAX_CONFIG_BYTES: u64 = 4 * 1024 * 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigDocument {
+  pub format: String,
+  pub version: u32,
+  /// Installer version that wrote the file
+  #[serde(default)]
+  pub installer_version: String,
+  /// Seconds since the Unix epoch
+  #[serde(default)]
+  pub exported_at: u64,
+  /// The wizard's `thinkube-config` object
+  pub config: Map<String, Value>,
+  #[serde(default)]
+  pub network: Value,
+  #[serde(default)]
+  pub nodes: Vec<Value>,
+  #[serde(default)]
+  pub gpu_assignments: Value,
+  #[serde(default)]
+  pub deployment_type: Option<String>,
+}
+
+/// Parse a config file, upgrading older versions, and validate it.
+pub fn parse(text: &str) -> Result<(ConfigDocument, migrate::Migration), String> {
+  let raw: Value = serde_json::from_str(text).map_err(|e| format!("Not a valid JSON file: {}", e))?;
+  let (upgraded, migration) = migrate::upgrade(raw)?;
+
+  let document: ConfigDocument = serde_json::from_value(upgraded)
+    .map_err(|e| format!("Config file does not match the expected structure: {}", e))?;
+  validate(&document)?;
+  Ok((document, migration))
+}
+
+/// Check the fields every install depends on.
+pub fn validate(document: &ConfigDocument) -> Result<(), String> {
+  match document.config.get("domainName") {
+    None | Some(Value::Null) => {}
+    Some(Value::String(domain)) if domain.is_empty() || validation::is_valid_domain(domain) => {}
+    Some(other) => return Err(format!("config.domainName is not a valid domain: {}", other)),
+  }
+
+  if let Some(provider) = document.config.get("overlayProvider").and_then(Value::as_str) {
+    if !matches!(provider, "zerotier" | "tailscale") {
+      return Err(format!("config.overlayProvider must be zerotier or tailscale, got {}", provider));
+    }
+  }
+
+  for (index, node) in document.nodes.iter().enumerate() {
+    let node = node
+      .as_object()
+      .ok_or_else(|| format!("nodes[{}] must be an object", index))?;
+    match node.get("hostname").and_then(Value::as_str) {
+      Some(hostname) if validation::is_valid_label(hostname) => {}
+      Some(hostname) => return Err(format!("nodes[{}].hostname is not a valid hostname: {}", index, hostname)),
+      None => return Err(format!("nodes[{}] has no hostname", index)),
+    }
+  }
+
+  if !document.network.is_null() && !document.network.is_object() {
+    return Err("network must be an object".to_string());
+  }
+  Ok(())
+}
+
+/// Keys in the `thinkube-config` object holding credentials. Dropped from
+/// exports unless the user explicitly asks to include them.
+fn is_secret_key(key: &str) -> bool {
+  let key = key.to_ascii_lowercase();
+  ["token", "password", "secret", "authkey", "apikey"]
+    .iter()
+    .any(|marker| key.contains(marker))
+}
+
+/// Read, upgrade and validate a config file from disk.
+pub fn read_file(path: &Path) -> Result<(ConfigDocument, migrate::Migration), String> {
+  let metadata = std::fs::metadata(path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+  if !metadata.is_file() {
+    return Err(format!("{} is not a file", path.display()));
+  }
+  if metadata.len() > MAX_CONFIG_BYTES {
+    return Err(format!("{} is too large to be an installer config", path.display()));
+  }
+  let text = std::fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+  parse(&text)
+}
+
+/// What the wizard hands over for export: the contents of its storage.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExportRequest {
+  pub config: Map<String, Value>,
+  #[serde(default)]
+  pub network: Value,
+  #[serde(default)]
+  pub nodes: Vec<Value>,
+  #[serde(default)]
+  pub gpu_assignments: Value,
+  #[serde(default)]
+  pub deployment_type: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportedConfig {
+  pub document: ConfigDocument,
+  pub migration: migrate::Migration,
+}
+
+/// Build the versioned document for `request`.
+pub fn build_document(app: &AppHandle, request: ExportRequest, include_secrets: bool) -> ConfigDocument {
+  let mut config = request.config;
+  if !include_secrets {
+    config.retain(|key, _| !is_secret_key(key));
+  }
+
+  ConfigDocument {
+    format: FORMAT.to_string(),
+    version: CURRENT_VERSION,
+    installer_version: app.package_info().version.to_string(),
+    exported_at: SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .map(|d| d.as_secs())
+      .unwrap_or_default(),
+    config,
+    network: request.network,
+    nodes: request.nodes,
+    gpu_assignments: request.gpu_assignments,
+    deployment_type: request.deployment_type,
+  }
+}
+
+/// Write the install definition to `path` as versioned JSON. The file is
+/// created 0600 since it may contain credentials.
+#[tauri::command]
+pub fn export_config(
+  app: AppHandle,
+  path: String,
+  request: ExportRequest,
+  include_secrets: Option<bool>,
+) -> Result<ConfigDocument, String> {
+  let document = build_document(&app, request, include_secrets.unwrap_or(false));
+  validate(&document)?;
+
+  let json = serde_json::to_string_pretty(&document).map_err(|e| e.to_string())?;
+  write_private_file(Path::new(&path), json.as_bytes())?;
+  info!("Exported config to {}", path);
+  Ok(document)
+}
+
+/// Load a config file, upgrading it from older versions if necessary.
+#[tauri::command]
+pub fn import_config(path: String) -> Result<ImportedConfig, String> {
+  let (document, migration) = read_file(Path::new(&path))?;
+  if migration.from_version != migration.to_version {
+    info!(
+      "Migrated {} from config version {} to {}",
+      path, migration.from_version, migration.to_version
+    );
+  }
+  Ok(ImportedConfig { document, migration })
+}
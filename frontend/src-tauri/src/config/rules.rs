@@ -0,0 +1,248 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Checking a whole installer config before anything is deployed.
+//!
+//! `validate_config` runs the embedded schema and then the rules that span
+//! fields, which the playbooks would otherwise only trip over halfway
+//! through: overlapping subnets, hostnames or addresses used twice, nodes
+//! and the gateway outside the cluster subnet, a load balancer range that
+//! is empty or covers a node, the wrong number of control planes and GPUs
+//! assigned on nodes that have none. Every problem is reported, each with
+//! the path of the field it's about (`nodes[2].hostname`), so the wizard
+//! can show it next to that field.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::net::Ipv4Addr;
+
+use super::{migrate, schema};
+use crate::net::ipplan::Subnet;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Issue {
+  /// Dotted path of the field, e.g. `network.networkConfig.cidr`
+  pub path: String,
+  /// Schema keyword or rule name, for the wizard to key on
+  pub code: String,
+  pub message: String,
+}
+
+impl Issue {
+  pub fn new(path: &str, code: &str, message: String) -> Self {
+    Self {
+      path: path.to_string(),
+      code: code.to_string(),
+      message,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ValidationReport {
+  pub valid: bool,
+  pub errors: Vec<Issue>,
+}
+
+fn text<'a>(value: &'a Value, key: &str) -> Option<&'a str> {
+  value.get(key).and_then(Value::as_str).filter(|s| !s.is_empty())
+}
+
+fn subnet(value: &Value, key: &str) -> Option<Subnet> {
+  text(value, key).and_then(|cidr| Subnet::parse(cidr).ok())
+}
+
+fn address(value: &Value, key: &str) -> Option<Ipv4Addr> {
+  text(value, key).and_then(|ip| ip.parse().ok())
+}
+
+fn octet(value: &Value, key: &str) -> Option<u8> {
+  text(value, key).and_then(|o| o.parse().ok())
+}
+
+/// Report every entry after the first that has the same `key`.
+fn duplicates(items: &[Value], prefix: &str, key: &str, what: &str, issues: &mut Vec<Issue>) {
+  let mut seen: HashMap<String, usize> = HashMap::new();
+  for (index, item) in items.iter().enumerate() {
+    let Some(value) = text(item, key) else {
+      continue;
+    };
+    match seen.get(&value.to_ascii_lowercase()) {
+      Some(first) => issues.push(Issue::new(
+        &format!("{}[{}].{}", prefix, index, key),
+        "duplicate",
+        format!("{} {} is also used by {}[{}]", what, value, prefix, first),
+      )),
+      None => {
+        seen.insert(value.to_ascii_lowercase(), index);
+      }
+    }
+  }
+}
+
+fn check_network(document: &Value, issues: &mut Vec<Issue>) {
+  let net = &document["network"]["networkConfig"];
+  let Some(cluster) = subnet(net, "cidr") else {
+    return;
+  };
+  let path = "network.networkConfig";
+
+  if let Some(overlay) = subnet(net, "overlayCIDR") {
+    if overlay.overlaps(&cluster) {
+      issues.push(Issue::new(
+        &format!("{}.overlayCIDR", path),
+        "overlap",
+        format!("The overlay subnet {} overlaps the cluster subnet {}", overlay, cluster),
+      ));
+    }
+  }
+  if let Some(gateway) = address(net, "gateway").filter(|g| !cluster.contains(*g)) {
+    issues.push(Issue::new(
+      &format!("{}.gateway", path),
+      "outside_subnet",
+      format!("The gateway {} is outside {}", gateway, cluster),
+    ));
+  }
+
+  let range = match (octet(net, "lbStartOctet"), octet(net, "lbEndOctet")) {
+    (Some(start), Some(end)) if start > end => {
+      issues.push(Issue::new(
+        &format!("{}.lbEndOctet", path),
+        "range",
+        format!("The load balancer range ends ({}) before it starts ({})", end, start),
+      ));
+      None
+    }
+    (Some(start), Some(end)) => Some(start..=end),
+    _ => None,
+  };
+
+  let servers = document["network"]["physicalServers"]
+    .as_array()
+    .map(Vec::as_slice)
+    .unwrap_or(&[]);
+  let nodes = document["nodes"].as_array().map(Vec::as_slice).unwrap_or(&[]);
+  for (prefix, list) in [("network.physicalServers", servers), ("nodes", nodes)] {
+    for (index, item) in list.iter().enumerate() {
+      let Some(ip) = address(item, "ip") else {
+        continue;
+      };
+      let field = format!("{}[{}].ip", prefix, index);
+      if !cluster.contains(ip) {
+        issues.push(Issue::new(
+          &field,
+          "outside_subnet",
+          format!("{} is outside the cluster subnet {}", ip, cluster),
+        ));
+      } else if range.as_ref().is_some_and(|r| r.contains(&ip.octets()[3])) {
+        issues.push(Issue::new(
+          &field,
+          "lb_conflict",
+          format!("{} is inside the load balancer range", ip),
+        ));
+      }
+    }
+  }
+}
+
+fn check_nodes(document: &Value, issues: &mut Vec<Issue>) {
+  let nodes = document["nodes"].as_array().map(Vec::as_slice).unwrap_or(&[]);
+  duplicates(nodes, "nodes", "hostname", "Hostname", issues);
+  duplicates(nodes, "nodes", "ip", "Address", issues);
+  if let Some(servers) = document["network"]["physicalServers"].as_array() {
+    duplicates(servers, "network.physicalServers", "hostname", "Hostname", issues);
+    duplicates(servers, "network.physicalServers", "ip", "Address", issues);
+    duplicates(
+      servers,
+      "network.physicalServers",
+      "overlayIP",
+      "Overlay address",
+      issues,
+    );
+  }
+
+  if !nodes.is_empty() {
+    match nodes
+      .iter()
+      .filter(|n| text(n, "role") == Some("control_plane"))
+      .count()
+    {
+      1 => {}
+      0 => issues.push(Issue::new(
+        "nodes",
+        "control_plane",
+        "No node has the control plane role".to_string(),
+      )),
+      n => issues.push(Issue::new(
+        "nodes",
+        "control_plane",
+        format!("{} nodes have the control plane role; Thinkube uses exactly one", n),
+      )),
+    }
+  }
+
+  let without_gpu = |hostname: &str| {
+    nodes
+      .iter()
+      .any(|n| text(n, "hostname") == Some(hostname) && n.get("hasGPU") == Some(&Value::Bool(false)))
+  };
+  for (index, node) in nodes.iter().enumerate() {
+    let Some(hostname) = text(node, "hostname").filter(|h| without_gpu(h)) else {
+      continue;
+    };
+    let assigned = node["gpus"].as_array().is_some_and(|g| !g.is_empty()) || node["gpu"].as_bool() == Some(true);
+    if assigned {
+      issues.push(Issue::new(
+        &format!("nodes[{}].gpus", index),
+        "gpu_without_device",
+        format!("{} has GPUs assigned but no GPU was detected on it", hostname),
+      ));
+    }
+  }
+  // Keyed by the hostname, or the hostname and a GPU index
+  if let Some(assignments) = document["gpu_assignments"].as_object() {
+    for key in assignments.keys() {
+      let host = key.rsplit_once(['-', ':']).map_or(key.as_str(), |(host, _)| host);
+      if let Some(hostname) = [key.as_str(), host].into_iter().find(|h| without_gpu(h)) {
+        issues.push(Issue::new(
+          &format!("gpu_assignments.{}", key),
+          "gpu_without_device",
+          format!("A GPU is assigned on {}, which has no GPU", hostname),
+        ));
+      }
+    }
+  }
+}
+
+/// Check a config: an exported file (any version) or the wizard's own
+/// `{ config, network, nodes, gpu_assignments }`.
+#[tauri::command]
+pub fn validate_config(json: Value) -> ValidationReport {
+  let document = if json.get("format").is_some() {
+    match migrate::upgrade(json) {
+      Ok((upgraded, _)) => upgraded,
+      Err(e) => {
+        return ValidationReport {
+          valid: false,
+          errors: vec![Issue::new("format", "format", e)],
+        }
+      }
+    }
+  } else {
+    json
+  };
+
+  let mut errors = Vec::new();
+  schema::check(schema::schema(), &document, "", &mut errors);
+  if document.is_object() {
+    check_network(&document, &mut errors);
+    check_nodes(&document, &mut errors);
+  }
+  ValidationReport {
+    valid: errors.is_empty(),
+    errors,
+  }
+}
@@ -0,0 +1,168 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! The JSON Schema for installer configs, and the part of JSON Schema
+//! needed to check it.
+//!
+//! `schema.json` is embedded in the binary. Only the keywords it uses are
+//! implemented: `type`, `enum`, `required`, `properties`,
+//! `additionalProperties`, `items`, `pattern`, `minimum` and `format`, the
+//! latter with this installer's formats (`hostname`, `domain`, `ipv4`,
+//! `ipv4-cidr`, `octet`). Empty strings pass every format, since the
+//! wizard leaves optional fields empty rather than unset.
+
+use regex::Regex;
+use serde_json::Value;
+use std::sync::OnceLock;
+
+use super::rules::Issue;
+use crate::net::ipplan::Subnet;
+use crate::validation;
+
+const SCHEMA: &str = include_str!("schema.json");
+
+pub fn schema() -> &'static Value {
+  static PARSED: OnceLock<Value> = OnceLock::new();
+  PARSED.get_or_init(|| serde_json::from_str(SCHEMA).expect("config/schema.json is valid JSON"))
+}
+
+fn type_name(value: &Value) -> &'static str {
+  match value {
+    Value::Null => "null",
+    Value::Bool(_) => "boolean",
+    Value::Number(n) if n.is_i64() || n.is_u64() => "integer",
+    Value::Number(_) => "number",
+    Value::String(_) => "string",
+    Value::Array(_) => "array",
+    Value::Object(_) => "object",
+  }
+}
+
+fn has_type(value: &Value, expected: &str) -> bool {
+  let actual = type_name(value);
+  actual == expected || (expected == "number" && actual == "integer")
+}
+
+fn format_ok(format: &str, text: &str) -> bool {
+  if text.is_empty() {
+    return true;
+  }
+  match format {
+    "hostname" => validation::is_valid_label(text) || validation::is_valid_domain(text),
+    "domain" => validation::is_valid_domain(text),
+    "ipv4" => text.parse::<std::net::Ipv4Addr>().is_ok(),
+    "ipv4-cidr" => text.contains('/') && Subnet::parse(text).is_ok(),
+    "octet" => text.parse::<u8>().is_ok_and(|n| (1..=254).contains(&n)),
+    _ => true,
+  }
+}
+
+fn join(path: &str, key: &str) -> String {
+  if path.is_empty() {
+    key.to_string()
+  } else {
+    format!("{}.{}", path, key)
+  }
+}
+
+/// Check `value` against `schema`, adding an issue per violation.
+pub fn check(schema: &Value, value: &Value, path: &str, issues: &mut Vec<Issue>) {
+  let shown = if path.is_empty() { "The document" } else { path };
+
+  if let Some(expected) = schema.get("type") {
+    let types: Vec<&str> = match expected {
+      Value::String(t) => vec![t.as_str()],
+      Value::Array(ts) => ts.iter().filter_map(Value::as_str).collect(),
+      _ => Vec::new(),
+    };
+    if !types.is_empty() && !types.iter().any(|t| has_type(value, t)) {
+      issues.push(Issue::new(
+        path,
+        "type",
+        format!("{} must be {}, not {}", shown, types.join(" or "), type_name(value)),
+      ));
+      return;
+    }
+  }
+
+  if let Some(options) = schema.get("enum").and_then(Value::as_array) {
+    if !options.contains(value) {
+      let allowed: Vec<String> = options.iter().map(Value::to_string).collect();
+      issues.push(Issue::new(
+        path,
+        "enum",
+        format!("{} must be one of {}, not {}", shown, allowed.join(", "), value),
+      ));
+    }
+  }
+
+  match value {
+    Value::String(text) => {
+      if let Some(pattern) = schema.get("pattern").and_then(Value::as_str) {
+        if Regex::new(pattern).is_ok_and(|re| !re.is_match(text)) {
+          issues.push(Issue::new(
+            path,
+            "pattern",
+            format!("{} has an invalid value: {:?}", shown, text),
+          ));
+        }
+      }
+      if let Some(format) = schema.get("format").and_then(Value::as_str) {
+        if !format_ok(format, text) {
+          issues.push(Issue::new(
+            path,
+            "format",
+            format!("{} is not a valid {}: {:?}", shown, format, text),
+          ));
+        }
+      }
+    }
+    Value::Number(n) => {
+      if let (Some(min), Some(n)) = (schema.get("minimum").and_then(Value::as_f64), n.as_f64()) {
+        if n < min {
+          issues.push(Issue::new(
+            path,
+            "minimum",
+            format!("{} must be at least {}", shown, min),
+          ));
+        }
+      }
+    }
+    Value::Array(items) => {
+      if let Some(item_schema) = schema.get("items") {
+        for (index, item) in items.iter().enumerate() {
+          check(item_schema, item, &format!("{}[{}]", path, index), issues);
+        }
+      }
+    }
+    Value::Object(object) => {
+      for key in schema.get("required").and_then(Value::as_array).into_iter().flatten() {
+        if let Some(key) = key.as_str().filter(|k| !object.contains_key(*k)) {
+          issues.push(Issue::new(
+            &join(path, key),
+            "required",
+            format!("{} is required", join(path, key)),
+          ));
+        }
+      }
+      let properties = schema.get("properties").and_then(Value::as_object);
+      for (key, child) in object {
+        match properties.and_then(|p| p.get(key)) {
+          Some(child_schema) => check(child_schema, child, &join(path, key), issues),
+          None => match schema.get("additionalProperties") {
+            Some(Value::Bool(false)) => issues.push(Issue::new(
+              &join(path, key),
+              "additional",
+              format!("{} is not allowed", join(path, key)),
+            )),
+            Some(extra @ Value::Object(_)) => check(extra, child, &join(path, key), issues),
+            _ => {}
+          },
+        }
+      }
+    }
+    _ => {}
+  }
+}
@@ -0,0 +1,121 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Crash reports for panics in the Rust shell.
+//!
+//! A panic otherwise just makes the window vanish. The hook installed here
+//! writes a report (panic message, backtrace, OS, app version and the tail of
+//! the shell log) to `<app data dir>/crashes/` and leaves a marker so the
+//! next launch can tell the user where the report is.
+
+use std::backtrace::Backtrace;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_dialog::{DialogExt, MessageDialogKind};
+use tracing::{info, warn};
+
+use crate::i18n;
+
+/// Number of trailing log lines included in a report.
+const LOG_TAIL_LINES: usize = 200;
+
+/// Marker holding the path of a report the user hasn't been told about yet.
+const PENDING_MARKER: &str = "pending";
+
+fn crash_dir(app: &AppHandle) -> Option<PathBuf> {
+  app.path().app_data_dir().ok().map(|dir| dir.join("crashes"))
+}
+
+/// Install the panic hook. The previous hook still runs afterwards so the
+/// panic is printed to stderr as usual.
+pub fn install_hook(app: &AppHandle) {
+  let Some(dir) = crash_dir(app) else {
+    warn!("Cannot resolve app data directory; crash reports disabled");
+    return;
+  };
+  let log_dir = app.path().app_log_dir().ok();
+  let app_version = app.package_info().version.to_string();
+  // Resolved now rather than inside the hook, which must not block or panic
+  let os = crate::platform::os_release::compatibility().os.pretty_name;
+
+  let previous = std::panic::take_hook();
+  std::panic::set_hook(Box::new(move |info| {
+    let backtrace = Backtrace::force_capture();
+    let thread = std::thread::current();
+    let mut report = String::new();
+
+    report.push_str("Thinkube Installer crash report\n\n");
+    report.push_str(&format!("App version: {}\n", app_version));
+    report.push_str(&format!("OS: {} ({})\n", os, std::env::consts::ARCH));
+    report.push_str(&format!("Thread: {}\n", thread.name().unwrap_or("<unnamed>")));
+    report.push_str(&format!("Panic: {}\n\n", info));
+    report.push_str(&format!("Backtrace:\n{}\n", backtrace));
+
+    if let Some(tail) = log_dir.as_deref().and_then(read_log_tail) {
+      report.push_str(&format!("\nLast {} log lines:\n", LOG_TAIL_LINES));
+      report.push_str(&tail);
+    }
+
+    match write_report(&dir, &report) {
+      Ok(path) => eprintln!("Crash report written to {}", path.display()),
+      Err(e) => eprintln!("Failed to write crash report: {}", e),
+    }
+
+    previous(info);
+  }));
+}
+
+fn write_report(dir: &Path, report: &str) -> std::io::Result<PathBuf> {
+  std::fs::create_dir_all(dir)?;
+  let timestamp = SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|d| d.as_secs())
+    .unwrap_or_default();
+  let path = dir.join(format!("crash-{}.txt", timestamp));
+
+  let mut file = std::fs::File::create(&path)?;
+  file.write_all(report.as_bytes())?;
+  std::fs::write(dir.join(PENDING_MARKER), path.display().to_string())?;
+  Ok(path)
+}
+
+/// Last `LOG_TAIL_LINES` lines of the shell log, or of the most recently
+/// written log file if there is none.
+fn read_log_tail(log_dir: &Path) -> Option<String> {
+  let shell_log = log_dir.join("shell.log");
+  let path = if shell_log.exists() {
+    shell_log
+  } else {
+    std::fs::read_dir(log_dir)
+      .ok()?
+      .flatten()
+      .filter(|entry| entry.path().extension().map(|ext| ext == "log").unwrap_or(false))
+      .max_by_key(|entry| entry.metadata().and_then(|m| m.modified()).ok())?
+      .path()
+  };
+
+  let contents = std::fs::read_to_string(path).ok()?;
+  let lines: Vec<&str> = contents.lines().collect();
+  let start = lines.len().saturating_sub(LOG_TAIL_LINES);
+  Some(lines[start..].join("\n") + "\n")
+}
+
+/// If the previous run crashed, tell the user where the report is.
+pub fn notify_previous_crash(app: &AppHandle) {
+  let Some(dir) = crash_dir(app) else { return };
+  let marker = dir.join(PENDING_MARKER);
+  let Ok(report_path) = std::fs::read_to_string(&marker) else { return };
+  let _ = std::fs::remove_file(&marker);
+
+  info!("Previous run crashed, report at {}", report_path);
+  app
+    .dialog()
+    .message(i18n::t_args("crash-dialog-body", &[("path", report_path.trim())]))
+    .title(i18n::t("crash-dialog-title"))
+    .kind(MessageDialogKind::Warning)
+    .show(|_| {});
+}
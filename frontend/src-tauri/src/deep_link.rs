@@ -0,0 +1,145 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! `thinkube://` links for sharing cluster settings.
+//!
+//! A link like
+//! `thinkube://configure?domain=lab.example.com&topology=multi-node&ref=v0.3.0`
+//! is parsed and validated here and handed to the wizard as a
+//! `deep-link-prefill` event. A link that launched the app arrives before the
+//! webview is listening, so the last prefill is also kept until the wizard
+//! collects it with `take_deep_link_prefill`. One that comes in while the
+//! splash is still up waits there too, and is only sent, with the main
+//! window brought forward, once startup has shown it.
+
+use serde::Serialize;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, State, Url};
+use tauri_plugin_deep_link::DeepLinkExt;
+use tracing::{info, warn};
+
+use crate::validation;
+
+pub const PREFILL_EVENT: &str = "deep-link-prefill";
+pub const ERROR_EVENT: &str = "deep-link-error";
+
+const SCHEME: &str = "thinkube";
+const TOPOLOGIES: &[&str] = &["single-node", "multi-node"];
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Prefill {
+  pub domain: Option<String>,
+  pub topology: Option<String>,
+  pub git_ref: Option<String>,
+}
+
+#[derive(Default)]
+pub struct PendingPrefill(Mutex<Option<Prefill>>);
+
+/// Parse and validate a `thinkube://configure?...` link.
+pub fn parse(url: &Url) -> Result<Prefill, String> {
+  if url.scheme() != SCHEME {
+    return Err(format!("Unsupported link scheme: {}", url.scheme()));
+  }
+  if url.host_str() != Some("configure") {
+    return Err(format!(
+      "Unsupported link action: {}",
+      url.host_str().unwrap_or("<none>")
+    ));
+  }
+
+  let mut prefill = Prefill::default();
+  for (key, value) in url.query_pairs() {
+    let value = value.trim().to_string();
+    match key.as_ref() {
+      "domain" => {
+        let domain = value.to_ascii_lowercase();
+        if !validation::is_valid_domain(&domain) {
+          return Err(format!("Invalid domain in link: {}", value));
+        }
+        prefill.domain = Some(domain);
+      }
+      "topology" => {
+        if !TOPOLOGIES.contains(&value.as_str()) {
+          return Err(format!(
+            "Invalid topology in link: {} (expected one of {})",
+            value,
+            TOPOLOGIES.join(", ")
+          ));
+        }
+        prefill.topology = Some(value);
+      }
+      "ref" => {
+        if !validation::is_valid_git_ref(&value) {
+          return Err(format!("Invalid git ref in link: {}", value));
+        }
+        prefill.git_ref = Some(value);
+      }
+      other => info!("Ignoring unknown deep link parameter: {}", other),
+    }
+  }
+
+  if prefill.domain.is_none() && prefill.topology.is_none() && prefill.git_ref.is_none() {
+    return Err("Link does not contain any configuration".to_string());
+  }
+  Ok(prefill)
+}
+
+fn handle_urls(app: &AppHandle, urls: Vec<Url>) {
+  for url in urls {
+    info!("Received deep link: {}", url);
+    match parse(&url) {
+      Ok(prefill) => {
+        if let Some(pending) = app.try_state::<PendingPrefill>() {
+          if let Ok(mut slot) = pending.0.lock() {
+            *slot = Some(prefill.clone());
+          }
+        }
+        if !crate::windows::main_revealed() {
+          info!("Holding the link until the installer has started");
+          continue;
+        }
+        let _ = app.emit(PREFILL_EVENT, prefill);
+        let _ = crate::windows::show_main(app);
+      }
+      Err(e) => {
+        warn!("Rejected deep link {}: {}", url, e);
+        let _ = app.emit(ERROR_EVENT, e);
+      }
+    }
+  }
+}
+
+/// Send the link that came in during startup, now that the main window
+/// is up.
+pub fn deliver_pending(app: &AppHandle) {
+  let prefill = app
+    .try_state::<PendingPrefill>()
+    .and_then(|pending| pending.0.lock().ok().and_then(|slot| slot.clone()));
+  if let Some(prefill) = prefill {
+    let _ = app.emit(PREFILL_EVENT, prefill);
+  }
+}
+
+/// Register the scheme (Linux dev builds aren't registered by a package)
+/// and start listening for links.
+pub fn setup(app: &AppHandle) {
+  #[cfg(target_os = "linux")]
+  if let Err(e) = app.deep_link().register_all() {
+    tracing::error!("Failed to register thinkube:// handler: {}", e);
+  }
+
+  if let Ok(Some(urls)) = app.deep_link().get_current() {
+    handle_urls(app, urls);
+  }
+
+  let handle = app.clone();
+  app.deep_link().on_open_url(move |event| handle_urls(&handle, event.urls()));
+}
+
+#[tauri::command]
+pub fn take_deep_link_prefill(pending: State<'_, PendingPrefill>) -> Option<Prefill> {
+  pending.0.lock().ok().and_then(|mut slot| slot.take())
+}
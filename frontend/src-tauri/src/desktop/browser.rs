@@ -0,0 +1,91 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Opening web pages in the user's browser.
+//!
+//! Links in the wizard go through `open_url` rather than straight to the
+//! desktop's opener, so the webview can't be used to launch arbitrary
+//! programs or `file://` paths. Only https URLs are opened, and only on
+//! the hosts below or the cluster's own domain, which the configuration
+//! page reports with `set_cluster_domain`.
+
+use tauri::{State, Url};
+use tracing::{info, warn};
+
+use crate::settings::SettingsStore;
+use crate::validation;
+
+/// Hosts the wizard links to; subdomains are allowed too
+const ALLOWED_HOSTS: &[&str] = &[
+  "thinkube.org",
+  "github.com",
+  "tailscale.com",
+  "huggingface.co",
+  "cloudflare.com",
+  "zerotier.com",
+  "nvidia.com",
+];
+
+fn on_host(host: &str, domain: &str) -> bool {
+  host == domain || host.strip_suffix(domain).is_some_and(|sub| sub.ends_with('.'))
+}
+
+fn check(url: &str, cluster_domain: Option<&str>) -> Result<Url, String> {
+  let parsed = Url::parse(url.trim()).map_err(|e| format!("Invalid URL {:?}: {}", url, e))?;
+  if parsed.scheme() != "https" {
+    return Err(format!("Refusing to open {}: only https links are allowed", parsed));
+  }
+  if !parsed.username().is_empty() || parsed.password().is_some() {
+    return Err(format!(
+      "Refusing to open a URL with credentials for {}",
+      parsed.host_str().unwrap_or("")
+    ));
+  }
+  let host = parsed
+    .host_str()
+    .map(|h| h.trim_end_matches('.').to_ascii_lowercase())
+    .ok_or_else(|| format!("Refusing to open {}: no host", parsed))?;
+  let allowed = ALLOWED_HOSTS
+    .iter()
+    .copied()
+    .chain(cluster_domain)
+    .any(|domain| on_host(&host, domain));
+  if !allowed {
+    return Err(format!("Refusing to open {}: {} is not a known host", parsed, host));
+  }
+  Ok(parsed)
+}
+
+/// Open an https `url` in the default browser if its host is allowed.
+#[tauri::command]
+pub fn open_url(settings: State<'_, SettingsStore>, url: String) -> Result<(), String> {
+  let cluster_domain = settings.get().cluster_domain;
+  let url = check(&url, cluster_domain.as_deref()).map_err(|e| {
+    warn!("{}", e);
+    e
+  })?;
+
+  #[cfg(target_os = "macos")]
+  let opener = "open";
+  #[cfg(not(target_os = "macos"))]
+  let opener = "xdg-open";
+
+  info!("Opening {}", url);
+  std::process::Command::new(opener)
+    .arg(url.as_str())
+    .spawn()
+    .map(|_| ())
+    .map_err(|e| format!("Failed to open {} with {}: {}", url, opener, e))
+}
+
+/// Remember the cluster domain so links to its services can be opened.
+#[tauri::command]
+pub fn set_cluster_domain(settings: State<'_, SettingsStore>, domain: String) -> Result<(), String> {
+  let domain = domain.trim().trim_end_matches('.').to_ascii_lowercase();
+  if !validation::is_valid_domain(&domain) {
+    return Err(format!("Invalid domain: {}", domain));
+  }
+  settings.update(|s| s.cluster_domain = Some(domain))
+}
@@ -0,0 +1,64 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Copying secrets (admin passwords, join tokens, kubeconfig snippets).
+//!
+//! `copy_secret` puts the value on the clipboard and clears it again after a
+//! timeout, but only if the clipboard still holds that value, so something
+//! the user copied in the meantime is left alone. Copying another secret
+//! restarts the timer.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+use tracing::info;
+
+use crate::settings::SettingsStore;
+
+/// Used unless `clipboard_clear_secs` is set in settings.json.
+const DEFAULT_CLEAR_SECS: u64 = 30;
+const MAX_CLEAR_SECS: u64 = 600;
+
+/// Bumped on every copy so only the newest timer clears the clipboard.
+#[derive(Default)]
+pub struct SecretClipboard {
+  generation: AtomicU64,
+}
+
+/// Copy `value` and schedule it to be cleared. Returns the timeout used.
+#[tauri::command]
+pub fn copy_secret(
+  app: AppHandle,
+  clipboard: State<'_, SecretClipboard>,
+  settings: State<'_, SettingsStore>,
+  value: String,
+  clear_after_secs: Option<u64>,
+) -> Result<u64, String> {
+  let secs = clear_after_secs
+    .or(settings.get().clipboard_clear_secs)
+    .unwrap_or(DEFAULT_CLEAR_SECS)
+    .clamp(1, MAX_CLEAR_SECS);
+
+  app
+    .clipboard()
+    .write_text(value.clone())
+    .map_err(|e| format!("Failed to copy to clipboard: {}", e))?;
+  let generation = clipboard.generation.fetch_add(1, Ordering::SeqCst) + 1;
+
+  std::thread::spawn(move || {
+    std::thread::sleep(Duration::from_secs(secs));
+    let state = app.state::<SecretClipboard>();
+    if state.generation.load(Ordering::SeqCst) != generation {
+      return;
+    }
+    let still_ours = app.clipboard().read_text().map(|t| t == value).unwrap_or(false);
+    if still_ours {
+      let _ = app.clipboard().clear();
+      info!("Cleared secret from clipboard after {}s", secs);
+    }
+  });
+  Ok(secs)
+}
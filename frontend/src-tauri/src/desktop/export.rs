@@ -0,0 +1,92 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Saving the kubeconfig and credentials where the user chooses.
+//!
+//! `export_file` asks for the destination with the native save dialog and
+//! writes the file itself, readable by the user only, so secrets never go
+//! through a webview download into a world-readable Downloads folder. The
+//! kubeconfig is read from `$KUBECONFIG` or `~/.kube/config` unless the
+//! page passes the contents; credentials always come from the page.
+
+use serde::Deserialize;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_dialog::DialogExt;
+use tracing::info;
+
+use crate::i18n;
+use crate::uninstall::kubeconfig_path;
+use crate::windows::MAIN_WINDOW;
+use crate::workspace::write_private_file;
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportKind {
+  Kubeconfig,
+  Credentials,
+}
+
+impl ExportKind {
+  fn default_name(self) -> &'static str {
+    match self {
+      ExportKind::Kubeconfig => "kubeconfig.yaml",
+      ExportKind::Credentials => "thinkube-credentials.txt",
+    }
+  }
+
+  fn filter(self) -> (String, &'static [&'static str]) {
+    match self {
+      ExportKind::Kubeconfig => (i18n::t("export-filter-kubeconfig"), &["yaml", "yml", "conf"]),
+      ExportKind::Credentials => (i18n::t("export-filter-text"), &["txt"]),
+    }
+  }
+}
+
+/// Ask where to save `kind` and write it there with 0600 permissions.
+/// Returns the chosen path, or `None` when the dialog was cancelled.
+#[tauri::command]
+pub async fn export_file(
+  app: AppHandle,
+  kind: ExportKind,
+  contents: Option<String>,
+  file_name: Option<String>,
+) -> Result<Option<String>, String> {
+  let contents = match (kind, contents) {
+    (_, Some(contents)) => contents,
+    (ExportKind::Kubeconfig, None) => {
+      let path = kubeconfig_path(&app).ok_or("Cannot locate the kubeconfig")?;
+      std::fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?
+    }
+    (ExportKind::Credentials, None) => return Err("No credentials to export".to_string()),
+  };
+
+  tauri::async_runtime::spawn_blocking(move || {
+    let (filter, extensions) = kind.filter();
+    let mut dialog = app
+      .dialog()
+      .file()
+      .set_title(match kind {
+        ExportKind::Kubeconfig => i18n::t("export-kubeconfig-title"),
+        ExportKind::Credentials => i18n::t("export-credentials-title"),
+      })
+      .set_file_name(file_name.unwrap_or_else(|| kind.default_name().to_string()))
+      .add_filter(filter, extensions);
+    if let Some(window) = app.get_webview_window(MAIN_WINDOW) {
+      dialog = dialog.set_parent(&window);
+    }
+    if let Ok(home) = app.path().home_dir() {
+      dialog = dialog.set_directory(home);
+    }
+    let Some(chosen) = dialog.blocking_save_file() else {
+      return Ok(None);
+    };
+    let path = chosen.into_path().map_err(|e| e.to_string())?;
+    write_private_file(&path, contents.as_bytes())?;
+    info!("Exported {:?} to {}", kind, path.display());
+    Ok(Some(path.display().to_string()))
+  })
+  .await
+  .map_err(|e| e.to_string())?
+}
@@ -0,0 +1,16 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Hand-offs to the user's desktop: terminals, file managers, notifications
+//! and the like.
+
+pub mod browser;
+pub mod clipboard;
+pub mod export;
+pub mod notify;
+pub mod pick;
+pub mod progress;
+pub mod reveal;
+pub mod terminal;
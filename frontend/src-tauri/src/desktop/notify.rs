@@ -0,0 +1,142 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Desktop notifications for an installation running in the background.
+//!
+//! The deploy page reports finished phases, failures and pauses with
+//! `notify_install_event`. When the main window doesn't have focus the
+//! shell shows a desktop notification and asks the window manager for
+//! attention. On Linux the notification goes through `notify-send` with a
+//! default action, so clicking it brings the installer back; macOS has no
+//! such hook for `osascript` notifications, and there the Dock icon
+//! bounces too. Setting `desktop_notifications` to false turns them off.
+
+use serde::Deserialize;
+use tauri::{AppHandle, Manager, State, UserAttentionType};
+
+use crate::i18n;
+use crate::settings::SettingsStore;
+use crate::windows::MAIN_WINDOW;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum InstallEvent {
+  /// The last playbook of a phase succeeded
+  PhaseComplete { phase: String },
+  InstallComplete,
+  /// The installation is paused until the user does something
+  InputRequired { prompt: String },
+  Failed { step: String, message: Option<String> },
+}
+
+impl InstallEvent {
+  fn text(&self) -> (String, String) {
+    match self {
+      InstallEvent::PhaseComplete { phase } => (
+        i18n::t("notify-phase-complete-title"),
+        i18n::t_args("notify-phase-complete-body", &[("phase", phase)]),
+      ),
+      InstallEvent::InstallComplete => (
+        i18n::t("notify-install-complete-title"),
+        i18n::t("notify-install-complete-body"),
+      ),
+      InstallEvent::InputRequired { prompt } => (i18n::t("notify-input-required-title"), prompt.clone()),
+      InstallEvent::Failed { step, message } => (
+        i18n::t_args("notify-failed-title", &[("step", step)]),
+        message.clone().unwrap_or_else(|| i18n::t("notify-failed-body")),
+      ),
+    }
+  }
+}
+
+#[cfg(target_os = "linux")]
+fn show(app: &AppHandle, title: &str, body: &str) {
+  use std::process::Command;
+  use tracing::{debug, warn};
+
+  use crate::platform::find_program;
+  use crate::windows;
+
+  const APP_NAME: &str = "Thinkube Installer";
+
+  let Some(notify_send) = find_program("notify-send", &[]) else {
+    debug!("notify-send not found; skipping the desktop notification");
+    return;
+  };
+  let app = app.clone();
+  let (title, body) = (title.to_string(), body.to_string());
+  // --wait blocks until the notification is clicked or closed
+  std::thread::spawn(move || {
+    let output = Command::new(&notify_send)
+      .args(["--app-name", APP_NAME, "--action=default=Show", "--wait"])
+      .args([&title, &body])
+      .output();
+    match output {
+      Ok(out) if out.status.success() => {
+        if String::from_utf8_lossy(&out.stdout).trim() == "default" {
+          let _ = windows::show_main(&app);
+        }
+      }
+      // libnotify before 0.7.9 has no actions; show it without one
+      Ok(_) => {
+        let _ = Command::new(&notify_send)
+          .args(["--app-name", APP_NAME])
+          .args([&title, &body])
+          .status();
+      }
+      Err(e) => warn!("Failed to run notify-send: {}", e),
+    }
+  });
+}
+
+#[cfg(target_os = "macos")]
+fn show(_app: &AppHandle, title: &str, body: &str) {
+  use std::process::Command;
+  use tracing::warn;
+
+  let quote = |s: &str| format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""));
+  let script = format!("display notification {} with title {}", quote(body), quote(title));
+  if let Err(e) = Command::new("osascript").args(["-e", &script]).spawn() {
+    warn!("Failed to run osascript: {}", e);
+  }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn show(_app: &AppHandle, _title: &str, _body: &str) {}
+
+/// Notify about something the shell did on its own, such as a scheduled
+/// start, whether or not the window has focus.
+pub fn send(app: &AppHandle, title: &str, body: &str) {
+  let enabled = app
+    .try_state::<SettingsStore>()
+    .map_or(true, |settings| settings.get().desktop_notifications != Some(false));
+  if enabled {
+    show(app, title, body);
+  }
+}
+
+/// Tell the user about `event` unless they're looking at the installer.
+#[tauri::command]
+pub fn notify_install_event(app: AppHandle, settings: State<'_, SettingsStore>, event: InstallEvent) {
+  if settings.get().desktop_notifications == Some(false) {
+    return;
+  }
+  let Some(window) = app.get_webview_window(MAIN_WINDOW) else {
+    return;
+  };
+  let visible = window.is_visible().unwrap_or(false);
+  if visible && window.is_focused().unwrap_or(false) {
+    return;
+  }
+  let (title, body) = event.text();
+  show(&app, &title, &body);
+  let attention = match event {
+    InstallEvent::Failed { .. } | InstallEvent::InputRequired { .. } => UserAttentionType::Critical,
+    _ => UserAttentionType::Informational,
+  };
+  if visible {
+    let _ = window.request_user_attention(Some(attention));
+  }
+}
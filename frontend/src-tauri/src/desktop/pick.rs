@@ -0,0 +1,226 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Native open dialogs for files the wizard needs a path to.
+//!
+//! `pick_file` shows the platform's open dialog filtered for SSH private
+//! keys, kubeconfigs or exported installer configs and checks what was
+//! picked before handing the path back: that it's a readable file of a
+//! sane size, that its contents are what the kind promises (a private key
+//! rather than the `.pub` half, a kubeconfig with contexts, a config file
+//! `import_config` will accept), and, for keys, that `ssh` won't refuse it
+//! for being readable by others.
+
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_dialog::DialogExt;
+
+use crate::config;
+use crate::i18n;
+use crate::windows::MAIN_WINDOW;
+
+/// Keys and kubeconfigs are a few KiB; anything this big is something else
+const MAX_BYTES: u64 = 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PickKind {
+  SshKey,
+  Kubeconfig,
+  InstallerConfig,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PickedFile {
+  pub path: String,
+  /// SSH key algorithm, from the PEM header or the OpenSSH key blob
+  pub key_type: Option<String>,
+  /// The key needs a passphrase
+  pub encrypted: Option<bool>,
+  /// Group or others can read the key, so ssh will ignore it
+  pub permissions_too_open: Option<bool>,
+  pub contexts: Vec<String>,
+  pub current_context: Option<String>,
+  /// Envelope version of an installer config
+  pub config_version: Option<u32>,
+  pub warnings: Vec<String>,
+}
+
+impl PickKind {
+  fn title(self) -> String {
+    i18n::t(match self {
+      PickKind::SshKey => "pick-ssh-key-title",
+      PickKind::Kubeconfig => "pick-kubeconfig-title",
+      PickKind::InstallerConfig => "pick-config-title",
+    })
+  }
+
+  /// Where the dialog opens, relative to $HOME
+  fn start_dir(self) -> &'static str {
+    match self {
+      PickKind::SshKey => ".ssh",
+      PickKind::Kubeconfig => ".kube",
+      PickKind::InstallerConfig => "",
+    }
+  }
+}
+
+/// Split a length-prefixed SSH wire-format string off the front of `data`.
+fn take_string<'a>(data: &mut &'a [u8]) -> Option<&'a [u8]> {
+  let len = u32::from_be_bytes(data.get(..4)?.try_into().ok()?) as usize;
+  let value = data.get(4..4 + len)?;
+  *data = &data[4 + len..];
+  Some(value)
+}
+
+/// The cipher name in an `openssh-key-v1` blob, and the first key's type.
+fn openssh_header(pem: &str) -> Option<(String, Option<String>)> {
+  let body: String = pem.lines().filter(|l| !l.starts_with("-----")).collect();
+  let blob = base64::engine::general_purpose::STANDARD.decode(body.trim()).ok()?;
+  let mut rest = blob.strip_prefix(b"openssh-key-v1\0")?;
+  let cipher = String::from_utf8_lossy(take_string(&mut rest)?).into_owned();
+  // kdfname and kdfoptions, then the key count before the first public key
+  take_string(&mut rest)?;
+  take_string(&mut rest)?;
+  let key_type = rest.get(4..).and_then(|mut keys| {
+    let mut public = take_string(&mut keys)?;
+    take_string(&mut public).map(|t| String::from_utf8_lossy(t).into_owned())
+  });
+  Some((cipher, key_type))
+}
+
+fn check_ssh_key(path: &Path, text: &str, picked: &mut PickedFile) -> Result<(), String> {
+  let first = text.lines().next().unwrap_or("").trim();
+  if first.starts_with("ssh-") || first.starts_with("ecdsa-") || first.starts_with("sk-") {
+    return Err(format!(
+      "{} is a public key; pick the private key next to it",
+      path.display()
+    ));
+  }
+  let label = first
+    .strip_prefix("-----BEGIN ")
+    .and_then(|l| l.strip_suffix("PRIVATE KEY-----"))
+    .ok_or_else(|| format!("{} is not a PEM or OpenSSH private key", path.display()))?
+    .trim();
+  match label {
+    "OPENSSH" => {
+      let (cipher, key_type) =
+        openssh_header(text).ok_or_else(|| format!("{} is not a valid OpenSSH private key", path.display()))?;
+      picked.encrypted = Some(cipher != "none");
+      picked.key_type = key_type;
+    }
+    "ENCRYPTED" => {
+      picked.encrypted = Some(true);
+    }
+    label => {
+      picked.encrypted = Some(text.contains("Proc-Type: 4,ENCRYPTED"));
+      picked.key_type = Some(if label.is_empty() { "pkcs8" } else { label }.to_ascii_lowercase());
+    }
+  }
+  if picked.encrypted == Some(true) {
+    picked
+      .warnings
+      .push("The key is protected by a passphrase; load it into ssh-agent before connecting".to_string());
+  }
+
+  #[cfg(unix)]
+  {
+    use std::os::unix::fs::PermissionsExt;
+    let mode = std::fs::metadata(path).map_err(|e| e.to_string())?.permissions().mode();
+    let too_open = mode & 0o077 != 0;
+    picked.permissions_too_open = Some(too_open);
+    if too_open {
+      picked.warnings.push(format!(
+        "Permissions {:o} are too open; ssh ignores the key until it is chmod 600",
+        mode & 0o777
+      ));
+    }
+  }
+  Ok(())
+}
+
+fn check_kubeconfig(path: &Path, text: &str, picked: &mut PickedFile) -> Result<(), String> {
+  let config: serde_yaml::Value =
+    serde_yaml::from_str(text).map_err(|e| format!("{} is not valid YAML: {}", path.display(), e))?;
+  let is_config = config["kind"].as_str() == Some("Config") || config["clusters"].is_sequence();
+  if !is_config {
+    return Err(format!("{} is not a kubeconfig", path.display()));
+  }
+  picked.contexts = config["contexts"]
+    .as_sequence()
+    .into_iter()
+    .flatten()
+    .filter_map(|c| c["name"].as_str().map(str::to_string))
+    .collect();
+  picked.current_context = config["current-context"]
+    .as_str()
+    .filter(|c| !c.is_empty())
+    .map(str::to_string);
+  if picked.contexts.is_empty() {
+    picked.warnings.push("The kubeconfig has no contexts".to_string());
+  }
+  Ok(())
+}
+
+fn check(kind: PickKind, path: &Path) -> Result<PickedFile, String> {
+  let mut picked = PickedFile {
+    path: path.display().to_string(),
+    ..Default::default()
+  };
+  if kind == PickKind::InstallerConfig {
+    let (document, migration) = config::read_file(path)?;
+    picked.config_version = Some(document.version);
+    picked.warnings = migration.notes;
+    return Ok(picked);
+  }
+
+  let metadata = std::fs::metadata(path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+  if !metadata.is_file() {
+    return Err(format!("{} is not a file", path.display()));
+  }
+  if metadata.len() > MAX_BYTES {
+    return Err(format!("{} is too large", path.display()));
+  }
+  let text = std::fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+  match kind {
+    PickKind::SshKey => check_ssh_key(path, &text, &mut picked)?,
+    PickKind::Kubeconfig => check_kubeconfig(path, &text, &mut picked)?,
+    PickKind::InstallerConfig => {}
+  }
+  Ok(picked)
+}
+
+/// Ask for a file of `kind` and check it. `None` when the dialog was
+/// cancelled; an error when the file isn't usable.
+#[tauri::command]
+pub async fn pick_file(app: AppHandle, kind: PickKind) -> Result<Option<PickedFile>, String> {
+  tauri::async_runtime::spawn_blocking(move || {
+    let mut dialog = app.dialog().file().set_title(kind.title());
+    // Private keys have no extension, so there's nothing to filter on
+    dialog = match kind {
+      PickKind::SshKey => dialog,
+      PickKind::Kubeconfig => {
+        dialog.add_filter(i18n::t("export-filter-kubeconfig"), &["yaml", "yml", "conf", "config"])
+      }
+      PickKind::InstallerConfig => dialog.add_filter(i18n::t("pick-filter-config"), &["json"]),
+    };
+    if let Some(window) = app.get_webview_window(MAIN_WINDOW) {
+      dialog = dialog.set_parent(&window);
+    }
+    if let Ok(home) = app.path().home_dir() {
+      let dir: PathBuf = home.join(kind.start_dir());
+      dialog = dialog.set_directory(if dir.is_dir() { dir } else { home });
+    }
+    let Some(chosen) = dialog.blocking_pick_file() else {
+      return Ok(None);
+    };
+    let path = chosen.into_path().map_err(|e| e.to_string())?;
+    check(kind, &path).map(Some)
+  })
+  .await
+  .map_err(|e| e.to_string())?
+}
@@ -0,0 +1,94 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Overall install progress on the launcher icon and the tray tooltip.
+//!
+//! The deploy page reports each finished playbook with
+//! `set_install_progress`, and the shell keeps the count and shows it as
+//! the Dock or taskbar icon's progress bar; on Linux that takes a desktop
+//! with the Unity launcher API (GNOME with Dash to Dock, KDE, ...). A
+//! failed step turns the bar to its error state where the platform has
+//! one, and a finished or abandoned run clears it.
+
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use tauri::window::{ProgressBarState, ProgressBarStatus};
+use tauri::{AppHandle, Manager, State};
+
+use crate::i18n;
+use crate::windows::MAIN_WINDOW;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunState {
+  #[default]
+  Idle,
+  Running,
+  Failed,
+  Complete,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct InstallProgress {
+  pub state: RunState,
+  pub completed: u32,
+  pub total: u32,
+  pub percent: u8,
+}
+
+#[derive(Default)]
+pub struct Progress(Mutex<InstallProgress>);
+
+fn show(app: &AppHandle, progress: &InstallProgress) {
+  let (status, value) = match progress.state {
+    RunState::Idle | RunState::Complete => (ProgressBarStatus::None, None),
+    RunState::Running => (ProgressBarStatus::Normal, Some(u64::from(progress.percent))),
+    RunState::Failed => (ProgressBarStatus::Error, Some(u64::from(progress.percent))),
+  };
+  if let Some(window) = app.get_webview_window(MAIN_WINDOW) {
+    let _ = window.set_progress_bar(ProgressBarState {
+      status: Some(status),
+      progress: value,
+    });
+  }
+  if let Some(tray) = app.tray_by_id("main") {
+    let tooltip = match progress.state {
+      RunState::Running => i18n::t_args("tray-tooltip-progress", &[("percent", &progress.percent.to_string())]),
+      RunState::Failed => i18n::t("tray-tooltip-failed"),
+      RunState::Idle | RunState::Complete => "Thinkube Installer".to_string(),
+    };
+    let _ = tray.set_tooltip(Some(tooltip));
+  }
+}
+
+/// Called by the deploy page as playbooks finish.
+#[tauri::command]
+pub fn set_install_progress(
+  app: AppHandle,
+  progress: State<'_, Progress>,
+  state: RunState,
+  completed: u32,
+  total: u32,
+) -> Result<InstallProgress, String> {
+  let mut current = progress.0.lock().map_err(|e| e.to_string())?;
+  let completed = completed.min(total);
+  *current = InstallProgress {
+    state,
+    completed,
+    total,
+    percent: if total == 0 {
+      0
+    } else {
+      (u64::from(completed) * 100 / u64::from(total)) as u8
+    },
+  };
+  show(&app, &current);
+  Ok(current.clone())
+}
+
+#[tauri::command]
+pub fn get_install_progress(progress: State<'_, Progress>) -> InstallProgress {
+  progress.0.lock().map(|p| p.clone()).unwrap_or_default()
+}
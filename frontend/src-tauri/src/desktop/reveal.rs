@@ -0,0 +1,38 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Showing directories in the platform file manager.
+
+use std::path::Path;
+use std::process::Command;
+use tauri::{AppHandle, Manager};
+
+/// Open `path` with the desktop's default handler (Files/Finder for a directory).
+pub fn open_path(path: &Path) -> Result<(), String> {
+  #[cfg(target_os = "macos")]
+  let opener = "open";
+  #[cfg(not(target_os = "macos"))]
+  let opener = "xdg-open";
+
+  Command::new(opener)
+    .arg(path)
+    .spawn()
+    .map(|_| ())
+    .map_err(|e| format!("Failed to open {} with {}: {}", path.display(), opener, e))
+}
+
+/// Open the app's log directory, creating it first so there's always
+/// something to show. Returns the path for the UI to display alongside.
+#[tauri::command]
+pub fn reveal_logs(app: AppHandle) -> Result<String, String> {
+  let dir = app
+    .path()
+    .app_log_dir()
+    .map_err(|e| format!("Cannot resolve app log directory: {}", e))?;
+  std::fs::create_dir_all(&dir)
+    .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+  open_path(&dir)?;
+  Ok(dir.display().to_string())
+}
@@ -0,0 +1,143 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! `open_terminal`: a shell prepared for poking at the cluster by hand.
+//!
+//! The shell starts in the run workspace with `KUBECONFIG`, the inventory
+//! (as both `THINKUBE_INVENTORY` and `ANSIBLE_INVENTORY`) and the thinkube
+//! variables exported. On Linux the terminal simply inherits them. Terminal.app
+//! is started by launchd and inherits nothing, so on macOS the same exports
+//! go into a `.command` script in the workspace that Terminal runs.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tauri::State;
+
+use crate::workspace::Workspace;
+
+/// Tried in order when `$TERMINAL` isn't set.
+#[cfg(target_os = "linux")]
+const LINUX_TERMINALS: &[&str] = &[
+  "x-terminal-emulator",
+  "gnome-terminal",
+  "konsole",
+  "xfce4-terminal",
+  "kitty",
+  "alacritty",
+  "xterm",
+];
+
+fn home() -> Option<PathBuf> {
+  std::env::var_os("HOME").map(PathBuf::from)
+}
+
+/// Explicit path if given, otherwise `default` relative to $HOME if it exists.
+fn existing_or_default(explicit: Option<String>, default: &str) -> Option<PathBuf> {
+  match explicit {
+    Some(path) => Some(PathBuf::from(path)),
+    None => home().map(|h| h.join(default)).filter(|p| p.exists()),
+  }
+}
+
+/// The variables the shell starts with; the embedded terminal's local
+/// sessions get them too.
+pub fn environment(
+  workspace: &Workspace,
+  kubeconfig: Option<String>,
+  inventory: Option<String>,
+) -> Vec<(String, String)> {
+  let mut vars = vec![
+    ("THINKUBE_WORKSPACE".to_string(), workspace.root().display().to_string()),
+    ("THINKUBE_RUN_ID".to_string(), workspace.run_id().to_string()),
+  ];
+  if let Some(path) = existing_or_default(kubeconfig, ".kube/config") {
+    vars.push(("KUBECONFIG".to_string(), path.display().to_string()));
+  }
+  if let Some(path) = existing_or_default(inventory, "thinkube/inventory/inventory.yaml") {
+    vars.push(("THINKUBE_INVENTORY".to_string(), path.display().to_string()));
+    vars.push(("ANSIBLE_INVENTORY".to_string(), path.display().to_string()));
+  }
+  for (compile_env, runtime_env) in [
+    (option_env!("THINKUBE_BUILD_BRANCH"),         "THINKUBE_BRANCH"),
+    (option_env!("THINKUBE_BUILD_REPO_URL"),       "THINKUBE_REPO_URL"),
+    (option_env!("THINKUBE_BUILD_METADATA_REPO"),  "THINKUBE_METADATA_REPO"),
+  ] {
+    let value = std::env::var(runtime_env)
+      .ok()
+      .or_else(|| compile_env.filter(|v| !v.is_empty()).map(str::to_string));
+    if let Some(value) = value {
+      vars.push((runtime_env.to_string(), value));
+    }
+  }
+  vars
+}
+
+#[cfg(target_os = "linux")]
+fn launch(dir: &Path, vars: &[(String, String)]) -> Result<(), String> {
+  let candidates: Vec<String> = std::env::var("TERMINAL")
+    .ok()
+    .filter(|t| !t.is_empty())
+    .into_iter()
+    .chain(LINUX_TERMINALS.iter().map(|t| t.to_string()))
+    .collect();
+
+  for terminal in &candidates {
+    let spawned = Command::new(terminal)
+      .current_dir(dir)
+      .envs(vars.iter().map(|(k, v)| (k, v)))
+      .spawn();
+    if spawned.is_ok() {
+      tracing::info!("Opened terminal {} in {}", terminal, dir.display());
+      return Ok(());
+    }
+  }
+  Err(format!("No terminal emulator found (tried {})", candidates.join(", ")))
+}
+
+#[cfg(target_os = "macos")]
+fn launch(dir: &Path, vars: &[(String, String)]) -> Result<(), String> {
+  let mut script = String::from("#!/bin/sh\n");
+  script.push_str(&format!("cd {}\n", shell_quote(&dir.display().to_string())));
+  for (key, value) in vars {
+    script.push_str(&format!("export {}={}\n", key, shell_quote(value)));
+  }
+  script.push_str("exec \"${SHELL:-/bin/zsh}\" -l\n");
+
+  let path = dir.join("thinkube-shell.command");
+  crate::workspace::write_private_file(&path, script.as_bytes())?;
+  {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o700))
+      .map_err(|e| format!("Failed to set permissions on {}: {}", path.display(), e))?;
+  }
+
+  Command::new("open")
+    .args(["-a", "Terminal"])
+    .arg(&path)
+    .spawn()
+    .map(|_| ())
+    .map_err(|e| format!("Failed to open Terminal: {}", e))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn launch(_dir: &Path, _vars: &[(String, String)]) -> Result<(), String> {
+  Err("Opening a terminal is not supported on this platform".to_string())
+}
+
+/// Single-quote `value` for a POSIX shell.
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn shell_quote(value: &str) -> String {
+  format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+#[tauri::command]
+pub fn open_terminal(
+  workspace: State<'_, Workspace>,
+  kubeconfig: Option<String>,
+  inventory: Option<String>,
+) -> Result<(), String> {
+  let vars = environment(&workspace, kubeconfig, inventory);
+  launch(workspace.root(), &vars)
+}
@@ -0,0 +1,77 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Dry runs: every check for real, no changes.
+//!
+//! Started with `--dry-run` (or `THINKUBE_DRY_RUN=1`), or switched with
+//! `set_dry_run`. Detection, preflight and planning commands run as usual.
+//! Commands that change a node or this machine validate their input, do the
+//! read-only part of their work and return what they would have returned,
+//! so the wizard builds the same report as for a real run. Playbooks run in
+//! the backend with `--check --diff`; it learns about the mode from its
+//! environment, so switching restarts it.
+//!
+//! `run_remote` and file transfers are plumbing that detection uses too and
+//! are not intercepted.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::{AppHandle, Manager, State};
+use tracing::info;
+
+use crate::install_guard::InstallGuard;
+
+pub const ENV: &str = "THINKUBE_DRY_RUN";
+
+pub struct DryRun(AtomicBool);
+
+impl Default for DryRun {
+  fn default() -> Self {
+    let enabled = std::env::args().any(|a| a == "--dry-run") || std::env::var(ENV).is_ok_and(|v| v == "1");
+    if enabled {
+      info!("Dry run: no changes will be made");
+    }
+    Self(AtomicBool::new(enabled))
+  }
+}
+
+impl DryRun {
+  pub fn enabled(&self) -> bool {
+    self.0.load(Ordering::SeqCst)
+  }
+}
+
+/// Whether changes should only be simulated.
+pub fn is_enabled(app: &AppHandle) -> bool {
+  app.try_state::<DryRun>().is_some_and(|d| d.enabled())
+}
+
+#[tauri::command]
+pub fn get_dry_run(dry_run: State<'_, DryRun>) -> bool {
+  dry_run.enabled()
+}
+
+/// Switch dry-run mode, restarting the backend so playbooks follow;
+/// refused while a step runs, as the restart would stop it.
+#[tauri::command]
+pub fn set_dry_run(
+  app: AppHandle,
+  dry_run: State<'_, DryRun>,
+  guard: State<'_, InstallGuard>,
+  enabled: bool,
+) -> Result<(), String> {
+  if dry_run.enabled() == enabled {
+    return Ok(());
+  }
+  if let Some(step) = guard.running_step() {
+    return Err(format!("Cannot switch dry-run mode while {} is running", step));
+  }
+  dry_run.0.store(enabled, Ordering::SeqCst);
+  info!(
+    "Dry run {}; restarting the backend",
+    if enabled { "enabled" } else { "disabled" }
+  );
+  crate::backend::restart(&app);
+  Ok(())
+}
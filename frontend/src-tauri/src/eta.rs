@@ -0,0 +1,300 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! How long the rest of the installation will take.
+//!
+//! Every successful step recorded with `record_step` also updates
+//! `step_timings.json` in the profile's data dir: a running average of how
+//! long that step took on this machine, which unlike the transcript is kept
+//! across installations. `estimate_install` takes the steps the deploy page
+//! is about to run and adds up their times. A step with history gets its
+//! average. One without gets a default, stretched when the benchmarks found
+//! a slow disk, plus its share of the download time from the download
+//! estimate; steps with history already include their downloads.
+//!
+//! As steps are recorded the estimate is redone for what is left, scaled
+//! by how far the steps of this run ran over or under their estimates, and
+//! goes out as an `install-eta` event.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tracing::{info, warn};
+
+use crate::migrations::Versioned;
+use crate::profiles;
+use crate::report::{StepRecord, Transcript};
+use crate::telemetry::Outcome;
+use crate::workspace::write_private_file;
+
+pub const EVENT: &str = "install-eta";
+
+/// For a step that has never run here
+const DEFAULT_STEP_MS: u64 = 180_000;
+/// Sequential write of a SATA SSD; the default step time assumes it
+const REFERENCE_WRITE_MIB_S: f64 = 200.0;
+const MAX_DISK_FACTOR: f64 = 4.0;
+/// Weight of the newest run in a step's average
+const SMOOTHING: f64 = 0.3;
+/// How far this run's pace may stretch or shrink the remaining estimate
+const PACE_RANGE: (f64, f64) = (0.5, 3.0);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepTiming {
+  pub step: String,
+  pub runs: u32,
+  pub average_ms: u64,
+  pub last_ms: u64,
+}
+
+pub struct StepTimings {
+  path: PathBuf,
+  timings: Mutex<HashMap<String, StepTiming>>,
+}
+
+impl StepTimings {
+  /// Load the timings left by earlier installations, if any.
+  pub fn load(app: &AppHandle) -> Result<Self, String> {
+    let path = profiles::data_dir(app)?.join("step_timings.json");
+
+    let timings: Vec<StepTiming> = match std::fs::read_to_string(&path) {
+      Ok(contents) => serde_json::from_str::<Versioned<_>>(&contents)
+        .map(|v| v.items)
+        .unwrap_or_else(|e| {
+          warn!("Ignoring invalid {}: {}", path.display(), e);
+          Vec::new()
+        }),
+      Err(_) => Vec::new(),
+    };
+
+    Ok(Self {
+      path,
+      timings: Mutex::new(timings.into_iter().map(|t| (t.step.clone(), t)).collect()),
+    })
+  }
+
+  pub fn get(&self, step: &str) -> Option<StepTiming> {
+    self.timings.lock().ok()?.get(step).cloned()
+  }
+
+  pub fn record(&self, step: &str, duration_ms: u64) -> Result<(), String> {
+    let mut timings = self.timings.lock().map_err(|e| e.to_string())?;
+    let timing = timings.entry(step.to_string()).or_insert_with(|| StepTiming {
+      step: step.to_string(),
+      runs: 0,
+      average_ms: duration_ms,
+      last_ms: duration_ms,
+    });
+    if timing.runs > 0 {
+      timing.average_ms =
+        (timing.average_ms as f64 * (1.0 - SMOOTHING) + duration_ms as f64 * SMOOTHING).round() as u64;
+    }
+    timing.runs += 1;
+    timing.last_ms = duration_ms;
+    self.save(&timings)
+  }
+
+  fn save(&self, timings: &HashMap<String, StepTiming>) -> Result<(), String> {
+    let mut items: Vec<&StepTiming> = timings.values().collect();
+    items.sort_by(|a, b| a.step.cmp(&b.step));
+    let json = serde_json::to_string_pretty(&Versioned::current(items)).map_err(|e| e.to_string())?;
+    let tmp = self.path.with_extension("json.tmp");
+    write_private_file(&tmp, json.as_bytes())?;
+    std::fs::rename(&tmp, &self.path).map_err(|e| format!("Failed to write {}: {}", self.path.display(), e))
+  }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PlannedStep {
+  /// As recorded with `record_step`, e.g. the playbook name
+  pub step: String,
+  pub title: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct EtaPlan {
+  pub steps: Vec<PlannedStep>,
+  /// `total_bytes` from `estimate_downloads`
+  pub download_bytes: Option<u64>,
+  /// `throughput_kib_s` from `estimate_downloads`
+  pub throughput_kib_s: Option<u64>,
+  /// The slowest `seq_write_mib_s` among the node benchmarks
+  pub disk_write_mib_s: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EstimateSource {
+  /// Averaged over earlier runs on this machine
+  History,
+  Default,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StepEstimate {
+  pub step: String,
+  pub title: String,
+  pub estimate_ms: u64,
+  pub source: EstimateSource,
+  /// Set once the step is in the transcript
+  pub actual_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct InstallEta {
+  pub steps: Vec<StepEstimate>,
+  pub completed: u32,
+  pub total: u32,
+  /// `None` without a download estimate or bandwidth sample
+  pub download_ms: Option<u64>,
+  pub disk_factor: f64,
+  /// Actual over estimated time of this run's finished steps
+  pub pace: f64,
+  pub elapsed_ms: u64,
+  pub remaining_ms: u64,
+  /// Milliseconds since the Unix epoch
+  pub finish_at: u64,
+}
+
+/// The plan being estimated, as `estimate_install` got it.
+struct Baseline {
+  plan: EtaPlan,
+  /// The history of each step then; the steps this run records shouldn't
+  /// move their own estimates
+  history: Vec<Option<StepTiming>>,
+  /// Steps recorded before this belong to an earlier run
+  since: u64,
+}
+
+#[derive(Default)]
+pub struct Eta(Mutex<Option<Baseline>>);
+
+fn now_ms() -> u64 {
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|d| d.as_millis() as u64)
+    .unwrap_or_default()
+}
+
+fn estimate(plan: &EtaPlan, history: &[Option<StepTiming>], recorded: &[StepRecord]) -> InstallEta {
+  let disk_factor = plan
+    .disk_write_mib_s
+    .filter(|speed| *speed > 0.0)
+    .map(|speed| (REFERENCE_WRITE_MIB_S / speed).clamp(1.0, MAX_DISK_FACTOR))
+    .unwrap_or(1.0);
+  let download_ms = match (plan.download_bytes, plan.throughput_kib_s) {
+    (Some(bytes), Some(rate)) if rate > 0 => Some(bytes * 1000 / (rate * 1024)),
+    _ => None,
+  };
+  let without_history = history.iter().filter(|h| h.is_none()).count() as u64;
+  let download_share = match without_history {
+    0 => 0,
+    n => download_ms.unwrap_or_default() / n,
+  };
+
+  let mut pace_actual = 0u64;
+  let mut pace_estimated = 0u64;
+  let steps: Vec<StepEstimate> = plan
+    .steps
+    .iter()
+    .zip(history)
+    .map(|(planned, history)| {
+      let (estimate_ms, source) = match history {
+        Some(timing) => (timing.average_ms, EstimateSource::History),
+        None => (
+          (DEFAULT_STEP_MS as f64 * disk_factor) as u64 + download_share,
+          EstimateSource::Default,
+        ),
+      };
+      // The latest record wins: a failed step that was retried counts as
+      // done once the retry is recorded
+      let record = recorded.iter().rev().find(|r| r.step == planned.step);
+      let actual_ms = record
+        .filter(|r| matches!(r.outcome, Outcome::Success | Outcome::Skipped))
+        .map(|r| r.duration_ms);
+      if let Some(record) = record.filter(|r| r.outcome == Outcome::Success) {
+        pace_actual += record.duration_ms;
+        pace_estimated += estimate_ms;
+      }
+      StepEstimate {
+        step: planned.step.clone(),
+        title: planned.title.clone().unwrap_or_else(|| planned.step.clone()),
+        estimate_ms,
+        source,
+        actual_ms,
+      }
+    })
+    .collect();
+
+  let pace = match pace_estimated {
+    0 => 1.0,
+    estimated => (pace_actual as f64 / estimated as f64).clamp(PACE_RANGE.0, PACE_RANGE.1),
+  };
+  let remaining_ms = (steps
+    .iter()
+    .filter(|s| s.actual_ms.is_none())
+    .map(|s| s.estimate_ms)
+    .sum::<u64>() as f64
+    * pace) as u64;
+  InstallEta {
+    completed: steps.iter().filter(|s| s.actual_ms.is_some()).count() as u32,
+    total: steps.len() as u32,
+    elapsed_ms: steps.iter().filter_map(|s| s.actual_ms).sum(),
+    finish_at: now_ms() + remaining_ms,
+    remaining_ms,
+    download_ms,
+    disk_factor,
+    pace,
+    steps,
+  }
+}
+
+fn current(app: &AppHandle) -> Option<InstallEta> {
+  let eta = app.state::<Eta>();
+  let current = eta.0.lock().ok()?;
+  let baseline = current.as_ref()?;
+  let mut recorded = app.state::<Transcript>().steps();
+  recorded.retain(|r| r.started_at >= baseline.since);
+  Some(estimate(&baseline.plan, &baseline.history, &recorded))
+}
+
+/// Add a finished step to the timings and send the refined estimate.
+pub fn step_recorded(app: &AppHandle, step: &str, duration_ms: u64, outcome: Outcome) {
+  if outcome == Outcome::Success {
+    if let Err(e) = app.state::<StepTimings>().record(step, duration_ms) {
+      warn!("Failed to update the step timings: {}", e);
+    }
+  }
+  if let Some(eta) = current(app) {
+    let _ = app.emit(EVENT, eta);
+  }
+}
+
+/// Estimate how long `plan` takes and keep refining it as its steps are
+/// recorded.
+#[tauri::command]
+pub fn estimate_install(app: AppHandle, eta: State<'_, Eta>, plan: EtaPlan) -> Result<InstallEta, String> {
+  if plan.steps.is_empty() {
+    return Err("No steps to estimate".to_string());
+  }
+  let timings = app.state::<StepTimings>();
+  let history: Vec<Option<StepTiming>> = plan.steps.iter().map(|s| timings.get(&s.step)).collect();
+  let result = estimate(&plan, &history, &[]);
+  info!("Estimated {} steps at {}s", result.total, result.remaining_ms / 1000);
+  *eta.0.lock().map_err(|e| e.to_string())? = Some(Baseline {
+    plan,
+    history,
+    since: now_ms(),
+  });
+  Ok(result)
+}
+
+#[tauri::command]
+pub fn get_install_eta(app: AppHandle) -> Option<InstallEta> {
+  current(&app)
+}
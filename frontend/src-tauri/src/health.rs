@@ -0,0 +1,324 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Watching the installed cluster, so the installer doubles as a status
+//! dashboard.
+//!
+//! `configure_health_probes` saves the service URLs and nodes to watch to
+//! `health.json` in the profile's config dir and starts probing them every
+//! `interval_secs` on a thread of its own, again at every launch until it's
+//! turned off. A service is up when it answers HTTPS with anything short of
+//! a server error, trusting the public roots plus the configured CA; a
+//! node is up when its SSH port takes a connection, which needs no
+//! credentials. Each round is kept for `get_service_health` and emitted as
+//! a `service-health` event, and anything that goes down between two
+//! rounds raises a desktop notification.
+
+use serde::{Deserialize, Serialize};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tracing::{info, warn};
+
+use crate::desktop::notify;
+use crate::migrations::STATE_VERSION;
+use crate::remote::ssh::SshTarget;
+use crate::workspace::write_private_file;
+use crate::{certs, i18n, profiles, smoke};
+
+pub const EVENT: &str = "service-health";
+
+const DEFAULT_INTERVAL_SECS: u64 = 60;
+const MIN_INTERVAL_SECS: u64 = 15;
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceProbe {
+  pub name: String,
+  /// An `https://` URL
+  pub url: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthConfig {
+  /// State format version, see `migrations`
+  #[serde(default)]
+  pub version: u32,
+  pub services: Vec<ServiceProbe>,
+  #[serde(default)]
+  pub nodes: Vec<SshTarget>,
+  pub interval_secs: Option<u64>,
+  /// PEM of the CA the services' certificates chain to, if not a public one
+  pub ca_pem: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProbeKind {
+  Service,
+  Node,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProbeResult {
+  pub kind: ProbeKind,
+  pub name: String,
+  pub up: bool,
+  pub message: String,
+  pub latency_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthSnapshot {
+  pub checked_at: u64,
+  pub results: Vec<ProbeResult>,
+  /// Everything is up
+  pub healthy: bool,
+}
+
+#[derive(Default)]
+pub struct HealthMonitor {
+  config: Mutex<Option<HealthConfig>>,
+  latest: Mutex<Option<HealthSnapshot>>,
+  /// Bumped whenever the config changes, so the thread of an older one stops
+  generation: AtomicU64,
+}
+
+impl HealthMonitor {
+  fn replace(&self, config: Option<HealthConfig>) -> u64 {
+    *self.config.lock().unwrap_or_else(|e| e.into_inner()) = config;
+    if let Ok(mut latest) = self.latest.lock() {
+      *latest = None;
+    }
+    self.generation.fetch_add(1, Ordering::SeqCst) + 1
+  }
+
+  fn is_current(&self, generation: u64) -> bool {
+    self.generation.load(Ordering::SeqCst) == generation
+  }
+}
+
+fn state_path(app: &AppHandle) -> Result<PathBuf, String> {
+  profiles::config_dir(app).map(|dir| dir.join("health.json"))
+}
+
+fn save(app: &AppHandle, config: &HealthConfig) -> Result<(), String> {
+  let path = state_path(app)?;
+  let json = serde_json::to_string_pretty(config).map_err(|e| e.to_string())?;
+  let tmp = path.with_extension("json.tmp");
+  write_private_file(&tmp, json.as_bytes())?;
+  std::fs::rename(&tmp, &path).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+fn remove_state(app: &AppHandle) -> Result<(), String> {
+  let path = state_path(app)?;
+  match std::fs::remove_file(&path) {
+    Ok(()) => Ok(()),
+    Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+    Err(e) => Err(format!("Failed to remove {}: {}", path.display(), e)),
+  }
+}
+
+fn load(app: &AppHandle) -> Option<HealthConfig> {
+  let path = state_path(app).ok()?;
+  let contents = std::fs::read_to_string(&path).ok()?;
+  serde_json::from_str(&contents)
+    .map_err(|e| warn!("Ignoring invalid {}: {}", path.display(), e))
+    .ok()
+}
+
+fn validate(config: &HealthConfig) -> Result<(), String> {
+  if config.services.is_empty() && config.nodes.is_empty() {
+    return Err("Nothing to watch".to_string());
+  }
+  for service in &config.services {
+    if !service.url.starts_with("https://") {
+      return Err(format!("{} is not an https:// URL", service.url));
+    }
+  }
+  for node in &config.nodes {
+    node.validate()?;
+  }
+  Ok(())
+}
+
+fn probe_service(agent: &ureq::Agent, service: &ServiceProbe) -> ProbeResult {
+  let started = Instant::now();
+  let (up, message) = match agent.get(&service.url).call() {
+    Ok(response) => (true, format!("HTTP {}", response.status())),
+    Err(ureq::Error::Status(code, _)) => (code < 500, format!("HTTP {}", code)),
+    Err(ureq::Error::Transport(transport)) => (false, transport.to_string()),
+  };
+  ProbeResult {
+    kind: ProbeKind::Service,
+    name: service.name.clone(),
+    up,
+    message,
+    latency_ms: started.elapsed().as_millis() as u64,
+  }
+}
+
+fn probe_node(node: &SshTarget) -> ProbeResult {
+  let started = Instant::now();
+  let port = node.port.unwrap_or(22);
+  let connected = (node.host.as_str(), port)
+    .to_socket_addrs()
+    .map_err(|e| e.to_string())
+    .and_then(|mut addrs| addrs.next().ok_or_else(|| "no address".to_string()))
+    .and_then(|addr| TcpStream::connect_timeout(&addr, TIMEOUT).map_err(|e| e.to_string()));
+  let (up, message) = match connected {
+    Ok(_) => (true, format!("SSH port {} open", port)),
+    Err(e) => (false, format!("Unreachable: {}", e)),
+  };
+  ProbeResult {
+    kind: ProbeKind::Node,
+    name: node.host.clone(),
+    up,
+    message,
+    latency_ms: started.elapsed().as_millis() as u64,
+  }
+}
+
+fn probe(config: &HealthConfig) -> Result<HealthSnapshot, String> {
+  let agent = smoke::agent(certs::roots(config.ca_pem.as_deref())?)?;
+  let results: Vec<ProbeResult> = std::thread::scope(|scope| {
+    let services: Vec<_> = config
+      .services
+      .iter()
+      .map(|service| scope.spawn(|| probe_service(&agent, service)))
+      .collect();
+    let nodes: Vec<_> = config
+      .nodes
+      .iter()
+      .map(|node| scope.spawn(|| probe_node(node)))
+      .collect();
+    services
+      .into_iter()
+      .chain(nodes)
+      .filter_map(|h| h.join().ok())
+      .collect()
+  });
+  Ok(HealthSnapshot {
+    checked_at: SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .map(|d| d.as_secs())
+      .unwrap_or_default(),
+    healthy: results.iter().all(|r| r.up),
+    results,
+  })
+}
+
+/// Run a round of probes and publish it, if the config is still current.
+fn refresh(app: &AppHandle, generation: u64, config: &HealthConfig) -> Result<HealthSnapshot, String> {
+  let snapshot = probe(config)?;
+  let monitor = app.state::<HealthMonitor>();
+  if !monitor.is_current(generation) {
+    return Ok(snapshot);
+  }
+  let previous = monitor
+    .latest
+    .lock()
+    .map_err(|e| e.to_string())?
+    .replace(snapshot.clone());
+  let was_up = |name: &str| {
+    previous
+      .as_ref()
+      .is_some_and(|p| p.results.iter().any(|r| r.name == name && r.up))
+  };
+  let down: Vec<&str> = snapshot
+    .results
+    .iter()
+    .filter(|r| !r.up && was_up(&r.name))
+    .map(|r| r.name.as_str())
+    .collect();
+  if !down.is_empty() {
+    warn!("Went down: {}", down.join(", "));
+    notify::send(
+      app,
+      &i18n::t("health-down-title"),
+      &i18n::t_args("health-down-body", &[("names", &down.join(", "))]),
+    );
+  }
+  let _ = app.emit(EVENT, snapshot.clone());
+  Ok(snapshot)
+}
+
+fn start(app: &AppHandle, config: HealthConfig) {
+  info!(
+    "Watching {} services and {} nodes",
+    config.services.len(),
+    config.nodes.len()
+  );
+  let interval = Duration::from_secs(
+    config
+      .interval_secs
+      .unwrap_or(DEFAULT_INTERVAL_SECS)
+      .max(MIN_INTERVAL_SECS),
+  );
+  let generation = app.state::<HealthMonitor>().replace(Some(config.clone()));
+  let app = app.clone();
+  std::thread::spawn(move || {
+    while app.state::<HealthMonitor>().is_current(generation) {
+      if let Err(e) = refresh(&app, generation, &config) {
+        warn!("Health probes failed: {}", e);
+      }
+      std::thread::sleep(interval);
+    }
+  });
+}
+
+/// Resume watching what was configured before the installer was closed.
+pub fn setup(app: &AppHandle) {
+  if let Some(config) = load(app).filter(|config| validate(config).is_ok()) {
+    start(app, config);
+  }
+}
+
+/// Watch `config`'s services and nodes from now on, replacing whatever was
+/// watched before; `None` stops watching.
+#[tauri::command]
+pub fn configure_health_probes(
+  app: AppHandle,
+  monitor: State<'_, HealthMonitor>,
+  config: Option<HealthConfig>,
+) -> Result<(), String> {
+  let Some(mut config) = config else {
+    monitor.replace(None);
+    info!("Health probes stopped");
+    return remove_state(&app);
+  };
+  validate(&config)?;
+  config.version = STATE_VERSION;
+  if let Some(pem) = &config.ca_pem {
+    certs::roots(Some(pem))?;
+  }
+  save(&app, &config)?;
+  start(&app, config);
+  Ok(())
+}
+
+#[tauri::command]
+pub fn get_health_config(monitor: State<'_, HealthMonitor>) -> Option<HealthConfig> {
+  monitor.config.lock().ok().and_then(|c| c.clone())
+}
+
+/// The latest round of probes; with `refresh`, a new one run right away.
+#[tauri::command]
+pub async fn get_service_health(app: AppHandle, refresh: Option<bool>) -> Result<Option<HealthSnapshot>, String> {
+  let monitor = app.state::<HealthMonitor>();
+  if !refresh.unwrap_or(false) {
+    return Ok(monitor.latest.lock().ok().and_then(|l| l.clone()));
+  }
+  let Some(config) = monitor.config.lock().ok().and_then(|c| c.clone()) else {
+    return Ok(None);
+  };
+  let generation = monitor.generation.load(Ordering::SeqCst);
+  tauri::async_runtime::spawn_blocking(move || self::refresh(&app, generation, &config).map(Some))
+    .await
+    .map_err(|e| e.to_string())?
+}
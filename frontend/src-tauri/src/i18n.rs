@@ -0,0 +1,176 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Localization of native UI strings produced by the Rust shell.
+//!
+//! Messages are Fluent resources embedded from `locales/*.ftl`. The active
+//! locale is global so code without an `AppHandle` (panic hook, background
+//! threads) can translate too; the wizard switches it with `set_locale`
+//! whenever the user picks a language.
+
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::{FluentArgs, FluentResource, FluentValue};
+use std::sync::{OnceLock, RwLock};
+use tauri::State;
+use tracing::error;
+use unic_langid::LanguageIdentifier;
+
+use crate::settings::SettingsStore;
+
+/// Embedded catalogs; the first entry is the fallback for missing messages.
+const CATALOGS: &[(&str, &str)] = &[
+  ("en-US", include_str!("../locales/en-US.ftl")),
+  ("es-ES", include_str!("../locales/es-ES.ftl")),
+];
+
+struct Catalog {
+  bundles: Vec<(&'static str, FluentBundle<FluentResource>)>,
+  current: RwLock<usize>,
+}
+
+static CATALOG: OnceLock<Catalog> = OnceLock::new();
+
+fn catalog() -> &'static Catalog {
+  CATALOG.get_or_init(|| {
+    let bundles = CATALOGS
+      .iter()
+      .map(|(tag, source)| {
+        let langid: LanguageIdentifier = tag.parse().expect("invalid built-in locale tag");
+        let mut bundle = FluentBundle::new_concurrent(vec![langid]);
+        // Unicode isolation marks show up as boxes in native dialogs
+        bundle.set_use_isolating(false);
+        let resource = FluentResource::try_new(source.to_string())
+          .unwrap_or_else(|(resource, errors)| {
+            error!("Errors in {} catalog: {:?}", tag, errors);
+            resource
+          });
+        if let Err(errors) = bundle.add_resource(resource) {
+          error!("Errors loading {} catalog: {:?}", tag, errors);
+        }
+        (*tag, bundle)
+      })
+      .collect();
+
+    Catalog {
+      bundles,
+      current: RwLock::new(0),
+    }
+  })
+}
+
+/// Map an arbitrary locale tag (`es`, `es_AR.UTF-8`, `en-GB`) to the closest
+/// embedded catalog, matching the exact tag first and then the language.
+fn negotiate(requested: &str) -> Option<usize> {
+  let normalized = requested
+    .split('.')
+    .next()
+    .unwrap_or(requested)
+    .replace('_', "-");
+  let langid: LanguageIdentifier = normalized.parse().ok()?;
+
+  let bundles = &catalog().bundles;
+  bundles
+    .iter()
+    .position(|(tag, _)| tag.eq_ignore_ascii_case(&normalized))
+    .or_else(|| {
+      bundles.iter().position(|(tag, _)| {
+        tag.parse::<LanguageIdentifier>()
+          .map(|candidate| candidate.language == langid.language)
+          .unwrap_or(false)
+      })
+    })
+}
+
+/// Best guess at the desktop session's language.
+pub fn system_locale() -> Option<String> {
+  for var in ["LC_ALL", "LC_MESSAGES", "LANG"] {
+    if let Ok(value) = std::env::var(var) {
+      if !value.is_empty() && value != "C" && value != "POSIX" {
+        return Some(value);
+      }
+    }
+  }
+
+  #[cfg(target_os = "macos")]
+  {
+    // Apps launched from Finder don't inherit LANG
+    if let Ok(out) = std::process::Command::new("defaults")
+      .args(["read", "-g", "AppleLocale"])
+      .output()
+    {
+      let value = String::from_utf8_lossy(&out.stdout).trim().to_string();
+      if out.status.success() && !value.is_empty() {
+        return Some(value);
+      }
+    }
+  }
+
+  None
+}
+
+/// Select the initial locale: the saved choice, else the system language.
+pub fn init(saved: Option<&str>) {
+  let index = saved
+    .and_then(negotiate)
+    .or_else(|| system_locale().as_deref().and_then(negotiate))
+    .unwrap_or(0);
+  if let Ok(mut current) = catalog().current.write() {
+    *current = index;
+  }
+}
+
+/// Tag of the active catalog.
+pub fn current_locale() -> String {
+  let catalog = catalog();
+  let index = catalog.current.read().map(|i| *i).unwrap_or(0);
+  catalog.bundles[index].0.to_string()
+}
+
+fn format(index: usize, id: &str, args: Option<&FluentArgs>) -> Option<String> {
+  let bundle = &catalog().bundles[index].1;
+  let pattern = bundle.get_message(id)?.value()?;
+  let mut errors = Vec::new();
+  let text = bundle.format_pattern(pattern, args, &mut errors);
+  if !errors.is_empty() {
+    error!("Errors formatting message {}: {:?}", id, errors);
+  }
+  Some(text.into_owned())
+}
+
+/// Translate `id` with the given named arguments. Falls back to the English
+/// catalog, and to the id itself if the message doesn't exist at all.
+pub fn t_args(id: &str, args: &[(&str, &str)]) -> String {
+  let mut fluent_args = FluentArgs::new();
+  for (name, value) in args {
+    fluent_args.set(*name, FluentValue::from(*value));
+  }
+  let index = catalog().current.read().map(|i| *i).unwrap_or(0);
+  format(index, id, Some(&fluent_args))
+    .or_else(|| format(0, id, Some(&fluent_args)))
+    .unwrap_or_else(|| id.to_string())
+}
+
+/// Translate `id` without arguments.
+pub fn t(id: &str) -> String {
+  t_args(id, &[])
+}
+
+#[tauri::command]
+pub fn get_locale() -> String {
+  current_locale()
+}
+
+/// Switch the shell's native strings to `locale` and remember the choice.
+/// Returns the catalog actually selected (e.g. `es-ES` for `es-MX`).
+#[tauri::command]
+pub fn set_locale(settings: State<'_, SettingsStore>, locale: String) -> Result<String, String> {
+  let index = negotiate(&locale).ok_or_else(|| format!("Unsupported locale: {}", locale))?;
+  if let Ok(mut current) = catalog().current.write() {
+    *current = index;
+  }
+  let resolved = current_locale();
+  settings.update(|s| s.locale = Some(resolved.clone()))?;
+  Ok(resolved)
+}
@@ -0,0 +1,111 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Confirming a window close while a playbook runs.
+//!
+//! Closing the main window stops the backend, and with it any playbook
+//! halfway through a node. The playbook runner reports when it starts and
+//! finishes with `set_install_running`; while one runs, closing the window
+//! asks first, with hiding it to the tray as the default answer and the
+//! tray's "Show installer" bringing it back. While an installation is
+//! scheduled, closing the window hides it without asking, as the app has to
+//! keep running for the start to happen.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State, WebviewWindow};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind, MessageDialogResult};
+use tracing::{info, warn};
+
+use crate::i18n;
+use crate::schedule::Scheduler;
+
+#[derive(Default)]
+pub struct InstallGuard {
+  /// Name of the running playbook step
+  step: Mutex<Option<String>>,
+  /// The user confirmed quitting; let the next close through
+  confirmed: AtomicBool,
+}
+
+impl InstallGuard {
+  pub fn running_step(&self) -> Option<String> {
+    self.step.lock().ok().and_then(|s| s.clone())
+  }
+
+  /// Stop treating the running step as running, e.g. once it's cancelled.
+  pub fn take_running_step(&self) -> Option<String> {
+    self.step.lock().ok().and_then(|mut s| s.take())
+  }
+}
+
+/// Called by the playbook runner when a step starts (`running`) and ends.
+#[tauri::command]
+pub fn set_install_running(guard: State<'_, InstallGuard>, running: bool, step: String) -> Result<(), String> {
+  let mut current = guard.step.lock().map_err(|e| e.to_string())?;
+  *current = running.then_some(step);
+  Ok(())
+}
+
+/// Whether the main window may close now. When a step is running this
+/// asks the user instead and returns false; choosing to quit closes the
+/// window again, which then goes through.
+pub fn allow_close(app: &AppHandle, window: &WebviewWindow) -> bool {
+  let Some(guard) = app.try_state::<InstallGuard>() else {
+    return true;
+  };
+  if guard.confirmed.load(Ordering::SeqCst) {
+    return true;
+  }
+  if app.try_state::<Scheduler>().is_some_and(|s| s.pending().is_some()) {
+    info!("Hiding the installer to the tray until the scheduled start");
+    if let Err(e) = window.hide() {
+      warn!("Failed to hide the main window: {}", e);
+    }
+    return false;
+  }
+  let Some(step) = guard.running_step() else {
+    return true;
+  };
+
+  let minimize = i18n::t("close-guard-minimize");
+  let quit = i18n::t("close-guard-quit");
+  let window = window.clone();
+  let app = app.clone();
+  app
+    .dialog()
+    .message(i18n::t_args("close-guard-body", &[("step", &step)]))
+    .title(i18n::t("close-guard-title"))
+    .kind(MessageDialogKind::Warning)
+    .parent(&window)
+    .buttons(MessageDialogButtons::YesNoCancelCustom(
+      minimize.clone(),
+      quit.clone(),
+      i18n::t("close-guard-keep-open"),
+    ))
+    .show_with_result(move |result| match result {
+      MessageDialogResult::Yes => hide(&window),
+      MessageDialogResult::Custom(label) if label == minimize => hide(&window),
+      MessageDialogResult::No => quit_anyway(&app, &window),
+      MessageDialogResult::Custom(label) if label == quit => quit_anyway(&app, &window),
+      _ => {}
+    });
+  false
+}
+
+fn hide(window: &WebviewWindow) {
+  info!("Hiding the installer to the tray while a playbook runs");
+  if let Err(e) = window.hide() {
+    warn!("Failed to hide the main window: {}", e);
+  }
+}
+
+fn quit_anyway(app: &AppHandle, window: &WebviewWindow) {
+  warn!("Closing the installer while a playbook runs");
+  if let Some(guard) = app.try_state::<InstallGuard>() {
+    guard.confirmed.store(true, Ordering::SeqCst);
+  }
+  let _ = window.close();
+}
@@ -0,0 +1,359 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Journal of changes made to the nodes, so a failed install can be undone.
+//!
+//! Every step that leaves something behind on a node (a snap, an LXD
+//! instance, a netplan, hostname, firewall, SELinux/AppArmor, swap or
+//! sysctl change, a service or timer stopped) records how to reverse it.
+//! Steps run by the shell record themselves; steps run by the backend's
+//! playbooks are recorded by the wizard through `record_action`. The
+//! journal lives in `journal.json` in the profile's data dir rather than
+//! the run workspace so it survives a reboot and a resumed run, and is
+//! cleared when a run finishes successfully.
+//!
+//! `rollback_install` undoes the entries newest first. It keeps going when
+//! one fails, and only the entries that were undone leave the journal, so a
+//! rollback can be retried after fixing whatever got in the way.
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager, State};
+use tracing::{error, info, warn};
+
+use crate::dry_run;
+use crate::migrations::Versioned;
+use crate::profiles;
+use crate::remote::conflicts;
+use crate::remote::firewall;
+use crate::remote::hostname;
+use crate::remote::mac;
+use crate::remote::netplan;
+use crate::remote::ssh::{self, shell_quote, SshPool, SshTarget};
+use crate::remote::swap;
+use crate::remote::tuning;
+use crate::validation;
+use crate::workspace::{write_private_file, Workspace};
+
+const UNDO_TIMEOUT: Duration = Duration::from_secs(120);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Action {
+  /// A snap installed on the node
+  Snap { target: SshTarget, name: String },
+  /// An LXD container or VM created on the node
+  LxdInstance { target: SshTarget, name: String },
+  /// A netplan file replaced, with the original saved at `backup`
+  Netplan {
+    target: SshTarget,
+    path: String,
+    backup: String,
+  },
+  /// The node renamed from `previous`
+  Hostname { target: SshTarget, previous: String },
+  /// A firewall rule letting another node in
+  FirewallRule { target: SshTarget, rule: firewall::Rule },
+  /// An SELinux or AppArmor setting changed
+  MacAdjustment {
+    target: SshTarget,
+    adjustment: mac::Adjustment,
+  },
+  /// Swap turned off: zram `units` masked, `/etc/fstab` entries commented out
+  Swap {
+    target: SshTarget,
+    units: Vec<String>,
+    fstab: bool,
+  },
+  /// Services or timers stopped, a conflicting runtime's or the APT timers;
+  /// `enabled` were also disabled at boot
+  ServicesStopped {
+    target: SshTarget,
+    units: Vec<String>,
+    enabled: Vec<String>,
+  },
+  /// sysctls raised for a workload profile, with the values they had
+  KernelTuning {
+    target: SshTarget,
+    previous: Vec<(String, u64)>,
+  },
+}
+
+fn check_units<'a>(units: impl IntoIterator<Item = &'a String>) -> Result<(), String> {
+  match units.into_iter().find(|u| !validation::is_valid_unit(u)) {
+    Some(unit) => Err(format!("Invalid unit: {}", unit)),
+    None => Ok(()),
+  }
+}
+
+impl Action {
+  fn target(&self) -> &SshTarget {
+    match self {
+      Action::Snap { target, .. }
+      | Action::LxdInstance { target, .. }
+      | Action::Netplan { target, .. }
+      | Action::Hostname { target, .. }
+      | Action::FirewallRule { target, .. }
+      | Action::MacAdjustment { target, .. }
+      | Action::Swap { target, .. }
+      | Action::ServicesStopped { target, .. }
+      | Action::KernelTuning { target, .. } => target,
+    }
+  }
+
+  pub fn describe(&self) -> String {
+    match self {
+      Action::Snap { target, name } => format!("Remove snap {} from {}", name, target.host),
+      Action::LxdInstance { target, name } => format!("Delete LXD instance {} on {}", name, target.host),
+      Action::Netplan { target, path, .. } => format!("Restore {} on {}", path, target.host),
+      Action::Hostname { target, previous } => format!("Rename {} back to {}", target.host, previous),
+      Action::FirewallRule { target, rule } => format!(
+        "Remove the firewall rule for {} on port {}/{} from {}",
+        rule.peer,
+        rule.port,
+        rule.protocol.name(),
+        target.host
+      ),
+      Action::MacAdjustment { target, adjustment } => {
+        format!("Revert \"{}\" on {}", adjustment.describe(), target.host)
+      }
+      Action::Swap { target, .. } => format!("Turn swap back on on {}", target.host),
+      Action::ServicesStopped { target, units, .. } => format!("Start {} again on {}", units.join(", "), target.host),
+      Action::KernelTuning { target, .. } => format!("Restore the kernel tuning on {}", target.host),
+    }
+  }
+
+  fn validate(&self) -> Result<(), String> {
+    self.target().validate()?;
+    match self {
+      Action::Snap { name, .. } | Action::LxdInstance { name, .. } if !validation::is_valid_label(name) => {
+        Err(format!("Invalid name: {}", name))
+      }
+      Action::Netplan { path, backup, .. }
+        if !netplan::is_netplan_path(path) || backup.strip_suffix(netplan::BACKUP_SUFFIX) != Some(path.as_str()) =>
+      {
+        Err(format!("Invalid netplan path: {}", path))
+      }
+      Action::Hostname { previous, .. } if !validation::is_valid_label(previous) => {
+        Err(format!("Invalid hostname: {}", previous))
+      }
+      Action::FirewallRule { rule, .. } => rule.validate(),
+      Action::Swap { units, .. } => check_units(units),
+      Action::ServicesStopped { units, enabled, .. } => check_units(units.iter().chain(enabled)),
+      Action::KernelTuning { previous, .. } => previous
+        .iter()
+        .find(|(key, _)| !tuning::TUNABLES.contains(&key.as_str()))
+        .map_or(Ok(()), |(key, _)| Err(format!("Invalid sysctl: {}", key))),
+      _ => Ok(()),
+    }
+  }
+
+  /// Shell script that reverses the action. Each one succeeds when there is
+  /// nothing left to undo, so running it twice is harmless.
+  fn undo_script(&self) -> String {
+    match self {
+      Action::Snap { name, .. } => format!(
+        "set -e\nif snap list {name} >/dev/null 2>&1; then sudo -n snap remove --purge {name}; fi\n",
+        name = shell_quote(name)
+      ),
+      Action::LxdInstance { name, .. } => format!(
+        "set -e\nif lxc info {name} >/dev/null 2>&1; then lxc delete --force {name}; fi\n",
+        name = shell_quote(name)
+      ),
+      Action::Netplan { path, backup, .. } => netplan::restore_script(path, backup),
+      Action::Hostname { previous, .. } => hostname::rename_script(previous),
+      Action::FirewallRule { rule, .. } => firewall::rules_script(std::slice::from_ref(rule), false),
+      Action::MacAdjustment { adjustment, .. } => adjustment.undo_script().to_string(),
+      Action::Swap { units, fstab, .. } => swap::restore_script(units, *fstab),
+      Action::ServicesStopped { units, enabled, .. } => conflicts::restart_script(units, enabled),
+      Action::KernelTuning { previous, .. } => tuning::restore_script(previous),
+    }
+  }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+  pub id: u64,
+  pub recorded_at: u64,
+  pub run_id: String,
+  pub action: Action,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RollbackResult {
+  pub id: u64,
+  pub description: String,
+  pub success: bool,
+  pub error: Option<String>,
+}
+
+pub struct Journal {
+  path: PathBuf,
+  entries: Mutex<Vec<JournalEntry>>,
+}
+
+impl Journal {
+  /// Load the journal left by earlier runs, if any.
+  pub fn load(app: &AppHandle) -> Result<Self, String> {
+    let path = profiles::data_dir(app)?.join("journal.json");
+
+    let entries = match std::fs::read_to_string(&path) {
+      Ok(contents) => serde_json::from_str::<Versioned<_>>(&contents).map(|v| v.items).unwrap_or_else(|e| {
+        warn!("Ignoring invalid {}: {}", path.display(), e);
+        Vec::new()
+      }),
+      Err(_) => Vec::new(),
+    };
+
+    Ok(Self {
+      path,
+      entries: Mutex::new(entries),
+    })
+  }
+
+  pub fn entries(&self) -> Vec<JournalEntry> {
+    self.entries.lock().map(|e| e.clone()).unwrap_or_default()
+  }
+
+  /// Append an action and write the journal to disk before returning, so
+  /// nothing is lost if the installer dies right after.
+  pub fn record(&self, run_id: &str, action: Action) -> Result<u64, String> {
+    action.validate()?;
+    let mut entries = self.entries.lock().map_err(|e| e.to_string())?;
+    let id = entries.iter().map(|e| e.id).max().unwrap_or(0) + 1;
+    info!("Journal: {}", action.describe());
+    entries.push(JournalEntry {
+      id,
+      recorded_at: SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default(),
+      run_id: run_id.to_string(),
+      action,
+    });
+    self.save(&entries)?;
+    Ok(id)
+  }
+
+  pub fn clear(&self) -> Result<(), String> {
+    let mut entries = self.entries.lock().map_err(|e| e.to_string())?;
+    entries.clear();
+    self.save(&entries)
+  }
+
+  fn remove(&self, id: u64) -> Result<(), String> {
+    let mut entries = self.entries.lock().map_err(|e| e.to_string())?;
+    entries.retain(|e| e.id != id);
+    self.save(&entries)
+  }
+
+  fn save(&self, entries: &[JournalEntry]) -> Result<(), String> {
+    if entries.is_empty() {
+      return match std::fs::remove_file(&self.path) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(format!("Failed to remove {}: {}", self.path.display(), e)),
+      };
+    }
+    let json = serde_json::to_string_pretty(&Versioned::current(entries)).map_err(|e| e.to_string())?;
+    let tmp = self.path.with_extension("json.tmp");
+    write_private_file(&tmp, json.as_bytes())?;
+    std::fs::rename(&tmp, &self.path).map_err(|e| format!("Failed to write {}: {}", self.path.display(), e))
+  }
+}
+
+/// Record an action from the current run, logging instead of failing the
+/// step that made it.
+pub fn record(app: &AppHandle, action: Action) {
+  if dry_run::is_enabled(app) {
+    return;
+  }
+  let (Some(journal), Some(workspace)) = (app.try_state::<Journal>(), app.try_state::<Workspace>()) else {
+    return;
+  };
+  if let Err(e) = journal.record(workspace.run_id(), action) {
+    error!("Failed to update the install journal: {}", e);
+  }
+}
+
+fn undo(workspace: &Workspace, pool: &SshPool, action: &Action) -> Result<(), String> {
+  let target = action.target();
+  let output = ssh::run_script(workspace, pool, target, &action.undo_script(), UNDO_TIMEOUT)?;
+  if output.status != Some(0) {
+    let detail = output
+      .stderr
+      .trim()
+      .lines()
+      .last()
+      .unwrap_or("command failed")
+      .to_string();
+    return Err(detail);
+  }
+  // A restored network or hostname may not keep the old connection usable
+  if matches!(action, Action::Netplan { .. } | Action::Hostname { .. }) {
+    pool.close(target);
+  }
+  Ok(())
+}
+
+/// Record a change the backend made so it can be rolled back.
+#[tauri::command]
+pub fn record_action(
+  app: AppHandle,
+  journal: State<'_, Journal>,
+  workspace: State<'_, Workspace>,
+  action: Action,
+) -> Result<u64, String> {
+  if dry_run::is_enabled(&app) {
+    action.validate()?;
+    return Ok(0);
+  }
+  journal.record(workspace.run_id(), action)
+}
+
+#[tauri::command]
+pub fn get_journal(journal: State<'_, Journal>) -> Vec<JournalEntry> {
+  journal.entries()
+}
+
+/// Undo every journaled change, newest first.
+#[tauri::command]
+pub async fn rollback_install(app: AppHandle) -> Result<Vec<RollbackResult>, String> {
+  tauri::async_runtime::spawn_blocking(move || {
+    let journal = app.state::<Journal>();
+    let workspace = app.state::<Workspace>();
+    let pool = app.state::<SshPool>();
+
+    let mut results = Vec::new();
+    for entry in journal.entries().into_iter().rev() {
+      let description = entry.action.describe();
+      let outcome = entry.action.validate().and_then(|_| {
+        if dry_run::is_enabled(&app) {
+          return Ok(());
+        }
+        undo(&workspace, &pool, &entry.action)
+      });
+      match &outcome {
+        Ok(()) if dry_run::is_enabled(&app) => info!("Dry run: would roll back: {}", description),
+        Ok(()) => {
+          info!("Rolled back: {}", description);
+          journal.remove(entry.id)?;
+        }
+        Err(e) => error!("Rollback failed: {}: {}", description, e),
+      }
+      results.push(RollbackResult {
+        id: entry.id,
+        description,
+        success: outcome.is_ok(),
+        error: outcome.err(),
+      });
+    }
+    Ok(results)
+  })
+  .await
+  .map_err(|e| e.to_string())?
+}
@@ -0,0 +1,114 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Secrets in the OS keyring.
+//!
+//! Linux uses the Secret Service through `secret-tool` (libsecret), macOS the
+//! login keychain through `security`. Secret values are written over stdin,
+//! never on the command line, so they don't show up in `ps`.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Service name every installer secret is filed under.
+pub const SERVICE: &str = "org.thinkube.installer";
+
+fn run_with_stdin(cmd: &mut Command, input: &str) -> Result<String, String> {
+  let mut child = cmd
+    .stdin(Stdio::piped())
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    .spawn()
+    .map_err(|e| format!("Keyring is not available: {}", e))?;
+  if let Some(mut stdin) = child.stdin.take() {
+    stdin
+      .write_all(input.as_bytes())
+      .map_err(|e| format!("Failed to write to keyring: {}", e))?;
+  }
+  let output = child
+    .wait_with_output()
+    .map_err(|e| format!("Keyring failed: {}", e))?;
+  if !output.status.success() {
+    return Err(format!(
+      "Keyring failed: {}",
+      String::from_utf8_lossy(&output.stderr).trim()
+    ));
+  }
+  Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+#[cfg(target_os = "macos")]
+fn quote(value: &str) -> String {
+  format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Store (or replace) the secret for `account`.
+pub fn set(account: &str, secret: &str) -> Result<(), String> {
+  crate::redact::register(secret);
+  #[cfg(target_os = "macos")]
+  {
+    // `security -i` reads commands from stdin, keeping the secret off argv
+    let line = format!(
+      "add-generic-password -U -s {} -a {} -w {}\n",
+      quote(SERVICE),
+      quote(account),
+      quote(secret)
+    );
+    run_with_stdin(Command::new("security").arg("-i"), &line).map(|_| ())
+  }
+  #[cfg(not(target_os = "macos"))]
+  {
+    run_with_stdin(
+      Command::new("secret-tool").args([
+        "store",
+        "--label",
+        &format!("Thinkube Installer: {}", account),
+        "service",
+        SERVICE,
+        "account",
+        account,
+      ]),
+      secret,
+    )
+    .map(|_| ())
+  }
+}
+
+/// The secret for `account`, or `None` if there isn't one.
+pub fn get(account: &str) -> Result<Option<String>, String> {
+  #[cfg(target_os = "macos")]
+  let output = Command::new("security")
+    .args(["find-generic-password", "-s", SERVICE, "-a", account, "-w"])
+    .output();
+  #[cfg(not(target_os = "macos"))]
+  let output = Command::new("secret-tool")
+    .args(["lookup", "service", SERVICE, "account", account])
+    .output();
+
+  let output = output.map_err(|e| format!("Keyring is not available: {}", e))?;
+  if !output.status.success() {
+    // Both tools exit non-zero when nothing matches
+    return Ok(None);
+  }
+  let secret = String::from_utf8_lossy(&output.stdout);
+  let secret = secret.strip_suffix('\n').unwrap_or(&secret);
+  crate::redact::register(secret);
+  Ok(Some(secret.to_string()).filter(|s| !s.is_empty()))
+}
+
+pub fn delete(account: &str) -> Result<(), String> {
+  #[cfg(target_os = "macos")]
+  let output = Command::new("security")
+    .args(["delete-generic-password", "-s", SERVICE, "-a", account])
+    .output();
+  #[cfg(not(target_os = "macos"))]
+  let output = Command::new("secret-tool")
+    .args(["clear", "service", SERVICE, "account", account])
+    .output();
+
+  output
+    .map(|_| ())
+    .map_err(|e| format!("Keyring is not available: {}", e))
+}
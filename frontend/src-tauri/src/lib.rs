@@ -3,201 +3,433 @@
  * SPDX-License-Identifier: Apache-2.0
  */
 
-use std::process::{Command, Child};
-use std::path::PathBuf;
-use std::sync::Mutex;
 use tauri::Manager;
+use tracing::{debug, info, warn};
 
-// State to hold the backend process
-struct BackendProcess(Mutex<Option<Child>>);
+mod audit;
+mod backend;
+mod backup;
+mod bmc;
+mod bundle;
+mod cancel;
+mod certs;
+mod compat;
+mod config;
+mod crash;
+mod deep_link;
+mod desktop;
+mod dry_run;
+mod eta;
+mod health;
+mod i18n;
+mod install_guard;
+mod journal;
+mod keyring;
+mod log_files;
+mod log_level;
+mod migrations;
+mod net;
+mod platform;
+mod playbooks;
+mod preflight;
+mod presets;
+mod profiles;
+mod provision;
+mod pty;
+mod qr;
+mod redact;
+mod remote;
+mod render_fallback;
+mod report;
+mod resume;
+mod schedule;
+mod settings;
+mod shell_log;
+mod smoke;
+mod snapshot;
+mod summary;
+mod support;
+mod tasks;
+mod telemetry;
+mod tokens;
+mod topology;
+mod tray;
+mod trust;
+mod uninstall;
+mod validation;
+mod verify;
+mod windows;
+mod workspace;
 
 #[tauri::command]
 fn get_config_flags() -> (bool, bool) {
     let tk_test_raw = std::env::var("TK_TEST").ok();
     let tk_shell_raw = std::env::var("TK_SHELL_CONFIG").ok();
 
-    println!("🔍 DEBUG get_config_flags:");
-    println!("  TK_TEST raw value: {:?}", tk_test_raw);
-    println!("  TK_SHELL_CONFIG raw value: {:?}", tk_shell_raw);
+    let test_mode = tk_test_raw.as_deref() == Some("1");
+    let shell_config = tk_shell_raw.as_deref() == Some("1");
 
-    let test_mode = tk_test_raw.map(|v| v == "1").unwrap_or(false);
-    let shell_config = tk_shell_raw.map(|v| v == "1").unwrap_or(false);
-
-    println!("  test_mode: {}", test_mode);
-    println!("  shell_config: {}", shell_config);
+    debug!(?tk_test_raw, ?tk_shell_raw, test_mode, shell_config, "get_config_flags");
 
     (test_mode, shell_config)
 }
 
+/// Set the WebKitGTK renderer variables for the desktop session and any
+/// fallbacks earlier launches needed; `main` calls this before anything
+/// starts GTK.
+pub fn prepare_webview() {
+  platform::session::apply(render_fallback::launch_level());
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
   tauri::Builder::default()
-    .invoke_handler(tauri::generate_handler![get_config_flags])
+    // Must come first: a second launch (e.g. from a thinkube:// link) hands
+    // its arguments to the running instance and exits.
+    .plugin(tauri_plugin_single_instance::init(|app, _argv, _cwd| {
+      let _ = windows::show_main(app);
+    }))
+    .plugin(tauri_plugin_deep_link::init())
+    .plugin(tauri_plugin_clipboard_manager::init())
+    .plugin(tauri_plugin_dialog::init())
+    .manage(compat::Compatibility::default())
+    .manage(deep_link::PendingPrefill::default())
+    .manage(dry_run::DryRun::default())
+    .manage(eta::Eta::default())
+    .manage(health::HealthMonitor::default())
+    .manage(install_guard::InstallGuard::default())
+    .manage(cancel::Cancellation::default())
+    .manage(schedule::Scheduler::default())
+    .manage(telemetry::Telemetry::default())
+    .manage(backend::Backend::default())
+    .manage(desktop::clipboard::SecretClipboard::default())
+    .manage(desktop::progress::Progress::default())
+    .manage(provision::pxe::PxeServer::default())
+    .manage(provision::usb::UsbWriter::default())
+    .manage(remote::ssh::SshPool::default())
+    .manage(remote::sftp::Transfers::default())
+    .manage(remote::logs::LogStreams::default())
+    .manage(support::SupportTunnel::default())
+    .manage(pty::PtySessions::default())
+    .manage(tasks::TaskGraphs::default())
+    .manage(backend::metrics::BackendMetrics::default())
+    .manage(net::domain_challenge::DomainChallenges::default())
+    .manage(backend::output::BackendLog::default())
+    .invoke_handler(tauri::generate_handler![
+      get_config_flags,
+      audit::export_audit_log,
+      audit::verify_audit_log,
+      backend::get_api_token,
+      backend::get_backend_status,
+      backend::metrics::get_backend_metrics,
+      backend::output::get_backend_log,
+      backend::restart_backend,
+      backup::backup_state,
+      backup::restore_state,
+      bmc::store_bmc_credentials,
+      bmc::delete_bmc_credentials,
+      bmc::bmc_power_status,
+      bmc::bmc_power_action,
+      bmc::bmc_set_pxe_boot_once,
+      bundle::clear_offline_bundle,
+      bundle::create_offline_bundle,
+      bundle::get_offline_bundle,
+      bundle::import_offline_bundle,
+      cancel::cancel_current_step,
+      certs::generate_bootstrap_certs,
+      certs::inspect_certificate,
+      compat::get_platform_compatibility,
+      config::export_config,
+      config::import_config,
+      config::rules::validate_config,
+      deep_link::take_deep_link_prefill,
+      desktop::browser::open_url,
+      desktop::browser::set_cluster_domain,
+      desktop::clipboard::copy_secret,
+      desktop::export::export_file,
+      desktop::notify::notify_install_event,
+      desktop::pick::pick_file,
+      desktop::progress::set_install_progress,
+      desktop::progress::get_install_progress,
+      desktop::reveal::reveal_logs,
+      desktop::terminal::open_terminal,
+      dry_run::get_dry_run,
+      dry_run::set_dry_run,
+      eta::estimate_install,
+      eta::get_install_eta,
+      health::configure_health_probes,
+      health::get_health_config,
+      health::get_service_health,
+      i18n::get_locale,
+      i18n::set_locale,
+      install_guard::set_install_running,
+      journal::record_action,
+      journal::get_journal,
+      journal::rollback_install,
+      log_files::get_log_usage,
+      log_level::get_log_level,
+      log_level::set_log_level,
+      migrations::get_state_migrations,
+      net::cidrs::check_cidr_conflicts,
+      net::connectivity::check_connectivity,
+      net::dhcp::check_dhcp_conflicts,
+      net::domain_challenge::clear_domain_challenge,
+      net::domain_challenge::create_domain_challenge,
+      net::domain_challenge::publish_domain_challenge,
+      net::domain_challenge::verify_domain_challenge,
+      net::download::download_artifact,
+      net::estimate::estimate_downloads,
+      net::exposure::check_public_exposure,
+      net::ipplan::validate_ip_plan,
+      net::lbrange::check_lb_range,
+      net::mirrors::rank_mirrors,
+      net::overlay::detect_overlay_clients,
+      net::overlay::join_overlay_network,
+      #[cfg(feature = "port-mapping")]
+      net::portmap::map_ports,
+      #[cfg(feature = "port-mapping")]
+      net::portmap::unmap_ports,
+      net::registries::check_registries,
+      net::registry_auth::delete_registry_credentials,
+      net::registry_auth::render_registry_auth,
+      net::registry_auth::store_registry_credentials,
+      net::registry_auth::test_registry_credentials,
+      net::vpn::check_vpn_interference,
+      net::wol::wake_node,
+      platform::os_release::get_os_compatibility,
+      platform::power::get_power_status,
+      platform::regional::get_regional_defaults,
+      platform::regional::set_keyboard_layout,
+      platform::theme::get_system_theme,
+      platform::virt::get_host_environment,
+      playbooks::get_playbooks_checkout,
+      playbooks::sync_playbooks,
+      preflight::run_preflight,
+      presets::list_topology_presets,
+      profiles::list_profiles,
+      profiles::create_profile,
+      profiles::switch_profile,
+      profiles::delete_profile,
+      profiles::save_profile_config,
+      profiles::get_profile_config,
+      provision::pxe::start_pxe_server,
+      provision::pxe::stop_pxe_server,
+      provision::pxe::get_pxe_status,
+      provision::seed::build_seed_iso,
+      provision::usb::list_usb_devices,
+      provision::usb::prepare_usb_write,
+      provision::usb::write_usb_image,
+      provision::usb::cancel_usb_write,
+      pty::open_pty_session,
+      pty::write_pty,
+      pty::resize_pty,
+      pty::close_pty_session,
+      qr::generate_qr,
+      redact::register_secret,
+      remote::agent::detect_ssh_agent,
+      remote::apt_lock::check_apt_locks,
+      remote::apt_lock::wait_for_apt_locks,
+      remote::apt_lock::stop_unattended_upgrades,
+      remote::bench::benchmark_nodes,
+      remote::board::detect_boards,
+      remote::board::get_local_board,
+      remote::conflicts::check_conflicts,
+      remote::conflicts::resolve_conflict,
+      remote::copy_id::distribute_ssh_key,
+      remote::disks::preview_disk_layout,
+      remote::firewall::check_firewalls,
+      remote::firewall::apply_firewall_rules,
+      remote::host_keys::scan_host_key,
+      remote::host_keys::trust_host_key,
+      remote::host_keys::forget_host_key,
+      remote::host_keys::list_host_keys,
+      remote::hostname::validate_hostnames,
+      remote::hostname::apply_hostname,
+      remote::inventory::collect_remote_inventory,
+      remote::ipv6::check_ipv6_readiness,
+      remote::known_hosts::list_known_hosts,
+      remote::known_hosts::add_known_host,
+      remote::known_hosts::remove_known_host,
+      remote::known_hosts::seed_known_hosts,
+      remote::link::check_link_speed,
+      remote::logs::start_log_stream,
+      remote::logs::stop_log_stream,
+      remote::logs::list_log_streams,
+      remote::mac::check_mac_policy,
+      remote::mac::apply_mac_adjustment,
+      remote::mtu::probe_path_mtu,
+      remote::netplan::plan_bridge,
+      remote::netplan::apply_bridge,
+      remote::nvidia::detect_nvidia_stack,
+      remote::passthrough::check_gpu_passthrough,
+      remote::ssh::run_remote,
+      remote::ssh::cancel_remote,
+      remote::sftp::upload_file,
+      remote::sftp::download_file,
+      remote::sftp::cancel_transfer,
+      remote::storage::detect_storage_backends,
+      remote::sudoers::preview_passwordless_sudo,
+      remote::sudoers::setup_passwordless_sudo,
+      remote::smart::check_disk_health,
+      remote::swap::check_swap,
+      remote::swap::disable_swap,
+      remote::tuning::check_kernel_tuning,
+      remote::tuning::apply_kernel_tuning,
+      remote::vmplan::plan_vm_resources,
+      render_fallback::webview_rendered,
+      report::record_step,
+      report::get_transcript,
+      report::clear_transcript,
+      report::export_report,
+      resume::schedule_resume,
+      resume::get_resume_state,
+      resume::clear_resume_state,
+      schedule::schedule_install,
+      schedule::get_scheduled_install,
+      schedule::cancel_scheduled_install,
+      shell_log::get_shell_logs,
+      smoke::run_smoke_tests,
+      snapshot::capture_environment,
+      snapshot::compare_environments,
+      summary::export_summary,
+      support::open_support_tunnel,
+      support::close_support_tunnel,
+      support::get_support_tunnel,
+      tasks::run_task_graph,
+      tasks::cancel_task_graph,
+      tasks::retry_step,
+      tasks::skip_step,
+      telemetry::get_telemetry_status,
+      telemetry::set_telemetry_consent,
+      telemetry::record_step_outcome,
+      telemetry::preview_telemetry,
+      telemetry::submit_telemetry,
+      tokens::cloudflare::validate_cloudflare_token,
+      tokens::github::validate_github_access,
+      topology::suggest_topology,
+      trust::get_trusted_cluster_ca,
+      trust::trust_cluster_ca,
+      trust::untrust_cluster_ca,
+      uninstall::scan_installation,
+      uninstall::uninstall,
+      verify::verify_artifact,
+      workspace::get_workspace,
+      workspace::resolve_workspace_path,
+      windows::open_logs_window,
+      windows::hide_logs_window,
+      windows::get_display_info,
+      windows::set_zoom,
+      workspace::finish_run,
+    ])
     .setup(|app| {
-      // Start backend
-      println!("Starting FastAPI backend...");
-
-      let backend_dir: PathBuf;
-      let venv_dir: String;
-
-      // In development mode, use local backend directory
-      #[cfg(debug_assertions)]
-      {
-        // In dev mode, cargo runs from frontend/src-tauri/, so backend is just ./backend
-        backend_dir = std::env::current_dir()
-          .unwrap()
-          .join("backend");
-        venv_dir = "venv-test".to_string();
-      }
-
-      // In production mode, use bundled backend from resources
-      #[cfg(not(debug_assertions))]
-      {
-        match app.path().resource_dir() {
-          Ok(resource_path) => {
-            backend_dir = resource_path.join("backend");
-            println!("Resource directory: {}", resource_path.display());
-            println!("Backend directory: {}", backend_dir.display());
-
-            if !backend_dir.exists() {
-              eprintln!("ERROR: Backend directory not found at: {}", backend_dir.display());
-              eprintln!("Resource directory contents:");
-              if let Ok(entries) = std::fs::read_dir(&resource_path) {
-                for entry in entries.flatten() {
-                  eprintln!("  - {}", entry.path().display());
-                }
-              }
-              panic!("Backend directory not found in app bundle");
-            }
-          }
-          Err(e) => {
-            eprintln!("ERROR: Failed to get resource directory: {}", e);
-            panic!("Cannot access app resources");
-          }
-        }
-        venv_dir = ".venv".to_string();
-
-        // On macOS, check if venv exists and create it if needed (no post-install script support)
-        #[cfg(target_os = "macos")]
-        {
-          let venv_path = backend_dir.join(&venv_dir);
-          if !venv_path.exists() {
-            println!("First run on macOS: Creating backend virtual environment...");
-
-            // Create venv
-            let status = std::process::Command::new("python3")
-              .args(&["-m", "venv", venv_path.to_str().unwrap()])
-              .status()
-              .expect("Failed to create venv");
-
-            if !status.success() {
-              panic!("Failed to create Python virtual environment");
-            }
+      migrations::upgrade_settings(app.handle());
+      let settings = settings::SettingsStore::load(app.handle())?;
+      i18n::init(settings.get().locale.as_deref());
+      app.manage(settings);
 
-            // Install dependencies
-            println!("Installing backend dependencies...");
-            let pip_path = venv_path.join("bin").join("pip");
-            let requirements_path = backend_dir.join("requirements.txt");
+      shell_log::init(app.handle());
+      let _setup = tracing::info_span!("setup").entered();
+      log_files::prune_playbook_logs(&log_files::Retention::configured(app.handle()));
 
-            let status = std::process::Command::new(pip_path)
-              .args(&["install", "-q", "-r", requirements_path.to_str().unwrap()])
-              .status()
-              .expect("Failed to install dependencies");
+      windows::show_splash(app.handle())?;
 
-            if !status.success() {
-              panic!("Failed to install backend dependencies");
-            }
+      crash::install_hook(app.handle());
+      crash::notify_previous_crash(app.handle());
 
-            println!("Backend environment setup complete");
-          }
-        }
+      let os = platform::os_release::compatibility();
+      info!("Host OS: {} ({:?}): {}", os.os.pretty_name, os.support, os.message);
+      if let Some(profile) = platform::session::applied() {
+        let applied: Vec<String> = profile.applied.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+        info!(
+          "Webview profile {} with {} fallbacks for a {} session ({}): set [{}], kept the user's [{}]",
+          profile.name,
+          profile.fallback,
+          profile.session,
+          profile.compositor.as_deref().unwrap_or("unknown compositor"),
+          applied.join(" "),
+          profile.kept.join(" ")
+        );
       }
 
-      println!("Backend directory: {}", backend_dir.display());
-
-      // Build the backend spawn command. Both Linux and macOS use the
-      // same bash invocation; the cfg-gated branches existed previously
-      // but the body was identical, so they're collapsed here.
-      //
-      // Branch bake-in:
-      //   If the build was invoked as `scripts/build.sh --branch <name>`
-      //   then THINKUBE_BUILD_BRANCH is set at compile time. We forward
-      //   it to the Python backend as THINKUBE_BRANCH so the produced
-      //   binary defaults to that branch when launched from the .desktop
-      //   menu (where env vars from the user shell don't propagate).
-      //   A user who launches from a terminal with THINKUBE_BRANCH set
-      //   wins — Command::env only sets the var if we don't see it
-      //   already in our own env.
-      //
-      //   Same shape for THINKUBE_REPO_URL and THINKUBE_METADATA_REPO
-      //   so a fork-pinned deb is also buildable.
-      let backend_child = {
-        let mut cmd = Command::new("bash");
-        cmd.arg("-c")
-           .arg(format!("cd {} && source {}/bin/activate && python3 main.py",
-                        backend_dir.display(), venv_dir));
-
-        // Forward baked-in defaults unless the user has overridden them.
-        for (compile_env, runtime_env) in [
-          (option_env!("THINKUBE_BUILD_BRANCH"),         "THINKUBE_BRANCH"),
-          (option_env!("THINKUBE_BUILD_REPO_URL"),       "THINKUBE_REPO_URL"),
-          (option_env!("THINKUBE_BUILD_METADATA_REPO"),  "THINKUBE_METADATA_REPO"),
-        ] {
-          if let Some(baked) = compile_env {
-            if !baked.is_empty() && std::env::var(runtime_env).is_err() {
-              cmd.env(runtime_env, baked);
-              println!("Baked-in {}: {}", runtime_env, baked);
-            }
-          }
-        }
-
-        cmd.spawn().expect("Failed to start backend")
-      };
-
-      // Store the backend process in app state
-      app.manage(BackendProcess(Mutex::new(Some(backend_child))));
+      info!("Installation profile: {}", profiles::active(app.handle()));
+      migrations::upgrade_profile(app.handle());
+      let run_workspace = workspace::Workspace::create(app.handle())?;
+      info!("Run workspace: {}", run_workspace.root().display());
+      audit::init(app.handle(), run_workspace.run_id());
+      app.manage(run_workspace);
+      resume::setup(app.handle());
+      app.manage(journal::Journal::load(app.handle())?);
+      app.manage(tasks::markers::Completed::load(app.handle())?);
+      app.manage(remote::host_keys::HostKeys::load(app.handle())?);
+      app.manage(report::Transcript::load(app.handle())?);
+      app.manage(eta::StepTimings::load(app.handle())?);
+      app.manage(backend::ApiToken::generate()?);
 
-      // Give backend time to start
-      std::thread::sleep(std::time::Duration::from_secs(3));
-      println!("Tauri setup starting...");
+      info!("Tauri setup starting...");
       
-      if cfg!(debug_assertions) {
-        app.handle().plugin(
-          tauri_plugin_log::Builder::default()
-            .level(log::LevelFilter::Info)
-            .build(),
-        )?;
-      }
+      // Attached in every build; the filter is narrowed right after
+      let retention = log_files::Retention::configured(app.handle());
+      app.handle().plugin(
+        tauri_plugin_log::Builder::default()
+          .level(log::LevelFilter::Trace)
+          .max_file_size(u128::from(retention.max_file_bytes))
+          .rotation_strategy(tauri_plugin_log::RotationStrategy::KeepSome(retention.keep_files))
+          .build(),
+      )?;
+      log_level::parse_args();
+      backend::mock::parse_args();
+      let level = log_level::current(app.handle());
+      log_level::init(level);
+      info!("Log level: {:?}", level);
       
+      tray::create(app.handle())?;
+      deep_link::setup(app.handle());
+      schedule::setup(app.handle());
+      health::setup(app.handle());
+      compat::start(app.handle());
+      platform::theme::watch(app.handle());
+
+      backend::start(app.handle());
+      backend::metrics::start_sampling(app.handle());
+      backend::watchdog::start(app.handle());
+
       // Get the main window
       if let Some(window) = app.get_webview_window("main") {
-        println!("Main window found, showing it...");
-        window.show().unwrap();
-        window.center().unwrap();
-        window.set_focus().unwrap();
-
         // Add cleanup handler for backend process when window closes
         let app_handle = app.handle().clone();
+        let main_window = window.clone();
         window.on_window_event(move |event| {
-          if let tauri::WindowEvent::CloseRequested { .. } = event {
-            println!("Window closing, killing backend process...");
-            if let Some(backend_state) = app_handle.try_state::<BackendProcess>() {
-              if let Ok(mut child_opt) = backend_state.0.lock() {
-                if let Some(mut child) = child_opt.take() {
-                  let _ = child.kill();
-                  println!("Backend process killed");
-                }
-              }
+          config::drop::handle_window_event(&app_handle, event);
+          platform::theme::handle_window_event(&app_handle, event);
+          if let tauri::WindowEvent::CloseRequested { api, .. } = event {
+            if !install_guard::allow_close(&app_handle, &main_window) {
+              api.prevent_close();
+              return;
             }
+            windows::save_main_geometry(&app_handle);
+            windows::close_secondary(&app_handle);
+            info!("Window closing, killing backend process...");
+            backend::shutdown(&app_handle);
+            remote::ssh::shutdown(&app_handle);
+            support::shutdown(&app_handle);
+            platform::theme::stop();
           }
         });
       } else {
-        println!("WARNING: Main window not found!");
+        warn!("Main window not found!");
       }
 
-      println!("Tauri setup complete");
+      info!("Tauri setup complete");
       Ok(())
     })
-    .run(tauri::generate_context!())
-    .expect("error while running tauri application");
+    .build(tauri::generate_context!())
+    .expect("error while running tauri application")
+    .run(|app_handle, event| {
+      // Also covers quitting from the tray, which never closes the main window
+      if let tauri::RunEvent::Exit = event {
+        backend::shutdown(app_handle);
+        remote::ssh::shutdown(app_handle);
+        support::shutdown(app_handle);
+      }
+    });
 }
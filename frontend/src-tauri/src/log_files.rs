@@ -0,0 +1,239 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Rotation and retention of log files.
+//!
+//! The shell log and the captured backend output are written through
+//! [`RotatingFile`]: `<name>.log` in the app log directory, renamed to
+//! `<name>.<timestamp>.log` once it reaches `log_max_file_mib` or when it is
+//! opened more than a day after it was last written. Rotated
+//! files beyond `log_keep_files` or older than `log_keep_days` are deleted,
+//! and so are playbook logs in `~/.thinkube-installer/logs` past that age.
+//! `get_log_usage` reports what all of it takes on disk.
+
+use serde::Serialize;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tauri::{AppHandle, Manager};
+use time::OffsetDateTime;
+use tracing::{info, warn};
+
+use crate::settings::SettingsStore;
+
+const DEFAULT_KEEP_FILES: usize = 5;
+const DEFAULT_KEEP_DAYS: u64 = 14;
+const DEFAULT_MAX_FILE_MIB: u64 = 20;
+const DAY: Duration = Duration::from_secs(86400);
+
+/// How many log files to keep and for how long.
+#[derive(Debug, Clone, Copy)]
+pub struct Retention {
+  /// Files per log, the one being written included
+  pub keep_files: usize,
+  pub max_age: Duration,
+  pub max_file_bytes: u64,
+}
+
+impl Retention {
+  pub fn configured(app: &AppHandle) -> Self {
+    let settings = app.try_state::<SettingsStore>().map(|s| s.get()).unwrap_or_default();
+    Self {
+      keep_files: settings.log_keep_files.unwrap_or(DEFAULT_KEEP_FILES).max(1),
+      max_age: DAY * settings.log_keep_days.unwrap_or(DEFAULT_KEEP_DAYS) as u32,
+      max_file_bytes: settings.log_max_file_mib.unwrap_or(DEFAULT_MAX_FILE_MIB).max(1) * 1024 * 1024,
+    }
+  }
+}
+
+pub fn app_log_dir(app: &AppHandle) -> Result<PathBuf, String> {
+  app
+    .path()
+    .app_log_dir()
+    .map_err(|e| format!("Cannot resolve app log directory: {}", e))
+}
+
+/// Where the backend writes its playbook logs.
+fn playbook_log_dir() -> Option<PathBuf> {
+  std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".thinkube-installer").join("logs"))
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+  std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+fn is_expired(path: &Path, max_age: Duration) -> bool {
+  modified(path)
+    .and_then(|m| SystemTime::now().duration_since(m).ok())
+    .is_some_and(|age| age > max_age)
+}
+
+/// Delete rotated `<name>.*.log` files in `dir` beyond the retention.
+///
+/// Silent: it runs while the shell log's writer is locked.
+fn prune(dir: &Path, name: &str, retention: &Retention) {
+  let Ok(entries) = std::fs::read_dir(dir) else { return };
+  let prefix = format!("{}.", name);
+  let mut rotated: Vec<PathBuf> = entries
+    .flatten()
+    .map(|entry| entry.path())
+    .filter(|path| {
+      path
+        .file_name()
+        .and_then(|f| f.to_str())
+        .is_some_and(|f| f.starts_with(&prefix) && f.ends_with(".log") && f != format!("{}.log", name))
+    })
+    .collect();
+  // Newest first; the current file is one of the files kept
+  rotated.sort_by_key(|path| std::cmp::Reverse(modified(path)));
+  for (i, path) in rotated.iter().enumerate() {
+    if i + 1 >= retention.keep_files || is_expired(path, retention.max_age) {
+      let _ = std::fs::remove_file(path);
+    }
+  }
+}
+
+/// Delete playbook logs (and failure copies) older than the retention.
+pub fn prune_playbook_logs(retention: &Retention) {
+  let Some(dir) = playbook_log_dir() else { return };
+  let mut removed = 0;
+  for dir in [dir.join("failures"), dir] {
+    let Ok(entries) = std::fs::read_dir(&dir) else { continue };
+    for path in entries.flatten().map(|entry| entry.path()) {
+      if !path.extension().is_some_and(|ext| ext == "log") || !is_expired(&path, retention.max_age) {
+        continue;
+      }
+      match std::fs::remove_file(&path) {
+        Ok(()) => removed += 1,
+        Err(e) => warn!("Failed to remove old playbook log {}: {}", path.display(), e),
+      }
+    }
+  }
+  if removed > 0 {
+    info!(
+      "Removed {} playbook log(s) older than {} days",
+      removed,
+      retention.max_age.as_secs() / DAY.as_secs()
+    );
+  }
+}
+
+/// A log file that rotates by size.
+pub struct RotatingFile {
+  dir: PathBuf,
+  name: String,
+  retention: Retention,
+  file: File,
+  written: u64,
+}
+
+impl RotatingFile {
+  pub fn open(dir: &Path, name: &str, retention: Retention) -> Result<Self, String> {
+    std::fs::create_dir_all(dir).map_err(|e| format!("Cannot create {}: {}", dir.display(), e))?;
+    let path = dir.join(format!("{}.log", name));
+    let file = OpenOptions::new()
+      .create(true)
+      .append(true)
+      .open(&path)
+      .map_err(|e| format!("Cannot open {}: {}", path.display(), e))?;
+    let written = file.metadata().map(|m| m.len()).unwrap_or(0);
+    prune(dir, name, &retention);
+    let mut log = Self {
+      dir: dir.to_path_buf(),
+      name: name.to_string(),
+      retention,
+      file,
+      written,
+    };
+    // One left from days ago starts over, however small
+    if written > 0 && is_expired(&path, DAY) {
+      let _ = log.rotate();
+    }
+    Ok(log)
+  }
+
+  fn rotate(&mut self) -> std::io::Result<()> {
+    let now = OffsetDateTime::now_utc();
+    let stamp = format!(
+      "{:04}{:02}{:02}-{:02}{:02}{:02}",
+      now.year(),
+      u8::from(now.month()),
+      now.day(),
+      now.hour(),
+      now.minute(),
+      now.second()
+    );
+    let current = self.dir.join(format!("{}.log", self.name));
+    std::fs::rename(&current, self.dir.join(format!("{}.{}.log", self.name, stamp)))?;
+    self.file = OpenOptions::new().create(true).append(true).open(&current)?;
+    self.written = 0;
+    prune(&self.dir, &self.name, &self.retention);
+    Ok(())
+  }
+}
+
+impl Write for RotatingFile {
+  fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+    if self.written > 0 && self.written + buf.len() as u64 > self.retention.max_file_bytes {
+      // Keep writing to the old file rather than losing the line
+      let _ = self.rotate();
+    }
+    let n = self.file.write(buf)?;
+    self.written += n as u64;
+    Ok(n)
+  }
+
+  fn flush(&mut self) -> std::io::Result<()> {
+    self.file.flush()
+  }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LogDirUsage {
+  pub path: String,
+  pub files: u64,
+  pub bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LogUsage {
+  pub directories: Vec<LogDirUsage>,
+  pub total_bytes: u64,
+}
+
+fn dir_usage(dir: &Path) -> LogDirUsage {
+  let mut usage = LogDirUsage {
+    path: dir.display().to_string(),
+    files: 0,
+    bytes: 0,
+  };
+  let mut pending = vec![dir.to_path_buf()];
+  while let Some(dir) = pending.pop() {
+    let Ok(entries) = std::fs::read_dir(&dir) else { continue };
+    for entry in entries.flatten() {
+      let Ok(metadata) = entry.metadata() else { continue };
+      if metadata.is_dir() {
+        pending.push(entry.path());
+      } else {
+        usage.files += 1;
+        usage.bytes += metadata.len();
+      }
+    }
+  }
+  usage
+}
+
+/// Disk used by the shell, backend and playbook logs.
+#[tauri::command]
+pub fn get_log_usage(app: AppHandle) -> Result<LogUsage, String> {
+  let mut dirs = vec![app_log_dir(&app)?];
+  dirs.extend(playbook_log_dir());
+  let directories: Vec<LogDirUsage> = dirs.iter().filter(|d| d.exists()).map(|d| dir_usage(d)).collect();
+  Ok(LogUsage {
+    total_bytes: directories.iter().map(|d| d.bytes).sum(),
+    directories,
+  })
+}
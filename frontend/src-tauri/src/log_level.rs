@@ -0,0 +1,191 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! One verbosity knob for the shell, the backend and Ansible.
+//!
+//! `set_log_level` adjusts the Rust log filters, remembers the level in
+//! settings.json and restarts the backend, which picks it up from its
+//! environment: `LOG_LEVEL` for Python logging and `ANSIBLE_VERBOSITY` for
+//! playbook runs (`debug` is `-vvv`, `trace` is `-vvvv` and also shows the
+//! connection debugging).
+//!
+//! Release builds log at `info` and dev builds at `debug` unless settings
+//! say otherwise; `--log-level <level>` overrides both for one run, until
+//! the level is changed from the UI.
+
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State};
+use tracing::{info, warn};
+
+use crate::install_guard::InstallGuard;
+use crate::settings::SettingsStore;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+  Error,
+  Warn,
+  Info,
+  Debug,
+  Trace,
+}
+
+impl Default for LogLevel {
+  fn default() -> Self {
+    if cfg!(debug_assertions) {
+      LogLevel::Debug
+    } else {
+      LogLevel::Info
+    }
+  }
+}
+
+/// Level given with `--log-level`, cleared by `set_log_level`.
+static CLI_OVERRIDE: Mutex<Option<LogLevel>> = Mutex::new(None);
+
+impl LogLevel {
+  pub fn parse(level: &str) -> Option<Self> {
+    match level.trim().to_ascii_lowercase().as_str() {
+      "error" => Some(LogLevel::Error),
+      "warn" | "warning" => Some(LogLevel::Warn),
+      "info" => Some(LogLevel::Info),
+      "debug" => Some(LogLevel::Debug),
+      "trace" => Some(LogLevel::Trace),
+      _ => None,
+    }
+  }
+
+  fn as_str(self) -> &'static str {
+    match self {
+      LogLevel::Error => "error",
+      LogLevel::Warn => "warn",
+      LogLevel::Info => "info",
+      LogLevel::Debug => "debug",
+      LogLevel::Trace => "trace",
+    }
+  }
+
+  fn filter(self) -> log::LevelFilter {
+    match self {
+      LogLevel::Error => log::LevelFilter::Error,
+      LogLevel::Warn => log::LevelFilter::Warn,
+      LogLevel::Info => log::LevelFilter::Info,
+      LogLevel::Debug => log::LevelFilter::Debug,
+      LogLevel::Trace => log::LevelFilter::Trace,
+    }
+  }
+
+  fn tracing(self) -> tracing::level_filters::LevelFilter {
+    use tracing::level_filters::LevelFilter;
+    match self {
+      LogLevel::Error => LevelFilter::ERROR,
+      LogLevel::Warn => LevelFilter::WARN,
+      LogLevel::Info => LevelFilter::INFO,
+      LogLevel::Debug => LevelFilter::DEBUG,
+      LogLevel::Trace => LevelFilter::TRACE,
+    }
+  }
+
+  /// Python `logging` has no trace level
+  fn python(self) -> &'static str {
+    match self {
+      LogLevel::Error => "ERROR",
+      LogLevel::Warn => "WARNING",
+      LogLevel::Info => "INFO",
+      LogLevel::Debug | LogLevel::Trace => "DEBUG",
+    }
+  }
+
+  /// Number of `-v` flags; playbooks have always run with one
+  fn ansible_verbosity(self) -> u8 {
+    match self {
+      LogLevel::Error | LogLevel::Warn | LogLevel::Info => 1,
+      LogLevel::Debug => 3,
+      LogLevel::Trace => 4,
+    }
+  }
+}
+
+/// Read `--log-level <level>` (or `--log-level=<level>`) from the command line.
+pub fn parse_args() {
+  let mut args = std::env::args().skip(1);
+  while let Some(arg) = args.next() {
+    let value = match arg.strip_prefix("--log-level") {
+      Some("") => args.next(),
+      Some(rest) => rest.strip_prefix('=').map(str::to_string),
+      None => continue,
+    };
+    match value.as_deref().and_then(LogLevel::parse) {
+      Some(level) => {
+        if let Ok(mut cli) = CLI_OVERRIDE.lock() {
+          *cli = Some(level);
+        }
+      }
+      None => warn!("Ignoring invalid --log-level {:?}", value.unwrap_or_default()),
+    }
+  }
+}
+
+/// The `--log-level` flag, else the level saved in settings, else the
+/// build's default.
+pub fn current(app: &AppHandle) -> LogLevel {
+  if let Some(level) = CLI_OVERRIDE.lock().ok().and_then(|cli| *cli) {
+    return level;
+  }
+  app
+    .try_state::<SettingsStore>()
+    .and_then(|settings| settings.get().log_level)
+    .and_then(|level| LogLevel::parse(&level))
+    .unwrap_or_default()
+}
+
+/// Apply `level` to the Rust log filters.
+pub fn init(level: LogLevel) {
+  log::set_max_level(level.filter());
+  crate::shell_log::set_level(level.tracing());
+}
+
+/// Pass the level on to a backend that is about to be spawned.
+pub fn apply_to_backend(app: &AppHandle, cmd: &mut Command) {
+  let level = current(app);
+  cmd
+    .env("LOG_LEVEL", level.python())
+    .env("ANSIBLE_VERBOSITY", level.ansible_verbosity().to_string());
+}
+
+#[tauri::command]
+pub fn get_log_level(app: AppHandle) -> LogLevel {
+  current(&app)
+}
+
+/// Change the verbosity everywhere, restarting the backend when it changed;
+/// refused while a step runs, as the restart would stop it.
+#[tauri::command]
+pub fn set_log_level(
+  app: AppHandle,
+  settings: State<'_, SettingsStore>,
+  guard: State<'_, InstallGuard>,
+  level: String,
+) -> Result<LogLevel, String> {
+  let level = LogLevel::parse(&level).ok_or_else(|| format!("Unknown log level: {}", level))?;
+  let previous = current(&app);
+  if level != previous {
+    if let Some(step) = guard.running_step() {
+      return Err(format!("Cannot change the log level while {} is running", step));
+    }
+  }
+  settings.update(|s| s.log_level = Some(level.as_str().to_string()))?;
+  if let Ok(mut cli) = CLI_OVERRIDE.lock() {
+    *cli = None;
+  }
+  init(level);
+  if level != previous {
+    info!("Log level set to {}; restarting the backend", level.as_str());
+    crate::backend::restart(&app);
+  }
+  Ok(level)
+}
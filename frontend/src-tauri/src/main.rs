@@ -4,10 +4,10 @@
  */
 
 fn main() {
-  // Set WebKit environment variable BEFORE Tauri/WebKit initializes
-  // This fixes white screen issues on NVIDIA GPU systems (DGX Spark, RTX workstations)
-  // See: https://bugs.webkit.org/show_bug.cgi?id=254901
-  std::env::set_var("WEBKIT_DISABLE_DMABUF_RENDERER", "1");
+  // Set the WebKit environment variables BEFORE Tauri/WebKit initializes.
+  // The DMA-BUF renderer shows a white screen on NVIDIA GPU systems (DGX
+  // Spark, RTX workstations); see platform::session for the other cases.
+  app_lib::prepare_webview();
 
   app_lib::run();
 }
@@ -0,0 +1,199 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Upgrading the shell's own files written by older installers.
+//!
+//! Exported configs have had a versioned envelope from the start (see
+//! `config::migrate`); the state the shell keeps on disk is versioned the
+//! same way: settings.json and resume.json carry a `version` field, and
+//! journal.json and transcript.json wrap their entries as `{ version,
+//! items }`. Files from before that are version 0. At startup, before
+//! anything loads them, each file is brought forward one step at a time,
+//! with the original kept next to it as `<name>.v<N>.bak`. A file written by
+//! a newer installer is left alone. `get_state_migrations` reports what was
+//! done, so the wizard can tell the user their old state was carried over.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+use tracing::{info, warn};
+
+use crate::config;
+use crate::profiles;
+use crate::workspace::write_private_file;
+
+/// Version of the shell's state files. settings.json, resume.json,
+/// journal.json and transcript.json predate it and are upgraded by
+/// [`upgrade_settings`] and [`upgrade_profile`]; schedule.json, health.json,
+/// completed.json, step_timings.json and host_keys.json were versioned
+/// from the start, so they need a [`StateKind`] of their own there once
+/// this goes past 1.
+pub const STATE_VERSION: u32 = 1;
+
+/// How journal.json and transcript.json are stored.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Versioned<T> {
+  pub version: u32,
+  pub items: T,
+}
+
+impl<T> Versioned<T> {
+  pub fn current(items: T) -> Self {
+    Self {
+      version: STATE_VERSION,
+      items,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StateKind {
+  Settings,
+  Journal,
+  Transcript,
+  Resume,
+  ProfileConfig,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MigratedFile {
+  pub kind: StateKind,
+  pub path: String,
+  pub from_version: u32,
+  pub to_version: u32,
+  /// What each step changed
+  pub notes: Vec<String>,
+  /// Copy of the file as it was
+  pub backup: Option<String>,
+  /// Why the file was left as it is
+  pub error: Option<String>,
+}
+
+static LOG: Mutex<Vec<MigratedFile>> = Mutex::new(Vec::new());
+
+fn version_of(raw: &Value) -> u32 {
+  match raw {
+    Value::Object(object) => object.get("version").and_then(Value::as_u64).unwrap_or(0) as u32,
+    _ => 0,
+  }
+}
+
+/// v0 → v1 for the shell's state files.
+fn v0_to_v1(kind: StateKind, raw: Value, notes: &mut Vec<String>) -> Result<Value, String> {
+  match (kind, raw) {
+    (StateKind::Journal | StateKind::Transcript, Value::Array(items)) => {
+      notes.push("Wrapped the entries in a versioned envelope".to_string());
+      Ok(json!({ "version": 1, "items": items }))
+    }
+    (StateKind::Settings | StateKind::Resume, Value::Object(mut object)) => {
+      notes.push("Added the state format version".to_string());
+      object.insert("version".to_string(), json!(1));
+      Ok(Value::Object(object))
+    }
+    _ => Err("unexpected structure for a version 0 file".to_string()),
+  }
+}
+
+fn upgrade(kind: StateKind, raw: Value) -> Result<(Value, u32, Vec<String>), String> {
+  if kind == StateKind::ProfileConfig {
+    let (upgraded, migration) = config::migrate::upgrade(raw)?;
+    return Ok((upgraded, migration.from_version, migration.notes));
+  }
+  let from_version = version_of(&raw);
+  if from_version > STATE_VERSION {
+    return Err(format!(
+      "written by a newer installer (state version {}, this one has {}); left unchanged",
+      from_version, STATE_VERSION
+    ));
+  }
+  let mut notes = Vec::new();
+  let mut value = raw;
+  let mut version = from_version;
+  while version < STATE_VERSION {
+    value = match version {
+      0 => v0_to_v1(kind, value, &mut notes)?,
+      _ => unreachable!("missing state migration from version {}", version),
+    };
+    version += 1;
+  }
+  Ok((value, from_version, notes))
+}
+
+fn upgrade_file(kind: StateKind, path: &Path) {
+  let Ok(text) = std::fs::read_to_string(path) else {
+    return;
+  };
+  // Unparseable files are reported and reset by their loaders
+  let Ok(raw) = serde_json::from_str::<Value>(&text) else {
+    return;
+  };
+  let to_version = match kind {
+    StateKind::ProfileConfig => config::CURRENT_VERSION,
+    _ => STATE_VERSION,
+  };
+  let mut entry = MigratedFile {
+    kind,
+    path: path.display().to_string(),
+    from_version: version_of(&raw),
+    to_version,
+    notes: Vec::new(),
+    backup: None,
+    error: None,
+  };
+  match upgrade(kind, raw) {
+    Ok((_, from, _)) if from == to_version => return,
+    Ok((value, from, notes)) => {
+      entry.from_version = from;
+      entry.notes = notes;
+      let backup = PathBuf::from(format!("{}.v{}.bak", path.display(), from));
+      let written = std::fs::copy(path, &backup)
+        .map_err(|e| format!("Failed to back up {}: {}", path.display(), e))
+        .and_then(|_| serde_json::to_string_pretty(&value).map_err(|e| e.to_string()))
+        .and_then(|json| write_private_file(path, json.as_bytes()));
+      match written {
+        Ok(()) => {
+          info!("Migrated {} from version {} to {}", path.display(), from, to_version);
+          entry.backup = Some(backup.display().to_string());
+        }
+        Err(e) => entry.error = Some(e),
+      }
+    }
+    Err(e) => entry.error = Some(e),
+  }
+  if let Some(error) = &entry.error {
+    warn!("Not migrating {}: {}", path.display(), error);
+  }
+  if let Ok(mut log) = LOG.lock() {
+    log.push(entry);
+  }
+}
+
+/// Upgrade settings.json; runs before the settings are loaded.
+pub fn upgrade_settings(app: &AppHandle) {
+  if let Ok(dir) = app.path().app_config_dir() {
+    upgrade_file(StateKind::Settings, &dir.join("settings.json"));
+  }
+}
+
+/// Upgrade the active profile's state; runs before any of it is loaded.
+pub fn upgrade_profile(app: &AppHandle) {
+  if let Ok(dir) = profiles::data_dir(app) {
+    upgrade_file(StateKind::Journal, &dir.join("journal.json"));
+    upgrade_file(StateKind::Transcript, &dir.join("transcript.json"));
+    upgrade_file(StateKind::ProfileConfig, &dir.join("config.json"));
+  }
+  if let Ok(dir) = profiles::config_dir(app) {
+    upgrade_file(StateKind::Resume, &dir.join("resume.json"));
+  }
+}
+
+/// Files upgraded (or left alone because they're too new) at startup.
+#[tauri::command]
+pub fn get_state_migrations() -> Vec<MigratedFile> {
+  LOG.lock().map(|log| log.clone()).unwrap_or_default()
+}
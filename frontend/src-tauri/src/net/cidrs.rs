@@ -0,0 +1,235 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Overlaps between the cluster's IPv4 networks.
+//!
+//! A pod or service CIDR that overlaps a network the nodes or this machine
+//! already route to makes the services on that network unreachable from
+//! inside the cluster, and vice versa. `check_cidr_conflicts` checks every
+//! configured network (node LAN, pod, service, load balancer pool,
+//! ZeroTier) against the others and against the networks on this machine:
+//! its interfaces, which include LXD, Docker and libvirt bridges, and the
+//! routes a VPN client added (see [`super::vpn`]). For each conflicting
+//! network the user can still change, it suggests the first block of the
+//! same size in the private ranges that conflicts with nothing.
+
+use serde::{Deserialize, Serialize};
+use std::net::Ipv4Addr;
+
+use super::ipplan::Subnet;
+use super::vpn;
+use crate::platform::find_program;
+
+/// Where suggestions are taken from, in order.
+const PRIVATE_RANGES: &[&str] = &["10.0.0.0/8", "172.16.0.0/12", "192.168.0.0/16"];
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ClusterNetwork {
+  /// e.g. `pod network`
+  pub name: String,
+  pub cidr: String,
+  /// Given by the environment (the LAN, an existing ZeroTier network), so
+  /// no alternative is suggested
+  #[serde(default)]
+  pub fixed: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LocalNetwork {
+  pub interface: String,
+  pub cidr: String,
+  /// `interface` or `vpn route`
+  pub source: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NetworkCheck {
+  pub name: String,
+  pub cidr: String,
+  pub conflicts: Vec<String>,
+  /// A block of the same size that overlaps nothing
+  pub suggestion: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CidrReport {
+  pub networks: Vec<NetworkCheck>,
+  pub local: Vec<LocalNetwork>,
+  /// No network overlaps another
+  pub ok: bool,
+}
+
+fn local_network(interface: &str, cidr: &str) -> Option<LocalNetwork> {
+  let subnet = Subnet::parse(cidr).ok()?;
+  Some(LocalNetwork {
+    interface: interface.to_string(),
+    cidr: subnet.to_string(),
+    source: "interface".to_string(),
+  })
+}
+
+/// The networks on this machine's interfaces, loopback and link-local
+/// left out.
+fn interface_networks() -> Vec<LocalNetwork> {
+  let (program, fallbacks, args): (&str, &[&str], &[&str]) = if cfg!(target_os = "macos") {
+    ("ifconfig", &["/sbin/ifconfig"], &[])
+  } else {
+    (
+      "ip",
+      &["/sbin/ip", "/usr/sbin/ip", "/usr/bin/ip"],
+      &["-o", "-4", "addr", "show"],
+    )
+  };
+  let Some(output) =
+    find_program(program, fallbacks).and_then(|p| std::process::Command::new(p).args(args).output().ok())
+  else {
+    return Vec::new();
+  };
+  let text = String::from_utf8_lossy(&output.stdout);
+  let mut networks = Vec::new();
+  let mut current = String::new();
+  for line in text.lines() {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if cfg!(target_os = "macos") {
+      // `en0: flags=…` starts an interface, `inet 192.168.1.5 netmask 0xffffff00` follows
+      if !line.starts_with(char::is_whitespace) {
+        current = line.split(':').next().unwrap_or_default().to_string();
+        continue;
+      }
+      let (Some(&"inet"), Some(addr)) = (fields.first(), fields.get(1)) else {
+        continue;
+      };
+      let prefix = fields
+        .iter()
+        .skip_while(|f| **f != "netmask")
+        .nth(1)
+        .and_then(|m| u32::from_str_radix(m.trim_start_matches("0x"), 16).ok())
+        .map(u32::count_ones)
+        .unwrap_or(32);
+      networks.extend(local_network(&current, &format!("{}/{}", addr, prefix)));
+    } else if let [_, interface, "inet", cidr, ..] = fields[..] {
+      networks.extend(local_network(interface, cidr));
+    }
+  }
+  networks.retain(|n| {
+    !n.interface.starts_with("lo")
+      && Subnet::parse(&n.cidr)
+        .is_ok_and(|s| !s.contains(Ipv4Addr::new(127, 0, 0, 1)) && !s.contains(Ipv4Addr::new(169, 254, 0, 1)))
+  });
+  networks
+}
+
+fn local_networks() -> Vec<LocalNetwork> {
+  let mut networks = interface_networks();
+  for vpn in vpn::interfaces() {
+    for route in vpn.routes.iter().filter(|r| r.as_str() != "0.0.0.0/0") {
+      networks.push(LocalNetwork {
+        interface: vpn.name.clone(),
+        cidr: route.clone(),
+        source: "vpn route".to_string(),
+      });
+    }
+  }
+  networks.sort_by(|a, b| (&a.interface, &a.cidr).cmp(&(&b.interface, &b.cidr)));
+  networks.dedup_by(|a, b| a.interface == b.interface && a.cidr == b.cidr);
+  networks
+}
+
+/// The first block with `prefix` in the private ranges overlapping none
+/// of `taken`.
+fn suggest(prefix: u8, taken: &[Subnet]) -> Option<String> {
+  let step = 1u64 << (32 - u32::from(prefix));
+  PRIVATE_RANGES.iter().find_map(|range| {
+    let range = Subnet::parse(range).ok()?;
+    if prefix < range.prefix() {
+      return None;
+    }
+    let start = u64::from(u32::from(range.network()));
+    let end = start + (1u64 << (32 - u32::from(range.prefix())));
+    (start..end).step_by(step as usize).find_map(|network| {
+      let candidate = Subnet::parse(&format!("{}/{}", Ipv4Addr::from(network as u32), prefix)).ok()?;
+      (!taken.iter().any(|t| t.overlaps(&candidate))).then(|| candidate.to_string())
+    })
+  })
+}
+
+/// Check the cluster's `networks` against each other and this machine's
+/// networks, suggesting free blocks for the ones that conflict.
+#[tauri::command]
+pub async fn check_cidr_conflicts(networks: Vec<ClusterNetwork>) -> Result<CidrReport, String> {
+  let parsed = networks
+    .iter()
+    .map(|n| Subnet::parse(&n.cidr))
+    .collect::<Result<Vec<_>, String>>()?;
+  tauri::async_runtime::spawn_blocking(move || {
+    let local = local_networks();
+    // The LAN and the ZeroTier network show up among this machine's
+    // interfaces; they aren't a conflict with themselves
+    let local_parsed: Vec<(&LocalNetwork, Subnet)> = local
+      .iter()
+      .filter_map(|l| Some((l, Subnet::parse(&l.cidr).ok()?)))
+      .filter(|(_, l)| {
+        !networks
+          .iter()
+          .zip(&parsed)
+          .any(|(n, p)| n.fixed && p.to_string() == l.to_string())
+      })
+      .collect();
+
+    let mut checks: Vec<NetworkCheck> = networks
+      .iter()
+      .zip(&parsed)
+      .enumerate()
+      .map(|(i, (network, subnet))| {
+        let mut conflicts: Vec<String> = networks
+          .iter()
+          .zip(&parsed)
+          .enumerate()
+          .filter(|(j, (_, other))| *j != i && other.overlaps(subnet))
+          .map(|(_, (other, cidr))| format!("Overlaps the {} {}", other.name, cidr))
+          .collect();
+        conflicts.extend(local_parsed.iter().filter(|(_, l)| l.overlaps(subnet)).map(
+          |(l, cidr)| match l.source.as_str() {
+            "interface" => format!("Overlaps {} on {} on this machine", cidr, l.interface),
+            _ => format!("Overlaps {}, routed through the VPN on {}", cidr, l.interface),
+          },
+        ));
+        NetworkCheck {
+          name: network.name.clone(),
+          cidr: subnet.to_string(),
+          conflicts,
+          suggestion: None,
+        }
+      })
+      .collect();
+
+    // Suggestions avoid everything else, including earlier suggestions
+    let mut taken: Vec<Subnet> = local_parsed.iter().map(|(_, s)| s.clone()).collect();
+    for (i, check) in checks.iter_mut().enumerate() {
+      if check.conflicts.is_empty() || networks[i].fixed {
+        continue;
+      }
+      let others: Vec<Subnet> = parsed
+        .iter()
+        .enumerate()
+        .filter(|(j, _)| *j != i)
+        .map(|(_, s)| s.clone())
+        .chain(taken.iter().cloned())
+        .collect();
+      check.suggestion = suggest(parsed[i].prefix(), &others);
+      if let Some(suggestion) = check.suggestion.as_deref().and_then(|s| Subnet::parse(s).ok()) {
+        taken.push(suggestion);
+      }
+    }
+
+    CidrReport {
+      ok: checks.iter().all(|c| c.conflicts.is_empty()),
+      networks: checks,
+      local,
+    }
+  })
+  .await
+  .map_err(|e| e.to_string())
+}
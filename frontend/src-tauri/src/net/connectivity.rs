@@ -0,0 +1,188 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Checking that the installer host really is on the internet.
+//!
+//! Hotel and lab networks often put a captive portal or a TLS-inspecting
+//! proxy in the way, and the install then fails deep inside apt or git with
+//! an error that says nothing about the network. `check_connectivity` asks a
+//! plain-HTTP endpoint that always answers `204 No Content`: a redirect or
+//! any other answer means something in between is rewriting traffic. It then
+//! fetches the certificate GitHub presents and verifies it against the public
+//! roots; one that doesn't verify was issued by whatever intercepts TLS.
+
+use serde::Serialize;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use crate::certs;
+
+/// Answers 204 with an empty body, and nothing else, when reached directly.
+const PROBE_URL: &str = "http://connectivitycheck.gstatic.com/generate_204";
+/// Where the installer downloads most of what it needs from.
+const TLS_HOST: &str = "github.com";
+const TIMEOUT: Duration = Duration::from_secs(10);
+/// Round trips slower than this are flagged.
+const SLOW_LATENCY_MS: u64 = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectivityStatus {
+  Online,
+  /// Online, but the round trip is slow
+  Slow,
+  CaptivePortal,
+  TlsIntercepted,
+  /// Names don't resolve
+  NoDns,
+  Offline,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectivityReport {
+  pub status: ConnectivityStatus,
+  pub message: String,
+  /// Time to answer the plain-HTTP probe
+  pub latency_ms: Option<u64>,
+  /// Where a captive portal redirected the probe
+  pub portal_url: Option<String>,
+  /// Issuer of the certificate presented in place of GitHub's
+  pub tls_issuer: Option<String>,
+}
+
+impl ConnectivityReport {
+  fn new(status: ConnectivityStatus, message: String) -> Self {
+    Self {
+      status,
+      message,
+      latency_ms: None,
+      portal_url: None,
+      tls_issuer: None,
+    }
+  }
+}
+
+/// What the plain-HTTP probe got back.
+enum Probe {
+  NoContent,
+  Redirect(Option<String>),
+  /// Some other answer, e.g. a login page served in place of the 204
+  Rewritten(u16),
+  NoDns(String),
+  Unreachable(String),
+}
+
+fn probe() -> (Probe, u64) {
+  let agent = ureq::AgentBuilder::new().timeout(TIMEOUT).redirects(0).build();
+  let started = Instant::now();
+  let result = agent.get(PROBE_URL).call();
+  let latency_ms = started.elapsed().as_millis() as u64;
+  let probe = match result {
+    Ok(response) if response.status() == 204 => Probe::NoContent,
+    Ok(response) if (300..400).contains(&response.status()) => {
+      Probe::Redirect(response.header("location").map(str::to_string))
+    }
+    Ok(response) => Probe::Rewritten(response.status()),
+    Err(ureq::Error::Status(code, _)) => Probe::Rewritten(code),
+    Err(ureq::Error::Transport(transport)) if transport.kind() == ureq::ErrorKind::Dns => {
+      Probe::NoDns(transport.to_string())
+    }
+    Err(ureq::Error::Transport(transport)) => Probe::Unreachable(transport.to_string()),
+  };
+  (probe, latency_ms)
+}
+
+/// The issuer of the certificate `host` presents on 443, if it isn't one the
+/// public roots vouch for.
+pub fn untrusted_issuer(host: &str) -> Result<Option<String>, String> {
+  let chain = certs::fetch_remote(host, 443)?;
+  if certs::verify_chain(&chain, host, None).is_ok() {
+    return Ok(None);
+  }
+  let issuer = x509_parser::parse_x509_certificate(&chain[0])
+    .map(|(_, cert)| cert.issuer().to_string())
+    .unwrap_or_else(|_| "an unknown issuer".to_string());
+  Ok(Some(issuer))
+}
+
+fn check() -> ConnectivityReport {
+  let (probe, latency_ms) = probe();
+  let mut report = match probe {
+    Probe::NoContent => ConnectivityReport::new(ConnectivityStatus::Online, "Connected to the internet".to_string()),
+    Probe::Redirect(location) => {
+      let mut report = ConnectivityReport::new(
+        ConnectivityStatus::CaptivePortal,
+        "This network redirects web traffic to a login page. Sign in to the network in a browser, then check again."
+          .to_string(),
+      );
+      report.portal_url = location;
+      report
+    }
+    Probe::Rewritten(code) => ConnectivityReport::new(
+      ConnectivityStatus::CaptivePortal,
+      format!(
+        "Something on this network answered a connectivity check with HTTP {}. It is likely a captive portal: sign in to the network in a browser, then check again.",
+        code
+      ),
+    ),
+    Probe::NoDns(e) => {
+      return ConnectivityReport::new(
+        ConnectivityStatus::NoDns,
+        format!("Cannot resolve internet host names; check the DNS settings ({})", e),
+      )
+    }
+    Probe::Unreachable(e) => {
+      return ConnectivityReport::new(
+        ConnectivityStatus::Offline,
+        format!("Cannot reach the internet ({})", e),
+      )
+    }
+  };
+  report.latency_ms = Some(latency_ms);
+  if report.status == ConnectivityStatus::CaptivePortal {
+    return report;
+  }
+
+  match untrusted_issuer(TLS_HOST) {
+    Ok(None) => {}
+    Ok(Some(issuer)) => {
+      report.status = ConnectivityStatus::TlsIntercepted;
+      report.message = format!(
+        "Secure connections on this network are intercepted: {} presented a certificate issued by {}. Downloads will fail unless that CA is trusted; use another network if you can.",
+        TLS_HOST, issuer
+      );
+      report.tls_issuer = Some(issuer);
+      return report;
+    }
+    Err(e) => {
+      report.status = ConnectivityStatus::Offline;
+      report.message = format!("Plain HTTP works but secure connections fail: {}", e);
+      return report;
+    }
+  }
+
+  if latency_ms > SLOW_LATENCY_MS {
+    report.status = ConnectivityStatus::Slow;
+    report.message = format!(
+      "Connected, but the network is slow ({} ms round trip); downloads will take a while",
+      latency_ms
+    );
+  }
+  report
+}
+
+/// Check for internet access, captive portals and TLS interception before
+/// anything is downloaded.
+#[tauri::command]
+pub async fn check_connectivity() -> Result<ConnectivityReport, String> {
+  let report = tauri::async_runtime::spawn_blocking(check)
+    .await
+    .map_err(|e| e.to_string())?;
+  match report.status {
+    ConnectivityStatus::Online | ConnectivityStatus::Slow => info!("{}", report.message),
+    _ => warn!("{}", report.message),
+  }
+  Ok(report)
+}
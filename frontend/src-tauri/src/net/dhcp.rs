@@ -0,0 +1,384 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Planned static addresses against the router's DHCP pool.
+//!
+//! DHCP has no way to ask a server for its pool, so it is inferred from
+//! what the pool hands out. `check_dhcp_conflicts` broadcasts a
+//! DHCPDISCOVER from this machine with a made-up hardware address and
+//! collects the offers, never requesting one, so no lease is taken; this
+//! needs UDP port 68, which on Linux only root or a DHCP client already
+//! holding it may bind, so it can fail and is then reported as skipped. On
+//! the nodes it reads the addresses the kernel marks as dynamic and the
+//! lease files of systemd-networkd. The lowest and highest of all those
+//! addresses give the part of the pool known to be in use; a planned
+//! address inside it, leased to another node, or just offered is flagged.
+
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::net::{Ipv4Addr, UdpSocket};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+use super::ipplan::{DhcpRange, PlannedAddress, Subnet};
+use crate::preflight::Outcome;
+use crate::remote::inventory::sections;
+use crate::remote::ssh::{self, SshPool, SshTarget};
+use crate::workspace::Workspace;
+
+const CLIENT_PORT: u16 = 68;
+const SERVER_PORT: u16 = 67;
+const OFFER_WAIT: Duration = Duration::from_secs(3);
+const SCAN_TIMEOUT: Duration = Duration::from_secs(30);
+const MAGIC_COOKIE: [u8; 4] = [99, 130, 83, 99];
+
+const LEASE_SCRIPT: &str = r#"
+export LC_ALL=C
+echo '@@dynamic'
+ip -4 -o addr show 2>/dev/null | awk '/ dynamic / { print $2, $4 }'
+echo '@@leases'
+for f in /run/systemd/netif/leases/*; do
+  [ -r "$f" ] || continue
+  echo "$(grep -E '^(ADDRESS|SERVER_ADDRESS|LIFETIME)=' "$f" | tr '\n' ' ')"
+done
+echo '@@end'
+"#;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DhcpOffer {
+  /// The server identifier option, or the address the offer came from
+  pub server: String,
+  pub offered: String,
+  pub lease_secs: Option<u32>,
+  pub router: Option<String>,
+  pub subnet_mask: Option<String>,
+  pub dns: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DhcpLease {
+  /// The node, as it was reached
+  pub host: String,
+  pub interface: String,
+  pub address: String,
+  pub server: Option<String>,
+  pub lease_secs: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LeaseVerdict {
+  pub node: String,
+  pub address: String,
+  pub outcome: Outcome,
+  pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DhcpReport {
+  pub offers: Vec<DhcpOffer>,
+  /// Why no DISCOVER could be sent from this machine
+  pub probe_error: Option<String>,
+  pub leases: Vec<DhcpLease>,
+  /// Lowest to highest address seen from the pool; the real pool is at
+  /// least this wide
+  pub inferred_pool: Option<DhcpRange>,
+  pub verdicts: Vec<LeaseVerdict>,
+  pub warnings: Vec<String>,
+}
+
+fn random_bytes<const N: usize>() -> Result<[u8; N], String> {
+  let mut bytes = [0u8; N];
+  ring::rand::SecureRandom::fill(&ring::rand::SystemRandom::new(), &mut bytes)
+    .map_err(|_| "Failed to generate random bytes".to_string())?;
+  Ok(bytes)
+}
+
+fn discover_packet(xid: [u8; 4], mac: [u8; 6]) -> Vec<u8> {
+  let mut packet = vec![0u8; 240];
+  packet[0] = 1; // BOOTREQUEST
+  packet[1] = 1; // Ethernet
+  packet[2] = 6;
+  packet[4..8].copy_from_slice(&xid);
+  // Ask for a broadcast reply; there is no address to unicast it to
+  packet[10] = 0x80;
+  packet[28..34].copy_from_slice(&mac);
+  packet[236..240].copy_from_slice(&MAGIC_COOKIE);
+  packet.extend_from_slice(&[53, 1, 1]);
+  packet.extend_from_slice(&[61, 7, 1]);
+  packet.extend_from_slice(&mac);
+  packet.extend_from_slice(&[55, 5, 1, 3, 6, 51, 54]);
+  packet.push(255);
+  packet.resize(300, 0);
+  packet
+}
+
+fn addr(bytes: &[u8]) -> Option<Ipv4Addr> {
+  let octets: [u8; 4] = bytes.get(..4)?.try_into().ok()?;
+  Some(Ipv4Addr::from(octets))
+}
+
+/// An OFFER answering `xid`, if `packet` is one.
+fn parse_offer(packet: &[u8], xid: [u8; 4], from: Ipv4Addr) -> Option<DhcpOffer> {
+  if packet.len() < 240 || packet[0] != 2 || packet[4..8] != xid || packet[236..240] != MAGIC_COOKIE {
+    return None;
+  }
+  let mut offer = DhcpOffer {
+    server: from.to_string(),
+    offered: addr(&packet[16..20])?.to_string(),
+    lease_secs: None,
+    router: None,
+    subnet_mask: None,
+    dns: Vec::new(),
+  };
+  let mut is_offer = false;
+  let mut options = &packet[240..];
+  while let [code, rest @ ..] = options {
+    match code {
+      0 => {
+        options = rest;
+        continue;
+      }
+      255 => break,
+      _ => {}
+    }
+    let (&len, rest) = rest.split_first()?;
+    let value = rest.get(..usize::from(len))?;
+    match code {
+      53 => is_offer = value.first() == Some(&2),
+      54 => offer.server = addr(value)?.to_string(),
+      51 => offer.lease_secs = value.try_into().ok().map(u32::from_be_bytes),
+      1 => offer.subnet_mask = addr(value).map(|a| a.to_string()),
+      3 => offer.router = addr(value).map(|a| a.to_string()),
+      6 => offer.dns = value.chunks(4).filter_map(addr).map(|a| a.to_string()).collect(),
+      _ => {}
+    }
+    options = &rest[usize::from(len)..];
+  }
+  is_offer.then_some(offer)
+}
+
+/// Broadcast a DISCOVER and collect the offers that come back.
+fn discover() -> Result<Vec<DhcpOffer>, String> {
+  let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, CLIENT_PORT)).map_err(|e| {
+    format!(
+      "Cannot listen on UDP port {} for DHCP offers ({}); the check needs root or a free port",
+      CLIENT_PORT, e
+    )
+  })?;
+  socket
+    .set_broadcast(true)
+    .map_err(|e| format!("Failed to enable broadcast: {}", e))?;
+  let xid = random_bytes::<4>()?;
+  let mut mac = random_bytes::<6>()?;
+  // Locally administered, unicast
+  mac[0] = (mac[0] & 0xfc) | 0x02;
+  socket
+    .send_to(&discover_packet(xid, mac), (Ipv4Addr::BROADCAST, SERVER_PORT))
+    .map_err(|e| format!("Failed to send DHCPDISCOVER: {}", e))?;
+
+  let started = Instant::now();
+  let mut offers: Vec<DhcpOffer> = Vec::new();
+  let mut buf = [0u8; 1500];
+  while let Some(left) = OFFER_WAIT.checked_sub(started.elapsed()).filter(|l| !l.is_zero()) {
+    socket.set_read_timeout(Some(left)).map_err(|e| e.to_string())?;
+    let Ok((len, from)) = socket.recv_from(&mut buf) else {
+      break;
+    };
+    let std::net::SocketAddr::V4(from) = from else {
+      continue;
+    };
+    if let Some(offer) = parse_offer(&buf[..len], xid, *from.ip()) {
+      if !offers.iter().any(|o| o.server == offer.server) {
+        offers.push(offer);
+      }
+    }
+  }
+  Ok(offers)
+}
+
+fn parse_leases(host: &str, output: &str) -> Vec<DhcpLease> {
+  let sections = sections(output);
+  let empty = Vec::new();
+  let files: Vec<Vec<(&str, &str)>> = sections
+    .get("leases")
+    .unwrap_or(&empty)
+    .iter()
+    .map(|line| line.split_whitespace().filter_map(|kv| kv.split_once('=')).collect())
+    .collect();
+  sections
+    .get("dynamic")
+    .unwrap_or(&empty)
+    .iter()
+    .filter_map(|line| {
+      let (interface, cidr) = line.trim().split_once(' ')?;
+      let address = cidr.split('/').next()?.to_string();
+      let file = files.iter().find(|f| f.contains(&("ADDRESS", address.as_str())));
+      let value = |key: &str| {
+        file
+          .and_then(|f| f.iter().find(|(k, _)| *k == key))
+          .map(|(_, v)| v.to_string())
+      };
+      Some(DhcpLease {
+        host: host.to_string(),
+        interface: interface.to_string(),
+        server: value("SERVER_ADDRESS"),
+        lease_secs: value("LIFETIME").and_then(|v| v.parse().ok()),
+        address,
+      })
+    })
+    .collect()
+}
+
+fn verdict(
+  planned: &PlannedAddress,
+  offers: &[DhcpOffer],
+  leases: &[DhcpLease],
+  pool: Option<(u32, u32)>,
+) -> LeaseVerdict {
+  let address = planned.address.trim();
+  let result = |outcome, message: String| LeaseVerdict {
+    node: planned.node.clone(),
+    address: address.to_string(),
+    outcome,
+    message,
+  };
+  let Ok(parsed) = address.parse::<Ipv4Addr>() else {
+    return result(Outcome::Fail, "Not a valid IPv4 address".to_string());
+  };
+  let own = |lease: &DhcpLease| planned.current_address.as_deref().map(str::trim) == Some(lease.host.as_str());
+  if let Some(lease) = leases.iter().find(|l| l.address == address && !own(l)) {
+    return result(
+      Outcome::Fail,
+      format!("Currently leased by DHCP to {} on {}", lease.host, lease.interface),
+    );
+  }
+  if let Some(offer) = offers.iter().find(|o| o.offered == address) {
+    return result(
+      Outcome::Fail,
+      format!("The DHCP server {} is offering this address right now", offer.server),
+    );
+  }
+  if leases.iter().any(|l| l.address == address && own(l)) {
+    return result(
+      Outcome::Warn,
+      "The node holds this address through a DHCP lease; reserve it on the router or pick one outside the pool"
+        .to_string(),
+    );
+  }
+  let Some((start, end)) = pool else {
+    return result(
+      Outcome::Skipped,
+      "No DHCP offers or leases seen to infer the pool from".to_string(),
+    );
+  };
+  if (start..=end).contains(&u32::from(parsed)) {
+    return result(
+      Outcome::Warn,
+      format!(
+        "Inside the DHCP pool seen in use ({} - {}); the router may hand it out",
+        Ipv4Addr::from(start),
+        Ipv4Addr::from(end)
+      ),
+    );
+  }
+  result(Outcome::Pass, "Outside the DHCP pool seen in use".to_string())
+}
+
+/// Infer the DHCP pool on `subnet` from an offer and the nodes' leases and
+/// check the planned static `addresses` against it.
+#[tauri::command]
+pub async fn check_dhcp_conflicts(
+  app: AppHandle,
+  subnet: String,
+  hosts: Vec<SshTarget>,
+  addresses: Vec<PlannedAddress>,
+) -> Result<DhcpReport, String> {
+  let subnet = Subnet::parse(&subnet)?;
+  for target in &hosts {
+    target.validate()?;
+  }
+  tauri::async_runtime::spawn_blocking(move || {
+    let workspace = app.state::<Workspace>();
+    let workspace: &Workspace = &workspace;
+    let pool = app.state::<SshPool>();
+    let pool: &SshPool = &pool;
+    let mut warnings = Vec::new();
+
+    let (probe, scans) = std::thread::scope(|scope| {
+      let probe = scope.spawn(discover);
+      let handles: Vec<_> = hosts
+        .iter()
+        .map(|target| {
+          scope.spawn(move || {
+            let output = ssh::run_script(workspace, pool, target, LEASE_SCRIPT, SCAN_TIMEOUT)?;
+            if !output.stdout.contains("@@end") {
+              return Err(format!("Scan did not complete: {}", output.stderr.trim()));
+            }
+            Ok(parse_leases(&target.host, &output.stdout))
+          })
+        })
+        .collect();
+      let scans: Vec<Result<Vec<DhcpLease>, String>> = handles
+        .into_iter()
+        .map(|h| h.join().unwrap_or_else(|_| Err("Scan panicked".to_string())))
+        .collect();
+      (
+        probe.join().unwrap_or_else(|_| Err("DHCP probe panicked".to_string())),
+        scans,
+      )
+    });
+
+    let mut leases = Vec::new();
+    for (target, scan) in hosts.iter().zip(scans) {
+      match scan {
+        Ok(found) => leases.extend(found),
+        Err(e) => warnings.push(format!("Could not read the DHCP leases on {}: {}", target.host, e)),
+      }
+    }
+    let (offers, probe_error) = match probe {
+      Ok(offers) => (offers, None),
+      Err(e) => (Vec::new(), Some(e)),
+    };
+    let servers: BTreeSet<&str> = offers
+      .iter()
+      .map(|o| o.server.as_str())
+      .chain(leases.iter().filter_map(|l| l.server.as_deref()))
+      .collect();
+    if servers.len() > 1 {
+      warnings.push(format!(
+        "More than one DHCP server answered ({}); devices may get addresses from either",
+        servers.into_iter().collect::<Vec<_>>().join(", ")
+      ));
+    }
+
+    let seen: Vec<u32> = offers
+      .iter()
+      .map(|o| o.offered.as_str())
+      .chain(leases.iter().map(|l| l.address.as_str()))
+      .filter_map(|a| a.parse::<Ipv4Addr>().ok())
+      .filter(|a| subnet.contains(*a))
+      .map(u32::from)
+      .collect();
+    let range = seen.iter().min().zip(seen.iter().max()).map(|(s, e)| (*s, *e));
+    let verdicts = addresses
+      .iter()
+      .map(|planned| verdict(planned, &offers, &leases, range))
+      .collect();
+
+    DhcpReport {
+      inferred_pool: range.map(|(start, end)| DhcpRange {
+        start: Ipv4Addr::from(start).to_string(),
+        end: Ipv4Addr::from(end).to_string(),
+      }),
+      offers,
+      probe_error,
+      leases,
+      verdicts,
+      warnings,
+    }
+  })
+  .await
+  .map_err(|e| e.to_string())
+}
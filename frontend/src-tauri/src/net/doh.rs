@@ -0,0 +1,71 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! DNS lookups through public DNS-over-HTTPS resolvers.
+//!
+//! Asking Cloudflare's and Google's JSON APIs directly shows what the rest
+//! of the internet sees, bypassing the local resolver, its cache and any
+//! split-horizon records on the LAN.
+
+use serde_json::Value;
+
+/// Resolver names and their JSON API endpoints.
+pub const RESOLVERS: &[(&str, &str)] = &[
+  ("Cloudflare", "https://cloudflare-dns.com/dns-query"),
+  ("Google", "https://dns.google/resolve"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordType {
+  A,
+  Txt,
+}
+
+impl RecordType {
+  fn name(self) -> &'static str {
+    match self {
+      RecordType::A => "A",
+      RecordType::Txt => "TXT",
+    }
+  }
+
+  fn code(self) -> u64 {
+    match self {
+      RecordType::A => 1,
+      RecordType::Txt => 16,
+    }
+  }
+}
+
+/// The `record_type` records `resolver` returns for `name`; TXT values
+/// without their quotes. CNAMEs are followed by the resolver and left out.
+pub fn query(agent: &ureq::Agent, resolver: &str, name: &str, record_type: RecordType) -> Result<Vec<String>, String> {
+  let body: Value = agent
+    .get(resolver)
+    .query("name", name)
+    .query("type", record_type.name())
+    .set("Accept", "application/dns-json")
+    .call()
+    .map_err(|e| e.to_string())?
+    .into_json()
+    .map_err(|e| format!("Unexpected resolver response: {}", e))?;
+  Ok(
+    body["Answer"]
+      .as_array()
+      .map(|answers| {
+        answers
+          .iter()
+          .filter(|a| a["type"] == record_type.code())
+          .filter_map(|a| a["data"].as_str())
+          .map(|data| match record_type {
+            // Long values come back as several quoted strings
+            RecordType::Txt => data.split("\" \"").collect::<String>().trim_matches('"').to_string(),
+            _ => data.to_string(),
+          })
+          .collect()
+      })
+      .unwrap_or_default(),
+  )
+}
@@ -0,0 +1,245 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Proving the user controls the cluster domain before Let's Encrypt is
+//! involved.
+//!
+//! `create_domain_challenge` picks a random value for a TXT record at
+//! `_thinkube-challenge.<domain>`. The user creates the record by hand, or
+//! `publish_domain_challenge` creates it through the Cloudflare API.
+//! `verify_domain_challenge` then asks public DNS-over-HTTPS resolvers for
+//! the record every few seconds, emitting a `domain-verification` event per
+//! round, until one of them returns the value or the time runs out. ACME
+//! rate limits are never spent on a domain that can't pass a DNS-01
+//! challenge.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tracing::{info, warn};
+
+use super::doh::{self, RecordType};
+use crate::tokens::cloudflare::{self, CreatedRecord};
+use crate::validation;
+
+pub const EVENT: &str = "domain-verification";
+
+const RECORD_PREFIX: &str = "_thinkube-challenge";
+const POLL_INTERVAL: Duration = Duration::from_secs(10);
+const DEFAULT_TIMEOUT_SECS: u64 = 300;
+const MAX_TIMEOUT_SECS: u64 = 3600;
+const QUERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DomainChallenge {
+  pub domain: String,
+  /// Name of the TXT record to create
+  pub record_name: String,
+  /// Value the TXT record must hold
+  pub value: String,
+  /// Seconds since the Unix epoch
+  pub created_at: u64,
+  /// Created through the Cloudflare API
+  pub published: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ResolverAnswer {
+  pub resolver: String,
+  pub found: bool,
+  /// TXT values the resolver returned for the record
+  pub values: Vec<String>,
+  pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VerificationRound {
+  pub domain: String,
+  pub attempt: u32,
+  pub verified: bool,
+  pub answers: Vec<ResolverAnswer>,
+  pub elapsed_secs: u64,
+}
+
+struct Pending {
+  challenge: DomainChallenge,
+  record: Option<CreatedRecord>,
+}
+
+/// Challenges by domain, kept for the session so retrying the verification
+/// doesn't change the value the user already put in DNS.
+#[derive(Default)]
+pub struct DomainChallenges(Mutex<HashMap<String, Pending>>);
+
+fn normalize(domain: &str) -> Result<String, String> {
+  let domain = domain.trim().trim_end_matches('.').to_ascii_lowercase();
+  if !validation::is_valid_domain(&domain) {
+    return Err(format!("Invalid domain: {}", domain));
+  }
+  Ok(domain)
+}
+
+fn pending(challenges: &DomainChallenges, domain: &str) -> Result<DomainChallenge, String> {
+  challenges
+    .0
+    .lock()
+    .map_err(|e| e.to_string())?
+    .get(domain)
+    .map(|p| p.challenge.clone())
+    .ok_or_else(|| format!("No challenge created for {}", domain))
+}
+
+fn ask_resolvers(agent: &ureq::Agent, challenge: &DomainChallenge) -> Vec<ResolverAnswer> {
+  doh::RESOLVERS
+    .iter()
+    .map(|(resolver, url)| match doh::query(agent, url, &challenge.record_name, RecordType::Txt) {
+      Ok(values) => ResolverAnswer {
+        resolver: resolver.to_string(),
+        found: values.contains(&challenge.value),
+        values,
+        error: None,
+      },
+      Err(e) => ResolverAnswer {
+        resolver: resolver.to_string(),
+        found: false,
+        values: Vec::new(),
+        error: Some(e),
+      },
+    })
+    .collect()
+}
+
+/// Start (or, with `renew`, restart) verification of `domain`.
+#[tauri::command]
+pub fn create_domain_challenge(
+  challenges: State<'_, DomainChallenges>,
+  domain: String,
+  renew: Option<bool>,
+) -> Result<DomainChallenge, String> {
+  let domain = normalize(&domain)?;
+  let mut challenges = challenges.0.lock().map_err(|e| e.to_string())?;
+  if let Some(existing) = challenges.get(&domain).filter(|_| !renew.unwrap_or(false)) {
+    return Ok(existing.challenge.clone());
+  }
+  let challenge = DomainChallenge {
+    record_name: format!("{}.{}", RECORD_PREFIX, domain),
+    value: crate::backend::random_hex(24)?,
+    created_at: SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .map(|d| d.as_secs())
+      .unwrap_or_default(),
+    published: false,
+    domain: domain.clone(),
+  };
+  challenges.insert(
+    domain,
+    Pending {
+      challenge: challenge.clone(),
+      record: None,
+    },
+  );
+  Ok(challenge)
+}
+
+/// Create the challenge's TXT record through the Cloudflare API.
+#[tauri::command]
+pub async fn publish_domain_challenge(
+  app: AppHandle,
+  domain: String,
+  cloudflare_token: String,
+) -> Result<DomainChallenge, String> {
+  let domain = normalize(&domain)?;
+  let token = cloudflare_token.trim().to_string();
+  crate::redact::register(&token);
+  let challenge = pending(&app.state::<DomainChallenges>(), &domain)?;
+  let record = {
+    let challenge = challenge.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+      cloudflare::create_txt_record(&token, &challenge.domain, &challenge.record_name, &challenge.value)
+    })
+    .await
+    .map_err(|e| e.to_string())??
+  };
+
+  let state = app.state::<DomainChallenges>();
+  let mut challenges = state.0.lock().map_err(|e| e.to_string())?;
+  let pending = challenges
+    .get_mut(&domain)
+    .ok_or_else(|| format!("No challenge created for {}", domain))?;
+  pending.challenge.published = true;
+  pending.record = Some(record);
+  Ok(pending.challenge.clone())
+}
+
+/// Poll the resolvers until one of them returns the challenge value, for at
+/// most `timeout_secs` (five minutes by default).
+#[tauri::command]
+pub async fn verify_domain_challenge(
+  app: AppHandle,
+  domain: String,
+  timeout_secs: Option<u64>,
+) -> Result<VerificationRound, String> {
+  let domain = normalize(&domain)?;
+  let challenge = pending(&app.state::<DomainChallenges>(), &domain)?;
+  let timeout = Duration::from_secs(timeout_secs.unwrap_or(DEFAULT_TIMEOUT_SECS).min(MAX_TIMEOUT_SECS));
+
+  tauri::async_runtime::spawn_blocking(move || {
+    let agent = ureq::AgentBuilder::new().timeout(QUERY_TIMEOUT).build();
+    let started = Instant::now();
+    let mut attempt = 0;
+    loop {
+      attempt += 1;
+      let answers = ask_resolvers(&agent, &challenge);
+      let round = VerificationRound {
+        domain: domain.clone(),
+        attempt,
+        verified: answers.iter().any(|a| a.found),
+        answers,
+        elapsed_secs: started.elapsed().as_secs(),
+      };
+      let _ = app.emit(EVENT, round.clone());
+      if round.verified {
+        info!("Verified control of {} after {} attempt(s)", domain, attempt);
+        return Ok(round);
+      }
+      if started.elapsed() + POLL_INTERVAL > timeout {
+        warn!(
+          "No resolver returned the challenge for {} within {}s",
+          domain,
+          timeout.as_secs()
+        );
+        return Ok(round);
+      }
+      std::thread::sleep(POLL_INTERVAL);
+    }
+  })
+  .await
+  .map_err(|e| e.to_string())?
+}
+
+/// Forget the challenge for `domain`, deleting its TXT record if it was
+/// published through Cloudflare and the token is given.
+#[tauri::command]
+pub async fn clear_domain_challenge(
+  app: AppHandle,
+  domain: String,
+  cloudflare_token: Option<String>,
+) -> Result<(), String> {
+  let domain = normalize(&domain)?;
+  let removed = app
+    .state::<DomainChallenges>()
+    .0
+    .lock()
+    .map_err(|e| e.to_string())?
+    .remove(&domain);
+  let (Some(record), Some(token)) = (removed.and_then(|p| p.record), cloudflare_token) else {
+    return Ok(());
+  };
+  tauri::async_runtime::spawn_blocking(move || cloudflare::delete_record(token.trim(), &record))
+    .await
+    .map_err(|e| e.to_string())?
+}
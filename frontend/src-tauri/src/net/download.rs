@@ -0,0 +1,156 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Downloading playbook bundles and helper binaries.
+//!
+//! `download_artifact` fetches a file over HTTPS into the profile's cache,
+//! along with its detached `.minisig`, and only keeps it once
+//! [`verify`](crate::verify::verify) accepts the signature: an unsigned
+//! or tampered download is deleted and the command fails. The file is
+//! written as `<name>.part` until then, so nothing half-downloaded or
+//! unverified ever sits under its real name. Progress goes out as
+//! `download-progress` events.
+
+use serde::Serialize;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+use tracing::{info, warn};
+
+use crate::{cancel, profiles};
+use crate::verify::{self, VerifiedArtifact};
+
+pub const PROGRESS_EVENT: &str = "download-progress";
+const TIMEOUT: Duration = Duration::from_secs(30);
+/// Signature files are a few hundred bytes
+const MAX_SIGNATURE_BYTES: u64 = 16 * 1024;
+const PROGRESS_EVERY: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DownloadProgress {
+  pub url: String,
+  pub received: u64,
+  pub total: Option<u64>,
+}
+
+fn downloads_dir(app: &AppHandle) -> Result<PathBuf, String> {
+  Ok(profiles::cache_dir(app)?.join("downloads"))
+}
+
+/// The last path segment of `url`, which must be a plain file name.
+fn file_name(url: &str) -> Result<String, String> {
+  let path = url.split(['?', '#']).next().unwrap_or_default();
+  let name = path.rsplit('/').next().unwrap_or_default();
+  if name.is_empty() || name.starts_with('.') || name.contains('\\') {
+    return Err(format!("Can't tell the file name from {}", url));
+  }
+  Ok(name.to_string())
+}
+
+fn part_path(path: &Path) -> PathBuf {
+  let mut name = path.as_os_str().to_owned();
+  name.push(".part");
+  PathBuf::from(name)
+}
+
+fn fetch(app: &AppHandle, agent: &ureq::Agent, url: &str, dest: &Path, limit: Option<u64>) -> Result<u64, String> {
+  let response = agent
+    .get(url)
+    .call()
+    .map_err(|e| format!("Failed to download {}: {}", url, e))?;
+  let total = response.header("Content-Length").and_then(|l| l.parse::<u64>().ok());
+  if let (Some(total), Some(limit)) = (total, limit) {
+    if total > limit {
+      return Err(format!("{} is larger than expected", url));
+    }
+  }
+  let mut reader = response.into_reader().take(limit.map_or(u64::MAX, |l| l + 1));
+  let mut file = std::fs::File::create(dest).map_err(|e| format!("Failed to create {}: {}", dest.display(), e))?;
+  let mut buf = [0u8; 64 * 1024];
+  let mut received = 0u64;
+  let mut reported = Instant::now();
+  let operation = cancel::begin(app, &format!("download of {}", url));
+  loop {
+    if operation.is_cancelled() {
+      return Err(format!("Download of {} cancelled", url));
+    }
+    let n = reader
+      .read(&mut buf)
+      .map_err(|e| format!("Failed to download {}: {}", url, e))?;
+    if n == 0 {
+      break;
+    }
+    file
+      .write_all(&buf[..n])
+      .map_err(|e| format!("Failed to write {}: {}", dest.display(), e))?;
+    received += n as u64;
+    if limit.is_none() && reported.elapsed() >= PROGRESS_EVERY {
+      reported = Instant::now();
+      let _ = app.emit(
+        PROGRESS_EVENT,
+        DownloadProgress {
+          url: url.to_string(),
+          received,
+          total,
+        },
+      );
+    }
+  }
+  if limit.is_some_and(|limit| received > limit) {
+    return Err(format!("{} is larger than expected", url));
+  }
+  file.sync_all().map_err(|e| e.to_string())?;
+  Ok(received)
+}
+
+fn download(app: &AppHandle, url: &str) -> Result<VerifiedArtifact, String> {
+  if !url.starts_with("https://") {
+    return Err("Artifacts are only downloaded over https".to_string());
+  }
+  let dir = downloads_dir(app)?;
+  std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+  let name = file_name(url)?;
+  let dest = dir.join(&name);
+  let part = part_path(&dest);
+  let sig = verify::signature_path(&part);
+
+  let agent = ureq::AgentBuilder::new().timeout_connect(TIMEOUT).build();
+  info!("Downloading {}", url);
+  let sig_url = format!("{}.{}", url, verify::SIGNATURE_EXTENSION);
+  let result = fetch(app, &agent, url, &part, None)
+    .and_then(|_| fetch(app, &agent, &sig_url, &sig, Some(MAX_SIGNATURE_BYTES)))
+    .and_then(|_| verify::verify_as(&part, &name));
+  let verified = match result {
+    Ok(verified) => verified,
+    Err(e) => {
+      warn!("Discarding the download of {}: {}", url, e);
+      let _ = std::fs::remove_file(&part);
+      let _ = std::fs::remove_file(&sig);
+      return Err(e);
+    }
+  };
+
+  std::fs::rename(&sig, verify::signature_path(&dest)).map_err(|e| e.to_string())?;
+  std::fs::rename(&part, &dest).map_err(|e| e.to_string())?;
+  info!(
+    "Downloaded {} ({}, key {})",
+    dest.display(),
+    verified.sha256,
+    verified.key_id
+  );
+  Ok(VerifiedArtifact {
+    path: dest.display().to_string(),
+    ..verified
+  })
+}
+
+/// Download `url` and its signature; the file is only kept if it verifies.
+#[tauri::command]
+pub async fn download_artifact(app: AppHandle, url: String) -> Result<VerifiedArtifact, String> {
+  tauri::async_runtime::spawn_blocking(move || download(&app, url.trim()))
+    .await
+    .map_err(|e| e.to_string())?
+}
@@ -0,0 +1,282 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! How much an install will download, and roughly how long that takes.
+//!
+//! `estimate_downloads` walks the artifacts an install fetches for the
+//! selected configuration: once for the installer host, once per node, or
+//! once per GPU node. Sizes come from where the artifact lives when that
+//! can be asked cheaply (the snap store's info API, GitHub's repository
+//! size, a `HEAD` request's `Content-Length`) and from a fixed estimate
+//! otherwise, and each item says which. The bandwidth is sampled from an
+//! Ubuntu archive the same way `rank_mirrors` does it. The nodes share the
+//! uplink, so the time is the whole total over that one rate.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::time::Duration;
+use tracing::{debug, info};
+
+use super::mirrors::{throughput_kib_s, UBUNTU_CODENAME};
+use crate::bundle::DEFAULT_SNAPS;
+use crate::playbooks;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+const DEFAULT_MIRROR: &str = "http://archive.ubuntu.com/ubuntu";
+const MIB: u64 = 1024 * 1024;
+/// The driver release the GPU nodes get, at least `MIN_DRIVER_MAJOR`
+const NVIDIA_DRIVER: &str = "580.95.05";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Scope {
+  /// Downloaded once, on the installer host
+  Once,
+  PerNode,
+  PerGpuNode,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SizeSource {
+  SnapStore,
+  Github,
+  Head,
+  /// A fixed estimate; the artifact's size wasn't asked for or couldn't be
+  Estimate,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DownloadItem {
+  pub name: String,
+  pub scope: Scope,
+  pub count: u32,
+  pub bytes_each: u64,
+  pub bytes: u64,
+  pub source: SizeSource,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DownloadEstimate {
+  pub items: Vec<DownloadItem>,
+  pub total_bytes: u64,
+  pub node_count: u32,
+  pub gpu_node_count: u32,
+  /// `None` when the archive couldn't be reached
+  pub throughput_kib_s: Option<u64>,
+  pub eta_secs: Option<u64>,
+}
+
+enum Lookup {
+  Snap(&'static str),
+  Playbooks,
+  Head(fn(&str) -> String),
+  Fixed,
+}
+
+struct Artifact {
+  name: &'static str,
+  scope: Scope,
+  lookup: Lookup,
+  /// Used when the lookup fails, and for `Fixed`
+  fallback: u64,
+}
+
+fn nvidia_url(arch: &str) -> String {
+  let platform = if arch == "arm64" { "aarch64" } else { "Linux-x86_64" };
+  let file_arch = if arch == "arm64" { "aarch64" } else { "x86_64" };
+  format!(
+    "https://us.download.nvidia.com/XFree86/{}/{}/NVIDIA-Linux-{}-{}.run",
+    platform, NVIDIA_DRIVER, file_arch, NVIDIA_DRIVER
+  )
+}
+
+fn artifacts() -> Vec<Artifact> {
+  let mut artifacts = vec![
+    Artifact {
+      name: "Thinkube playbooks",
+      scope: Scope::Once,
+      lookup: Lookup::Playbooks,
+      fallback: 60 * MIB,
+    },
+    Artifact {
+      name: "Ansible and backend Python packages",
+      scope: Scope::Once,
+      lookup: Lookup::Fixed,
+      fallback: 90 * MIB,
+    },
+    Artifact {
+      name: "Ubuntu package updates",
+      scope: Scope::PerNode,
+      lookup: Lookup::Fixed,
+      fallback: 400 * MIB,
+    },
+  ];
+  artifacts.extend(DEFAULT_SNAPS.iter().map(|snap| Artifact {
+    name: snap,
+    scope: Scope::PerNode,
+    lookup: Lookup::Snap(snap),
+    fallback: 200 * MIB,
+  }));
+  artifacts.extend([
+    Artifact {
+      name: "Platform container images",
+      scope: Scope::PerNode,
+      lookup: Lookup::Fixed,
+      fallback: 3 * 1024 * MIB,
+    },
+    Artifact {
+      name: "NVIDIA driver",
+      scope: Scope::PerGpuNode,
+      lookup: Lookup::Head(nvidia_url),
+      fallback: 400 * MIB,
+    },
+    Artifact {
+      name: "CUDA container toolkit and GPU operator images",
+      scope: Scope::PerGpuNode,
+      lookup: Lookup::Fixed,
+      fallback: 1536 * MIB,
+    },
+  ]);
+  artifacts
+}
+
+/// `download.size` of the latest/stable revision for `arch`.
+fn snap_size(agent: &ureq::Agent, name: &str, arch: &str) -> Option<u64> {
+  let info: Value = agent
+    .get(&format!("https://api.snapcraft.io/v2/snaps/info/{}", name))
+    .query("architecture", arch)
+    .query("fields", "download")
+    .set("Snap-Device-Series", "16")
+    .call()
+    .ok()?
+    .into_json()
+    .ok()?;
+  let channels = info["channel-map"].as_array()?;
+  let stable = |c: &&Value| c["channel"]["risk"] == "stable" && c["channel"]["architecture"] == arch;
+  let latest = channels
+    .iter()
+    .filter(stable)
+    .find(|c| c["channel"]["track"] == "latest")
+    .or_else(|| channels.iter().find(stable))?;
+  latest["download"]["size"].as_u64()
+}
+
+/// GitHub reports a repository's size in KiB.
+fn playbooks_size(agent: &ureq::Agent) -> Option<u64> {
+  let repo = playbooks::repo_url();
+  let path = repo.strip_prefix("https://github.com/")?.trim_end_matches(".git");
+  let info: Value = agent
+    .get(&format!("https://api.github.com/repos/{}", path))
+    .set("Accept", "application/vnd.github+json")
+    .call()
+    .ok()?
+    .into_json()
+    .ok()?;
+  info["size"].as_u64().map(|kib| kib * 1024)
+}
+
+fn head_size(agent: &ureq::Agent, url: &str) -> Option<u64> {
+  agent
+    .head(url)
+    .call()
+    .ok()?
+    .header("Content-Length")
+    .and_then(|l| l.parse().ok())
+}
+
+fn node_arch(node: &Value) -> &'static str {
+  let arch = node["architecture"]
+    .as_str()
+    .or_else(|| node["hardware"]["architecture"].as_str())
+    .unwrap_or_default();
+  if matches!(arch, "arm64" | "aarch64") {
+    "arm64"
+  } else {
+    "amd64"
+  }
+}
+
+/// The nodes of the wizard's `{ nodes, ... }` or an exported document.
+fn nodes(config: &Value) -> Vec<Value> {
+  config["nodes"].as_array().cloned().unwrap_or_default()
+}
+
+/// Bandwidth to an Ubuntu archive, from a large index file.
+fn bandwidth(agent: &ureq::Agent, mirror: &str) -> Option<u64> {
+  let url = format!(
+    "{}/dists/{}/Contents-amd64.gz",
+    mirror.trim_end_matches('/'),
+    UBUNTU_CODENAME
+  );
+  throughput_kib_s(agent.get(&url).call().ok()?)
+}
+
+fn estimate(config: &Value, mirror: Option<&str>) -> DownloadEstimate {
+  let agent = ureq::AgentBuilder::new().timeout(TIMEOUT).build();
+  let nodes = nodes(config);
+  let gpu_nodes: Vec<&Value> = nodes.iter().filter(|n| n["hasGPU"].as_bool() == Some(true)).collect();
+  // Sizes are looked up for the architecture most nodes have
+  let arm = nodes.iter().filter(|n| node_arch(n) == "arm64").count();
+  let arch = if arm * 2 > nodes.len() { "arm64" } else { "amd64" };
+
+  let mut items = Vec::new();
+  for artifact in artifacts() {
+    let count = match artifact.scope {
+      Scope::Once => 1,
+      Scope::PerNode => nodes.len() as u32,
+      Scope::PerGpuNode => gpu_nodes.len() as u32,
+    };
+    if count == 0 {
+      continue;
+    }
+    let looked_up = match &artifact.lookup {
+      Lookup::Snap(name) => snap_size(&agent, name, arch).map(|s| (s, SizeSource::SnapStore)),
+      Lookup::Playbooks => playbooks_size(&agent).map(|s| (s, SizeSource::Github)),
+      Lookup::Head(url) => head_size(&agent, &url(arch)).map(|s| (s, SizeSource::Head)),
+      Lookup::Fixed => None,
+    };
+    if looked_up.is_none() && !matches!(artifact.lookup, Lookup::Fixed) {
+      debug!("Couldn't look up the size of {}; using the estimate", artifact.name);
+    }
+    let (bytes_each, source) = looked_up.unwrap_or((artifact.fallback, SizeSource::Estimate));
+    items.push(DownloadItem {
+      name: artifact.name.to_string(),
+      scope: artifact.scope,
+      count,
+      bytes_each,
+      bytes: bytes_each * u64::from(count),
+      source,
+    });
+  }
+
+  let total_bytes = items.iter().map(|i| i.bytes).sum();
+  let throughput = bandwidth(&agent, mirror.unwrap_or(DEFAULT_MIRROR)).filter(|&kib| kib > 0);
+  let eta_secs = throughput.map(|kib| total_bytes / (kib * 1024));
+  info!(
+    "Download estimate: {} MiB for {} nodes ({} with GPUs), {:?} KiB/s",
+    total_bytes / MIB,
+    nodes.len(),
+    gpu_nodes.len(),
+    throughput
+  );
+  DownloadEstimate {
+    items,
+    total_bytes,
+    node_count: nodes.len() as u32,
+    gpu_node_count: gpu_nodes.len() as u32,
+    throughput_kib_s: throughput,
+    eta_secs,
+  }
+}
+
+/// Size and time estimate for installing `config`, measuring bandwidth
+/// against `mirror` (the apt mirror picked in the wizard) when given.
+#[tauri::command]
+pub async fn estimate_downloads(config: Value, mirror: Option<String>) -> Result<DownloadEstimate, String> {
+  tauri::async_runtime::spawn_blocking(move || estimate(&config, mirror.as_deref()))
+    .await
+    .map_err(|e| e.to_string())
+}
@@ -0,0 +1,338 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Checking that a cluster meant to be reachable from the internet is.
+//!
+//! `check_public_exposure` reports three findings separately, since each
+//! fails for a different reason and is fixed in a different place:
+//!
+//! - the public IP, asked of several services so one wrong answer (or a
+//!   multi-WAN setup handing out different addresses) is visible;
+//! - whether the domain's A records, as public resolvers see them, point at
+//!   that IP;
+//! - whether ports 80 and 443 on the public IP reach this host. A listener
+//!   answering with a random value is started on each port, and the public IP
+//!   is connected to: getting the value back proves the forwarding. Routers
+//!   that don't loop traffic back to the LAN (no NAT hairpinning) make this
+//!   look closed from inside, so a closed result says so.
+
+use serde::Serialize;
+use std::io::{ErrorKind, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::info;
+
+use super::doh::{self, RecordType};
+use crate::preflight::Outcome;
+use crate::validation;
+
+const IP_SERVICES: &[&str] = &[
+  "https://api.ipify.org",
+  "https://ifconfig.me/ip",
+  "https://icanhazip.com",
+  "https://checkip.amazonaws.com",
+];
+const DEFAULT_PORTS: &[u16] = &[80, 443];
+const TIMEOUT: Duration = Duration::from_secs(10);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IpAnswer {
+  pub service: String,
+  pub ip: Option<String>,
+  pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Finding {
+  pub outcome: Outcome,
+  pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PublicIpFinding {
+  /// What most services agree on
+  pub ip: Option<String>,
+  pub answers: Vec<IpAnswer>,
+  #[serde(flatten)]
+  pub finding: Finding,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DnsFinding {
+  pub domain: String,
+  /// A records public resolvers return
+  pub addresses: Vec<String>,
+  #[serde(flatten)]
+  pub finding: Finding,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PortFinding {
+  pub port: u16,
+  #[serde(flatten)]
+  pub finding: Finding,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExposureReport {
+  pub public_ip: PublicIpFinding,
+  /// `None` when no domain was given
+  pub dns: Option<DnsFinding>,
+  pub ports: Vec<PortFinding>,
+}
+
+fn finding(outcome: Outcome, message: impl Into<String>) -> Finding {
+  Finding {
+    outcome,
+    message: message.into(),
+  }
+}
+
+/// The public IP as most of `IP_SERVICES` see it.
+pub fn public_ip(agent: &ureq::Agent) -> PublicIpFinding {
+  let answers: Vec<IpAnswer> = IP_SERVICES
+    .iter()
+    .map(|service| {
+      let result = agent
+        .get(service)
+        .call()
+        .map_err(|e| e.to_string())
+        .and_then(|response| response.into_string().map_err(|e| e.to_string()))
+        .and_then(|body| {
+          body
+            .trim()
+            .parse::<IpAddr>()
+            .map(|ip| ip.to_string())
+            .map_err(|_| "Answer is not an IP address".to_string())
+        });
+      IpAnswer {
+        service: service.to_string(),
+        error: result.as_ref().err().cloned(),
+        ip: result.ok(),
+      }
+    })
+    .collect();
+
+  let mut counts: Vec<(&str, usize)> = Vec::new();
+  for ip in answers.iter().filter_map(|a| a.ip.as_deref()) {
+    match counts.iter_mut().find(|(seen, _)| *seen == ip) {
+      Some((_, count)) => *count += 1,
+      None => counts.push((ip, 1)),
+    }
+  }
+  counts.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+  let ip = counts.first().map(|(ip, _)| ip.to_string());
+  let finding = match (counts.len(), &ip) {
+    (0, _) => finding(
+      Outcome::Fail,
+      "No service could tell the public IP; is this host online?",
+    ),
+    (1, Some(ip)) => finding(Outcome::Pass, format!("Public IP is {}", ip)),
+    (_, Some(ip)) => finding(
+      Outcome::Warn,
+      format!(
+        "Services disagree on the public IP ({}); traffic may leave through more than one connection. Using {}",
+        counts.iter().map(|(ip, _)| *ip).collect::<Vec<_>>().join(", "),
+        ip
+      ),
+    ),
+    _ => unreachable!(),
+  };
+  PublicIpFinding { ip, answers, finding }
+}
+
+fn dns(agent: &ureq::Agent, domain: &str, public_ip: Option<&str>) -> DnsFinding {
+  let mut addresses = Vec::new();
+  let mut errors = Vec::new();
+  for (resolver, url) in doh::RESOLVERS {
+    match doh::query(agent, url, domain, RecordType::A) {
+      Ok(found) => {
+        for address in found {
+          if !addresses.contains(&address) {
+            addresses.push(address);
+          }
+        }
+      }
+      Err(e) => errors.push(format!("{}: {}", resolver, e)),
+    }
+  }
+
+  let finding = if addresses.is_empty() {
+    if errors.len() == doh::RESOLVERS.len() {
+      finding(
+        Outcome::Fail,
+        format!("Could not look up {}: {}", domain, errors.join("; ")),
+      )
+    } else {
+      finding(
+        Outcome::Fail,
+        format!("{} has no A record; point it at the public IP", domain),
+      )
+    }
+  } else {
+    match public_ip {
+      Some(ip) if addresses.iter().any(|a| a == ip) && addresses.len() == 1 => {
+        finding(Outcome::Pass, format!("{} points at the public IP {}", domain, ip))
+      }
+      Some(ip) if addresses.iter().any(|a| a == ip) => finding(
+        Outcome::Warn,
+        format!(
+          "{} points at {} as well as at the public IP {}",
+          domain,
+          addresses.join(", "),
+          ip
+        ),
+      ),
+      Some(ip) => finding(
+        Outcome::Fail,
+        format!(
+          "{} points at {}, not at the public IP {}",
+          domain,
+          addresses.join(", "),
+          ip
+        ),
+      ),
+      None => finding(Outcome::Warn, format!("{} points at {}", domain, addresses.join(", "))),
+    }
+  };
+  DnsFinding {
+    domain: domain.to_string(),
+    addresses,
+    finding,
+  }
+}
+
+/// Answers every connection on `listener` with `nonce` until `stop` is set.
+fn serve(listener: TcpListener, nonce: String, stop: Arc<AtomicBool>) {
+  let _ = listener.set_nonblocking(true);
+  std::thread::spawn(move || {
+    while !stop.load(Ordering::Relaxed) {
+      match listener.accept() {
+        Ok((mut stream, _)) => {
+          let _ = stream.set_nonblocking(false);
+          let _ = stream.set_read_timeout(Some(Duration::from_secs(1)));
+          let mut request = [0u8; 1024];
+          let _ = stream.read(&mut request);
+          let _ = write!(
+            stream,
+            "HTTP/1.0 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            nonce.len(),
+            nonce
+          );
+        }
+        Err(e) if e.kind() == ErrorKind::WouldBlock => std::thread::sleep(Duration::from_millis(50)),
+        Err(_) => break,
+      }
+    }
+  });
+}
+
+/// What comes back from `ip:port` for a plain HTTP request.
+fn fetch(ip: IpAddr, port: u16) -> std::io::Result<String> {
+  let mut stream = TcpStream::connect_timeout(&SocketAddr::new(ip, port), CONNECT_TIMEOUT)?;
+  stream.set_read_timeout(Some(CONNECT_TIMEOUT))?;
+  write!(stream, "GET / HTTP/1.0\r\nHost: {}\r\n\r\n", ip)?;
+  let mut response = Vec::new();
+  let _ = stream.take(64 * 1024).read_to_end(&mut response);
+  Ok(String::from_utf8_lossy(&response).into_owned())
+}
+
+/// Whether `port` on `ip` reaches a listener on this host.
+pub fn check_port(ip: IpAddr, port: u16) -> PortFinding {
+  let nonce = crate::backend::random_hex(16).unwrap_or_else(|_| format!("thinkube-{}", port));
+  let stop = Arc::new(AtomicBool::new(false));
+  let listening = match TcpListener::bind(SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), port)) {
+    Ok(listener) => {
+      serve(listener, nonce.clone(), stop.clone());
+      Ok(())
+    }
+    Err(e) => Err(e),
+  };
+
+  let result = fetch(ip, port);
+  stop.store(true, Ordering::Relaxed);
+
+  let finding = match (listening, result) {
+    (Ok(()), Ok(body)) if body.contains(&nonce) => {
+      finding(Outcome::Pass, format!("Port {} on {} reaches this host", port, ip))
+    }
+    (Ok(()), Ok(_)) => finding(
+      Outcome::Fail,
+      format!(
+        "Port {} on {} is answered by another device (often the router's own admin page); forward it to this host",
+        port, ip
+      ),
+    ),
+    (Ok(()), Err(e)) => finding(
+      Outcome::Warn,
+      format!(
+        "Port {} on {} did not reach this host ({}). Forward it on the router; if it is forwarded, the router may not \
+         loop traffic back to the LAN, so test from outside",
+        port, ip, e
+      ),
+    ),
+    // Something (e.g. an ingress controller) already serves the port
+    (Err(e), Ok(_)) if e.kind() == ErrorKind::AddrInUse => finding(
+      Outcome::Pass,
+      format!(
+        "Port {} on {} answers; it is already in use on this host, so which device answered can't be confirmed",
+        port, ip
+      ),
+    ),
+    (Err(e), Err(_)) if e.kind() == ErrorKind::AddrInUse => finding(
+      Outcome::Warn,
+      format!("Port {} is in use on this host but doesn't answer on {}", port, ip),
+    ),
+    (Err(e), _) if e.kind() == ErrorKind::PermissionDenied => finding(
+      Outcome::Skipped,
+      format!(
+        "Listening on port {} needs privileges this installer doesn't have, so forwarding can't be tested",
+        port
+      ),
+    ),
+    (Err(e), _) => finding(Outcome::Skipped, format!("Cannot listen on port {}: {}", port, e)),
+  };
+  PortFinding { port, finding }
+}
+
+/// Find the public IP, compare `domain`'s A records with it and test
+/// whether `ports` (80 and 443 by default) are forwarded to this host.
+#[tauri::command]
+pub async fn check_public_exposure(domain: Option<String>, ports: Option<Vec<u16>>) -> Result<ExposureReport, String> {
+  let domain = domain
+    .map(|d| d.trim().trim_end_matches('.').to_ascii_lowercase())
+    .filter(|d| !d.is_empty());
+  if let Some(domain) = &domain {
+    if !validation::is_valid_domain(domain) {
+      return Err(format!("Invalid domain: {}", domain));
+    }
+  }
+  let ports = ports.unwrap_or_else(|| DEFAULT_PORTS.to_vec());
+
+  tauri::async_runtime::spawn_blocking(move || {
+    let agent = ureq::AgentBuilder::new().timeout(TIMEOUT).build();
+    let public_ip = public_ip(&agent);
+    let dns = domain
+      .as_deref()
+      .map(|domain| dns(&agent, domain, public_ip.ip.as_deref()));
+    let ports = match public_ip.ip.as_deref().and_then(|ip| ip.parse::<IpAddr>().ok()) {
+      Some(ip) => ports.iter().map(|p| check_port(ip, *p)).collect(),
+      None => ports
+        .iter()
+        .map(|p| PortFinding {
+          port: *p,
+          finding: finding(Outcome::Skipped, "Skipped: the public IP is unknown"),
+        })
+        .collect(),
+    };
+    info!("Public exposure: {}", public_ip.finding.message);
+    Ok(ExposureReport { public_ip, dns, ports })
+  })
+  .await
+  .map_err(|e| e.to_string())?
+}
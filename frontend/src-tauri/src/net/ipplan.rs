@@ -0,0 +1,341 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Checking a static IPv4 address plan before it is written to the nodes.
+//!
+//! Each planned address must sit inside the cluster subnet, stay clear of
+//! the network, broadcast and gateway addresses and of the router's DHCP
+//! pool, and be used by one node only. Addresses that pass are then probed
+//! from this machine: one ICMP echo, followed by a look at the ARP cache,
+//! which catches hosts that drop pings but still answered the ARP request
+//! the ping triggered. A node that already holds its planned address is
+//! expected to answer.
+
+use serde::{Deserialize, Serialize};
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::process::{Command, Stdio};
+
+use crate::platform::find_program;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DhcpRange {
+  pub start: String,
+  pub end: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PlannedAddress {
+  pub node: String,
+  pub address: String,
+  /// Where the node is reachable now; it may answer on its planned address
+  pub current_address: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IpPlan {
+  /// Cluster subnet in CIDR form, e.g. `192.168.1.0/24`
+  pub subnet: String,
+  pub gateway: Option<String>,
+  pub dhcp_range: Option<DhcpRange>,
+  pub addresses: Vec<PlannedAddress>,
+  /// Probe the network for addresses already in use; on by default
+  pub probe: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AddressVerdict {
+  pub node: String,
+  pub address: String,
+  pub valid: bool,
+  pub problems: Vec<String>,
+  /// Whether something answered; `None` when not probed
+  pub in_use: Option<bool>,
+  /// Hardware address of whatever answered, from the ARP cache
+  pub mac: Option<String>,
+}
+
+/// An IPv4 network in CIDR form.
+#[derive(Clone)]
+pub struct Subnet {
+  network: u32,
+  prefix: u8,
+}
+
+impl Subnet {
+  pub fn parse(cidr: &str) -> Result<Self, String> {
+    let invalid = || format!("Invalid subnet (expected IPv4 CIDR): {}", cidr);
+    let (addr, prefix) = cidr.trim().split_once('/').ok_or_else(invalid)?;
+    let addr: Ipv4Addr = addr.parse().map_err(|_| invalid())?;
+    let prefix: u8 = prefix.parse().map_err(|_| invalid())?;
+    if prefix > 32 {
+      return Err(invalid());
+    }
+    let subnet = Self { network: 0, prefix };
+    Ok(Self {
+      network: u32::from(addr) & subnet.mask(),
+      prefix,
+    })
+  }
+
+  fn mask(&self) -> u32 {
+    u32::MAX.checked_shl(32 - u32::from(self.prefix)).unwrap_or(0)
+  }
+
+  pub fn prefix(&self) -> u8 {
+    self.prefix
+  }
+
+  pub fn network(&self) -> Ipv4Addr {
+    Ipv4Addr::from(self.network)
+  }
+
+  pub fn contains(&self, addr: Ipv4Addr) -> bool {
+    u32::from(addr) & self.mask() == self.network
+  }
+
+  pub fn overlaps(&self, other: &Subnet) -> bool {
+    let mask = self.mask() & other.mask();
+    self.network & mask == other.network & mask
+  }
+
+  fn broadcast(&self) -> u32 {
+    self.network | !self.mask()
+  }
+}
+
+impl std::fmt::Display for Subnet {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}/{}", Ipv4Addr::from(self.network), self.prefix)
+  }
+}
+
+/// An IPv6 network in CIDR form.
+pub struct Subnet6 {
+  network: u128,
+  prefix: u8,
+}
+
+impl Subnet6 {
+  pub fn parse(cidr: &str) -> Result<Self, String> {
+    let invalid = || format!("Invalid subnet (expected IPv6 CIDR): {}", cidr);
+    let (addr, prefix) = cidr.trim().split_once('/').ok_or_else(invalid)?;
+    let addr: Ipv6Addr = addr.parse().map_err(|_| invalid())?;
+    let prefix: u8 = prefix.parse().map_err(|_| invalid())?;
+    if prefix > 128 {
+      return Err(invalid());
+    }
+    let subnet = Self { network: 0, prefix };
+    Ok(Self {
+      network: u128::from(addr) & subnet.mask(),
+      prefix,
+    })
+  }
+
+  fn mask(&self) -> u128 {
+    u128::MAX.checked_shl(128 - u32::from(self.prefix)).unwrap_or(0)
+  }
+
+  pub fn prefix(&self) -> u8 {
+    self.prefix
+  }
+
+  pub fn address(&self) -> Ipv6Addr {
+    Ipv6Addr::from(self.network)
+  }
+
+  pub fn contains(&self, addr: Ipv6Addr) -> bool {
+    u128::from(addr) & self.mask() == self.network
+  }
+
+  pub fn overlaps(&self, other: &Subnet6) -> bool {
+    let mask = self.mask() & other.mask();
+    self.network & mask == other.network & mask
+  }
+}
+
+impl std::fmt::Display for Subnet6 {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}/{}", Ipv6Addr::from(self.network), self.prefix)
+  }
+}
+
+fn parse_addr(value: &str, what: &str) -> Result<Ipv4Addr, String> {
+  value
+    .trim()
+    .parse()
+    .map_err(|_| format!("Invalid {} address: {}", what, value))
+}
+
+/// Send one echo request to `addr`, waiting up to a second for the reply.
+fn ping(addr: Ipv4Addr) -> bool {
+  let Some(ping) = find_program("ping", &["/sbin/ping", "/bin/ping"]) else {
+    return false;
+  };
+  let mut cmd = Command::new(ping);
+  cmd.args(["-c", "1"]);
+  // Linux takes -W in seconds; macOS takes -t in seconds (its -W is in ms)
+  if cfg!(target_os = "macos") {
+    cmd.args(["-t", "1"]);
+  } else {
+    cmd.args(["-W", "1"]);
+  }
+  cmd
+    .arg(addr.to_string())
+    .stdin(Stdio::null())
+    .stdout(Stdio::null())
+    .stderr(Stdio::null())
+    .status()
+    .map(|s| s.success())
+    .unwrap_or(false)
+}
+
+/// The ARP cache entry for `addr`, if it resolved to a hardware address.
+fn arp_entry(addr: Ipv4Addr) -> Option<String> {
+  let addr = addr.to_string();
+  let output = match find_program("ip", &["/sbin/ip", "/usr/sbin/ip"]) {
+    // `192.168.1.5 dev eth0 lladdr aa:bb:cc:dd:ee:ff REACHABLE`
+    Some(ip) if cfg!(target_os = "linux") => Command::new(ip).args(["neigh", "show", &addr]).output(),
+    // `? (192.168.1.5) at aa:bb:cc:dd:ee:ff on en0 ifscope [ethernet]`
+    _ => Command::new(find_program("arp", &["/usr/sbin/arp", "/sbin/arp"])?)
+      .args(["-n", &addr])
+      .output(),
+  }
+  .ok()?;
+  let text = String::from_utf8_lossy(&output.stdout);
+  let mut tokens = text.split_whitespace();
+  tokens.find(|t| *t == "lladdr" || *t == "at")?;
+  tokens
+    .next()
+    .filter(|mac| mac.contains(':') && !mac.starts_with('('))
+    .map(str::to_ascii_lowercase)
+}
+
+/// Whether something answers on `addr`, and its hardware address if the
+/// ARP cache has it.
+pub fn probe(addr: Ipv4Addr) -> (bool, Option<String>) {
+  let answered = ping(addr);
+  let mac = arp_entry(addr);
+  (answered || mac.is_some(), mac)
+}
+
+fn check(plan: &IpPlan) -> Result<Vec<AddressVerdict>, String> {
+  let subnet = Subnet::parse(&plan.subnet)?;
+  let gateway = plan
+    .gateway
+    .as_deref()
+    .map(|g| parse_addr(g, "gateway"))
+    .transpose()?;
+  if let Some(gateway) = gateway.filter(|g| !subnet.contains(*g)) {
+    return Err(format!("Gateway {} is outside {}", gateway, subnet));
+  }
+  let dhcp = match &plan.dhcp_range {
+    Some(range) => {
+      let start = parse_addr(&range.start, "DHCP range start")?;
+      let end = parse_addr(&range.end, "DHCP range end")?;
+      if u32::from(start) > u32::from(end) {
+        return Err(format!("DHCP range {} - {} is reversed", start, end));
+      }
+      Some((start, end))
+    }
+    None => None,
+  };
+
+  let parsed: Vec<Option<Ipv4Addr>> = plan.addresses.iter().map(|a| a.address.trim().parse().ok()).collect();
+  let mut verdicts: Vec<AddressVerdict> = plan
+    .addresses
+    .iter()
+    .zip(&parsed)
+    .enumerate()
+    .map(|(index, (planned, addr))| {
+      let mut problems = Vec::new();
+      match addr {
+        None => problems.push("Not a valid IPv4 address".to_string()),
+        Some(addr) => {
+          let value = u32::from(*addr);
+          if !subnet.contains(*addr) {
+            problems.push(format!("Outside the cluster subnet {}", subnet));
+          } else if subnet.prefix < 31 && value == subnet.network {
+            problems.push("Is the network address of the subnet".to_string());
+          } else if subnet.prefix < 31 && value == subnet.broadcast() {
+            problems.push("Is the broadcast address of the subnet".to_string());
+          }
+          if gateway == Some(*addr) {
+            problems.push("Is the gateway address".to_string());
+          }
+          if let Some((start, end)) = dhcp {
+            if (u32::from(start)..=u32::from(end)).contains(&value) {
+              problems.push(format!("Inside the DHCP pool {} - {}", start, end));
+            }
+          }
+          let others: Vec<&str> = plan
+            .addresses
+            .iter()
+            .zip(&parsed)
+            .enumerate()
+            .filter(|(i, (_, other_addr))| *i != index && **other_addr == Some(*addr))
+            .map(|(_, (other, _))| other.node.as_str())
+            .collect();
+          if !others.is_empty() {
+            problems.push(format!("Also planned for {}", others.join(", ")));
+          }
+        }
+      }
+      AddressVerdict {
+        node: planned.node.clone(),
+        address: planned.address.trim().to_string(),
+        valid: problems.is_empty(),
+        problems,
+        in_use: None,
+        mac: None,
+      }
+    })
+    .collect();
+
+  if plan.probe.unwrap_or(true) {
+    // Only addresses that are otherwise fine are worth a probe
+    let targets: Vec<(usize, Ipv4Addr)> = verdicts
+      .iter()
+      .zip(&parsed)
+      .enumerate()
+      .filter_map(|(i, (v, addr))| addr.filter(|_| v.valid).map(|a| (i, a)))
+      .collect();
+    let results: Vec<(usize, (bool, Option<String>))> = std::thread::scope(|scope| {
+      let handles: Vec<_> = targets
+        .iter()
+        .map(|&(i, addr)| scope.spawn(move || (i, probe(addr))))
+        .collect();
+      handles.into_iter().filter_map(|h| h.join().ok()).collect()
+    });
+
+    for (i, (in_use, mac)) in results {
+      let planned = &plan.addresses[i];
+      let verdict = &mut verdicts[i];
+      let own = planned
+        .current_address
+        .as_deref()
+        .is_some_and(|current| current.trim() == verdict.address);
+      if in_use && !own {
+        let by = mac.as_deref().map(|m| format!(" ({})", m)).unwrap_or_default();
+        verdict.problems.push(format!("Already in use by another device{}", by));
+        verdict.valid = false;
+      }
+      verdict.in_use = Some(in_use);
+      verdict.mac = mac;
+    }
+  }
+
+  Ok(verdicts)
+}
+
+/// Check every planned static address and return a verdict per address.
+#[tauri::command]
+pub async fn validate_ip_plan(plan: IpPlan) -> Result<Vec<AddressVerdict>, String> {
+  if plan.addresses.is_empty() {
+    return Err("No addresses to check".to_string());
+  }
+  tauri::async_runtime::spawn_blocking(move || check(&plan))
+    .await
+    .map_err(|e| e.to_string())?
+}
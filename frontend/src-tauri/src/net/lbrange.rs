@@ -0,0 +1,101 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Making sure the load balancer pool is free before it is handed out.
+//!
+//! An address in the pool that a printer or a forgotten VM already holds
+//! ends up answering ARP from two hosts at once, and the service behind it
+//! is reachable only some of the time. `check_lb_range` probes every
+//! address in the proposed range from this machine the same way the static
+//! address plan is probed (see [`super::ipplan`]): an ICMP echo, then the
+//! ARP cache, so hosts that drop pings are caught too. Occupied addresses
+//! are reported with the hardware address that answered.
+
+use serde::Serialize;
+use std::net::Ipv4Addr;
+
+use super::ipplan;
+
+/// A /22; larger pools are a typo more often than not
+const MAX_ADDRESSES: u32 = 1024;
+/// Probes in flight at once, each waiting up to a second
+const CONCURRENCY: usize = 32;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OccupiedAddress {
+  pub address: String,
+  /// From the ARP cache; `None` when only the ping was answered
+  pub mac: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LbRangeReport {
+  pub start: String,
+  pub end: String,
+  pub probed: u32,
+  pub occupied: Vec<OccupiedAddress>,
+  /// Nothing answered on any address in the range
+  pub free: bool,
+}
+
+fn parse_addr(value: &str, what: &str) -> Result<Ipv4Addr, String> {
+  value
+    .trim()
+    .parse()
+    .map_err(|_| format!("Invalid {} address: {}", what, value))
+}
+
+/// Probe every address from `start` to `end` inclusive and report the ones
+/// something already answers on.
+#[tauri::command]
+pub async fn check_lb_range(start: String, end: String) -> Result<LbRangeReport, String> {
+  let first = parse_addr(&start, "range start")?;
+  let last = parse_addr(&end, "range end")?;
+  let (first, last) = (u32::from(first), u32::from(last));
+  if first > last {
+    return Err(format!("Range {} - {} is reversed", start.trim(), end.trim()));
+  }
+  let count = last - first + 1;
+  if count > MAX_ADDRESSES {
+    return Err(format!(
+      "Range {} - {} has {} addresses; at most {} are probed",
+      start.trim(),
+      end.trim(),
+      count,
+      MAX_ADDRESSES
+    ));
+  }
+  tauri::async_runtime::spawn_blocking(move || {
+    let addresses: Vec<Ipv4Addr> = (first..=last).map(Ipv4Addr::from).collect();
+    let mut occupied = Vec::new();
+    for chunk in addresses.chunks(CONCURRENCY) {
+      let results: Vec<(Ipv4Addr, (bool, Option<String>))> = std::thread::scope(|scope| {
+        let handles: Vec<_> = chunk
+          .iter()
+          .map(|&addr| scope.spawn(move || (addr, ipplan::probe(addr))))
+          .collect();
+        handles.into_iter().filter_map(|h| h.join().ok()).collect()
+      });
+      occupied.extend(
+        results
+          .into_iter()
+          .filter(|(_, (in_use, _))| *in_use)
+          .map(|(addr, (_, mac))| OccupiedAddress {
+            address: addr.to_string(),
+            mac,
+          }),
+      );
+    }
+    LbRangeReport {
+      start: Ipv4Addr::from(first).to_string(),
+      end: Ipv4Addr::from(last).to_string(),
+      probed: count,
+      free: occupied.is_empty(),
+      occupied,
+    }
+  })
+  .await
+  .map_err(|e| e.to_string())
+}
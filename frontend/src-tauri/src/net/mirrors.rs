@@ -0,0 +1,220 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Picking the fastest package and image mirrors.
+//!
+//! `rank_mirrors` measures each candidate apt archive, snap store and
+//! container registry mirror from the installer host, which normally shares
+//! the nodes' uplink: the best of a few round trips for latency, and for apt
+//! archives the rate at which a release index downloads. Candidates are the
+//! well-known public mirrors plus any the user adds (a local apt cache, a snap
+//! store proxy, a registry pull-through cache). Each kind comes back ranked,
+//! fastest first, and the wizard passes the pick into the inventory.
+
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::time::{Duration, Instant};
+use tracing::info;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+const LATENCY_SAMPLES: usize = 3;
+/// Throughput is measured on at most this much of the release index...
+const THROUGHPUT_BYTES: u64 = 2 * 1024 * 1024;
+/// ...or whatever arrives in this long.
+const THROUGHPUT_TIME: Duration = Duration::from_secs(5);
+/// The release every node runs, see `platform::os_release::SUPPORTED_UBUNTU`.
+pub const UBUNTU_CODENAME: &str = "noble";
+
+const APT_MIRRORS: &[&str] = &[
+  "http://archive.ubuntu.com/ubuntu",
+  "http://us.archive.ubuntu.com/ubuntu",
+  "http://gb.archive.ubuntu.com/ubuntu",
+  "http://de.archive.ubuntu.com/ubuntu",
+  "http://fr.archive.ubuntu.com/ubuntu",
+  "http://nl.archive.ubuntu.com/ubuntu",
+  "http://se.archive.ubuntu.com/ubuntu",
+  "http://jp.archive.ubuntu.com/ubuntu",
+  "http://au.archive.ubuntu.com/ubuntu",
+  "http://br.archive.ubuntu.com/ubuntu",
+  "http://in.archive.ubuntu.com/ubuntu",
+  "http://mirrors.edge.kernel.org/ubuntu",
+];
+const SNAP_STORES: &[&str] = &["https://api.snapcraft.io"];
+const REGISTRY_MIRRORS: &[&str] = &["https://registry-1.docker.io", "https://mirror.gcr.io"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MirrorKind {
+  Apt,
+  Snap,
+  Registry,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MirrorCandidate {
+  pub kind: MirrorKind,
+  pub url: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MirrorResult {
+  pub kind: MirrorKind,
+  pub url: String,
+  pub reachable: bool,
+  /// Best time to first byte
+  pub latency_ms: Option<u64>,
+  /// KiB/s downloading the release index; apt archives only
+  pub throughput_kib_s: Option<u64>,
+  pub error: Option<String>,
+}
+
+/// Each kind's candidates, fastest first; unreachable ones last.
+#[derive(Debug, Clone, Serialize)]
+pub struct MirrorRanking {
+  pub apt: Vec<MirrorResult>,
+  pub snap: Vec<MirrorResult>,
+  pub registry: Vec<MirrorResult>,
+}
+
+/// What to fetch from a mirror of `kind` at `base`.
+fn probe_url(kind: MirrorKind, base: &str) -> String {
+  match kind {
+    MirrorKind::Apt => format!("{}/dists/{}/InRelease", base, UBUNTU_CODENAME),
+    MirrorKind::Snap => format!("{}/", base),
+    MirrorKind::Registry => format!("{}/v2/", base),
+  }
+}
+
+/// Time until the response headers arrive, and the response if it was a
+/// success. Only an apt archive has to serve the file: registries answer
+/// `/v2/` with 401 until logged in and store proxies vary, but any answer
+/// shows they are there.
+fn first_byte(agent: &ureq::Agent, kind: MirrorKind, url: &str) -> Result<(Duration, Option<ureq::Response>), String> {
+  let started = Instant::now();
+  match agent.get(url).call() {
+    Ok(response) => Ok((started.elapsed(), Some(response))),
+    Err(ureq::Error::Status(code, _)) if kind == MirrorKind::Apt => Err(format!("HTTP {}", code)),
+    Err(ureq::Error::Status(_, _)) => Ok((started.elapsed(), None)),
+    Err(ureq::Error::Transport(transport)) => Err(transport.to_string()),
+  }
+}
+
+/// How fast the body of `response` arrives, over a bounded sample.
+pub fn throughput_kib_s(response: ureq::Response) -> Option<u64> {
+  let started = Instant::now();
+  let mut reader = response.into_reader().take(THROUGHPUT_BYTES);
+  let mut buf = [0u8; 16 * 1024];
+  let mut total = 0u64;
+  while started.elapsed() < THROUGHPUT_TIME {
+    match reader.read(&mut buf) {
+      Ok(0) => break,
+      Ok(n) => total += n as u64,
+      Err(_) => return None,
+    }
+  }
+  let secs = started.elapsed().as_secs_f64();
+  (total > 0 && secs > 0.0).then(|| (total as f64 / 1024.0 / secs) as u64)
+}
+
+fn measure(candidate: &MirrorCandidate) -> MirrorResult {
+  let base = candidate.url.trim().trim_end_matches('/');
+  let mut result = MirrorResult {
+    kind: candidate.kind,
+    url: base.to_string(),
+    reachable: false,
+    latency_ms: None,
+    throughput_kib_s: None,
+    error: None,
+  };
+  if !base.starts_with("http://") && !base.starts_with("https://") {
+    result.error = Some("Mirror URL must start with http:// or https://".to_string());
+    return result;
+  }
+
+  let agent = ureq::AgentBuilder::new().timeout(TIMEOUT).build();
+  let url = probe_url(candidate.kind, base);
+  let mut latency: Option<Duration> = None;
+  for _ in 0..LATENCY_SAMPLES {
+    match first_byte(&agent, candidate.kind, &url) {
+      Ok((elapsed, response)) => {
+        latency = Some(latency.map_or(elapsed, |best| best.min(elapsed)));
+        // The first full index download doubles as the throughput test
+        if candidate.kind == MirrorKind::Apt && result.throughput_kib_s.is_none() {
+          result.throughput_kib_s = response.and_then(throughput_kib_s);
+        }
+      }
+      Err(e) => {
+        result.error = Some(e);
+        break;
+      }
+    }
+  }
+  if let Some(latency) = latency {
+    result.reachable = true;
+    result.latency_ms = Some(latency.as_millis() as u64);
+    result.error = None;
+  }
+  result
+}
+
+/// Reachable first, then by throughput where measured, then by latency.
+fn rank(mut results: Vec<MirrorResult>) -> Vec<MirrorResult> {
+  results.sort_by_key(|r| {
+    (
+      !r.reachable,
+      std::cmp::Reverse(r.throughput_kib_s.unwrap_or(0)),
+      r.latency_ms.unwrap_or(u64::MAX),
+    )
+  });
+  results
+}
+
+fn candidates(extra: Vec<MirrorCandidate>) -> Vec<MirrorCandidate> {
+  let mut candidates = extra;
+  for (kind, urls) in [
+    (MirrorKind::Apt, APT_MIRRORS),
+    (MirrorKind::Snap, SNAP_STORES),
+    (MirrorKind::Registry, REGISTRY_MIRRORS),
+  ] {
+    candidates.extend(urls.iter().map(|url| MirrorCandidate {
+      kind,
+      url: url.to_string(),
+    }));
+  }
+  // A user-added mirror that is also a default is measured once
+  let mut seen = std::collections::HashSet::new();
+  candidates.retain(|c| seen.insert((c.kind, c.url.trim().trim_end_matches('/').to_string())));
+  candidates
+}
+
+/// Measure the default mirrors plus `extra` and rank each kind.
+#[tauri::command]
+pub async fn rank_mirrors(extra: Option<Vec<MirrorCandidate>>) -> Result<MirrorRanking, String> {
+  tauri::async_runtime::spawn_blocking(move || {
+    let candidates = candidates(extra.unwrap_or_default());
+    let results: Vec<MirrorResult> = std::thread::scope(|scope| {
+      let handles: Vec<_> = candidates.iter().map(|c| scope.spawn(move || measure(c))).collect();
+      handles.into_iter().filter_map(|h| h.join().ok()).collect()
+    });
+
+    let of_kind = |kind: MirrorKind| rank(results.iter().filter(|r| r.kind == kind).cloned().collect());
+    let ranking = MirrorRanking {
+      apt: of_kind(MirrorKind::Apt),
+      snap: of_kind(MirrorKind::Snap),
+      registry: of_kind(MirrorKind::Registry),
+    };
+    if let Some(best) = ranking.apt.first().filter(|r| r.reachable) {
+      info!(
+        "Fastest apt mirror: {} ({} ms, {} KiB/s)",
+        best.url,
+        best.latency_ms.unwrap_or_default(),
+        best.throughput_kib_s.unwrap_or_default()
+      );
+    }
+    Ok(ranking)
+  })
+  .await
+  .map_err(|e| e.to_string())?
+}
@@ -0,0 +1,26 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Networking on the installer host.
+
+pub mod cidrs;
+pub mod connectivity;
+pub mod dhcp;
+pub mod doh;
+pub mod domain_challenge;
+pub mod download;
+pub mod estimate;
+pub mod exposure;
+pub mod ipplan;
+pub mod lbrange;
+pub mod mirrors;
+pub mod overlay;
+#[cfg(feature = "port-mapping")]
+pub mod portmap;
+pub mod registries;
+pub mod registry_auth;
+pub mod tls;
+pub mod vpn;
+pub mod wol;
@@ -0,0 +1,305 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Local ZeroTier / Tailscale clients.
+//!
+//! Multi-site clusters reach their nodes over an overlay network. These
+//! commands talk to the clients installed on this machine through their
+//! CLIs (`zerotier-cli -j`, `tailscale status --json`) to report whether
+//! they're running and which addresses they hold, and to join a network.
+//! Controller-side settings (CIDRs, ACL tags) stay in the backend, which
+//! talks to the providers' cloud APIs.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use tauri::{AppHandle, Manager};
+use tracing::info;
+
+use crate::{audit, dry_run};
+use crate::platform::find_program;
+use crate::workspace::{write_private_file, Workspace};
+
+const ZEROTIER_PATHS: &[&str] = &[
+  "/usr/sbin/zerotier-cli",
+  "/usr/local/bin/zerotier-cli",
+  "/Library/Application Support/ZeroTier/One/zerotier-cli",
+];
+const TAILSCALE_PATHS: &[&str] = &[
+  "/usr/bin/tailscale",
+  "/usr/local/bin/tailscale",
+  "/opt/homebrew/bin/tailscale",
+  "/Applications/Tailscale.app/Contents/MacOS/Tailscale",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Provider {
+  Zerotier,
+  Tailscale,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OverlayNetwork {
+  pub id: String,
+  pub name: String,
+  pub status: String,
+  pub addresses: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OverlayClient {
+  pub provider: Provider,
+  pub installed: bool,
+  pub binary: Option<String>,
+  /// The daemon answered the CLI
+  pub running: bool,
+  /// Connected to the provider's control plane
+  pub online: bool,
+  pub version: Option<String>,
+  /// ZeroTier node address or Tailscale hostname
+  pub node_id: Option<String>,
+  pub addresses: Vec<String>,
+  pub networks: Vec<OverlayNetwork>,
+  pub error: Option<String>,
+}
+
+impl OverlayClient {
+  fn missing(provider: Provider) -> Self {
+    Self {
+      provider,
+      installed: false,
+      binary: None,
+      running: false,
+      online: false,
+      version: None,
+      node_id: None,
+      addresses: Vec::new(),
+      networks: Vec::new(),
+      error: None,
+    }
+  }
+}
+
+fn program(provider: Provider) -> Option<PathBuf> {
+  match provider {
+    Provider::Zerotier => find_program("zerotier-cli", ZEROTIER_PATHS),
+    Provider::Tailscale => find_program("tailscale", TAILSCALE_PATHS),
+  }
+}
+
+/// Run the client CLI, retrying through `sudo -n` when it's refused for lack
+/// of privileges (zerotier-cli needs root to read its auth token). `sudo -n`
+/// never prompts; it only helps when credentials are cached.
+fn run(binary: &Path, args: &[&str]) -> Result<Output, String> {
+  let output = Command::new(binary)
+    .args(args)
+    .output()
+    .map_err(|e| format!("Failed to run {}: {}", binary.display(), e))?;
+  if output.status.success() || !looks_unprivileged(&output) {
+    return Ok(output);
+  }
+  let binary_name = binary.display().to_string();
+  let mut audited = vec!["-n", binary_name.as_str()];
+  audited.extend_from_slice(args);
+  audit::local_sudo(&audited);
+  match Command::new("sudo").arg("-n").arg(binary).args(args).output() {
+    Ok(elevated) if elevated.status.success() => Ok(elevated),
+    _ => Ok(output),
+  }
+}
+
+fn looks_unprivileged(output: &Output) -> bool {
+  let text = format!(
+    "{}{}",
+    String::from_utf8_lossy(&output.stdout),
+    String::from_utf8_lossy(&output.stderr)
+  )
+  .to_ascii_lowercase();
+  text.contains("authtoken") || text.contains("permission denied") || text.contains("access denied")
+}
+
+fn failure(output: &Output) -> String {
+  let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+  if stderr.is_empty() {
+    String::from_utf8_lossy(&output.stdout).trim().to_string()
+  } else {
+    stderr
+  }
+}
+
+fn run_json(binary: &Path, args: &[&str]) -> Result<Value, String> {
+  let output = run(binary, args)?;
+  if !output.status.success() {
+    return Err(failure(&output));
+  }
+  serde_json::from_slice(&output.stdout).map_err(|e| format!("Unexpected output from {}: {}", binary.display(), e))
+}
+
+fn strings(value: &Value) -> Vec<String> {
+  value
+    .as_array()
+    .map(|items| items.iter().filter_map(|v| v.as_str().map(str::to_string)).collect())
+    .unwrap_or_default()
+}
+
+fn zerotier_status(binary: &Path) -> OverlayClient {
+  let mut client = OverlayClient::missing(Provider::Zerotier);
+  client.installed = true;
+  client.binary = Some(binary.display().to_string());
+
+  let info = match run_json(binary, &["-j", "info"]) {
+    Ok(info) => info,
+    Err(e) => {
+      client.error = Some(e);
+      return client;
+    }
+  };
+  client.running = true;
+  client.online = info["online"].as_bool().unwrap_or(false);
+  client.version = info["version"].as_str().map(str::to_string);
+  client.node_id = info["address"].as_str().map(str::to_string);
+
+  match run_json(binary, &["-j", "listnetworks"]) {
+    Ok(networks) => {
+      for network in networks.as_array().into_iter().flatten() {
+        // assignedAddresses are CIDRs like 10.147.17.5/24
+        let addresses: Vec<String> = strings(&network["assignedAddresses"])
+          .into_iter()
+          .map(|a| a.split('/').next().unwrap_or_default().to_string())
+          .collect();
+        client.addresses.extend(addresses.iter().cloned());
+        client.networks.push(OverlayNetwork {
+          id: network["nwid"].as_str().unwrap_or_default().to_string(),
+          name: network["name"].as_str().unwrap_or_default().to_string(),
+          status: network["status"].as_str().unwrap_or_default().to_string(),
+          addresses,
+        });
+      }
+    }
+    Err(e) => client.error = Some(e),
+  }
+  client
+}
+
+fn tailscale_status(binary: &Path) -> OverlayClient {
+  let mut client = OverlayClient::missing(Provider::Tailscale);
+  client.installed = true;
+  client.binary = Some(binary.display().to_string());
+
+  if let Ok(output) = run(binary, &["version"]) {
+    client.version = String::from_utf8_lossy(&output.stdout).lines().next().map(|l| l.trim().to_string());
+  }
+
+  // `status --json` exits non-zero when logged out but still prints JSON
+  let status = match run(binary, &["status", "--json"]) {
+    Ok(output) => match serde_json::from_slice::<Value>(&output.stdout) {
+      Ok(status) => status,
+      Err(_) => {
+        client.error = Some(failure(&output));
+        return client;
+      }
+    },
+    Err(e) => {
+      client.error = Some(e);
+      return client;
+    }
+  };
+
+  let state = status["BackendState"].as_str().unwrap_or("").to_string();
+  client.running = !state.is_empty() && state != "NoState";
+  client.online = state == "Running";
+  client.node_id = status["Self"]["HostName"].as_str().map(str::to_string);
+  client.addresses = strings(&status["Self"]["TailscaleIPs"]);
+  if let Some(tailnet) = status["CurrentTailnet"]["Name"].as_str() {
+    client.networks.push(OverlayNetwork {
+      id: tailnet.to_string(),
+      name: tailnet.to_string(),
+      status: state,
+      addresses: client.addresses.clone(),
+    });
+  }
+  client
+}
+
+fn status(provider: Provider) -> OverlayClient {
+  match program(provider) {
+    None => OverlayClient::missing(provider),
+    Some(binary) => match provider {
+      Provider::Zerotier => zerotier_status(&binary),
+      Provider::Tailscale => tailscale_status(&binary),
+    },
+  }
+}
+
+fn is_zerotier_network_id(id: &str) -> bool {
+  id.len() == 16 && id.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+fn is_tailscale_auth_key(key: &str) -> bool {
+  key.starts_with("tskey-")
+    && key.len() <= 200
+    && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+fn join(workspace: &Workspace, provider: Provider, network: &str) -> Result<OverlayClient, String> {
+  let binary = program(provider).ok_or_else(|| match provider {
+    Provider::Zerotier => "ZeroTier is not installed on this machine".to_string(),
+    Provider::Tailscale => "Tailscale is not installed on this machine".to_string(),
+  })?;
+
+  let output = match provider {
+    Provider::Zerotier => {
+      let id = network.to_ascii_lowercase();
+      if !is_zerotier_network_id(&id) {
+        return Err(format!("Invalid ZeroTier network id: {}", network));
+      }
+      run(&binary, &["join", &id])?
+    }
+    Provider::Tailscale => {
+      if !is_tailscale_auth_key(network) {
+        return Err("Invalid Tailscale auth key (expected tskey-...)".to_string());
+      }
+      // Pass the key through a private file so it never shows up in `ps`
+      let key_file = workspace.resolve("tailscale-authkey")?;
+      write_private_file(&key_file, network.as_bytes())?;
+      let arg = format!("--auth-key=file:{}", key_file.display());
+      let result = run(&binary, &["up", &arg]);
+      let _ = std::fs::remove_file(&key_file);
+      result?
+    }
+  };
+
+  if !output.status.success() {
+    return Err(format!("Failed to join overlay network: {}", failure(&output)));
+  }
+  Ok(status(provider))
+}
+
+#[tauri::command]
+pub async fn detect_overlay_clients() -> Result<Vec<OverlayClient>, String> {
+  tauri::async_runtime::spawn_blocking(|| vec![status(Provider::Zerotier), status(Provider::Tailscale)])
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Join a ZeroTier network id, or bring Tailscale up with an auth key.
+#[tauri::command]
+pub async fn join_overlay_network(
+  app: AppHandle,
+  provider: Provider,
+  network: String,
+) -> Result<OverlayClient, String> {
+  if dry_run::is_enabled(&app) {
+    info!("Dry run: not joining {:?} network", provider);
+    return tauri::async_runtime::spawn_blocking(move || Ok(status(provider)))
+      .await
+      .map_err(|e| e.to_string())?;
+  }
+  tauri::async_runtime::spawn_blocking(move || join(&app.state::<Workspace>(), provider, network.trim()))
+    .await
+    .map_err(|e| e.to_string())?
+}
@@ -0,0 +1,601 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Asking the router to forward ports to this host, for users who'd rather
+//! not find the setting in its admin pages. Built with the `port-mapping`
+//! feature only.
+//!
+//! `map_ports` looks for a UPnP Internet Gateway Device with SSDP and adds
+//! a permanent mapping per port through its WANIPConnection (or
+//! WANPPPConnection) service. Routers without UPnP are tried over NAT-PMP
+//! on the default gateway; those mappings last as long as the router grants
+//! and are renewed by mapping again. Each mapping is then checked end to end
+//! like `check_public_exposure` does, and recorded in
+//! `~/.thinkube-installer/port-mappings.json` so `unmap_ports`, and the
+//! uninstaller, can remove exactly what was added.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::AppHandle;
+use tracing::{info, warn};
+
+use super::exposure::{self, PortFinding};
+use crate::dry_run;
+use crate::workspace::write_private_file;
+
+const DEFAULT_PORTS: &[u16] = &[80, 443];
+const DESCRIPTION: &str = "Thinkube";
+
+const SSDP_ADDR: (Ipv4Addr, u16) = (Ipv4Addr::new(239, 255, 255, 250), 1900);
+const SSDP_TIMEOUT: Duration = Duration::from_secs(3);
+const IGD_DEVICE: &str = "urn:schemas-upnp-org:device:InternetGatewayDevice:1";
+/// Services that can add port mappings, preferred first
+const WAN_SERVICES: &[&str] = &[
+  "urn:schemas-upnp-org:service:WANIPConnection:2",
+  "urn:schemas-upnp-org:service:WANIPConnection:1",
+  "urn:schemas-upnp-org:service:WANPPPConnection:1",
+];
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+const NATPMP_PORT: u16 = 5351;
+/// Seven days; routers may grant less
+const NATPMP_LIFETIME: u32 = 7 * 24 * 3600;
+const NATPMP_TRIES: u32 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Method {
+  Upnp,
+  NatPmp,
+}
+
+/// A mapping this installer added.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PortMapping {
+  pub method: Method,
+  pub external_port: u16,
+  pub internal_port: u16,
+  pub internal_ip: String,
+  /// The UPnP control URL, or the NAT-PMP gateway address
+  pub gateway: String,
+  /// The UPnP service the mapping was added through
+  pub service_type: Option<String>,
+  /// Seconds the router will keep the mapping; `None` for permanent ones
+  pub lifetime_secs: Option<u32>,
+  /// Seconds since the Unix epoch
+  pub created_at: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MappingResult {
+  pub port: u16,
+  pub mapped: bool,
+  pub error: Option<String>,
+  /// End-to-end check of the mapping, when it was added
+  pub verification: Option<PortFinding>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PortMappingReport {
+  pub method: Method,
+  pub gateway: String,
+  pub external_ip: Option<String>,
+  pub results: Vec<MappingResult>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UnmapResult {
+  pub mapping: PortMapping,
+  pub removed: bool,
+  pub error: Option<String>,
+}
+
+/// A UPnP gateway's port mapping service.
+struct Igd {
+  control_url: String,
+  service_type: String,
+}
+
+enum Gateway {
+  Upnp(Igd),
+  NatPmp(Ipv4Addr),
+}
+
+fn now() -> u64 {
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|d| d.as_secs())
+    .unwrap_or_default()
+}
+
+fn records_path() -> Option<PathBuf> {
+  std::env::var_os("HOME").map(|home| {
+    PathBuf::from(home)
+      .join(".thinkube-installer")
+      .join("port-mappings.json")
+  })
+}
+
+/// Mappings recorded by earlier `map_ports` calls.
+pub fn recorded() -> Vec<PortMapping> {
+  records_path()
+    .and_then(|path| std::fs::read_to_string(path).ok())
+    .and_then(|text| serde_json::from_str(&text).ok())
+    .unwrap_or_default()
+}
+
+fn save(mappings: &[PortMapping]) -> Result<(), String> {
+  let path = records_path().ok_or("Cannot locate the home directory")?;
+  if mappings.is_empty() {
+    return match std::fs::remove_file(&path) {
+      Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(format!("Failed to remove {}: {}", path.display(), e)),
+      _ => Ok(()),
+    };
+  }
+  let json = serde_json::to_vec_pretty(mappings).map_err(|e| e.to_string())?;
+  write_private_file(&path, &json)
+}
+
+fn same_mapping(a: &PortMapping, b: &PortMapping) -> bool {
+  a.method == b.method && a.gateway == b.gateway && a.external_port == b.external_port
+}
+
+/// The IPv4 default gateway.
+#[cfg(target_os = "linux")]
+fn default_gateway() -> Option<Ipv4Addr> {
+  let routes = std::fs::read_to_string("/proc/net/route").ok()?;
+  routes.lines().skip(1).find_map(|line| {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if fields.get(1) != Some(&"00000000") {
+      return None;
+    }
+    // Printed as the in-memory value of a network-order address
+    let gateway = u32::from_str_radix(fields.get(2)?, 16).ok()?;
+    Some(Ipv4Addr::from(gateway.to_ne_bytes())).filter(|ip| !ip.is_unspecified())
+  })
+}
+
+/// The IPv4 default gateway.
+#[cfg(not(target_os = "linux"))]
+fn default_gateway() -> Option<Ipv4Addr> {
+  let output = std::process::Command::new("route")
+    .args(["-n", "get", "default"])
+    .output()
+    .ok()?;
+  String::from_utf8_lossy(&output.stdout).lines().find_map(|line| {
+    line
+      .trim()
+      .strip_prefix("gateway:")
+      .and_then(|gateway| gateway.trim().parse().ok())
+  })
+}
+
+/// The address this host uses to reach `peer`.
+fn local_ip_towards(peer: IpAddr) -> Result<IpAddr, String> {
+  let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).map_err(|e| format!("Failed to open UDP socket: {}", e))?;
+  socket
+    .connect(SocketAddr::new(peer, 9))
+    .map_err(|e| format!("No route to {}: {}", peer, e))?;
+  socket.local_addr().map(|a| a.ip()).map_err(|e| e.to_string())
+}
+
+/// Location of the first Internet Gateway Device's description answering
+/// an SSDP search.
+fn ssdp_search() -> Result<Option<String>, String> {
+  let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).map_err(|e| format!("Failed to open UDP socket: {}", e))?;
+  let request = format!(
+    "M-SEARCH * HTTP/1.1\r\nHOST: 239.255.255.250:1900\r\nMAN: \"ssdp:discover\"\r\nMX: 2\r\nST: {}\r\n\r\n",
+    IGD_DEVICE
+  );
+  socket
+    .send_to(request.as_bytes(), SSDP_ADDR)
+    .map_err(|e| format!("Failed to send SSDP search: {}", e))?;
+
+  let deadline = Instant::now() + SSDP_TIMEOUT;
+  let mut buffer = [0u8; 2048];
+  while let Some(left) = deadline.checked_duration_since(Instant::now()).filter(|d| !d.is_zero()) {
+    socket.set_read_timeout(Some(left)).map_err(|e| e.to_string())?;
+    let Ok((len, _)) = socket.recv_from(&mut buffer) else {
+      break;
+    };
+    let response = String::from_utf8_lossy(&buffer[..len]);
+    let location = response.lines().find_map(|line| {
+      let (name, value) = line.split_once(':')?;
+      name
+        .trim()
+        .eq_ignore_ascii_case("location")
+        .then(|| value.trim().to_string())
+    });
+    if location.is_some() {
+      return Ok(location);
+    }
+  }
+  Ok(None)
+}
+
+fn xml_element<'a>(xml: &'a str, name: &str) -> Option<&'a str> {
+  let start = xml.find(&format!("<{}>", name))? + name.len() + 2;
+  let end = xml[start..].find(&format!("</{}>", name))? + start;
+  Some(xml[start..end].trim())
+}
+
+/// `url` resolved against the device description at `location`.
+fn resolve_url(location: &str, base: Option<&str>, url: &str) -> String {
+  if url.starts_with("http://") || url.starts_with("https://") {
+    return url.to_string();
+  }
+  let base = base.unwrap_or(location);
+  let origin = match base
+    .find("://")
+    .and_then(|i| base[i + 3..].find('/').map(|j| i + 3 + j))
+  {
+    Some(end) => &base[..end],
+    None => base.trim_end_matches('/'),
+  };
+  format!("{}/{}", origin, url.trim_start_matches('/'))
+}
+
+fn describe(agent: &ureq::Agent, location: &str) -> Result<Igd, String> {
+  let xml = agent
+    .get(location)
+    .call()
+    .map_err(|e| format!("Failed to read the gateway description: {}", e))?
+    .into_string()
+    .map_err(|e| e.to_string())?;
+  let services = Regex::new(r"(?s)<service>(.*?)</service>").map_err(|e| e.to_string())?;
+  let found: Vec<(&str, &str)> = services
+    .captures_iter(&xml)
+    .filter_map(|c| {
+      let service = c.get(1)?.as_str();
+      Some((
+        xml_element(service, "serviceType")?,
+        xml_element(service, "controlURL")?,
+      ))
+    })
+    .collect();
+  WAN_SERVICES
+    .iter()
+    .find_map(|wanted| found.iter().find(|(service_type, _)| service_type == wanted))
+    .map(|(service_type, control_url)| Igd {
+      control_url: resolve_url(location, xml_element(&xml, "URLBase"), control_url),
+      service_type: service_type.to_string(),
+    })
+    .ok_or_else(|| "The gateway has no port mapping service".to_string())
+}
+
+fn soap(agent: &ureq::Agent, igd: &Igd, action: &str, args: &[(&str, String)]) -> Result<String, String> {
+  let args: String = args
+    .iter()
+    .map(|(name, value)| format!("<{name}>{value}</{name}>", name = name, value = value))
+    .collect();
+  let body = format!(
+    "<?xml version=\"1.0\"?>\
+     <s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\" \
+     s:encodingStyle=\"http://schemas.xmlsoap.org/soap/encoding/\">\
+     <s:Body><u:{action} xmlns:u=\"{service}\">{args}</u:{action}></s:Body></s:Envelope>",
+    action = action,
+    service = igd.service_type,
+    args = args
+  );
+  match agent
+    .post(&igd.control_url)
+    .set("Content-Type", "text/xml; charset=\"utf-8\"")
+    .set("SOAPAction", &format!("\"{}#{}\"", igd.service_type, action))
+    .send_string(&body)
+  {
+    Ok(response) => response.into_string().map_err(|e| e.to_string()),
+    Err(ureq::Error::Status(code, response)) => {
+      let text = response.into_string().unwrap_or_default();
+      Err(
+        match (xml_element(&text, "errorCode"), xml_element(&text, "errorDescription")) {
+          (Some("718"), _) => "The router already maps this port to another host".to_string(),
+          (Some(code), Some(description)) => format!("{} failed: {} ({})", action, description, code),
+          _ => format!("{} failed with HTTP {}", action, code),
+        },
+      )
+    }
+    Err(e) => Err(format!("{} failed: {}", action, e)),
+  }
+}
+
+fn upnp_external_ip(agent: &ureq::Agent, igd: &Igd) -> Option<String> {
+  let response = soap(agent, igd, "GetExternalIPAddress", &[]).ok()?;
+  xml_element(&response, "NewExternalIPAddress")
+    .filter(|ip| ip.parse::<IpAddr>().is_ok())
+    .map(str::to_string)
+}
+
+fn upnp_map(agent: &ureq::Agent, igd: &Igd, port: u16, internal_ip: IpAddr) -> Result<PortMapping, String> {
+  soap(
+    agent,
+    igd,
+    "AddPortMapping",
+    &[
+      ("NewRemoteHost", String::new()),
+      ("NewExternalPort", port.to_string()),
+      ("NewProtocol", "TCP".to_string()),
+      ("NewInternalPort", port.to_string()),
+      ("NewInternalClient", internal_ip.to_string()),
+      ("NewEnabled", "1".to_string()),
+      ("NewPortMappingDescription", DESCRIPTION.to_string()),
+      ("NewLeaseDuration", "0".to_string()),
+    ],
+  )?;
+  Ok(PortMapping {
+    method: Method::Upnp,
+    external_port: port,
+    internal_port: port,
+    internal_ip: internal_ip.to_string(),
+    gateway: igd.control_url.clone(),
+    service_type: Some(igd.service_type.clone()),
+    lifetime_secs: None,
+    created_at: now(),
+  })
+}
+
+/// Send a NAT-PMP request to `gateway`, retrying with a doubling timeout.
+fn natpmp(gateway: Ipv4Addr, request: &[u8]) -> Result<Vec<u8>, String> {
+  let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).map_err(|e| format!("Failed to open UDP socket: {}", e))?;
+  socket
+    .connect((gateway, NATPMP_PORT))
+    .map_err(|e| format!("No route to {}: {}", gateway, e))?;
+  let mut timeout = Duration::from_millis(250);
+  let mut buffer = [0u8; 16];
+  for _ in 0..NATPMP_TRIES {
+    socket
+      .send(request)
+      .map_err(|e| format!("Failed to send NAT-PMP request: {}", e))?;
+    socket.set_read_timeout(Some(timeout)).map_err(|e| e.to_string())?;
+    if let Ok(len) = socket.recv(&mut buffer) {
+      // Version 0, the request's opcode + 128
+      if len >= 8 && buffer[0] == 0 && buffer[1] == request[1] + 128 {
+        return match u16::from_be_bytes([buffer[2], buffer[3]]) {
+          0 => Ok(buffer[..len].to_vec()),
+          2 => Err("The router refuses NAT-PMP mappings".to_string()),
+          3 => Err("The router has no internet connection".to_string()),
+          4 => Err("The router is out of mappings".to_string()),
+          code => Err(format!("NAT-PMP request failed with result {}", code)),
+        };
+      }
+    }
+    timeout *= 2;
+  }
+  Err(format!("{} does not answer NAT-PMP", gateway))
+}
+
+fn natpmp_external_ip(gateway: Ipv4Addr) -> Result<Ipv4Addr, String> {
+  let response = natpmp(gateway, &[0, 0])?;
+  if response.len() < 12 {
+    return Err("Short NAT-PMP response".to_string());
+  }
+  Ok(Ipv4Addr::new(response[8], response[9], response[10], response[11]))
+}
+
+fn natpmp_request(port: u16, external_port: u16, lifetime: u32) -> Vec<u8> {
+  // Version 0, opcode 2 (TCP), two reserved bytes
+  let mut request = vec![0, 2, 0, 0];
+  request.extend_from_slice(&port.to_be_bytes());
+  request.extend_from_slice(&external_port.to_be_bytes());
+  request.extend_from_slice(&lifetime.to_be_bytes());
+  request
+}
+
+fn natpmp_map(gateway: Ipv4Addr, port: u16, internal_ip: IpAddr) -> Result<PortMapping, String> {
+  let response = natpmp(gateway, &natpmp_request(port, port, NATPMP_LIFETIME))?;
+  if response.len() < 16 {
+    return Err("Short NAT-PMP response".to_string());
+  }
+  let external_port = u16::from_be_bytes([response[10], response[11]]);
+  let lifetime = u32::from_be_bytes([response[12], response[13], response[14], response[15]]);
+  if external_port != port {
+    // Certificates and links use the standard ports; another one is no use
+    let _ = natpmp(gateway, &natpmp_request(port, 0, 0));
+    return Err(format!("The router offered port {} instead", external_port));
+  }
+  Ok(PortMapping {
+    method: Method::NatPmp,
+    external_port,
+    internal_port: port,
+    internal_ip: internal_ip.to_string(),
+    gateway: gateway.to_string(),
+    service_type: None,
+    lifetime_secs: Some(lifetime),
+    created_at: now(),
+  })
+}
+
+fn find_gateway(agent: &ureq::Agent) -> Result<Gateway, String> {
+  let upnp = match ssdp_search()? {
+    Some(location) => describe(agent, &location).map(Gateway::Upnp),
+    None => Err("No UPnP gateway answered".to_string()),
+  };
+  let upnp_error = match upnp {
+    Ok(gateway) => return Ok(gateway),
+    Err(e) => e,
+  };
+  let gateway = default_gateway().ok_or_else(|| format!("{}, and there is no default gateway", upnp_error))?;
+  natpmp_external_ip(gateway)
+    .map(|_| Gateway::NatPmp(gateway))
+    .map_err(|e| format!("{}; {}", upnp_error, e))
+}
+
+/// Remove `mapping` from the router.
+pub fn remove(mapping: &PortMapping) -> Result<(), String> {
+  match mapping.method {
+    Method::Upnp => {
+      let igd = Igd {
+        control_url: mapping.gateway.clone(),
+        service_type: mapping.service_type.clone().ok_or("The mapping has no UPnP service")?,
+      };
+      let agent = ureq::AgentBuilder::new().timeout(HTTP_TIMEOUT).build();
+      soap(
+        &agent,
+        &igd,
+        "DeletePortMapping",
+        &[
+          ("NewRemoteHost", String::new()),
+          ("NewExternalPort", mapping.external_port.to_string()),
+          ("NewProtocol", "TCP".to_string()),
+        ],
+      )
+      .map(|_| ())
+    }
+    Method::NatPmp => {
+      let gateway = mapping
+        .gateway
+        .parse()
+        .map_err(|_| format!("Invalid gateway: {}", mapping.gateway))?;
+      natpmp(gateway, &natpmp_request(mapping.internal_port, 0, 0)).map(|_| ())
+    }
+  }
+}
+
+/// Forget `mapping` once it is gone from the router.
+pub fn forget(mapping: &PortMapping) -> Result<(), String> {
+  let mut mappings = recorded();
+  mappings.retain(|m| !same_mapping(m, mapping));
+  save(&mappings)
+}
+
+/// Ask the router to forward `ports` (80 and 443 by default) to this host,
+/// then check each mapping from the public IP unless `verify` is false.
+#[tauri::command]
+pub async fn map_ports(
+  app: AppHandle,
+  ports: Option<Vec<u16>>,
+  verify: Option<bool>,
+) -> Result<PortMappingReport, String> {
+  let ports = ports.unwrap_or_else(|| DEFAULT_PORTS.to_vec());
+  if ports.is_empty() || ports.contains(&0) {
+    return Err("Ports must be between 1 and 65535".to_string());
+  }
+  let dry_run = dry_run::is_enabled(&app);
+
+  tauri::async_runtime::spawn_blocking(move || {
+    let agent = ureq::AgentBuilder::new().timeout(HTTP_TIMEOUT).build();
+    let gateway = find_gateway(&agent)?;
+    let (method, gateway_name, gateway_ip, external_ip) = match &gateway {
+      Gateway::Upnp(igd) => {
+        let host = igd
+          .control_url
+          .split("://")
+          .nth(1)
+          .and_then(|rest| rest.split([':', '/']).next())
+          .and_then(|host| host.parse::<IpAddr>().ok());
+        (
+          Method::Upnp,
+          igd.control_url.clone(),
+          host,
+          upnp_external_ip(&agent, igd),
+        )
+      }
+      Gateway::NatPmp(ip) => (
+        Method::NatPmp,
+        ip.to_string(),
+        Some(IpAddr::V4(*ip)),
+        natpmp_external_ip(*ip).ok().map(|ip| ip.to_string()),
+      ),
+    };
+    let internal_ip = local_ip_towards(gateway_ip.ok_or("Cannot tell the gateway's address")?)?;
+    // The router's idea of its address can be a carrier-grade NAT one
+    let public_ip = if verify.unwrap_or(true) {
+      exposure::public_ip(&agent).ip.and_then(|ip| ip.parse::<IpAddr>().ok())
+    } else {
+      None
+    };
+    if let (Some(public), Some(external)) = (public_ip, &external_ip) {
+      if public.to_string() != *external {
+        warn!(
+          "The router's external address {} is not the public IP {}; mappings won't make it reachable",
+          external, public
+        );
+      }
+    }
+
+    let mut mappings = recorded();
+    let mut results = Vec::new();
+    for port in ports {
+      if dry_run {
+        info!("Dry run: would map port {} to {}", port, internal_ip);
+        results.push(MappingResult {
+          port,
+          mapped: true,
+          error: None,
+          verification: None,
+        });
+        continue;
+      }
+      let mapped = match &gateway {
+        Gateway::Upnp(igd) => upnp_map(&agent, igd, port, internal_ip),
+        Gateway::NatPmp(ip) => natpmp_map(*ip, port, internal_ip),
+      };
+      let result = match mapped {
+        Ok(mapping) => {
+          info!("Mapped port {} to {} with {:?}", port, internal_ip, method);
+          mappings.retain(|m| !same_mapping(m, &mapping));
+          mappings.push(mapping);
+          MappingResult {
+            port,
+            mapped: true,
+            error: None,
+            verification: public_ip.map(|ip| exposure::check_port(ip, port)),
+          }
+        }
+        Err(e) => {
+          warn!("Failed to map port {}: {}", port, e);
+          MappingResult {
+            port,
+            mapped: false,
+            error: Some(e),
+            verification: None,
+          }
+        }
+      };
+      results.push(result);
+    }
+    if !dry_run {
+      save(&mappings)?;
+    }
+    Ok(PortMappingReport {
+      method,
+      gateway: gateway_name,
+      external_ip,
+      results,
+    })
+  })
+  .await
+  .map_err(|e| e.to_string())?
+}
+
+/// Remove every mapping `map_ports` added.
+#[tauri::command]
+pub async fn unmap_ports(app: AppHandle) -> Result<Vec<UnmapResult>, String> {
+  let dry_run = dry_run::is_enabled(&app);
+  tauri::async_runtime::spawn_blocking(move || {
+    let mut results = Vec::new();
+    for mapping in recorded() {
+      let outcome = if dry_run { Ok(()) } else { remove(&mapping) };
+      match &outcome {
+        Ok(()) if dry_run => info!("Dry run: would unmap port {}", mapping.external_port),
+        Ok(()) => {
+          info!("Unmapped port {}", mapping.external_port);
+          forget(&mapping)?;
+        }
+        Err(e) => warn!("Failed to unmap port {}: {}", mapping.external_port, e),
+      }
+      results.push(UnmapResult {
+        mapping,
+        removed: outcome.is_ok(),
+        error: outcome.err(),
+      });
+    }
+    Ok(results)
+  })
+  .await
+  .map_err(|e| e.to_string())?
+}
@@ -0,0 +1,345 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Checking the container registries the platform pulls from.
+//!
+//! A firewall that blocks a registry or an anonymous pull limit that runs out
+//! otherwise shows up as an image pull error halfway through the deploy.
+//! `check_registries` pings the v2 API of each registry, gets a pull token
+//! the way a container runtime would (with the user's credentials when
+//! given), and asks for the manifest of a small public image with `HEAD`,
+//! which Docker Hub doesn't count against the limit. Rate-limit headers are
+//! reported, and a TLS failure is looked into to tell interception from
+//! plain unreachability.
+
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use super::{connectivity, tls};
+use crate::preflight::Outcome;
+use crate::redact;
+
+const TIMEOUT: Duration = Duration::from_secs(15);
+/// Fewer pulls than this left in the window is a warning.
+const LOW_REMAINING: u64 = 20;
+const MANIFEST_TYPES: &str = "application/vnd.oci.image.index.v1+json, \
+  application/vnd.docker.distribution.manifest.list.v2+json, \
+  application/vnd.oci.image.manifest.v1+json, \
+  application/vnd.docker.distribution.manifest.v2+json";
+
+/// A registry the platform pulls from and a small image it serves.
+struct Registry {
+  name: &'static str,
+  /// Where the v2 API is, when it isn't at `name`
+  api_host: &'static str,
+  repository: &'static str,
+  tag: &'static str,
+}
+
+const REGISTRIES: &[Registry] = &[
+  Registry {
+    name: "docker.io",
+    api_host: "registry-1.docker.io",
+    repository: "library/alpine",
+    tag: "latest",
+  },
+  Registry {
+    name: "ghcr.io",
+    api_host: "ghcr.io",
+    repository: "containerd/busybox",
+    tag: "1.36",
+  },
+  Registry {
+    name: "quay.io",
+    api_host: "quay.io",
+    repository: "prometheus/busybox",
+    tag: "latest",
+  },
+  Registry {
+    name: "registry.k8s.io",
+    api_host: "registry.k8s.io",
+    repository: "pause",
+    tag: "3.10",
+  },
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegistryCredentials {
+  /// e.g. `docker.io`
+  pub registry: String,
+  pub username: String,
+  pub password: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RateLimit {
+  pub limit: Option<u64>,
+  pub remaining: Option<u64>,
+  /// Length of the window the limit applies to
+  pub window_secs: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RegistryCheck {
+  pub registry: String,
+  pub image: String,
+  pub outcome: Outcome,
+  pub message: String,
+  pub authenticated: bool,
+  /// Time for the manifest request
+  pub latency_ms: Option<u64>,
+  pub rate_limit: Option<RateLimit>,
+  /// Issuer of a certificate the public roots don't vouch for
+  pub tls_issuer: Option<String>,
+}
+
+/// `100;w=21600` → (100, 21600)
+fn parse_limit(header: Option<&str>) -> (Option<u64>, Option<u64>) {
+  let Some(header) = header else { return (None, None) };
+  let mut parts = header.split(';');
+  let value = parts.next().and_then(|v| v.trim().parse().ok());
+  let window = parts
+    .filter_map(|p| p.trim().strip_prefix("w="))
+    .find_map(|w| w.parse().ok());
+  (value, window)
+}
+
+fn rate_limit(response: &ureq::Response) -> Option<RateLimit> {
+  let (limit, window_secs) = parse_limit(response.header("ratelimit-limit"));
+  let (remaining, _) = parse_limit(response.header("ratelimit-remaining"));
+  (limit.is_some() || remaining.is_some()).then_some(RateLimit {
+    limit,
+    remaining,
+    window_secs,
+  })
+}
+
+/// The parameters of a `Bearer realm="…",service="…"` challenge.
+fn bearer_challenge(header: &str) -> Option<Vec<(String, String)>> {
+  let params = header.trim().strip_prefix("Bearer ")?;
+  Some(
+    params
+      .split(',')
+      .filter_map(|p| p.split_once('='))
+      .map(|(k, v)| (k.trim().to_string(), v.trim().trim_matches('"').to_string()))
+      .collect(),
+  )
+}
+
+fn basic(credentials: &RegistryCredentials) -> String {
+  format!(
+    "Basic {}",
+    base64::engine::general_purpose::STANDARD.encode(format!("{}:{}", credentials.username, credentials.password))
+  )
+}
+
+/// A token per the registry's challenge: for pulling `repository`, or with
+/// no scope just proof that the credentials work.
+fn token(
+  agent: &ureq::Agent,
+  challenge: &[(String, String)],
+  repository: Option<&str>,
+  credentials: Option<&RegistryCredentials>,
+) -> Result<String, String> {
+  let param = |name: &str| challenge.iter().find(|(k, _)| k == name).map(|(_, v)| v.as_str());
+  let realm = param("realm").ok_or("Registry sent a token challenge without a realm")?;
+  let mut request = agent.get(realm);
+  if let Some(repository) = repository {
+    request = request.query("scope", &format!("repository:{}:pull", repository));
+  }
+  if let Some(service) = param("service") {
+    request = request.query("service", service);
+  }
+  if let Some(credentials) = credentials {
+    request = request.set("Authorization", &basic(credentials));
+  }
+  let body: Value = match request.call() {
+    Ok(response) => response
+      .into_json()
+      .map_err(|e| format!("Invalid token response: {}", e))?,
+    Err(ureq::Error::Status(401, _)) | Err(ureq::Error::Status(403, _)) => {
+      return Err("Registry rejected the credentials".to_string())
+    }
+    Err(ureq::Error::Status(code, _)) => return Err(format!("Token service answered HTTP {}", code)),
+    Err(ureq::Error::Transport(transport)) => return Err(format!("Cannot reach the token service: {}", transport)),
+  };
+  body["token"]
+    .as_str()
+    .or_else(|| body["access_token"].as_str())
+    .map(str::to_string)
+    .ok_or_else(|| "Token service returned no token".to_string())
+}
+
+/// Where the v2 API of the registry called `name` is.
+pub fn api_host(name: &str) -> &str {
+  REGISTRIES
+    .iter()
+    .find(|r| r.name == name)
+    .map(|r| r.api_host)
+    .unwrap_or(name)
+}
+
+/// Log in to the registry called `name` the way `docker login` does.
+pub fn test_login(name: &str, credentials: &RegistryCredentials, insecure_tls: bool) -> Result<(), String> {
+  let mut builder = ureq::AgentBuilder::new().timeout(TIMEOUT);
+  if insecure_tls {
+    builder = builder.tls_config(Arc::new(tls::accept_any_certificate()?));
+  }
+  let agent = builder.build();
+  let base = format!("https://{}/v2/", api_host(name));
+  let challenge = match agent.get(&base).call() {
+    Ok(_) => None,
+    Err(ureq::Error::Status(401, response)) => response.header("www-authenticate").and_then(bearer_challenge),
+    Err(ureq::Error::Status(code, _)) => return Err(format!("Registry API answered HTTP {}", code)),
+    Err(ureq::Error::Transport(transport)) => return Err(format!("Cannot reach {}: {}", name, transport)),
+  };
+  match challenge {
+    Some(challenge) => token(&agent, &challenge, None, Some(credentials)).map(|_| ()),
+    None => match agent.get(&base).set("Authorization", &basic(credentials)).call() {
+      Ok(_) => Ok(()),
+      Err(ureq::Error::Status(401, _)) | Err(ureq::Error::Status(403, _)) => {
+        Err("Registry rejected the credentials".to_string())
+      }
+      Err(ureq::Error::Status(code, _)) => Err(format!("Registry API answered HTTP {}", code)),
+      Err(ureq::Error::Transport(transport)) => Err(format!("Cannot reach {}: {}", name, transport)),
+    },
+  }
+}
+
+fn check(registry: &Registry, credentials: Option<&RegistryCredentials>) -> RegistryCheck {
+  let mut result = RegistryCheck {
+    registry: registry.name.to_string(),
+    image: format!("{}/{}:{}", registry.name, registry.repository, registry.tag),
+    outcome: Outcome::Fail,
+    message: String::new(),
+    authenticated: false,
+    latency_ms: None,
+    rate_limit: None,
+    tls_issuer: None,
+  };
+  let agent = ureq::AgentBuilder::new().timeout(TIMEOUT).build();
+  let base = format!("https://{}/v2/", registry.api_host);
+
+  // Ping: 200 means anonymous access, 401 says where to get a token
+  let mut authorization = None;
+  match agent.get(&base).call() {
+    Ok(_) => {
+      if let Some(credentials) = credentials {
+        authorization = Some(basic(credentials));
+      }
+    }
+    Err(ureq::Error::Status(401, response)) => {
+      let challenge = response.header("www-authenticate").unwrap_or_default().to_string();
+      match bearer_challenge(&challenge) {
+        Some(challenge) => match token(&agent, &challenge, Some(registry.repository), credentials) {
+          Ok(token) => authorization = Some(format!("Bearer {}", token)),
+          Err(e) => {
+            result.message = e;
+            return result;
+          }
+        },
+        None => match credentials {
+          Some(credentials) => authorization = Some(basic(credentials)),
+          None => {
+            result.message = "Registry requires credentials".to_string();
+            return result;
+          }
+        },
+      }
+    }
+    Err(ureq::Error::Status(code, _)) => {
+      result.message = format!("Registry API answered HTTP {}", code);
+      return result;
+    }
+    Err(ureq::Error::Transport(transport)) => {
+      // Tell a certificate problem from a blocked port
+      match connectivity::untrusted_issuer(registry.api_host) {
+        Ok(Some(issuer)) => {
+          result.message = format!("{} presented a certificate issued by {}", registry.api_host, issuer);
+          result.tls_issuer = Some(issuer);
+        }
+        _ => result.message = format!("Cannot reach {}: {}", registry.api_host, transport),
+      }
+      return result;
+    }
+  }
+  result.authenticated = credentials.is_some();
+
+  let mut request = agent
+    .head(&format!("{}{}/manifests/{}", base, registry.repository, registry.tag))
+    .set("Accept", MANIFEST_TYPES);
+  if let Some(authorization) = &authorization {
+    request = request.set("Authorization", authorization);
+  }
+  let started = Instant::now();
+  let response = request.call();
+  result.latency_ms = Some(started.elapsed().as_millis() as u64);
+  match response {
+    Ok(response) => {
+      result.rate_limit = rate_limit(&response);
+      match result.rate_limit.as_ref().and_then(|r| r.remaining) {
+        Some(remaining) if remaining < LOW_REMAINING => {
+          result.outcome = Outcome::Warn;
+          result.message = format!(
+            "Reachable, but only {} pull(s) left in the rate-limit window; log in to raise the limit",
+            remaining
+          );
+        }
+        _ => {
+          result.outcome = Outcome::Pass;
+          result.message = "Reachable and serving images".to_string();
+        }
+      }
+    }
+    Err(ureq::Error::Status(429, response)) => {
+      result.rate_limit = rate_limit(&response);
+      result.message = "Pull rate limit exceeded; log in or wait for the window to reset".to_string();
+    }
+    Err(ureq::Error::Status(401, _)) | Err(ureq::Error::Status(403, _)) => {
+      result.message = "Registry refused the test image with these credentials".to_string();
+    }
+    Err(ureq::Error::Status(code, _)) => result.message = format!("Manifest request answered HTTP {}", code),
+    Err(ureq::Error::Transport(transport)) => result.message = format!("Manifest request failed: {}", transport),
+  }
+  result
+}
+
+/// Check every registry the platform pulls from, using `credentials` for
+/// the registries they name.
+#[tauri::command]
+pub async fn check_registries(credentials: Option<Vec<RegistryCredentials>>) -> Result<Vec<RegistryCheck>, String> {
+  let credentials = credentials.unwrap_or_default();
+  for c in &credentials {
+    if !REGISTRIES.iter().any(|r| r.name == c.registry) {
+      return Err(format!("Unknown registry: {}", c.registry));
+    }
+    redact::register(&c.password);
+  }
+  tauri::async_runtime::spawn_blocking(move || {
+    let results: Vec<RegistryCheck> = std::thread::scope(|scope| {
+      let handles: Vec<_> = REGISTRIES
+        .iter()
+        .map(|registry| {
+          let credentials = credentials.iter().find(|c| c.registry == registry.name);
+          scope.spawn(move || check(registry, credentials))
+        })
+        .collect();
+      handles.into_iter().filter_map(|h| h.join().ok()).collect()
+    });
+    for result in &results {
+      match result.outcome {
+        Outcome::Pass => info!("{}: {}", result.registry, result.message),
+        _ => warn!("{}: {}", result.registry, result.message),
+      }
+    }
+    Ok(results)
+  })
+  .await
+  .map_err(|e| e.to_string())?
+}
@@ -0,0 +1,158 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Credentials for the cluster's Harbor and for upstream registries.
+//!
+//! `store_registry_credentials` logs in to the registry first (unless told
+//! not to, e.g. for a Harbor that isn't deployed yet) and files the
+//! credentials in the OS keyring under `registry:<host>`.
+//! `render_registry_auth` writes them as a Docker `config.json` in the run
+//! workspace, which the playbooks turn into pull secrets and containerd
+//! auth, so nobody edits auth files by hand.
+
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use tauri::{AppHandle, Manager};
+use tracing::info;
+
+use super::registries::{self, RegistryCredentials};
+use crate::keyring;
+use crate::validation;
+use crate::workspace::{write_private_file, Workspace};
+
+/// Docker Hub's key in `config.json`, for historical reasons.
+const DOCKER_HUB_KEY: &str = "https://index.docker.io/v1/";
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct StoreRegistryOptions {
+  /// Store without logging in first
+  #[serde(default)]
+  pub skip_test: bool,
+  /// Accept the registry's certificate, e.g. Harbor's bootstrap certificate
+  #[serde(default)]
+  pub insecure_tls: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RegistryAuthConfig {
+  /// The Docker `config.json` for the playbooks
+  pub path: String,
+  pub registries: Vec<String>,
+  /// Asked for, but with no stored credentials
+  pub missing: Vec<String>,
+}
+
+fn account(registry: &str) -> String {
+  format!("registry:{}", registry)
+}
+
+/// `registry` lowercased and checked: a host with an optional port.
+fn normalize(registry: &str) -> Result<String, String> {
+  let registry = registry.trim().to_ascii_lowercase();
+  let (host, port) = match registry.rsplit_once(':') {
+    Some((host, port)) if !host.contains(':') => (host, Some(port)),
+    _ => (registry.as_str(), None),
+  };
+  if !validation::is_valid_host(host) || port.is_some_and(|p| p.parse::<u16>().is_err()) {
+    return Err(format!("Invalid registry: {}", registry));
+  }
+  Ok(registry)
+}
+
+fn stored(registry: &str) -> Result<Option<RegistryCredentials>, String> {
+  let Some(secret) = keyring::get(&account(registry))? else {
+    return Ok(None);
+  };
+  serde_json::from_str(&secret)
+    .map(Some)
+    .map_err(|e| format!("Stored credentials for {} are invalid: {}", registry, e))
+}
+
+/// Log in to `registry` and, if that works, store the credentials.
+#[tauri::command]
+pub async fn store_registry_credentials(
+  registry: String,
+  username: String,
+  password: String,
+  options: Option<StoreRegistryOptions>,
+) -> Result<(), String> {
+  let registry = normalize(&registry)?;
+  if username.trim().is_empty() || password.is_empty() {
+    return Err("Registry username and password must not be empty".to_string());
+  }
+  crate::redact::register(&password);
+  let credentials = RegistryCredentials {
+    registry,
+    username: username.trim().to_string(),
+    password,
+  };
+  let options = options.unwrap_or(StoreRegistryOptions {
+    skip_test: false,
+    insecure_tls: false,
+  });
+  tauri::async_runtime::spawn_blocking(move || {
+    if !options.skip_test {
+      registries::test_login(&credentials.registry, &credentials, options.insecure_tls)?;
+      info!("Logged in to {} as {}", credentials.registry, credentials.username);
+    }
+    let secret = serde_json::to_string(&credentials).map_err(|e| e.to_string())?;
+    keyring::set(&account(&credentials.registry), &secret)
+  })
+  .await
+  .map_err(|e| e.to_string())?
+}
+
+/// Log in to `registry` with the stored credentials.
+#[tauri::command]
+pub async fn test_registry_credentials(registry: String, insecure_tls: Option<bool>) -> Result<(), String> {
+  let registry = normalize(&registry)?;
+  tauri::async_runtime::spawn_blocking(move || {
+    let credentials = stored(&registry)?.ok_or_else(|| format!("No credentials stored for {}", registry))?;
+    registries::test_login(&registry, &credentials, insecure_tls.unwrap_or(false))
+  })
+  .await
+  .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub fn delete_registry_credentials(registry: String) -> Result<(), String> {
+  keyring::delete(&account(&normalize(&registry)?))
+}
+
+/// Write the stored credentials for `registries` as a Docker `config.json`
+/// in the run workspace.
+#[tauri::command]
+pub fn render_registry_auth(app: AppHandle, registries: Vec<String>) -> Result<RegistryAuthConfig, String> {
+  let mut auths = Map::new();
+  let mut config = RegistryAuthConfig {
+    path: String::new(),
+    registries: Vec::new(),
+    missing: Vec::new(),
+  };
+  for registry in registries {
+    let registry = normalize(&registry)?;
+    let Some(credentials) = stored(&registry)? else {
+      config.missing.push(registry);
+      continue;
+    };
+    let key = if registry == "docker.io" {
+      DOCKER_HUB_KEY.to_string()
+    } else {
+      registry.clone()
+    };
+    let auth =
+      base64::engine::general_purpose::STANDARD.encode(format!("{}:{}", credentials.username, credentials.password));
+    auths.insert(key, json!({ "auth": auth }));
+    config.registries.push(registry);
+  }
+
+  let path = app.state::<Workspace>().resolve("registry/config.json")?;
+  let contents = serde_json::to_vec_pretty(&json!({ "auths": Value::Object(auths) })).map_err(|e| e.to_string())?;
+  write_private_file(&path, &contents)?;
+  info!("Wrote registry auth for [{}]", config.registries.join(", "));
+  config.path = path.display().to_string();
+  Ok(config)
+}
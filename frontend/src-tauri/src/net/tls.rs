@@ -0,0 +1,67 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Shared rustls configuration.
+
+use std::sync::Arc;
+
+/// The crypto provider every rustls config in the app is built from.
+pub fn provider() -> Arc<rustls::crypto::CryptoProvider> {
+  Arc::new(rustls::crypto::ring::default_provider())
+}
+
+/// Client settings that accept any server certificate. Only for talking to
+/// things that can't have a trusted certificate (BMCs) or where the
+/// certificate itself is what we want to look at; handshake signatures are
+/// still checked so the connection itself stays sound.
+pub fn accept_any_certificate() -> Result<rustls::ClientConfig, String> {
+  let provider = provider();
+  Ok(
+    rustls::ClientConfig::builder_with_provider(provider.clone())
+      .with_safe_default_protocol_versions()
+      .map_err(|e| e.to_string())?
+      .dangerous()
+      .with_custom_certificate_verifier(Arc::new(AcceptAnyCertificate(provider)))
+      .with_no_client_auth(),
+  )
+}
+
+#[derive(Debug)]
+struct AcceptAnyCertificate(Arc<rustls::crypto::CryptoProvider>);
+
+impl rustls::client::danger::ServerCertVerifier for AcceptAnyCertificate {
+  fn verify_server_cert(
+    &self,
+    _end_entity: &rustls::pki_types::CertificateDer<'_>,
+    _intermediates: &[rustls::pki_types::CertificateDer<'_>],
+    _server_name: &rustls::pki_types::ServerName<'_>,
+    _ocsp_response: &[u8],
+    _now: rustls::pki_types::UnixTime,
+  ) -> Result<rustls::client::danger::ServerCertVerified, rustls::Error> {
+    Ok(rustls::client::danger::ServerCertVerified::assertion())
+  }
+
+  fn verify_tls12_signature(
+    &self,
+    message: &[u8],
+    cert: &rustls::pki_types::CertificateDer<'_>,
+    dss: &rustls::DigitallySignedStruct,
+  ) -> Result<rustls::client::danger::HandshakeSignatureValid, rustls::Error> {
+    rustls::crypto::verify_tls12_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+  }
+
+  fn verify_tls13_signature(
+    &self,
+    message: &[u8],
+    cert: &rustls::pki_types::CertificateDer<'_>,
+    dss: &rustls::DigitallySignedStruct,
+  ) -> Result<rustls::client::danger::HandshakeSignatureValid, rustls::Error> {
+    rustls::crypto::verify_tls13_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+  }
+
+  fn supported_verify_schemes(&self) -> Vec<rustls::SignatureScheme> {
+    self.0.signature_verification_algorithms.supported_schemes()
+  }
+}
@@ -0,0 +1,415 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! VPN clients that get in the way of the cluster's networks.
+//!
+//! Corporate VPN clients add routes for private ranges and send DNS
+//! queries for their domains, or all of them, to the company's resolvers.
+//! Either breaks the install in ways that look like node problems: a
+//! route through the tunnel that overlaps the LAN, pod or service CIDR
+//! sends traffic for the cluster to the company network, and split DNS
+//! makes local names and the cluster domain resolve elsewhere, if at all.
+//! `check_vpn_interference` finds tunnel interfaces (by their link kind on
+//! Linux, by name on macOS), the routes and resolvers that go through
+//! them, and reports each conflict with the planned networks naming the
+//! interface responsible. ZeroTier and Tailscale interfaces are left out:
+//! they are the overlay the cluster itself uses, see [`super::overlay`].
+
+use serde::{Deserialize, Serialize};
+#[cfg(target_os = "linux")]
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::net::Ipv4Addr;
+use std::process::{Command, Stdio};
+
+use super::ipplan::Subnet;
+use crate::platform::find_program;
+use crate::preflight::Outcome;
+
+/// Interface names VPN clients use: OpenVPN and most others (`tun`,
+/// `tap`), WireGuard, PPP/L2TP, macOS `utun` and IPsec, GlobalProtect,
+/// Cisco AnyConnect, Fortinet and NordVPN.
+const VPN_PREFIXES: &[&str] = &[
+  "tun", "tap", "wg", "ppp", "utun", "ipsec", "gpd", "cscotun", "vpn", "fortissl", "nordlynx",
+];
+/// Link kinds `ip -d link` reports for tunnels.
+#[cfg(target_os = "linux")]
+const VPN_KINDS: &[&str] = &["tun", "wireguard", "ppp", "vti", "xfrm"];
+/// The overlays the installer manages itself.
+const OVERLAY_PREFIXES: &[&str] = &["zt", "tailscale"];
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PlannedNetwork {
+  /// e.g. `LAN`, `pod network`
+  pub name: String,
+  pub cidr: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VpnInterface {
+  pub name: String,
+  /// Link kind or the name prefix that gave it away
+  pub kind: String,
+  pub addresses: Vec<String>,
+  /// IPv4 destinations routed through it; `0.0.0.0/0` for a full tunnel
+  pub routes: Vec<String>,
+  /// DNS domains sent to its resolvers; `.` when it takes every query
+  pub dns_domains: Vec<String>,
+  pub dns_servers: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VpnFinding {
+  pub interface: String,
+  pub outcome: Outcome,
+  pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VpnReport {
+  pub interfaces: Vec<VpnInterface>,
+  pub findings: Vec<VpnFinding>,
+}
+
+fn is_vpn_name(name: &str) -> Option<&'static str> {
+  if OVERLAY_PREFIXES.iter().any(|p| name.starts_with(p)) {
+    return None;
+  }
+  VPN_PREFIXES.iter().copied().find(|p| name.starts_with(p))
+}
+
+fn run(program: &str, fallbacks: &[&str], args: &[&str]) -> Option<String> {
+  let output = Command::new(find_program(program, fallbacks)?)
+    .args(args)
+    .env("LC_ALL", "C")
+    .stdin(Stdio::null())
+    .output()
+    .ok()?;
+  output
+    .status
+    .success()
+    .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn interface(name: &str, kind: &str) -> VpnInterface {
+  VpnInterface {
+    name: name.to_string(),
+    kind: kind.to_string(),
+    addresses: Vec::new(),
+    routes: Vec::new(),
+    dns_domains: Vec::new(),
+    dns_servers: Vec::new(),
+  }
+}
+
+/// A route destination as `ip` or `netstat` prints it, in CIDR form.
+/// macOS drops trailing zero octets (`10/8`, `172.16`) and the prefix of
+/// classful networks.
+fn destination(dest: &str) -> Option<String> {
+  if dest == "default" {
+    return Some("0.0.0.0/0".to_string());
+  }
+  let (addr, prefix) = match dest.split_once('/') {
+    Some((addr, prefix)) => (addr, Some(prefix.parse::<u8>().ok()?)),
+    None => (dest, None),
+  };
+  let octets: Vec<&str> = addr.split('.').collect();
+  if octets.is_empty() || octets.len() > 4 {
+    return None;
+  }
+  let mut padded = octets.clone();
+  padded.resize(4, "0");
+  let addr: Ipv4Addr = padded.join(".").parse().ok()?;
+  let prefix = prefix.unwrap_or(8 * octets.len() as u8);
+  Some(format!("{}/{}", addr, prefix))
+}
+
+/// The active VPN interfaces with their routes and DNS settings.
+#[cfg(target_os = "linux")]
+pub fn interfaces() -> Vec<VpnInterface> {
+  let ip = ["/sbin/ip", "/usr/sbin/ip", "/usr/bin/ip"];
+  let mut found: BTreeMap<String, VpnInterface> = BTreeMap::new();
+  let links: Vec<Value> = run("ip", &ip, &["-j", "-d", "addr", "show", "up"])
+    .and_then(|out| serde_json::from_str(&out).ok())
+    .unwrap_or_default();
+  for link in &links {
+    let Some(name) = link["ifname"].as_str() else {
+      continue;
+    };
+    let kind = link["linkinfo"]["info_kind"].as_str().unwrap_or_default();
+    let kind = match is_vpn_name(name) {
+      _ if OVERLAY_PREFIXES.iter().any(|p| name.starts_with(p)) => continue,
+      _ if VPN_KINDS.contains(&kind) => kind,
+      Some(prefix) => prefix,
+      None => continue,
+    };
+    let mut vpn = interface(name, kind);
+    vpn.addresses = link["addr_info"]
+      .as_array()
+      .into_iter()
+      .flatten()
+      .filter(|a| a["family"] == "inet")
+      .filter_map(|a| Some(format!("{}/{}", a["local"].as_str()?, a["prefixlen"].as_u64()?)))
+      .collect();
+    found.insert(name.to_string(), vpn);
+  }
+
+  let routes: Vec<Value> = run("ip", &ip, &["-j", "-4", "route", "show", "table", "all"])
+    .and_then(|out| serde_json::from_str(&out).ok())
+    .unwrap_or_default();
+  for route in &routes {
+    let (Some(dev), Some(dst)) = (route["dev"].as_str(), route["dst"].as_str()) else {
+      continue;
+    };
+    // Local and broadcast entries for the interface's own addresses
+    if route["type"].as_str().is_some_and(|t| t != "unicast") {
+      continue;
+    }
+    if let (Some(vpn), Some(dst)) = (found.get_mut(dev), destination(dst)) {
+      if !vpn.routes.contains(&dst) {
+        vpn.routes.push(dst);
+      }
+    }
+  }
+
+  // systemd-resolved keeps per-link DNS; without it there is no split DNS
+  let mut link: Option<String> = None;
+  for line in run("resolvectl", &["/usr/bin/resolvectl"], &["status"])
+    .unwrap_or_default()
+    .lines()
+  {
+    let line = line.trim();
+    if let Some(rest) = line.strip_prefix("Link ") {
+      link = rest
+        .split_once('(')
+        .and_then(|(_, name)| name.strip_suffix(')'))
+        .map(str::to_string);
+      continue;
+    }
+    if line.starts_with("Global") {
+      link = None;
+    }
+    let Some(vpn) = link.as_ref().and_then(|name| found.get_mut(name)) else {
+      continue;
+    };
+    if let Some(servers) = line.strip_prefix("DNS Servers:") {
+      vpn.dns_servers.extend(servers.split_whitespace().map(str::to_string));
+    } else if let Some(domains) = line.strip_prefix("DNS Domain:") {
+      vpn
+        .dns_domains
+        .extend(domains.split_whitespace().map(|d| match d.trim_start_matches('~') {
+          "." | "" => ".".to_string(),
+          d => d.to_string(),
+        }));
+    }
+  }
+  found.into_values().collect()
+}
+
+#[cfg(target_os = "macos")]
+pub fn interfaces() -> Vec<VpnInterface> {
+  let mut found: BTreeMap<String, VpnInterface> = BTreeMap::new();
+  // The system keeps a few `utun`s for iCloud and the like; only those
+  // with an IPv4 address belong to a VPN
+  let mut current: Option<String> = None;
+  for line in run("ifconfig", &["/sbin/ifconfig"], &[]).unwrap_or_default().lines() {
+    if !line.starts_with(char::is_whitespace) {
+      current = line
+        .split_once(':')
+        .map(|(name, flags)| (name, flags.contains("<UP")))
+        .filter(|(name, up)| *up && is_vpn_name(name).is_some())
+        .map(|(name, _)| name.to_string());
+      continue;
+    }
+    let Some(name) = &current else {
+      continue;
+    };
+    let mut fields = line.split_whitespace();
+    if fields.next() != Some("inet") {
+      continue;
+    }
+    let Some(addr) = fields.next() else {
+      continue;
+    };
+    let prefix = line
+      .split_whitespace()
+      .skip_while(|f| *f != "netmask")
+      .nth(1)
+      .and_then(|m| u32::from_str_radix(m.trim_start_matches("0x"), 16).ok())
+      .map(u32::count_ones)
+      .unwrap_or(32);
+    found
+      .entry(name.clone())
+      .or_insert_with(|| interface(name, is_vpn_name(name).unwrap_or_default()))
+      .addresses
+      .push(format!("{}/{}", addr, prefix));
+  }
+
+  for line in run("netstat", &["/usr/sbin/netstat"], &["-rn", "-f", "inet"])
+    .unwrap_or_default()
+    .lines()
+  {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    let (Some(dest), Some(netif)) = (fields.first(), fields.get(3)) else {
+      continue;
+    };
+    if let (Some(vpn), Some(dst)) = (found.get_mut(*netif), destination(dest)) {
+      if !vpn.routes.contains(&dst) {
+        vpn.routes.push(dst);
+      }
+    }
+  }
+
+  // Resolvers are listed with the interface they are scoped to
+  for resolver in run("scutil", &["/usr/sbin/scutil"], &["--dns"])
+    .unwrap_or_default()
+    .split("resolver #")
+    .skip(1)
+  {
+    let value = |key: &str| -> Vec<String> {
+      resolver
+        .lines()
+        .filter_map(|l| l.split_once(':'))
+        .filter(|(k, _)| k.trim().starts_with(key))
+        .map(|(_, v)| v.trim().to_string())
+        .collect()
+    };
+    let Some(ifname) = value("if_index")
+      .first()
+      .and_then(|v| v.split_once('(').map(|(_, n)| n.trim_end_matches(')').to_string()))
+    else {
+      continue;
+    };
+    let Some(vpn) = found.get_mut(&ifname) else {
+      continue;
+    };
+    let domains = value("domain");
+    if domains.is_empty() {
+      vpn.dns_domains.push(".".to_string());
+    }
+    vpn.dns_domains.extend(domains);
+    vpn.dns_servers.extend(value("nameserver"));
+  }
+  for vpn in found.values_mut() {
+    vpn.dns_domains.sort();
+    vpn.dns_domains.dedup();
+    vpn.dns_servers.sort();
+    vpn.dns_servers.dedup();
+  }
+  found.into_values().collect()
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+pub fn interfaces() -> Vec<VpnInterface> {
+  Vec::new()
+}
+
+fn findings(
+  interfaces: &[VpnInterface],
+  networks: &[(PlannedNetwork, Subnet)],
+  domain: Option<&str>,
+) -> Vec<VpnFinding> {
+  let mut findings = Vec::new();
+  let mut finding = |vpn: &VpnInterface, outcome, message| {
+    findings.push(VpnFinding {
+      interface: vpn.name.clone(),
+      outcome,
+      message,
+    })
+  };
+  for vpn in interfaces {
+    for route in &vpn.routes {
+      let Ok(routed) = Subnet::parse(route) else {
+        continue;
+      };
+      if route == "0.0.0.0/0" {
+        finding(
+          vpn,
+          Outcome::Warn,
+          format!(
+            "{} routes all traffic through the VPN; networks without a more specific route will not reach the nodes",
+            vpn.name
+          ),
+        );
+        continue;
+      }
+      for (network, subnet) in networks.iter().filter(|(_, s)| s.overlaps(&routed)) {
+        finding(
+          vpn,
+          Outcome::Fail,
+          format!(
+            "{} routes {} through the VPN, which overlaps the {} {}",
+            vpn.name, routed, network.name, subnet
+          ),
+        );
+      }
+    }
+    for address in &vpn.addresses {
+      let Some(addr) = address.split('/').next().and_then(|a| a.parse::<Ipv4Addr>().ok()) else {
+        continue;
+      };
+      for (network, subnet) in networks.iter().filter(|(_, s)| s.contains(addr)) {
+        finding(
+          vpn,
+          Outcome::Fail,
+          format!(
+            "{} has the address {} inside the {} {}",
+            vpn.name, addr, network.name, subnet
+          ),
+        );
+      }
+    }
+    if vpn.dns_domains.iter().any(|d| d == ".") {
+      finding(
+        vpn,
+        Outcome::Warn,
+        format!(
+          "{} sends every DNS query to the VPN's resolvers ({}); local node names may not resolve",
+          vpn.name,
+          vpn.dns_servers.join(", ")
+        ),
+      );
+    }
+    if let Some(domain) = domain {
+      let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+      for split in vpn.dns_domains.iter().filter(|d| d.as_str() != ".") {
+        let split = split.trim_end_matches('.').to_ascii_lowercase();
+        if domain == split || domain.ends_with(&format!(".{}", split)) || split.ends_with(&format!(".{}", domain)) {
+          finding(
+            vpn,
+            Outcome::Fail,
+            format!(
+              "{} resolves {} through the VPN, which overlaps the cluster domain {}",
+              vpn.name, split, domain
+            ),
+          );
+        }
+      }
+    }
+  }
+  findings
+}
+
+/// Find active VPN interfaces and how their routes and DNS conflict with
+/// the planned `networks` and cluster `domain`.
+#[tauri::command]
+pub async fn check_vpn_interference(
+  networks: Vec<PlannedNetwork>,
+  domain: Option<String>,
+) -> Result<VpnReport, String> {
+  let networks = networks
+    .into_iter()
+    .map(|n| Subnet::parse(&n.cidr).map(|s| (n, s)))
+    .collect::<Result<Vec<_>, String>>()?;
+  tauri::async_runtime::spawn_blocking(move || {
+    let interfaces = interfaces();
+    VpnReport {
+      findings: findings(&interfaces, &networks, domain.as_deref()),
+      interfaces,
+    }
+  })
+  .await
+  .map_err(|e| e.to_string())
+}
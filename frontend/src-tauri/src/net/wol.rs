@@ -0,0 +1,147 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Wake-on-LAN for nodes that are powered off when the installer starts.
+//!
+//! `wake_node` broadcasts magic packets (6 × 0xFF followed by the MAC 16
+//! times) to UDP port 9, then, if a host was given, waits for its SSH port
+//! to accept connections, re-sending the packet periodically in case the
+//! first one was missed. Progress goes out as `wake-node-progress` events.
+
+use serde::Serialize;
+use std::net::{Ipv4Addr, SocketAddr, TcpStream, ToSocketAddrs, UdpSocket};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+
+pub const PROGRESS_EVENT: &str = "wake-node-progress";
+
+const WOL_PORT: u16 = 9;
+const DEFAULT_TIMEOUT_SECS: u64 = 180;
+const RESEND_EVERY: Duration = Duration::from_secs(15);
+const POLL_EVERY: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "phase", rename_all = "snake_case")]
+pub enum WakeProgress {
+  PacketSent { mac: String, attempt: u32 },
+  Waiting { mac: String, host: String, elapsed_secs: u64 },
+  Online { mac: String, host: String, elapsed_secs: u64 },
+}
+
+/// Accepts `aa:bb:cc:dd:ee:ff`, `aa-bb-cc-dd-ee-ff`, `aabb.ccdd.eeff` and
+/// `aabbccddeeff`.
+pub fn parse_mac(mac: &str) -> Result<[u8; 6], String> {
+  let hex: String = mac.chars().filter(|c| !matches!(c, ':' | '-' | '.')).collect();
+  if hex.len() != 12 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+    return Err(format!("Invalid MAC address: {}", mac));
+  }
+  let mut bytes = [0u8; 6];
+  for (i, byte) in bytes.iter_mut().enumerate() {
+    *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).map_err(|e| e.to_string())?;
+  }
+  Ok(bytes)
+}
+
+pub fn magic_packet(mac: &[u8; 6]) -> Vec<u8> {
+  let mut packet = vec![0xFF; 6];
+  for _ in 0..16 {
+    packet.extend_from_slice(mac);
+  }
+  packet
+}
+
+pub fn send_magic_packet(mac: &[u8; 6], broadcast: Ipv4Addr) -> Result<(), String> {
+  let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))
+    .map_err(|e| format!("Failed to open UDP socket: {}", e))?;
+  socket
+    .set_broadcast(true)
+    .map_err(|e| format!("Failed to enable broadcast: {}", e))?;
+  socket
+    .send_to(&magic_packet(mac), (broadcast, WOL_PORT))
+    .map_err(|e| format!("Failed to send magic packet to {}: {}", broadcast, e))?;
+  Ok(())
+}
+
+fn ssh_reachable(addrs: &[SocketAddr]) -> bool {
+  addrs
+    .iter()
+    .any(|addr| TcpStream::connect_timeout(addr, Duration::from_secs(2)).is_ok())
+}
+
+fn wake(
+  app: &AppHandle,
+  mac_text: &str,
+  broadcast: Ipv4Addr,
+  host: Option<&str>,
+  timeout: Duration,
+) -> Result<bool, String> {
+  let mac = parse_mac(mac_text)?;
+  let mac_text = mac_text.to_ascii_lowercase();
+
+  let mut attempt = 1;
+  send_magic_packet(&mac, broadcast)?;
+  let _ = app.emit(PROGRESS_EVENT, WakeProgress::PacketSent { mac: mac_text.clone(), attempt });
+
+  let Some(host) = host else { return Ok(true) };
+
+  let started = Instant::now();
+  let mut last_sent = Instant::now();
+  while started.elapsed() < timeout {
+    // Resolve each round: the node's DHCP lease may only appear once it's up
+    let addrs: Vec<SocketAddr> = (host, 22).to_socket_addrs().map(|a| a.collect()).unwrap_or_default();
+    if ssh_reachable(&addrs) {
+      let _ = app.emit(
+        PROGRESS_EVENT,
+        WakeProgress::Online {
+          mac: mac_text,
+          host: host.to_string(),
+          elapsed_secs: started.elapsed().as_secs(),
+        },
+      );
+      return Ok(true);
+    }
+
+    if last_sent.elapsed() >= RESEND_EVERY {
+      attempt += 1;
+      send_magic_packet(&mac, broadcast)?;
+      let _ = app.emit(PROGRESS_EVENT, WakeProgress::PacketSent { mac: mac_text.clone(), attempt });
+      last_sent = Instant::now();
+    }
+    let _ = app.emit(
+      PROGRESS_EVENT,
+      WakeProgress::Waiting {
+        mac: mac_text.clone(),
+        host: host.to_string(),
+        elapsed_secs: started.elapsed().as_secs(),
+      },
+    );
+    std::thread::sleep(POLL_EVERY);
+  }
+  Ok(false)
+}
+
+/// Wake a node and optionally wait for `host` to come up. Returns whether the
+/// host answered before the timeout (always `true` without a host).
+#[tauri::command]
+pub async fn wake_node(
+  app: AppHandle,
+  mac: String,
+  broadcast: Option<String>,
+  host: Option<String>,
+  timeout_secs: Option<u64>,
+) -> Result<bool, String> {
+  let broadcast = match broadcast.as_deref().map(str::trim).filter(|b| !b.is_empty()) {
+    Some(b) => b
+      .parse::<Ipv4Addr>()
+      .map_err(|_| format!("Invalid broadcast address: {}", b))?,
+    None => Ipv4Addr::BROADCAST,
+  };
+  let host = host.map(|h| h.trim().to_string()).filter(|h| !h.is_empty());
+  let timeout = Duration::from_secs(timeout_secs.unwrap_or(DEFAULT_TIMEOUT_SECS));
+
+  tauri::async_runtime::spawn_blocking(move || wake(&app, &mac, broadcast, host.as_deref(), timeout))
+    .await
+    .map_err(|e| e.to_string())?
+}
@@ -0,0 +1,93 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! CPU architecture of the installer host and the bundled artifacts for it.
+//!
+//! A bundle can carry per-architecture artifacts next to the backend
+//! sources: a pip wheelhouse in `backend/wheels/<arch>` and helper binaries
+//! in `backend/bin/<arch>`, with `<arch>` in Debian's naming (`amd64`,
+//! `arm64`, as `dpkg --print-architecture` prints it). The shell picks the
+//! directory for the machine it runs on, which on an Apple Silicon Mac
+//! running the x86_64 build under Rosetta is still arm64. A bundle that
+//! carries artifacts but none for this machine fails to start the backend
+//! with an error naming the architectures it does have.
+
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Arch {
+  Amd64,
+  Arm64,
+}
+
+impl Arch {
+  /// Debian's name, which the artifact directories use.
+  pub fn name(self) -> &'static str {
+    match self {
+      Arch::Amd64 => "amd64",
+      Arch::Arm64 => "arm64",
+    }
+  }
+
+  fn parse(name: &str) -> Option<Self> {
+    match name {
+      "x86_64" | "amd64" => Some(Arch::Amd64),
+      "aarch64" | "arm64" => Some(Arch::Arm64),
+      _ => None,
+    }
+  }
+}
+
+/// Whether this process runs translated by Rosetta.
+fn translated() -> bool {
+  cfg!(target_os = "macos")
+    && std::process::Command::new("sysctl")
+      .args(["-n", "sysctl.proc_translated"])
+      .output()
+      .is_ok_and(|out| String::from_utf8_lossy(&out.stdout).trim() == "1")
+}
+
+/// The machine's architecture, which isn't the build's under Rosetta.
+pub fn detect() -> Result<Arch, String> {
+  if translated() {
+    return Ok(Arch::Arm64);
+  }
+  Arch::parse(std::env::consts::ARCH).ok_or_else(|| {
+    format!(
+      "Unsupported CPU architecture {}; the installer runs on x86_64 and arm64",
+      std::env::consts::ARCH
+    )
+  })
+}
+
+/// `base/<arch>` when the bundle has artifacts under `base` for this
+/// architecture, `None` when it has none of that kind at all.
+pub fn artifact_dir(base: &Path, arch: Arch, what: &str) -> Result<Option<PathBuf>, String> {
+  let Ok(entries) = std::fs::read_dir(base) else {
+    return Ok(None);
+  };
+  let dir = base.join(arch.name());
+  if dir.is_dir() {
+    return Ok(Some(dir));
+  }
+  let mut available: Vec<String> = entries
+    .flatten()
+    .filter(|e| e.path().is_dir())
+    .map(|e| e.file_name().to_string_lossy().to_string())
+    .collect();
+  if available.is_empty() {
+    return Ok(None);
+  }
+  available.sort();
+  Err(format!(
+    "This installer has no {} for {} machines (it has them for {}); download the {} build",
+    what,
+    arch.name(),
+    available.join(", "),
+    arch.name()
+  ))
+}
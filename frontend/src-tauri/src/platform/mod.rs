@@ -0,0 +1,43 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Facts about the machine the installer itself is running on.
+
+pub mod arch;
+pub mod os_release;
+pub mod power;
+pub mod regional;
+pub mod session;
+pub mod theme;
+pub mod virt;
+
+use std::path::{Path, PathBuf};
+
+/// Locate an executable on `$PATH`, then in `extra` (absolute paths of
+/// well-known install locations like app bundles that aren't on the PATH of
+/// a GUI-launched process).
+pub fn find_program(name: &str, extra: &[&str]) -> Option<PathBuf> {
+  let on_path = std::env::var_os("PATH")
+    .map(|path| std::env::split_paths(&path).map(|dir| dir.join(name)).collect::<Vec<_>>())
+    .unwrap_or_default();
+  on_path
+    .into_iter()
+    .chain(extra.iter().map(PathBuf::from))
+    .find(|candidate| is_executable(candidate))
+}
+
+fn is_executable(path: &Path) -> bool {
+  #[cfg(unix)]
+  {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::metadata(path)
+      .map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+      .unwrap_or(false)
+  }
+  #[cfg(not(unix))]
+  {
+    path.is_file()
+  }
+}
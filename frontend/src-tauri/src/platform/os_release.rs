@@ -0,0 +1,240 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Install-host OS detection.
+//!
+//! The playbooks only run against Ubuntu targets, but the installer itself
+//! also has requirements on the machine it runs on (Python venv layout,
+//! packaging, shell tools). Rather than letting an unsupported distro fail
+//! somewhere inside a playbook, the shell detects the OS once at startup and
+//! the wizard gates on the verdict returned by `get_os_compatibility`.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use crate::i18n;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OsInfo {
+  /// `linux`, `macos`, ... (same values as `std::env::consts::OS`)
+  pub family: String,
+  /// os-release `ID` (`ubuntu`, `debian`, ...) or `macos`
+  pub id: String,
+  /// os-release `ID_LIKE`, split on whitespace
+  pub id_like: Vec<String>,
+  pub version_id: String,
+  pub codename: Option<String>,
+  pub pretty_name: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Support {
+  /// Tested and expected to work
+  Supported,
+  /// Probably works (derivative or neighbouring release) but is not tested
+  Untested,
+  /// Known not to work; the wizard should block
+  Unsupported,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OsCompatibility {
+  pub os: OsInfo,
+  pub support: Support,
+  pub message: String,
+}
+
+/// Ubuntu releases the installer host is tested on, by `VERSION_ID` prefix.
+pub const SUPPORTED_UBUNTU: &[&str] = &["24.04"];
+
+/// Ubuntu releases that are expected to work but are not part of the test matrix.
+pub const UNTESTED_UBUNTU: &[&str] = &["24.10", "25.04", "25.10", "26.04"];
+
+/// Oldest macOS release supported, matching `bundle.macOS.minimumSystemVersion`.
+const MIN_MACOS: (u32, u32) = (11, 0);
+
+static DETECTED: OnceLock<(OsInfo, Verdict)> = OnceLock::new();
+
+/// Parse the contents of an os-release file into a key/value map.
+///
+/// Follows os-release(5): `KEY=value` lines, values optionally wrapped in
+/// single or double quotes, with backslash escapes inside double quotes.
+pub fn parse_os_release(contents: &str) -> HashMap<String, String> {
+  let mut fields = HashMap::new();
+  for line in contents.lines() {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+      continue;
+    }
+    if let Some((key, value)) = line.split_once('=') {
+      fields.insert(key.trim().to_string(), unquote(value.trim()));
+    }
+  }
+  fields
+}
+
+fn unquote(value: &str) -> String {
+  if value.len() >= 2 && value.starts_with('\'') && value.ends_with('\'') {
+    return value[1..value.len() - 1].to_string();
+  }
+  if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+    let mut out = String::new();
+    let mut chars = value[1..value.len() - 1].chars();
+    while let Some(c) = chars.next() {
+      if c == '\\' {
+        if let Some(escaped) = chars.next() {
+          out.push(escaped);
+        }
+      } else {
+        out.push(c);
+      }
+    }
+    return out;
+  }
+  value.to_string()
+}
+
+fn detect_linux() -> OsInfo {
+  let contents = std::fs::read_to_string("/etc/os-release")
+    .or_else(|_| std::fs::read_to_string("/usr/lib/os-release"))
+    .unwrap_or_default();
+  let fields = parse_os_release(&contents);
+  let get = |key: &str| fields.get(key).cloned().unwrap_or_default();
+
+  let id = get("ID");
+  let version_id = get("VERSION_ID");
+  let pretty_name = fields
+    .get("PRETTY_NAME")
+    .cloned()
+    .unwrap_or_else(|| format!("{} {}", id, version_id).trim().to_string());
+
+  OsInfo {
+    family: "linux".to_string(),
+    id,
+    id_like: get("ID_LIKE").split_whitespace().map(str::to_string).collect(),
+    version_id,
+    codename: fields
+      .get("VERSION_CODENAME")
+      .or_else(|| fields.get("UBUNTU_CODENAME"))
+      .cloned(),
+    pretty_name,
+  }
+}
+
+fn detect_macos() -> OsInfo {
+  let version_id = std::process::Command::new("sw_vers")
+    .arg("-productVersion")
+    .output()
+    .ok()
+    .filter(|out| out.status.success())
+    .map(|out| String::from_utf8_lossy(&out.stdout).trim().to_string())
+    .unwrap_or_default();
+
+  OsInfo {
+    family: "macos".to_string(),
+    id: "macos".to_string(),
+    id_like: Vec::new(),
+    pretty_name: format!("macOS {}", version_id).trim().to_string(),
+    version_id,
+    codename: None,
+  }
+}
+
+/// Detect the running OS.
+pub fn detect() -> OsInfo {
+  match std::env::consts::OS {
+    "linux" => detect_linux(),
+    "macos" => detect_macos(),
+    other => OsInfo {
+      family: other.to_string(),
+      id: other.to_string(),
+      id_like: Vec::new(),
+      version_id: String::new(),
+      codename: None,
+      pretty_name: other.to_string(),
+    },
+  }
+}
+
+fn parse_major_minor(version: &str) -> Option<(u32, u32)> {
+  let mut parts = version.split('.');
+  let major = parts.next()?.trim().parse().ok()?;
+  let minor = parts.next().and_then(|p| p.trim().parse().ok()).unwrap_or(0);
+  Some((major, minor))
+}
+
+/// Outcome of the matrix lookup; the message is rendered per call so it
+/// follows the current locale.
+struct Verdict {
+  support: Support,
+  message_id: &'static str,
+  args: Vec<(&'static str, String)>,
+}
+
+/// Compare detected OS facts against the supported matrix.
+fn evaluate(os: &OsInfo) -> Verdict {
+  let verdict = |support, message_id, args: &[(&'static str, &str)]| Verdict {
+    support,
+    message_id,
+    args: args.iter().map(|(k, v)| (*k, v.to_string())).collect(),
+  };
+  let name = os.pretty_name.as_str();
+
+  match os.family.as_str() {
+    "linux" if os.id == "ubuntu" => {
+      if SUPPORTED_UBUNTU.iter().any(|v| os.version_id.starts_with(v)) {
+        verdict(Support::Supported, "os-supported", &[("os", name)])
+      } else if UNTESTED_UBUNTU.iter().any(|v| os.version_id.starts_with(v)) {
+        verdict(Support::Untested, "os-untested-ubuntu", &[("os", name)])
+      } else {
+        verdict(Support::Unsupported, "os-unsupported-linux", &[("os", name)])
+      }
+    }
+    "linux" if os.id_like.iter().any(|like| like == "ubuntu") => {
+      verdict(Support::Untested, "os-untested-derivative", &[("os", name)])
+    }
+    "linux" if name.is_empty() => verdict(
+      Support::Unsupported,
+      "os-unsupported-linux",
+      &[("os", &i18n::t("os-unknown-linux"))],
+    ),
+    "linux" => verdict(Support::Unsupported, "os-unsupported-linux", &[("os", name)]),
+    "macos" => match parse_major_minor(&os.version_id) {
+      Some(version) if version >= MIN_MACOS => {
+        verdict(Support::Supported, "os-supported", &[("os", name)])
+      }
+      Some(_) => verdict(
+        Support::Unsupported,
+        "os-macos-too-old",
+        &[("os", name), ("min", &format!("{}.{}", MIN_MACOS.0, MIN_MACOS.1))],
+      ),
+      None => verdict(Support::Untested, "os-macos-unknown", &[]),
+    },
+    _ => verdict(Support::Unsupported, "os-unsupported", &[("os", name)]),
+  }
+}
+
+/// Detect and evaluate the OS once; later calls reuse the cached verdict.
+pub fn compatibility() -> OsCompatibility {
+  let (os, verdict) = DETECTED.get_or_init(|| {
+    let os = detect();
+    let verdict = evaluate(&os);
+    (os, verdict)
+  });
+  let args: Vec<(&str, &str)> = verdict.args.iter().map(|(k, v)| (*k, v.as_str())).collect();
+
+  OsCompatibility {
+    os: os.clone(),
+    support: verdict.support,
+    message: i18n::t_args(verdict.message_id, &args),
+  }
+}
+
+#[tauri::command]
+pub fn get_os_compatibility() -> OsCompatibility {
+  compatibility()
+}
@@ -0,0 +1,145 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Whether the installer host runs on battery.
+//!
+//! A full install takes hours, and a laptop that sleeps or dies halfway
+//! through leaves the nodes mid-playbook. The wizard asks
+//! `get_power_status` before it starts deploying and warns on battery, or
+//! refuses to start when `block_on_battery` is set in settings.json. Linux
+//! reads `/sys/class/power_supply`, macOS asks `pmset`.
+
+use serde::Serialize;
+use tauri::State;
+
+use crate::i18n;
+use crate::settings::SettingsStore;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PowerSource {
+  Ac,
+  Battery,
+  /// No battery found, or the platform doesn't say
+  Unknown,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PowerStatus {
+  pub source: PowerSource,
+  pub has_battery: bool,
+  pub charge_percent: Option<u8>,
+  /// The deployment must not start until the machine is plugged in
+  pub block: bool,
+  /// Set when there is something to warn about
+  pub message: Option<String>,
+}
+
+struct Reading {
+  source: PowerSource,
+  has_battery: bool,
+  charge_percent: Option<u8>,
+}
+
+#[cfg(target_os = "linux")]
+fn read() -> Reading {
+  let mut reading = Reading {
+    source: PowerSource::Unknown,
+    has_battery: false,
+    charge_percent: None,
+  };
+  let Ok(entries) = std::fs::read_dir("/sys/class/power_supply") else {
+    return reading;
+  };
+  let mut on_mains = None;
+  let mut discharging = false;
+  for entry in entries.flatten() {
+    let path = entry.path();
+    let attr = |name: &str| {
+      std::fs::read_to_string(path.join(name))
+        .map(|v| v.trim().to_string())
+        .unwrap_or_default()
+    };
+    match attr("type").as_str() {
+      "Mains" | "USB" => {
+        let online = attr("online") == "1";
+        on_mains = Some(on_mains.unwrap_or(false) || online);
+      }
+      // Peripherals (mice, keyboards) report a scope of "Device"
+      "Battery" if attr("scope") != "Device" => {
+        reading.has_battery = true;
+        discharging |= attr("status") == "Discharging";
+        if let Ok(capacity) = attr("capacity").parse::<u8>() {
+          reading.charge_percent = Some(reading.charge_percent.map_or(capacity, |c| c.min(capacity)));
+        }
+      }
+      _ => {}
+    }
+  }
+  reading.source = match (on_mains, reading.has_battery) {
+    (Some(true), _) => PowerSource::Ac,
+    (Some(false), true) => PowerSource::Battery,
+    (None, true) if discharging => PowerSource::Battery,
+    (None, true) => PowerSource::Ac,
+    _ => PowerSource::Unknown,
+  };
+  reading
+}
+
+#[cfg(target_os = "macos")]
+fn read() -> Reading {
+  // "Now drawing from 'Battery Power'\n -InternalBattery-0 (id=…)\t85%; discharging; …"
+  let output = std::process::Command::new("pmset")
+    .args(["-g", "batt"])
+    .output()
+    .map(|out| String::from_utf8_lossy(&out.stdout).to_string())
+    .unwrap_or_default();
+  let charge_percent = output
+    .lines()
+    .find(|l| l.contains("InternalBattery"))
+    .and_then(|l| l.split('\t').nth(1))
+    .and_then(|l| l.split('%').next())
+    .and_then(|v| v.trim().parse::<u8>().ok());
+  Reading {
+    source: if output.contains("'Battery Power'") {
+      PowerSource::Battery
+    } else if output.contains("'AC Power'") {
+      PowerSource::Ac
+    } else {
+      PowerSource::Unknown
+    },
+    has_battery: charge_percent.is_some(),
+    charge_percent,
+  }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn read() -> Reading {
+  Reading {
+    source: PowerSource::Unknown,
+    has_battery: false,
+    charge_percent: None,
+  }
+}
+
+/// Where the installer host draws power from right now.
+#[tauri::command]
+pub fn get_power_status(settings: State<'_, SettingsStore>) -> PowerStatus {
+  let reading = read();
+  let on_battery = reading.source == PowerSource::Battery;
+  let block = on_battery && settings.get().block_on_battery.unwrap_or(false);
+  let message = on_battery.then(|| match (block, reading.charge_percent) {
+    (true, _) => i18n::t("power-battery-blocked"),
+    (false, Some(percent)) => i18n::t_args("power-battery-charge", &[("percent", &percent.to_string())]),
+    (false, None) => i18n::t("power-battery"),
+  });
+  PowerStatus {
+    source: reading.source,
+    has_battery: reading.has_battery,
+    charge_percent: reading.charge_percent,
+    block,
+    message,
+  }
+}
@@ -0,0 +1,362 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Timezone, locale and keyboard layout of the installer host.
+//!
+//! The playbooks set these on every node. The person running the installer
+//! is usually sitting next to the cluster, so this machine's settings are
+//! the best default; `get_regional_defaults` reports them in the forms the
+//! playbooks expect (an IANA zone name, a glibc `ll_CC.UTF-8` locale and an
+//! XKB layout) so the wizard can preselect them.
+//!
+//! The keyboard layout also goes into the cloud-init seeds and autoinstall
+//! configs, which is what the console of a fresh node or VM uses. The user
+//! can override it with `set_keyboard_layout` when the nodes' keyboards
+//! differ from this machine's.
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::process::Command;
+use tauri::{AppHandle, Manager, State};
+
+use crate::i18n;
+use crate::settings::SettingsStore;
+
+const ZONEINFO_DIRS: &[&str] = &["/usr/share/zoneinfo", "/var/db/timezone/zoneinfo"];
+const FALLBACK_TIMEZONE: &str = "UTC";
+const FALLBACK_LOCALE: &str = "en_US.UTF-8";
+const FALLBACK_KEYBOARD: &str = "us";
+const FALLBACK_KEYBOARD_MODEL: &str = "pc105";
+
+/// macOS keyboard layout names and the XKB layout and variant they match.
+const MACOS_LAYOUTS: &[(&str, &str, &str)] = &[
+  ("US", "us", ""),
+  ("ABC", "us", ""),
+  ("USInternational-PC", "us", "intl"),
+  ("Dvorak", "us", "dvorak"),
+  ("Colemak", "us", "colemak"),
+  ("British", "gb", ""),
+  ("British-PC", "gb", ""),
+  ("Irish", "ie", ""),
+  ("Spanish", "es", ""),
+  ("Spanish-ISO", "es", ""),
+  ("Catalan", "es", "cat"),
+  ("LatinAmerican", "latam", ""),
+  ("Portuguese", "pt", ""),
+  ("Brazilian", "br", ""),
+  ("Brazilian-Pro", "br", ""),
+  ("French", "fr", ""),
+  ("French-PC", "fr", ""),
+  ("Belgian", "be", ""),
+  ("Canadian-CSA", "ca", ""),
+  ("German", "de", ""),
+  ("Austrian", "at", ""),
+  ("SwissGerman", "ch", ""),
+  ("SwissFrench", "ch", "fr"),
+  ("Italian", "it", ""),
+  ("Italian-Pro", "it", ""),
+  ("Dutch", "nl", ""),
+  ("Danish", "dk", ""),
+  ("Swedish", "se", ""),
+  ("Swedish-Pro", "se", ""),
+  ("Norwegian", "no", ""),
+  ("Finnish", "fi", ""),
+  ("Icelandic", "is", ""),
+  ("Polish", "pl", ""),
+  ("PolishPro", "pl", ""),
+  ("Czech", "cz", ""),
+  ("Hungarian", "hu", ""),
+  ("Greek", "gr", ""),
+  ("Turkish", "tr", ""),
+  ("Russian", "ru", ""),
+  ("Ukrainian", "ua", ""),
+  ("Hebrew", "il", ""),
+  ("Japanese", "jp", ""),
+];
+
+/// Languages whose usual country code isn't the language code uppercased.
+const DEFAULT_COUNTRY: &[(&str, &str)] = &[
+  ("en", "US"),
+  ("ja", "JP"),
+  ("zh", "CN"),
+  ("ko", "KR"),
+  ("sv", "SE"),
+  ("da", "DK"),
+  ("cs", "CZ"),
+  ("el", "GR"),
+  ("uk", "UA"),
+  ("ca", "ES"),
+  ("eu", "ES"),
+  ("gl", "ES"),
+  ("nb", "NO"),
+  ("he", "IL"),
+  ("hi", "IN"),
+  ("vi", "VN"),
+  ("fa", "IR"),
+  ("ar", "EG"),
+];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RegionalDefaults {
+  /// IANA zone name, e.g. `Europe/Madrid`
+  pub timezone: String,
+  /// Where the timezone came from: `tz`, `etc_timezone`, `localtime` or `default`
+  pub timezone_source: String,
+  /// glibc locale, e.g. `es_ES.UTF-8`
+  pub locale: String,
+  /// False when nothing usable was found and the fallbacks are used
+  pub locale_detected: bool,
+  pub keyboard: KeyboardLayout,
+  /// Where the layout came from: `override`, `etc_default_keyboard`,
+  /// `vconsole`, `xorg`, `macos` or `default`
+  pub keyboard_source: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyboardLayout {
+  /// XKB layout, e.g. `es`, or several separated by commas
+  pub layout: String,
+  /// XKB variant, one per layout; empty for the default
+  #[serde(default)]
+  pub variant: String,
+  /// XKB model; `pc105` when empty
+  #[serde(default)]
+  pub model: String,
+}
+
+impl KeyboardLayout {
+  fn new(layout: &str, variant: &str, model: &str) -> KeyboardLayout {
+    KeyboardLayout {
+      layout: layout.to_string(),
+      variant: variant.to_string(),
+      model: Some(model)
+        .filter(|m| !m.is_empty())
+        .unwrap_or(FALLBACK_KEYBOARD_MODEL)
+        .to_string(),
+    }
+  }
+
+  /// XKB names are lowercase letters, digits, `_` and `-`, comma-separated.
+  pub fn validate(&self) -> Result<(), String> {
+    let is_name = |name: &str| {
+      name
+        .chars()
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '_' | '-' | ','))
+    };
+    if self.layout.is_empty() || self.layout.split(',').any(str::is_empty) || !is_name(&self.layout) {
+      return Err(format!("Invalid keyboard layout: {}", self.layout));
+    }
+    if !is_name(&self.variant) {
+      return Err(format!("Invalid keyboard variant: {}", self.variant));
+    }
+    if self.model.contains(',') || !is_name(&self.model) {
+      return Err(format!("Invalid keyboard model: {}", self.model));
+    }
+    Ok(())
+  }
+}
+
+fn is_known_zone(name: &str) -> bool {
+  !name.is_empty()
+    && !name.starts_with('/')
+    && !name.split('/').any(|part| part.is_empty() || part == "..")
+    && ZONEINFO_DIRS.iter().any(|dir| Path::new(dir).join(name).is_file())
+}
+
+/// `Europe/Madrid` from `/usr/share/zoneinfo/Europe/Madrid` and similar.
+fn zone_from_path(path: &Path) -> Option<String> {
+  let path = path.to_string_lossy();
+  let (_, zone) = path.rsplit_once("zoneinfo/")?;
+  // Some distros link into zoneinfo/posix/ or zoneinfo/right/
+  let zone = zone
+    .strip_prefix("posix/")
+    .or_else(|| zone.strip_prefix("right/"))
+    .unwrap_or(zone);
+  Some(zone.to_string())
+}
+
+fn detect_timezone() -> (String, &'static str) {
+  if let Ok(tz) = std::env::var("TZ") {
+    let tz = tz.trim_start_matches(':');
+    let zone = if tz.starts_with('/') {
+      zone_from_path(Path::new(tz))
+    } else {
+      Some(tz.to_string())
+    };
+    if let Some(zone) = zone.filter(|z| is_known_zone(z)) {
+      return (zone, "tz");
+    }
+  }
+
+  if let Ok(contents) = std::fs::read_to_string("/etc/timezone") {
+    let zone = contents.trim();
+    if is_known_zone(zone) {
+      return (zone.to_string(), "etc_timezone");
+    }
+  }
+
+  if let Some(zone) = std::fs::read_link("/etc/localtime")
+    .ok()
+    .and_then(|target| zone_from_path(&target))
+    .filter(|z| is_known_zone(z))
+  {
+    return (zone, "localtime");
+  }
+
+  (FALLBACK_TIMEZONE.to_string(), "default")
+}
+
+/// Turn `es_ES.UTF-8`, `es-AR`, `en_US@rg=gbzzzz` or `de` into a glibc
+/// UTF-8 locale name.
+fn normalize_locale(raw: &str) -> Option<String> {
+  let tag = raw.split('@').next()?.split('.').next()?.replace('-', "_");
+  let mut parts = tag.split('_');
+  let language = parts.next()?.to_ascii_lowercase();
+  if !(2..=3).contains(&language.len()) || !language.chars().all(|c| c.is_ascii_alphabetic()) {
+    return None;
+  }
+  // Skip a script subtag (zh_Hans_CN) to get to the region
+  let country = parts
+    .find(|p| p.len() == 2 && p.chars().all(|c| c.is_ascii_alphabetic()))
+    .map(|p| p.to_ascii_uppercase())
+    .or_else(|| {
+      DEFAULT_COUNTRY
+        .iter()
+        .find(|(lang, _)| *lang == language)
+        .map(|(_, country)| country.to_string())
+    })
+    .unwrap_or_else(|| language.to_ascii_uppercase());
+  Some(format!("{}_{}.UTF-8", language, country))
+}
+
+fn detect_locale() -> Option<String> {
+  if let Some(locale) = i18n::system_locale().and_then(|l| normalize_locale(&l)) {
+    return Some(locale);
+  }
+  // GUI sessions on Linux don't always export LANG; fall back to the system default
+  for file in ["/etc/default/locale", "/etc/locale.conf"] {
+    let Ok(contents) = std::fs::read_to_string(file) else { continue };
+    for line in contents.lines() {
+      if let Some(value) = line.trim().strip_prefix("LANG=") {
+        if let Some(locale) = normalize_locale(value.trim_matches('"')) {
+          return Some(locale);
+        }
+      }
+    }
+  }
+  None
+}
+
+/// `KEY=value` and `KEY="value"` lines of a shell-style config file.
+fn config_value(contents: &str, key: &str) -> Option<String> {
+  contents.lines().find_map(|line| {
+    let value = line.trim().strip_prefix(key)?.strip_prefix('=')?;
+    Some(value.trim().trim_matches('"').to_string()).filter(|v| !v.is_empty())
+  })
+}
+
+/// `Option "XkbLayout" "es"` lines of an xorg.conf snippet.
+fn xorg_option(contents: &str, option: &str) -> Option<String> {
+  contents.lines().find_map(|line| {
+    let mut quoted = line.trim().strip_prefix("Option")?.split('"').skip(1).step_by(2);
+    if !quoted.next()?.eq_ignore_ascii_case(option) {
+      return None;
+    }
+    quoted.next().map(str::to_string).filter(|v| !v.is_empty())
+  })
+}
+
+/// `es` from `com.apple.keylayout.Spanish-ISO`.
+fn macos_layout(source_id: &str) -> Option<KeyboardLayout> {
+  let name = source_id.trim().strip_prefix("com.apple.keylayout.")?;
+  MACOS_LAYOUTS
+    .iter()
+    .find(|(mac, _, _)| *mac == name)
+    .map(|(_, layout, variant)| KeyboardLayout::new(layout, variant, ""))
+}
+
+fn detect_keyboard() -> Option<(KeyboardLayout, &'static str)> {
+  if cfg!(target_os = "macos") {
+    let output = Command::new("defaults")
+      .args(["read", "com.apple.HIToolbox", "AppleCurrentKeyboardLayoutInputSourceID"])
+      .output()
+      .ok()?;
+    return macos_layout(&String::from_utf8_lossy(&output.stdout)).map(|k| (k, "macos"));
+  }
+
+  // Debian and Ubuntu
+  if let Ok(contents) = std::fs::read_to_string("/etc/default/keyboard") {
+    if let Some(layout) = config_value(&contents, "XKBLAYOUT") {
+      let variant = config_value(&contents, "XKBVARIANT").unwrap_or_default();
+      let model = config_value(&contents, "XKBMODEL").unwrap_or_default();
+      return Some((KeyboardLayout::new(&layout, &variant, &model), "etc_default_keyboard"));
+    }
+  }
+  // Fedora and Arch keep the X11 layout next to the console keymap
+  if let Ok(contents) = std::fs::read_to_string("/etc/vconsole.conf") {
+    let layout = config_value(&contents, "XKBLAYOUT")
+      // Console keymaps are named after the layout: `es`, `de-latin1`
+      .or_else(|| Some(config_value(&contents, "KEYMAP")?.split('-').next()?.to_string()));
+    if let Some(layout) = layout {
+      let variant = config_value(&contents, "XKBVARIANT").unwrap_or_default();
+      let model = config_value(&contents, "XKBMODEL").unwrap_or_default();
+      return Some((KeyboardLayout::new(&layout, &variant, &model), "vconsole"));
+    }
+  }
+  let contents = std::fs::read_to_string("/etc/X11/xorg.conf.d/00-keyboard.conf").ok()?;
+  let layout = xorg_option(&contents, "XkbLayout")?;
+  let variant = xorg_option(&contents, "XkbVariant").unwrap_or_default();
+  let model = xorg_option(&contents, "XkbModel").unwrap_or_default();
+  Some((KeyboardLayout::new(&layout, &variant, &model), "xorg"))
+}
+
+/// The layout for the nodes and where it came from: the user's override,
+/// else this machine's.
+fn keyboard_with_source(settings: &SettingsStore) -> (KeyboardLayout, &'static str) {
+  if let Some(keyboard) = settings.get().keyboard_layout {
+    return (keyboard, "override");
+  }
+  detect_keyboard()
+    .filter(|(k, _)| k.validate().is_ok())
+    .unwrap_or_else(|| (KeyboardLayout::new(FALLBACK_KEYBOARD, "", ""), "default"))
+}
+
+/// The keyboard layout provisioned nodes get.
+pub fn keyboard(app: &AppHandle) -> KeyboardLayout {
+  match app.try_state::<SettingsStore>() {
+    Some(settings) => keyboard_with_source(&settings).0,
+    None => detect_keyboard().map_or_else(|| KeyboardLayout::new(FALLBACK_KEYBOARD, "", ""), |(k, _)| k),
+  }
+}
+
+#[tauri::command]
+pub fn get_regional_defaults(settings: State<'_, SettingsStore>) -> RegionalDefaults {
+  let (timezone, source) = detect_timezone();
+  let locale = detect_locale();
+  let (keyboard, keyboard_source) = keyboard_with_source(&settings);
+  RegionalDefaults {
+    timezone,
+    timezone_source: source.to_string(),
+    locale_detected: locale.is_some(),
+    locale: locale.unwrap_or_else(|| FALLBACK_LOCALE.to_string()),
+    keyboard,
+    keyboard_source: keyboard_source.to_string(),
+  }
+}
+
+/// Use `keyboard` on the nodes instead of this machine's layout, or go back
+/// to it with `None`.
+#[tauri::command]
+pub fn set_keyboard_layout(
+  settings: State<'_, SettingsStore>,
+  keyboard: Option<KeyboardLayout>,
+) -> Result<RegionalDefaults, String> {
+  let keyboard = keyboard.map(|k| KeyboardLayout::new(k.layout.trim(), k.variant.trim(), k.model.trim()));
+  if let Some(keyboard) = &keyboard {
+    keyboard.validate()?;
+  }
+  settings.update(|s| s.keyboard_layout = keyboard)?;
+  Ok(get_regional_defaults(settings))
+}
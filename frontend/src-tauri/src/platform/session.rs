@@ -0,0 +1,125 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Desktop session type and the WebKitGTK workarounds it needs.
+//!
+//! WebKitGTK's DMA-BUF renderer shows a white window with NVIDIA's driver
+//! (https://bugs.webkit.org/show_bug.cgi?id=254901), on Wayland the driver
+//! also aborts with "Error 71 (Protocol error)" unless explicit sync is
+//! off, and without a GPU render node at all, in most VMs, accelerated
+//! compositing is what leaves the window blank. [`apply`] picks a profile
+//! from the session type and GPU before GTK starts, adds the fallbacks
+//! `render_fallback` asks for after a launch that didn't render, leaves any
+//! variable the user already set alone, and remembers the profile so setup
+//! can log it. On macOS and Windows it does nothing.
+
+use std::path::Path;
+use std::sync::OnceLock;
+
+#[derive(Debug, Clone)]
+pub struct Profile {
+  /// e.g. `wayland-nvidia`
+  pub name: &'static str,
+  /// How many [`FALLBACKS`] were added after failed launches
+  pub fallback: usize,
+  /// `wayland`, `x11` or whatever `XDG_SESSION_TYPE` says
+  pub session: String,
+  /// From `XDG_CURRENT_DESKTOP`
+  pub compositor: Option<String>,
+  /// Variables set by the profile
+  pub applied: Vec<(&'static str, &'static str)>,
+  /// Variables the profile would set but the user already had
+  pub kept: Vec<&'static str>,
+}
+
+static APPLIED: OnceLock<Profile> = OnceLock::new();
+
+fn session_type() -> String {
+  match std::env::var("XDG_SESSION_TYPE").ok().filter(|s| !s.is_empty()) {
+    Some(session) => session.to_lowercase(),
+    None if std::env::var_os("WAYLAND_DISPLAY").is_some() => "wayland".to_string(),
+    None if std::env::var_os("DISPLAY").is_some() => "x11".to_string(),
+    None => "unknown".to_string(),
+  }
+}
+
+fn has_render_node() -> bool {
+  std::fs::read_dir("/dev/dri")
+    .map(|entries| {
+      entries
+        .flatten()
+        .any(|e| e.file_name().to_string_lossy().starts_with("renderD"))
+    })
+    .unwrap_or(false)
+}
+
+fn choose(session: &str, nvidia: bool, render_node: bool) -> (&'static str, Vec<(&'static str, &'static str)>) {
+  match (session, nvidia, render_node) {
+    ("wayland", true, _) => (
+      "wayland-nvidia",
+      vec![
+        ("WEBKIT_DISABLE_DMABUF_RENDERER", "1"),
+        ("__NV_DISABLE_EXPLICIT_SYNC", "1"),
+      ],
+    ),
+    (_, true, _) => ("x11-nvidia", vec![("WEBKIT_DISABLE_DMABUF_RENDERER", "1")]),
+    (_, false, false) => (
+      "software",
+      vec![
+        ("WEBKIT_DISABLE_DMABUF_RENDERER", "1"),
+        ("WEBKIT_DISABLE_COMPOSITING_MODE", "1"),
+      ],
+    ),
+    ("wayland", false, true) => ("wayland", Vec::new()),
+    _ => ("x11", Vec::new()),
+  }
+}
+
+/// Variables added on top of the profile at each fallback level, each
+/// level keeping the ones before it.
+pub const FALLBACKS: &[(&str, &str)] = &[
+  ("WEBKIT_DISABLE_DMABUF_RENDERER", "1"),
+  ("WEBKIT_DISABLE_COMPOSITING_MODE", "1"),
+  ("LIBGL_ALWAYS_SOFTWARE", "1"),
+];
+
+/// Set the renderer variables for this session, plus the first `fallback`
+/// entries of [`FALLBACKS`]. Must run before Tauri creates the first
+/// webview.
+pub fn apply(fallback: usize) {
+  if std::env::consts::OS != "linux" {
+    return;
+  }
+  let session = session_type();
+  let nvidia = Path::new("/proc/driver/nvidia/version").exists();
+  let (name, mut vars) = choose(&session, nvidia, has_render_node());
+  for &(key, value) in FALLBACKS.iter().take(fallback) {
+    if !vars.iter().any(|(k, _)| *k == key) {
+      vars.push((key, value));
+    }
+  }
+  let mut profile = Profile {
+    name,
+    fallback,
+    session,
+    compositor: std::env::var("XDG_CURRENT_DESKTOP").ok().filter(|c| !c.is_empty()),
+    applied: Vec::new(),
+    kept: Vec::new(),
+  };
+  for (key, value) in vars {
+    if std::env::var_os(key).is_some() {
+      profile.kept.push(key);
+    } else {
+      std::env::set_var(key, value);
+      profile.applied.push((key, value));
+    }
+  }
+  let _ = APPLIED.set(profile);
+}
+
+/// The profile [`apply`] picked, once it has run on Linux.
+pub fn applied() -> Option<&'static Profile> {
+  APPLIED.get()
+}
@@ -0,0 +1,191 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! The desktop's light or dark preference, and changes to it.
+//!
+//! macOS and Windows tell the window directly, which arrives as a
+//! `ThemeChanged` window event. WebKitGTK only follows the GTK theme, which
+//! GNOME and KDE no longer switch with the dark style, so on Linux the
+//! preference comes from the XDG desktop portal's `color-scheme`, then
+//! GNOME's gsettings key, and a watcher on whichever answered reports
+//! changes. Either way the webview gets a `system-theme-changed` event.
+
+use serde::Serialize;
+use std::io::{BufRead, BufReader};
+use std::process::{Child, Command, Stdio};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, WindowEvent};
+use tracing::debug;
+
+use crate::windows::MAIN_WINDOW;
+
+pub const EVENT: &str = "system-theme-changed";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SystemTheme {
+  Light,
+  Dark,
+}
+
+/// Last theme reported, so the watchers don't repeat themselves
+static LAST: Mutex<Option<SystemTheme>> = Mutex::new(None);
+/// The Linux watcher process, killed on shutdown
+static WATCHER: Mutex<Option<Child>> = Mutex::new(None);
+
+impl From<tauri::Theme> for SystemTheme {
+  fn from(theme: tauri::Theme) -> Self {
+    match theme {
+      tauri::Theme::Dark => SystemTheme::Dark,
+      _ => SystemTheme::Light,
+    }
+  }
+}
+
+fn run(program: &str, args: &[&str]) -> Option<String> {
+  let out = Command::new(program).args(args).output().ok()?;
+  out
+    .status
+    .success()
+    .then(|| String::from_utf8_lossy(&out.stdout).trim().to_string())
+}
+
+/// `(<<uint32 1>>,)`; 1 is dark, 2 light, 0 no preference.
+fn portal_theme() -> Option<SystemTheme> {
+  let out = run(
+    "gdbus",
+    &[
+      "call",
+      "--session",
+      "--dest",
+      "org.freedesktop.portal.Desktop",
+      "--object-path",
+      "/org/freedesktop/portal/desktop",
+      "--method",
+      "org.freedesktop.portal.Settings.Read",
+      "org.freedesktop.appearance",
+      "color-scheme",
+    ],
+  )?;
+  match out.trim_end_matches([')', '>', ',']).rsplit(' ').next()? {
+    "1" => Some(SystemTheme::Dark),
+    "2" => Some(SystemTheme::Light),
+    _ => None,
+  }
+}
+
+/// `'prefer-dark'`, else a `-dark` GTK theme name.
+fn gsettings_theme() -> Option<SystemTheme> {
+  let scheme = run("gsettings", &["get", "org.gnome.desktop.interface", "color-scheme"])?;
+  match scheme.trim_matches('\'') {
+    "prefer-dark" => Some(SystemTheme::Dark),
+    "prefer-light" => Some(SystemTheme::Light),
+    _ => {
+      let gtk = run("gsettings", &["get", "org.gnome.desktop.interface", "gtk-theme"])?;
+      Some(if gtk.to_lowercase().contains("dark") {
+        SystemTheme::Dark
+      } else {
+        SystemTheme::Light
+      })
+    }
+  }
+}
+
+fn detect(app: &AppHandle) -> SystemTheme {
+  if std::env::consts::OS == "linux" {
+    if let Some(theme) = portal_theme().or_else(gsettings_theme) {
+      return theme;
+    }
+  }
+  app
+    .get_webview_window(MAIN_WINDOW)
+    .and_then(|w| w.theme().ok())
+    .map(SystemTheme::from)
+    .unwrap_or(SystemTheme::Light)
+}
+
+fn report(app: &AppHandle, theme: SystemTheme) {
+  let Ok(mut last) = LAST.lock() else {
+    return;
+  };
+  if *last != Some(theme) {
+    debug!("System theme is now {:?}", theme);
+    *last = Some(theme);
+    let _ = app.emit(EVENT, theme);
+  }
+}
+
+/// The desktop's current light or dark preference.
+#[tauri::command]
+pub fn get_system_theme(app: AppHandle) -> SystemTheme {
+  let theme = detect(&app);
+  if let Ok(mut last) = LAST.lock() {
+    *last = Some(theme);
+  }
+  theme
+}
+
+/// Window event hook for the platforms that report theme changes.
+pub fn handle_window_event(app: &AppHandle, event: &WindowEvent) {
+  if let WindowEvent::ThemeChanged(theme) = event {
+    if std::env::consts::OS != "linux" {
+      report(app, SystemTheme::from(*theme));
+    }
+  }
+}
+
+/// Watch the portal (or gsettings) for changes on Linux.
+pub fn watch(app: &AppHandle) {
+  if std::env::consts::OS != "linux" {
+    return;
+  }
+  let app = app.clone();
+  std::thread::spawn(move || {
+    let monitor = if portal_theme().is_some() {
+      Command::new("gdbus")
+        .args([
+          "monitor",
+          "--session",
+          "--dest",
+          "org.freedesktop.portal.Desktop",
+          "--object-path",
+          "/org/freedesktop/portal/desktop",
+        ])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+    } else if gsettings_theme().is_some() {
+      Command::new("gsettings")
+        .args(["monitor", "org.gnome.desktop.interface"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+    } else {
+      return;
+    };
+    let Ok(mut child) = monitor else {
+      return;
+    };
+    let Some(stdout) = child.stdout.take() else {
+      return;
+    };
+    if let Ok(mut watcher) = WATCHER.lock() {
+      *watcher = Some(child);
+    }
+    for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+      if line.contains("color-scheme") || line.contains("gtk-theme") {
+        report(&app, detect(&app));
+      }
+    }
+  });
+}
+
+/// Stop the Linux watcher, e.g. when the main window closes.
+pub fn stop() {
+  if let Some(mut child) = WATCHER.lock().ok().and_then(|mut w| w.take()) {
+    let _ = child.kill();
+    let _ = child.wait();
+  }
+}
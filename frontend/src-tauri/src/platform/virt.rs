@@ -0,0 +1,195 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Whether a machine is bare metal, a VM, a container or WSL.
+//!
+//! The k8s and LXD snaps need a full machine, and LXD VMs also need KVM,
+//! which a VM only has with nested virtualization turned on. Installs in
+//! WSL or a container fail deep inside snapd, and in a VM without nested
+//! virtualization only once the first LXD VM starts, so the wizard checks
+//! up front: `get_host_environment` for the machine the installer runs on,
+//! and the `virt` preflight check (from [`SCRIPT`]'s output) for each node.
+//! Detection uses the CPU's hypervisor flag, DMI vendor strings, container
+//! markers under `/run` and `/proc`, and the WSL interop entry.
+
+use serde::Serialize;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use crate::i18n;
+
+/// Prints the facts [`Facts::parse`] reads, for running on a node.
+pub const SCRIPT: &str = r#"
+echo "wsl=$( { [ -e /proc/sys/fs/binfmt_misc/WSLInterop ] || grep -qi microsoft /proc/sys/kernel/osrelease; } 2>/dev/null && echo yes)"
+echo "container=$(cat /run/systemd/container 2>/dev/null || { [ -e /.dockerenv ] && echo docker; } || { [ -e /run/.containerenv ] && echo podman; })"
+echo "hypervisor=$(grep -qw hypervisor /proc/cpuinfo 2>/dev/null && echo yes)"
+echo "vendor=$(cat /sys/class/dmi/id/sys_vendor 2>/dev/null)"
+echo "product=$(cat /sys/class/dmi/id/product_name 2>/dev/null)"
+echo "kvm=$([ -c /dev/kvm ] && echo yes)"
+"#;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Kind {
+  BareMetal,
+  Vm,
+  Container,
+  Wsl,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Facts {
+  pub wsl: bool,
+  /// `docker`, `lxc`, `podman`, ... from the container markers
+  pub container: Option<String>,
+  /// The CPU reports running under a hypervisor
+  pub hypervisor: bool,
+  pub vendor: String,
+  pub product: String,
+  /// `/dev/kvm` exists
+  pub kvm: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HostEnvironment {
+  pub kind: Kind,
+  /// Hypervisor or container engine, when known
+  pub platform: Option<String>,
+  /// LXD can run VMs here
+  pub kvm: bool,
+  /// The machine can also be a cluster node
+  pub can_be_node: bool,
+  pub message: String,
+}
+
+static DETECTED: OnceLock<Facts> = OnceLock::new();
+
+impl Facts {
+  fn local() -> Self {
+    let read = |path: &str| std::fs::read_to_string(path).unwrap_or_default().trim().to_string();
+    match std::env::consts::OS {
+      "linux" => Self {
+        wsl: std::env::var_os("WSL_DISTRO_NAME").is_some()
+          || Path::new("/proc/sys/fs/binfmt_misc/WSLInterop").exists()
+          || read("/proc/sys/kernel/osrelease").to_lowercase().contains("microsoft"),
+        container: Some(read("/run/systemd/container"))
+          .filter(|c| !c.is_empty())
+          .or_else(|| Path::new("/.dockerenv").exists().then(|| "docker".to_string()))
+          .or_else(|| Path::new("/run/.containerenv").exists().then(|| "podman".to_string())),
+        hypervisor: read("/proc/cpuinfo")
+          .lines()
+          .any(|l| l.starts_with("flags") && l.split_whitespace().any(|f| f == "hypervisor")),
+        vendor: read("/sys/class/dmi/id/sys_vendor"),
+        product: read("/sys/class/dmi/id/product_name"),
+        kvm: Path::new("/dev/kvm").exists(),
+      },
+      "macos" => Self {
+        hypervisor: std::process::Command::new("sysctl")
+          .args(["-n", "kern.hv_vmm_present"])
+          .output()
+          .is_ok_and(|out| String::from_utf8_lossy(&out.stdout).trim() == "1"),
+        ..Self::default()
+      },
+      _ => Self::default(),
+    }
+  }
+
+  /// Read [`SCRIPT`]'s output.
+  pub fn parse(output: &str) -> Self {
+    let fact = |key: &str| {
+      output
+        .lines()
+        .find_map(|l| l.trim().strip_prefix(key)?.strip_prefix('='))
+        .unwrap_or("")
+        .trim()
+        .to_string()
+    };
+    Self {
+      wsl: fact("wsl") == "yes",
+      container: Some(fact("container")).filter(|c| !c.is_empty()),
+      hypervisor: fact("hypervisor") == "yes",
+      vendor: fact("vendor"),
+      product: fact("product"),
+      kvm: fact("kvm") == "yes",
+    }
+  }
+
+  /// The hypervisor, going by the DMI strings it fills in.
+  fn hypervisor_name(&self) -> Option<&'static str> {
+    let vendor = self.vendor.as_str();
+    let product = self.product.as_str();
+    let name = if vendor.contains("QEMU") || product.contains("KVM") {
+      "QEMU/KVM"
+    } else if vendor.contains("VMware") {
+      "VMware"
+    } else if vendor.contains("innotek") || product.contains("VirtualBox") {
+      "VirtualBox"
+    } else if vendor.contains("Microsoft") && product.contains("Virtual Machine") {
+      "Hyper-V"
+    } else if vendor.contains("Xen") {
+      "Xen"
+    } else if vendor.contains("Parallels") {
+      "Parallels"
+    } else if vendor.contains("Amazon") {
+      "Amazon EC2"
+    } else if vendor.contains("Google") {
+      "Google Compute Engine"
+    } else {
+      return None;
+    };
+    Some(name)
+  }
+
+  pub fn kind(&self) -> Kind {
+    if self.wsl {
+      Kind::Wsl
+    } else if self.container.is_some() {
+      Kind::Container
+    } else if self.hypervisor || self.hypervisor_name().is_some() {
+      Kind::Vm
+    } else {
+      Kind::BareMetal
+    }
+  }
+
+  pub fn platform(&self) -> Option<String> {
+    match self.kind() {
+      Kind::Wsl => Some("WSL".to_string()),
+      Kind::Container => self.container.clone(),
+      Kind::Vm => self.hypervisor_name().map(str::to_string),
+      Kind::BareMetal => None,
+    }
+  }
+}
+
+fn evaluate(facts: &Facts) -> HostEnvironment {
+  let kind = facts.kind();
+  let platform = facts.platform();
+  let name = platform.clone().unwrap_or_else(|| i18n::t("env-unknown-platform"));
+  let linux = std::env::consts::OS == "linux";
+  let (can_be_node, message) = match kind {
+    Kind::Wsl => (false, i18n::t("env-wsl")),
+    Kind::Container => (false, i18n::t_args("env-container", &[("platform", &name)])),
+    Kind::Vm if !linux => (false, i18n::t("env-controller-only")),
+    Kind::Vm if facts.kvm => (true, i18n::t_args("env-vm-nested", &[("platform", &name)])),
+    Kind::Vm => (true, i18n::t_args("env-vm-no-kvm", &[("platform", &name)])),
+    Kind::BareMetal if !linux => (false, i18n::t("env-controller-only")),
+    Kind::BareMetal if facts.kvm => (true, i18n::t("env-bare-metal")),
+    Kind::BareMetal => (true, i18n::t("env-no-kvm")),
+  };
+  HostEnvironment {
+    kind,
+    platform,
+    kvm: facts.kvm,
+    can_be_node,
+    message,
+  }
+}
+
+/// Where the installer itself runs; detected once per process.
+#[tauri::command]
+pub fn get_host_environment() -> HostEnvironment {
+  evaluate(DETECTED.get_or_init(Facts::local))
+}
@@ -0,0 +1,365 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! The shell's own checkout of the thinkube playbooks repository.
+//!
+//! Left to itself the backend makes a fresh shallow clone of
+//! `THINKUBE_BRANCH` in /tmp for every run. `sync_playbooks` instead keeps
+//! a full clone in the profile's data directory, fetches it and checks out
+//! a pinned tag, branch or commit, which is remembered as `playbooks_ref`
+//! in settings.json. The backend is told where the checkout is through
+//! `THINKUBE_PLAYBOOKS_DIR` and uses it when it exists. Offline, or when
+//! the fetch fails, an already-present checkout is used as it is, as long
+//! as it has the requested ref. This wraps the `git` CLI, so the user's
+//! credential helpers and proxy settings apply; clone and fetch progress
+//! goes out as `playbooks-progress` events. Nothing is checked out unless
+//! its commit is signed with one of the build's release keys (see
+//! [`verify::allowed_signers`]).
+
+use serde::Serialize;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tracing::{info, warn};
+
+use crate::cancel;
+use crate::platform::find_program;
+use crate::profiles;
+use crate::settings::SettingsStore;
+use crate::verify;
+
+pub const PROGRESS_EVENT: &str = "playbooks-progress";
+const CHECKOUT: &str = "thinkube";
+const DEFAULT_REPO: &str = "https://github.com/thinkube/thinkube.git";
+const DEFAULT_BRANCH: &str = "main";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlaybooksProgress {
+  /// git's own stage name, e.g. `Receiving objects`
+  pub stage: String,
+  pub percent: Option<u8>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlaybooksCheckout {
+  pub path: String,
+  pub present: bool,
+  pub remote_url: Option<String>,
+  /// The ref the checkout is pinned to, or the default branch
+  pub requested_ref: String,
+  pub commit: Option<String>,
+  /// Tags and branches pointing at the checked-out commit
+  pub refs: Vec<String>,
+  pub describe: Option<String>,
+  pub dirty: bool,
+  /// The last sync used the checkout without fetching
+  pub offline: bool,
+}
+
+/// `THINKUBE_*`, overridden at runtime, else baked in, else the default.
+fn setting(runtime: &str, baked: Option<&'static str>, default: &str) -> String {
+  std::env::var(runtime)
+    .ok()
+    .filter(|v| !v.is_empty())
+    .or_else(|| baked.filter(|v| !v.is_empty()).map(str::to_string))
+    .unwrap_or_else(|| default.to_string())
+}
+
+/// The playbooks repository, from `THINKUBE_REPO_URL`.
+pub fn repo_url() -> String {
+  setting(
+    "THINKUBE_REPO_URL",
+    option_env!("THINKUBE_BUILD_REPO_URL"),
+    DEFAULT_REPO,
+  )
+}
+
+/// The branch the backend clones, from `THINKUBE_BRANCH`.
+pub fn default_branch() -> String {
+  setting("THINKUBE_BRANCH", option_env!("THINKUBE_BUILD_BRANCH"), DEFAULT_BRANCH)
+}
+
+pub fn checkout_dir(app: &AppHandle) -> Result<PathBuf, String> {
+  Ok(profiles::data_dir(app)?.join(CHECKOUT))
+}
+
+fn git_program() -> Result<PathBuf, String> {
+  find_program("git", &[]).ok_or_else(|| "git is not installed".to_string())
+}
+
+fn git(dir: &Path, args: &[&str]) -> Option<String> {
+  let output = Command::new(git_program().ok()?)
+    .arg("-C")
+    .arg(dir)
+    .args(args)
+    .output()
+    .ok()?;
+  if !output.status.success() {
+    return None;
+  }
+  Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Refuse `commit` unless it carries an SSH signature from a release key.
+/// A build without keys can only warn.
+fn check_signed(dir: &Path, commit: &str) -> Result<(), String> {
+  let Some(signers) = verify::allowed_signers() else {
+    warn!("This build has no signing keys, so playbooks commit {} is not verified", commit);
+    return Ok(());
+  };
+  let file = dir.join(".git").join("thinkube-allowed-signers");
+  std::fs::write(&file, signers).map_err(|e| format!("Failed to write {}: {}", file.display(), e))?;
+  let allowed = format!("gpg.ssh.allowedSignersFile={}", file.display());
+  git(dir, &["-c", "gpg.format=ssh", "-c", &allowed, "verify-commit", commit])
+    .map(|_| ())
+    .ok_or_else(|| format!("Playbooks commit {} is not signed with a release key", commit))
+}
+
+/// Run a clone or fetch, relaying `--progress` output. git redraws its
+/// progress line with carriage returns, so stderr is split on those too.
+fn run_with_progress(app: &AppHandle, dir: Option<&Path>, args: &[&str]) -> Result<(), String> {
+  let mut cmd = Command::new(git_program()?);
+  if let Some(dir) = dir {
+    cmd.arg("-C").arg(dir);
+  }
+  cmd
+    .args(args)
+    // Fail instead of waiting on a password prompt nobody can see
+    .env("GIT_TERMINAL_PROMPT", "0")
+    .stdin(Stdio::null())
+    .stdout(Stdio::null())
+    .stderr(Stdio::piped());
+  let operation = cancel::begin(app, &format!("git {}", args.first().unwrap_or(&"")));
+  let mut child = operation
+    .spawn(&mut cmd)
+    .map_err(|e| format!("Failed to run git: {}", e))?;
+  let mut stderr = child.stderr.take().ok_or("git has no stderr")?;
+  let mut tail = String::new();
+  let mut buf = [0u8; 4096];
+  let mut pending = String::new();
+  loop {
+    let n = stderr.read(&mut buf).map_err(|e| e.to_string())?;
+    if n == 0 {
+      break;
+    }
+    pending.push_str(&String::from_utf8_lossy(&buf[..n]));
+    while let Some(end) = pending.find(['\r', '\n']) {
+      let line: String = pending.drain(..=end).collect();
+      let line = line.trim();
+      if line.is_empty() {
+        continue;
+      }
+      if let Some(progress) = parse_progress(line) {
+        let _ = app.emit(PROGRESS_EVENT, progress);
+      } else {
+        tail = line.to_string();
+      }
+    }
+  }
+  let status = child.wait().map_err(|e| e.to_string())?;
+  operation.forget(child.id());
+  if operation.is_cancelled() {
+    return Err(format!("git {} cancelled", args.first().unwrap_or(&"")));
+  }
+  if status.success() {
+    Ok(())
+  } else {
+    Err(if tail.is_empty() {
+      format!("git {} failed", args.first().unwrap_or(&""))
+    } else {
+      tail
+    })
+  }
+}
+
+/// `Receiving objects:  45% (450/1000), 1.20 MiB | 2.00 MiB/s`
+fn parse_progress(line: &str) -> Option<PlaybooksProgress> {
+  let line = line.strip_prefix("remote: ").unwrap_or(line);
+  let (stage, rest) = line.split_once(':')?;
+  let percent = rest.trim_start().split_once('%')?.0.trim().parse::<u8>().ok()?;
+  Some(PlaybooksProgress {
+    stage: stage.trim().to_string(),
+    percent: Some(percent.min(100)),
+  })
+}
+
+/// The commit `reference` names: a remote branch first, so a pin to a
+/// branch follows it, then a tag or commit.
+fn resolve(dir: &Path, reference: &str) -> Option<String> {
+  [format!("refs/remotes/origin/{}", reference), reference.to_string()]
+    .iter()
+    .find_map(|candidate| {
+      git(
+        dir,
+        &["rev-parse", "--verify", "--quiet", &format!("{}^{{commit}}", candidate)],
+      )
+    })
+}
+
+fn describe(app: &AppHandle, requested_ref: String, offline: bool) -> Result<PlaybooksCheckout, String> {
+  let dir = checkout_dir(app)?;
+  let present = dir.join(".git").exists();
+  let commit = present.then(|| git(&dir, &["rev-parse", "HEAD"])).flatten();
+  let refs = if present {
+    git(
+      &dir,
+      &["for-each-ref", "--points-at", "HEAD", "--format=%(refname:short)"],
+    )
+    .map(|out| out.lines().map(str::to_string).collect())
+    .unwrap_or_default()
+  } else {
+    Vec::new()
+  };
+  Ok(PlaybooksCheckout {
+    path: dir.display().to_string(),
+    present,
+    remote_url: present
+      .then(|| git(&dir, &["remote", "get-url", "origin"]))
+      .flatten()
+      .map(|url| crate::redact::redact(&url).into_owned()),
+    requested_ref,
+    commit,
+    refs,
+    describe: present
+      .then(|| git(&dir, &["describe", "--tags", "--always", "--dirty"]))
+      .flatten(),
+    dirty: present && git(&dir, &["status", "--porcelain"]).is_some_and(|status| !status.is_empty()),
+    offline,
+  })
+}
+
+fn sync(app: &AppHandle, reference: &str, offline: bool) -> Result<PlaybooksCheckout, String> {
+  let dir = checkout_dir(app)?;
+  let present = dir.join(".git").exists();
+  let mut used_offline = offline;
+  if !present {
+    if offline {
+      return Err(format!(
+        "There is no playbooks checkout at {} to use offline",
+        dir.display()
+      ));
+    }
+    let url = repo_url();
+    info!("Cloning {} into {}", url, dir.display());
+    if let Some(parent) = dir.parent() {
+      std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    let target = dir.to_string_lossy().into_owned();
+    if let Err(e) = run_with_progress(app, None, &["clone", "--progress", "--no-checkout", &url, &target]) {
+      let _ = std::fs::remove_dir_all(&dir);
+      return Err(format!("Failed to clone {}: {}", url, e));
+    }
+  } else if !offline {
+    if let Err(e) = run_with_progress(
+      app,
+      Some(&dir),
+      &["fetch", "--progress", "--tags", "--prune", "--force", "origin"],
+    ) {
+      // The checkout on disk still works if it has the ref
+      warn!("Failed to fetch the playbooks, using the checkout as it is: {}", e);
+      used_offline = true;
+    }
+  }
+
+  let commit = resolve(&dir, reference).ok_or_else(|| {
+    if used_offline {
+      format!("The playbooks checkout has no {} and it couldn't be fetched", reference)
+    } else {
+      format!("{} is not a branch, tag or commit of {}", reference, repo_url())
+    }
+  })?;
+  check_signed(&dir, &commit)?;
+  if git(&dir, &["status", "--porcelain"]).is_some_and(|status| !status.is_empty()) {
+    return Err(format!(
+      "The playbooks checkout at {} has local changes; commit or discard them first",
+      dir.display()
+    ));
+  }
+  if git(&dir, &["rev-parse", "HEAD"]).as_deref() != Some(commit.as_str()) {
+    git(&dir, &["checkout", "--quiet", "--detach", &commit])
+      .ok_or_else(|| format!("Failed to check out {} ({})", reference, commit))?;
+  }
+  info!("Playbooks at {} ({})", reference, commit);
+  describe(app, reference.to_string(), used_offline)
+}
+
+/// Write the checkout, with all its refs, to a git bundle at `dest`.
+/// Returns the checked-out commit.
+pub fn write_bundle(app: &AppHandle, dest: &Path) -> Result<String, String> {
+  let dir = checkout_dir(app)?;
+  let commit = git(&dir, &["rev-parse", "HEAD"]).ok_or("There is no playbooks checkout; sync the playbooks first")?;
+  let target = dest.to_string_lossy().into_owned();
+  git(&dir, &["bundle", "create", &target, "HEAD", "--all"])
+    .ok_or_else(|| format!("Failed to bundle the playbooks into {}", dest.display()))?;
+  Ok(commit)
+}
+
+/// Replace the checkout with a clone of the git bundle at `bundle`, at
+/// `commit`. origin points back at the repository, so a later online
+/// sync fetches from it as usual.
+pub fn install_bundle(app: &AppHandle, bundle: &Path, commit: &str) -> Result<PlaybooksCheckout, String> {
+  let dir = checkout_dir(app)?;
+  if dir.exists() {
+    std::fs::remove_dir_all(&dir).map_err(|e| format!("Failed to remove {}: {}", dir.display(), e))?;
+  }
+  if let Some(parent) = dir.parent() {
+    std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+  }
+  let source = bundle.to_string_lossy().into_owned();
+  let target = dir.to_string_lossy().into_owned();
+  run_with_progress(app, None, &["clone", "--progress", "--no-checkout", &source, &target])
+    .map_err(|e| format!("Failed to clone the bundled playbooks: {}", e))?;
+  git(&dir, &["remote", "set-url", "origin", &repo_url()]);
+  if let Err(e) = check_signed(&dir, commit) {
+    let _ = std::fs::remove_dir_all(&dir);
+    return Err(e);
+  }
+  git(&dir, &["checkout", "--quiet", "--detach", commit])
+    .ok_or_else(|| format!("The bundled playbooks don't have {}", commit))?;
+  info!("Playbooks installed from {} at {}", bundle.display(), commit);
+  describe(app, commit.to_string(), true)
+}
+
+/// Clone or update the playbooks and check out `reference`, or the pinned
+/// ref, or `THINKUBE_BRANCH`. A given `reference` becomes the new pin.
+#[tauri::command]
+pub async fn sync_playbooks(
+  app: AppHandle,
+  settings: State<'_, SettingsStore>,
+  reference: Option<String>,
+  offline: Option<bool>,
+) -> Result<PlaybooksCheckout, String> {
+  let reference = reference
+    .map(|r| r.trim().to_string())
+    .filter(|r| !r.is_empty())
+    .or_else(|| settings.get().playbooks_ref)
+    .unwrap_or_else(default_branch);
+  if reference.starts_with('-') {
+    return Err(format!("Invalid ref: {}", reference));
+  }
+  let handle = app.clone();
+  let pinned = reference.clone();
+  let checkout = tauri::async_runtime::spawn_blocking(move || sync(&handle, &pinned, offline.unwrap_or(false)))
+    .await
+    .map_err(|e| e.to_string())??;
+  if settings.get().playbooks_ref.as_deref() != Some(reference.as_str()) {
+    settings.update(|s| s.playbooks_ref = Some(reference))?;
+  }
+  Ok(checkout)
+}
+
+/// What the checkout has checked out now, without touching the network.
+#[tauri::command]
+pub async fn get_playbooks_checkout(app: AppHandle) -> Result<PlaybooksCheckout, String> {
+  let requested = app
+    .state::<SettingsStore>()
+    .get()
+    .playbooks_ref
+    .unwrap_or_else(default_branch);
+  tauri::async_runtime::spawn_blocking(move || describe(&app, requested, false))
+    .await
+    .map_err(|e| e.to_string())?
+}
@@ -0,0 +1,601 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! The preflight suite. Each check runs one short script on the node and
+//! turns its output into a verdict; the thresholds match what the
+//! playbooks need to get through a full platform install.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::{Check, Node, Verdict};
+use crate::platform::os_release::{self, SUPPORTED_UBUNTU, UNTESTED_UBUNTU};
+use crate::platform::virt::{self, Kind};
+use crate::remote::{link, mac, smart, swap};
+
+pub const CONNECT: &str = "ssh";
+
+const MIN_MEMORY_GIB: u64 = 8;
+const RECOMMENDED_MEMORY_GIB: u64 = 16;
+const MIN_ROOT_FREE_GIB: u64 = 50;
+const RECOMMENDED_ROOT_FREE_GIB: u64 = 100;
+/// etcd starts complaining about peers at around a second of skew; leases
+/// and certificate `notBefore` checks fail soon after.
+const WARN_CLOCK_OFFSET_MS: i64 = 500;
+const MAX_CLOCK_OFFSET_MS: i64 = 2000;
+/// Oldest systemd that manages a cgroup v2 hierarchy well enough for
+/// kubelet's systemd cgroup driver
+const MIN_SYSTEMD_VERSION: u32 = 244;
+/// cgroup controllers kubelet needs to enforce pod limits
+const REQUIRED_CGROUP_CONTROLLERS: &[&str] = &["cpu", "memory", "pids"];
+/// Kernel modules the CNI and container runtime load: bridge filtering for
+/// Service traffic, overlayfs for image layers and VXLAN for the overlay
+/// network between nodes
+const REQUIRED_MODULES: &[&str] = &["br_netfilter", "overlay", "vxlan"];
+/// sysctls the cluster playbooks set, with the module that provides them
+const REQUIRED_SYSCTLS: &[(&str, Option<&str>)] = &[
+  ("net.ipv4.ip_forward", None),
+  ("net.bridge.bridge-nf-call-iptables", Some("br_netfilter")),
+  ("net.bridge.bridge-nf-call-ip6tables", Some("br_netfilter")),
+];
+/// Oldest kernel Cilium and the k8s snap's containerd support
+const MIN_KERNEL: (u32, u32) = (5, 4);
+/// Kernel flavours Ubuntu builds ZFS and prebuilt NVIDIA modules for
+const UBUNTU_KERNEL_FLAVOURS: &[&str] = &["generic", "lowlatency", "generic-64k", "raspi"];
+/// Time sync daemons, any of which counts
+const TIME_SERVICES: &[&str] = &["chrony", "chronyd", "systemd-timesyncd", "ntp", "ntpsec", "openntpd"];
+
+pub const SUITE: &[Check] = &[
+  Check {
+    id: CONNECT,
+    run: connect,
+  },
+  Check { id: "os", run: os },
+  Check { id: "sudo", run: sudo },
+  Check {
+    id: "python",
+    run: python,
+  },
+  Check {
+    id: "memory",
+    run: memory,
+  },
+  Check {
+    id: "disk",
+    run: disk,
+  },
+  Check {
+    id: "time",
+    run: clock,
+  },
+  Check {
+    id: "mac",
+    run: mac_policy,
+  },
+  Check {
+    id: "swap",
+    run: swap,
+  },
+  Check {
+    id: "disk-health",
+    run: disk_health,
+  },
+  Check {
+    id: "cgroup",
+    run: cgroup,
+  },
+  Check {
+    id: "kernel",
+    run: kernel_modules,
+  },
+  Check {
+    id: "kernel-version",
+    run: kernel_version,
+  },
+  Check {
+    id: "snapd",
+    run: snapd,
+  },
+  Check {
+    id: "virt",
+    run: virtualization,
+  },
+  Check {
+    id: "wireless",
+    run: wireless,
+  },
+];
+
+/// Trimmed stdout of a script that must exit 0.
+fn output(node: &Node, script: &str) -> Result<String, String> {
+  let out = node.sh(script)?;
+  if out.status != Some(0) {
+    return Err(out.stderr.trim().lines().last().unwrap_or("command failed").to_string());
+  }
+  Ok(out.stdout.trim().to_string())
+}
+
+fn gib(bytes: u64) -> u64 {
+  bytes / (1024 * 1024 * 1024)
+}
+
+fn connect(node: &Node) -> Verdict {
+  // Tell a dead bastion apart from a dead node behind it
+  let via = match node.jump_sh("echo ok") {
+    Ok(None) => None,
+    Ok(Some(out)) if out.status == Some(0) => node.target.jump_host.clone(),
+    Ok(Some(out)) => {
+      let detail = out.stderr.trim().lines().last().unwrap_or("command failed").to_string();
+      return Verdict::fail(format!("The jump host could not run commands: {}", detail));
+    }
+    Err(e) => return Verdict::fail(format!("The jump host is unreachable: {}", e)),
+  };
+  match (output(node, "echo ok"), via) {
+    (Ok(_), Some(jump)) => Verdict::pass(format!("Connected as {} through {}", node.target.user, jump)),
+    (Ok(_), None) => Verdict::pass(format!("Connected as {}", node.target.user)),
+    (Err(e), _) => Verdict::fail(e),
+  }
+}
+
+fn os(node: &Node) -> Verdict {
+  let contents = match output(node, "cat /etc/os-release") {
+    Ok(contents) => contents,
+    Err(e) => return Verdict::fail(format!("Cannot read /etc/os-release: {}", e)),
+  };
+  let fields = os_release::parse_os_release(&contents);
+  let field = |key: &str| fields.get(key).map(String::as_str).unwrap_or("");
+  let name = match field("PRETTY_NAME") {
+    "" => "unknown OS",
+    name => name,
+  };
+  let version = field("VERSION_ID");
+
+  if field("ID") != "ubuntu" {
+    Verdict::fail(format!("{} is not supported; nodes must run Ubuntu", name))
+  } else if SUPPORTED_UBUNTU.iter().any(|v| version.starts_with(v)) {
+    Verdict::pass(name)
+  } else if UNTESTED_UBUNTU.iter().any(|v| version.starts_with(v)) {
+    Verdict::warn(format!("{} is not tested; {} is recommended", name, SUPPORTED_UBUNTU.join(", ")))
+  } else {
+    Verdict::fail(format!("{} is not supported; use Ubuntu {}", name, SUPPORTED_UBUNTU.join(", ")))
+  }
+}
+
+fn sudo(node: &Node) -> Verdict {
+  match output(node, "sudo -n true") {
+    Ok(_) => Verdict::pass("Passwordless sudo available"),
+    Err(e) => Verdict::fail(format!(
+      "{} needs passwordless sudo for the installer ({})",
+      node.target.user, e
+    )),
+  }
+}
+
+fn python(node: &Node) -> Verdict {
+  match output(node, "python3 --version") {
+    Ok(version) => Verdict::pass(version),
+    Err(_) => Verdict::fail("python3 is not installed; Ansible needs it on every node"),
+  }
+}
+
+fn memory(node: &Node) -> Verdict {
+  let kib = output(node, "awk '/^MemTotal:/ { print $2 }' /proc/meminfo")
+    .ok()
+    .and_then(|v| v.parse::<u64>().ok());
+  let Some(kib) = kib else {
+    return Verdict::fail("Cannot read total memory");
+  };
+  let total = gib(kib * 1024);
+  // MemTotal excludes firmware/kernel reservations, so "16 GB" shows as 15
+  if total + 1 < MIN_MEMORY_GIB {
+    Verdict::fail(format!("{} GiB RAM; at least {} GiB is required", total, MIN_MEMORY_GIB))
+  } else if total + 1 < RECOMMENDED_MEMORY_GIB {
+    Verdict::warn(format!("{} GiB RAM; {} GiB or more is recommended", total, RECOMMENDED_MEMORY_GIB))
+  } else {
+    Verdict::pass(format!("{} GiB RAM", total))
+  }
+}
+
+fn disk(node: &Node) -> Verdict {
+  let free = output(node, "df -P -B1 / | awk 'NR == 2 { print $4 }'")
+    .ok()
+    .and_then(|v| v.parse::<u64>().ok());
+  let Some(free) = free else {
+    return Verdict::fail("Cannot read free space on /");
+  };
+  let free = gib(free);
+  if free < MIN_ROOT_FREE_GIB {
+    Verdict::fail(format!("{} GiB free on /; at least {} GiB is required", free, MIN_ROOT_FREE_GIB))
+  } else if free < RECOMMENDED_ROOT_FREE_GIB {
+    Verdict::warn(format!(
+      "{} GiB free on /; {} GiB or more is recommended",
+      free, RECOMMENDED_ROOT_FREE_GIB
+    ))
+  } else {
+    Verdict::pass(format!("{} GiB free on /", free))
+  }
+}
+
+fn now_ms() -> i64 {
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|d| d.as_millis() as i64)
+    .unwrap_or_default()
+}
+
+/// Offset of the node's clock from this machine's, which is the reference
+/// every node is compared against, plus whether a sync daemon is running.
+fn clock(node: &Node) -> Verdict {
+  let script = format!(
+    "timedatectl show -p NTPSynchronized --value 2>/dev/null || echo unknown\n\
+     for s in {}; do systemctl is-active --quiet \"$s\" 2>/dev/null && echo \"$s\" && break; done; true\n",
+    TIME_SERVICES.join(" ")
+  );
+  let out = match output(node, &script) {
+    Ok(out) => out,
+    Err(e) => return Verdict::fail(format!("Cannot read the clock: {}", e)),
+  };
+  let mut lines = out.lines().map(str::trim);
+  let synchronized = lines.next().unwrap_or("unknown").to_string();
+  let service = lines.next().filter(|l| !l.is_empty()).map(str::to_string);
+
+  // The connection is up now, so this round trip is just the command
+  let sent = now_ms();
+  let remote = match output(node, "date +%s%3N") {
+    Ok(out) => out.trim().parse::<i64>().ok(),
+    Err(e) => return Verdict::fail(format!("Cannot read the clock: {}", e)),
+  };
+  let received = now_ms();
+  let Some(remote) = remote else {
+    return Verdict::fail("Cannot read the clock: unexpected `date` output");
+  };
+
+  // The remote `date` ran somewhere within the round trip
+  let offset = remote - (sent + received) / 2;
+  let uncertainty = (received - sent) / 2;
+  let skew = (offset.abs() - uncertainty).max(0);
+  let measured = format!("Clock offset {:+} ms (±{} ms)", offset, uncertainty);
+  let imprecise = uncertainty > WARN_CLOCK_OFFSET_MS;
+
+  let sync = match (&service, synchronized.as_str()) {
+    (Some(service), "yes") => format!("{} active and synchronized", service),
+    (Some(service), _) => format!("{} active but not yet synchronized", service),
+    (None, _) => "no time sync service (chrony or systemd-timesyncd) is active".to_string(),
+  };
+
+  if skew > MAX_CLOCK_OFFSET_MS {
+    Verdict::fail(format!("{}; {}", measured, sync))
+  } else if imprecise {
+    Verdict::warn(format!(
+      "{}; the round trip was too slow to measure the offset precisely; {}",
+      measured, sync
+    ))
+  } else if service.is_none() || synchronized != "yes" || skew > WARN_CLOCK_OFFSET_MS {
+    Verdict::warn(format!("{}; {}", measured, sync))
+  } else {
+    Verdict::pass(format!("{}; {}", measured, sync))
+  }
+}
+
+/// SELinux and AppArmor; `check_mac_policy` has the details and fixes.
+fn mac_policy(node: &Node) -> Verdict {
+  match output(node, mac::SCAN_SCRIPT) {
+    Ok(out) => {
+      let status = mac::parse(&out);
+      Verdict {
+        outcome: status.outcome(),
+        message: status.summary(),
+      }
+    }
+    Err(e) => Verdict::fail(format!("Cannot read SELinux/AppArmor status: {}", e)),
+  }
+}
+
+/// Active and boot-time swap; `disable_swap` turns it off.
+fn swap(node: &Node) -> Verdict {
+  match output(node, swap::SCAN_SCRIPT) {
+    Ok(out) => {
+      let (outcome, message) = swap::parse(&out).verdict();
+      Verdict { outcome, message }
+    }
+    Err(e) => Verdict::fail(format!("Cannot read swap status: {}", e)),
+  }
+}
+
+/// SMART health of the disks behind the data mounts; `check_disk_health`
+/// also covers the disk picked for the LXD pool.
+fn disk_health(node: &Node) -> Verdict {
+  match output(node, &smart::scan_script(&[])).and_then(|out| smart::parse(&out)) {
+    Ok(disks) => {
+      let (outcome, message) = smart::verdict(&disks);
+      Verdict { outcome, message }
+    }
+    Err(e) => Verdict::warn(format!("Cannot check disk health: {}", e)),
+  }
+}
+
+/// The cgroup hierarchy and systemd version. Kubelet and the k8s snap's
+/// containerd use the systemd cgroup driver, which needs the unified
+/// (v2) hierarchy and a systemd recent enough to delegate it.
+fn cgroup(node: &Node) -> Verdict {
+  let script = "stat -fc %T /sys/fs/cgroup\n\
+                [ -d /sys/fs/cgroup/unified ] && echo hybrid || echo -\n\
+                cat /sys/fs/cgroup/cgroup.controllers 2>/dev/null || echo\n\
+                systemctl --version 2>/dev/null | head -n 1\n";
+  let out = match output(node, script) {
+    Ok(out) => out,
+    Err(e) => return Verdict::fail(format!("Cannot read the cgroup hierarchy: {}", e)),
+  };
+  let mut lines = out.lines().map(str::trim);
+  let fs_type = lines.next().unwrap_or("");
+  let hybrid = lines.next() == Some("hybrid");
+  let controllers: Vec<&str> = lines.next().unwrap_or("").split_whitespace().collect();
+  // "systemd 255 (255.4-1ubuntu8)"
+  let systemd = lines
+    .next()
+    .and_then(|l| l.strip_prefix("systemd "))
+    .and_then(|l| l.split_whitespace().next())
+    .and_then(|v| v.parse::<u32>().ok());
+
+  let Some(systemd) = systemd else {
+    return Verdict::fail("systemd is not running; kubelet needs it for the systemd cgroup driver");
+  };
+  if fs_type != "cgroup2fs" {
+    let layout = if hybrid { "hybrid" } else { "legacy" };
+    return Verdict::fail(format!(
+      "cgroup v1 ({} hierarchy) with systemd {}; the container runtime needs cgroup v2. \
+       Boot with systemd.unified_cgroup_hierarchy=1 on the kernel command line",
+      layout, systemd
+    ));
+  }
+  if systemd < MIN_SYSTEMD_VERSION {
+    return Verdict::fail(format!(
+      "systemd {} is too old to manage cgroup v2 for kubelet; {} or later is required",
+      systemd, MIN_SYSTEMD_VERSION
+    ));
+  }
+  let missing: Vec<&str> = REQUIRED_CGROUP_CONTROLLERS
+    .iter()
+    .copied()
+    .filter(|c| !controllers.contains(c))
+    .collect();
+  if !missing.is_empty() {
+    return Verdict::fail(format!(
+      "cgroup v2 without the {} controller(s); enable them on the kernel command line \
+       (cgroup_enable=memory on Raspberry Pi)",
+      missing.join(", ")
+    ));
+  }
+  Verdict::pass(format!("cgroup v2, systemd {}", systemd))
+}
+
+/// Whether each required module is loaded, built in or at least loadable,
+/// and whether the sysctls the playbooks set can be written. Writing a
+/// sysctl back with its current value changes nothing.
+fn kernel_modules(node: &Node) -> Verdict {
+  let mut script = String::from("echo \"kernel $(uname -r)\"\nbuiltin=/lib/modules/$(uname -r)/modules.builtin\n");
+  for module in REQUIRED_MODULES {
+    script.push_str(&format!(
+      "if [ -d /sys/module/{m} ]; then echo 'module {m} loaded'; \
+       elif grep -q '/{m}\\.ko' \"$builtin\" 2>/dev/null; then echo 'module {m} builtin'; \
+       elif modinfo -n {m} >/dev/null 2>&1; then echo 'module {m} loadable'; \
+       else echo 'module {m} missing'; fi\n",
+      m = module
+    ));
+  }
+  for (key, _) in REQUIRED_SYSCTLS {
+    script.push_str(&format!(
+      "if ! v=$(sysctl -n {k} 2>/dev/null); then echo 'sysctl {k} absent'; \
+       elif sudo -n sysctl -q -w {k}=\"$v\" >/dev/null 2>&1; then echo 'sysctl {k} writable'; \
+       else echo 'sysctl {k} readonly'; fi\n",
+      k = key
+    ));
+  }
+  let out = match output(node, &script) {
+    Ok(out) => out,
+    Err(e) => return Verdict::fail(format!("Cannot check kernel modules: {}", e)),
+  };
+  let state = |kind: &str, name: &str| {
+    out.lines().find_map(|l| {
+      let mut fields = l.split_whitespace();
+      (fields.next() == Some(kind) && fields.next() == Some(name)).then(|| fields.next().unwrap_or(""))
+    })
+  };
+
+  let kernel = out
+    .lines()
+    .find_map(|l| l.strip_prefix("kernel "))
+    .unwrap_or("unknown");
+
+  let mut problems = Vec::new();
+  let mut to_load = Vec::new();
+  for module in REQUIRED_MODULES {
+    match state("module", module) {
+      Some("loaded") | Some("builtin") => {}
+      Some("loadable") => to_load.push(*module),
+      _ => problems.push(format!("module {} is missing from kernel {}", module, kernel)),
+    }
+  }
+  for (key, module) in REQUIRED_SYSCTLS {
+    match state("sysctl", key) {
+      Some("writable") => {}
+      // Appears once the module is loaded
+      Some("absent") if module.is_some_and(|m| to_load.contains(&m)) => {}
+      Some("absent") => problems.push(format!("sysctl {} does not exist", key)),
+      _ => problems.push(format!("sysctl {} cannot be set", key)),
+    }
+  }
+
+  if !problems.is_empty() {
+    Verdict::fail(problems.join("; "))
+  } else if !to_load.is_empty() {
+    Verdict::pass(format!("{} not loaded yet but available", to_load.join(", ")))
+  } else {
+    Verdict::pass(format!("{} available", REQUIRED_MODULES.join(", ")))
+  }
+}
+
+/// The running kernel against what the container runtime, ZFS and the
+/// NVIDIA driver need. Ubuntu's own kernels ship the ZFS module and have
+/// prebuilt NVIDIA modules; anything else needs DKMS and the headers.
+fn kernel_version(node: &Node) -> Verdict {
+  let script = "echo \"release=$(uname -r)\"\n\
+                echo \"hwe=$(dpkg-query -W -f='${Package} ${db:Status-Status}\\n' 'linux-generic-hwe-*' 2>/dev/null \\\n\
+                  | awk '$2 == \"installed\" { print $1; exit }')\"\n\
+                echo \"gpus=$(lspci -nn 2>/dev/null | grep -E '\\[(0300|0302|0380)\\]' | grep -c '\\[10de\\]')\"\n\
+                echo \"headers=$([ -d /lib/modules/$(uname -r)/build ] && echo yes || echo no)\"\n";
+  let out = match output(node, script) {
+    Ok(out) => out,
+    Err(e) => return Verdict::fail(format!("Cannot read the kernel version: {}", e)),
+  };
+  let fact = |key: &str| {
+    out
+      .lines()
+      .find_map(|l| l.trim().strip_prefix(key)?.strip_prefix('='))
+      .unwrap_or("")
+  };
+  let release = fact("release");
+  let hwe = !fact("hwe").is_empty();
+  let gpus = fact("gpus").parse::<u32>().unwrap_or(0);
+  let headers = fact("headers") == "yes";
+
+  // "6.8.0-45-generic": version, ABI number, flavour
+  let mut numbers = release
+    .split(|c: char| !c.is_ascii_digit())
+    .map(|n| n.parse::<u32>().ok());
+  let (Some(Some(major)), Some(Some(minor))) = (numbers.next(), numbers.next()) else {
+    return Verdict::fail(format!("Cannot parse kernel release {:?}", release));
+  };
+  let ubuntu_kernel = release
+    .splitn(3, '-')
+    .nth(2)
+    .is_some_and(|flavour| UBUNTU_KERNEL_FLAVOURS.contains(&flavour));
+  let kind = match (hwe, ubuntu_kernel) {
+    (true, true) => "HWE",
+    (false, true) => "GA",
+    (_, false) => "custom",
+  };
+  let described = format!("Kernel {} ({})", release, kind);
+
+  if (major, minor) < MIN_KERNEL {
+    return Verdict::fail(format!(
+      "{}; the container runtime and Cilium need {}.{} or later",
+      described, MIN_KERNEL.0, MIN_KERNEL.1
+    ));
+  }
+  if !ubuntu_kernel && gpus > 0 && !headers {
+    return Verdict::fail(format!(
+      "{}; no prebuilt NVIDIA modules for it and no headers to build them with DKMS",
+      described
+    ));
+  }
+  if !ubuntu_kernel {
+    return Verdict::warn(format!(
+      "{}; not an Ubuntu kernel, so the ZFS module{} must come from DKMS",
+      described,
+      if gpus > 0 { " and NVIDIA driver" } else { "" }
+    ));
+  }
+  Verdict::pass(described)
+}
+
+/// snapd installed, running and seeded, with no change holding it up, and
+/// the store (or the store proxy snapd is configured with) reachable.
+fn snapd(node: &Node) -> Verdict {
+  let script = "if ! command -v snap >/dev/null 2>&1; then echo missing; exit 0; fi\n\
+                echo \"active=$(systemctl is-active snapd.service 2>/dev/null)\"\n\
+                echo \"seeded=$(timeout 5 snap debug seeding 2>/dev/null | awk '$1 == \"seeded:\" { print $2 }')\"\n\
+                echo \"held=$(apt-mark showhold 2>/dev/null | grep -x snapd)\"\n\
+                echo \"doing=$(snap changes 2>/dev/null | awk '$2 == \"Doing\" || $2 == \"Wait\"' | wc -l)\"\n\
+                echo \"store_proxy=$(snap get system proxy.store 2>/dev/null)\"\n\
+                echo \"https_proxy=$(snap get system proxy.https 2>/dev/null)\"\n\
+                timeout 15 snap debug connectivity >/dev/null 2>&1 && echo store=ok || echo store=unreachable\n";
+  let out = match output(node, script) {
+    Ok(out) => out,
+    Err(e) => return Verdict::fail(format!("Cannot check snapd: {}", e)),
+  };
+  if out == "missing" {
+    return Verdict::fail("snapd is not installed; the k8s and LXD snaps need it (sudo apt install snapd)");
+  }
+  let fact = |key: &str| {
+    out
+      .lines()
+      .find_map(|l| l.trim().strip_prefix(key)?.strip_prefix('='))
+      .unwrap_or("")
+  };
+
+  if fact("active") != "active" {
+    return Verdict::fail("snapd is not running (sudo systemctl enable --now snapd)");
+  }
+  if fact("seeded") != "true" {
+    return Verdict::fail("snapd has not finished seeding; wait for `snap wait system seed.loaded`");
+  }
+  let via = match (fact("store_proxy"), fact("https_proxy")) {
+    ("", "") => String::new(),
+    ("", proxy) => format!(" through proxy {}", proxy),
+    (store, _) => format!(" through store proxy {}", store),
+  };
+  if fact("store") != "ok" {
+    return Verdict::fail(format!(
+      "snapd cannot reach the snap store{}; set `snap set system proxy.https=...` if the node needs a proxy",
+      via
+    ));
+  }
+  let mut notes = Vec::new();
+  if !fact("held").is_empty() {
+    notes.push("the snapd package is held in apt".to_string());
+  }
+  match fact("doing").parse::<u32>().unwrap_or(0) {
+    0 => {}
+    n => notes.push(format!("{} snap change(s) still in progress", n)),
+  }
+  if notes.is_empty() {
+    Verdict::pass(format!("snapd running and seeded; store reachable{}", via))
+  } else {
+    Verdict::warn(format!("snapd running, store reachable{}; {}", via, notes.join("; ")))
+  }
+}
+
+/// Bare metal, a VM (with or without nested virtualization), a container
+/// or WSL. The node must be a full machine, and LXD VMs need KVM.
+fn virtualization(node: &Node) -> Verdict {
+  let facts = match output(node, virt::SCRIPT) {
+    Ok(out) => virt::Facts::parse(&out),
+    Err(e) => return Verdict::fail(format!("Cannot detect virtualization: {}", e)),
+  };
+  let platform = facts.platform().unwrap_or_else(|| "an unknown hypervisor".to_string());
+  match facts.kind() {
+    Kind::Wsl | Kind::Container => Verdict::fail(format!(
+      "Runs inside {}; Kubernetes and LXD need a full machine or VM",
+      platform
+    )),
+    Kind::Vm if facts.kvm => Verdict::warn(format!(
+      "{} VM with nested virtualization; bare metal is recommended",
+      platform
+    )),
+    Kind::Vm => Verdict::warn(format!(
+      "{} VM without nested virtualization; LXD cannot run VMs on this node",
+      platform
+    )),
+    Kind::BareMetal if facts.kvm => Verdict::pass("Bare metal with KVM"),
+    Kind::BareMetal => Verdict::warn("KVM is not available; enable VT-x/AMD-V in the firmware to run LXD VMs"),
+  }
+}
+
+/// Whether the interface with the default route is Wi-Fi. The roles aren't
+/// known here, so it's a warning; `check_link_speed` fails it for
+/// control-plane and storage nodes.
+fn wireless(node: &Node) -> Verdict {
+  let status = match output(node, &link::script(None)) {
+    Ok(out) => link::parse(&out),
+    Err(e) => return Verdict::fail(format!("Cannot read the network interfaces: {}", e)),
+  };
+  let Some(status) = status else {
+    return Verdict::warn("No default route to find the cluster interface by");
+  };
+  match status.wireless_verdict(&[], false) {
+    Some((outcome, message)) => Verdict { outcome, message },
+    None => Verdict::pass(format!("{} is wired", status.link.interface)),
+  }
+}
@@ -0,0 +1,322 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Preflight checks across cluster nodes.
+//!
+//! Every check in [`checks::SUITE`] runs against every node, nodes in
+//! parallel up to `max_parallel` at a time and checks within a node in
+//! order over its pooled SSH connection. If a node can't be reached, its
+//! remaining checks are reported as skipped rather than each failing with
+//! the same connection error. Each finished check is emitted as a
+//! `preflight-update` event so the wizard can fill in the node × check
+//! matrix live; the command resolves with the complete matrix.
+
+mod checks;
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::remote::ssh::{self, ScriptOutput, SshPool, SshTarget};
+use crate::workspace::Workspace;
+
+pub const EVENT: &str = "preflight-update";
+
+const DEFAULT_PARALLEL: usize = 4;
+const MAX_PARALLEL: usize = 32;
+const CHECK_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+  Pass,
+  Warn,
+  Fail,
+  Skipped,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckResult {
+  pub check: String,
+  pub outcome: Outcome,
+  pub message: String,
+  pub duration_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeReport {
+  pub host: String,
+  pub checks: Vec<CheckResult>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Summary {
+  pub pass: usize,
+  pub warn: usize,
+  pub fail: usize,
+  pub skipped: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PreflightReport {
+  pub run_id: String,
+  /// In the order the hosts were given
+  pub nodes: Vec<NodeReport>,
+  pub summary: Summary,
+  /// No check failed or was skipped
+  pub ok: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "phase", rename_all = "snake_case")]
+pub enum PreflightEvent {
+  Started {
+    run_id: String,
+    hosts: Vec<String>,
+    checks: Vec<String>,
+  },
+  CheckCompleted {
+    run_id: String,
+    host: String,
+    result: CheckResult,
+  },
+  NodeCompleted {
+    run_id: String,
+    host: String,
+  },
+  Finished {
+    run_id: String,
+    summary: Summary,
+  },
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PreflightOptions {
+  /// Event tag; generated when omitted
+  pub run_id: Option<String>,
+  pub max_parallel: Option<usize>,
+  /// Check ids to run; all of them when omitted
+  pub checks: Option<Vec<String>>,
+}
+
+/// What a check function sees: one node, reachable over the pool.
+pub struct Node<'a> {
+  workspace: &'a Workspace,
+  pool: &'a SshPool,
+  pub target: &'a SshTarget,
+}
+
+impl Node<'_> {
+  /// Run a short script on the node.
+  pub fn sh(&self, script: &str) -> Result<ScriptOutput, String> {
+    ssh::run_script(self.workspace, self.pool, self.target, script, CHECK_TIMEOUT)
+  }
+
+  /// Run a short script on the jump host the node is reached through, if
+  /// it has one.
+  pub fn jump_sh(&self, script: &str) -> Result<Option<ScriptOutput>, String> {
+    let Some(jump) = self.target.jump_target()? else {
+      return Ok(None);
+    };
+    ssh::run_script(self.workspace, self.pool, &jump, script, CHECK_TIMEOUT).map(Some)
+  }
+}
+
+pub struct Verdict {
+  pub outcome: Outcome,
+  pub message: String,
+}
+
+impl Verdict {
+  pub fn pass(message: impl Into<String>) -> Self {
+    Self {
+      outcome: Outcome::Pass,
+      message: message.into(),
+    }
+  }
+
+  pub fn warn(message: impl Into<String>) -> Self {
+    Self {
+      outcome: Outcome::Warn,
+      message: message.into(),
+    }
+  }
+
+  pub fn fail(message: impl Into<String>) -> Self {
+    Self {
+      outcome: Outcome::Fail,
+      message: message.into(),
+    }
+  }
+}
+
+pub struct Check {
+  pub id: &'static str,
+  pub run: fn(&Node) -> Verdict,
+}
+
+fn run_node(app: &AppHandle, run_id: &str, node: &Node, suite: &[&Check]) -> NodeReport {
+  let mut results = Vec::with_capacity(suite.len());
+  let mut unreachable = None;
+
+  for check in suite {
+    let started = Instant::now();
+    let verdict = match &unreachable {
+      Some(reason) => Verdict {
+        outcome: Outcome::Skipped,
+        message: format!("Skipped: {}", reason),
+      },
+      None => (check.run)(node),
+    };
+    if check.id == checks::CONNECT && verdict.outcome == Outcome::Fail {
+      unreachable = Some(verdict.message.clone());
+    }
+
+    let result = CheckResult {
+      check: check.id.to_string(),
+      outcome: verdict.outcome,
+      message: verdict.message,
+      duration_ms: started.elapsed().as_millis() as u64,
+    };
+    let _ = app.emit(
+      EVENT,
+      PreflightEvent::CheckCompleted {
+        run_id: run_id.to_string(),
+        host: node.target.host.clone(),
+        result: result.clone(),
+      },
+    );
+    results.push(result);
+  }
+
+  let _ = app.emit(
+    EVENT,
+    PreflightEvent::NodeCompleted {
+      run_id: run_id.to_string(),
+      host: node.target.host.clone(),
+    },
+  );
+  NodeReport {
+    host: node.target.host.clone(),
+    checks: results,
+  }
+}
+
+/// Run the suite; the command's body, and what a scheduled start checks with.
+pub fn run(app: &AppHandle, hosts: Vec<SshTarget>, options: PreflightOptions) -> Result<PreflightReport, String> {
+  let suite: Vec<&Check> = match &options.checks {
+    Some(ids) => {
+      if let Some(unknown) = ids.iter().find(|id| !checks::SUITE.iter().any(|c| c.id == id.as_str())) {
+        return Err(format!("Unknown preflight check: {}", unknown));
+      }
+      // Reaching the node is what the skip logic keys on, so it always runs first
+      checks::SUITE
+        .iter()
+        .filter(|c| c.id == checks::CONNECT || ids.iter().any(|id| id == c.id))
+        .collect()
+    }
+    None => checks::SUITE.iter().collect(),
+  };
+  let run_id = options.run_id.unwrap_or_else(|| {
+    let now = std::time::SystemTime::now()
+      .duration_since(std::time::UNIX_EPOCH)
+      .unwrap_or_default();
+    format!("preflight-{}", now.as_millis())
+  });
+  let parallel = options
+    .max_parallel
+    .unwrap_or(DEFAULT_PARALLEL)
+    .clamp(1, MAX_PARALLEL)
+    .min(hosts.len().max(1));
+
+  let _ = app.emit(
+    EVENT,
+    PreflightEvent::Started {
+      run_id: run_id.clone(),
+      hosts: hosts.iter().map(|h| h.host.clone()).collect(),
+      checks: suite.iter().map(|c| c.id.to_string()).collect(),
+    },
+  );
+
+  let workspace = app.state::<Workspace>();
+  let pool = app.state::<SshPool>();
+  let queue: Mutex<VecDeque<usize>> = Mutex::new((0..hosts.len()).collect());
+  let reports: Mutex<Vec<Option<NodeReport>>> = Mutex::new(vec![None; hosts.len()]);
+
+  std::thread::scope(|scope| {
+    for _ in 0..parallel {
+      scope.spawn(|| {
+        while let Some(index) = queue.lock().ok().and_then(|mut q| q.pop_front()) {
+          let node = Node {
+            workspace: &workspace,
+            pool: &pool,
+            target: &hosts[index],
+          };
+          let report = run_node(app, &run_id, &node, &suite);
+          if let Ok(mut reports) = reports.lock() {
+            reports[index] = Some(report);
+          }
+        }
+      });
+    }
+  });
+
+  let nodes: Vec<NodeReport> = reports
+    .into_inner()
+    .map_err(|e| e.to_string())?
+    .into_iter()
+    .zip(&hosts)
+    .map(|(report, target)| {
+      report.unwrap_or_else(|| NodeReport {
+        host: target.host.clone(),
+        checks: Vec::new(),
+      })
+    })
+    .collect();
+
+  let mut summary = Summary::default();
+  for result in nodes.iter().flat_map(|n| &n.checks) {
+    match result.outcome {
+      Outcome::Pass => summary.pass += 1,
+      Outcome::Warn => summary.warn += 1,
+      Outcome::Fail => summary.fail += 1,
+      Outcome::Skipped => summary.skipped += 1,
+    }
+  }
+  let _ = app.emit(
+    EVENT,
+    PreflightEvent::Finished {
+      run_id: run_id.clone(),
+      summary: summary.clone(),
+    },
+  );
+
+  Ok(PreflightReport {
+    run_id,
+    ok: summary.fail == 0 && summary.skipped == 0,
+    nodes,
+    summary,
+  })
+}
+
+/// Run the preflight suite on every node and return the node × check matrix.
+#[tauri::command]
+pub async fn run_preflight(
+  app: AppHandle,
+  hosts: Vec<SshTarget>,
+  options: Option<PreflightOptions>,
+) -> Result<PreflightReport, String> {
+  if hosts.is_empty() {
+    return Err("No nodes to check".to_string());
+  }
+  for target in &hosts {
+    target.validate()?;
+  }
+  tauri::async_runtime::spawn_blocking(move || run(&app, hosts, options.unwrap_or_default()))
+    .await
+    .map_err(|e| e.to_string())?
+}
@@ -0,0 +1,176 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Topology presets for common cluster shapes.
+//!
+//! A preset says how many nodes the cluster is meant to have, where GPUs
+//! should go and which wizard settings to prefill; the role assignment
+//! page applies it to the nodes it found. The built-in presets are JSON
+//! files in `presets/` compiled into the binary. Users can add their own
+//! as `*.json` files in `<app config dir>/templates/`; those are read on
+//! every call, shared by all profiles, and can't replace a built-in one.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
+use tracing::warn;
+
+use crate::validation;
+
+const BUILTIN: &[&str] = &[
+  include_str!("../presets/gpu-workstation.json"),
+  include_str!("../presets/homelab.json"),
+  include_str!("../presets/gpu-cpu-workers.json"),
+];
+
+/// User templates are a few hundred bytes
+const MAX_TEMPLATE_BYTES: u64 = 256 * 1024;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GpuPlacement {
+  /// GPUs are used wherever they are
+  #[default]
+  Any,
+  /// Single-node setups: the one node must have a GPU
+  RequiredOnControlPlane,
+  /// Put the control plane on a CPU-only node if there is one
+  PreferWorkers,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PresetSource {
+  #[default]
+  Builtin,
+  User,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Preset {
+  pub id: String,
+  pub name: String,
+  #[serde(default)]
+  pub description: String,
+  #[serde(default = "one")]
+  pub min_nodes: u32,
+  pub max_nodes: Option<u32>,
+  #[serde(default)]
+  pub gpu: GpuPlacement,
+  /// Values merged into the wizard's `thinkube-config`
+  #[serde(default)]
+  pub config: Map<String, Value>,
+  #[serde(default, skip_deserializing)]
+  pub source: PresetSource,
+  /// The template file, for user presets
+  #[serde(default, skip_deserializing)]
+  pub path: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PresetList {
+  pub presets: Vec<Preset>,
+  /// Where user templates are read from
+  pub templates_dir: Option<String>,
+  /// Template files that were skipped, and why
+  pub errors: Vec<String>,
+}
+
+fn one() -> u32 {
+  1
+}
+
+fn check(preset: &Preset) -> Result<(), String> {
+  if !validation::is_valid_label(&preset.id) {
+    return Err(format!("invalid id {:?}", preset.id));
+  }
+  if preset.name.trim().is_empty() {
+    return Err("name is empty".to_string());
+  }
+  if preset.min_nodes == 0 {
+    return Err("min_nodes must be at least 1".to_string());
+  }
+  if preset.max_nodes.is_some_and(|max| max < preset.min_nodes) {
+    return Err("max_nodes is below min_nodes".to_string());
+  }
+  if preset.gpu == GpuPlacement::RequiredOnControlPlane && preset.max_nodes != Some(1) {
+    return Err("required_on_control_plane is only for single-node presets".to_string());
+  }
+  Ok(())
+}
+
+fn builtin() -> Vec<Preset> {
+  BUILTIN
+    .iter()
+    .filter_map(|json| match serde_json::from_str::<Preset>(json) {
+      Ok(preset) => Some(preset),
+      Err(e) => {
+        warn!("Ignoring an invalid built-in preset: {}", e);
+        None
+      }
+    })
+    .collect()
+}
+
+fn templates_dir(app: &AppHandle) -> Option<PathBuf> {
+  app.path().app_config_dir().ok().map(|dir| dir.join("templates"))
+}
+
+fn read_template(path: &std::path::Path) -> Result<Preset, String> {
+  let metadata = std::fs::metadata(path).map_err(|e| e.to_string())?;
+  if metadata.len() > MAX_TEMPLATE_BYTES {
+    return Err("file is too large".to_string());
+  }
+  let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+  let mut preset: Preset = serde_json::from_str(&text).map_err(|e| e.to_string())?;
+  check(&preset)?;
+  preset.source = PresetSource::User;
+  preset.path = Some(path.display().to_string());
+  Ok(preset)
+}
+
+/// The built-in presets followed by the user's templates, sorted by name.
+#[tauri::command]
+pub fn list_topology_presets(app: AppHandle) -> PresetList {
+  let mut presets = builtin();
+  let mut errors = Vec::new();
+  let dir = templates_dir(&app);
+
+  let mut files: Vec<PathBuf> = dir
+    .iter()
+    .filter_map(|d| std::fs::read_dir(d).ok())
+    .flatten()
+    .filter_map(Result::ok)
+    .map(|e| e.path())
+    .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+    .collect();
+  files.sort();
+  let mut user = Vec::new();
+  for path in files {
+    match read_template(&path) {
+      Ok(preset) if presets.iter().chain(&user).any(|p: &Preset| p.id == preset.id) => {
+        errors.push(format!(
+          "{}: a preset with id {} already exists",
+          path.display(),
+          preset.id
+        ));
+      }
+      Ok(preset) => user.push(preset),
+      Err(e) => errors.push(format!("{}: {}", path.display(), e)),
+    }
+  }
+  for error in &errors {
+    warn!("Skipping topology template {}", error);
+  }
+  user.sort_by(|a, b| a.name.cmp(&b.name));
+  presets.extend(user);
+
+  PresetList {
+    presets,
+    templates_dir: dir.map(|d| d.display().to_string()),
+    errors,
+  }
+}
@@ -0,0 +1,253 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Named installation profiles, one per cluster.
+//!
+//! Each profile has its own saved configuration, run workspaces, step
+//! transcript, rollback journal and resume state. The `default` profile
+//! keeps using the app data, cache and config directories themselves, so
+//! an installer upgraded from before profiles carries on where it was;
+//! every other profile lives below `profiles/<name>/` in each of them.
+//! The active profile is chosen in settings.json and read at startup, so
+//! switching restarts the shell, which is refused while a playbook runs.
+//! Settings, logs and crash reports are shared by all profiles.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager, State};
+use tracing::{info, warn};
+
+use crate::config::{self, ConfigDocument, ExportRequest, ImportedConfig};
+use crate::install_guard::InstallGuard;
+use crate::settings::SettingsStore;
+use crate::validation;
+use crate::workspace::{create_private_dir, write_private_file};
+
+pub const DEFAULT_PROFILE: &str = "default";
+const PROFILES_DIR: &str = "profiles";
+const CONFIG_FILE: &str = "config.json";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfileInfo {
+  pub name: String,
+  pub active: bool,
+  /// Seconds since the Unix epoch; `None` for the default profile
+  pub created_at: Option<u64>,
+  /// A configuration was saved with `save_profile_config`
+  pub has_config: bool,
+  /// The rollback journal holds actions, i.e. an install didn't finish
+  pub unfinished: bool,
+}
+
+fn base_dirs(app: &AppHandle) -> Result<[PathBuf; 3], String> {
+  let path = app.path();
+  Ok([
+    path
+      .app_data_dir()
+      .map_err(|e| format!("Cannot resolve app data directory: {}", e))?,
+    path
+      .app_cache_dir()
+      .map_err(|e| format!("Cannot resolve app cache directory: {}", e))?,
+    path
+      .app_config_dir()
+      .map_err(|e| format!("Cannot resolve app config directory: {}", e))?,
+  ])
+}
+
+fn profile_dir(base: &Path, name: &str) -> PathBuf {
+  if name == DEFAULT_PROFILE {
+    base.to_path_buf()
+  } else {
+    base.join(PROFILES_DIR).join(name)
+  }
+}
+
+fn exists(app: &AppHandle, name: &str) -> bool {
+  name == DEFAULT_PROFILE
+    || app
+      .path()
+      .app_data_dir()
+      .map(|base| profile_dir(&base, name).is_dir())
+      .unwrap_or(false)
+}
+
+fn check_name(name: &str) -> Result<(), String> {
+  let valid = validation::is_valid_label(name) && !name.chars().any(|c| c.is_ascii_uppercase());
+  if valid {
+    Ok(())
+  } else {
+    Err(format!(
+      "Invalid profile name {:?}: use lowercase letters, digits and hyphens",
+      name
+    ))
+  }
+}
+
+/// The profile chosen in settings, or the default one if it's gone.
+pub fn active(app: &AppHandle) -> String {
+  let chosen = app
+    .try_state::<SettingsStore>()
+    .and_then(|s| s.get().profile)
+    .unwrap_or_else(|| DEFAULT_PROFILE.to_string());
+  if check_name(&chosen).is_ok() && exists(app, &chosen) {
+    chosen
+  } else {
+    warn!("Profile {:?} doesn't exist; using the default profile", chosen);
+    DEFAULT_PROFILE.to_string()
+  }
+}
+
+/// The active profile's data directory (transcript, journal, reports).
+pub fn data_dir(app: &AppHandle) -> Result<PathBuf, String> {
+  let [data, _, _] = base_dirs(app)?;
+  Ok(profile_dir(&data, &active(app)))
+}
+
+/// The active profile's cache directory, where run workspaces go.
+pub fn cache_dir(app: &AppHandle) -> Result<PathBuf, String> {
+  let [_, cache, _] = base_dirs(app)?;
+  Ok(profile_dir(&cache, &active(app)))
+}
+
+/// The active profile's config directory (resume state).
+pub fn config_dir(app: &AppHandle) -> Result<PathBuf, String> {
+  let [_, _, config] = base_dirs(app)?;
+  Ok(profile_dir(&config, &active(app)))
+}
+
+fn now() -> u64 {
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|d| d.as_secs())
+    .unwrap_or_default()
+}
+
+fn describe(app: &AppHandle, name: &str, active: &str) -> Result<ProfileInfo, String> {
+  let [data, _, _] = base_dirs(app)?;
+  let dir = profile_dir(&data, name);
+  // Other profiles' journals may still be in the unversioned format
+  let journal: Value = std::fs::read_to_string(dir.join("journal.json"))
+    .ok()
+    .and_then(|s| serde_json::from_str(&s).ok())
+    .unwrap_or_default();
+  let entries = journal.get("items").unwrap_or(&journal);
+  Ok(ProfileInfo {
+    name: name.to_string(),
+    active: name == active,
+    created_at: std::fs::read_to_string(dir.join("created"))
+      .ok()
+      .and_then(|s| s.trim().parse().ok()),
+    has_config: dir.join(CONFIG_FILE).is_file(),
+    unfinished: entries.as_array().is_some_and(|entries| !entries.is_empty()),
+  })
+}
+
+#[tauri::command]
+pub fn list_profiles(app: AppHandle) -> Result<Vec<ProfileInfo>, String> {
+  let [data, _, _] = base_dirs(&app)?;
+  let active = active(&app);
+  let mut names: Vec<String> = std::fs::read_dir(data.join(PROFILES_DIR))
+    .into_iter()
+    .flatten()
+    .filter_map(Result::ok)
+    .filter(|e| e.path().is_dir())
+    .filter_map(|e| e.file_name().into_string().ok())
+    .filter(|name| check_name(name).is_ok() && name != DEFAULT_PROFILE)
+    .collect();
+  names.sort();
+  std::iter::once(DEFAULT_PROFILE.to_string())
+    .chain(names)
+    .map(|name| describe(&app, &name, &active))
+    .collect()
+}
+
+/// Create an empty profile; it becomes active with `switch_profile`.
+#[tauri::command]
+pub fn create_profile(app: AppHandle, name: String) -> Result<ProfileInfo, String> {
+  let name = name.trim().to_string();
+  check_name(&name)?;
+  if exists(&app, &name) {
+    return Err(format!("A profile named {} already exists", name));
+  }
+  let [data, _, _] = base_dirs(&app)?;
+  let dir = profile_dir(&data, &name);
+  create_private_dir(&dir)?;
+  write_private_file(&dir.join("created"), now().to_string().as_bytes())?;
+  info!("Created profile {}", name);
+  describe(&app, &name, &active(&app))
+}
+
+/// Make `name` the active profile and restart the shell to load it.
+#[tauri::command]
+pub fn switch_profile(
+  app: AppHandle,
+  settings: State<'_, SettingsStore>,
+  guard: State<'_, InstallGuard>,
+  name: String,
+) -> Result<(), String> {
+  check_name(&name)?;
+  if !exists(&app, &name) {
+    return Err(format!("There is no profile named {}", name));
+  }
+  if let Some(step) = guard.running_step() {
+    return Err(format!("Cannot switch profiles while {} is running", step));
+  }
+  if name == active(&app) {
+    return Ok(());
+  }
+  settings.update(|s| s.profile = Some(name.clone()).filter(|n| n != DEFAULT_PROFILE))?;
+  info!("Switching to profile {}; restarting", name);
+  app.restart()
+}
+
+/// Delete a profile other than the default and the active one, with its
+/// saved configuration, workspaces and state.
+#[tauri::command]
+pub fn delete_profile(app: AppHandle, name: String) -> Result<(), String> {
+  check_name(&name)?;
+  if name == DEFAULT_PROFILE {
+    return Err("The default profile cannot be deleted".to_string());
+  }
+  if name == active(&app) {
+    return Err("Switch to another profile before deleting this one".to_string());
+  }
+  if !exists(&app, &name) {
+    return Err(format!("There is no profile named {}", name));
+  }
+  for base in base_dirs(&app)? {
+    let dir = profile_dir(&base, &name);
+    match std::fs::remove_dir_all(&dir) {
+      Ok(()) => {}
+      Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+      Err(e) => return Err(format!("Failed to remove {}: {}", dir.display(), e)),
+    }
+  }
+  info!("Deleted profile {}", name);
+  Ok(())
+}
+
+/// Save the wizard's configuration in the active profile. Secrets are left
+/// out; they stay in the keyring.
+#[tauri::command]
+pub fn save_profile_config(app: AppHandle, request: ExportRequest) -> Result<ConfigDocument, String> {
+  let document = config::build_document(&app, request, false);
+  config::validate(&document)?;
+  let json = serde_json::to_string_pretty(&document).map_err(|e| e.to_string())?;
+  write_private_file(&data_dir(&app)?.join(CONFIG_FILE), json.as_bytes())?;
+  Ok(document)
+}
+
+/// The active profile's saved configuration, if any.
+#[tauri::command]
+pub fn get_profile_config(app: AppHandle) -> Result<Option<ImportedConfig>, String> {
+  let path = data_dir(&app)?.join(CONFIG_FILE);
+  if !path.is_file() {
+    return Ok(None);
+  }
+  let (document, migration) = config::read_file(&path)?;
+  Ok(Some(ImportedConfig { document, migration }))
+}
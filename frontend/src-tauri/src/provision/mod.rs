@@ -0,0 +1,10 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Preparing machines that don't have an OS (or a user for us) yet.
+
+pub mod pxe;
+pub mod seed;
+pub mod usb;
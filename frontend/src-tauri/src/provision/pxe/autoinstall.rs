@@ -0,0 +1,95 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! iPXE scripts and Ubuntu autoinstall configs served to booting nodes.
+
+use super::{PxeNode, Shared};
+use crate::platform::regional::KeyboardLayout;
+use crate::provision::seed::{self, yaml_str, DEFAULT_USER};
+
+/// Entry point the DHCP filename points at; chains to the per-MAC script.
+pub fn chain_script(shared: &Shared) -> String {
+  format!(
+    "#!ipxe\ndhcp\nchain {}/boot/${{net0/mac:hexhyp}} || shell\n",
+    shared.base_url()
+  )
+}
+
+pub fn boot_script(shared: &Shared, mac: &str) -> String {
+  let base = shared.base_url();
+  let mut args = format!(
+    "ip=dhcp autoinstall ds=nocloud-net;s={}/autoinstall/{}/",
+    base,
+    mac.replace(':', "-")
+  );
+  if let Some(iso) = &shared.config.iso {
+    args.push_str(&format!(" url={}/files/{} cloud-config-url=/dev/null", base, iso));
+  }
+  format!(
+    "#!ipxe\nkernel {base}/files/{kernel} {args} ---\ninitrd {base}/files/{initrd}\nboot\n",
+    base = base,
+    kernel = shared.config.kernel,
+    initrd = shared.config.initrd,
+    args = args
+  )
+}
+
+pub fn unknown_script(mac: &str) -> String {
+  format!("#!ipxe\necho {} is not part of this Thinkube install, booting locally\nexit\n", mac)
+}
+
+pub fn user_data(shared: &Shared, mac: &str, node: &PxeNode) -> String {
+  let user = node.node.username.as_deref().unwrap_or(DEFAULT_USER);
+  let mut out = String::from("#cloud-config\nautoinstall:\n  version: 1\n");
+  out.push_str("  locale: en_US.UTF-8\n");
+  if let Some(keyboard) = &node.node.keyboard {
+    // Subiquity has no keyboard model, only layout and variant
+    let keyboard = KeyboardLayout {
+      model: String::new(),
+      ..keyboard.clone()
+    };
+    out.push_str(&seed::render_keyboard(&keyboard, "  "));
+  }
+  out.push_str("  ssh:\n    install-server: true\n    allow-pw: false\n");
+  out.push_str("  storage:\n    layout:\n      name: lvm\n");
+  if node.node.network.is_some() {
+    out.push_str("  network:\n");
+    for line in seed::render_network_config(&node.node).lines() {
+      out.push_str(&format!("    {}\n", line));
+    }
+  }
+  out.push_str("  packages: [python3, curl]\n");
+  // `user-data` replaces the interactive identity section
+  out.push_str("  user-data:\n");
+  out.push_str(&format!("    hostname: {}\n", yaml_str(&node.node.hostname)));
+  out.push_str("    users:\n");
+  out.push_str(&format!("      - name: {}\n", yaml_str(user)));
+  out.push_str("        groups: [sudo]\n");
+  out.push_str("        shell: /bin/bash\n");
+  out.push_str("        sudo: \"ALL=(ALL) NOPASSWD:ALL\"\n");
+  out.push_str("        lock_passwd: true\n");
+  out.push_str("        ssh_authorized_keys:\n");
+  for key in &node.node.ssh_authorized_keys {
+    out.push_str(&format!("          - {}\n", yaml_str(key.trim())));
+  }
+  out.push_str("  late-commands:\n");
+  out.push_str(&format!(
+    "    - {}\n",
+    yaml_str(&format!(
+      "curl -fsS -X POST {}/done/{} || true",
+      shared.base_url(),
+      mac.replace(':', "-")
+    ))
+  ));
+  out
+}
+
+pub fn meta_data(node: &PxeNode) -> String {
+  format!(
+    "instance-id: {}\nlocal-hostname: {}\n",
+    yaml_str(&format!("thinkube-{}", node.node.hostname)),
+    yaml_str(&node.node.hostname)
+  )
+}
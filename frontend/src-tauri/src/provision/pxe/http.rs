@@ -0,0 +1,156 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Just enough HTTP/1.1 for iPXE, curl and the Ubuntu installer: one
+//! request per connection, GET/HEAD/POST, no keep-alive.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use super::{autoinstall, BootPhase, Shared};
+
+const MAX_HEADER_BYTES: usize = 8192;
+
+/// Resolve a file name inside the artifacts directory; `None` for anything
+/// that would escape it.
+pub fn artifact_path(root: &Path, name: &str) -> Option<PathBuf> {
+  let relative = Path::new(name.trim_start_matches('/'));
+  if relative.as_os_str().is_empty() || relative.components().any(|c| !matches!(c, Component::Normal(_))) {
+    return None;
+  }
+  Some(root.join(relative))
+}
+
+pub fn serve(listener: TcpListener, shared: Arc<Shared>, stop: Arc<AtomicBool>) {
+  if let Err(e) = listener.set_nonblocking(true) {
+    warn!("PXE HTTP: {}", e);
+    return;
+  }
+  while !stop.load(Ordering::SeqCst) {
+    match listener.accept() {
+      Ok((stream, _)) => {
+        let shared = shared.clone();
+        std::thread::spawn(move || {
+          if let Err(e) = handle(stream, &shared) {
+            warn!("PXE HTTP: {}", e);
+          }
+        });
+      }
+      Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => std::thread::sleep(Duration::from_millis(100)),
+      Err(e) => {
+        warn!("PXE HTTP accept failed: {}", e);
+        std::thread::sleep(Duration::from_millis(500));
+      }
+    }
+  }
+}
+
+fn respond(stream: &mut TcpStream, code: u16, reason: &str, content_type: &str, body: &[u8], head: bool) -> std::io::Result<()> {
+  write!(
+    stream,
+    "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+    code,
+    reason,
+    content_type,
+    body.len()
+  )?;
+  if !head {
+    stream.write_all(body)?;
+  }
+  Ok(())
+}
+
+fn not_found(stream: &mut TcpStream, head: bool) -> std::io::Result<()> {
+  respond(stream, 404, "Not Found", "text/plain", b"not found\n", head)
+}
+
+fn handle(mut stream: TcpStream, shared: &Shared) -> std::io::Result<()> {
+  stream.set_nonblocking(false)?;
+  stream.set_read_timeout(Some(Duration::from_secs(10)))?;
+  let peer = stream.peer_addr().map(|a| a.to_string()).unwrap_or_default();
+
+  let mut reader = BufReader::new(stream.try_clone()?);
+  let mut request_line = String::new();
+  reader.read_line(&mut request_line)?;
+  // Skip headers; nothing we serve depends on them
+  let mut header_bytes = 0;
+  loop {
+    let mut line = String::new();
+    let n = reader.read_line(&mut line)?;
+    header_bytes += n;
+    if n == 0 || line == "\r\n" || line == "\n" || header_bytes > MAX_HEADER_BYTES {
+      break;
+    }
+  }
+
+  let mut parts = request_line.split_whitespace();
+  let method = parts.next().unwrap_or("");
+  let target = parts.next().unwrap_or("");
+  let path = target.split('?').next().unwrap_or("");
+  let head = method == "HEAD";
+  if !matches!(method, "GET" | "HEAD" | "POST") {
+    return respond(&mut stream, 405, "Method Not Allowed", "text/plain", b"", false);
+  }
+  info!("PXE HTTP {} {} {}", peer, method, path);
+
+  let segments: Vec<&str> = path.trim_start_matches('/').split('/').collect();
+  let script = "text/plain; charset=utf-8";
+  match segments.as_slice() {
+    ["boot.ipxe"] => respond(&mut stream, 200, "OK", script, autoinstall::chain_script(shared).as_bytes(), head),
+    ["boot", mac] => match shared.node(mac) {
+      Some((mac, _)) => {
+        shared.advance(&mac, BootPhase::Booted);
+        respond(&mut stream, 200, "OK", script, autoinstall::boot_script(shared, &mac).as_bytes(), head)
+      }
+      None => respond(&mut stream, 200, "OK", script, autoinstall::unknown_script(mac).as_bytes(), head),
+    },
+    ["autoinstall", mac, file] => match (shared.node(mac), *file) {
+      (Some((mac, node)), "user-data") => {
+        shared.advance(&mac, BootPhase::Installing);
+        let body = autoinstall::user_data(shared, &mac, node);
+        respond(&mut stream, 200, "OK", "text/yaml", body.as_bytes(), head)
+      }
+      (Some((_, node)), "meta-data") => {
+        respond(&mut stream, 200, "OK", "text/yaml", autoinstall::meta_data(node).as_bytes(), head)
+      }
+      (Some(_), "vendor-data") => respond(&mut stream, 200, "OK", "text/yaml", b"", head),
+      _ => not_found(&mut stream, head),
+    },
+    ["done", mac] => match shared.node(mac) {
+      Some((mac, _)) => {
+        shared.advance(&mac, BootPhase::Completed);
+        respond(&mut stream, 200, "OK", "text/plain", b"ok\n", head)
+      }
+      None => not_found(&mut stream, head),
+    },
+    ["files", name] => serve_file(&mut stream, shared, name, head),
+    _ => not_found(&mut stream, head),
+  }
+}
+
+fn serve_file(stream: &mut TcpStream, shared: &Shared, name: &str, head: bool) -> std::io::Result<()> {
+  let Some(path) = artifact_path(&shared.artifacts, name) else {
+    return not_found(stream, head);
+  };
+  let Ok(mut file) = File::open(&path) else {
+    return not_found(stream, head);
+  };
+  let len = file.metadata()?.len();
+  write!(
+    stream,
+    "HTTP/1.1 200 OK\r\nContent-Type: application/octet-stream\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+    len
+  )?;
+  if !head {
+    std::io::copy(&mut file, stream)?;
+  }
+  Ok(())
+}
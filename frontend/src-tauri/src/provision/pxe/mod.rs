@@ -0,0 +1,284 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Network installs for labs with many nodes.
+//!
+//! While running, the installer machine serves:
+//! - over HTTP, an iPXE script (`/boot.ipxe`) that chains to a per-MAC
+//!   script booting the Ubuntu kernel/initrd from the artifacts directory,
+//!   the autoinstall `user-data`/`meta-data` for each node, and a `/done`
+//!   hook the install calls when it finishes;
+//! - optionally over TFTP, the artifacts directory itself (for the iPXE
+//!   binaries the firmware fetches first; port 69 needs root).
+//!
+//! DHCP stays with the lab's router: point its next-server/filename at
+//! this machine. Every request from a known MAC moves that node along
+//! waiting → booted → installing → completed, published as
+//! `pxe-node-update` events.
+
+mod autoinstall;
+mod http;
+mod tftp;
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::net::{Ipv4Addr, TcpListener, UdpSocket};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, State};
+use tracing::info;
+
+use super::seed::{self, SeedNode};
+use crate::net::wol::parse_mac;
+use crate::platform::regional;
+
+pub const NODE_EVENT: &str = "pxe-node-update";
+
+const DEFAULT_HTTP_PORT: u16 = 8070;
+const TFTP_PORT: u16 = 69;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PxeNode {
+  pub mac: String,
+  #[serde(flatten)]
+  pub node: SeedNode,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PxeConfig {
+  /// Address of this machine on the provisioning network
+  pub bind_address: Ipv4Addr,
+  pub http_port: Option<u16>,
+  #[serde(default)]
+  pub tftp: bool,
+  /// Holds the iPXE binaries, kernel, initrd and optionally the live ISO
+  pub artifacts_dir: String,
+  #[serde(default = "default_kernel")]
+  pub kernel: String,
+  #[serde(default = "default_initrd")]
+  pub initrd: String,
+  /// Live-server ISO inside `artifacts_dir`, passed to the installer as `url=`
+  pub iso: Option<String>,
+  pub nodes: Vec<PxeNode>,
+}
+
+fn default_kernel() -> String {
+  "vmlinuz".to_string()
+}
+
+fn default_initrd() -> String {
+  "initrd".to_string()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BootPhase {
+  Waiting,
+  Booted,
+  Installing,
+  Completed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeBootState {
+  pub mac: String,
+  pub hostname: String,
+  pub phase: BootPhase,
+  /// Seconds since the Unix epoch
+  pub updated_at: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PxeStatus {
+  pub running: bool,
+  pub boot_url: Option<String>,
+  pub tftp: bool,
+  pub nodes: Vec<NodeBootState>,
+}
+
+/// What the server threads share.
+pub struct Shared {
+  app: AppHandle,
+  config: PxeConfig,
+  artifacts: PathBuf,
+  /// Nodes by normalized MAC (`aa:bb:cc:dd:ee:ff`)
+  nodes: BTreeMap<String, PxeNode>,
+  states: Mutex<BTreeMap<String, NodeBootState>>,
+}
+
+impl Shared {
+  fn base_url(&self) -> String {
+    format!(
+      "http://{}:{}",
+      self.config.bind_address,
+      self.config.http_port.unwrap_or(DEFAULT_HTTP_PORT)
+    )
+  }
+
+  fn node(&self, mac: &str) -> Option<(String, &PxeNode)> {
+    let mac = normalize_mac(mac).ok()?;
+    let node = self.nodes.get(&mac)?;
+    Some((mac, node))
+  }
+
+  /// Record progress for `mac`. Phases only move forward, except that a
+  /// fresh boot after completion starts the node over.
+  fn advance(&self, mac: &str, phase: BootPhase) {
+    let Ok(mut states) = self.states.lock() else { return };
+    let Some(state) = states.get_mut(mac) else { return };
+    if phase <= state.phase && !(phase == BootPhase::Booted && state.phase == BootPhase::Completed) {
+      return;
+    }
+    state.phase = phase;
+    state.updated_at = now();
+    info!("PXE: {} ({}) is now {:?}", state.hostname, mac, phase);
+    let _ = self.app.emit(NODE_EVENT, state.clone());
+  }
+
+  fn states(&self) -> Vec<NodeBootState> {
+    self.states.lock().map(|s| s.values().cloned().collect()).unwrap_or_default()
+  }
+}
+
+struct Running {
+  shared: Arc<Shared>,
+  stop: Arc<AtomicBool>,
+  threads: Vec<JoinHandle<()>>,
+}
+
+#[derive(Default)]
+pub struct PxeServer(Mutex<Option<Running>>);
+
+fn now() -> u64 {
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|d| d.as_secs())
+    .unwrap_or(0)
+}
+
+fn normalize_mac(mac: &str) -> Result<String, String> {
+  let bytes = parse_mac(mac)?;
+  Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect::<Vec<_>>().join(":"))
+}
+
+fn prepare(app: &AppHandle, config: PxeConfig) -> Result<Shared, String> {
+  let artifacts = PathBuf::from(&config.artifacts_dir);
+  if !artifacts.is_dir() {
+    return Err(format!("Artifacts directory {} does not exist", artifacts.display()));
+  }
+  for file in [Some(&config.kernel), Some(&config.initrd), config.iso.as_ref()].into_iter().flatten() {
+    if http::artifact_path(&artifacts, file).map(|p| p.is_file()) != Some(true) {
+      return Err(format!("{} not found in {}", file, artifacts.display()));
+    }
+  }
+
+  let keyboard = regional::keyboard(app);
+  let mut nodes = BTreeMap::new();
+  let mut states = BTreeMap::new();
+  for node in &config.nodes {
+    seed::validate(&node.node)?;
+    let mut node = node.clone();
+    node.node.keyboard.get_or_insert_with(|| keyboard.clone());
+    let mac = normalize_mac(&node.mac)?;
+    states.insert(
+      mac.clone(),
+      NodeBootState {
+        mac: mac.clone(),
+        hostname: node.node.hostname.clone(),
+        phase: BootPhase::Waiting,
+        updated_at: now(),
+      },
+    );
+    if nodes.insert(mac.clone(), node).is_some() {
+      return Err(format!("MAC {} is listed twice", mac));
+    }
+  }
+
+  Ok(Shared {
+    app: app.clone(),
+    config,
+    artifacts,
+    nodes,
+    states: Mutex::new(states),
+  })
+}
+
+fn status(running: Option<&Running>) -> PxeStatus {
+  match running {
+    Some(r) => PxeStatus {
+      running: true,
+      boot_url: Some(format!("{}/boot.ipxe", r.shared.base_url())),
+      tftp: r.shared.config.tftp,
+      nodes: r.shared.states(),
+    },
+    None => PxeStatus {
+      running: false,
+      boot_url: None,
+      tftp: false,
+      nodes: Vec::new(),
+    },
+  }
+}
+
+fn stop_running(running: Running) {
+  running.stop.store(true, Ordering::SeqCst);
+  for thread in running.threads {
+    let _ = thread.join();
+  }
+  info!("PXE server stopped");
+}
+
+#[tauri::command]
+pub fn start_pxe_server(app: AppHandle, server: State<'_, PxeServer>, config: PxeConfig) -> Result<PxeStatus, String> {
+  let mut slot = server.0.lock().map_err(|e| e.to_string())?;
+  if let Some(running) = slot.take() {
+    stop_running(running);
+  }
+
+  let shared = Arc::new(prepare(&app, config)?);
+  let address = shared.config.bind_address;
+  let http_port = shared.config.http_port.unwrap_or(DEFAULT_HTTP_PORT);
+
+  let listener = TcpListener::bind((address, http_port))
+    .map_err(|e| format!("Cannot listen on {}:{}: {}", address, http_port, e))?;
+  let tftp_socket = if shared.config.tftp {
+    Some(UdpSocket::bind((address, TFTP_PORT)).map_err(|e| {
+      format!("Cannot listen on {}:{} for TFTP (needs root): {}", address, TFTP_PORT, e)
+    })?)
+  } else {
+    None
+  };
+
+  let stop = Arc::new(AtomicBool::new(false));
+  let mut threads = Vec::new();
+  {
+    let (shared, stop) = (shared.clone(), stop.clone());
+    threads.push(std::thread::spawn(move || http::serve(listener, shared, stop)));
+  }
+  if let Some(socket) = tftp_socket {
+    let (root, stop) = (shared.artifacts.clone(), stop.clone());
+    threads.push(std::thread::spawn(move || tftp::serve(socket, root, stop)));
+  }
+  info!("PXE server listening on {}", shared.base_url());
+
+  *slot = Some(Running { shared, stop, threads });
+  Ok(status(slot.as_ref()))
+}
+
+#[tauri::command]
+pub fn stop_pxe_server(server: State<'_, PxeServer>) -> Result<(), String> {
+  if let Some(running) = server.0.lock().map_err(|e| e.to_string())?.take() {
+    stop_running(running);
+  }
+  Ok(())
+}
+
+#[tauri::command]
+pub fn get_pxe_status(server: State<'_, PxeServer>) -> PxeStatus {
+  server.0.lock().map(|slot| status(slot.as_ref())).unwrap_or_else(|_| status(None))
+}
@@ -0,0 +1,109 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Read-only TFTP (RFC 1350) for the iPXE binaries firmware loads before
+//! it can speak HTTP. Options such as `blksize` are ignored, so transfers use
+//! 512-byte blocks, which every client falls back to.
+
+use std::fs::File;
+use std::io::Read;
+use std::net::{SocketAddr, UdpSocket};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use super::http::artifact_path;
+
+const RRQ: u16 = 1;
+const DATA: u16 = 3;
+const ACK: u16 = 4;
+const ERROR: u16 = 5;
+const BLOCK: usize = 512;
+const RETRIES: u32 = 5;
+
+pub fn serve(socket: UdpSocket, root: PathBuf, stop: Arc<AtomicBool>) {
+  let _ = socket.set_read_timeout(Some(Duration::from_millis(500)));
+  let mut buf = [0u8; 1024];
+  while !stop.load(Ordering::SeqCst) {
+    let Ok((n, peer)) = socket.recv_from(&mut buf) else { continue };
+    if n < 4 || u16::from_be_bytes([buf[0], buf[1]]) != RRQ {
+      continue;
+    }
+    let Some(name) = buf[2..n].split(|&b| b == 0).next().map(|s| String::from_utf8_lossy(s).to_string()) else {
+      continue;
+    };
+    let local = socket.local_addr().ok();
+    let (root, stop) = (root.clone(), stop.clone());
+    std::thread::spawn(move || {
+      if let Err(e) = transfer(local, peer, &root, &name, &stop) {
+        warn!("PXE TFTP {} {}: {}", peer, name, e);
+      }
+    });
+  }
+}
+
+fn error_packet(message: &str) -> Vec<u8> {
+  let mut packet = vec![0, ERROR as u8, 0, 1];
+  packet.extend_from_slice(message.as_bytes());
+  packet.push(0);
+  packet
+}
+
+/// Send `name` to `peer` from a fresh ephemeral port, as the protocol wants.
+fn transfer(local: Option<SocketAddr>, peer: SocketAddr, root: &std::path::Path, name: &str, stop: &AtomicBool) -> Result<(), String> {
+  let bind_ip = local.map(|a| a.ip()).ok_or("no local address")?;
+  let socket = UdpSocket::bind((bind_ip, 0)).map_err(|e| e.to_string())?;
+  socket.connect(peer).map_err(|e| e.to_string())?;
+  socket
+    .set_read_timeout(Some(Duration::from_secs(2)))
+    .map_err(|e| e.to_string())?;
+
+  let file = artifact_path(root, name).and_then(|p| File::open(p).ok());
+  let Some(mut file) = file else {
+    let _ = socket.send(&error_packet("File not found"));
+    return Err("not found".to_string());
+  };
+  info!("PXE TFTP {} {}", peer, name);
+
+  let mut block: u16 = 1;
+  let mut data = [0u8; BLOCK];
+  loop {
+    let mut filled = 0;
+    while filled < BLOCK {
+      match file.read(&mut data[filled..]).map_err(|e| e.to_string())? {
+        0 => break,
+        n => filled += n,
+      }
+    }
+    let mut packet = vec![0, DATA as u8];
+    packet.extend_from_slice(&block.to_be_bytes());
+    packet.extend_from_slice(&data[..filled]);
+
+    let mut acked = false;
+    for _ in 0..RETRIES {
+      if stop.load(Ordering::SeqCst) {
+        return Err("server stopped".to_string());
+      }
+      socket.send(&packet).map_err(|e| e.to_string())?;
+      let mut ack = [0u8; 4];
+      if let Ok(4) = socket.recv(&mut ack) {
+        if u16::from_be_bytes([ack[0], ack[1]]) == ACK && u16::from_be_bytes([ack[2], ack[3]]) == block {
+          acked = true;
+          break;
+        }
+      }
+    }
+    if !acked {
+      return Err(format!("no ACK for block {}", block));
+    }
+    // A short (possibly empty) block ends the transfer
+    if filled < BLOCK {
+      return Ok(());
+    }
+    block = block.wrapping_add(1);
+  }
+}
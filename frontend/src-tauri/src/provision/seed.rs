@@ -0,0 +1,298 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! cloud-init NoCloud seed images.
+//!
+//! `build_seed_iso` renders `user-data`, `meta-data` and `network-config`
+//! for a node into `seeds/<hostname>/` in the run workspace and packs them
+//! into an ISO labelled `cidata`, which cloud-init picks up when it's
+//! attached as a CD-ROM or USB stick. The YAML is written by hand with every
+//! string as a JSON-quoted scalar (valid YAML), so no value can break out of
+//! its field. The ISO is built with whichever tool the host has:
+//! `cloud-localds`, `genisoimage`/`mkisofs`, `xorriso`, or `hdiutil` on
+//! macOS.
+
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tauri::{AppHandle, Manager};
+use tracing::info;
+
+use crate::cancel::{self, Operation};
+use crate::platform::find_program;
+use crate::platform::regional::{self, KeyboardLayout};
+use crate::validation;
+use crate::workspace::{write_private_file, Workspace};
+
+pub const DEFAULT_USER: &str = "thinkube";
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct StaticNetwork {
+  /// Interface name; `None` matches the first wired interface (`en*`/`eth*`)
+  pub interface: Option<String>,
+  /// Address in CIDR form, e.g. `192.168.1.10/24`
+  pub address: String,
+  pub gateway: Option<String>,
+  #[serde(default)]
+  pub dns: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SeedNode {
+  pub hostname: String,
+  pub ssh_authorized_keys: Vec<String>,
+  pub username: Option<String>,
+  /// `None` configures DHCP
+  pub network: Option<StaticNetwork>,
+  /// Console and X11 keyboard; the wizard's choice when unset
+  #[serde(default)]
+  pub keyboard: Option<KeyboardLayout>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SeedIso {
+  pub path: String,
+  pub user_data: String,
+  pub meta_data: String,
+  pub network_config: String,
+}
+
+/// `value` as a double-quoted YAML scalar.
+pub fn yaml_str(value: &str) -> String {
+  serde_json::Value::String(value.to_string()).to_string()
+}
+
+fn is_valid_ssh_key(key: &str) -> bool {
+  let kind = key.split_whitespace().next().unwrap_or("");
+  !key.contains('\n')
+    && key.split_whitespace().count() >= 2
+    && (kind.starts_with("ssh-") || kind.starts_with("ecdsa-") || kind.starts_with("sk-"))
+}
+
+fn is_valid_cidr(cidr: &str) -> bool {
+  let Some((addr, prefix)) = cidr.split_once('/') else { return false };
+  match (addr.parse::<IpAddr>(), prefix.parse::<u8>()) {
+    (Ok(IpAddr::V4(_)), Ok(p)) => p <= 32,
+    (Ok(IpAddr::V6(_)), Ok(p)) => p <= 128,
+    _ => false,
+  }
+}
+
+pub fn validate(node: &SeedNode) -> Result<(), String> {
+  if !validation::is_valid_label(&node.hostname) {
+    return Err(format!("Invalid hostname: {}", node.hostname));
+  }
+  if let Some(user) = &node.username {
+    if !validation::is_valid_label(user) || user.starts_with(|c: char| c.is_ascii_digit()) {
+      return Err(format!("Invalid username: {}", user));
+    }
+  }
+  if node.ssh_authorized_keys.is_empty() {
+    return Err("At least one SSH public key is required".to_string());
+  }
+  if let Some(key) = node.ssh_authorized_keys.iter().find(|k| !is_valid_ssh_key(k.trim())) {
+    return Err(format!("Not an SSH public key: {}", key.chars().take(40).collect::<String>()));
+  }
+  if let Some(keyboard) = &node.keyboard {
+    keyboard.validate()?;
+  }
+  if let Some(net) = &node.network {
+    if !is_valid_cidr(&net.address) {
+      return Err(format!("Invalid address (expected CIDR): {}", net.address));
+    }
+    if let Some(interface) = &net.interface {
+      if !validation::is_valid_interface(interface) {
+        return Err(format!("Invalid interface name: {}", interface));
+      }
+    }
+    for ip in net.gateway.iter().chain(net.dns.iter()) {
+      if ip.parse::<IpAddr>().is_err() {
+        return Err(format!("Invalid IP address: {}", ip));
+      }
+    }
+  }
+  Ok(())
+}
+
+fn render_user_data(node: &SeedNode) -> String {
+  let user = node.username.as_deref().unwrap_or(DEFAULT_USER);
+  let mut out = String::from("#cloud-config\n");
+  out.push_str(&format!("hostname: {}\n", yaml_str(&node.hostname)));
+  out.push_str("preserve_hostname: false\n");
+  out.push_str("ssh_pwauth: false\n");
+  out.push_str("users:\n");
+  out.push_str(&format!("  - name: {}\n", yaml_str(user)));
+  out.push_str("    groups: [sudo]\n");
+  out.push_str("    shell: /bin/bash\n");
+  out.push_str("    sudo: \"ALL=(ALL) NOPASSWD:ALL\"\n");
+  out.push_str("    lock_passwd: true\n");
+  out.push_str("    ssh_authorized_keys:\n");
+  for key in &node.ssh_authorized_keys {
+    out.push_str(&format!("      - {}\n", yaml_str(key.trim())));
+  }
+  if let Some(keyboard) = &node.keyboard {
+    out.push_str(&render_keyboard(keyboard, ""));
+  }
+  out.push_str("package_update: true\n");
+  out.push_str("packages: [openssh-server, python3]\n");
+  out
+}
+
+/// The `keyboard` section of cloud-init and autoinstall configs, each line
+/// starting with `indent`.
+pub fn render_keyboard(keyboard: &KeyboardLayout, indent: &str) -> String {
+  let mut out = format!("{0}keyboard:\n{0}  layout: {1}\n", indent, yaml_str(&keyboard.layout));
+  if !keyboard.variant.is_empty() {
+    out.push_str(&format!("{}  variant: {}\n", indent, yaml_str(&keyboard.variant)));
+  }
+  if !keyboard.model.is_empty() {
+    out.push_str(&format!("{}  model: {}\n", indent, yaml_str(&keyboard.model)));
+  }
+  out
+}
+
+fn render_meta_data(node: &SeedNode) -> String {
+  format!(
+    "instance-id: {}\nlocal-hostname: {}\n",
+    yaml_str(&format!("thinkube-{}", node.hostname)),
+    yaml_str(&node.hostname)
+  )
+}
+
+pub fn render_network_config(node: &SeedNode) -> String {
+  let mut out = String::from("version: 2\nethernets:\n  primary:\n");
+  let interface = node.network.as_ref().and_then(|n| n.interface.as_deref());
+  match interface {
+    Some(name) => out.push_str(&format!("    match:\n      name: {}\n", yaml_str(name))),
+    None => out.push_str("    match:\n      name: \"e[nt]*\"\n"),
+  }
+  match &node.network {
+    None => out.push_str("    dhcp4: true\n"),
+    Some(net) => {
+      out.push_str("    dhcp4: false\n");
+      out.push_str(&format!("    addresses: [{}]\n", yaml_str(&net.address)));
+      if let Some(gateway) = &net.gateway {
+        out.push_str(&format!("    routes:\n      - to: default\n        via: {}\n", yaml_str(gateway)));
+      }
+      if !net.dns.is_empty() {
+        let servers: Vec<String> = net.dns.iter().map(|d| yaml_str(d)).collect();
+        out.push_str(&format!("    nameservers:\n      addresses: [{}]\n", servers.join(", ")));
+      }
+    }
+  }
+  out
+}
+
+fn run(operation: &Operation, cmd: &mut Command, tool: &str) -> Result<(), String> {
+  let output = operation
+    .output(cmd).map_err(|e| format!("Failed to run {}: {}", tool, e))?;
+  if !output.status.success() {
+    return Err(format!("{} failed: {}", tool, String::from_utf8_lossy(&output.stderr).trim()));
+  }
+  Ok(())
+}
+
+fn build_iso(operation: &Operation, dir: &Path, iso: &Path) -> Result<(), String> {
+  let files = ["user-data", "meta-data", "network-config"];
+
+  if let Some(tool) = find_program("cloud-localds", &[]) {
+    return run(
+      operation,
+      Command::new(tool)
+        .current_dir(dir)
+        .arg("--network-config=network-config")
+        .arg(iso)
+        .args(["user-data", "meta-data"]),
+      "cloud-localds",
+    );
+  }
+  for name in ["genisoimage", "mkisofs"] {
+    if let Some(tool) = find_program(name, &[]) {
+      return run(
+        operation,
+        Command::new(tool)
+          .current_dir(dir)
+          .arg("-output")
+          .arg(iso)
+          .args(["-volid", "cidata", "-joliet", "-rock"])
+          .args(files),
+        name,
+      );
+    }
+  }
+  if let Some(tool) = find_program("xorriso", &[]) {
+    return run(
+      operation,
+      Command::new(tool)
+        .current_dir(dir)
+        .args(["-as", "mkisofs", "-output"])
+        .arg(iso)
+        .args(["-volid", "cidata", "-joliet", "-rock"])
+        .args(files),
+      "xorriso",
+    );
+  }
+  if cfg!(target_os = "macos") {
+    // hdiutil packs a whole directory, so stage just the seed files
+    let staging = dir.join("cidata");
+    std::fs::create_dir_all(&staging).map_err(|e| e.to_string())?;
+    for name in files {
+      std::fs::copy(dir.join(name), staging.join(name)).map_err(|e| e.to_string())?;
+    }
+    let result = run(
+      operation,
+      Command::new("hdiutil")
+        .args(["makehybrid", "-iso", "-joliet", "-default-volume-name", "cidata", "-o"])
+        .arg(iso)
+        .arg(&staging),
+      "hdiutil",
+    );
+    let _ = std::fs::remove_dir_all(&staging);
+    return result;
+  }
+  Err("No ISO tool found; install cloud-image-utils, genisoimage or xorriso".to_string())
+}
+
+fn build(workspace: &Workspace, operation: &Operation, node: &SeedNode) -> Result<SeedIso, String> {
+  validate(node)?;
+
+  let user_data = render_user_data(node);
+  let meta_data = render_meta_data(node);
+  let network_config = render_network_config(node);
+
+  let iso: PathBuf = workspace.resolve(&format!("seeds/{}/seed.iso", node.hostname))?;
+  let dir = iso.parent().ok_or("Invalid seed path")?.to_path_buf();
+  for (name, contents) in [
+    ("user-data", &user_data),
+    ("meta-data", &meta_data),
+    ("network-config", &network_config),
+  ] {
+    write_private_file(&dir.join(name), contents.as_bytes())?;
+  }
+  let _ = std::fs::remove_file(&iso);
+  build_iso(operation, &dir, &iso)?;
+  info!("Built cloud-init seed for {} at {}", node.hostname, iso.display());
+
+  Ok(SeedIso {
+    path: iso.display().to_string(),
+    user_data,
+    meta_data,
+    network_config,
+  })
+}
+
+#[tauri::command]
+pub async fn build_seed_iso(app: AppHandle, mut node: SeedNode) -> Result<SeedIso, String> {
+  if node.keyboard.is_none() {
+    node.keyboard = Some(regional::keyboard(&app));
+  }
+  tauri::async_runtime::spawn_blocking(move || {
+    let operation = cancel::begin(&app, &format!("seed ISO for {}", node.hostname));
+    build(&app.state::<Workspace>(), &operation, &node)
+  })
+    .await
+    .map_err(|e| e.to_string())?
+}
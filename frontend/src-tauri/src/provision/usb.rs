@@ -0,0 +1,481 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Writing node images to USB sticks.
+//!
+//! Only removable/USB whole disks are listed, and never the disk holding the
+//! root filesystem. Writing is two-step: `prepare_usb_write` checks the image
+//! against the device and returns a short-lived token describing exactly
+//! what will be overwritten; the UI shows that to the user and only then
+//! calls `write_usb_image` with the token. Before writing, the device is
+//! looked up again and must still match what was confirmed. The image is
+//! streamed in chunks with `usb-write-progress` events, synced, and read
+//! back for comparison.
+//!
+//! Raw block devices are only writable by root (or the `disk` group on
+//! Linux); the error says so rather than trying to elevate.
+
+use serde::Serialize;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tracing::info;
+
+use crate::cancel;
+
+pub const PROGRESS_EVENT: &str = "usb-write-progress";
+
+const CHUNK: usize = 4 * 1024 * 1024;
+const PLAN_TTL: Duration = Duration::from_secs(120);
+/// Anything bigger is unlikely to be a USB stick someone meant to wipe.
+const MAX_DEVICE_BYTES: u64 = 2 * 1024 * 1024 * 1024 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UsbDevice {
+  /// Whole-disk device node, e.g. `/dev/sdb` or `/dev/disk4`
+  pub path: String,
+  pub model: String,
+  pub size_bytes: u64,
+  /// Mounted partitions, which are unmounted before writing on macOS and
+  /// must be unmounted by the user on Linux
+  pub mounted: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UsbWritePlan {
+  pub token: String,
+  pub device: UsbDevice,
+  pub image: String,
+  pub image_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "phase", rename_all = "snake_case")]
+pub enum WriteProgress {
+  Writing { bytes_done: u64, bytes_total: u64 },
+  Verifying { bytes_done: u64, bytes_total: u64 },
+  Done { bytes_total: u64, elapsed_secs: u64 },
+}
+
+struct PendingPlan {
+  plan: UsbWritePlan,
+  created: Instant,
+}
+
+#[derive(Default)]
+pub struct UsbWriter {
+  pending: Mutex<Option<PendingPlan>>,
+  busy: AtomicBool,
+  cancel: AtomicBool,
+}
+
+/// `dev` is a partition of `disk`: `/dev/sda1` of `/dev/sda`, `/dev/nvme0n1p1`
+/// of `/dev/nvme0n1`, but not `/dev/sdaa`.
+#[cfg(target_os = "linux")]
+fn is_partition_of(dev: &str, disk: &str) -> bool {
+  let Some(rest) = dev.strip_prefix(disk) else {
+    return false;
+  };
+  let rest = match rest.strip_prefix('p') {
+    Some(number) if disk.ends_with(|c: char| c.is_ascii_digit()) => number,
+    _ => rest,
+  };
+  !rest.is_empty() && rest.chars().all(|c| c.is_ascii_digit())
+}
+
+/// lsblk's raw output escapes spaces and the like as `\xNN`.
+#[cfg(target_os = "linux")]
+fn unescape_lsblk(field: &str) -> String {
+  let mut bytes = Vec::new();
+  let mut rest = field.as_bytes();
+  while let Some((&b, tail)) = rest.split_first() {
+    if b == b'\\' && tail.first() == Some(&b'x') {
+      if let Some(byte) = tail
+        .get(1..3)
+        .and_then(|h| std::str::from_utf8(h).ok())
+        .and_then(|h| u8::from_str_radix(h, 16).ok())
+      {
+        bytes.push(byte);
+        rest = &tail[3..];
+        continue;
+      }
+    }
+    bytes.push(b);
+    rest = tail;
+  }
+  String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// Where `disk` or anything on it is mounted. lsblk follows the disk down
+/// through partitions, LUKS and LVM to what's really mounted; without it
+/// only the disk and its partitions are recognised.
+#[cfg(target_os = "linux")]
+fn mounts_on(disk: &str, mount_points: &[(String, String)]) -> Vec<String> {
+  let lsblk = crate::platform::find_program("lsblk", &[])
+    .and_then(|lsblk| {
+      std::process::Command::new(lsblk)
+        .args(["-n", "-r", "-o", "MOUNTPOINT", disk])
+        .output()
+        .ok()
+    })
+    .filter(|output| output.status.success());
+  if let Some(output) = lsblk {
+    return String::from_utf8_lossy(&output.stdout)
+      .lines()
+      .filter(|line| !line.trim().is_empty())
+      .map(unescape_lsblk)
+      .collect();
+  }
+  mount_points
+    .iter()
+    .filter(|(dev, _)| dev == disk || is_partition_of(dev, disk))
+    .map(|(_, point)| point.clone())
+    .collect()
+}
+
+#[cfg(target_os = "linux")]
+fn list_devices() -> Result<Vec<UsbDevice>, String> {
+  let mounts = std::fs::read_to_string("/proc/mounts").unwrap_or_default();
+  let mount_points: Vec<(String, String)> = mounts
+    .lines()
+    .filter_map(|line| {
+      let mut fields = line.split_whitespace();
+      Some((fields.next()?.to_string(), fields.next()?.to_string()))
+    })
+    .collect();
+  let read = |path: PathBuf| {
+    std::fs::read_to_string(path)
+      .map(|s| s.trim().to_string())
+      .unwrap_or_default()
+  };
+
+  let mut devices = Vec::new();
+  let entries = std::fs::read_dir("/sys/block").map_err(|e| format!("Cannot list block devices: {}", e))?;
+  for entry in entries.flatten() {
+    let name = entry.file_name().to_string_lossy().to_string();
+    if name.starts_with("loop") || name.starts_with("ram") || name.starts_with("dm-") || name.starts_with("zram") {
+      continue;
+    }
+    let sys = entry.path();
+    let on_usb = std::fs::canonicalize(sys.join("device"))
+      .map(|p| p.to_string_lossy().contains("/usb"))
+      .unwrap_or(false);
+    if read(sys.join("removable")) != "1" && !on_usb {
+      continue;
+    }
+    let size_bytes = read(sys.join("size")).parse::<u64>().unwrap_or(0) * 512;
+    if size_bytes == 0 {
+      continue;
+    }
+
+    let path = format!("/dev/{}", name);
+    let mounted = mounts_on(&path, &mount_points);
+    if mounted.iter().any(|p| p == "/" || p == "/boot" || p == "/boot/efi") {
+      continue;
+    }
+
+    let model = format!("{} {}", read(sys.join("device/vendor")), read(sys.join("device/model")))
+      .trim()
+      .to_string();
+    devices.push(UsbDevice {
+      path,
+      model,
+      size_bytes,
+      mounted,
+    });
+  }
+  Ok(devices)
+}
+
+#[cfg(target_os = "macos")]
+fn list_devices() -> Result<Vec<UsbDevice>, String> {
+  use std::process::Command;
+
+  let diskutil = |args: &[&str]| -> String {
+    Command::new("diskutil")
+      .args(args)
+      .output()
+      .map(|o| String::from_utf8_lossy(&o.stdout).to_string())
+      .unwrap_or_default()
+  };
+  let field = |info: &str, key: &str| -> String {
+    info
+      .lines()
+      .find_map(|l| l.trim().strip_prefix(key).map(|v| v.trim().to_string()))
+      .unwrap_or_default()
+  };
+
+  let mut devices = Vec::new();
+  // "/dev/disk4 (external, physical):"
+  for line in diskutil(&["list", "external", "physical"]).lines() {
+    let Some(path) = line.strip_suffix(" (external, physical):") else {
+      continue;
+    };
+    let info = diskutil(&["info", path]);
+    if field(&info, "Internal:") == "Yes" {
+      continue;
+    }
+    // "Disk Size: 16.0 GB (16008609792 Bytes) (exactly 31266816 512-Byte-Units)"
+    let size_bytes = field(&info, "Disk Size:")
+      .split('(')
+      .nth(1)
+      .and_then(|s| s.split_whitespace().next())
+      .and_then(|n| n.parse::<u64>().ok())
+      .unwrap_or(0);
+    let mounted = diskutil(&["list", path])
+      .lines()
+      .filter_map(|l| l.split_whitespace().last())
+      .filter(|id| id.starts_with("disk") && id.contains('s'))
+      .filter_map(|id| {
+        let point = field(&diskutil(&["info", id]), "Mount Point:");
+        Some(point).filter(|p| !p.is_empty())
+      })
+      .collect();
+    devices.push(UsbDevice {
+      path: path.to_string(),
+      model: field(&info, "Device / Media Name:"),
+      size_bytes,
+      mounted,
+    });
+  }
+  Ok(devices)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn list_devices() -> Result<Vec<UsbDevice>, String> {
+  Err("Writing USB media is not supported on this platform".to_string())
+}
+
+fn find_device(path: &str) -> Result<UsbDevice, String> {
+  list_devices()?
+    .into_iter()
+    .find(|d| d.path == path)
+    .ok_or_else(|| format!("{} is not a removable device", path))
+}
+
+/// The node to write to: on macOS the raw `rdisk` node is much faster.
+fn write_target(device: &UsbDevice) -> PathBuf {
+  if cfg!(target_os = "macos") {
+    PathBuf::from(device.path.replacen("/dev/disk", "/dev/rdisk", 1))
+  } else {
+    PathBuf::from(&device.path)
+  }
+}
+
+fn unmount(device: &UsbDevice) -> Result<(), String> {
+  if device.mounted.is_empty() {
+    return Ok(());
+  }
+  if cfg!(target_os = "macos") {
+    let status = std::process::Command::new("diskutil")
+      .args(["unmountDisk", &device.path])
+      .status()
+      .map_err(|e| format!("Failed to unmount {}: {}", device.path, e))?;
+    if status.success() {
+      return Ok(());
+    }
+  }
+  Err(format!(
+    "{} has mounted partitions ({}); eject them first",
+    device.path,
+    device.mounted.join(", ")
+  ))
+}
+
+fn open_device(path: &Path, write: bool) -> Result<File, String> {
+  OpenOptions::new().read(true).write(write).open(path).map_err(|e| {
+    if e.kind() == std::io::ErrorKind::PermissionDenied {
+      format!(
+        "Permission denied opening {}; writing USB media needs root (or membership of the disk group)",
+        path.display()
+      )
+    } else {
+      format!("Failed to open {}: {}", path.display(), e)
+    }
+  })
+}
+
+fn read_full(reader: &mut impl Read, buf: &mut [u8]) -> std::io::Result<usize> {
+  let mut filled = 0;
+  while filled < buf.len() {
+    match reader.read(&mut buf[filled..])? {
+      0 => break,
+      n => filled += n,
+    }
+  }
+  Ok(filled)
+}
+
+fn write_and_verify(app: &AppHandle, writer: &UsbWriter, plan: &UsbWritePlan) -> Result<(), String> {
+  let current = find_device(&plan.device.path)?;
+  if current.model != plan.device.model || current.size_bytes != plan.device.size_bytes {
+    return Err(format!(
+      "{} changed since it was confirmed; start again",
+      plan.device.path
+    ));
+  }
+  unmount(&current)?;
+
+  let started = Instant::now();
+  let total = plan.image_bytes;
+  let target = write_target(&current);
+  let operation = cancel::begin(app, &format!("USB write to {}", plan.device.path));
+  let cancelled = || writer.cancel.load(Ordering::SeqCst) || operation.is_cancelled();
+  let mut buf = vec![0u8; CHUNK];
+
+  let mut image = File::open(&plan.image).map_err(|e| format!("Failed to open {}: {}", plan.image, e))?;
+  let mut device = open_device(&target, true)?;
+  let mut done = 0u64;
+  while done < total {
+    if cancelled() {
+      return Err("USB write cancelled; the device is left partially written".to_string());
+    }
+    let n = read_full(&mut image, &mut buf).map_err(|e| format!("Failed to read image: {}", e))?;
+    if n == 0 {
+      break;
+    }
+    device
+      .write_all(&buf[..n])
+      .map_err(|e| format!("Failed to write {}: {}", target.display(), e))?;
+    done += n as u64;
+    let _ = app.emit(
+      PROGRESS_EVENT,
+      WriteProgress::Writing {
+        bytes_done: done,
+        bytes_total: total,
+      },
+    );
+  }
+  device
+    .sync_all()
+    .map_err(|e| format!("Failed to flush {}: {}", target.display(), e))?;
+  drop(device);
+
+  let mut image = File::open(&plan.image).map_err(|e| format!("Failed to open {}: {}", plan.image, e))?;
+  let mut device = open_device(&target, false)?;
+  device.seek(SeekFrom::Start(0)).map_err(|e| e.to_string())?;
+  let mut written = vec![0u8; CHUNK];
+  let mut checked = 0u64;
+  while checked < total {
+    if cancelled() {
+      return Err("Verification cancelled; the image was written but not checked".to_string());
+    }
+    let n = read_full(&mut image, &mut buf).map_err(|e| format!("Failed to read image: {}", e))?;
+    if n == 0 {
+      break;
+    }
+    let m = read_full(&mut device, &mut written[..n])
+      .map_err(|e| format!("Failed to read back {}: {}", target.display(), e))?;
+    if m != n || buf[..n] != written[..n] {
+      return Err(format!(
+        "Verification failed at byte {}; the stick may be faulty",
+        checked
+      ));
+    }
+    checked += n as u64;
+    let _ = app.emit(
+      PROGRESS_EVENT,
+      WriteProgress::Verifying {
+        bytes_done: checked,
+        bytes_total: total,
+      },
+    );
+  }
+
+  let _ = app.emit(
+    PROGRESS_EVENT,
+    WriteProgress::Done {
+      bytes_total: total,
+      elapsed_secs: started.elapsed().as_secs(),
+    },
+  );
+  info!("Wrote and verified {} to {}", plan.image, plan.device.path);
+  Ok(())
+}
+
+fn new_token() -> Result<String, String> {
+  crate::backend::random_hex(16)
+}
+
+#[tauri::command]
+pub fn list_usb_devices() -> Result<Vec<UsbDevice>, String> {
+  list_devices()
+}
+
+/// Check `image` fits on `device` and return the plan the user must confirm.
+#[tauri::command]
+pub fn prepare_usb_write(writer: State<'_, UsbWriter>, image: String, device: String) -> Result<UsbWritePlan, String> {
+  let image_bytes = std::fs::metadata(&image)
+    .map_err(|e| format!("Cannot read image {}: {}", image, e))?
+    .len();
+  if image_bytes == 0 {
+    return Err(format!("Image {} is empty", image));
+  }
+  let device = find_device(&device)?;
+  if device.size_bytes > MAX_DEVICE_BYTES {
+    return Err(format!(
+      "{} is larger than 2 TiB; refusing to treat it as a USB stick",
+      device.path
+    ));
+  }
+  if image_bytes > device.size_bytes {
+    return Err(format!(
+      "Image is {} bytes but {} only holds {} bytes",
+      image_bytes, device.path, device.size_bytes
+    ));
+  }
+
+  let plan = UsbWritePlan {
+    token: new_token()?,
+    device,
+    image,
+    image_bytes,
+  };
+  *writer.pending.lock().map_err(|e| e.to_string())? = Some(PendingPlan {
+    plan: plan.clone(),
+    created: Instant::now(),
+  });
+  Ok(plan)
+}
+
+/// Write the image of a confirmed plan. `token` must be the one returned
+/// by `prepare_usb_write` within the last two minutes.
+#[tauri::command]
+pub async fn write_usb_image(app: AppHandle, token: String) -> Result<(), String> {
+  let writer = app.state::<UsbWriter>();
+  let plan = {
+    let mut pending = writer.pending.lock().map_err(|e| e.to_string())?;
+    match pending.take() {
+      Some(p) if p.plan.token == token && p.created.elapsed() < PLAN_TTL => p.plan,
+      _ => return Err("USB write was not confirmed or the confirmation expired".to_string()),
+    }
+  };
+  if crate::dry_run::is_enabled(&app) {
+    info!("Dry run: not writing {} to {}", plan.image, plan.device.path);
+    return Ok(());
+  }
+  if writer.busy.swap(true, Ordering::SeqCst) {
+    return Err("A USB write is already in progress".to_string());
+  }
+  writer.cancel.store(false, Ordering::SeqCst);
+
+  let handle = app.clone();
+  let result =
+    tauri::async_runtime::spawn_blocking(move || write_and_verify(&handle, &handle.state::<UsbWriter>(), &plan))
+      .await
+      .map_err(|e| e.to_string())
+      .and_then(|r| r);
+  writer.busy.store(false, Ordering::SeqCst);
+  result
+}
+
+#[tauri::command]
+pub fn cancel_usb_write(writer: State<'_, UsbWriter>) {
+  writer.cancel.store(true, Ordering::SeqCst);
+}
@@ -0,0 +1,292 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Shells on a pseudo-terminal, for the wizard's embedded terminal.
+//!
+//! `open_pty_session` starts either a local login shell, set up like the
+//! one `open_terminal` opens, or `ssh -tt` to a node over the pooled
+//! connection, on a PTY of its own as the leader of a new session. What the
+//! shell writes is emitted as `pty-output` events, base64 encoded since a
+//! read can end halfway through a UTF-8 sequence or an escape; the
+//! frontend's terminal widget decodes and renders it. Keystrokes come back
+//! through `write_pty` and window size changes through `resize_pty`, which
+//! the shell sees as SIGWINCH. `close_pty_session` stops the shell and
+//! whatever it started; either way a `pty-exit` event follows once the
+//! shell is gone.
+
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::process::{Child, Command};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tracing::info;
+
+use crate::backend::process;
+use crate::desktop::terminal;
+use crate::remote::ssh::{self, SshPool, SshTarget};
+use crate::workspace::Workspace;
+
+pub const OUTPUT_EVENT: &str = "pty-output";
+pub const EXIT_EVENT: &str = "pty-exit";
+
+const MAX_SESSIONS: usize = 16;
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct PtySize {
+  pub cols: u16,
+  pub rows: u16,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PtySession {
+  pub session_id: String,
+  /// The node, or `None` for a local shell
+  pub host: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PtyOutput {
+  pub session_id: String,
+  /// Raw terminal output, base64 encoded
+  pub data: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PtyExit {
+  pub session_id: String,
+  pub exit_code: Option<i32>,
+}
+
+struct Session {
+  master: File,
+  /// Leader of the shell's session and process group
+  pid: u32,
+}
+
+#[derive(Default)]
+pub struct PtySessions {
+  sessions: Mutex<HashMap<String, Session>>,
+  next_id: AtomicU64,
+}
+
+#[cfg(unix)]
+fn winsize(size: PtySize) -> libc::winsize {
+  libc::winsize {
+    ws_row: size.rows.max(1),
+    ws_col: size.cols.max(1),
+    ws_xpixel: 0,
+    ws_ypixel: 0,
+  }
+}
+
+/// A new PTY as `(master, slave)`, neither inherited by children.
+#[cfg(unix)]
+fn open_pty(size: PtySize) -> Result<(File, File), String> {
+  use std::os::fd::FromRawFd;
+
+  let (mut master, mut slave) = (0, 0);
+  let mut size = winsize(size);
+  // SAFETY: openpty only writes the two descriptors; name and termios may be
+  // null. The size is a `*const` on Linux but a `*mut` on macOS
+  let opened = unsafe {
+    libc::openpty(
+      &mut master,
+      &mut slave,
+      std::ptr::null_mut(),
+      std::ptr::null_mut(),
+      std::ptr::addr_of_mut!(size),
+    )
+  };
+  if opened != 0 {
+    return Err(format!(
+      "Failed to open a pseudo-terminal: {}",
+      std::io::Error::last_os_error()
+    ));
+  }
+  for fd in [master, slave] {
+    // SAFETY: fd was just opened and is owned here
+    unsafe { libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) };
+  }
+  // SAFETY: both descriptors are open and nothing else owns them
+  Ok(unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) })
+}
+
+#[cfg(unix)]
+fn set_size(master: &File, size: PtySize) -> Result<(), String> {
+  use std::os::fd::AsRawFd;
+
+  let size = winsize(size);
+  // SAFETY: TIOCSWINSZ reads a winsize from the pointer
+  if unsafe { libc::ioctl(master.as_raw_fd(), libc::TIOCSWINSZ as _, &size) } == -1 {
+    return Err(format!(
+      "Failed to resize the terminal: {}",
+      std::io::Error::last_os_error()
+    ));
+  }
+  Ok(())
+}
+
+/// Start `cmd` on `slave` as the leader of a new session, with the PTY as
+/// its controlling terminal.
+#[cfg(unix)]
+fn spawn(mut cmd: Command, slave: File) -> Result<Child, String> {
+  use std::os::unix::process::CommandExt;
+
+  let io = |file: &File| file.try_clone().map_err(|e| e.to_string());
+  cmd
+    .stdin(io(&slave)?)
+    .stdout(io(&slave)?)
+    .stderr(slave)
+    .env("TERM", "xterm-256color");
+  // SAFETY: only async-signal-safe calls between fork and exec
+  unsafe {
+    cmd.pre_exec(|| {
+      if libc::setsid() == -1 || libc::ioctl(0, libc::TIOCSCTTY as _, 0) == -1 {
+        return Err(std::io::Error::last_os_error());
+      }
+      Ok(())
+    });
+  }
+  // `cmd` holds copies of the slave; they go with it, so the shell exiting
+  // ends the output
+  cmd.spawn().map_err(|e| format!("Failed to start the shell: {}", e))
+}
+
+#[cfg(not(unix))]
+fn open_pty(_size: PtySize) -> Result<(File, File), String> {
+  Err("The embedded terminal is not supported on this platform".to_string())
+}
+
+#[cfg(not(unix))]
+fn set_size(_master: &File, _size: PtySize) -> Result<(), String> {
+  Err("The embedded terminal is not supported on this platform".to_string())
+}
+
+#[cfg(not(unix))]
+fn spawn(_cmd: Command, _slave: File) -> Result<Child, String> {
+  Err("The embedded terminal is not supported on this platform".to_string())
+}
+
+fn local_shell(workspace: &Workspace) -> Command {
+  let shell = std::env::var("SHELL")
+    .ok()
+    .filter(|s| !s.is_empty())
+    .unwrap_or_else(|| "/bin/bash".to_string());
+  let mut cmd = Command::new(shell);
+  cmd
+    .arg("-l")
+    .current_dir(workspace.root())
+    .envs(terminal::environment(workspace, None, None));
+  cmd
+}
+
+/// Relay the shell's output until it exits, then report how.
+fn forward(app: AppHandle, session_id: String, mut master: File, mut child: Child) {
+  std::thread::spawn(move || {
+    let engine = base64::engine::general_purpose::STANDARD;
+    let mut buf = [0u8; 16 * 1024];
+    loop {
+      // Linux fails the read with EIO rather than returning 0 once the
+      // shell has closed its side
+      let n = match master.read(&mut buf) {
+        Ok(0) | Err(_) => break,
+        Ok(n) => n,
+      };
+      let _ = app.emit(
+        OUTPUT_EVENT,
+        PtyOutput {
+          session_id: session_id.clone(),
+          data: engine.encode(&buf[..n]),
+        },
+      );
+    }
+    let exit_code = child.wait().ok().and_then(|status| status.code());
+    if let Ok(mut sessions) = app.state::<PtySessions>().sessions.lock() {
+      sessions.remove(&session_id);
+    }
+    info!("Terminal session {} ended", session_id);
+    let _ = app.emit(EXIT_EVENT, PtyExit { session_id, exit_code });
+  });
+}
+
+/// Open a shell on a PTY of `size`: on `target` over SSH, or locally.
+#[tauri::command]
+pub fn open_pty_session(
+  app: AppHandle,
+  sessions: State<'_, PtySessions>,
+  target: Option<SshTarget>,
+  size: PtySize,
+) -> Result<PtySession, String> {
+  if sessions.sessions.lock().map_err(|e| e.to_string())?.len() >= MAX_SESSIONS {
+    return Err(format!("At most {} terminals can be open at once", MAX_SESSIONS));
+  }
+  let workspace = app.state::<Workspace>();
+  let cmd = match &target {
+    Some(target) => ssh::interactive(&workspace, &app.state::<SshPool>(), target)?,
+    None => local_shell(&workspace),
+  };
+
+  let (master, slave) = open_pty(size)?;
+  let child = spawn(cmd, slave)?;
+  let session_id = format!("pty-{}", sessions.next_id.fetch_add(1, Ordering::SeqCst) + 1);
+  let reader = master.try_clone().map_err(|e| e.to_string())?;
+  sessions.sessions.lock().map_err(|e| e.to_string())?.insert(
+    session_id.clone(),
+    Session {
+      master,
+      pid: child.id(),
+    },
+  );
+  let host = target.map(|t| t.host);
+  info!(
+    "Terminal session {} opened on {}",
+    session_id,
+    host.as_deref().unwrap_or("this machine")
+  );
+  forward(app.clone(), session_id.clone(), reader, child);
+  Ok(PtySession { session_id, host })
+}
+
+/// Type `data` into the session.
+#[tauri::command]
+pub fn write_pty(sessions: State<'_, PtySessions>, session_id: String, data: String) -> Result<(), String> {
+  let sessions = sessions.sessions.lock().map_err(|e| e.to_string())?;
+  let session = sessions
+    .get(&session_id)
+    .ok_or_else(|| format!("No terminal session {}", session_id))?;
+  (&session.master)
+    .write_all(data.as_bytes())
+    .map_err(|e| format!("Failed to write to the terminal: {}", e))
+}
+
+#[tauri::command]
+pub fn resize_pty(sessions: State<'_, PtySessions>, session_id: String, size: PtySize) -> Result<(), String> {
+  let sessions = sessions.sessions.lock().map_err(|e| e.to_string())?;
+  let session = sessions
+    .get(&session_id)
+    .ok_or_else(|| format!("No terminal session {}", session_id))?;
+  set_size(&session.master, size)
+}
+
+/// End the session's shell and everything it started.
+#[tauri::command]
+pub fn close_pty_session(sessions: State<'_, PtySessions>, session_id: String) -> Result<(), String> {
+  let pid = sessions
+    .sessions
+    .lock()
+    .map_err(|e| e.to_string())?
+    .get(&session_id)
+    .map(|s| s.pid)
+    .ok_or_else(|| format!("No terminal session {}", session_id))?;
+  info!("Closing terminal session {}", session_id);
+  std::thread::spawn(move || {
+    process::terminate_group(pid, || !process::group_exists(pid));
+  });
+  Ok(())
+}
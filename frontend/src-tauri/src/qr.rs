@@ -0,0 +1,46 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! QR codes for service URLs, so dashboards can be opened from a phone.
+
+use base64::Engine;
+use qrcode::render::svg;
+use qrcode::{EcLevel, QrCode};
+use serde::Serialize;
+use tauri::Url;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QrImage {
+  pub mime_type: &'static str,
+  /// Base64 of the SVG document
+  pub data: String,
+  /// Ready to use as an `<img src>`
+  pub data_url: String,
+}
+
+/// Render `url` as an SVG QR code. Only http(s) URLs are accepted so the
+/// code can't be used to smuggle arbitrary payloads onto the screen.
+#[tauri::command]
+pub fn generate_qr(url: String) -> Result<QrImage, String> {
+  let parsed = Url::parse(url.trim()).map_err(|e| format!("Invalid URL {:?}: {}", url, e))?;
+  if !matches!(parsed.scheme(), "http" | "https") {
+    return Err(format!("Only http(s) URLs can be encoded, got {}", parsed.scheme()));
+  }
+
+  let code = QrCode::with_error_correction_level(parsed.as_str(), EcLevel::M)
+    .map_err(|e| format!("Failed to encode {}: {}", parsed, e))?;
+  let image = code
+    .render::<svg::Color>()
+    .min_dimensions(256, 256)
+    .quiet_zone(true)
+    .build();
+
+  let data = base64::engine::general_purpose::STANDARD.encode(image);
+  Ok(QrImage {
+    mime_type: "image/svg+xml",
+    data_url: format!("data:image/svg+xml;base64,{}", data),
+    data,
+  })
+}
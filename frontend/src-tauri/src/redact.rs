@@ -0,0 +1,115 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Masking secrets out of everything that is logged or streamed.
+//!
+//! Two kinds of secret are masked. Values the shell knows (keyring
+//! entries, the API and instance tokens, and whatever the wizard registers
+//! with `register_secret`, such as the sudo password) are replaced wherever
+//! they appear. Common token shapes are masked whether registered or not:
+//! GitHub, GitLab and Hugging Face tokens, `Bearer`/`Basic` credentials, and
+//! the value of any `password`, `secret` or `token` key. Backend output,
+//! remote command output and every shell log line pass through [`redact`]
+//! before they reach a file, the terminal or a window.
+
+use regex::{Captures, Regex};
+use std::borrow::Cow;
+use std::io::Write;
+use std::sync::{OnceLock, RwLock};
+
+pub const MASK: &str = "********";
+
+/// Registered values shorter than this would mask ordinary text.
+const MIN_SECRET_LEN: usize = 4;
+
+static SECRETS: RwLock<Vec<String>> = RwLock::new(Vec::new());
+
+struct Patterns {
+  tokens: Regex,
+  auth: Regex,
+  keyed: Regex,
+}
+
+fn patterns() -> &'static Patterns {
+  static PATTERNS: OnceLock<Patterns> = OnceLock::new();
+  PATTERNS.get_or_init(|| Patterns {
+    tokens: Regex::new(
+      r"\b(?:gh[pousr]_[A-Za-z0-9]{20,}|github_pat_[A-Za-z0-9_]{20,}|glpat-[A-Za-z0-9_-]{20,}|hf_[A-Za-z0-9]{20,})",
+    )
+    .expect("valid token pattern"),
+    auth: Regex::new(r"(?i)\b(bearer|basic)(\s+)[A-Za-z0-9._~+/=-]{8,}").expect("valid auth pattern"),
+    keyed: Regex::new(
+      r#"(?i)([A-Za-z0-9_-]*(?:password|passwd|secret|token|api_?key|become_pass)[A-Za-z0-9_-]*["']?\s*[:=]\s*["']?)([^\s"',&;}]+)"#,
+    )
+    .expect("valid key pattern"),
+  })
+}
+
+/// Mask `secret` from now on.
+pub fn register(secret: &str) {
+  let secret = secret.trim();
+  if secret.len() < MIN_SECRET_LEN {
+    return;
+  }
+  if let Ok(mut secrets) = SECRETS.write() {
+    if !secrets.iter().any(|s| s == secret) {
+      secrets.push(secret.to_string());
+      // Longest first, so a secret containing another is masked whole
+      secrets.sort_by_key(|s| std::cmp::Reverse(s.len()));
+    }
+  }
+}
+
+/// `text` with every registered secret and known token shape masked.
+pub fn redact(text: &str) -> Cow<'_, str> {
+  let mut text = Cow::Borrowed(text);
+  if let Ok(secrets) = SECRETS.read() {
+    for secret in secrets.iter() {
+      if text.contains(secret.as_str()) {
+        text = Cow::Owned(text.replace(secret.as_str(), MASK));
+      }
+    }
+  }
+
+  let patterns = patterns();
+  for (regex, replace) in [
+    (&patterns.tokens, (|_: &Captures| MASK.to_string()) as fn(&Captures) -> String),
+    (&patterns.auth, |c: &Captures| format!("{}{}{}", &c[1], &c[2], MASK)),
+    (&patterns.keyed, |c: &Captures| {
+      if &c[2] == MASK {
+        c[0].to_string()
+      } else {
+        format!("{}{}", &c[1], MASK)
+      }
+    }),
+  ] {
+    if regex.is_match(&text) {
+      text = Cow::Owned(regex.replace_all(&text, replace).into_owned());
+    }
+  }
+  text
+}
+
+/// A writer that masks secrets in what passes through it. Log formatters
+/// write each line in one call, so a secret is never split across writes.
+pub struct Redacting<W>(pub W);
+
+impl<W: Write> Write for Redacting<W> {
+  fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+    let text = String::from_utf8_lossy(buf);
+    self.0.write_all(redact(&text).as_bytes())?;
+    Ok(buf.len())
+  }
+
+  fn flush(&mut self) -> std::io::Result<()> {
+    self.0.flush()
+  }
+}
+
+/// Mask a value the wizard knows to be secret, e.g. the sudo password.
+#[tauri::command]
+pub fn register_secret(value: String) {
+  register(&value);
+}
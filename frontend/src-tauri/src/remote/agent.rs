@@ -0,0 +1,155 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Authenticating to nodes with keys held by an ssh-agent.
+//!
+//! `detect_ssh_agent` finds the agent the app can reach, through
+//! `SSH_AUTH_SOCK` or, for desktop sessions that didn't pass it on, the
+//! sockets GNOME Keyring, gcr, systemd user units, GnuPG and 1Password
+//! listen on, and lists its identities with `ssh-add -L`; keys on a
+//! hardware token show up like any other. A target with an `agent_key`
+//! authenticates with that identity only: its public key is written to the
+//! run workspace and handed to `ssh` with `IdentitiesOnly`, which makes
+//! `ssh` ask the agent to sign with the matching private key, so no key
+//! file path is needed.
+
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use super::host_keys;
+use crate::platform::find_program;
+use crate::workspace::{write_private_file, Workspace};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AgentIdentity {
+  /// e.g. `ssh-ed25519` or `sk-ssh-ed25519@openssh.com` for a token
+  pub key_type: String,
+  pub fingerprint: String,
+  /// Usually the key's file or the token it lives on
+  pub comment: String,
+  /// The base64 public key
+  pub key: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AgentStatus {
+  pub socket: Option<String>,
+  /// An agent answered on `socket`
+  pub running: bool,
+  pub identities: Vec<AgentIdentity>,
+  pub error: Option<String>,
+}
+
+/// Where agents listen when `SSH_AUTH_SOCK` isn't set, relative to
+/// `XDG_RUNTIME_DIR` and the home directory.
+const RUNTIME_SOCKETS: &[&str] = &["ssh-agent.socket", "keyring/ssh", "gcr/ssh", "gnupg/S.gpg-agent.ssh"];
+const HOME_SOCKETS: &[&str] = &[".1password/agent.sock", ".gnupg/S.gpg-agent.ssh"];
+
+/// The agent socket to use, if one exists.
+pub fn socket() -> Option<PathBuf> {
+  if let Some(path) = std::env::var_os("SSH_AUTH_SOCK").map(PathBuf::from) {
+    if path.exists() {
+      return Some(path);
+    }
+  }
+  let runtime = std::env::var_os("XDG_RUNTIME_DIR").map(PathBuf::from);
+  let home = std::env::var_os("HOME").map(PathBuf::from);
+  runtime
+    .iter()
+    .flat_map(|dir| RUNTIME_SOCKETS.iter().map(move |s| dir.join(s)))
+    .chain(
+      home
+        .iter()
+        .flat_map(|dir| HOME_SOCKETS.iter().map(move |s| dir.join(s))),
+    )
+    .find(|path| path.exists())
+}
+
+/// The identities the agent on `socket` holds.
+pub fn identities(socket: &Path) -> Result<Vec<AgentIdentity>, String> {
+  let ssh_add = find_program("ssh-add", &[]).ok_or("ssh-add is not installed")?;
+  let output = Command::new(ssh_add)
+    .arg("-L")
+    .env("SSH_AUTH_SOCK", socket)
+    .stdin(Stdio::null())
+    .output()
+    .map_err(|e| format!("Failed to run ssh-add: {}", e))?;
+  // 1 is an agent without identities, 2 no agent at all
+  match output.status.code() {
+    Some(0) => {}
+    Some(1) => return Ok(Vec::new()),
+    _ => {
+      let stderr = String::from_utf8_lossy(&output.stderr);
+      return Err(format!(
+        "No ssh-agent answered on {}: {}",
+        socket.display(),
+        stderr.trim().lines().last().unwrap_or("ssh-add failed")
+      ));
+    }
+  }
+  Ok(
+    String::from_utf8_lossy(&output.stdout)
+      .lines()
+      .filter_map(|line| {
+        let mut fields = line.splitn(3, ' ');
+        let key_type = fields.next()?.to_string();
+        let key = fields.next()?.to_string();
+        Some(AgentIdentity {
+          fingerprint: host_keys::fingerprint(&key).ok()?,
+          comment: fields.next().unwrap_or_default().trim().to_string(),
+          key_type,
+          key,
+        })
+      })
+      .collect(),
+  )
+}
+
+/// The agent socket and a public key file for the identity with
+/// `fingerprint`, for `ssh -o IdentityAgent=… -i …`.
+pub fn identity_file(workspace: &Workspace, fingerprint: &str) -> Result<(PathBuf, PathBuf), String> {
+  let socket = socket().ok_or("No ssh-agent is running")?;
+  let identity = identities(&socket)?
+    .into_iter()
+    .find(|i| i.fingerprint == fingerprint)
+    .ok_or_else(|| format!("The ssh-agent no longer holds the key {}", fingerprint))?;
+  let name: String = fingerprint
+    .chars()
+    .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+    .collect();
+  let path = workspace.resolve(&format!("ssh/agent-{}.pub", name))?;
+  write_private_file(
+    &path,
+    format!("{} {} {}\n", identity.key_type, identity.key, identity.comment).as_bytes(),
+  )?;
+  Ok((socket, path))
+}
+
+#[tauri::command]
+pub async fn detect_ssh_agent() -> Result<AgentStatus, String> {
+  tauri::async_runtime::spawn_blocking(|| {
+    let Some(socket) = socket() else {
+      return AgentStatus {
+        socket: None,
+        running: false,
+        identities: Vec::new(),
+        error: None,
+      };
+    };
+    let (running, identities, error) = match identities(&socket) {
+      Ok(identities) => (true, identities, None),
+      Err(e) => (false, Vec::new(), Some(e)),
+    };
+    AgentStatus {
+      socket: Some(socket.display().to_string()),
+      running,
+      identities,
+      error,
+    }
+  })
+  .await
+  .map_err(|e| e.to_string())
+}
@@ -0,0 +1,242 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! dpkg and APT locks held on the nodes.
+//!
+//! A freshly booted Ubuntu runs unattended-upgrades within minutes, and
+//! every playbook task that installs a package fails while it holds the
+//! dpkg lock. `check_apt_locks` shows which process holds which lock;
+//! `wait_for_apt_locks` polls until they're free, emitting an
+//! `apt-lock-wait` event with the time left after each poll; and
+//! `stop_unattended_upgrades` stops the APT timers and lets a running
+//! upgrade finish its current package and exit, journaled so a rollback
+//! starts the timers again.
+
+use serde::Serialize;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+use tracing::info;
+
+use super::ssh::{self, SshPool, SshTarget};
+use crate::dry_run;
+use crate::journal::{self, Action};
+use crate::workspace::Workspace;
+
+pub const EVENT: &str = "apt-lock-wait";
+
+const SCAN_TIMEOUT: Duration = Duration::from_secs(30);
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+const DEFAULT_WAIT: Duration = Duration::from_secs(600);
+const MAX_WAIT: Duration = Duration::from_secs(3600);
+/// `unattended-upgrade` finishes the package it is on before exiting
+const STOP_TIMEOUT: Duration = Duration::from_secs(600);
+const LOCKS: &[&str] = &[
+  "/var/lib/dpkg/lock-frontend",
+  "/var/lib/dpkg/lock",
+  "/var/lib/apt/lists/lock",
+  "/var/cache/apt/archives/lock",
+];
+const APT_TIMERS: &[&str] = &["apt-daily.timer", "apt-daily-upgrade.timer"];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LockHolder {
+  pub lock: String,
+  pub pid: u32,
+  pub command: String,
+  /// How long the process has been running
+  pub elapsed_secs: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeAptLocks {
+  pub host: String,
+  pub holders: Vec<LockHolder>,
+  pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AptLockWait {
+  pub host: String,
+  pub holders: Vec<LockHolder>,
+  pub remaining_secs: u64,
+}
+
+fn scan_script() -> String {
+  format!(
+    r#"
+export LC_ALL=C
+for lock in {locks}; do
+  [ -e "$lock" ] || continue
+  for pid in $(sudo -n fuser "$lock" 2>/dev/null); do
+    echo "$lock $(ps -o pid=,etimes=,args= -p "$pid")"
+  done
+done
+echo '@@end'
+"#,
+    locks = LOCKS.join(" ")
+  )
+}
+
+fn parse(output: &str) -> Vec<LockHolder> {
+  let mut holders: Vec<LockHolder> = Vec::new();
+  for line in output.lines() {
+    let mut fields = line.split_whitespace();
+    let (Some(lock), Some(pid), Some(elapsed)) = (fields.next(), fields.next(), fields.next()) else {
+      continue;
+    };
+    let Ok(pid) = pid.parse::<u32>() else {
+      continue;
+    };
+    // The frontend lock holder usually holds the others too
+    if holders.iter().any(|h| h.pid == pid) {
+      continue;
+    }
+    holders.push(LockHolder {
+      lock: lock.to_string(),
+      pid,
+      command: fields.collect::<Vec<_>>().join(" "),
+      elapsed_secs: elapsed.parse().unwrap_or_default(),
+    });
+  }
+  holders
+}
+
+fn scan(workspace: &Workspace, pool: &SshPool, target: &SshTarget) -> Result<Vec<LockHolder>, String> {
+  let output = ssh::run_script(workspace, pool, target, &scan_script(), SCAN_TIMEOUT)?;
+  if !output.stdout.contains("@@end") {
+    return Err(format!("Lock check did not complete: {}", output.stderr.trim()));
+  }
+  Ok(parse(&output.stdout))
+}
+
+/// List the processes holding dpkg or APT locks on each node.
+#[tauri::command]
+pub async fn check_apt_locks(app: AppHandle, hosts: Vec<SshTarget>) -> Result<Vec<NodeAptLocks>, String> {
+  for target in &hosts {
+    target.validate()?;
+  }
+  tauri::async_runtime::spawn_blocking(move || {
+    let workspace = app.state::<Workspace>();
+    let workspace: &Workspace = &workspace;
+    let pool = app.state::<SshPool>();
+    let pool: &SshPool = &pool;
+
+    std::thread::scope(|scope| {
+      let handles: Vec<_> = hosts
+        .iter()
+        .map(|target| scope.spawn(move || scan(workspace, pool, target)))
+        .collect();
+      handles
+        .into_iter()
+        .zip(&hosts)
+        .map(|(handle, target)| {
+          let result = handle.join().unwrap_or_else(|_| Err("Lock check panicked".to_string()));
+          NodeAptLocks {
+            host: target.host.clone(),
+            error: result.as_ref().err().cloned(),
+            holders: result.unwrap_or_default(),
+          }
+        })
+        .collect()
+    })
+  })
+  .await
+  .map_err(|e| e.to_string())
+}
+
+/// Wait until no process holds a dpkg or APT lock on the node, for up to
+/// `timeout_secs` (10 minutes by default). Fails with the remaining
+/// holders when time runs out.
+#[tauri::command]
+pub async fn wait_for_apt_locks(app: AppHandle, host: SshTarget, timeout_secs: Option<u64>) -> Result<(), String> {
+  host.validate()?;
+  let timeout = timeout_secs
+    .map(Duration::from_secs)
+    .unwrap_or(DEFAULT_WAIT)
+    .min(MAX_WAIT);
+  tauri::async_runtime::spawn_blocking(move || {
+    let workspace = app.state::<Workspace>();
+    let pool = app.state::<SshPool>();
+    let deadline = Instant::now() + timeout;
+    loop {
+      let holders = scan(&workspace, &pool, &host)?;
+      let remaining = deadline.saturating_duration_since(Instant::now());
+      let _ = app.emit(
+        EVENT,
+        AptLockWait {
+          host: host.host.clone(),
+          holders: holders.clone(),
+          remaining_secs: remaining.as_secs(),
+        },
+      );
+      if holders.is_empty() {
+        return Ok(());
+      }
+      if remaining.is_zero() {
+        let commands: Vec<String> = holders.iter().map(|h| format!("{} ({})", h.command, h.pid)).collect();
+        return Err(format!(
+          "APT is still locked on {} after {} s by {}",
+          host.host,
+          timeout.as_secs(),
+          commands.join(", ")
+        ));
+      }
+      std::thread::sleep(POLL_INTERVAL.min(remaining));
+    }
+  })
+  .await
+  .map_err(|e| e.to_string())?
+}
+
+/// Stop the APT timers for the rest of the install and stop a running
+/// unattended-upgrades at its next safe point.
+#[tauri::command]
+pub async fn stop_unattended_upgrades(app: AppHandle, host: SshTarget) -> Result<(), String> {
+  host.validate()?;
+  if dry_run::is_enabled(&app) {
+    info!("Dry run: would stop unattended-upgrades on {}", host.host);
+    return Ok(());
+  }
+  tauri::async_runtime::spawn_blocking(move || {
+    // systemd sends SIGTERM, on which unattended-upgrade finishes the
+    // package it is installing instead of leaving dpkg half-configured
+    let script = format!(
+      "set -e\nsudo -n systemctl stop {}\nsudo -n systemctl stop apt-daily-upgrade.service apt-daily.service\n",
+      APT_TIMERS.join(" ")
+    );
+    let output = ssh::run_script(
+      &app.state::<Workspace>(),
+      &app.state::<SshPool>(),
+      &host,
+      &script,
+      STOP_TIMEOUT,
+    )?;
+    if output.status != Some(0) {
+      let detail = output
+        .stderr
+        .trim()
+        .lines()
+        .last()
+        .unwrap_or("command failed")
+        .to_string();
+      return Err(format!(
+        "Failed to stop unattended-upgrades on {}: {}",
+        host.host, detail
+      ));
+    }
+    info!("Stopped unattended-upgrades on {}", host.host);
+    journal::record(
+      &app,
+      Action::ServicesStopped {
+        target: host,
+        units: APT_TIMERS.iter().map(|t| t.to_string()).collect(),
+        enabled: Vec::new(),
+      },
+    );
+    Ok(())
+  })
+  .await
+  .map_err(|e| e.to_string())?
+}
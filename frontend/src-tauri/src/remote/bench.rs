@@ -0,0 +1,478 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Quick disk and network benchmarks of cluster nodes.
+//!
+//! Not a tuning tool, just enough to spot hardware that will make the
+//! install miserable: a disk whose `fdatasync` takes tens of milliseconds
+//! (etcd writes one per commit) or a link that turns multi-gigabyte image
+//! pulls into half an hour.
+//!
+//! Disk tests run `fio` in the target directory when it's installed and
+//! fall back to `dd`, which gives sequential throughput and an average sync
+//! latency but no random IOPS. The test file is removed however the script
+//! exits. Nodes are benchmarked in parallel for disk, since each uses its
+//! own disk, but one at a time for the network, since they'd share this
+//! machine's link.
+//!
+//! The network test streams zeros over a plain TCP connection to a one-shot
+//! Python listener on the node, in both directions. When a firewall blocks
+//! the listener's port it falls back to streaming through SSH, which also
+//! measures cipher overhead and so reads low on fast links.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, TcpStream, ToSocketAddrs};
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+use tracing::info;
+
+use super::inventory::sections;
+use super::ssh::{self, shell_quote, SshPool, SshTarget};
+use crate::workspace::Workspace;
+
+const DEFAULT_PATH: &str = "/var/tmp";
+const DEFAULT_DISK_MIB: u64 = 256;
+const MAX_DISK_MIB: u64 = 4096;
+const DEFAULT_NET_SECS: u64 = 5;
+const MAX_NET_SECS: u64 = 30;
+const DISK_TIMEOUT: Duration = Duration::from_secs(300);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const CHUNK: usize = 1 << 20;
+
+/// etcd's guidance: 99% of fsyncs under 10 ms
+const WARN_FSYNC_MS: f64 = 10.0;
+const WARN_SEQ_MIB_S: f64 = 100.0;
+const WARN_RAND_IOPS: f64 = 1000.0;
+const WARN_MBIT_S: f64 = 500.0;
+
+const LISTENER: &str = r#"
+import socket, sys, time
+s = socket.socket(socket.AF_INET6 if ':' in sys.argv[2] else socket.AF_INET)
+s.bind(('', 0))
+s.listen(1)
+s.settimeout(20)
+print(s.getsockname()[1], flush=True)
+c, _ = s.accept()
+while c.recv(1 << 20):
+    pass
+c.close()
+c, _ = s.accept()
+buf = b'\0' * (1 << 20)
+end = time.time() + float(sys.argv[1])
+try:
+    while time.time() < end:
+        c.sendall(buf)
+except OSError:
+    pass
+c.close()
+"#;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct BenchOptions {
+  /// Directory to test the disk under; `/var/tmp` when omitted
+  pub path: Option<String>,
+  pub disk_size_mib: Option<u64>,
+  pub network_secs: Option<u64>,
+  /// Both tests run unless one is switched off
+  pub skip_disk: Option<bool>,
+  pub skip_network: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiskTool {
+  Fio,
+  Dd,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DiskBench {
+  pub path: String,
+  pub tool: DiskTool,
+  pub seq_write_mib_s: Option<f64>,
+  pub seq_read_mib_s: Option<f64>,
+  /// 4 KiB random IO; fio only
+  pub rand_read_iops: Option<f64>,
+  pub rand_write_iops: Option<f64>,
+  /// Latency of small writes followed by fdatasync, as etcd does them
+  pub fsync_p99_ms: Option<f64>,
+  /// The only sync figure `dd` can give
+  pub fsync_avg_ms: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NetMethod {
+  Tcp,
+  Ssh,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NetBench {
+  pub method: NetMethod,
+  /// From this machine to the node
+  pub upload_mbit_s: f64,
+  pub download_mbit_s: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BenchReport {
+  pub host: String,
+  pub disk: Option<DiskBench>,
+  pub disk_error: Option<String>,
+  pub network: Option<NetBench>,
+  pub network_error: Option<String>,
+  pub warnings: Vec<String>,
+}
+
+fn is_safe_path(path: &str) -> bool {
+  path.starts_with('/')
+    && !path.split('/').any(|p| p == "..")
+    && path
+      .chars()
+      .all(|c| c.is_ascii_alphanumeric() || matches!(c, '/' | '-' | '_' | '.'))
+}
+
+fn disk_script(path: &str, size_mib: u64) -> String {
+  format!(
+    r#"set -u
+export LC_ALL=C
+dir={path}
+size={size}
+[ -d "$dir" ] && [ -w "$dir" ] || {{ echo "$dir is not a writable directory" >&2; exit 2; }}
+f="$dir/.thinkube-bench-$$"
+trap 'rm -f "$f" "$f.sync"' EXIT
+if command -v fio >/dev/null 2>&1; then
+  run() {{ fio --name=bench --filename="$f" --output-format=json "$@" 2>/dev/null; }}
+  echo '@@fio_seq_write'; run --rw=write --bs=1M --size=${{size}}M --direct=1 --end_fsync=1
+  echo '@@fio_seq_read'; run --rw=read --bs=1M --size=${{size}}M --direct=1
+  echo '@@fio_rand_read'; run --rw=randread --bs=4k --size=${{size}}M --direct=1 --ioengine=libaio --iodepth=32 --runtime=10 --time_based
+  echo '@@fio_rand_write'; run --rw=randwrite --bs=4k --size=${{size}}M --direct=1 --ioengine=libaio --iodepth=32 --runtime=10 --time_based
+  echo '@@fio_fsync'; fio --name=etcd --filename="$f.sync" --rw=write --ioengine=sync --fdatasync=1 --bs=2300 --size=22m --output-format=json 2>/dev/null
+else
+  echo '@@dd_seq_write'; dd if=/dev/zero of="$f" bs=1M count=$size oflag=direct conv=fsync 2>&1 | tail -1
+  echo '@@dd_seq_read'; dd if="$f" of=/dev/null bs=1M iflag=direct 2>&1 | tail -1
+  echo '@@dd_fsync'; dd if=/dev/zero of="$f.sync" bs=2300 count=500 oflag=dsync 2>&1 | tail -1
+fi
+echo '@@end'
+"#,
+    path = shell_quote(path),
+    size = size_mib,
+  )
+}
+
+fn fio_job(lines: &[&str]) -> Option<Value> {
+  let json: Value = serde_json::from_str(&lines.join("\n")).ok()?;
+  json["jobs"].get(0).cloned()
+}
+
+/// (bytes, seconds) from dd's `268435456 bytes (268 MB, 256 MiB) copied, 1.2 s, 217 MB/s`
+fn dd_stats(lines: &[&str]) -> Option<(f64, f64)> {
+  let line = lines.iter().rev().find(|l| l.contains(" copied,"))?;
+  let bytes = line.split_whitespace().next()?.parse().ok()?;
+  let (_, after) = line.split_once(" copied, ")?;
+  let secs = after.split_whitespace().next()?.parse().ok()?;
+  Some((bytes, secs))
+}
+
+fn parse_disk(path: &str, output: &str) -> DiskBench {
+  let sections = sections(output);
+  let empty = Vec::new();
+  let section = |name: &str| sections.get(name).unwrap_or(&empty);
+  let mib = 1024.0 * 1024.0;
+
+  if sections.contains_key("fio_seq_write") {
+    // fio reports bandwidth in KiB/s
+    let bw = |name: &str, dir: &str| {
+      fio_job(section(name))
+        .and_then(|j| j[dir]["bw"].as_f64())
+        .map(|kib| kib / 1024.0)
+    };
+    let iops = |name: &str, dir: &str| fio_job(section(name)).and_then(|j| j[dir]["iops"].as_f64());
+    let fsync_p99_ms = fio_job(section("fio_fsync")).and_then(|j| {
+      j["sync"]["lat_ns"]["percentile"]["99.000000"]
+        .as_f64()
+        .map(|ns| ns / 1_000_000.0)
+    });
+    return DiskBench {
+      path: path.to_string(),
+      tool: DiskTool::Fio,
+      seq_write_mib_s: bw("fio_seq_write", "write"),
+      seq_read_mib_s: bw("fio_seq_read", "read"),
+      rand_read_iops: iops("fio_rand_read", "read"),
+      rand_write_iops: iops("fio_rand_write", "write"),
+      fsync_p99_ms,
+      fsync_avg_ms: None,
+    };
+  }
+
+  let rate = |name: &str| {
+    dd_stats(section(name))
+      .filter(|(_, s)| *s > 0.0)
+      .map(|(b, s)| b / mib / s)
+  };
+  DiskBench {
+    path: path.to_string(),
+    tool: DiskTool::Dd,
+    seq_write_mib_s: rate("dd_seq_write"),
+    seq_read_mib_s: rate("dd_seq_read"),
+    rand_read_iops: None,
+    rand_write_iops: None,
+    fsync_p99_ms: None,
+    fsync_avg_ms: dd_stats(section("dd_fsync")).map(|(_, secs)| secs * 1000.0 / 500.0),
+  }
+}
+
+fn bench_disk(
+  workspace: &Workspace,
+  pool: &SshPool,
+  target: &SshTarget,
+  path: &str,
+  size_mib: u64,
+) -> Result<DiskBench, String> {
+  let output = ssh::run_script(workspace, pool, target, &disk_script(path, size_mib), DISK_TIMEOUT)?;
+  if !output.stdout.contains("@@end") {
+    let detail = output.stderr.trim().lines().last().unwrap_or("no output").to_string();
+    return Err(format!("Disk benchmark failed on {}: {}", target.host, detail));
+  }
+  Ok(parse_disk(path, &output.stdout))
+}
+
+fn mbit_s(bytes: u64, elapsed: Duration) -> f64 {
+  bytes as f64 * 8.0 / 1_000_000.0 / elapsed.as_secs_f64().max(0.001)
+}
+
+/// Write zeros to `out` for `secs`, returning the bytes written.
+fn send_for(out: &mut impl Write, secs: Duration) -> (u64, Duration) {
+  let buf = vec![0u8; CHUNK];
+  let started = Instant::now();
+  let mut sent = 0;
+  while started.elapsed() < secs {
+    if out.write_all(&buf).is_err() {
+      break;
+    }
+    sent += CHUNK as u64;
+  }
+  let _ = out.flush();
+  (sent, started.elapsed())
+}
+
+fn receive_all(input: &mut impl Read) -> (u64, Duration) {
+  let mut buf = vec![0u8; CHUNK];
+  let started = Instant::now();
+  let mut received = 0;
+  while let Ok(n) = input.read(&mut buf) {
+    if n == 0 {
+      break;
+    }
+    received += n as u64;
+  }
+  (received, started.elapsed())
+}
+
+fn bench_tcp(workspace: &Workspace, pool: &SshPool, target: &SshTarget, secs: Duration) -> Result<NetBench, String> {
+  let remote = format!(
+    "python3 -u -c {} {} {}",
+    shell_quote(LISTENER),
+    secs.as_secs(),
+    shell_quote(&target.host)
+  );
+  let mut child = ssh::command(workspace, pool, target, &remote)?
+    .stdin(Stdio::null())
+    .stdout(Stdio::piped())
+    .stderr(Stdio::null())
+    .spawn()
+    .map_err(|e| format!("Failed to run ssh: {}", e))?;
+
+  let result = (|| {
+    let stdout = child.stdout.take().ok_or("ssh stdout unavailable")?;
+    let mut line = String::new();
+    BufReader::new(stdout).read_line(&mut line).map_err(|e| e.to_string())?;
+    let port: u16 = line.trim().parse().map_err(|_| "The node could not start a listener")?;
+    let addr = (target.host.as_str(), port)
+      .to_socket_addrs()
+      .map_err(|e| e.to_string())?
+      .next()
+      .ok_or("Cannot resolve the node's address")?;
+    let connect =
+      || TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT).map_err(|e| format!("Cannot reach port {}: {}", port, e));
+
+    let mut up = connect()?;
+    let (sent, up_time) = send_for(&mut up, secs);
+    let _ = up.shutdown(Shutdown::Write);
+    // Wait for the node to see EOF before the second connection
+    let _ = up.read(&mut [0u8; 1]);
+
+    let mut down = connect()?;
+    let (received, down_time) = receive_all(&mut down);
+    Ok(NetBench {
+      method: NetMethod::Tcp,
+      upload_mbit_s: mbit_s(sent, up_time),
+      download_mbit_s: mbit_s(received, down_time),
+    })
+  })();
+  let _ = child.kill();
+  let _ = child.wait();
+  result
+}
+
+fn bench_ssh(workspace: &Workspace, pool: &SshPool, target: &SshTarget, secs: Duration) -> Result<NetBench, String> {
+  let mut up = ssh::command(workspace, pool, target, "cat > /dev/null")?
+    .stdin(Stdio::piped())
+    .stdout(Stdio::null())
+    .stderr(Stdio::null())
+    .spawn()
+    .map_err(|e| format!("Failed to run ssh: {}", e))?;
+  let (sent, up_time) = match up.stdin.take() {
+    Some(mut stdin) => send_for(&mut stdin, secs),
+    None => (0, secs),
+  };
+  let _ = up.wait();
+
+  let mut down = ssh::command(
+    workspace,
+    pool,
+    target,
+    &format!("timeout {} cat /dev/zero", secs.as_secs()),
+  )?
+  .stdin(Stdio::null())
+  .stdout(Stdio::piped())
+  .stderr(Stdio::null())
+  .spawn()
+  .map_err(|e| format!("Failed to run ssh: {}", e))?;
+  let (received, down_time) = match down.stdout.take() {
+    Some(mut stdout) => receive_all(&mut stdout),
+    None => (0, secs),
+  };
+  let _ = down.wait();
+
+  if sent == 0 || received == 0 {
+    return Err(format!("Could not stream data to or from {}", target.host));
+  }
+  Ok(NetBench {
+    method: NetMethod::Ssh,
+    upload_mbit_s: mbit_s(sent, up_time),
+    download_mbit_s: mbit_s(received, down_time),
+  })
+}
+
+fn warnings(report: &mut BenchReport) {
+  if let Some(disk) = &report.disk {
+    if let Some(ms) = disk.fsync_p99_ms.or(disk.fsync_avg_ms) {
+      if ms > WARN_FSYNC_MS {
+        report.warnings.push(format!(
+          "fdatasync takes {:.1} ms; etcd needs under {} ms and will be unstable on this disk",
+          ms, WARN_FSYNC_MS
+        ));
+      }
+    }
+    if let Some(rate) = disk.seq_write_mib_s.filter(|r| *r < WARN_SEQ_MIB_S) {
+      report.warnings.push(format!(
+        "Sequential writes at {:.0} MiB/s; image pulls and builds will be slow",
+        rate
+      ));
+    }
+    if let Some(iops) = disk.rand_read_iops.filter(|i| *i < WARN_RAND_IOPS) {
+      report.warnings.push(format!(
+        "{:.0} random read IOPS; this looks like a spinning disk, use an SSD for the system",
+        iops
+      ));
+    }
+  }
+  if let Some(net) = &report.network {
+    let slowest = net.upload_mbit_s.min(net.download_mbit_s);
+    if slowest < WARN_MBIT_S {
+      report.warnings.push(format!(
+        "{:.0} Mbit/s to this node; gigabit Ethernet or better is recommended",
+        slowest
+      ));
+    }
+  }
+}
+
+/// Benchmark each node's disk and its network link to this machine.
+#[tauri::command]
+pub async fn benchmark_nodes(
+  app: AppHandle,
+  hosts: Vec<SshTarget>,
+  options: Option<BenchOptions>,
+) -> Result<Vec<BenchReport>, String> {
+  for target in &hosts {
+    target.validate()?;
+  }
+  let options = options.unwrap_or_default();
+  let path = options.path.clone().unwrap_or_else(|| DEFAULT_PATH.to_string());
+  if !is_safe_path(&path) {
+    return Err(format!("Invalid benchmark path: {}", path));
+  }
+  let size_mib = options
+    .disk_size_mib
+    .unwrap_or(DEFAULT_DISK_MIB)
+    .clamp(16, MAX_DISK_MIB);
+  let secs = Duration::from_secs(options.network_secs.unwrap_or(DEFAULT_NET_SECS).clamp(1, MAX_NET_SECS));
+
+  tauri::async_runtime::spawn_blocking(move || {
+    let workspace = app.state::<Workspace>();
+    let workspace: &Workspace = &workspace;
+    let pool = app.state::<SshPool>();
+    let pool: &SshPool = &pool;
+    let path = path.as_str();
+
+    let mut reports: Vec<BenchReport> = hosts
+      .iter()
+      .map(|target| BenchReport {
+        host: target.host.clone(),
+        ..Default::default()
+      })
+      .collect();
+
+    if !options.skip_disk.unwrap_or(false) {
+      let results: Vec<Result<DiskBench, String>> = std::thread::scope(|scope| {
+        let handles: Vec<_> = hosts
+          .iter()
+          .map(|target| scope.spawn(move || bench_disk(workspace, pool, target, path, size_mib)))
+          .collect();
+        handles
+          .into_iter()
+          .map(|h| h.join().unwrap_or_else(|_| Err("Disk benchmark panicked".to_string())))
+          .collect()
+      });
+      for (report, result) in reports.iter_mut().zip(results) {
+        match result {
+          Ok(disk) => report.disk = Some(disk),
+          Err(e) => report.disk_error = Some(e),
+        }
+      }
+    }
+
+    if !options.skip_network.unwrap_or(false) {
+      for (report, target) in reports.iter_mut().zip(&hosts) {
+        match bench_tcp(workspace, pool, target, secs) {
+          Ok(net) => report.network = Some(net),
+          Err(tcp_error) => {
+            info!(
+              "TCP benchmark to {} failed ({}); falling back to SSH",
+              target.host, tcp_error
+            );
+            match bench_ssh(workspace, pool, target, secs) {
+              Ok(net) => report.network = Some(net),
+              Err(e) => report.network_error = Some(e),
+            }
+          }
+        }
+      }
+    }
+
+    for report in &mut reports {
+      warnings(report);
+    }
+    reports
+  })
+  .await
+  .map_err(|e| e.to_string())
+}
@@ -0,0 +1,231 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Raspberry Pi nodes and the roles they can take.
+//!
+//! Pis make cheap workers, but with 1 to 16 GB of RAM and storage that is
+//! often an SD card they can't carry the control plane, and a Pi with too
+//! little memory or a 32-bit OS can't run the k8s snap at all. Detection
+//! reads the device-tree model, which names the board on every Pi OS and
+//! Ubuntu image, and follows the root filesystem to its disk to tell an SD
+//! card from eMMC, USB or NVMe storage. `detect_boards` runs it over SSH,
+//! `get_local_board` on the installer host, for when that is a Pi that
+//! will also be a node; the role assignment page offers only the roles in
+//! each report's `allowed_roles`.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+use super::ssh::{self, SshPool, SshTarget};
+use crate::workspace::Workspace;
+
+const DETECT_TIMEOUT: Duration = Duration::from_secs(20);
+/// Below this a Pi can't hold the kubelet, containerd and a workload
+const MIN_WORKER_MEMORY_MB: u64 = 3500;
+
+const SCRIPT: &str = r#"
+export LC_ALL=C
+echo "model=$(tr -d '\0' 2>/dev/null < /proc/device-tree/model)"
+echo "arch=$(uname -m)"
+echo "mem_kb=$(awk '/^MemTotal:/ { print $2 }' /proc/meminfo)"
+root=$(findmnt -no SOURCE / 2>/dev/null)
+disk=$(lsblk -no PKNAME "$root" 2>/dev/null | head -1)
+# Root on a whole disk, or an lsblk too old for PKNAME
+[ -n "$disk" ] || disk=$(echo "${root#/dev/}" | sed 's/p\{0,1\}[0-9]*$//')
+echo "root=$root"
+echo "disk=$disk"
+echo "tran=$(lsblk -dno TRAN "/dev/$disk" 2>/dev/null)"
+echo "mmc_type=$(cat "/sys/block/$disk/device/type" 2>/dev/null)"
+echo '@@end'
+"#;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Storage {
+  SdCard,
+  Emmc,
+  Usb,
+  Nvme,
+  Other,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+  ControlPlane,
+  Worker,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BoardReport {
+  pub host: String,
+  pub error: Option<String>,
+  pub raspberry_pi: bool,
+  /// e.g. `Raspberry Pi 5 Model B Rev 1.0`
+  pub model: Option<String>,
+  pub arch: Option<String>,
+  pub memory_mb: u64,
+  /// What the root filesystem lives on
+  pub root_storage: Option<Storage>,
+  pub allowed_roles: Vec<Role>,
+  pub warnings: Vec<String>,
+}
+
+impl BoardReport {
+  fn failed(host: &str, error: String) -> Self {
+    Self {
+      host: host.to_string(),
+      error: Some(error),
+      raspberry_pi: false,
+      model: None,
+      arch: None,
+      memory_mb: 0,
+      root_storage: None,
+      // The other pages' checks still apply; don't lock the node out here
+      allowed_roles: vec![Role::ControlPlane, Role::Worker],
+      warnings: Vec::new(),
+    }
+  }
+}
+
+fn parse(host: &str, output: &str) -> BoardReport {
+  let facts: HashMap<&str, &str> = output.lines().filter_map(|l| l.split_once('=')).collect();
+  let fact = |key: &str| facts.get(key).map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+
+  let model = fact("model");
+  let raspberry_pi = model.as_deref().is_some_and(|m| m.starts_with("Raspberry Pi"));
+  let memory_mb = fact("mem_kb").and_then(|kb| kb.parse::<u64>().ok()).unwrap_or(0) / 1024;
+  let disk = fact("disk");
+  let root_storage = disk.as_deref().map(|disk| {
+    if disk.starts_with("mmcblk") {
+      // A Pi's only MMC slot is the SD card unless it's a Compute Module
+      match fact("mmc_type").as_deref() {
+        Some("MMC") => Storage::Emmc,
+        _ => Storage::SdCard,
+      }
+    } else if disk.starts_with("nvme") {
+      Storage::Nvme
+    } else if fact("tran").as_deref() == Some("usb") {
+      Storage::Usb
+    } else {
+      Storage::Other
+    }
+  });
+
+  let mut report = BoardReport {
+    host: host.to_string(),
+    error: None,
+    raspberry_pi,
+    model,
+    arch: fact("arch"),
+    memory_mb,
+    root_storage,
+    allowed_roles: vec![Role::ControlPlane, Role::Worker],
+    warnings: Vec::new(),
+  };
+  if !raspberry_pi {
+    return report;
+  }
+
+  report.allowed_roles = vec![Role::Worker];
+  if report.arch.as_deref() != Some("aarch64") {
+    report.allowed_roles.clear();
+    report.warnings.push(format!(
+      "The OS is {}; the k8s snap needs a 64-bit (arm64) OS on the Pi",
+      report.arch.as_deref().unwrap_or("not 64-bit")
+    ));
+  } else if memory_mb < MIN_WORKER_MEMORY_MB {
+    report.allowed_roles.clear();
+    report.warnings.push(format!(
+      "{} MB of RAM is too little for a worker; use a Pi with 4 GB or more",
+      memory_mb
+    ));
+  }
+  match root_storage {
+    Some(Storage::SdCard) => report.warnings.push(
+      "The root filesystem is on an SD card, which container images and etcd wear out quickly; boot from USB or NVMe storage if you can"
+        .to_string(),
+    ),
+    Some(Storage::Usb) => report
+      .warnings
+      .push("The root filesystem is on USB storage; use a powered hub or the Pi's official supply to avoid brown-outs".to_string()),
+    _ => {}
+  }
+  report
+}
+
+fn detect(workspace: &Workspace, pool: &SshPool, target: &SshTarget) -> BoardReport {
+  match ssh::run_script(workspace, pool, target, SCRIPT, DETECT_TIMEOUT) {
+    Ok(output) if output.stdout.contains("@@end") => parse(&target.host, &output.stdout),
+    Ok(output) => BoardReport::failed(
+      &target.host,
+      format!(
+        "Board detection did not complete on {}: {}",
+        target.host,
+        output.stderr.trim()
+      ),
+    ),
+    Err(e) => BoardReport::failed(&target.host, e),
+  }
+}
+
+/// Identify Raspberry Pi nodes and the roles each node can take.
+#[tauri::command]
+pub async fn detect_boards(app: AppHandle, hosts: Vec<SshTarget>) -> Result<Vec<BoardReport>, String> {
+  for target in &hosts {
+    target.validate()?;
+  }
+  tauri::async_runtime::spawn_blocking(move || {
+    let workspace = app.state::<Workspace>();
+    let workspace: &Workspace = &workspace;
+    let pool = app.state::<SshPool>();
+    let pool: &SshPool = &pool;
+    std::thread::scope(|scope| {
+      let handles: Vec<_> = hosts
+        .iter()
+        .map(|target| scope.spawn(move || detect(workspace, pool, target)))
+        .collect();
+      handles
+        .into_iter()
+        .zip(&hosts)
+        .map(|(handle, target)| {
+          handle
+            .join()
+            .unwrap_or_else(|_| BoardReport::failed(&target.host, "Board detection panicked".to_string()))
+        })
+        .collect()
+    })
+  })
+  .await
+  .map_err(|e| e.to_string())
+}
+
+/// The same report for the installer host.
+#[tauri::command]
+pub async fn get_local_board() -> Result<BoardReport, String> {
+  if std::env::consts::OS != "linux" {
+    return Ok(parse("localhost", ""));
+  }
+  tauri::async_runtime::spawn_blocking(|| {
+    let mut child = Command::new("bash")
+      .arg("-s")
+      .stdin(Stdio::piped())
+      .stdout(Stdio::piped())
+      .stderr(Stdio::null())
+      .spawn()
+      .map_err(|e| format!("Failed to run bash: {}", e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+      let _ = stdin.write_all(SCRIPT.as_bytes());
+    }
+    let output = child.wait_with_output().map_err(|e| e.to_string())?;
+    Ok(parse("localhost", &String::from_utf8_lossy(&output.stdout)))
+  })
+  .await
+  .map_err(|e| e.to_string())?
+}
@@ -0,0 +1,558 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Container runtimes and Kubernetes distributions already on the nodes.
+//!
+//! Docker, a standalone containerd, k3s, RKE2, MicroK8s, kubeadm's kubelet
+//! or LXD from apt each fight the k8s and LXD snaps over sockets, iptables
+//! chains, cgroups or the cluster ports, and the failures show up far from
+//! the cause. `check_conflicts` looks for the packages, snaps, services and
+//! binaries of each, and for anything else listening on the ports the
+//! cluster needs. Each conflict comes with the ways to resolve it:
+//! `resolve_conflict` stops and disables its services, journaled so a
+//! rollback starts them again, or removes it for good.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tracing::info;
+
+use super::firewall::{Protocol, REQUIRED_PORTS};
+use super::inventory::sections;
+use super::ssh::{self, SshPool, SshTarget};
+use crate::dry_run;
+use crate::journal::{self, Action};
+use crate::validation;
+use crate::workspace::Workspace;
+
+const SCAN_TIMEOUT: Duration = Duration::from_secs(30);
+/// Purging packages and running uninstall scripts can take minutes
+const RESOLVE_TIMEOUT: Duration = Duration::from_secs(600);
+/// Ports the ingress controller binds on every node, on top of the ones
+/// the nodes reach each other on
+const INGRESS_PORTS: &[u16] = &[80, 443];
+/// Units of the snaps the installer sets up itself, which may already be
+/// running when a node is checked again
+const OWN_UNIT_PREFIXES: &[&str] = &["snap.k8s.", "snap.lxd."];
+
+struct Known {
+  id: &'static str,
+  name: &'static str,
+  packages: &'static [&'static str],
+  snaps: &'static [&'static str],
+  units: &'static [&'static str],
+  /// Binaries of installs that bypass the package manager
+  files: &'static [&'static str],
+  /// Extra script needed to remove it, run after the units are disabled
+  uninstall: &'static str,
+}
+
+const KNOWN: &[Known] = &[
+  Known {
+    id: "docker",
+    name: "Docker",
+    packages: &["docker-ce", "docker.io", "docker-engine", "moby-engine"],
+    snaps: &["docker"],
+    units: &["docker.service", "docker.socket", "snap.docker.dockerd.service"],
+    files: &[],
+    uninstall: "",
+  },
+  Known {
+    id: "containerd",
+    name: "Standalone containerd",
+    packages: &["containerd", "containerd.io"],
+    snaps: &[],
+    units: &["containerd.service"],
+    files: &[],
+    uninstall: "",
+  },
+  Known {
+    id: "k3s",
+    name: "k3s",
+    packages: &[],
+    snaps: &[],
+    units: &["k3s.service", "k3s-agent.service"],
+    files: &["/usr/local/bin/k3s"],
+    uninstall: "for s in /usr/local/bin/k3s-uninstall.sh /usr/local/bin/k3s-agent-uninstall.sh; do\n\
+                  if [ -x \"$s\" ]; then sudo -n \"$s\"; fi\n\
+                done\n",
+  },
+  Known {
+    id: "rke2",
+    name: "RKE2",
+    packages: &[],
+    snaps: &[],
+    units: &["rke2-server.service", "rke2-agent.service"],
+    files: &["/usr/local/bin/rke2", "/usr/bin/rke2"],
+    uninstall: "for s in /usr/local/bin/rke2-uninstall.sh /usr/bin/rke2-uninstall.sh; do\n\
+                  if [ -x \"$s\" ]; then sudo -n \"$s\"; break; fi\n\
+                done\n",
+  },
+  Known {
+    id: "microk8s",
+    name: "MicroK8s",
+    packages: &[],
+    snaps: &["microk8s"],
+    units: &[
+      "snap.microk8s.daemon-kubelite.service",
+      "snap.microk8s.daemon-containerd.service",
+      "snap.microk8s.daemon-k8s-dqlite.service",
+    ],
+    files: &[],
+    uninstall: "",
+  },
+  Known {
+    id: "kubeadm",
+    name: "kubeadm / kubelet",
+    packages: &["kubelet", "kubeadm"],
+    snaps: &["kubelet", "kubeadm"],
+    units: &["kubelet.service", "snap.kubelet.daemon.service"],
+    files: &[],
+    uninstall: "if command -v kubeadm >/dev/null 2>&1; then sudo -n kubeadm reset --force; fi\n",
+  },
+  Known {
+    id: "minikube",
+    name: "minikube",
+    packages: &["minikube"],
+    snaps: &[],
+    units: &[],
+    files: &["/usr/local/bin/minikube"],
+    uninstall: "if command -v minikube >/dev/null 2>&1; then minikube delete --all --purge || true; fi\n\
+                sudo -n rm -f /usr/local/bin/minikube\n",
+  },
+  Known {
+    id: "lxd-deb",
+    name: "LXD from apt",
+    packages: &["lxd", "lxd-client"],
+    snaps: &[],
+    units: &["lxd.service", "lxd.socket"],
+    files: &[],
+    uninstall: "",
+  },
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Resolution {
+  /// Leave it alone and carry on
+  Ignore,
+  /// Stop and disable its services; a rollback starts them again
+  Stop,
+  /// Uninstall it; this can't be rolled back
+  Remove,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Conflict {
+  /// A known product's id, or `port-<n>` for an unknown listener
+  pub id: String,
+  pub name: String,
+  /// What was found, one line each
+  pub evidence: Vec<String>,
+  pub packages: Vec<String>,
+  pub snaps: Vec<String>,
+  /// Services that are running or start at boot
+  pub units: Vec<String>,
+  /// Enabled at boot, a subset of `units`
+  pub enabled_units: Vec<String>,
+  /// Cluster ports it listens on
+  pub ports: Vec<u16>,
+  pub options: Vec<Resolution>,
+  #[serde(skip)]
+  uninstall: &'static str,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeConflicts {
+  pub host: String,
+  pub conflicts: Vec<Conflict>,
+  pub error: Option<String>,
+}
+
+struct Listener {
+  port: u16,
+  process: String,
+  unit: Option<String>,
+}
+
+fn cluster_ports() -> Vec<(u16, &'static str)> {
+  let mut ports: Vec<(u16, &'static str)> = INGRESS_PORTS.iter().map(|p| (*p, "ingress")).collect();
+  ports.extend(
+    REQUIRED_PORTS
+      .iter()
+      .filter(|p| p.protocol == Protocol::Tcp && p.port != 22)
+      .map(|p| (p.port, p.purpose)),
+  );
+  ports
+}
+
+fn scan_script() -> String {
+  let packages: Vec<&str> = KNOWN.iter().flat_map(|k| k.packages).copied().collect();
+  let units: Vec<&str> = KNOWN.iter().flat_map(|k| k.units).copied().collect();
+  let files: Vec<&str> = KNOWN.iter().flat_map(|k| k.files).copied().collect();
+  format!(
+    r#"
+export LC_ALL=C
+echo '@@packages'
+dpkg-query -W -f='${{Package}} ${{db:Status-Status}}\n' {packages} 2>/dev/null | awk '$2 == "installed" {{ print $1 }}'
+echo '@@snaps'
+snap list 2>/dev/null | awk 'NR > 1 {{ print $1 }}'
+echo '@@units'
+for u in {units}; do
+  echo "$u $(systemctl is-active "$u" 2>/dev/null) $(systemctl is-enabled "$u" 2>/dev/null)"
+done
+echo '@@files'
+for f in {files}; do [ -e "$f" ] && echo "$f"; done
+echo '@@listeners'
+sudo -n ss -Hltnp 2>/dev/null
+echo '@@pids'
+pids=$(sudo -n ss -Hltnp 2>/dev/null | grep -o 'pid=[0-9]*' | cut -d= -f2 | sort -u | paste -sd, -)
+[ -n "$pids" ] && ps -o pid=,unit= -p "$pids"
+echo '@@end'
+"#,
+    packages = packages.join(" "),
+    units = units.join(" "),
+    files = files.join(" "),
+  )
+}
+
+/// `ss -Hltnp` lines: state, queues, local address, peer, then
+/// `users:(("name",pid=123,fd=4),...)`.
+fn parse_listeners(lines: &[String], units: &HashMap<String, String>) -> Vec<Listener> {
+  lines
+    .iter()
+    .filter_map(|line| {
+      let fields: Vec<&str> = line.split_whitespace().collect();
+      let port = fields.get(3)?.rsplit_once(':')?.1.parse::<u16>().ok()?;
+      let users = fields.get(5).copied().unwrap_or("");
+      let process = users.split('"').nth(1).unwrap_or("unknown process").to_string();
+      let unit = users
+        .split("pid=")
+        .nth(1)
+        .and_then(|p| p.split(|c: char| !c.is_ascii_digit()).next())
+        .and_then(|pid| units.get(pid))
+        .cloned();
+      Some(Listener { port, process, unit })
+    })
+    .collect()
+}
+
+fn parse(output: &str) -> Vec<Conflict> {
+  let sections = sections(output);
+  let empty = Vec::new();
+  let section = |name: &str| sections.get(name).unwrap_or(&empty);
+  let lines = |name: &str| -> Vec<String> {
+    section(name)
+      .iter()
+      .map(|l| l.trim().to_string())
+      .filter(|l| !l.is_empty())
+      .collect()
+  };
+
+  let packages = lines("packages");
+  let snaps = lines("snaps");
+  let files = lines("files");
+  // unit -> (active, enabled)
+  let unit_states: HashMap<String, (bool, bool)> = lines("units")
+    .iter()
+    .filter_map(|l| {
+      let mut fields = l.split_whitespace();
+      let unit = fields.next()?.to_string();
+      let active = fields.next() == Some("active");
+      let enabled = matches!(fields.next(), Some("enabled") | Some("static") | Some("indirect"));
+      Some((unit, (active, enabled)))
+    })
+    .collect();
+  let pid_units: HashMap<String, String> = lines("pids")
+    .iter()
+    .filter_map(|l| {
+      let (pid, unit) = l.split_once(char::is_whitespace)?;
+      Some((pid.to_string(), unit.trim().to_string()))
+    })
+    .collect();
+  let ports = cluster_ports();
+  let listeners: Vec<Listener> = parse_listeners(&lines("listeners"), &pid_units)
+    .into_iter()
+    .filter(|l| ports.iter().any(|(p, _)| *p == l.port))
+    .filter(|l| {
+      !l.unit
+        .as_deref()
+        .is_some_and(|u| OWN_UNIT_PREFIXES.iter().any(|p| u.starts_with(p)))
+    })
+    .collect();
+  let purpose = |port: u16| ports.iter().find(|(p, _)| *p == port).map(|(_, w)| *w).unwrap_or("");
+
+  let mut claimed = vec![false; listeners.len()];
+  let mut conflicts = Vec::new();
+  for known in KNOWN {
+    let mut conflict = Conflict {
+      id: known.id.to_string(),
+      name: known.name.to_string(),
+      evidence: Vec::new(),
+      packages: known
+        .packages
+        .iter()
+        .filter(|p| packages.iter().any(|i| i == *p))
+        .map(|p| p.to_string())
+        .collect(),
+      snaps: known
+        .snaps
+        .iter()
+        .filter(|s| snaps.iter().any(|i| i == *s))
+        .map(|s| s.to_string())
+        .collect(),
+      units: Vec::new(),
+      enabled_units: Vec::new(),
+      ports: Vec::new(),
+      options: Vec::new(),
+      uninstall: known.uninstall,
+    };
+    for package in &conflict.packages {
+      conflict.evidence.push(format!("Package {} is installed", package));
+    }
+    for snap in &conflict.snaps {
+      conflict.evidence.push(format!("Snap {} is installed", snap));
+    }
+    for unit in known.units {
+      let Some(&(active, enabled)) = unit_states.get(*unit) else {
+        continue;
+      };
+      if active || enabled {
+        conflict.units.push(unit.to_string());
+        conflict.evidence.push(format!(
+          "Service {} is {}",
+          unit,
+          match (active, enabled) {
+            (true, true) => "running and enabled",
+            (true, false) => "running",
+            _ => "enabled at boot",
+          }
+        ));
+      }
+      if enabled {
+        conflict.enabled_units.push(unit.to_string());
+      }
+    }
+    let found_files: Vec<&String> = files.iter().filter(|f| known.files.contains(&f.as_str())).collect();
+    for file in &found_files {
+      conflict.evidence.push(format!("{} exists", file));
+    }
+    for (listener, claimed) in listeners.iter().zip(claimed.iter_mut()) {
+      if listener.unit.as_deref().is_some_and(|u| known.units.contains(&u)) {
+        *claimed = true;
+        conflict.ports.push(listener.port);
+        conflict.evidence.push(format!(
+          "{} listens on port {} ({})",
+          listener.process,
+          listener.port,
+          purpose(listener.port)
+        ));
+      }
+    }
+    if conflict.evidence.is_empty() {
+      continue;
+    }
+    conflict.options.push(Resolution::Ignore);
+    if !conflict.units.is_empty() {
+      conflict.options.push(Resolution::Stop);
+    }
+    if !conflict.packages.is_empty()
+      || !conflict.snaps.is_empty()
+      || (!found_files.is_empty() && !known.uninstall.is_empty())
+    {
+      conflict.options.push(Resolution::Remove);
+    }
+    conflicts.push(conflict);
+  }
+
+  // Whatever else holds a cluster port
+  let mut others: Vec<Conflict> = Vec::new();
+  for (listener, _) in listeners.iter().zip(&claimed).filter(|(_, claimed)| !**claimed) {
+    let id = format!("port-{}", listener.port);
+    if others.iter().any(|c| c.id == id) {
+      continue;
+    }
+    let unit = listener.unit.clone().filter(|u| validation::is_valid_unit(u));
+    let enabled = unit
+      .as_ref()
+      .is_some_and(|u| unit_states.get(u).is_some_and(|(_, enabled)| *enabled));
+    others.push(Conflict {
+      id,
+      name: format!("Port {} in use", listener.port),
+      evidence: vec![format!(
+        "{}{} listens on port {} ({})",
+        listener.process,
+        unit.as_ref().map(|u| format!(" ({})", u)).unwrap_or_default(),
+        listener.port,
+        purpose(listener.port)
+      )],
+      packages: Vec::new(),
+      snaps: Vec::new(),
+      enabled_units: unit.iter().filter(|_| enabled).cloned().collect(),
+      options: if unit.is_some() {
+        vec![Resolution::Ignore, Resolution::Stop]
+      } else {
+        vec![Resolution::Ignore]
+      },
+      units: unit.into_iter().collect(),
+      ports: vec![listener.port],
+      uninstall: "",
+    });
+  }
+  conflicts.extend(others);
+  conflicts
+}
+
+fn scan(workspace: &Workspace, pool: &SshPool, target: &SshTarget) -> Result<Vec<Conflict>, String> {
+  let output = ssh::run_script(workspace, pool, target, &scan_script(), SCAN_TIMEOUT)?;
+  if !output.stdout.contains("@@end") {
+    return Err(format!("Scan did not complete: {}", output.stderr.trim()));
+  }
+  Ok(parse(&output.stdout))
+}
+
+fn remove_script(conflict: &Conflict) -> String {
+  let mut script = String::from("set -e\n");
+  if !conflict.units.is_empty() {
+    script.push_str(&format!(
+      "sudo -n systemctl disable --now {}\n",
+      conflict.units.join(" ")
+    ));
+  }
+  script.push_str(conflict.uninstall);
+  if !conflict.packages.is_empty() {
+    script.push_str(&format!(
+      "sudo -n env DEBIAN_FRONTEND=noninteractive apt-get purge -y {}\n",
+      conflict.packages.join(" ")
+    ));
+  }
+  for snap in &conflict.snaps {
+    script.push_str(&format!("sudo -n snap remove --purge {}\n", snap));
+  }
+  script
+}
+
+/// Script starting services again after `resolve_conflict` stopped them.
+/// The names must be validated unit names.
+pub fn restart_script(units: &[String], enabled: &[String]) -> String {
+  let mut script = String::from("set -e\n");
+  if !enabled.is_empty() {
+    script.push_str(&format!("sudo -n systemctl enable {}\n", enabled.join(" ")));
+  }
+  if !units.is_empty() {
+    script.push_str(&format!("sudo -n systemctl start {}\n", units.join(" ")));
+  }
+  script
+}
+
+/// Look for conflicting runtimes, clusters and port listeners on each node.
+#[tauri::command]
+pub async fn check_conflicts(app: AppHandle, hosts: Vec<SshTarget>) -> Result<Vec<NodeConflicts>, String> {
+  for target in &hosts {
+    target.validate()?;
+  }
+  tauri::async_runtime::spawn_blocking(move || {
+    let workspace = app.state::<Workspace>();
+    let workspace: &Workspace = &workspace;
+    let pool = app.state::<SshPool>();
+    let pool: &SshPool = &pool;
+
+    std::thread::scope(|scope| {
+      let handles: Vec<_> = hosts
+        .iter()
+        .map(|target| scope.spawn(move || scan(workspace, pool, target)))
+        .collect();
+      handles
+        .into_iter()
+        .zip(&hosts)
+        .map(|(handle, target)| {
+          let result = handle.join().unwrap_or_else(|_| Err("Scan panicked".to_string()));
+          NodeConflicts {
+            host: target.host.clone(),
+            error: result.as_ref().err().cloned(),
+            conflicts: result.unwrap_or_default(),
+          }
+        })
+        .collect()
+    })
+  })
+  .await
+  .map_err(|e| e.to_string())
+}
+
+/// Resolve one conflict `check_conflicts` reported. The node is scanned
+/// again so only what is still there is stopped or removed.
+#[tauri::command]
+pub async fn resolve_conflict(
+  app: AppHandle,
+  host: SshTarget,
+  id: String,
+  resolution: Resolution,
+) -> Result<(), String> {
+  host.validate()?;
+  if resolution == Resolution::Ignore {
+    info!("Ignoring conflict {} on {}", id, host.host);
+    return Ok(());
+  }
+  let dry_run = dry_run::is_enabled(&app);
+  tauri::async_runtime::spawn_blocking(move || {
+    let workspace = app.state::<Workspace>();
+    let pool = app.state::<SshPool>();
+    let conflict = scan(&workspace, &pool, &host)?
+      .into_iter()
+      .find(|c| c.id == id)
+      .ok_or_else(|| format!("{} is no longer present on {}", id, host.host))?;
+    if !conflict.options.contains(&resolution) {
+      return Err(format!(
+        "{} on {} cannot be resolved that way",
+        conflict.name, host.host
+      ));
+    }
+
+    let script = match resolution {
+      Resolution::Stop => format!("set -e\nsudo -n systemctl disable --now {}\n", conflict.units.join(" ")),
+      _ => remove_script(&conflict),
+    };
+    let verb = if resolution == Resolution::Stop {
+      "stop"
+    } else {
+      "remove"
+    };
+    if dry_run {
+      info!("Dry run: would {} {} on {}", verb, conflict.name, host.host);
+      return Ok(());
+    }
+
+    let output = ssh::run_script(&workspace, &pool, &host, &script, RESOLVE_TIMEOUT)?;
+    if output.status != Some(0) {
+      let detail = output
+        .stderr
+        .trim()
+        .lines()
+        .last()
+        .unwrap_or("command failed")
+        .to_string();
+      return Err(format!(
+        "Failed to {} {} on {}: {}",
+        verb, conflict.name, host.host, detail
+      ));
+    }
+    info!("Resolved {} on {}: {}", conflict.name, host.host, verb);
+    if resolution == Resolution::Stop {
+      journal::record(
+        &app,
+        Action::ServicesStopped {
+          target: host,
+          units: conflict.units,
+          enabled: conflict.enabled_units,
+        },
+      );
+    }
+    Ok(())
+  })
+  .await
+  .map_err(|e| e.to_string())?
+}
@@ -0,0 +1,370 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Installing the cluster key on fresh nodes, like `ssh-copy-id`.
+//!
+//! Everything else here logs in with keys only, so a freshly installed
+//! node needs the installer's public key in `authorized_keys` first.
+//! `distribute_ssh_key` takes each node's password once and, on all nodes
+//! at the same time, logs in with it and appends the key unless it's
+//! already there, creating `~/.ssh` with the permissions sshd insists on.
+//! The key is `~/.ssh/thinkube_cluster_key`, generated when missing with
+//! the comment the SSH setup playbook and the uninstaller know it by,
+//! unless another one is given. Afterwards each node is logged into again
+//! with the key alone, so a node whose sshd ignores `authorized_keys`
+//! shows up here rather than halfway through the install.
+//!
+//! `ssh` reads the password from an askpass helper in the run workspace,
+//! which prints it from the environment, so it is never on a command line
+//! or in a file. Host keys are checked against the confirmed ones, as for
+//! every other connection. Nodes behind a jump host aren't supported: the
+//! bastion would need its own password.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tracing::info;
+
+use super::ssh::{self, shell_quote, SshPool, SshTarget};
+use crate::platform::find_program;
+use crate::preflight::Outcome;
+use crate::workspace::{create_private_dir, write_private_file, Workspace};
+use crate::{dry_run, redact};
+
+const KEY_NAME: &str = "thinkube_cluster_key";
+/// The comment the SSH setup playbook gives the cluster key
+const KEY_COMMENT: &str = "thinkube_cluster";
+const CONNECT_TIMEOUT_SECS: u32 = 10;
+const VERIFY_TIMEOUT: Duration = Duration::from_secs(30);
+const PASSWORD_VAR: &str = "THINKUBE_SSH_PASSWORD";
+
+const ASKPASS: &str = "#!/bin/sh\nprintf '%s\\n' \"$THINKUBE_SSH_PASSWORD\"\n";
+
+/// Run as the node user; `{key}` is shell quoted.
+const INSTALL_SCRIPT: &str = r#"
+umask 077
+mkdir -p ~/.ssh && chmod 700 ~/.ssh || exit 3
+touch ~/.ssh/authorized_keys && chmod 600 ~/.ssh/authorized_keys || exit 3
+if grep -qxF {key} ~/.ssh/authorized_keys; then
+  echo present
+else
+  # authorized_keys may not end with a newline
+  [ -s ~/.ssh/authorized_keys ] && [ "$(tail -c 1 ~/.ssh/authorized_keys)" != "" ] && echo >> ~/.ssh/authorized_keys
+  printf '%s\n' {key} >> ~/.ssh/authorized_keys || exit 3
+  echo added
+fi
+command -v restorecon > /dev/null 2>&1 && restorecon -R ~/.ssh > /dev/null 2>&1
+exit 0
+"#;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PasswordNode {
+  #[serde(flatten)]
+  pub target: SshTarget,
+  pub password: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeKeyInstall {
+  pub host: String,
+  /// The key was already in `authorized_keys`
+  pub already_present: bool,
+  /// Logging in with the key alone worked afterwards
+  pub verified: bool,
+  pub outcome: Outcome,
+  pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct KeyDistribution {
+  /// The private key the nodes now accept
+  pub identity_file: String,
+  pub public_key: String,
+  pub nodes: Vec<NodeKeyInstall>,
+  /// Every node accepts the key
+  pub ok: bool,
+}
+
+fn default_key() -> Result<PathBuf, String> {
+  let home = std::env::var_os("HOME").ok_or("HOME is not set")?;
+  Ok(PathBuf::from(home).join(".ssh").join(KEY_NAME))
+}
+
+/// The public half of `identity`, generating the pair if it doesn't exist.
+fn public_key(identity: &Path) -> Result<String, String> {
+  if !identity.exists() {
+    let keygen = find_program("ssh-keygen", &[]).ok_or("ssh-keygen is not installed")?;
+    if let Some(dir) = identity.parent() {
+      create_private_dir(dir)?;
+    }
+    let output = Command::new(keygen)
+      .args(["-q", "-t", "ed25519", "-N", "", "-C", KEY_COMMENT, "-f"])
+      .arg(identity)
+      .stdin(Stdio::null())
+      .output()
+      .map_err(|e| format!("Failed to run ssh-keygen: {}", e))?;
+    if !output.status.success() {
+      return Err(format!(
+        "Failed to generate {}: {}",
+        identity.display(),
+        String::from_utf8_lossy(&output.stderr).trim()
+      ));
+    }
+    info!("Generated the cluster key {}", identity.display());
+  }
+  read_public_key(identity)
+}
+
+/// The public half of an existing `identity`.
+fn read_public_key(identity: &Path) -> Result<String, String> {
+  let public = PathBuf::from(format!("{}.pub", identity.display()));
+  let key = std::fs::read_to_string(&public)
+    .map_err(|e| format!("Failed to read {}: {}", public.display(), e))?
+    .trim()
+    .to_string();
+  if key.lines().count() != 1 || key.split_whitespace().count() < 2 {
+    return Err(format!("{} is not an SSH public key", public.display()));
+  }
+  Ok(key)
+}
+
+fn askpass(workspace: &Workspace) -> Result<PathBuf, String> {
+  let path = workspace.resolve("ssh/askpass.sh")?;
+  write_private_file(&path, ASKPASS.as_bytes())?;
+  #[cfg(unix)]
+  {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o700))
+      .map_err(|e| format!("Failed to set permissions on {}: {}", path.display(), e))?;
+  }
+  Ok(path)
+}
+
+/// What a run would do, without generating the key or touching the nodes.
+fn dry_run_plan(identity: &Path, nodes: &[PasswordNode]) -> Result<KeyDistribution, String> {
+  let (key, action) = if identity.exists() {
+    (read_public_key(identity)?, "add the key to ~/.ssh/authorized_keys".to_string())
+  } else {
+    (
+      String::new(),
+      format!("generate {} and add it to ~/.ssh/authorized_keys", identity.display()),
+    )
+  };
+  let nodes = nodes
+    .iter()
+    .map(|node| {
+      info!("Dry run: not authorizing the cluster key on {}", node.target.host);
+      NodeKeyInstall {
+        host: node.target.host.clone(),
+        already_present: false,
+        verified: false,
+        outcome: Outcome::Skipped,
+        message: format!("Dry run: would {}", action),
+      }
+    })
+    .collect();
+  Ok(KeyDistribution {
+    identity_file: identity.display().to_string(),
+    public_key: key,
+    ok: false,
+    nodes,
+  })
+}
+
+/// Log in with `password` and add `key` to `authorized_keys`; true if it
+/// was there already.
+fn install(workspace: &Workspace, askpass: &Path, node: &PasswordNode, key: &str) -> Result<bool, String> {
+  let target = &node.target;
+  let ssh = find_program("ssh", &[]).ok_or("ssh is not installed")?;
+  let known_hosts = workspace.resolve("ssh/known_hosts")?;
+  let script = INSTALL_SCRIPT.replace("{key}", &shell_quote(key));
+  let output = Command::new(ssh)
+    .args(["-p", &target.port.unwrap_or(22).to_string()])
+    .args(["-o", "BatchMode=no"])
+    .args(["-o", "PubkeyAuthentication=no"])
+    .args(["-o", "PreferredAuthentications=password,keyboard-interactive"])
+    .args(["-o", "NumberOfPasswordPrompts=1"])
+    .args(["-o", &format!("ConnectTimeout={}", CONNECT_TIMEOUT_SECS)])
+    .args(["-o", "ControlPath=none"])
+    .args(["-o", "StrictHostKeyChecking=yes"])
+    .arg("-o")
+    .arg(format!("UserKnownHostsFile={}", known_hosts.display()))
+    .arg(format!("{}@{}", target.user, target.host))
+    .arg(format!("sh -c {}", shell_quote(&script)))
+    .env("SSH_ASKPASS", askpass)
+    .env("SSH_ASKPASS_REQUIRE", "force")
+    // Older OpenSSH only uses the askpass helper with a display set
+    .env("DISPLAY", std::env::var_os("DISPLAY").unwrap_or_else(|| ":0".into()))
+    .env(PASSWORD_VAR, &node.password)
+    .stdin(Stdio::null())
+    .output()
+    .map_err(|e| format!("Failed to run ssh: {}", e))?;
+
+  let stdout = String::from_utf8_lossy(&output.stdout);
+  let stderr = String::from_utf8_lossy(&output.stderr);
+  if stderr.contains("REMOTE HOST IDENTIFICATION HAS CHANGED") {
+    return Err(format!(
+      "The host key of {} has changed since it was trusted; refusing to connect",
+      target.host
+    ));
+  }
+  if stderr.contains("Host key verification failed") {
+    return Err(format!(
+      "The host key of {} has not been confirmed yet; check its fingerprint before connecting",
+      target.host
+    ));
+  }
+  if stderr.contains("Permission denied") {
+    return Err(format!(
+      "The password for {}@{} was not accepted",
+      target.user, target.host
+    ));
+  }
+  match stdout.lines().map(str::trim).find(|l| *l == "added" || *l == "present") {
+    Some(result) if output.status.success() => Ok(result == "present"),
+    _ => Err(format!(
+      "Failed to update authorized_keys on {}: {}",
+      target.host,
+      stderr.trim().lines().last().unwrap_or("command failed")
+    )),
+  }
+}
+
+fn distribute(
+  workspace: &Workspace,
+  pool: &SshPool,
+  askpass: &Path,
+  node: &PasswordNode,
+  identity: &Path,
+  key: &str,
+) -> NodeKeyInstall {
+  let host = node.target.host.clone();
+  let already_present = match install(workspace, askpass, node, key) {
+    Ok(present) => present,
+    Err(e) => {
+      return NodeKeyInstall {
+        host,
+        already_present: false,
+        verified: false,
+        outcome: Outcome::Fail,
+        message: e,
+      }
+    }
+  };
+
+  let target = SshTarget {
+    identity_file: Some(identity.display().to_string()),
+    agent_key: None,
+    ..node.target.clone()
+  };
+  // A master opened earlier with other credentials would prove nothing
+  pool.close(&target);
+  let verified = ssh::run_script(workspace, pool, &target, "echo ok", VERIFY_TIMEOUT).and_then(|output| {
+    if output.stdout.lines().any(|l| l.trim() == "ok") {
+      Ok(())
+    } else {
+      Err(output.stderr.trim().to_string())
+    }
+  });
+  let installed = if already_present {
+    "The key was already authorized"
+  } else {
+    "Added the key to ~/.ssh/authorized_keys"
+  };
+  let (outcome, message) = match verified {
+    Ok(()) => {
+      info!("{} accepts the cluster key", host);
+      (Outcome::Pass, format!("{}; key-based login works", installed))
+    }
+    Err(e) => (
+      Outcome::Fail,
+      format!("{}, but logging in with it failed: {}", installed, e),
+    ),
+  };
+  NodeKeyInstall {
+    host,
+    already_present,
+    verified: outcome == Outcome::Pass,
+    outcome,
+    message,
+  }
+}
+
+/// Authorize the cluster key, or `identity_file`, on each node using its
+/// password, then check each node accepts the key.
+#[tauri::command]
+pub async fn distribute_ssh_key(
+  app: AppHandle,
+  nodes: Vec<PasswordNode>,
+  identity_file: Option<String>,
+) -> Result<KeyDistribution, String> {
+  if nodes.is_empty() {
+    return Err("No nodes to set up".to_string());
+  }
+  for node in &nodes {
+    node.target.validate()?;
+    if node.target.jump_host.as_deref().is_some_and(|j| !j.is_empty()) {
+      return Err(format!(
+        "{} is behind a jump host; authorize the key there by hand",
+        node.target.host
+      ));
+    }
+    if node.password.is_empty() {
+      return Err(format!("No password given for {}", node.target.host));
+    }
+    redact::register(&node.password);
+  }
+  let dry_run = dry_run::is_enabled(&app);
+  tauri::async_runtime::spawn_blocking(move || {
+    let identity = match identity_file {
+      Some(path) => PathBuf::from(path),
+      None => default_key()?,
+    };
+    if dry_run {
+      return dry_run_plan(&identity, &nodes);
+    }
+    let key = public_key(&identity)?;
+    let workspace = app.state::<Workspace>();
+    let workspace: &Workspace = &workspace;
+    let pool = app.state::<SshPool>();
+    let pool: &SshPool = &pool;
+    let askpass = askpass(workspace)?;
+
+    let results: Vec<NodeKeyInstall> = std::thread::scope(|scope| {
+      let handles: Vec<_> = nodes
+        .iter()
+        .map(|node| {
+          let (identity, key, askpass) = (&identity, &key, &askpass);
+          scope.spawn(move || distribute(workspace, pool, askpass, node, identity, key))
+        })
+        .collect();
+      handles
+        .into_iter()
+        .zip(&nodes)
+        .map(|(handle, node)| {
+          handle.join().unwrap_or_else(|_| NodeKeyInstall {
+            host: node.target.host.clone(),
+            already_present: false,
+            verified: false,
+            outcome: Outcome::Fail,
+            message: "Key installation panicked".to_string(),
+          })
+        })
+        .collect()
+    });
+    let _ = std::fs::remove_file(&askpass);
+
+    Ok(KeyDistribution {
+      identity_file: identity.display().to_string(),
+      public_key: key,
+      ok: results.iter().all(|r| r.verified),
+      nodes: results,
+    })
+  })
+  .await
+  .map_err(|e| e.to_string())?
+}
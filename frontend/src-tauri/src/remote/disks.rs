@@ -0,0 +1,685 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Read-only preview of a node's disks and where the LXD storage pool
+//! would go.
+//!
+//! The script only lists things (`lsblk`, `vgs`, `zpool list`, `df`); it
+//! never creates, wipes or mounts anything, and `sudo` is used just so
+//! `vgs` can see the volume groups. The planned pool follows the order the
+//! playbooks prefer: a blank disk the user picked, then free space in an
+//! existing ZFS pool or volume group, then a loop file on the root
+//! filesystem sized the way `lxd init` sizes one.
+//!
+//! md arrays and multipath devices show up in `lsblk` below each of their
+//! member disks, so the member disks are marked with what they belong to
+//! and the arrays and multipath maps are listed on their own, with
+//! `/proc/mdstat` saying whether an array is degraded or rebuilding. Those
+//! are what a pool should go on, never one of their members. Disks behind
+//! a hardware RAID controller are only visible as the logical volume it
+//! exports; its own health has to be checked with the vendor's tools.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+use super::inventory::sections;
+use super::ssh::{self, SshPool, SshTarget};
+use crate::workspace::Workspace;
+
+const PREVIEW_TIMEOUT: Duration = Duration::from_secs(30);
+const GIB: u64 = 1024 * 1024 * 1024;
+/// Room left on `/` for images, logs and container layers
+const ROOT_RESERVE_GIB: u64 = 50;
+/// `lxd init` gives a loop pool 20% of free space, between 5 and 30 GiB
+const LOOP_MIN_GIB: u64 = 5;
+const LOOP_MAX_GIB: u64 = 30;
+const LOOP_DIR: &str = "/var/snap/lxd/common/lxd/disks";
+/// Model or vendor strings of logical volumes exported by RAID controllers
+const HARDWARE_RAID_MODELS: &[&str] = &["perc", "megaraid", "logical volume", "smart array", "serveraid", "raid"];
+
+const SCRIPT: &str = r#"
+export LC_ALL=C
+echo '@@lsblk'
+lsblk -J -b -o NAME,PATH,SIZE,TYPE,FSTYPE,MOUNTPOINT,LABEL,MODEL,VENDOR,ROTA 2>/dev/null
+echo '@@mdstat'
+cat /proc/mdstat 2>/dev/null
+echo '@@vgs'
+sudo -n vgs --reportformat json --units b --nosuffix -o vg_name,vg_size,vg_free 2>/dev/null
+echo '@@zpool'
+zpool list -Hp -o name,size,free,health 2>/dev/null
+echo '@@facts'
+echo "root_source=$(findmnt -n -o SOURCE / 2>/dev/null)"
+echo "var_free=$(df -P -B1 /var 2>/dev/null | awk 'NR == 2 { print $4 }')"
+echo "zfs=$([ -d /sys/module/zfs ] || modinfo -n zfs >/dev/null 2>&1 && echo yes || echo no)"
+echo '@@end'
+"#;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct StorageRequest {
+  /// Disk to dedicate to the pool, by name (`sdb`) or path (`/dev/sdb`)
+  pub disk: Option<String>,
+  /// Pool size; the whole disk or the `lxd init` default when omitted
+  pub pool_size_gib: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BlockDevice {
+  pub name: String,
+  pub path: String,
+  pub size_bytes: u64,
+  /// `disk`, `part`, `lvm`, `crypt`, ...
+  pub kind: String,
+  pub fstype: Option<String>,
+  pub mountpoint: Option<String>,
+  pub label: Option<String>,
+  pub model: Option<String>,
+  pub vendor: Option<String>,
+  pub rotational: bool,
+  /// Holds a filesystem, LVM or ZFS member, swap or a mount, itself or below
+  pub in_use: bool,
+  /// md array or multipath map the disk is a member or path of
+  pub member_of: Option<String>,
+  /// A logical volume exported by a RAID controller
+  pub hardware_raid: bool,
+  pub children: Vec<BlockDevice>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RaidArray {
+  pub name: String,
+  pub path: String,
+  /// `raid1`, `raid5`, `raid10`, ...
+  pub level: String,
+  pub size_bytes: u64,
+  /// Disks the members are on
+  pub disks: Vec<String>,
+  /// Members working out of those the array should have, from `/proc/mdstat`
+  pub working_members: Option<u32>,
+  pub total_members: Option<u32>,
+  /// Members marked failed
+  pub failed: Vec<String>,
+  /// `inactive` arrays aren't assembled
+  pub active: bool,
+  pub degraded: bool,
+  /// `recovery 12.6%` while rebuilding or resyncing
+  pub sync: Option<String>,
+  pub in_use: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MultipathDevice {
+  pub name: String,
+  pub path: String,
+  pub size_bytes: u64,
+  /// Disks that are paths to the same LUN
+  pub paths: Vec<String>,
+  pub in_use: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VolumeGroup {
+  pub name: String,
+  pub size_bytes: u64,
+  pub free_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ZfsPool {
+  pub name: String,
+  pub size_bytes: u64,
+  pub free_bytes: u64,
+  pub health: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PoolSource {
+  /// A whole, currently unused disk
+  Disk,
+  /// A whole md array or multipath device
+  RaidArray,
+  Multipath,
+  ZfsPool,
+  VolumeGroup,
+  /// A sparse file on the root filesystem
+  LoopFile,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlannedPool {
+  /// LXD storage driver: `zfs`, `lvm` or `btrfs`
+  pub driver: String,
+  pub source: PoolSource,
+  /// Disk path, pool or volume group name, or the loop file directory
+  pub location: String,
+  pub size_bytes: u64,
+  /// Space available at `location` before the pool is created
+  pub available_bytes: u64,
+  pub fits: bool,
+  /// Devices whose contents would be erased when the plan is applied
+  pub erases: Vec<String>,
+  pub notes: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DiskLayout {
+  pub host: String,
+  pub error: Option<String>,
+  pub disks: Vec<BlockDevice>,
+  pub volume_groups: Vec<VolumeGroup>,
+  pub zfs_pools: Vec<ZfsPool>,
+  pub raid_arrays: Vec<RaidArray>,
+  pub multipath: Vec<MultipathDevice>,
+  /// Degraded arrays, single-path maps and the like
+  pub warnings: Vec<String>,
+  /// Device mounted at `/`
+  pub root_source: Option<String>,
+  pub pool: Option<PlannedPool>,
+}
+
+impl BlockDevice {
+  /// No partitions, filesystem or signature: safe to hand to the pool.
+  pub fn is_blank(&self) -> bool {
+    !self.in_use && self.children.is_empty() && self.member_of.is_none()
+  }
+
+  fn descendants(&self) -> Box<dyn Iterator<Item = &BlockDevice> + '_> {
+    Box::new(
+      self
+        .children
+        .iter()
+        .flat_map(|c| std::iter::once(c).chain(c.descendants())),
+    )
+  }
+}
+
+fn is_raid(kind: &str) -> bool {
+  kind.starts_with("raid") || kind == "linear" || kind == "md"
+}
+
+fn text(v: &Value) -> Option<String> {
+  v.as_str().map(|s| s.trim().to_string()).filter(|s| !s.is_empty())
+}
+
+/// Older util-linux and LVM print numbers as strings.
+fn number(v: &Value) -> u64 {
+  v.as_u64()
+    .or_else(|| v.as_str().and_then(|s| s.trim().parse().ok()))
+    .unwrap_or(0)
+}
+
+fn block_device(v: &Value) -> Option<BlockDevice> {
+  let name = v["name"].as_str()?.to_string();
+  let children: Vec<BlockDevice> = v["children"]
+    .as_array()
+    .map(|c| c.iter().filter_map(block_device).collect())
+    .unwrap_or_default();
+  let fstype = text(&v["fstype"]);
+  let mountpoint = text(&v["mountpoint"]);
+  let in_use = fstype.is_some() || mountpoint.is_some() || children.iter().any(|c| c.in_use);
+  Some(BlockDevice {
+    path: text(&v["path"]).unwrap_or_else(|| format!("/dev/{}", name)),
+    name,
+    size_bytes: number(&v["size"]),
+    kind: text(&v["type"]).unwrap_or_default(),
+    fstype,
+    mountpoint,
+    label: text(&v["label"]),
+    model: text(&v["model"]),
+    vendor: text(&v["vendor"]),
+    rotational: v["rota"].as_bool().unwrap_or_else(|| number(&v["rota"]) == 1),
+    in_use,
+    member_of: None,
+    hardware_raid: false,
+    children,
+  })
+}
+
+pub fn parse_disks(lines: &[&str]) -> Vec<BlockDevice> {
+  let Ok(json) = serde_json::from_str::<Value>(&lines.join("\n")) else {
+    return Vec::new();
+  };
+  json["blockdevices"]
+    .as_array()
+    .map(|devices| {
+      devices
+        .iter()
+        .filter_map(block_device)
+        // Loop devices are snaps; zram is swap in memory
+        .filter(|d| d.kind == "disk" && !d.name.starts_with("zram"))
+        .map(|mut d| {
+          let member_of = d
+            .descendants()
+            .find(|c| is_raid(&c.kind) || c.kind == "mpath")
+            .map(|c| c.name.clone());
+          d.member_of = member_of;
+          let described = format!(
+            "{} {}",
+            d.model.as_deref().unwrap_or(""),
+            d.vendor.as_deref().unwrap_or("")
+          );
+          d.hardware_raid = HARDWARE_RAID_MODELS
+            .iter()
+            .any(|m| described.to_lowercase().contains(m));
+          d
+        })
+        .collect()
+    })
+    .unwrap_or_default()
+}
+
+/// md arrays and multipath maps from the disks they sit on, most
+/// arrays are below partitions rather than whole disks.
+fn topology(disks: &[BlockDevice], mdstat: &[&str]) -> (Vec<RaidArray>, Vec<MultipathDevice>) {
+  let mut arrays: Vec<RaidArray> = Vec::new();
+  let mut maps: Vec<MultipathDevice> = Vec::new();
+  for disk in disks {
+    for device in disk.descendants() {
+      if is_raid(&device.kind) {
+        match arrays.iter_mut().find(|a| a.name == device.name) {
+          Some(array) => array.disks.push(disk.name.clone()),
+          None => arrays.push(RaidArray {
+            name: device.name.clone(),
+            path: device.path.clone(),
+            level: device.kind.clone(),
+            size_bytes: device.size_bytes,
+            disks: vec![disk.name.clone()],
+            working_members: None,
+            total_members: None,
+            failed: Vec::new(),
+            active: true,
+            degraded: false,
+            sync: None,
+            in_use: device.in_use || !device.children.is_empty(),
+          }),
+        }
+      } else if device.kind == "mpath" {
+        match maps.iter_mut().find(|m| m.name == device.name) {
+          Some(map) => map.paths.push(disk.name.clone()),
+          None => maps.push(MultipathDevice {
+            name: device.name.clone(),
+            path: device.path.clone(),
+            size_bytes: device.size_bytes,
+            paths: vec![disk.name.clone()],
+            in_use: device.in_use || !device.children.is_empty(),
+          }),
+        }
+      }
+    }
+  }
+
+  // md0 : active raid1 sdb1[1] sda1[0](F)
+  //       976630464 blocks super 1.2 [2/1] [U_]
+  //       [==>..................]  recovery = 12.6% (...)
+  let mut current: Option<usize> = None;
+  for line in mdstat {
+    if let Some((name, rest)) = line.split_once(" : ") {
+      let name = name.trim();
+      current = arrays.iter().position(|a| a.name == name);
+      if current.is_none() && name.starts_with("md") {
+        // Inactive arrays have no block device for lsblk to show
+        arrays.push(RaidArray {
+          name: name.to_string(),
+          path: format!("/dev/{}", name),
+          level: rest
+            .split_whitespace()
+            .nth(1)
+            .filter(|_| rest.starts_with("active"))
+            .unwrap_or("unknown")
+            .to_string(),
+          size_bytes: 0,
+          disks: Vec::new(),
+          working_members: None,
+          total_members: None,
+          failed: Vec::new(),
+          active: true,
+          degraded: false,
+          sync: None,
+          in_use: false,
+        });
+        current = Some(arrays.len() - 1);
+      }
+      if let Some(array) = current.map(|i| &mut arrays[i]) {
+        array.active = !rest.starts_with("inactive");
+        array.failed = rest
+          .split_whitespace()
+          .filter(|m| m.ends_with("(F)"))
+          .map(|m| m.split('[').next().unwrap_or(m).to_string())
+          .collect();
+      }
+      continue;
+    }
+    let Some(array) = current.map(|i| &mut arrays[i]) else {
+      continue;
+    };
+    if line.trim().is_empty() {
+      current = None;
+    } else if let Some(counts) = line
+      .split_whitespace()
+      .find_map(|w| w.strip_prefix('[')?.strip_suffix(']')?.split_once('/'))
+    {
+      array.total_members = counts.0.parse().ok();
+      array.working_members = counts.1.parse().ok();
+    } else if let Some(at) = line.find(" = ") {
+      let action = line[..at].split_whitespace().last().unwrap_or("");
+      let progress = line[at + 3..].split_whitespace().next().unwrap_or("");
+      if matches!(action, "recovery" | "resync" | "reshape" | "check") {
+        array.sync = Some(format!("{} {}", action, progress));
+      }
+    }
+  }
+  for array in &mut arrays {
+    array.degraded = !array.active
+      || !array.failed.is_empty()
+      || matches!((array.working_members, array.total_members), (Some(w), Some(t)) if w < t);
+  }
+  (arrays, maps)
+}
+
+fn topology_warnings(layout: &DiskLayout) -> Vec<String> {
+  let mut warnings = Vec::new();
+  for array in &layout.raid_arrays {
+    if !array.active {
+      warnings.push(format!("{} is not assembled", array.name));
+    } else if array.degraded {
+      let mut warning = match (array.working_members, array.total_members) {
+        (Some(w), Some(t)) => format!("{} is degraded: {} of {} members working", array.name, w, t),
+        _ => format!("{} is degraded", array.name),
+      };
+      if !array.failed.is_empty() {
+        warning.push_str(&format!(", {} failed", array.failed.join(", ")));
+      }
+      if let Some(sync) = &array.sync {
+        warning.push_str(&format!(" ({})", sync));
+      }
+      warnings.push(warning);
+    }
+  }
+  for map in layout.multipath.iter().filter(|m| m.paths.len() < 2) {
+    warnings.push(format!("{} has only one path left", map.name));
+  }
+  for disk in layout.disks.iter().filter(|d| d.hardware_raid) {
+    warnings.push(format!(
+      "{} is a RAID controller volume; check its health with the controller's tools",
+      disk.name
+    ));
+  }
+  warnings
+}
+
+/// A pool on a whole md array or multipath device, by name.
+fn plan_on_virtual_device(layout: &DiskLayout, name: &str, zfs: bool) -> Option<PlannedPool> {
+  let driver = if zfs { "zfs" } else { "lvm" }.to_string();
+  if let Some(array) = layout.raid_arrays.iter().find(|a| a.name == name) {
+    let mut notes = Vec::new();
+    if array.in_use {
+      notes.push(format!("{} has partitions, filesystems or mounts", array.path));
+    }
+    if array.degraded {
+      notes.push(format!(
+        "{} is degraded; it has no redundancy until it is rebuilt",
+        array.path
+      ));
+    }
+    let fits = !array.in_use && array.active;
+    return Some(PlannedPool {
+      driver,
+      source: PoolSource::RaidArray,
+      location: array.path.clone(),
+      size_bytes: array.size_bytes,
+      available_bytes: array.size_bytes,
+      fits,
+      erases: if fits { vec![array.path.clone()] } else { Vec::new() },
+      notes,
+    });
+  }
+  let map = layout.multipath.iter().find(|m| m.name == name)?;
+  Some(PlannedPool {
+    driver,
+    source: PoolSource::Multipath,
+    location: map.path.clone(),
+    size_bytes: map.size_bytes,
+    available_bytes: map.size_bytes,
+    fits: !map.in_use,
+    erases: if map.in_use { Vec::new() } else { vec![map.path.clone()] },
+    notes: if map.in_use {
+      vec![format!("{} has partitions, filesystems or mounts", map.path)]
+    } else {
+      Vec::new()
+    },
+  })
+}
+
+pub fn parse_vgs(lines: &[&str]) -> Vec<VolumeGroup> {
+  let Ok(json) = serde_json::from_str::<Value>(&lines.join("\n")) else {
+    return Vec::new();
+  };
+  json["report"]
+    .as_array()
+    .into_iter()
+    .flatten()
+    .flat_map(|r| r["vg"].as_array().cloned().unwrap_or_default())
+    .filter_map(|vg| {
+      Some(VolumeGroup {
+        name: text(&vg["vg_name"])?,
+        size_bytes: number(&vg["vg_size"]),
+        free_bytes: number(&vg["vg_free"]),
+      })
+    })
+    .collect()
+}
+
+pub fn parse_zpools(lines: &[&str]) -> Vec<ZfsPool> {
+  lines
+    .iter()
+    .filter_map(|line| {
+      let fields: Vec<&str> = line.split('\t').collect();
+      if fields.len() < 4 {
+        return None;
+      }
+      Some(ZfsPool {
+        name: fields[0].to_string(),
+        size_bytes: fields[1].parse().unwrap_or(0),
+        free_bytes: fields[2].parse().unwrap_or(0),
+        health: fields[3].trim().to_string(),
+      })
+    })
+    .collect()
+}
+
+fn plan_pool(layout: &DiskLayout, request: &StorageRequest, var_free: u64, zfs: bool) -> Result<PlannedPool, String> {
+  let requested = request.pool_size_gib.map(|g| g * GIB);
+  let loop_driver = if zfs { "zfs" } else { "btrfs" };
+
+  if let Some(wanted) = &request.disk {
+    let name = wanted.trim().trim_start_matches("/dev/").trim_start_matches("mapper/");
+    if let Some(planned) = plan_on_virtual_device(layout, name, zfs) {
+      return Ok(planned);
+    }
+    let disk = layout
+      .disks
+      .iter()
+      .find(|d| d.name == name)
+      .ok_or_else(|| format!("{} has no disk named {}", layout.host, wanted))?;
+    let mut notes = Vec::new();
+    if let Some(parent) = &disk.member_of {
+      notes.push(format!(
+        "{} is part of {}; use {} itself for the pool",
+        disk.path, parent, parent
+      ));
+    } else if !disk.is_blank() {
+      notes.push(format!(
+        "{} has partitions, filesystems or mounts; the installer only uses blank disks",
+        disk.path
+      ));
+    }
+    if requested.is_some_and(|size| size < disk.size_bytes) {
+      notes.push("The pool takes the whole disk; the requested size is ignored".to_string());
+    }
+    return Ok(PlannedPool {
+      driver: if zfs { "zfs" } else { "lvm" }.to_string(),
+      source: PoolSource::Disk,
+      location: disk.path.clone(),
+      size_bytes: disk.size_bytes,
+      available_bytes: disk.size_bytes,
+      fits: disk.is_blank(),
+      erases: if disk.is_blank() { vec![disk.path.clone()] } else { Vec::new() },
+      notes,
+    });
+  }
+
+  let loop_default = (var_free / 5).clamp(LOOP_MIN_GIB * GIB, LOOP_MAX_GIB * GIB);
+  let size = requested.unwrap_or(loop_default);
+
+  if let Some(pool) = layout
+    .zfs_pools
+    .iter()
+    .filter(|p| p.health == "ONLINE" && p.free_bytes >= size)
+    .max_by_key(|p| p.free_bytes)
+  {
+    return Ok(PlannedPool {
+      driver: "zfs".to_string(),
+      source: PoolSource::ZfsPool,
+      location: pool.name.clone(),
+      size_bytes: size,
+      available_bytes: pool.free_bytes,
+      fits: true,
+      erases: Vec::new(),
+      notes: vec![format!("A dataset in the existing {} pool", pool.name)],
+    });
+  }
+
+  if let Some(vg) = layout
+    .volume_groups
+    .iter()
+    .filter(|vg| vg.free_bytes >= size)
+    .max_by_key(|vg| vg.free_bytes)
+  {
+    return Ok(PlannedPool {
+      driver: "lvm".to_string(),
+      source: PoolSource::VolumeGroup,
+      location: vg.name.clone(),
+      size_bytes: size,
+      available_bytes: vg.free_bytes,
+      fits: true,
+      erases: Vec::new(),
+      notes: vec![format!("A thin pool in the free space of volume group {}", vg.name)],
+    });
+  }
+
+  let reserve = ROOT_RESERVE_GIB * GIB;
+  let fits = var_free >= size + reserve;
+  let mut notes = vec!["A loop file performs worse than a dedicated disk; fine for small clusters".to_string()];
+  if !fits {
+    notes.push(format!(
+      "Needs {} GiB plus {} GiB left free on /, but only {} GiB is free",
+      size / GIB,
+      ROOT_RESERVE_GIB,
+      var_free / GIB
+    ));
+  }
+  Ok(PlannedPool {
+    driver: loop_driver.to_string(),
+    source: PoolSource::LoopFile,
+    location: LOOP_DIR.to_string(),
+    size_bytes: size,
+    available_bytes: var_free,
+    fits,
+    erases: Vec::new(),
+    notes,
+  })
+}
+
+fn preview(workspace: &Workspace, pool: &SshPool, target: &SshTarget, request: &StorageRequest) -> DiskLayout {
+  let failed = |error: String| DiskLayout {
+    host: target.host.clone(),
+    error: Some(error),
+    ..Default::default()
+  };
+  let output = match ssh::run_script(workspace, pool, target, SCRIPT, PREVIEW_TIMEOUT) {
+    Ok(output) if output.stdout.contains("@@end") => output.stdout,
+    Ok(output) => {
+      return failed(format!(
+        "Disk layout script did not complete on {}: {}",
+        target.host,
+        output.stderr.trim()
+      ))
+    }
+    Err(e) => return failed(e),
+  };
+
+  let sections = sections(&output);
+  let empty = Vec::new();
+  let section = |name: &str| sections.get(name).unwrap_or(&empty);
+  let fact = |key: &str| {
+    section("facts")
+      .iter()
+      .find_map(|l| l.strip_prefix(key).and_then(|v| v.strip_prefix('=')))
+      .map(|v| v.trim().to_string())
+      .filter(|v| !v.is_empty())
+  };
+
+  let disks = parse_disks(section("lsblk"));
+  let (raid_arrays, multipath) = topology(&disks, section("mdstat"));
+  let mut layout = DiskLayout {
+    host: target.host.clone(),
+    disks,
+    volume_groups: parse_vgs(section("vgs")),
+    zfs_pools: parse_zpools(section("zpool")),
+    raid_arrays,
+    multipath,
+    root_source: fact("root_source"),
+    ..Default::default()
+  };
+  layout.warnings = topology_warnings(&layout);
+  let var_free = fact("var_free").and_then(|v| v.parse().ok()).unwrap_or(0);
+  match plan_pool(&layout, request, var_free, fact("zfs").as_deref() == Some("yes")) {
+    Ok(planned) => layout.pool = Some(planned),
+    Err(e) => layout.error = Some(e),
+  }
+  layout
+}
+
+/// Show each node's current disk layout and where the LXD pool would be
+/// created, without changing anything.
+#[tauri::command]
+pub async fn preview_disk_layout(
+  app: AppHandle,
+  hosts: Vec<SshTarget>,
+  request: Option<StorageRequest>,
+) -> Result<Vec<DiskLayout>, String> {
+  for target in &hosts {
+    target.validate()?;
+  }
+  let request = request.unwrap_or_default();
+  tauri::async_runtime::spawn_blocking(move || {
+    let workspace = app.state::<Workspace>();
+    let workspace: &Workspace = &workspace;
+    let pool = app.state::<SshPool>();
+    let pool: &SshPool = &pool;
+    let request = &request;
+    std::thread::scope(|scope| {
+      let handles: Vec<_> = hosts
+        .iter()
+        .map(|target| scope.spawn(move || preview(workspace, pool, target, request)))
+        .collect();
+      handles
+        .into_iter()
+        .zip(&hosts)
+        .map(|(handle, target)| {
+          handle.join().unwrap_or_else(|_| DiskLayout {
+            host: target.host.clone(),
+            error: Some("Disk layout preview panicked".to_string()),
+            ..Default::default()
+          })
+        })
+        .collect()
+    })
+  })
+  .await
+  .map_err(|e| e.to_string())
+}
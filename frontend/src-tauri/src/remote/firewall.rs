@@ -0,0 +1,753 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Host firewalls on the nodes.
+//!
+//! ufw and firewalld drop node-to-node traffic without a word: the install
+//! gets as far as joining the second node and then times out.
+//! `check_firewalls` finds the active firewall on each node and works out,
+//! for every port in [`REQUIRED_PORTS`] and every other planned node
+//! address, whether the traffic gets in: following ufw's rule order and
+//! default policy, or firewalld's source and interface zones. What is
+//! blocked comes back as the exact commands that would open it;
+//! `apply_firewall_rules` runs them when the user agrees, journaling each
+//! rule so a rollback closes it again.
+//!
+//! ufw application profiles and firewalld rich rules other than plain
+//! source/port accepts aren't evaluated; when one of them could decide a
+//! port, the port is reported as unknown rather than guessed.
+
+use serde::{Deserialize, Serialize};
+use std::net::{Ipv4Addr, ToSocketAddrs};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tracing::info;
+
+use super::inventory::sections;
+use super::ssh::{self, SshPool, SshTarget};
+use crate::dry_run;
+use crate::journal::{self, Action};
+use crate::net::ipplan::Subnet;
+use crate::preflight::Outcome;
+use crate::validation;
+use crate::workspace::Workspace;
+
+const SCAN_TIMEOUT: Duration = Duration::from_secs(30);
+const APPLY_TIMEOUT: Duration = Duration::from_secs(60);
+/// Comment on the ufw rules the installer adds
+const RULE_COMMENT: &str = "thinkube";
+
+const SCAN_SCRIPT: &str = r#"
+export LC_ALL=C
+echo '@@ufw'
+if command -v ufw >/dev/null 2>&1; then sudo -n ufw status verbose 2>&1; fi
+echo '@@firewalld'
+if command -v firewall-cmd >/dev/null 2>&1 && [ "$(sudo -n firewall-cmd --state 2>/dev/null)" = running ]; then
+  echo "default: $(sudo -n firewall-cmd --get-default-zone)"
+  for zone in $(sudo -n firewall-cmd --get-active-zones | grep -v '^[[:space:]]'); do
+    echo "zone: $zone"
+    sudo -n firewall-cmd --zone="$zone" --list-all
+  done
+fi
+echo '@@nftables'
+if command -v nft >/dev/null 2>&1; then sudo -n nft list ruleset 2>/dev/null | grep -E 'hook input .*policy (drop|reject)'; fi
+echo '@@end'
+"#;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Protocol {
+  Tcp,
+  Udp,
+}
+
+impl Protocol {
+  pub fn name(self) -> &'static str {
+    match self {
+      Protocol::Tcp => "tcp",
+      Protocol::Udp => "udp",
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FirewallKind {
+  None,
+  Ufw,
+  Firewalld,
+  /// Raw nftables rules, which aren't evaluated
+  Nftables,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RequiredPort {
+  pub port: u16,
+  pub protocol: Protocol,
+  pub purpose: &'static str,
+}
+
+/// What the nodes must reach on each other.
+pub const REQUIRED_PORTS: &[RequiredPort] = &[
+  RequiredPort {
+    port: 22,
+    protocol: Protocol::Tcp,
+    purpose: "SSH",
+  },
+  RequiredPort {
+    port: 6443,
+    protocol: Protocol::Tcp,
+    purpose: "Kubernetes API",
+  },
+  RequiredPort {
+    port: 6400,
+    protocol: Protocol::Tcp,
+    purpose: "k8sd cluster management",
+  },
+  RequiredPort {
+    port: 9000,
+    protocol: Protocol::Tcp,
+    purpose: "k8s-dqlite datastore",
+  },
+  RequiredPort {
+    port: 10250,
+    protocol: Protocol::Tcp,
+    purpose: "kubelet",
+  },
+  RequiredPort {
+    port: 4240,
+    protocol: Protocol::Tcp,
+    purpose: "Cilium health checks",
+  },
+  RequiredPort {
+    port: 8472,
+    protocol: Protocol::Udp,
+    purpose: "Cilium VXLAN overlay",
+  },
+];
+
+/// firewalld services that open one of the ports.
+const FIREWALLD_SERVICES: &[(&str, u16, Protocol)] = &[
+  ("ssh", 22, Protocol::Tcp),
+  ("kube-apiserver", 6443, Protocol::Tcp),
+  ("kube-api", 6443, Protocol::Tcp),
+  ("kubelet", 10250, Protocol::Tcp),
+];
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FirewallNode {
+  pub target: SshTarget,
+  /// Where the other nodes will reach it; `target.host` when omitted
+  pub address: Option<String>,
+}
+
+/// An opening for one peer on one port.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rule {
+  pub firewall: FirewallKind,
+  /// firewalld zone the rule goes in
+  pub zone: Option<String>,
+  pub peer: String,
+  pub port: u16,
+  pub protocol: Protocol,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PortCheck {
+  pub peer: String,
+  pub port: u16,
+  pub protocol: Protocol,
+  pub purpose: String,
+  /// `None` when the rules couldn't be evaluated
+  pub allowed: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeFirewall {
+  pub host: String,
+  pub firewall: FirewallKind,
+  pub outcome: Outcome,
+  pub message: String,
+  pub checks: Vec<PortCheck>,
+  /// Rules that would let the blocked traffic in
+  pub missing: Vec<Rule>,
+  /// The same rules as commands to run on the node
+  pub commands: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AppliedFirewallRules {
+  pub host: String,
+  pub commands: Vec<String>,
+}
+
+impl Rule {
+  pub fn validate(&self) -> Result<(), String> {
+    if self.peer.parse::<Ipv4Addr>().is_err() {
+      return Err(format!("Invalid peer address: {}", self.peer));
+    }
+    if self.port == 0 {
+      return Err("Invalid port: 0".to_string());
+    }
+    match (&self.firewall, &self.zone) {
+      (FirewallKind::Ufw, None) => Ok(()),
+      (FirewallKind::Firewalld, Some(zone)) if validation::is_valid_label(zone) => Ok(()),
+      (FirewallKind::Firewalld, _) => Err("firewalld rules need a valid zone".to_string()),
+      _ => Err("Rules can only be added to ufw or firewalld".to_string()),
+    }
+  }
+
+  fn rich_rule(&self) -> String {
+    format!(
+      "rule family=\"ipv4\" source address=\"{}\" port port=\"{}\" protocol=\"{}\" accept",
+      self.peer,
+      self.port,
+      self.protocol.name()
+    )
+  }
+
+  /// The command adding (or removing) the rule, without `sudo`.
+  fn command(&self, add: bool) -> String {
+    match self.firewall {
+      FirewallKind::Firewalld => format!(
+        "firewall-cmd --permanent --zone={} --{}-rich-rule='{}'",
+        self.zone.as_deref().unwrap_or_default(),
+        if add { "add" } else { "remove" },
+        self.rich_rule()
+      ),
+      _ if add => format!(
+        "ufw allow proto {} from {} to any port {} comment '{}'",
+        self.protocol.name(),
+        self.peer,
+        self.port,
+        RULE_COMMENT
+      ),
+      _ => format!(
+        "ufw delete allow proto {} from {} to any port {}",
+        self.protocol.name(),
+        self.peer,
+        self.port
+      ),
+    }
+  }
+}
+
+/// Script adding (or removing) `rules`, reloading firewalld if needed.
+/// Callers validate the rules first.
+pub fn rules_script(rules: &[Rule], add: bool) -> String {
+  let mut script = String::from("set -e\n");
+  for rule in rules {
+    script.push_str(&format!("sudo -n {} >/dev/null\n", rule.command(add)));
+  }
+  if rules.iter().any(|r| r.firewall == FirewallKind::Firewalld) {
+    script.push_str("sudo -n firewall-cmd --reload >/dev/null\n");
+  }
+  script
+}
+
+fn display_commands(rules: &[Rule]) -> Vec<String> {
+  let mut commands: Vec<String> = rules.iter().map(|r| format!("sudo {}", r.command(true))).collect();
+  if rules.iter().any(|r| r.firewall == FirewallKind::Firewalld) {
+    commands.push("sudo firewall-cmd --reload".to_string());
+  }
+  commands
+}
+
+/// Whether `source` (an address, a CIDR or `Anywhere`) covers `peer`;
+/// `None` when it can't be read.
+fn source_matches(source: &str, peer: Ipv4Addr) -> Option<bool> {
+  let source = source.split_whitespace().next()?;
+  if source == "Anywhere" {
+    return Some(true);
+  }
+  if source.contains(':') {
+    // IPv6
+    return Some(false);
+  }
+  if source.contains('/') {
+    return Subnet::parse(source).ok().map(|subnet| subnet.contains(peer));
+  }
+  source.parse::<Ipv4Addr>().ok().map(|ip| ip == peer)
+}
+
+/// Whether a port list like `80,443/tcp`, `2379:2380` or `6443-6444/tcp`
+/// covers `port`; `None` when it isn't a port list.
+fn ports_match(spec: &str, port: u16, protocol: Protocol, range: char) -> Option<bool> {
+  let (ports, proto) = match spec.split_once('/') {
+    Some((ports, proto)) => (ports, Some(proto)),
+    None => (spec, None),
+  };
+  if proto.is_some_and(|p| p != protocol.name()) {
+    return Some(false);
+  }
+  let mut covered = false;
+  for part in ports.split(',') {
+    let (start, end) = part.split_once(range).unwrap_or((part, part));
+    let (start, end) = (start.parse::<u16>().ok()?, end.parse::<u16>().ok()?);
+    covered |= (start..=end).contains(&port);
+  }
+  Some(covered)
+}
+
+#[derive(Debug)]
+enum UfwAction {
+  Allow,
+  Block,
+}
+
+#[derive(Debug)]
+struct UfwRule {
+  to: String,
+  action: UfwAction,
+  from: String,
+}
+
+struct Ufw {
+  active: bool,
+  default_allow: bool,
+  rules: Vec<UfwRule>,
+}
+
+fn parse_ufw(lines: &[&str]) -> Result<Option<Ufw>, String> {
+  let Some(status) = lines.iter().find_map(|l| l.trim().strip_prefix("Status:")) else {
+    return match lines.iter().map(|l| l.trim()).find(|l| !l.is_empty()) {
+      Some(error) => Err(format!("Cannot read ufw status: {}", error)),
+      None => Ok(None),
+    };
+  };
+  let default_allow = lines
+    .iter()
+    .find_map(|l| l.trim().strip_prefix("Default:"))
+    .is_some_and(|d| d.trim_start().starts_with("allow (incoming)"));
+
+  let columns = regex::Regex::new(r"\s{2,}").map_err(|e| e.to_string())?;
+  let rules = lines
+    .iter()
+    .skip_while(|l| !l.trim_start().starts_with("--"))
+    .skip(1)
+    .filter(|l| !l.contains("(v6)"))
+    .filter_map(|line| {
+      let line = line.split(" # ").next().unwrap_or(line);
+      let fields: Vec<&str> = columns.split(line.trim()).collect();
+      let [to, action, from] = fields[..] else {
+        return None;
+      };
+      let action = match action.split_whitespace().collect::<Vec<_>>()[..] {
+        ["ALLOW"] | ["ALLOW", "IN"] | ["LIMIT"] | ["LIMIT", "IN"] => UfwAction::Allow,
+        ["DENY"] | ["DENY", "IN"] | ["REJECT"] | ["REJECT", "IN"] => UfwAction::Block,
+        // Outgoing and routed rules don't affect incoming traffic
+        _ => return None,
+      };
+      Some(UfwRule {
+        to: to.to_string(),
+        action,
+        from: from.to_string(),
+      })
+    })
+    .collect();
+  Ok(Some(Ufw {
+    active: status.trim() == "active",
+    default_allow,
+    rules,
+  }))
+}
+
+fn ufw_allows(ufw: &Ufw, peer: Ipv4Addr, port: u16, protocol: Protocol) -> Option<bool> {
+  for rule in &ufw.rules {
+    let to = rule.to.split(" on ").next().unwrap_or(&rule.to);
+    let spec = to.split_whitespace().last().unwrap_or(to);
+    // A bare destination address or `Anywhere` covers every port
+    let to_matches = if spec == "Anywhere" || spec.parse::<Ipv4Addr>().is_ok() || spec.contains('.') {
+      Some(true)
+    } else {
+      ports_match(spec, port, protocol, ':')
+    };
+    match (to_matches, source_matches(&rule.from, peer)) {
+      (Some(false), _) | (_, Some(false)) => continue,
+      (Some(true), Some(true)) => return Some(matches!(rule.action, UfwAction::Allow)),
+      // An application profile or unreadable source could decide it
+      _ => return None,
+    }
+  }
+  Some(ufw.default_allow)
+}
+
+#[derive(Debug, Default)]
+struct Zone {
+  name: String,
+  target: String,
+  interfaces: Vec<String>,
+  sources: Vec<String>,
+  services: Vec<String>,
+  ports: Vec<String>,
+  rich_rules: Vec<String>,
+}
+
+struct Firewalld {
+  default_zone: String,
+  zones: Vec<Zone>,
+}
+
+fn parse_firewalld(lines: &[&str]) -> Option<Firewalld> {
+  let default_zone = lines
+    .iter()
+    .find_map(|l| l.strip_prefix("default:"))?
+    .trim()
+    .to_string();
+  let mut zones: Vec<Zone> = Vec::new();
+  let mut in_rich_rules = false;
+  for line in lines {
+    if let Some(name) = line.strip_prefix("zone:") {
+      zones.push(Zone {
+        name: name.trim().to_string(),
+        ..Zone::default()
+      });
+      in_rich_rules = false;
+      continue;
+    }
+    let Some(zone) = zones.last_mut() else {
+      continue;
+    };
+    let trimmed = line.trim();
+    if in_rich_rules && trimmed.starts_with("rule ") {
+      zone.rich_rules.push(trimmed.to_string());
+      continue;
+    }
+    let Some((key, value)) = trimmed.split_once(':') else {
+      continue;
+    };
+    let values = || value.split_whitespace().map(str::to_string).collect::<Vec<_>>();
+    in_rich_rules = key == "rich rules";
+    match key {
+      "target" => zone.target = value.trim().to_string(),
+      "interfaces" => zone.interfaces = values(),
+      "sources" => zone.sources = values(),
+      "services" => zone.services = values(),
+      "ports" => zone.ports = values(),
+      _ => {}
+    }
+  }
+  Some(Firewalld { default_zone, zones })
+}
+
+/// Value of `name="..."` in a rich rule.
+fn rich_rule_value<'a>(rule: &'a str, name: &str) -> Option<&'a str> {
+  let start = rule.find(&format!("{}=\"", name))? + name.len() + 2;
+  let end = rule[start..].find('"')? + start;
+  Some(&rule[start..end])
+}
+
+/// The zone handling traffic from `peer`: one listing it as a source, else
+/// the one bound to an interface, else the default.
+fn firewalld_zone(firewalld: &Firewalld, peer: Ipv4Addr) -> Option<&Zone> {
+  firewalld
+    .zones
+    .iter()
+    .find(|z| z.sources.iter().any(|s| source_matches(s, peer) == Some(true)))
+    .or_else(|| firewalld.zones.iter().find(|z| !z.interfaces.is_empty()))
+    .or_else(|| firewalld.zones.iter().find(|z| z.name == firewalld.default_zone))
+}
+
+fn firewalld_allows(zone: &Zone, peer: Ipv4Addr, port: u16, protocol: Protocol) -> Option<bool> {
+  if zone.target == "ACCEPT" || zone.name == "trusted" {
+    return Some(true);
+  }
+  if zone
+    .ports
+    .iter()
+    .any(|p| ports_match(p, port, protocol, '-') == Some(true))
+  {
+    return Some(true);
+  }
+  if FIREWALLD_SERVICES
+    .iter()
+    .any(|(service, p, proto)| *p == port && *proto == protocol && zone.services.iter().any(|s| s == service))
+  {
+    return Some(true);
+  }
+  let mut unknown = false;
+  for rule in &zone.rich_rules {
+    let plain = rule.ends_with(" accept")
+      && rich_rule_value(rule, "protocol").is_some()
+      && !rule.contains(" not ")
+      && !rule.contains("destination");
+    if !plain {
+      unknown = true;
+      continue;
+    }
+    let source = rich_rule_value(rule, "address").map_or(Some(true), |a| source_matches(a, peer));
+    let ports = rich_rule_value(rule, "port").and_then(|p| {
+      let protocol_matches = rich_rule_value(rule, "protocol") == Some(protocol.name());
+      ports_match(p, port, protocol, '-').map(|m| m && protocol_matches)
+    });
+    match (source, ports) {
+      (Some(true), Some(true)) => return Some(true),
+      (Some(false), _) | (_, Some(false)) => {}
+      _ => unknown = true,
+    }
+  }
+  if unknown {
+    None
+  } else {
+    Some(false)
+  }
+}
+
+fn resolve(address: &str) -> Option<Ipv4Addr> {
+  if let Ok(ip) = address.parse() {
+    return Some(ip);
+  }
+  (address, 0).to_socket_addrs().ok()?.find_map(|a| match a.ip() {
+    std::net::IpAddr::V4(ip) => Some(ip),
+    _ => None,
+  })
+}
+
+enum Active {
+  Ufw(Ufw),
+  Firewalld(Firewalld),
+}
+
+impl Active {
+  /// Whether the traffic gets in, and the firewalld zone deciding it.
+  fn allows(&self, peer: Ipv4Addr, port: u16, protocol: Protocol) -> (Option<bool>, Option<String>) {
+    match self {
+      Active::Ufw(ufw) => (ufw_allows(ufw, peer, port, protocol), None),
+      Active::Firewalld(firewalld) => match firewalld_zone(firewalld, peer) {
+        Some(zone) => (firewalld_allows(zone, peer, port, protocol), Some(zone.name.clone())),
+        None => (None, None),
+      },
+    }
+  }
+}
+
+fn evaluate(host: &str, output: &str, peers: &[(String, Option<Ipv4Addr>)]) -> NodeFirewall {
+  let sections = sections(output);
+  let empty = Vec::new();
+  let section = |name: &str| sections.get(name).unwrap_or(&empty).clone();
+  let mut report = NodeFirewall {
+    host: host.to_string(),
+    firewall: FirewallKind::None,
+    outcome: Outcome::Pass,
+    message: String::new(),
+    checks: Vec::new(),
+    missing: Vec::new(),
+    commands: Vec::new(),
+  };
+
+  let ufw = match parse_ufw(&section("ufw")) {
+    Ok(ufw) => ufw.filter(|u| u.active),
+    Err(e) => {
+      report.firewall = FirewallKind::Ufw;
+      report.outcome = Outcome::Warn;
+      report.message = format!("{}; ufw may block cluster traffic", e);
+      return report;
+    }
+  };
+  let firewalld = parse_firewalld(&section("firewalld"));
+  let active = if let Some(ufw) = ufw {
+    report.firewall = FirewallKind::Ufw;
+    Active::Ufw(ufw)
+  } else if let Some(firewalld) = firewalld {
+    report.firewall = FirewallKind::Firewalld;
+    Active::Firewalld(firewalld)
+  } else if section("nftables").iter().any(|l| !l.trim().is_empty()) {
+    report.firewall = FirewallKind::Nftables;
+    report.outcome = Outcome::Warn;
+    report.message =
+      "nftables drops incoming traffic by default; check that its rules let the other nodes in".to_string();
+    return report;
+  } else {
+    report.message = "No firewall is active".to_string();
+    return report;
+  };
+
+  let mut unresolved = Vec::new();
+  for (peer, ip) in peers {
+    let Some(ip) = ip else {
+      unresolved.push(peer.clone());
+      continue;
+    };
+    for required in REQUIRED_PORTS {
+      let (allowed, zone) = active.allows(*ip, required.port, required.protocol);
+      if allowed == Some(false) {
+        report.missing.push(Rule {
+          firewall: report.firewall,
+          zone,
+          peer: ip.to_string(),
+          port: required.port,
+          protocol: required.protocol,
+        });
+      }
+      report.checks.push(PortCheck {
+        peer: peer.clone(),
+        port: required.port,
+        protocol: required.protocol,
+        purpose: required.purpose.to_string(),
+        allowed,
+      });
+    }
+  }
+  report.commands = display_commands(&report.missing);
+
+  let unknown = report.checks.iter().filter(|c| c.allowed.is_none()).count();
+  let name = if report.firewall == FirewallKind::Ufw {
+    "ufw"
+  } else {
+    "firewalld"
+  };
+  (report.outcome, report.message) = if !report.missing.is_empty() {
+    (
+      Outcome::Fail,
+      format!(
+        "{} blocks {} of the connections the other nodes need",
+        name,
+        report.missing.len()
+      ),
+    )
+  } else if unknown > 0 || !unresolved.is_empty() {
+    let mut problems = Vec::new();
+    if unknown > 0 {
+      problems.push(format!(
+        "{} connection(s) depend on rules that can't be evaluated",
+        unknown
+      ));
+    }
+    if !unresolved.is_empty() {
+      problems.push(format!("cannot resolve {}", unresolved.join(", ")));
+    }
+    (Outcome::Warn, format!("{} is active; {}", name, problems.join("; ")))
+  } else {
+    (Outcome::Pass, format!("{} lets the other nodes in", name))
+  };
+  report
+}
+
+/// Check each node's firewall against the ports the cluster needs from the
+/// other nodes' addresses.
+#[tauri::command]
+pub async fn check_firewalls(app: AppHandle, nodes: Vec<FirewallNode>) -> Result<Vec<NodeFirewall>, String> {
+  for node in &nodes {
+    node.target.validate()?;
+  }
+  tauri::async_runtime::spawn_blocking(move || {
+    let addresses: Vec<(String, Option<Ipv4Addr>)> = nodes
+      .iter()
+      .map(|n| {
+        let address = n.address.clone().unwrap_or_else(|| n.target.host.clone());
+        let ip = resolve(&address);
+        (address, ip)
+      })
+      .collect();
+    let workspace = app.state::<Workspace>();
+    let workspace: &Workspace = &workspace;
+    let pool = app.state::<SshPool>();
+    let pool: &SshPool = &pool;
+    let addresses = &addresses;
+
+    std::thread::scope(|scope| {
+      let handles: Vec<_> = nodes
+        .iter()
+        .enumerate()
+        .map(|(index, node)| {
+          scope.spawn(move || {
+            let peers: Vec<(String, Option<Ipv4Addr>)> = addresses
+              .iter()
+              .enumerate()
+              .filter(|(i, _)| *i != index)
+              .map(|(_, peer)| peer.clone())
+              .collect();
+            let output = ssh::run_script(workspace, pool, &node.target, SCAN_SCRIPT, SCAN_TIMEOUT)?;
+            if !output.stdout.contains("@@end") {
+              return Err(format!("Firewall scan did not complete: {}", output.stderr.trim()));
+            }
+            Ok(evaluate(&node.target.host, &output.stdout, &peers))
+          })
+        })
+        .collect();
+      handles
+        .into_iter()
+        .zip(&nodes)
+        .map(|(handle, node)| {
+          let result = handle
+            .join()
+            .unwrap_or_else(|_| Err("Firewall scan panicked".to_string()));
+          result.unwrap_or_else(|error| NodeFirewall {
+            host: node.target.host.clone(),
+            firewall: FirewallKind::None,
+            outcome: Outcome::Skipped,
+            message: format!("Skipped: {}", error),
+            checks: Vec::new(),
+            missing: Vec::new(),
+            commands: Vec::new(),
+          })
+        })
+        .collect()
+    })
+  })
+  .await
+  .map_err(|e| e.to_string())
+}
+
+/// Add `rules` (from `check_firewalls`) on the node, each journaled so a
+/// rollback removes it.
+#[tauri::command]
+pub async fn apply_firewall_rules(
+  app: AppHandle,
+  host: SshTarget,
+  rules: Vec<Rule>,
+) -> Result<AppliedFirewallRules, String> {
+  host.validate()?;
+  if rules.is_empty() {
+    return Err("No firewall rules to apply".to_string());
+  }
+  for rule in &rules {
+    rule.validate()?;
+  }
+  let commands = display_commands(&rules);
+  if dry_run::is_enabled(&app) {
+    info!("Dry run: not adding {} firewall rule(s) on {}", rules.len(), host.host);
+    return Ok(AppliedFirewallRules {
+      host: host.host,
+      commands,
+    });
+  }
+
+  tauri::async_runtime::spawn_blocking(move || {
+    let output = ssh::run_script(
+      &app.state::<Workspace>(),
+      &app.state::<SshPool>(),
+      &host,
+      &rules_script(&rules, true),
+      APPLY_TIMEOUT,
+    )?;
+    if output.status != Some(0) {
+      let detail = output
+        .stderr
+        .trim()
+        .lines()
+        .last()
+        .unwrap_or("command failed")
+        .to_string();
+      return Err(format!("Failed to add firewall rules on {}: {}", host.host, detail));
+    }
+    info!("Added {} firewall rule(s) on {}", rules.len(), host.host);
+    for rule in rules {
+      journal::record(
+        &app,
+        Action::FirewallRule {
+          target: host.clone(),
+          rule,
+        },
+      );
+    }
+    Ok(AppliedFirewallRules {
+      host: host.host,
+      commands,
+    })
+  })
+  .await
+  .map_err(|e| e.to_string())?
+}
@@ -0,0 +1,359 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! The host keys the user confirmed for each node.
+//!
+//! `scan_host_key` fetches a node's keys with `ssh-keyscan` and says
+//! whether they are already trusted, new, or different from the ones
+//! trusted before; the user compares the fingerprints with the node's
+//! console and confirms them with `trust_host_key`. Trusted keys are kept
+//! in `host_keys.json` in the profile's data dir and written out as the
+//! `known_hosts` file `ssh` checks with `StrictHostKeyChecking=yes`, so the
+//! installer never connects to a node whose key nobody confirmed. A key
+//! that changed is never replaced by confirming the new one: the old one
+//! has to be forgotten with `forget_host_key` first, once the user knows
+//! the node was reinstalled rather than impersonated.
+
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager, State};
+use tracing::{info, warn};
+
+use crate::migrations::Versioned;
+use crate::platform::find_program;
+use crate::profiles;
+use crate::validation;
+use crate::workspace::{write_private_file, Workspace};
+
+const SCAN_TIMEOUT_SECS: u32 = 10;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HostKey {
+  pub host: String,
+  pub port: u16,
+  /// e.g. `ssh-ed25519`
+  pub key_type: String,
+  /// The base64 public key, as in `known_hosts`
+  pub key: String,
+  /// `SHA256:…`, as `ssh-keygen -l` prints it
+  pub fingerprint: String,
+  pub trusted_at: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyStatus {
+  Trusted,
+  /// Never seen; needs confirming
+  Unknown,
+  /// Doesn't match the keys trusted before
+  Changed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ScannedKey {
+  pub key_type: String,
+  pub fingerprint: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HostKeyScan {
+  pub host: String,
+  pub port: u16,
+  pub status: KeyStatus,
+  pub keys: Vec<ScannedKey>,
+  /// What was trusted before, for comparing when the key changed
+  pub trusted: Vec<ScannedKey>,
+}
+
+pub struct HostKeys {
+  path: PathBuf,
+  known_hosts: PathBuf,
+  keys: Mutex<Vec<HostKey>>,
+}
+
+/// The fingerprint of a base64 public key.
+pub fn fingerprint(key: &str) -> Result<String, String> {
+  let blob = base64::engine::general_purpose::STANDARD
+    .decode(key)
+    .map_err(|e| format!("Invalid host key: {}", e))?;
+  let digest = ring::digest::digest(&ring::digest::SHA256, &blob);
+  Ok(format!(
+    "SHA256:{}",
+    base64::engine::general_purpose::STANDARD_NO_PAD.encode(digest)
+  ))
+}
+
+/// How `known_hosts` names a host; ports other than 22 go in brackets.
+pub fn host_pattern(host: &str, port: u16) -> String {
+  if port == 22 {
+    host.to_string()
+  } else {
+    format!("[{}]:{}", host, port)
+  }
+}
+
+fn write_known_hosts(path: &Path, keys: &[HostKey]) -> Result<(), String> {
+  let lines: String = keys
+    .iter()
+    .map(|k| format!("{} {} {}\n", host_pattern(&k.host, k.port), k.key_type, k.key))
+    .collect();
+  write_private_file(path, lines.as_bytes())
+}
+
+/// The `(type, base64 key)` pairs the host offers.
+pub fn scan(host: &str, port: u16) -> Result<Vec<(String, String)>, String> {
+  let keyscan = find_program("ssh-keyscan", &[]).ok_or("ssh-keyscan is not installed")?;
+  let output = Command::new(keyscan)
+    .args(["-T", &SCAN_TIMEOUT_SECS.to_string()])
+    .args(["-p", &port.to_string()])
+    .args(["-t", "ed25519,ecdsa,rsa"])
+    .arg("--")
+    .arg(host)
+    .stdin(Stdio::null())
+    .output()
+    .map_err(|e| format!("Failed to run ssh-keyscan: {}", e))?;
+  let keys: Vec<(String, String)> = String::from_utf8_lossy(&output.stdout)
+    .lines()
+    .filter(|l| !l.starts_with('#'))
+    .filter_map(|l| {
+      let mut fields = l.split_whitespace().skip(1);
+      Some((fields.next()?.to_string(), fields.next()?.to_string()))
+    })
+    .collect();
+  if keys.is_empty() {
+    return Err(format!("{}:{} did not offer a host key", host, port));
+  }
+  Ok(keys)
+}
+
+impl HostKeys {
+  /// Load the trusted keys and write them out for `ssh`.
+  pub fn load(app: &AppHandle) -> Result<Self, String> {
+    let path = profiles::data_dir(app)?.join("host_keys.json");
+    let known_hosts = app.state::<Workspace>().resolve("ssh/known_hosts")?;
+
+    let keys: Vec<HostKey> = match std::fs::read_to_string(&path) {
+      Ok(contents) => serde_json::from_str::<Versioned<_>>(&contents)
+        .map(|v| v.items)
+        .unwrap_or_else(|e| {
+          warn!("Ignoring invalid {}: {}", path.display(), e);
+          Vec::new()
+        }),
+      Err(_) => Vec::new(),
+    };
+
+    write_known_hosts(&known_hosts, &keys)?;
+    Ok(Self {
+      path,
+      known_hosts,
+      keys: Mutex::new(keys),
+    })
+  }
+
+  fn save(&self, keys: &[HostKey]) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(&Versioned::current(keys)).map_err(|e| e.to_string())?;
+    let tmp = self.path.with_extension("json.tmp");
+    write_private_file(&tmp, json.as_bytes())?;
+    std::fs::rename(&tmp, &self.path).map_err(|e| format!("Failed to write {}: {}", self.path.display(), e))?;
+    write_known_hosts(&self.known_hosts, keys)
+  }
+
+  /// Trust `offered` as `host`'s keys, adding to those trusted already;
+  /// refused when it has a different key of a type trusted before. Keys of
+  /// a new type for a host that has trusted keys are only added when the
+  /// user `confirmed` them, or an impersonator offering just another type
+  /// would get in.
+  pub fn add(
+    &self,
+    host: &str,
+    port: u16,
+    offered: Vec<(String, String)>,
+    confirmed: bool,
+  ) -> Result<Vec<HostKey>, String> {
+    let mut keys = self.keys.lock().map_err(|e| e.to_string())?;
+    let trusted: Vec<&HostKey> = keys.iter().filter(|k| k.host == host && k.port == port).collect();
+    if offered
+      .iter()
+      .any(|(key_type, key)| trusted.iter().any(|t| &t.key_type == key_type && &t.key != key))
+    {
+      return Err(changed(host));
+    }
+    if !confirmed
+      && !trusted.is_empty()
+      && offered
+        .iter()
+        .any(|(key_type, _)| !trusted.iter().any(|t| &t.key_type == key_type))
+    {
+      return Err(format!(
+        "{} offers a type of host key not trusted for it before; confirm its fingerprint first",
+        host
+      ));
+    }
+    let now = SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .map(|d| d.as_secs())
+      .unwrap_or_default();
+    let added = offered
+      .into_iter()
+      .filter(|(key_type, key)| !trusted.iter().any(|t| &t.key_type == key_type && &t.key == key))
+      .map(|(key_type, key)| {
+        Ok(HostKey {
+          host: host.to_string(),
+          port,
+          fingerprint: fingerprint(&key)?,
+          key_type,
+          key,
+          trusted_at: now,
+        })
+      })
+      .collect::<Result<Vec<_>, String>>()?;
+    if !added.is_empty() {
+      keys.extend(added.iter().cloned());
+      self.save(&keys)?;
+      info!("Trusted {} host keys for {}:{}", added.len(), host, port);
+    }
+    Ok(added)
+  }
+
+  /// Drop the keys trusted for `host`; false if there were none.
+  pub fn forget(&self, host: &str, port: u16) -> Result<bool, String> {
+    let mut keys = self.keys.lock().map_err(|e| e.to_string())?;
+    let before = keys.len();
+    keys.retain(|k| !(k.host == host && k.port == port));
+    if keys.len() == before {
+      return Ok(false);
+    }
+    self.save(&keys)?;
+    info!("Forgot the host keys of {}:{}", host, port);
+    Ok(true)
+  }
+
+  pub fn all(&self) -> Vec<HostKey> {
+    self.keys.lock().map(|keys| keys.clone()).unwrap_or_default()
+  }
+
+  pub fn trusted(&self, host: &str, port: u16) -> Vec<HostKey> {
+    self
+      .keys
+      .lock()
+      .map(|keys| {
+        keys
+          .iter()
+          .filter(|k| k.host == host && k.port == port)
+          .cloned()
+          .collect()
+      })
+      .unwrap_or_default()
+  }
+}
+
+/// How what a host offers compares with what was trusted for it.
+pub fn status(trusted: &[HostKey], scanned: &[(String, String)]) -> KeyStatus {
+  if trusted.is_empty() {
+    KeyStatus::Unknown
+  } else if scanned
+    .iter()
+    .any(|(key_type, key)| trusted.iter().any(|t| &t.key_type == key_type && &t.key == key))
+  {
+    KeyStatus::Trusted
+  } else {
+    KeyStatus::Changed
+  }
+}
+
+fn changed(host: &str) -> String {
+  format!(
+    "The host key of {} has changed since it was trusted. If the node was reinstalled, forget the old key first",
+    host
+  )
+}
+
+pub fn validate(host: &str) -> Result<(), String> {
+  if !validation::is_valid_host(host) {
+    return Err(format!("Invalid host: {}", host));
+  }
+  Ok(())
+}
+
+/// Fetch the keys `host` offers and compare them with the trusted ones.
+#[tauri::command]
+pub async fn scan_host_key(app: AppHandle, host: String, port: Option<u16>) -> Result<HostKeyScan, String> {
+  validate(&host)?;
+  let port = port.unwrap_or(22);
+  tauri::async_runtime::spawn_blocking(move || {
+    let scanned = scan(&host, port)?;
+    let trusted = app.state::<HostKeys>().trusted(&host, port);
+    let keys = scanned
+      .iter()
+      .map(|(key_type, key)| {
+        Ok(ScannedKey {
+          key_type: key_type.clone(),
+          fingerprint: fingerprint(key)?,
+        })
+      })
+      .collect::<Result<_, String>>()?;
+    Ok(HostKeyScan {
+      status: status(&trusted, &scanned),
+      trusted: trusted
+        .into_iter()
+        .map(|k| ScannedKey {
+          key_type: k.key_type,
+          fingerprint: k.fingerprint,
+        })
+        .collect(),
+      host,
+      port,
+      keys,
+    })
+  })
+  .await
+  .map_err(|e| e.to_string())?
+}
+
+/// Trust the keys `host` offers, once the user has checked that
+/// `fingerprints` are the node's. Scans again so what's trusted is what
+/// the user saw.
+#[tauri::command]
+pub async fn trust_host_key(
+  app: AppHandle,
+  host: String,
+  port: Option<u16>,
+  fingerprints: Vec<String>,
+) -> Result<Vec<HostKey>, String> {
+  validate(&host)?;
+  let port = port.unwrap_or(22);
+  tauri::async_runtime::spawn_blocking(move || {
+    let confirmed: Vec<(String, String)> = scan(&host, port)?
+      .into_iter()
+      .filter(|(_, key)| fingerprint(key).is_ok_and(|f| fingerprints.contains(&f)))
+      .collect();
+    if confirmed.is_empty() {
+      return Err(format!(
+        "{} no longer offers a key with the confirmed fingerprint; scan it again",
+        host
+      ));
+    }
+    app.state::<HostKeys>().add(&host, port, confirmed, true)
+  })
+  .await
+  .map_err(|e| e.to_string())?
+}
+
+/// Forget the keys trusted for `host`, so a reinstalled node can be
+/// confirmed again.
+#[tauri::command]
+pub fn forget_host_key(store: State<'_, HostKeys>, host: String, port: Option<u16>) -> Result<(), String> {
+  store.forget(&host, port.unwrap_or(22)).map(|_| ())
+}
+
+#[tauri::command]
+pub fn list_host_keys(store: State<'_, HostKeys>) -> Vec<HostKey> {
+  store.all()
+}
@@ -0,0 +1,257 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Node hostnames.
+//!
+//! The hostname becomes the Kubernetes node name, which must be a lowercase
+//! RFC 1123 name and unique in the cluster. A node with `Node_1` or a name
+//! shared with another node installs fine and then fails kubelet
+//! registration near the end, so names are checked up front, with a
+//! suggested fix for each problem, and can be applied to the node from here.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tracing::info;
+
+use super::ssh::{self, shell_quote, SshPool, SshTarget};
+use crate::dry_run;
+use crate::journal::{self, Action};
+use crate::validation;
+use crate::workspace::Workspace;
+
+const MAX_HOSTNAME_LEN: usize = 63;
+const APPLY_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProposedHostname {
+  /// Address of the node, for matching results back; not validated here
+  pub host: Option<String>,
+  pub hostname: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HostnameVerdict {
+  pub host: Option<String>,
+  pub hostname: String,
+  pub valid: bool,
+  pub problems: Vec<String>,
+  /// A valid, unique name close to the proposed one
+  pub suggestion: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AppliedHostname {
+  pub previous: String,
+  pub hostname: String,
+}
+
+fn problems(hostname: &str) -> Vec<String> {
+  let mut problems = Vec::new();
+  if hostname.is_empty() {
+    problems.push("Hostname is empty".to_string());
+    return problems;
+  }
+  if hostname.len() > MAX_HOSTNAME_LEN {
+    problems.push(format!("Longer than {} characters", MAX_HOSTNAME_LEN));
+  }
+  if hostname.contains('.') {
+    problems.push("Contains dots; use the short name without the domain".to_string());
+  }
+  if hostname.chars().any(|c| c.is_ascii_uppercase()) {
+    problems.push("Contains uppercase letters".to_string());
+  }
+  if hostname.contains('_') {
+    problems.push("Contains underscores".to_string());
+  }
+  if hostname
+    .chars()
+    .any(|c| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')))
+  {
+    problems.push("Contains characters other than letters, digits and hyphens".to_string());
+  }
+  if hostname.starts_with('-') || hostname.ends_with('-') {
+    problems.push("Starts or ends with a hyphen".to_string());
+  }
+  if hostname.chars().all(|c| c.is_ascii_digit()) {
+    problems.push("Is entirely numeric".to_string());
+  }
+  problems
+}
+
+/// The closest valid label: lowercased, separators turned into hyphens,
+/// anything else dropped.
+fn sanitize(hostname: &str) -> String {
+  let mut out = String::new();
+  for c in hostname.chars() {
+    match c {
+      'a'..='z' | '0'..='9' => out.push(c),
+      'A'..='Z' => out.push(c.to_ascii_lowercase()),
+      '-' | '_' | '.' | ' ' if !out.ends_with('-') => out.push('-'),
+      _ => {}
+    }
+  }
+  let mut out = out.trim_matches('-').to_string();
+  out.truncate(MAX_HOSTNAME_LEN);
+  let mut out = out.trim_end_matches('-').to_string();
+  if out.is_empty() || out.chars().all(|c| c.is_ascii_digit()) {
+    out = format!("node-{}", out).trim_end_matches('-').to_string();
+  }
+  out
+}
+
+fn with_suffix(base: &str, n: usize) -> String {
+  let suffix = format!("-{}", n);
+  let mut stem = base.to_string();
+  stem.truncate(MAX_HOSTNAME_LEN - suffix.len());
+  format!("{}{}", stem.trim_end_matches('-'), suffix)
+}
+
+pub fn check(proposed: &[ProposedHostname]) -> Vec<HostnameVerdict> {
+  let mut counts: HashMap<String, usize> = HashMap::new();
+  for p in proposed {
+    *counts.entry(p.hostname.trim().to_ascii_lowercase()).or_default() += 1;
+  }
+
+  // Names already spoken for: every valid proposal, so suggestions avoid them
+  let mut taken: Vec<String> = proposed
+    .iter()
+    .map(|p| p.hostname.trim())
+    .filter(|h| problems(h).is_empty())
+    .map(str::to_string)
+    .collect();
+  let mut seen: HashMap<String, usize> = HashMap::new();
+
+  proposed
+    .iter()
+    .map(|p| {
+      let hostname = p.hostname.trim().to_string();
+      let mut issues = problems(&hostname);
+      let key = hostname.to_ascii_lowercase();
+      let occurrence = {
+        let n = seen.entry(key.clone()).or_default();
+        *n += 1;
+        *n
+      };
+      let duplicate = counts.get(&key).copied().unwrap_or(0) > 1;
+      if duplicate {
+        issues.push("Used by more than one node".to_string());
+      }
+
+      // The first holder of a duplicated valid name keeps it
+      let keeps_name = issues.is_empty() || (duplicate && occurrence == 1 && issues.len() == 1);
+      let suggestion = if keeps_name {
+        None
+      } else {
+        let base = sanitize(&hostname);
+        let mut candidate = base.clone();
+        let mut n = 2;
+        while taken.iter().any(|t| t.eq_ignore_ascii_case(&candidate)) {
+          candidate = with_suffix(&base, n);
+          n += 1;
+        }
+        taken.push(candidate.clone());
+        Some(candidate)
+      };
+
+      HostnameVerdict {
+        host: p.host.clone(),
+        hostname,
+        valid: issues.is_empty(),
+        problems: issues,
+        suggestion,
+      }
+    })
+    .collect()
+}
+
+/// Check proposed node hostnames against RFC 1123 and each other.
+#[tauri::command]
+pub fn validate_hostnames(hostnames: Vec<ProposedHostname>) -> Vec<HostnameVerdict> {
+  check(&hostnames)
+}
+
+/// Set the hostname and the matching `/etc/hosts` entry, and stop
+/// cloud-init from resetting it on the next boot. Prints the old and new
+/// names.
+pub fn rename_script(hostname: &str) -> String {
+  format!(
+    r#"set -e
+new={name}
+old=$(hostname)
+sudo -n hostnamectl set-hostname "$new"
+if grep -qE '^127\.0\.1\.1[[:space:]]' /etc/hosts; then
+  sudo -n sed -i -E "s/^127\.0\.1\.1[[:space:]].*/127.0.1.1\t$new/" /etc/hosts
+else
+  printf '127.0.1.1\t%s\n' "$new" | sudo -n tee -a /etc/hosts >/dev/null
+fi
+if [ -f /etc/cloud/cloud.cfg ]; then
+  if grep -q '^preserve_hostname:' /etc/cloud/cloud.cfg; then
+    sudo -n sed -i -E 's/^preserve_hostname:.*/preserve_hostname: true/' /etc/cloud/cloud.cfg
+  else
+    echo 'preserve_hostname: true' | sudo -n tee -a /etc/cloud/cloud.cfg >/dev/null
+  fi
+fi
+echo "$old"
+hostname
+"#,
+    name = shell_quote(hostname)
+  )
+}
+
+/// Set the node's hostname, keep `/etc/hosts` resolving it, and stop
+/// cloud-init from resetting it on the next boot.
+#[tauri::command]
+pub async fn apply_hostname(app: AppHandle, host: SshTarget, hostname: String) -> Result<AppliedHostname, String> {
+  host.validate()?;
+  let hostname = hostname.trim().to_string();
+  if !problems(&hostname).is_empty() || !validation::is_valid_label(&hostname) {
+    return Err(format!("Invalid hostname: {}", hostname));
+  }
+
+  let script = if dry_run::is_enabled(&app) {
+    info!("Dry run: not renaming {} to {}", host.host, hostname);
+    format!("hostname\necho {}\n", shell_quote(&hostname))
+  } else {
+    rename_script(&hostname)
+  };
+
+  tauri::async_runtime::spawn_blocking(move || {
+    let output = ssh::run_script(
+      &app.state::<Workspace>(),
+      &app.state::<SshPool>(),
+      &host,
+      &script,
+      APPLY_TIMEOUT,
+    )?;
+    if output.status != Some(0) {
+      let detail = output.stderr.trim().lines().last().unwrap_or("command failed").to_string();
+      return Err(format!("Failed to set hostname on {}: {}", host.host, detail));
+    }
+    let mut lines = output.stdout.lines().map(str::trim);
+    let previous = lines.next().unwrap_or_default().to_string();
+    let current = lines.next().unwrap_or_default().to_string();
+    if current != hostname {
+      return Err(format!("{} still reports hostname {}", host.host, current));
+    }
+    info!("Renamed {} from {} to {}", host.host, previous, current);
+    if previous != current {
+      journal::record(
+        &app,
+        Action::Hostname {
+          target: host.clone(),
+          previous: previous.clone(),
+        },
+      );
+    }
+    Ok(AppliedHostname {
+      previous,
+      hostname: current,
+    })
+  })
+  .await
+  .map_err(|e| e.to_string())?
+}
@@ -0,0 +1,316 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Hardware inventory of cluster nodes.
+//!
+//! One SSH session per node runs a short script built from tools present on
+//! a stock Ubuntu server (`lscpu`, `/proc/meminfo`, `lsblk`, `lspci`, and
+//! `nvidia-smi` when a driver is installed). Its output is split into
+//! `@@section` blocks and normalized here, so the wizard gets the same shape
+//! for every node. Nodes are queried in parallel; an unreachable node gets
+//! an entry with `error` set rather than failing the whole request.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+use super::ssh::{self, SshPool, SshTarget};
+use crate::workspace::Workspace;
+
+const COLLECT_TIMEOUT: Duration = Duration::from_secs(45);
+
+const SCRIPT: &str = r#"
+export LC_ALL=C
+echo '@@facts'
+echo "hostname=$(hostname)"
+echo "arch=$(uname -m)"
+echo "kernel=$(uname -r)"
+( . /etc/os-release 2>/dev/null && echo "os=$PRETTY_NAME" )
+lscpu 2>/dev/null | sed -n \
+  -e 's/^Model name: *\(.*\)/cpu_model=\1/p' \
+  -e 's/^Socket(s): *\(.*\)/cpu_sockets=\1/p' \
+  -e 's/^Core(s) per socket: *\(.*\)/cpu_cores_per_socket=\1/p'
+echo "cpu_threads=$(nproc 2>/dev/null)"
+awk '/^MemTotal:/ { print "mem_kb=" $2 }' /proc/meminfo
+echo '@@lsblk'
+lsblk -J -b -d -o NAME,SIZE,ROTA,TYPE,MODEL,TRAN 2>/dev/null
+echo '@@lspci'
+lspci -mm -nn 2>/dev/null | grep -E '\[(0300|0302|0380)\]'
+echo '@@nvidia'
+# Drivers older than 510 reject the whole query over compute_cap
+nvidia-smi --query-gpu=name,memory.total,driver_version,compute_cap,mig.mode.current \
+  --format=csv,noheader,nounits 2>/dev/null \
+  || nvidia-smi --query-gpu=name,memory.total,driver_version --format=csv,noheader,nounits 2>/dev/null
+echo '@@end'
+"#;
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CpuInfo {
+  pub model: String,
+  pub sockets: u32,
+  /// Physical cores across all sockets
+  pub cores: u32,
+  /// Logical CPUs
+  pub threads: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DiskInfo {
+  pub name: String,
+  pub size_bytes: u64,
+  pub rotational: bool,
+  pub model: Option<String>,
+  /// `sata`, `nvme`, `usb`, ...
+  pub transport: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GpuInfo {
+  /// `nvidia`, `amd`, `intel` or the raw PCI vendor name
+  pub vendor: String,
+  pub model: String,
+  pub pci_slot: String,
+  /// From `nvidia-smi`, when the driver is loaded. GPUs sharing system
+  /// memory, like the GB10, report none.
+  pub memory_mib: Option<u64>,
+  pub driver_version: Option<String>,
+  /// `8.9` and so on; drivers before 510 don't report it
+  pub compute_capability: Option<String>,
+  /// `enabled` or `disabled` on GPUs that support MIG
+  pub mig_mode: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct HostInventory {
+  /// The address we connected to, as given
+  pub host: String,
+  pub reachable: bool,
+  pub error: Option<String>,
+  pub hostname: Option<String>,
+  pub os: Option<String>,
+  pub kernel: Option<String>,
+  pub arch: Option<String>,
+  pub cpu: CpuInfo,
+  pub memory_bytes: u64,
+  pub disks: Vec<DiskInfo>,
+  pub gpus: Vec<GpuInfo>,
+}
+
+pub fn sections(output: &str) -> HashMap<&str, Vec<&str>> {
+  let mut sections: HashMap<&str, Vec<&str>> = HashMap::new();
+  let mut current = None;
+  for line in output.lines() {
+    if let Some(name) = line.strip_prefix("@@") {
+      current = Some(name.trim());
+      sections.entry(name.trim()).or_default();
+    } else if let Some(name) = current {
+      sections.entry(name).or_default().push(line);
+    }
+  }
+  sections
+}
+
+fn parse_disks(lines: &[&str]) -> Vec<DiskInfo> {
+  let Ok(json) = serde_json::from_str::<Value>(&lines.join("\n")) else {
+    return Vec::new();
+  };
+  let text = |v: &Value| v.as_str().map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
+  // Older util-linux prints numbers as strings and booleans as "0"/"1"
+  let number = |v: &Value| v.as_u64().or_else(|| v.as_str().and_then(|s| s.parse().ok()));
+
+  json["blockdevices"]
+    .as_array()
+    .map(|devices| {
+      devices
+        .iter()
+        .filter(|d| d["type"].as_str() == Some("disk"))
+        .filter_map(|d| {
+          let name = d["name"].as_str()?.to_string();
+          // zram swap devices report as disks
+          if name.starts_with("zram") {
+            return None;
+          }
+          Some(DiskInfo {
+            name,
+            size_bytes: number(&d["size"]).unwrap_or(0),
+            rotational: d["rota"].as_bool().unwrap_or_else(|| number(&d["rota"]) == Some(1)),
+            model: text(&d["model"]),
+            transport: text(&d["tran"]),
+          })
+        })
+        .collect()
+    })
+    .unwrap_or_default()
+}
+
+/// Quoted fields of an `lspci -mm` line, after the slot.
+pub fn lspci_fields(line: &str) -> (String, Vec<String>) {
+  let (slot, rest) = line.split_once(' ').unwrap_or((line, ""));
+  let fields = rest
+    .split('"')
+    .enumerate()
+    .filter(|(i, _)| i % 2 == 1)
+    .map(|(_, f)| f.to_string())
+    .collect();
+  (slot.to_string(), fields)
+}
+
+/// `NVIDIA Corporation [10de]` -> (`NVIDIA Corporation`, `10de`)
+pub fn split_id(field: &str) -> (&str, &str) {
+  match field.rfind(" [") {
+    Some(at) if field.ends_with(']') => (&field[..at], &field[at + 2..field.len() - 1]),
+    _ => (field, ""),
+  }
+}
+
+fn parse_gpus(lspci: &[&str], nvidia: &[&str]) -> Vec<GpuInfo> {
+  let mut smi = nvidia.iter().filter(|l| !l.trim().is_empty()).map(|line| {
+    let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+    // `[N/A]` where a GPU lacks the feature
+    let field = |i: usize| {
+      fields
+        .get(i)
+        .filter(|s| !s.is_empty() && !s.starts_with('['))
+        .map(|s| s.to_string())
+    };
+    (
+      field(0),
+      fields.get(1).and_then(|s| s.parse::<u64>().ok()),
+      field(2),
+      field(3),
+      field(4).map(|mode| mode.to_lowercase()),
+    )
+  });
+
+  lspci
+    .iter()
+    .filter(|l| !l.trim().is_empty())
+    .map(|line| {
+      let (slot, fields) = lspci_fields(line);
+      let (vendor_name, vendor_id) = split_id(fields.get(1).map(String::as_str).unwrap_or(""));
+      let (device, _) = split_id(fields.get(2).map(String::as_str).unwrap_or(""));
+      let vendor = match vendor_id {
+        "10de" => "nvidia".to_string(),
+        "1002" => "amd".to_string(),
+        "8086" => "intel".to_string(),
+        _ => vendor_name.to_string(),
+      };
+
+      let mut gpu = GpuInfo {
+        vendor,
+        model: device.to_string(),
+        pci_slot: slot,
+        memory_mib: None,
+        driver_version: None,
+        compute_capability: None,
+        mig_mode: None,
+      };
+      // nvidia-smi lists GPUs in PCI bus order, as lspci does
+      if gpu.vendor == "nvidia" {
+        if let Some((name, memory, driver, compute_capability, mig_mode)) = smi.next() {
+          gpu.model = name.unwrap_or(gpu.model);
+          gpu.memory_mib = memory;
+          gpu.driver_version = driver;
+          gpu.compute_capability = compute_capability;
+          gpu.mig_mode = mig_mode;
+        }
+      }
+      gpu
+    })
+    .collect()
+}
+
+fn parse(host: &str, output: &str) -> HostInventory {
+  let sections = sections(output);
+  let facts: HashMap<&str, &str> = sections
+    .get("facts")
+    .map(|lines| lines.iter().filter_map(|l| l.split_once('=')).collect())
+    .unwrap_or_default();
+  let fact = |key: &str| facts.get(key).map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+  let count = |key: &str| fact(key).and_then(|v| v.parse::<u32>().ok()).unwrap_or(0);
+
+  let sockets = count("cpu_sockets").max(1);
+  let threads = count("cpu_threads");
+  let cores = match count("cpu_cores_per_socket") {
+    0 => threads,
+    per_socket => per_socket * sockets,
+  };
+  let empty = Vec::new();
+
+  HostInventory {
+    host: host.to_string(),
+    reachable: true,
+    error: None,
+    hostname: fact("hostname"),
+    os: fact("os"),
+    kernel: fact("kernel"),
+    arch: fact("arch"),
+    cpu: CpuInfo {
+      model: fact("cpu_model").unwrap_or_default(),
+      sockets,
+      cores,
+      threads,
+    },
+    memory_bytes: fact("mem_kb").and_then(|v| v.parse::<u64>().ok()).unwrap_or(0) * 1024,
+    disks: parse_disks(sections.get("lsblk").unwrap_or(&empty)),
+    gpus: parse_gpus(
+      sections.get("lspci").unwrap_or(&empty),
+      sections.get("nvidia").unwrap_or(&empty),
+    ),
+  }
+}
+
+fn collect(workspace: &Workspace, pool: &SshPool, target: &SshTarget) -> HostInventory {
+  let failed = |error: String| HostInventory {
+    host: target.host.clone(),
+    error: Some(error),
+    ..Default::default()
+  };
+  match ssh::run_script(workspace, pool, target, SCRIPT, COLLECT_TIMEOUT) {
+    Ok(output) if output.stdout.contains("@@end") => parse(&target.host, &output.stdout),
+    Ok(output) => failed(format!(
+      "Inventory script did not complete on {} (exit status {}): {}",
+      target.host,
+      output.status.map(|c| c.to_string()).unwrap_or_else(|| "unknown".to_string()),
+      output.stderr.trim()
+    )),
+    Err(e) => failed(e),
+  }
+}
+
+/// Gather CPU, memory, disk and GPU facts from each node over SSH.
+#[tauri::command]
+pub async fn collect_remote_inventory(app: AppHandle, hosts: Vec<SshTarget>) -> Result<Vec<HostInventory>, String> {
+  for target in &hosts {
+    target.validate()?;
+  }
+  tauri::async_runtime::spawn_blocking(move || {
+    let workspace = app.state::<Workspace>();
+    let workspace: &Workspace = &workspace;
+    let pool = app.state::<SshPool>();
+    let pool: &SshPool = &pool;
+    std::thread::scope(|scope| {
+      let handles: Vec<_> = hosts
+        .iter()
+        .map(|target| scope.spawn(move || collect(workspace, pool, target)))
+        .collect();
+      handles
+        .into_iter()
+        .zip(&hosts)
+        .map(|(handle, target)| {
+          handle.join().unwrap_or_else(|_| HostInventory {
+            host: target.host.clone(),
+            error: Some("Inventory collection panicked".to_string()),
+            ..Default::default()
+          })
+        })
+        .collect()
+    })
+  })
+  .await
+  .map_err(|e| e.to_string())
+}
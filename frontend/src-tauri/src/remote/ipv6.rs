@@ -0,0 +1,403 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Whether the nodes and the planned CIDRs are ready for a dual-stack
+//! cluster.
+//!
+//! `check_ipv6_readiness` looks at each node's global IPv6 addresses and
+//! default route, the prefixes router advertisements hand out on its
+//! network (which is where a prefix delegated to the router shows up), and
+//! whether it reaches the internet over IPv6. A route learned from an RA
+//! is worth a warning when `accept_ra` isn't 2: Kubernetes turns on
+//! forwarding, after which the kernel ignores RAs and the route expires.
+//! The pod and service CIDRs the user planned are checked for what
+//! Kubernetes accepts and against the node prefixes. Nodes reported ready
+//! get an `ipv6_address` in the inventory, and the CIDRs go in as the
+//! dual-stack settings once every check passes.
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+use super::inventory::sections;
+use super::ssh::{self, SshPool, SshTarget};
+use crate::net::ipplan::Subnet6;
+use crate::preflight::Outcome;
+use crate::workspace::Workspace;
+
+const SCAN_TIMEOUT: Duration = Duration::from_secs(30);
+/// kube-apiserver refuses IPv6 service ranges with more host bits than this
+const MIN_SERVICE_PREFIX: u8 = 108;
+/// Leaves the pod range room for a block per node
+const MAX_POD_PREFIX: u8 = 112;
+
+pub const SCAN_SCRIPT: &str = r#"
+export LC_ALL=C
+echo '@@addresses'
+ip -6 -o addr show scope global 2>/dev/null
+echo '@@routes'
+ip -6 route show 2>/dev/null
+echo '@@sysctl'
+for dir in /proc/sys/net/ipv6/conf/*/; do
+  name=$(basename "$dir")
+  echo "$name $(cat "$dir/accept_ra" 2>/dev/null) $(cat "$dir/forwarding" 2>/dev/null) $(cat "$dir/disable_ipv6" 2>/dev/null)"
+done
+echo '@@internet'
+if ping -6 -c 1 -W 3 2606:4700:4700::1111 >/dev/null 2>&1; then
+  echo ping
+elif command -v curl >/dev/null 2>&1 && curl -6 -sS -o /dev/null -m 5 'http://[2606:4700:4700::1111]/' 2>/dev/null; then
+  echo http
+fi
+echo '@@end'
+"#;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Ipv6Plan {
+  pub pod_cidr: Option<String>,
+  pub service_cidr: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Ipv6Address {
+  pub interface: String,
+  /// In CIDR form
+  pub address: String,
+  /// Assigned by SLAAC or DHCPv6 rather than configured
+  pub dynamic: bool,
+  /// A privacy address, which changes over time
+  pub temporary: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Ipv6Route {
+  pub via: Option<String>,
+  pub interface: String,
+  /// Learned from a router advertisement
+  pub from_ra: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeIpv6Status {
+  pub addresses: Vec<Ipv6Address>,
+  pub default_route: Option<Ipv6Route>,
+  /// On-link prefixes router advertisements announce
+  pub ra_prefixes: Vec<String>,
+  /// `accept_ra` on the default route's interface
+  pub accept_ra: Option<u8>,
+  pub forwarding: bool,
+  pub disabled: bool,
+  /// Reached a public IPv6 address
+  pub internet: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeIpv6 {
+  pub host: String,
+  pub status: Option<NodeIpv6Status>,
+  /// The stable global address to put in the inventory
+  pub address: Option<String>,
+  pub outcome: Outcome,
+  pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CidrVerdict {
+  /// `pod_cidr` or `service_cidr`
+  pub name: String,
+  pub cidr: String,
+  pub valid: bool,
+  pub problems: Vec<String>,
+  pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Ipv6Readiness {
+  pub nodes: Vec<NodeIpv6>,
+  pub cidrs: Vec<CidrVerdict>,
+  /// Every node has an address and every planned CIDR is valid
+  pub dual_stack: bool,
+}
+
+pub fn parse(output: &str) -> NodeIpv6Status {
+  let sections = sections(output);
+  let empty = Vec::new();
+  let section = |name: &str| -> Vec<&str> {
+    sections
+      .get(name)
+      .unwrap_or(&empty)
+      .iter()
+      .map(|l| l.trim())
+      .filter(|l| !l.is_empty())
+      .collect()
+  };
+
+  let addresses = section("addresses")
+    .into_iter()
+    .filter_map(|line| {
+      let fields: Vec<&str> = line.split_whitespace().collect();
+      let [_, interface, "inet6", address, ..] = fields[..] else {
+        return None;
+      };
+      // Not usable until duplicate address detection is done, or no more
+      if fields
+        .iter()
+        .any(|f| matches!(*f, "tentative" | "dadfailed" | "deprecated"))
+      {
+        return None;
+      }
+      Some(Ipv6Address {
+        interface: interface.trim_end_matches(':').to_string(),
+        address: address.to_string(),
+        dynamic: fields.contains(&"dynamic"),
+        temporary: fields.contains(&"temporary"),
+      })
+    })
+    .collect();
+
+  let mut default_route = None;
+  let mut ra_prefixes = Vec::new();
+  for line in section("routes") {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    let value = |key: &str| {
+      fields
+        .iter()
+        .position(|f| *f == key)
+        .and_then(|i| fields.get(i + 1))
+        .map(|v| v.to_string())
+    };
+    let from_ra = value("proto").as_deref() == Some("ra");
+    match fields.first() {
+      Some(&"default") if default_route.is_none() => {
+        default_route = value("dev").map(|interface| Ipv6Route {
+          via: value("via"),
+          interface,
+          from_ra,
+        });
+      }
+      Some(prefix) if from_ra && Subnet6::parse(prefix).is_ok() => ra_prefixes.push(prefix.to_string()),
+      _ => {}
+    }
+  }
+
+  let sysctl = |interface: &str| -> Option<Vec<u8>> {
+    section("sysctl").into_iter().find_map(|line| {
+      let mut fields = line.split_whitespace();
+      (fields.next()? == interface).then(|| fields.filter_map(|f| f.parse().ok()).collect())
+    })
+  };
+  let interface = default_route.as_ref().map(|r: &Ipv6Route| r.interface.clone());
+  let settings = interface.as_deref().and_then(sysctl).unwrap_or_default();
+  let all = sysctl("all").unwrap_or_default();
+  NodeIpv6Status {
+    addresses,
+    default_route,
+    ra_prefixes,
+    accept_ra: settings.first().copied(),
+    forwarding: settings.get(1).or(all.get(1)).is_some_and(|v| *v == 1),
+    disabled: all.get(2).is_some_and(|v| *v == 1) || settings.get(2).is_some_and(|v| *v == 1),
+    internet: !section("internet").is_empty(),
+  }
+}
+
+impl NodeIpv6Status {
+  /// The address other nodes should use: a stable one on the default
+  /// route's interface, configured ones first.
+  pub fn address(&self) -> Option<String> {
+    let interface = self.default_route.as_ref().map(|r| r.interface.as_str());
+    let mut candidates: Vec<&Ipv6Address> = self.addresses.iter().filter(|a| !a.temporary).collect();
+    candidates.sort_by_key(|a| (Some(a.interface.as_str()) != interface, a.dynamic));
+    candidates
+      .first()
+      .map(|a| a.address.split('/').next().unwrap_or_default().to_string())
+  }
+
+  pub fn verdict(&self) -> (Outcome, String) {
+    if self.disabled {
+      return (Outcome::Fail, "IPv6 is disabled on this node".to_string());
+    }
+    if self.address().is_none() {
+      return (Outcome::Fail, "No stable global IPv6 address".to_string());
+    }
+    let Some(route) = &self.default_route else {
+      return (Outcome::Fail, "No IPv6 default route".to_string());
+    };
+    if route.from_ra && self.accept_ra != Some(2) {
+      return (
+        Outcome::Warn,
+        format!(
+          "The default route on {} comes from router advertisements, which stop once Kubernetes enables forwarding; set net.ipv6.conf.{}.accept_ra=2",
+          route.interface, route.interface
+        ),
+      );
+    }
+    if !self.internet {
+      return (
+        Outcome::Warn,
+        "Has a global IPv6 address but could not reach the internet over IPv6".to_string(),
+      );
+    }
+    (Outcome::Pass, "Ready for dual-stack".to_string())
+  }
+}
+
+/// Check a planned CIDR against what Kubernetes accepts and the networks
+/// the nodes are on.
+fn check_cidr(name: &str, cidr: &str, node_networks: &[Subnet6], other: Option<&Subnet6>) -> CidrVerdict {
+  let mut problems = Vec::new();
+  let mut warnings = Vec::new();
+  match Subnet6::parse(cidr) {
+    Err(e) => problems.push(e),
+    Ok(subnet) => {
+      let special = [
+        ("fe80::/10", "link-local"),
+        ("ff00::/8", "multicast"),
+        ("::/127", "unspecified or loopback"),
+        ("::ffff:0:0/96", "IPv4-mapped"),
+        ("2001:db8::/32", "documentation"),
+      ];
+      for (range, kind) in special {
+        if Subnet6::parse(range).is_ok_and(|r| r.overlaps(&subnet)) {
+          problems.push(format!("Overlaps the {} range {}", kind, range));
+        }
+      }
+      if name == "service_cidr" && subnet.prefix() < MIN_SERVICE_PREFIX {
+        problems.push(format!(
+          "Service ranges must be /{} or smaller, not /{}",
+          MIN_SERVICE_PREFIX,
+          subnet.prefix()
+        ));
+      }
+      if name == "pod_cidr" && subnet.prefix() > MAX_POD_PREFIX {
+        problems.push(format!(
+          "Pod ranges must be /{} or larger to leave a block per node",
+          MAX_POD_PREFIX
+        ));
+      }
+      if let Some(other) = other.filter(|o| o.overlaps(&subnet)) {
+        problems.push(format!("Overlaps {}", other));
+      }
+      for network in node_networks.iter().filter(|n| n.overlaps(&subnet)) {
+        problems.push(format!("Overlaps the node network {}", network));
+      }
+      if !Subnet6::parse("fc00::/7").is_ok_and(|ula| ula.contains(subnet.address())) {
+        warnings.push(
+          "Not a unique local (fc00::/7) range; a global range only works if the router routes it to the nodes"
+            .to_string(),
+        );
+      }
+    }
+  }
+  CidrVerdict {
+    name: name.to_string(),
+    cidr: cidr.to_string(),
+    valid: problems.is_empty(),
+    problems,
+    warnings,
+  }
+}
+
+fn node_networks(nodes: &[NodeIpv6]) -> Vec<Subnet6> {
+  nodes
+    .iter()
+    .filter_map(|n| n.status.as_ref())
+    .flat_map(|s| {
+      s.addresses
+        .iter()
+        .map(|a| a.address.as_str())
+        .chain(s.ra_prefixes.iter().map(String::as_str))
+    })
+    .filter_map(|cidr| Subnet6::parse(cidr).ok())
+    .collect()
+}
+
+/// Check each node and the planned IPv6 pod and service CIDRs for a
+/// dual-stack cluster.
+#[tauri::command]
+pub async fn check_ipv6_readiness(
+  app: AppHandle,
+  hosts: Vec<SshTarget>,
+  plan: Option<Ipv6Plan>,
+) -> Result<Ipv6Readiness, String> {
+  for target in &hosts {
+    target.validate()?;
+  }
+  tauri::async_runtime::spawn_blocking(move || {
+    let workspace = app.state::<Workspace>();
+    let workspace: &Workspace = &workspace;
+    let pool = app.state::<SshPool>();
+    let pool: &SshPool = &pool;
+
+    let nodes: Vec<NodeIpv6> = std::thread::scope(|scope| {
+      let handles: Vec<_> = hosts
+        .iter()
+        .map(|target| {
+          scope.spawn(move || {
+            let output = ssh::run_script(workspace, pool, target, SCAN_SCRIPT, SCAN_TIMEOUT)?;
+            if !output.stdout.contains("@@end") {
+              return Err(format!("Scan did not complete: {}", output.stderr.trim()));
+            }
+            Ok(parse(&output.stdout))
+          })
+        })
+        .collect();
+      handles
+        .into_iter()
+        .zip(&hosts)
+        .map(|(handle, target)| {
+          let result = handle.join().unwrap_or_else(|_| Err("Scan panicked".to_string()));
+          match result {
+            Ok(status) => {
+              let (outcome, message) = status.verdict();
+              NodeIpv6 {
+                host: target.host.clone(),
+                address: status.address(),
+                status: Some(status),
+                outcome,
+                message,
+              }
+            }
+            Err(e) => NodeIpv6 {
+              host: target.host.clone(),
+              status: None,
+              address: None,
+              outcome: Outcome::Skipped,
+              message: format!("Skipped: {}", e),
+            },
+          }
+        })
+        .collect()
+    });
+
+    let networks = node_networks(&nodes);
+    let plan = plan.unwrap_or(Ipv6Plan {
+      pod_cidr: None,
+      service_cidr: None,
+    });
+    let pod = plan.pod_cidr.as_deref().map(str::trim).filter(|c| !c.is_empty());
+    let service = plan.service_cidr.as_deref().map(str::trim).filter(|c| !c.is_empty());
+    let mut cidrs = Vec::new();
+    if let Some(pod) = pod {
+      let other = service.and_then(|s| Subnet6::parse(s).ok());
+      cidrs.push(check_cidr("pod_cidr", pod, &networks, other.as_ref()));
+    }
+    if let Some(service) = service {
+      cidrs.push(check_cidr("service_cidr", service, &networks, None));
+    }
+
+    let dual_stack = !nodes.is_empty()
+      && nodes
+        .iter()
+        .all(|n| n.address.is_some() && matches!(n.outcome, Outcome::Pass | Outcome::Warn))
+      && cidrs.len() == 2
+      && cidrs.iter().all(|c| c.valid);
+    Ipv6Readiness {
+      nodes,
+      cidrs,
+      dual_stack,
+    }
+  })
+  .await
+  .map_err(|e| e.to_string())
+}
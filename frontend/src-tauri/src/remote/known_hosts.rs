@@ -0,0 +1,331 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Managing the `known_hosts` entries for the nodes.
+//!
+//! The installer's own file is the one [`super::host_keys`] writes from
+//! the trusted keys, so entries added or removed here go through that
+//! store. With `user_file` the same commands work on the user's
+//! `~/.ssh/known_hosts` instead, which is what Ansible and a plain `ssh`
+//! from a terminal check: entries are appended, and removed with
+//! `ssh-keygen -R` (which keeps a `.old` copy and handles hashed names).
+//!
+//! `seed_known_hosts` takes the nodes found during discovery, scans their
+//! keys with `ssh-keyscan` and trusts those of nodes seen for the first
+//! time, so no later connection stops at a host key prompt. A node already
+//! known is reported and left alone, whatever keys it offers now.
+
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use tauri::{AppHandle, Manager};
+use tracing::info;
+
+use super::host_keys::{self, host_pattern, HostKeys, KeyStatus};
+use crate::platform::find_program;
+use crate::workspace::create_private_dir;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct KnownHost {
+  /// `None` when the file hashes host names
+  pub host: Option<String>,
+  pub port: u16,
+  pub key_type: String,
+  pub fingerprint: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct KnownHostTarget {
+  pub host: String,
+  pub port: Option<u16>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SeededHost {
+  pub host: String,
+  pub port: u16,
+  /// What the scan found before anything was added
+  pub status: Option<KeyStatus>,
+  /// Keys added to the installer's file
+  pub added: usize,
+  /// Keys added to the user's file, with `user_file`
+  pub added_to_user_file: usize,
+  pub error: Option<String>,
+}
+
+fn user_known_hosts(app: &AppHandle) -> Result<PathBuf, String> {
+  let home = app.path().home_dir().map_err(|e| e.to_string())?;
+  Ok(home.join(".ssh").join("known_hosts"))
+}
+
+/// `[host]:port` or `host` back into its parts.
+fn parse_pattern(pattern: &str) -> (String, u16) {
+  if let Some((host, port)) = pattern.strip_prefix('[').and_then(|p| p.split_once("]:")) {
+    return (host.to_string(), port.parse().unwrap_or(22));
+  }
+  (pattern.to_string(), 22)
+}
+
+/// `(pattern, type, key)` for each plain entry, skipping comments and
+/// `@cert-authority` / `@revoked` lines.
+fn entries(contents: &str) -> Vec<(String, String, String)> {
+  contents
+    .lines()
+    .map(str::trim)
+    .filter(|l| !l.is_empty() && !l.starts_with('#') && !l.starts_with('@'))
+    .filter_map(|l| {
+      let mut fields = l.split_whitespace();
+      Some((
+        fields.next()?.to_string(),
+        fields.next()?.to_string(),
+        fields.next()?.to_string(),
+      ))
+    })
+    .collect()
+}
+
+/// The `(type, key)` pairs the user's file has for `host`, hashed or not.
+fn user_keys(file: &Path, host: &str, port: u16) -> Result<Vec<(String, String)>, String> {
+  if !file.exists() {
+    return Ok(Vec::new());
+  }
+  let keygen = find_program("ssh-keygen", &[]).ok_or("ssh-keygen is not installed")?;
+  let output = Command::new(keygen)
+    .arg("-F")
+    .arg(host_pattern(host, port))
+    .arg("-f")
+    .arg(file)
+    .stdin(Stdio::null())
+    .output()
+    .map_err(|e| format!("Failed to run ssh-keygen: {}", e))?;
+  Ok(
+    entries(&String::from_utf8_lossy(&output.stdout))
+      .into_iter()
+      .map(|(_, key_type, key)| (key_type, key))
+      .collect(),
+  )
+}
+
+/// Append the keys of `offered` the user's file doesn't have yet; refused
+/// when it has a different key of the same type for `host`.
+fn add_to_user_file(file: &Path, host: &str, port: u16, offered: &[(String, String)]) -> Result<usize, String> {
+  let existing = user_keys(file, host, port)?;
+  if offered
+    .iter()
+    .any(|(key_type, key)| existing.iter().any(|(t, k)| t == key_type && k != key))
+  {
+    return Err(format!(
+      "{} already has a different key for {}; remove it first if the node was reinstalled",
+      file.display(),
+      host
+    ));
+  }
+  let missing: Vec<&(String, String)> = offered.iter().filter(|o| !existing.contains(o)).collect();
+  if missing.is_empty() {
+    return Ok(0);
+  }
+  if let Some(dir) = file.parent() {
+    create_private_dir(dir)?;
+  }
+  let mut options = std::fs::OpenOptions::new();
+  options.append(true).create(true);
+  #[cfg(unix)]
+  {
+    use std::os::unix::fs::OpenOptionsExt;
+    options.mode(0o600);
+  }
+  let mut out = options
+    .open(file)
+    .map_err(|e| format!("Failed to open {}: {}", file.display(), e))?;
+  for (key_type, key) in &missing {
+    writeln!(out, "{} {} {}", host_pattern(host, port), key_type, key)
+      .map_err(|e| format!("Failed to write {}: {}", file.display(), e))?;
+  }
+  info!("Added {} keys for {} to {}", missing.len(), host, file.display());
+  Ok(missing.len())
+}
+
+fn known_host(host: Option<String>, port: u16, key_type: String, key: &str) -> KnownHost {
+  KnownHost {
+    host,
+    port,
+    key_type,
+    fingerprint: host_keys::fingerprint(key).unwrap_or_default(),
+  }
+}
+
+/// The entries in the installer's `known_hosts`, or with `user_file` the
+/// user's.
+#[tauri::command]
+pub fn list_known_hosts(app: AppHandle, user_file: Option<bool>) -> Result<Vec<KnownHost>, String> {
+  if !user_file.unwrap_or(false) {
+    let keys = app.state::<HostKeys>().all();
+    return Ok(
+      keys
+        .into_iter()
+        .map(|k| known_host(Some(k.host), k.port, k.key_type, &k.key))
+        .collect(),
+    );
+  }
+  let file = user_known_hosts(&app)?;
+  let contents = match std::fs::read_to_string(&file) {
+    Ok(contents) => contents,
+    Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+    Err(e) => return Err(format!("Failed to read {}: {}", file.display(), e)),
+  };
+  Ok(
+    entries(&contents)
+      .into_iter()
+      .map(|(pattern, key_type, key)| {
+        if pattern.starts_with('|') {
+          return known_host(None, 22, key_type, &key);
+        }
+        // Only the first of a comma-separated list of names
+        let (host, port) = parse_pattern(pattern.split(',').next().unwrap_or_default());
+        known_host(Some(host), port, key_type, &key)
+      })
+      .collect(),
+  )
+}
+
+/// Add a key for `host` as given, e.g. copied from the node's console.
+#[tauri::command]
+pub fn add_known_host(
+  app: AppHandle,
+  host: String,
+  port: Option<u16>,
+  key_type: String,
+  key: String,
+  user_file: Option<bool>,
+) -> Result<KnownHost, String> {
+  host_keys::validate(&host)?;
+  let port = port.unwrap_or(22);
+  if key_type.is_empty()
+    || !key_type
+      .chars()
+      .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '@' | '.'))
+  {
+    return Err(format!("Invalid key type: {}", key_type));
+  }
+  host_keys::fingerprint(&key)?;
+  let offered = vec![(key_type.clone(), key.clone())];
+  if user_file.unwrap_or(false) {
+    add_to_user_file(&user_known_hosts(&app)?, &host, port, &offered)?;
+  } else {
+    app.state::<HostKeys>().add(&host, port, offered, true)?;
+  }
+  Ok(known_host(Some(host), port, key_type, &key))
+}
+
+/// Remove every key for `host` from the installer's `known_hosts`, or with
+/// `user_file` the user's.
+#[tauri::command]
+pub fn remove_known_host(
+  app: AppHandle,
+  host: String,
+  port: Option<u16>,
+  user_file: Option<bool>,
+) -> Result<(), String> {
+  host_keys::validate(&host)?;
+  let port = port.unwrap_or(22);
+  if !user_file.unwrap_or(false) {
+    return app.state::<HostKeys>().forget(&host, port).map(|_| ());
+  }
+  let file = user_known_hosts(&app)?;
+  if !file.exists() {
+    return Ok(());
+  }
+  let keygen = find_program("ssh-keygen", &[]).ok_or("ssh-keygen is not installed")?;
+  let output = Command::new(keygen)
+    .arg("-R")
+    .arg(host_pattern(&host, port))
+    .arg("-f")
+    .arg(&file)
+    .stdin(Stdio::null())
+    .output()
+    .map_err(|e| format!("Failed to run ssh-keygen: {}", e))?;
+  if !output.status.success() {
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    return Err(format!(
+      "Failed to remove {} from {}: {}",
+      host,
+      file.display(),
+      stderr.trim().lines().last().unwrap_or("ssh-keygen failed")
+    ));
+  }
+  info!("Removed {} from {}", host, file.display());
+  Ok(())
+}
+
+/// Scan `hosts` and trust the keys of those not known yet, in the
+/// installer's `known_hosts` and with `user_file` also in the user's.
+#[tauri::command]
+pub async fn seed_known_hosts(
+  app: AppHandle,
+  hosts: Vec<KnownHostTarget>,
+  user_file: Option<bool>,
+) -> Result<Vec<SeededHost>, String> {
+  for target in &hosts {
+    host_keys::validate(&target.host)?;
+  }
+  let user_file = match user_file.unwrap_or(false) {
+    true => Some(user_known_hosts(&app)?),
+    false => None,
+  };
+  tauri::async_runtime::spawn_blocking(move || {
+    let store = app.state::<HostKeys>();
+    let store: &HostKeys = &store;
+    let user_file = user_file.as_ref();
+
+    std::thread::scope(|scope| {
+      let handles: Vec<_> = hosts
+        .iter()
+        .map(|target| {
+          scope.spawn(move || {
+            let port = target.port.unwrap_or(22);
+            let mut seeded = SeededHost {
+              host: target.host.clone(),
+              port,
+              status: None,
+              added: 0,
+              added_to_user_file: 0,
+              error: None,
+            };
+            let offered = match host_keys::scan(&target.host, port) {
+              Ok(offered) => offered,
+              Err(e) => {
+                seeded.error = Some(e);
+                return seeded;
+              }
+            };
+            let trusted = store.trusted(&target.host, port);
+            let status = host_keys::status(&trusted, &offered);
+            seeded.status = Some(status);
+            // Only first contact is trusted unconfirmed
+            if status != KeyStatus::Unknown {
+              return seeded;
+            }
+            let result = store.add(&target.host, port, offered.clone(), false).and_then(|added| {
+              seeded.added = added.len();
+              match user_file {
+                Some(file) => add_to_user_file(file, &target.host, port, &offered),
+                None => Ok(0),
+              }
+            });
+            match result {
+              Ok(added) => seeded.added_to_user_file = added,
+              Err(e) => seeded.error = Some(e),
+            }
+            seeded
+          })
+        })
+        .collect();
+      handles.into_iter().filter_map(|h| h.join().ok()).collect()
+    })
+  })
+  .await
+  .map_err(|e| e.to_string())
+}
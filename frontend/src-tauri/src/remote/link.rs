@@ -0,0 +1,344 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Negotiated link speed and duplex of the cluster interface on each node.
+//!
+//! A node plugged into a bad cable or a 100 Mbit port slows every image
+//! pull and volume replica that goes through it, and nothing fails
+//! outright. `check_link_speed` reads the speed and duplex the kernel
+//! negotiated for the selected interface (the one with the default route
+//! when none is given) from sysfs, or the members' for a bond or bridge,
+//! and the bitrate `iw` reports for Wi-Fi. A node below the threshold, at
+//! half duplex, or slower than the rest is flagged by name.
+//!
+//! A Wi-Fi interface is a warning on a worker, but fails the check on a
+//! node that carries control-plane or storage traffic: etcd elections and
+//! volume replicas don't survive the latency spikes and dropouts. The user
+//! can still allow it for that node with `allow_wireless`.
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+use super::inventory::sections;
+use super::ssh::{self, shell_quote, SshPool, SshTarget};
+use crate::preflight::Outcome;
+use crate::validation;
+use crate::workspace::Workspace;
+
+const SCAN_TIMEOUT: Duration = Duration::from_secs(30);
+pub const DEFAULT_MIN_SPEED_MBPS: u32 = 1000;
+
+/// `{interface}` is shell quoted, empty for the default route's.
+const SCAN_SCRIPT: &str = r#"
+export LC_ALL=C
+iface={interface}
+[ -n "$iface" ] || iface=$(ip -o route show default 2>/dev/null | sed -n 's/.* dev \([^ ]*\).*/\1/p' | head -n 1)
+link() {
+  d=/sys/class/net/$1
+  echo "$1 $(cat "$d/operstate" 2>/dev/null || echo -) $(cat "$d/speed" 2>/dev/null || echo -) $(cat "$d/duplex" 2>/dev/null || echo -)"
+}
+echo '@@link'
+[ -n "$iface" ] && [ -d "/sys/class/net/$iface" ] && link "$iface"
+echo '@@members'
+for m in $(cat "/sys/class/net/$iface/bonding/slaves" 2>/dev/null) $(ls "/sys/class/net/$iface/brif" 2>/dev/null); do
+  link "$m"
+done
+echo '@@wireless'
+if [ -d "/sys/class/net/$iface/wireless" ]; then
+  iw dev "$iface" link 2>/dev/null | sed -n 's/.*tx bitrate: \([0-9.]*\) MBit.*/\1/p' || true
+  echo wireless
+fi
+echo '@@end'
+"#;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LinkTarget {
+  #[serde(flatten)]
+  pub target: SshTarget,
+  /// The cluster interface; the default route's when not set
+  pub interface: Option<String>,
+  /// Runs a control-plane (etcd) member
+  #[serde(default)]
+  pub control_plane: bool,
+  /// Holds storage replicas
+  #[serde(default)]
+  pub storage: bool,
+  /// The user accepted that this node's cluster traffic goes over Wi-Fi
+  #[serde(default)]
+  pub allow_wireless: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LinkState {
+  pub interface: String,
+  /// `up`, `down`, `unknown`…
+  pub operstate: String,
+  pub speed_mbps: Option<u32>,
+  /// `full` or `half`
+  pub duplex: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LinkStatus {
+  pub link: LinkState,
+  /// Bond slaves or bridge ports, whose speed counts when the interface
+  /// itself has none
+  pub members: Vec<LinkState>,
+  pub wireless: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeLink {
+  pub host: String,
+  pub status: Option<LinkStatus>,
+  pub outcome: Outcome,
+  pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LinkReport {
+  pub nodes: Vec<NodeLink>,
+  pub min_speed_mbps: u32,
+  /// Set when the nodes didn't all negotiate the same speed
+  pub mismatch: Option<String>,
+}
+
+/// The scan script for `interface`, or the default route's.
+pub fn script(interface: Option<&str>) -> String {
+  SCAN_SCRIPT.replace("{interface}", &shell_quote(interface.unwrap_or_default()))
+}
+
+fn parse_state(line: &str) -> Option<LinkState> {
+  let fields: Vec<&str> = line.split_whitespace().collect();
+  let [interface, operstate, speed, duplex] = fields[..] else {
+    return None;
+  };
+  Some(LinkState {
+    interface: interface.to_string(),
+    operstate: operstate.to_string(),
+    // -1 or a read error when the link is down or the driver doesn't say
+    speed_mbps: speed.parse::<i64>().ok().filter(|s| *s > 0).map(|s| s as u32),
+    duplex: matches!(duplex, "full" | "half").then(|| duplex.to_string()),
+  })
+}
+
+pub fn parse(output: &str) -> Option<LinkStatus> {
+  let sections = sections(output);
+  let empty = Vec::new();
+  let section = |name: &str| {
+    sections
+      .get(name)
+      .unwrap_or(&empty)
+      .iter()
+      .map(|l| l.trim())
+      .filter(|l| !l.is_empty())
+      .collect::<Vec<_>>()
+  };
+  let mut link = section("link").into_iter().find_map(parse_state)?;
+  let wireless = section("wireless");
+  if link.speed_mbps.is_none() {
+    link.speed_mbps = wireless
+      .first()
+      .and_then(|rate| rate.parse::<f64>().ok())
+      .map(|rate| rate as u32);
+  }
+  Some(LinkStatus {
+    link,
+    members: section("members").into_iter().filter_map(parse_state).collect(),
+    wireless: wireless.contains(&"wireless"),
+  })
+}
+
+impl LinkStatus {
+  /// The speed traffic gets: the interface's own, or for a bond or bridge
+  /// without one, its slowest member that is up.
+  pub fn speed_mbps(&self) -> Option<u32> {
+    self.link.speed_mbps.or_else(|| {
+      self
+        .members
+        .iter()
+        .filter(|m| m.operstate == "up")
+        .filter_map(|m| m.speed_mbps)
+        .min()
+    })
+  }
+
+  pub fn verdict(&self, min_speed_mbps: u32) -> (Outcome, String) {
+    let name = &self.link.interface;
+    if self.link.operstate == "down" {
+      return (Outcome::Fail, format!("{} is down", name));
+    }
+    let half: Vec<&str> = std::iter::once(&self.link)
+      .chain(&self.members)
+      .filter(|l| l.duplex.as_deref() == Some("half"))
+      .map(|l| l.interface.as_str())
+      .collect();
+    if !half.is_empty() {
+      return (
+        Outcome::Warn,
+        format!(
+          "{} negotiated half duplex; check the cable and the switch port",
+          half.join(", ")
+        ),
+      );
+    }
+    let Some(speed) = self.speed_mbps() else {
+      return (
+        Outcome::Skipped,
+        format!("The driver of {} doesn't report a link speed", name),
+      );
+    };
+    if speed < min_speed_mbps {
+      let hint = match self.wireless {
+        true => "use a wired connection",
+        false => "check the cable and the switch port",
+      };
+      return (
+        Outcome::Warn,
+        format!(
+          "{} links at {} Mbit/s, below {} Mbit/s; {}",
+          name, speed, min_speed_mbps, hint
+        ),
+      );
+    }
+    (Outcome::Pass, format!("{} links at {} Mbit/s full duplex", name, speed))
+  }
+
+  /// What Wi-Fi on the cluster interface means for a node carrying
+  /// `traffic` (e.g. `control-plane`); `None` for a wired one.
+  pub fn wireless_verdict(&self, traffic: &[&str], allowed: bool) -> Option<(Outcome, String)> {
+    if !self.wireless {
+      return None;
+    }
+    let name = &self.link.interface;
+    if traffic.is_empty() {
+      return Some((
+        Outcome::Warn,
+        format!(
+          "{} is a Wi-Fi interface; traffic to this node will be slow and drop out",
+          name
+        ),
+      ));
+    }
+    let traffic = traffic.join(" and ");
+    Some(match allowed {
+      true => (
+        Outcome::Warn,
+        format!("{} carries {} traffic over Wi-Fi, as allowed", name, traffic),
+      ),
+      false => (
+        Outcome::Fail,
+        format!(
+          "{} would carry {} traffic over Wi-Fi; pick a wired interface, or allow Wi-Fi for this node",
+          name, traffic
+        ),
+      ),
+    })
+  }
+}
+
+/// The nodes slower than the fastest one, if any.
+fn mismatch(nodes: &[NodeLink]) -> Option<String> {
+  let speeds: Vec<(&str, u32)> = nodes
+    .iter()
+    .filter_map(|n| Some((n.host.as_str(), n.status.as_ref()?.speed_mbps()?)))
+    .collect();
+  let fastest = speeds.iter().map(|(_, s)| *s).max()?;
+  let slower: Vec<String> = speeds
+    .iter()
+    .filter(|(_, s)| *s < fastest)
+    .map(|(host, s)| format!("{} ({} Mbit/s)", host, s))
+    .collect();
+  if slower.is_empty() {
+    return None;
+  }
+  Some(format!(
+    "{} link slower than the other nodes' {} Mbit/s",
+    slower.join(", "),
+    fastest
+  ))
+}
+
+/// Report the link speed and duplex of the cluster interface on each node
+/// and flag nodes below `min_speed_mbps` (1000 by default) or slower than
+/// the rest.
+#[tauri::command]
+pub async fn check_link_speed(
+  app: AppHandle,
+  hosts: Vec<LinkTarget>,
+  min_speed_mbps: Option<u32>,
+) -> Result<LinkReport, String> {
+  for host in &hosts {
+    host.target.validate()?;
+    if let Some(interface) = host.interface.as_deref().filter(|i| !validation::is_valid_interface(i)) {
+      return Err(format!("Invalid interface name: {}", interface));
+    }
+  }
+  let min_speed_mbps = min_speed_mbps.unwrap_or(DEFAULT_MIN_SPEED_MBPS);
+  tauri::async_runtime::spawn_blocking(move || {
+    let workspace = app.state::<Workspace>();
+    let workspace: &Workspace = &workspace;
+    let pool = app.state::<SshPool>();
+    let pool: &SshPool = &pool;
+
+    let nodes: Vec<NodeLink> = std::thread::scope(|scope| {
+      let handles: Vec<_> = hosts
+        .iter()
+        .map(|host| {
+          scope.spawn(move || {
+            let script = script(host.interface.as_deref());
+            let output = ssh::run_script(workspace, pool, &host.target, &script, SCAN_TIMEOUT)?;
+            if !output.stdout.contains("@@end") {
+              return Err(format!("Scan did not complete: {}", output.stderr.trim()));
+            }
+            parse(&output.stdout).ok_or_else(|| match &host.interface {
+              Some(interface) => format!("No interface named {}", interface),
+              None => "No default route to find the cluster interface by".to_string(),
+            })
+          })
+        })
+        .collect();
+      handles
+        .into_iter()
+        .zip(&hosts)
+        .map(|(handle, host)| {
+          let result = handle.join().unwrap_or_else(|_| Err("Scan panicked".to_string()));
+          match result {
+            Ok(status) => {
+              let traffic: Vec<&str> = [(host.control_plane, "control-plane"), (host.storage, "storage")]
+                .into_iter()
+                .filter_map(|(carries, name)| carries.then_some(name))
+                .collect();
+              let (outcome, message) = status
+                .wireless_verdict(&traffic, host.allow_wireless)
+                .unwrap_or_else(|| status.verdict(min_speed_mbps));
+              NodeLink {
+                host: host.target.host.clone(),
+                status: Some(status),
+                outcome,
+                message,
+              }
+            }
+            Err(e) => NodeLink {
+              host: host.target.host.clone(),
+              status: None,
+              outcome: Outcome::Skipped,
+              message: format!("Skipped: {}", e),
+            },
+          }
+        })
+        .collect()
+    });
+
+    LinkReport {
+      mismatch: mismatch(&nodes),
+      nodes,
+      min_speed_mbps,
+    }
+  })
+  .await
+  .map_err(|e| e.to_string())
+}
@@ -0,0 +1,192 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Following a node's logs from the installer.
+//!
+//! `start_log_stream` runs `journalctl -f` for a unit (kubelet, containerd,
+//! k8s services) or `tail -F` on a file on the node through [`ssh::run`],
+//! so every line arrives as a `remote-output` event under the stream id
+//! and the connection is the pooled one. The stream runs until
+//! `stop_log_stream`, the node closing it, or the longest run time a
+//! remote command may have; a `log-stream` event says when it ends and
+//! why. Several streams can run at once, on one node or several.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tracing::info;
+
+use super::ssh::{self, shell_quote, RunOptions, SshPool, SshTarget};
+
+pub const EVENT: &str = "log-stream";
+
+/// Lines of history shown before following
+const DEFAULT_LINES: u32 = 200;
+const MAX_LINES: u32 = 10_000;
+/// Effectively until stopped; `ssh::run` caps it
+const STREAM_TIMEOUT_SECS: u64 = u64::MAX;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LogSource {
+  /// `journalctl -f -u <unit>`
+  Unit { unit: String },
+  /// `tail -F <path>`
+  File { path: String },
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct LogStreamOptions {
+  /// History to show first; defaults to 200 lines
+  pub lines: Option<u32>,
+  /// Read through sudo, for files and journals the user can't read
+  #[serde(default)]
+  pub sudo: bool,
+  pub sudo_password: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LogStream {
+  pub stream_id: String,
+  pub host: String,
+  pub source: LogSource,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "phase", rename_all = "snake_case")]
+pub enum LogStreamEvent {
+  Started {
+    #[serde(flatten)]
+    stream: LogStream,
+  },
+  Ended {
+    stream_id: String,
+    /// `None` when stopped or when the command didn't exit by itself
+    exit_code: Option<i32>,
+    stopped: bool,
+    error: Option<String>,
+  },
+}
+
+#[derive(Default)]
+pub struct LogStreams {
+  active: Mutex<HashMap<String, LogStream>>,
+}
+
+fn command(source: &LogSource, lines: u32) -> Result<String, String> {
+  match source {
+    LogSource::Unit { unit } => {
+      if unit.is_empty()
+        || !unit
+          .chars()
+          .all(|c| c.is_ascii_alphanumeric() || matches!(c, '@' | '.' | '_' | ':' | '-'))
+      {
+        return Err(format!("Invalid unit name: {}", unit));
+      }
+      Ok(format!(
+        "journalctl --no-pager -o short-iso -n {} -f -u {}",
+        lines,
+        shell_quote(unit)
+      ))
+    }
+    LogSource::File { path } => {
+      if !path.starts_with('/') || path.contains('\0') || path.split('/').any(|part| part == "..") {
+        return Err(format!("Invalid log file path: {}", path));
+      }
+      Ok(format!("tail -n {} -F -- {}", lines, shell_quote(path)))
+    }
+  }
+}
+
+/// Follow `source` on `host`, streaming its lines as `remote-output` events
+/// tagged `stream_id`. Returns once the stream has started.
+#[tauri::command]
+pub fn start_log_stream(
+  app: AppHandle,
+  streams: State<'_, LogStreams>,
+  host: SshTarget,
+  source: LogSource,
+  stream_id: String,
+  options: Option<LogStreamOptions>,
+) -> Result<LogStream, String> {
+  host.validate()?;
+  if stream_id.trim().is_empty() {
+    return Err("No stream id given".to_string());
+  }
+  let options = options.unwrap_or_default();
+  let remote_command = command(&source, options.lines.unwrap_or(DEFAULT_LINES).min(MAX_LINES))?;
+  let stream = LogStream {
+    stream_id: stream_id.clone(),
+    host: host.host.clone(),
+    source,
+  };
+  {
+    let mut active = streams.active.lock().map_err(|e| e.to_string())?;
+    if active.contains_key(&stream_id) {
+      return Err(format!("A log stream with id {} is already running", stream_id));
+    }
+    active.insert(stream_id.clone(), stream.clone());
+  }
+  info!("Following {:?} on {} as {}", stream.source, host.host, stream_id);
+  let _ = app.emit(EVENT, LogStreamEvent::Started { stream: stream.clone() });
+
+  std::thread::spawn(move || {
+    let run = RunOptions {
+      run_id: Some(stream_id.clone()),
+      sudo: options.sudo,
+      sudo_password: options.sudo_password.filter(|_| options.sudo),
+      timeout_secs: Some(STREAM_TIMEOUT_SECS),
+      stall_secs: None,
+    };
+    let result = ssh::run(&app, &host, &remote_command, run);
+    if let Ok(mut active) = app.state::<LogStreams>().active.lock() {
+      active.remove(&stream_id);
+    }
+    let event = match result {
+      Ok(result) => LogStreamEvent::Ended {
+        stream_id: stream_id.clone(),
+        exit_code: result.exit_code,
+        stopped: result.cancelled,
+        error: None,
+      },
+      Err(e) => LogStreamEvent::Ended {
+        stream_id: stream_id.clone(),
+        exit_code: None,
+        stopped: false,
+        error: Some(e),
+      },
+    };
+    info!("Log stream {} ended", stream_id);
+    let _ = app.emit(EVENT, event);
+  });
+  Ok(stream)
+}
+
+#[tauri::command]
+pub fn stop_log_stream(
+  pool: State<'_, SshPool>,
+  streams: State<'_, LogStreams>,
+  stream_id: String,
+) -> Result<(), String> {
+  let known = streams
+    .active
+    .lock()
+    .map_err(|e| e.to_string())?
+    .contains_key(&stream_id);
+  if !known || !pool.cancel(&stream_id) {
+    return Err(format!("No log stream with id {} is running", stream_id));
+  }
+  Ok(())
+}
+
+#[tauri::command]
+pub fn list_log_streams(streams: State<'_, LogStreams>) -> Vec<LogStream> {
+  streams
+    .active
+    .lock()
+    .map(|active| active.values().cloned().collect())
+    .unwrap_or_default()
+}
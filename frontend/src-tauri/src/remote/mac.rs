@@ -0,0 +1,356 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! SELinux and AppArmor on the nodes.
+//!
+//! Ubuntu runs AppArmor, which Kubernetes and LXD cope with, but two
+//! configurations are known to break the install: SELinux enforcing (there
+//! is no policy for the k8s snap's containerd on Ubuntu), and the `runc`
+//! profile Ubuntu 24.04 ships, which denies the signals containerd sends to
+//! stop containers and leaves pods stuck terminating. `check_mac_policy`
+//! reports the enforcement mode and the container-related profiles on each
+//! node, with a hint for each known-bad setting; `apply_mac_adjustment`
+//! makes the suggested change, journaled so a rollback reverts it.
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tracing::info;
+
+use super::inventory::sections;
+use super::ssh::{self, SshPool, SshTarget};
+use crate::dry_run;
+use crate::journal::{self, Action};
+use crate::preflight::Outcome;
+use crate::workspace::Workspace;
+
+const SCAN_TIMEOUT: Duration = Duration::from_secs(30);
+const APPLY_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Profile names that confine container runtimes, LXD or the k8s snap
+const RELEVANT_PROFILES: &[&str] = &["runc", "crun", "containerd", "lxc", "lxd", "snap.k8s"];
+
+pub const SCAN_SCRIPT: &str = r#"
+export LC_ALL=C
+echo '@@selinux'
+if [ -f /sys/fs/selinux/enforce ]; then
+  if [ "$(cat /sys/fs/selinux/enforce)" = 1 ]; then echo enforcing; else echo permissive; fi
+  grep -E '^SELINUX=' /etc/selinux/config 2>/dev/null
+fi
+echo '@@apparmor'
+cat /sys/module/apparmor/parameters/enabled 2>/dev/null
+echo '@@profiles'
+sudo -n cat /sys/kernel/security/apparmor/profiles 2>/dev/null
+echo '@@end'
+"#;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Adjustment {
+  /// Switch SELinux to permissive, now and in `/etc/selinux/config`
+  SelinuxPermissive,
+  /// Unload AppArmor's `runc` profile and keep it from loading at boot
+  DisableRuncProfile,
+}
+
+impl Adjustment {
+  pub fn describe(self) -> &'static str {
+    match self {
+      Adjustment::SelinuxPermissive => "Set SELinux to permissive",
+      Adjustment::DisableRuncProfile => "Disable the AppArmor runc profile",
+    }
+  }
+
+  fn script(self) -> &'static str {
+    match self {
+      Adjustment::SelinuxPermissive => {
+        "set -e\n\
+         sudo -n setenforce 0\n\
+         [ -f /etc/selinux/config ] && sudo -n sed -i -E 's/^SELINUX=enforcing/SELINUX=permissive/' /etc/selinux/config\n\
+         true\n"
+      }
+      Adjustment::DisableRuncProfile => {
+        "set -e\n\
+         if [ -f /etc/apparmor.d/runc ]; then\n\
+           sudo -n ln -sf /etc/apparmor.d/runc /etc/apparmor.d/disable/runc\n\
+           sudo -n apparmor_parser -R /etc/apparmor.d/runc 2>/dev/null || true\n\
+         fi\n"
+      }
+    }
+  }
+
+  /// Script reversing the adjustment; harmless when it was never made.
+  pub fn undo_script(self) -> &'static str {
+    match self {
+      Adjustment::SelinuxPermissive => {
+        "set -e\n\
+         [ -f /etc/selinux/config ] && sudo -n sed -i -E 's/^SELINUX=permissive/SELINUX=enforcing/' /etc/selinux/config\n\
+         sudo -n setenforce 1\n"
+      }
+      Adjustment::DisableRuncProfile => {
+        "set -e\n\
+         if [ -L /etc/apparmor.d/disable/runc ]; then\n\
+           sudo -n rm -f /etc/apparmor.d/disable/runc\n\
+           sudo -n apparmor_parser -r /etc/apparmor.d/runc\n\
+         fi\n"
+      }
+    }
+  }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Profile {
+  pub name: String,
+  /// `enforce`, `complain`, `kill` or `unconfined`
+  pub mode: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MacFinding {
+  pub outcome: Outcome,
+  pub message: String,
+  /// What to do about it
+  pub hint: Option<String>,
+  /// The change `apply_mac_adjustment` can make for it
+  pub adjustment: Option<Adjustment>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MacStatus {
+  /// `enforcing` or `permissive`; `None` without SELinux
+  pub selinux: Option<String>,
+  /// The mode set in `/etc/selinux/config`, used from the next boot
+  pub selinux_config: Option<String>,
+  pub apparmor: bool,
+  pub enforced_profiles: usize,
+  pub complain_profiles: usize,
+  /// Profiles for container runtimes, LXD and the k8s snap
+  pub relevant_profiles: Vec<Profile>,
+  pub findings: Vec<MacFinding>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeMacPolicy {
+  pub host: String,
+  pub status: Option<MacStatus>,
+  pub error: Option<String>,
+}
+
+impl MacStatus {
+  /// The worst finding's outcome.
+  pub fn outcome(&self) -> Outcome {
+    let rank = |o: Outcome| match o {
+      Outcome::Fail => 3,
+      Outcome::Warn => 2,
+      Outcome::Skipped => 1,
+      Outcome::Pass => 0,
+    };
+    self
+      .findings
+      .iter()
+      .map(|f| f.outcome)
+      .max_by_key(|o| rank(*o))
+      .unwrap_or(Outcome::Pass)
+  }
+
+  /// One line for the preflight matrix.
+  pub fn summary(&self) -> String {
+    let mut parts = Vec::new();
+    if let Some(mode) = &self.selinux {
+      parts.push(format!("SELinux {}", mode));
+    }
+    if self.apparmor {
+      parts.push(format!(
+        "AppArmor with {} enforced, {} complaining profile(s)",
+        self.enforced_profiles, self.complain_profiles
+      ));
+    }
+    if parts.is_empty() {
+      parts.push("No SELinux or AppArmor".to_string());
+    }
+    let problems: Vec<&str> = self
+      .findings
+      .iter()
+      .filter(|f| f.outcome != Outcome::Pass)
+      .map(|f| f.message.as_str())
+      .collect();
+    if problems.is_empty() {
+      parts.join("; ")
+    } else {
+      format!("{}; {}", parts.join("; "), problems.join("; "))
+    }
+  }
+}
+
+pub fn parse(output: &str) -> MacStatus {
+  let sections = sections(output);
+  let empty = Vec::new();
+  let section = |name: &str| sections.get(name).unwrap_or(&empty);
+
+  let selinux_lines = section("selinux");
+  let selinux = selinux_lines
+    .first()
+    .map(|l| l.trim().to_string())
+    .filter(|l| !l.is_empty());
+  let selinux_config = selinux_lines
+    .iter()
+    .find_map(|l| l.trim().strip_prefix("SELINUX="))
+    .map(|v| v.trim().to_ascii_lowercase());
+  let apparmor = section("apparmor").first().is_some_and(|l| l.trim() == "Y");
+
+  let profiles: Vec<Profile> = section("profiles")
+    .iter()
+    .filter_map(|line| {
+      let (name, mode) = line.trim().rsplit_once(" (")?;
+      Some(Profile {
+        name: name.to_string(),
+        mode: mode.trim_end_matches(')').to_string(),
+      })
+    })
+    .collect();
+  let relevant_profiles: Vec<Profile> = profiles
+    .iter()
+    .filter(|p| RELEVANT_PROFILES.iter().any(|r| p.name.contains(r)))
+    .cloned()
+    .collect();
+
+  let mut findings = Vec::new();
+  match (selinux.as_deref(), selinux_config.as_deref()) {
+    (Some("enforcing"), _) => findings.push(MacFinding {
+      outcome: Outcome::Fail,
+      message: "SELinux is enforcing".to_string(),
+      hint: Some(
+        "There is no SELinux policy for the k8s snap's containerd on Ubuntu; set SELinux to permissive".to_string(),
+      ),
+      adjustment: Some(Adjustment::SelinuxPermissive),
+    }),
+    (Some(_), Some("enforcing")) => findings.push(MacFinding {
+      outcome: Outcome::Warn,
+      message: "SELinux is permissive now but enforcing after a reboot".to_string(),
+      hint: Some("Set SELINUX=permissive in /etc/selinux/config".to_string()),
+      adjustment: Some(Adjustment::SelinuxPermissive),
+    }),
+    _ => {}
+  }
+  if relevant_profiles
+    .iter()
+    .any(|p| p.name == "runc" && p.mode == "enforce")
+  {
+    findings.push(MacFinding {
+      outcome: Outcome::Warn,
+      message: "AppArmor's runc profile is enforced".to_string(),
+      hint: Some(
+        "It denies the signals containerd sends to stop containers, leaving pods stuck terminating; disable it"
+          .to_string(),
+      ),
+      adjustment: Some(Adjustment::DisableRuncProfile),
+    });
+  }
+  if apparmor && profiles.is_empty() {
+    findings.push(MacFinding {
+      outcome: Outcome::Warn,
+      message: "Cannot list AppArmor profiles".to_string(),
+      hint: Some("Reading them needs passwordless sudo".to_string()),
+      adjustment: None,
+    });
+  }
+
+  MacStatus {
+    selinux,
+    selinux_config,
+    apparmor,
+    enforced_profiles: profiles.iter().filter(|p| p.mode == "enforce").count(),
+    complain_profiles: profiles.iter().filter(|p| p.mode == "complain").count(),
+    relevant_profiles,
+    findings,
+  }
+}
+
+/// Report SELinux and AppArmor status on each node.
+#[tauri::command]
+pub async fn check_mac_policy(app: AppHandle, hosts: Vec<SshTarget>) -> Result<Vec<NodeMacPolicy>, String> {
+  for target in &hosts {
+    target.validate()?;
+  }
+  tauri::async_runtime::spawn_blocking(move || {
+    let workspace = app.state::<Workspace>();
+    let workspace: &Workspace = &workspace;
+    let pool = app.state::<SshPool>();
+    let pool: &SshPool = &pool;
+
+    std::thread::scope(|scope| {
+      let handles: Vec<_> = hosts
+        .iter()
+        .map(|target| {
+          scope.spawn(move || {
+            let output = ssh::run_script(workspace, pool, target, SCAN_SCRIPT, SCAN_TIMEOUT)?;
+            if !output.stdout.contains("@@end") {
+              return Err(format!("Scan did not complete: {}", output.stderr.trim()));
+            }
+            Ok(parse(&output.stdout))
+          })
+        })
+        .collect();
+      handles
+        .into_iter()
+        .zip(&hosts)
+        .map(|(handle, target)| {
+          let result = handle.join().unwrap_or_else(|_| Err("Scan panicked".to_string()));
+          NodeMacPolicy {
+            host: target.host.clone(),
+            error: result.as_ref().err().cloned(),
+            status: result.ok(),
+          }
+        })
+        .collect()
+    })
+  })
+  .await
+  .map_err(|e| e.to_string())
+}
+
+/// Make the change suggested for a finding on the node.
+#[tauri::command]
+pub async fn apply_mac_adjustment(app: AppHandle, host: SshTarget, adjustment: Adjustment) -> Result<(), String> {
+  host.validate()?;
+  if dry_run::is_enabled(&app) {
+    info!(
+      "Dry run: would {} on {}",
+      adjustment.describe().to_lowercase(),
+      host.host
+    );
+    return Ok(());
+  }
+  tauri::async_runtime::spawn_blocking(move || {
+    let output = ssh::run_script(
+      &app.state::<Workspace>(),
+      &app.state::<SshPool>(),
+      &host,
+      adjustment.script(),
+      APPLY_TIMEOUT,
+    )?;
+    if output.status != Some(0) {
+      let detail = output
+        .stderr
+        .trim()
+        .lines()
+        .last()
+        .unwrap_or("command failed")
+        .to_string();
+      return Err(format!("{} failed on {}: {}", adjustment.describe(), host.host, detail));
+    }
+    info!("{} on {}", adjustment.describe(), host.host);
+    journal::record(
+      &app,
+      Action::MacAdjustment {
+        target: host,
+        adjustment,
+      },
+    );
+    Ok(())
+  })
+  .await
+  .map_err(|e| e.to_string())?
+}
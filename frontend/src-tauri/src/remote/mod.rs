@@ -0,0 +1,35 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Talking to cluster nodes from the installer host.
+
+pub mod agent;
+pub mod apt_lock;
+pub mod bench;
+pub mod board;
+pub mod conflicts;
+pub mod copy_id;
+pub mod disks;
+pub mod firewall;
+pub mod host_keys;
+pub mod hostname;
+pub mod inventory;
+pub mod ipv6;
+pub mod known_hosts;
+pub mod link;
+pub mod logs;
+pub mod mac;
+pub mod mtu;
+pub mod netplan;
+pub mod nvidia;
+pub mod passthrough;
+pub mod sftp;
+pub mod smart;
+pub mod ssh;
+pub mod storage;
+pub mod sudoers;
+pub mod swap;
+pub mod tuning;
+pub mod vmplan;
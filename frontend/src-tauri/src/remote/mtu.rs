@@ -0,0 +1,267 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! The path MTU between the nodes, and the MTU the CNI should use.
+//!
+//! A packet bigger than some link on the way (PPPoE, a VPN, a switch
+//! without jumbo frames) is dropped once the CNI's encapsulation headers
+//! are added, which shows up as pod traffic that works for small requests
+//! and hangs on large ones. `probe_path_mtu` has every node ping every
+//! other one with fragmentation forbidden, narrowing the payload size
+//! down to the largest that gets through, in both directions. The
+//! smallest path MTU minus the encapsulation overhead is the MTU to give
+//! the CNI. A path whose MTU differs by direction, or an interface set
+//! larger than what gets through, is flagged: one end believes it can
+//! send packets the other end never sees.
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+use super::inventory::sections;
+use super::ssh::{self, shell_quote, SshPool, SshTarget};
+use crate::preflight::Outcome;
+use crate::workspace::Workspace;
+
+const BASE_TIMEOUT: Duration = Duration::from_secs(30);
+/// A search takes about a dozen pings, each waiting up to a second
+const PER_PEER_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// `{peers}` is a list of shell-quoted hosts. Prints `peer dev mtu pmtu`
+/// per peer, with `-` for what couldn't be found; 28 bytes of IPv4 and
+/// ICMP headers go on top of the ping payload.
+const PROBE_SCRIPT: &str = r#"
+export LC_ALL=C
+fits() { ping -M do -c 1 -W 1 -s "$2" "$1" >/dev/null 2>&1; }
+echo '@@paths'
+for peer in {peers}; do
+  dev=$(ip -o route get "$peer" 2>/dev/null | sed -n 's/.* dev \([^ ]*\).*/\1/p')
+  mtu=$(cat "/sys/class/net/$dev/mtu" 2>/dev/null || echo -)
+  if ! fits "$peer" 56 && ! fits "$peer" 56; then
+    echo "$peer ${dev:--} $mtu -"
+    continue
+  fi
+  lo=548
+  case "$mtu" in ''|-|*[!0-9]*) hi=8972 ;; *) hi=$((mtu - 28)) ;; esac
+  if fits "$peer" "$hi"; then
+    lo=$hi
+  else
+    while [ $((hi - lo)) -gt 1 ]; do
+      mid=$(((lo + hi) / 2))
+      if fits "$peer" "$mid"; then lo=$mid; else hi=$mid; fi
+    done
+  fi
+  echo "$peer ${dev:--} $mtu $((lo + 28))"
+done
+echo '@@end'
+"#;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Encapsulation {
+  #[default]
+  Vxlan,
+  Geneve,
+  Wireguard,
+  /// Native routing, no extra headers
+  None,
+}
+
+impl Encapsulation {
+  /// Bytes the CNI adds to each packet.
+  pub fn overhead(self) -> u32 {
+    match self {
+      Encapsulation::Vxlan | Encapsulation::Geneve => 50,
+      Encapsulation::Wireguard => 80,
+      Encapsulation::None => 0,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PathMtu {
+  pub from: String,
+  pub to: String,
+  /// The interface `from` sends through
+  pub interface: Option<String>,
+  pub interface_mtu: Option<u32>,
+  /// `None` when `to` didn't answer at all
+  pub path_mtu: Option<u32>,
+  pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MtuFinding {
+  pub outcome: Outcome,
+  pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MtuReport {
+  pub paths: Vec<PathMtu>,
+  pub findings: Vec<MtuFinding>,
+  pub min_path_mtu: Option<u32>,
+  /// What to set as the CNI MTU for `encapsulation`
+  pub suggested_mtu: Option<u32>,
+  pub encapsulation: Encapsulation,
+}
+
+fn parse(from: &str, output: &str) -> Vec<PathMtu> {
+  let sections = sections(output);
+  let number = |v: &str| v.parse::<u32>().ok();
+  sections
+    .get("paths")
+    .map(|lines| {
+      lines
+        .iter()
+        .filter_map(|line| {
+          let fields: Vec<&str> = line.split_whitespace().collect();
+          let [to, interface, mtu, path_mtu] = fields[..] else {
+            return None;
+          };
+          let path_mtu = number(path_mtu);
+          Some(PathMtu {
+            from: from.to_string(),
+            to: to.to_string(),
+            interface: (interface != "-").then(|| interface.to_string()),
+            interface_mtu: number(mtu),
+            error: path_mtu.is_none().then(|| format!("{} did not answer pings", to)),
+            path_mtu,
+          })
+        })
+        .collect()
+    })
+    .unwrap_or_default()
+}
+
+fn probe(workspace: &Workspace, pool: &SshPool, target: &SshTarget, peers: &[&str]) -> Result<Vec<PathMtu>, String> {
+  let script = PROBE_SCRIPT.replace(
+    "{peers}",
+    &peers.iter().map(|p| shell_quote(p)).collect::<Vec<_>>().join(" "),
+  );
+  let timeout = BASE_TIMEOUT + PER_PEER_TIMEOUT * peers.len() as u32;
+  let output = ssh::run_script(workspace, pool, target, &script, timeout)?;
+  if !output.stdout.contains("@@end") {
+    return Err(format!("Probe did not complete: {}", output.stderr.trim()));
+  }
+  Ok(parse(&target.host, &output.stdout))
+}
+
+fn findings(paths: &[PathMtu]) -> Vec<MtuFinding> {
+  let mut findings = Vec::new();
+  for (index, path) in paths.iter().enumerate() {
+    if let Some(error) = &path.error {
+      findings.push(MtuFinding {
+        outcome: Outcome::Skipped,
+        message: format!("{} → {}: {}", path.from, path.to, error),
+      });
+      continue;
+    }
+    let Some(path_mtu) = path.path_mtu else {
+      continue;
+    };
+    if let Some(mtu) = path.interface_mtu.filter(|mtu| *mtu > path_mtu) {
+      findings.push(MtuFinding {
+        outcome: Outcome::Warn,
+        message: format!(
+          "{} has MTU {} on {} but only {} bytes get through to {}; something on the way doesn't pass frames that large",
+          path.from,
+          mtu,
+          path.interface.as_deref().unwrap_or("its interface"),
+          path_mtu,
+          path.to
+        ),
+      });
+    }
+    // Each pair once, from the side listed first
+    let reverse = paths
+      .iter()
+      .skip(index + 1)
+      .find(|p| p.from == path.to && p.to == path.from)
+      .and_then(|p| p.path_mtu);
+    if let Some(reverse_mtu) = reverse.filter(|mtu| *mtu != path_mtu) {
+      findings.push(MtuFinding {
+        outcome: Outcome::Warn,
+        message: format!(
+          "The path MTU differs by direction: {} from {} to {}, {} back",
+          path_mtu, path.from, path.to, reverse_mtu
+        ),
+      });
+    }
+  }
+  if findings.is_empty() && !paths.is_empty() {
+    findings.push(MtuFinding {
+      outcome: Outcome::Pass,
+      message: "The path MTU is the same in both directions between every pair of nodes".to_string(),
+    });
+  }
+  findings
+}
+
+/// Measure the path MTU between every pair of `hosts` and suggest the
+/// CNI MTU for `encapsulation` (VXLAN by default).
+#[tauri::command]
+pub async fn probe_path_mtu(
+  app: AppHandle,
+  hosts: Vec<SshTarget>,
+  encapsulation: Option<Encapsulation>,
+) -> Result<MtuReport, String> {
+  for target in &hosts {
+    target.validate()?;
+  }
+  if hosts.len() < 2 {
+    return Err("At least two nodes are needed to measure the path MTU".to_string());
+  }
+  let encapsulation = encapsulation.unwrap_or_default();
+  tauri::async_runtime::spawn_blocking(move || {
+    let workspace = app.state::<Workspace>();
+    let workspace: &Workspace = &workspace;
+    let pool = app.state::<SshPool>();
+    let pool: &SshPool = &pool;
+    let names: Vec<&str> = hosts.iter().map(|t| t.host.as_str()).collect();
+    let names = &names;
+
+    let paths: Vec<PathMtu> = std::thread::scope(|scope| {
+      let handles: Vec<_> = hosts
+        .iter()
+        .map(|target| {
+          scope.spawn(move || {
+            let peers: Vec<&str> = names.iter().copied().filter(|h| *h != target.host).collect();
+            (target, peers.clone(), probe(workspace, pool, target, &peers))
+          })
+        })
+        .collect();
+      handles
+        .into_iter()
+        .filter_map(|h| h.join().ok())
+        .flat_map(|(target, peers, result)| match result {
+          Ok(paths) => paths,
+          Err(e) => peers
+            .into_iter()
+            .map(|peer| PathMtu {
+              from: target.host.clone(),
+              to: peer.to_string(),
+              interface: None,
+              interface_mtu: None,
+              path_mtu: None,
+              error: Some(e.clone()),
+            })
+            .collect(),
+        })
+        .collect()
+    });
+
+    let min_path_mtu = paths.iter().filter_map(|p| p.path_mtu).min();
+    MtuReport {
+      findings: findings(&paths),
+      suggested_mtu: min_path_mtu.map(|mtu| mtu.saturating_sub(encapsulation.overhead())),
+      min_path_mtu,
+      paths,
+      encapsulation,
+    }
+  })
+  .await
+  .map_err(|e| e.to_string())
+}
@@ -0,0 +1,675 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Bridging a node's uplink for VMs.
+//!
+//! VM hosts need their primary interface enslaved to a bridge (`br0`) with
+//! the node's addresses moved onto the bridge. Getting this wrong by hand
+//! cuts the node off, so it's done in two steps:
+//!
+//! - `plan_bridge` reads `/etc/netplan`, finds the file defining the
+//!   interface that carries the default route, and returns the rewritten
+//!   file with a unified diff for the user to confirm. Nothing is changed.
+//! - `apply_bridge` writes the confirmed file, arms a systemd timer that
+//!   restores the backup and re-applies it, then runs `netplan apply`. Once
+//!   a fresh SSH connection gets through, the timer is disarmed. If the node
+//!   never comes back, the timer puts the old configuration back, the same
+//!   way `netplan try` reverts when nobody confirms.
+//!
+//! The file is re-serialized, so comments in it are lost; the diff shows
+//! exactly what will be written.
+
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+use tracing::info;
+
+use super::ssh::{self, shell_quote, SshPool, SshTarget};
+use crate::dry_run;
+use crate::journal::{self, Action};
+use crate::validation;
+use crate::workspace::Workspace;
+
+const DEFAULT_BRIDGE: &str = "br0";
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+const DEFAULT_ROLLBACK_SECS: u64 = 120;
+const MIN_ROLLBACK_SECS: u64 = 60;
+const MAX_ROLLBACK_SECS: u64 = 600;
+const ROLLBACK_UNIT: &str = "thinkube-netplan-rollback";
+/// Appended to the netplan file's path for the copy kept before a change
+pub const BACKUP_SUFFIX: &str = ".thinkube-bak";
+const DIFF_CONTEXT: usize = 3;
+
+/// Interface sections whose members can be bridged
+const BRIDGEABLE: &[&str] = &["ethernets", "bonds"];
+
+/// Layer 3 settings that move from the interface to the bridge
+const MOVED_KEYS: &[&str] = &[
+  "addresses",
+  "dhcp4",
+  "dhcp6",
+  "dhcp4-overrides",
+  "dhcp6-overrides",
+  "dhcp-identifier",
+  "accept-ra",
+  "ipv6-privacy",
+  "link-local",
+  "gateway4",
+  "gateway6",
+  "routes",
+  "routing-policy",
+  "nameservers",
+];
+
+const READ_SCRIPT: &str = r#"
+export LC_ALL=C
+for f in /etc/netplan/*.yaml /etc/netplan/*.yml; do
+  [ -f "$f" ] || continue
+  echo "@@file $f"
+  sudo -n cat "$f" | base64 | tr -d '\n'
+  echo
+done
+echo '@@route'
+ip -o route show default 2>/dev/null
+ip -o -6 route show default 2>/dev/null
+echo '@@mac'
+for d in /sys/class/net/*; do
+  echo "${d##*/} $(cat "$d/address" 2>/dev/null)"
+done
+echo '@@end'
+"#;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct BridgeOptions {
+  /// Bridge name; `br0` when omitted
+  pub bridge: Option<String>,
+  /// netplan id of the interface to bridge; the one carrying the default
+  /// route when omitted
+  pub interface: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BridgePlan {
+  pub host: String,
+  /// The netplan file that will be rewritten
+  pub path: String,
+  pub bridge: String,
+  pub interface: String,
+  pub current: String,
+  pub proposed: String,
+  pub diff: String,
+  /// Static addresses moved to the bridge
+  pub addresses: Vec<String>,
+  /// The bridge gets its address over DHCP
+  pub dhcp: bool,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ApplyBridgeOptions {
+  /// How long the node has to come back before the old configuration is
+  /// restored
+  pub rollback_secs: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BridgeApplied {
+  pub bridge: String,
+  /// Addresses on the bridge after the change, as `ip` reports them
+  pub addresses: Vec<String>,
+  /// Where the previous file was kept
+  pub backup: String,
+}
+
+struct NetplanFile {
+  path: String,
+  contents: String,
+}
+
+struct NodeNetwork {
+  files: Vec<NetplanFile>,
+  default_devices: Vec<String>,
+  macs: Vec<(String, String)>,
+}
+
+fn parse_node(output: &str) -> Result<NodeNetwork, String> {
+  let mut network = NodeNetwork {
+    files: Vec::new(),
+    default_devices: Vec::new(),
+    macs: Vec::new(),
+  };
+  let mut section = "";
+  let mut path = None;
+  for line in output.lines() {
+    if let Some(name) = line.strip_prefix("@@") {
+      let (name, arg) = name.split_once(' ').unwrap_or((name, ""));
+      section = name;
+      path = (name == "file").then(|| arg.to_string());
+      if name == "end" {
+        break;
+      }
+      continue;
+    }
+    match section {
+      "file" => {
+        let Some(path) = path.take() else { continue };
+        let bytes = base64::engine::general_purpose::STANDARD
+          .decode(line.trim())
+          .map_err(|e| format!("Cannot read {}: {}", path, e))?;
+        if bytes.is_empty() {
+          return Err(format!("Cannot read {}; passwordless sudo is required", path));
+        }
+        let contents = String::from_utf8(bytes).map_err(|_| format!("{} is not valid UTF-8", path))?;
+        network.files.push(NetplanFile { path, contents });
+      }
+      "route" => {
+        let mut tokens = line.split_whitespace();
+        if tokens.by_ref().any(|t| t == "dev") {
+          if let Some(dev) = tokens.next() {
+            if !network.default_devices.iter().any(|d| d == dev) {
+              network.default_devices.push(dev.to_string());
+            }
+          }
+        }
+      }
+      "mac" => {
+        if let Some((dev, mac)) = line.split_once(' ') {
+          network.macs.push((dev.to_string(), mac.trim().to_string()));
+        }
+      }
+      _ => {}
+    }
+  }
+  Ok(network)
+}
+
+fn key(name: &str) -> Value {
+  Value::String(name.to_string())
+}
+
+/// `network.<section>` of a netplan document, if present.
+fn section<'a>(doc: &'a Value, name: &str) -> Option<&'a Mapping> {
+  doc.get("network")?.get(name)?.as_mapping()
+}
+
+/// The kernel name netplan gives the interface `id`: its `set-name`, a
+/// literal `match.name`, or the id itself.
+fn device_name(id: &str, config: &Value) -> String {
+  config
+    .get("set-name")
+    .and_then(Value::as_str)
+    .or_else(|| {
+      config
+        .get("match")
+        .and_then(|m| m.get("name"))
+        .and_then(Value::as_str)
+        .filter(|n| !n.contains(['*', '?', '[']))
+    })
+    .unwrap_or(id)
+    .to_string()
+}
+
+fn is_true(value: Option<&Value>) -> bool {
+  match value {
+    Some(Value::Bool(b)) => *b,
+    Some(Value::String(s)) => matches!(s.as_str(), "true" | "yes" | "on"),
+    _ => false,
+  }
+}
+
+/// Rewrite `doc` so `interface` in `section_name` becomes a member of
+/// `bridge` and its layer 3 configuration moves onto the bridge.
+fn bridged(
+  doc: &Value,
+  section_name: &str,
+  interface: &str,
+  bridge: &str,
+  mac: Option<&str>,
+) -> Result<(Value, Vec<String>, bool), String> {
+  let mut doc = doc.clone();
+  let network = doc
+    .get_mut("network")
+    .and_then(Value::as_mapping_mut)
+    .ok_or("netplan file has no network section")?;
+
+  let members = network
+    .get_mut(section_name)
+    .and_then(Value::as_mapping_mut)
+    .ok_or_else(|| format!("No {} section", section_name))?;
+  let member = members
+    .get_mut(interface)
+    .and_then(Value::as_mapping_mut)
+    .ok_or_else(|| format!("{} is not configured in this file", interface))?;
+
+  let mut bridge_config = Mapping::new();
+  bridge_config.insert(key("interfaces"), Value::Sequence(vec![key(interface)]));
+  for name in MOVED_KEYS {
+    if let Some(value) = member.remove(*name) {
+      bridge_config.insert(key(name), value);
+    }
+  }
+  member.insert(key("dhcp4"), Value::Bool(false));
+  member.insert(key("dhcp6"), Value::Bool(false));
+
+  let addresses: Vec<String> = bridge_config
+    .get("addresses")
+    .and_then(Value::as_sequence)
+    .map(|seq| {
+      seq
+        .iter()
+        .filter_map(|a| match a {
+          Value::String(s) => Some(s.clone()),
+          // The long form is a map keyed by the address
+          Value::Mapping(m) => m.keys().next().and_then(Value::as_str).map(str::to_string),
+          _ => None,
+        })
+        .collect()
+    })
+    .unwrap_or_default();
+  let dhcp = is_true(bridge_config.get("dhcp4")) || is_true(bridge_config.get("dhcp6"));
+  if addresses.is_empty() && !dhcp {
+    return Err(format!(
+      "{} has neither static addresses nor DHCP; nothing to move to {}",
+      interface, bridge
+    ));
+  }
+
+  // A bridge otherwise gets a generated MAC, and with it a new DHCP lease
+  let mac = member
+    .get("macaddress")
+    .and_then(Value::as_str)
+    .map(str::to_string)
+    .or_else(|| mac.map(str::to_string));
+  if let Some(mac) = mac {
+    bridge_config.insert(key("macaddress"), Value::String(mac));
+  }
+  let mut parameters = Mapping::new();
+  parameters.insert(key("stp"), Value::Bool(false));
+  parameters.insert(key("forward-delay"), Value::Number(0.into()));
+  bridge_config.insert(key("parameters"), Value::Mapping(parameters));
+
+  let bridges = network
+    .entry(key("bridges"))
+    .or_insert_with(|| Value::Mapping(Mapping::new()))
+    .as_mapping_mut()
+    .ok_or("network.bridges is not a map")?;
+  bridges.insert(key(bridge), Value::Mapping(bridge_config));
+
+  Ok((doc, addresses, dhcp))
+}
+
+fn unified_diff(path: &str, old: &str, new: &str) -> String {
+  let a: Vec<&str> = old.lines().collect();
+  let b: Vec<&str> = new.lines().collect();
+
+  // lcs[i][j]: longest common subsequence of a[i..] and b[j..]
+  let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+  for i in (0..a.len()).rev() {
+    for j in (0..b.len()).rev() {
+      lcs[i][j] = if a[i] == b[j] {
+        lcs[i + 1][j + 1] + 1
+      } else {
+        lcs[i + 1][j].max(lcs[i][j + 1])
+      };
+    }
+  }
+
+  // (op, index into a, index into b)
+  let mut ops: Vec<(char, usize, usize)> = Vec::new();
+  let (mut i, mut j) = (0, 0);
+  while i < a.len() || j < b.len() {
+    if i < a.len() && j < b.len() && a[i] == b[j] {
+      ops.push((' ', i, j));
+      i += 1;
+      j += 1;
+    } else if i < a.len() && (j == b.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+      ops.push(('-', i, j));
+      i += 1;
+    } else {
+      ops.push(('+', i, j));
+      j += 1;
+    }
+  }
+
+  let changes: Vec<usize> = (0..ops.len()).filter(|&k| ops[k].0 != ' ').collect();
+  if changes.is_empty() {
+    return String::new();
+  }
+  let mut out = format!("--- a{}\n+++ b{}\n", path, path);
+  let mut k = 0;
+  while k < changes.len() {
+    let mut last = k;
+    while last + 1 < changes.len() && changes[last + 1] - changes[last] <= 2 * DIFF_CONTEXT {
+      last += 1;
+    }
+    let start = changes[k].saturating_sub(DIFF_CONTEXT);
+    let end = (changes[last] + DIFF_CONTEXT + 1).min(ops.len());
+    let hunk = &ops[start..end];
+    let old_len = hunk.iter().filter(|op| op.0 != '+').count();
+    let new_len = hunk.iter().filter(|op| op.0 != '-').count();
+    let old_start = hunk[0].1 + usize::from(old_len > 0);
+    let new_start = hunk[0].2 + usize::from(new_len > 0);
+    out.push_str(&format!(
+      "@@ -{},{} +{},{} @@\n",
+      old_start, old_len, new_start, new_len
+    ));
+    for &(op, i, j) in hunk {
+      let line = if op == '+' { b[j] } else { a[i] };
+      out.push_str(&format!("{}{}\n", op, line));
+    }
+    k = last + 1;
+  }
+  out
+}
+
+fn plan(network: &NodeNetwork, target: &SshTarget, options: &BridgeOptions) -> Result<BridgePlan, String> {
+  let bridge = options.bridge.clone().unwrap_or_else(|| DEFAULT_BRIDGE.to_string());
+  if !validation::is_valid_label(&bridge) || bridge.len() > 15 {
+    return Err(format!("Invalid bridge name: {}", bridge));
+  }
+  if network.files.is_empty() {
+    return Err(format!("{} has no netplan configuration in /etc/netplan", target.host));
+  }
+
+  let mut docs = Vec::new();
+  for file in &network.files {
+    let doc: Value = serde_yaml::from_str(&file.contents).map_err(|e| format!("Cannot parse {}: {}", file.path, e))?;
+    if section(&doc, "bridges").is_some_and(|b| b.contains_key(bridge.as_str())) {
+      return Err(format!(
+        "{} already defines bridge {} in {}",
+        target.host, bridge, file.path
+      ));
+    }
+    docs.push((file, doc));
+  }
+
+  // Every (file, section, id) that configures a bridgeable interface
+  let mut candidates = Vec::new();
+  for (file, doc) in &docs {
+    for section_name in BRIDGEABLE {
+      for (id, config) in section(doc, section_name).into_iter().flatten() {
+        if let Some(id) = id.as_str() {
+          candidates.push((*file, doc, *section_name, id.to_string(), device_name(id, config)));
+        }
+      }
+    }
+  }
+
+  let interface = match &options.interface {
+    Some(interface) => interface.clone(),
+    None => candidates
+      .iter()
+      .find(|c| network.default_devices.contains(&c.4))
+      .map(|c| c.3.clone())
+      .ok_or_else(|| {
+        format!(
+          "Cannot tell which interface carries the default route on {}; choose one",
+          target.host
+        )
+      })?,
+  };
+  let matching: Vec<_> = candidates.iter().filter(|c| c.3 == interface).collect();
+  let (file, doc, section_name, _, device) = match matching.as_slice() {
+    [] => {
+      return Err(format!(
+        "{} is not configured in /etc/netplan on {}",
+        interface, target.host
+      ))
+    }
+    [one] => one,
+    _ => {
+      return Err(format!(
+        "{} is configured in more than one netplan file; merge them first",
+        interface
+      ))
+    }
+  };
+
+  let mac = network
+    .macs
+    .iter()
+    .find(|(dev, _)| dev == device)
+    .map(|(_, mac)| mac.as_str());
+  let (new_doc, addresses, dhcp) = bridged(doc, section_name, &interface, &bridge, mac)?;
+  let proposed = serde_yaml::to_string(&new_doc).map_err(|e| e.to_string())?;
+
+  Ok(BridgePlan {
+    host: target.host.clone(),
+    path: file.path.clone(),
+    bridge,
+    interface,
+    diff: unified_diff(&file.path, &file.contents, &proposed),
+    current: file.contents.clone(),
+    proposed,
+    addresses,
+    dhcp,
+  })
+}
+
+/// Work out the bridged netplan configuration for a node without changing
+/// anything.
+#[tauri::command]
+pub async fn plan_bridge(
+  app: AppHandle,
+  host: SshTarget,
+  options: Option<BridgeOptions>,
+) -> Result<BridgePlan, String> {
+  host.validate()?;
+  tauri::async_runtime::spawn_blocking(move || {
+    let output = ssh::run_script(
+      &app.state::<Workspace>(),
+      &app.state::<SshPool>(),
+      &host,
+      READ_SCRIPT,
+      READ_TIMEOUT,
+    )?;
+    if !output.stdout.contains("@@end") {
+      let detail = output.stderr.trim().lines().last().unwrap_or("no output").to_string();
+      return Err(format!(
+        "Cannot read network configuration on {}: {}",
+        host.host, detail
+      ));
+    }
+    plan(&parse_node(&output.stdout)?, &host, &options.unwrap_or_default())
+  })
+  .await
+  .map_err(|e| e.to_string())?
+}
+
+pub fn is_netplan_path(path: &str) -> bool {
+  path.strip_prefix("/etc/netplan/").is_some_and(|name| {
+    !name.is_empty()
+      && !name.starts_with('.')
+      && name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+      && (name.ends_with(".yaml") || name.ends_with(".yml"))
+  })
+}
+
+/// Put back the file saved by an applied bridge change and reapply it.
+pub fn restore_script(path: &str, backup: &str) -> String {
+  format!(
+    r#"set -e
+path={path}
+backup={backup}
+if ! sudo -n test -f "$backup"; then
+  echo "$backup is missing" >&2
+  exit 3
+fi
+sudo -n systemctl stop {unit}.timer 2>/dev/null || true
+sudo -n cp -p "$backup" "$path"
+sudo -n rm -f /etc/cloud/cloud.cfg.d/99-thinkube-network.cfg
+sudo -n netplan generate
+sudo -n systemd-run --quiet --collect --on-active=2 netplan apply
+"#,
+    path = shell_quote(path),
+    backup = shell_quote(backup),
+    unit = ROLLBACK_UNIT,
+  )
+}
+
+fn b64(text: &str) -> String {
+  base64::engine::general_purpose::STANDARD.encode(text)
+}
+
+fn apply(app: &AppHandle, target: &SshTarget, plan: &BridgePlan, rollback_secs: u64) -> Result<BridgeApplied, String> {
+  let workspace = app.state::<Workspace>();
+  let pool = app.state::<SshPool>();
+  let backup = format!("{}{}", plan.path, BACKUP_SUFFIX);
+
+  // Check the file is still what the user reviewed, swap it in, make sure
+  // netplan accepts it, then arm the rollback before touching the network
+  let script = format!(
+    r#"set -e
+path={path}
+backup={backup}
+printf '%s' {current} | base64 -d > /tmp/thinkube-netplan-current
+if ! sudo -n cat "$path" | cmp -s - /tmp/thinkube-netplan-current; then
+  rm -f /tmp/thinkube-netplan-current
+  echo "$path changed since the plan was made" >&2
+  exit 3
+fi
+rm -f /tmp/thinkube-netplan-current
+sudo -n cp -p "$path" "$backup"
+printf '%s' {proposed} | base64 -d | sudo -n tee "$path" >/dev/null
+sudo -n chmod 600 "$path"
+if ! sudo -n netplan generate; then
+  sudo -n cp -p "$backup" "$path"
+  echo "netplan rejected the new configuration" >&2
+  exit 4
+fi
+case "$path" in
+  */50-cloud-init.yaml)
+    if [ -d /etc/cloud/cloud.cfg.d ]; then
+      echo 'network: {{config: disabled}}' | sudo -n tee /etc/cloud/cloud.cfg.d/99-thinkube-network.cfg >/dev/null
+    fi
+    ;;
+esac
+sudo -n systemctl stop {unit}.timer 2>/dev/null || true
+sudo -n systemctl reset-failed {unit}.service 2>/dev/null || true
+sudo -n systemd-run --quiet --unit={unit} --on-active={secs} /bin/sh -c "cp -p '$backup' '$path' && rm -f /etc/cloud/cloud.cfg.d/99-thinkube-network.cfg && netplan apply"
+sudo -n systemd-run --quiet --collect --on-active=2 netplan apply
+echo scheduled
+"#,
+    path = shell_quote(&plan.path),
+    backup = shell_quote(&backup),
+    current = shell_quote(&b64(&plan.current)),
+    proposed = shell_quote(&b64(&plan.proposed)),
+    unit = ROLLBACK_UNIT,
+    secs = rollback_secs,
+  );
+  let output = ssh::run_script(&workspace, &pool, target, &script, READ_TIMEOUT)?;
+  if output.status != Some(0) || !output.stdout.contains("scheduled") {
+    let detail = output
+      .stderr
+      .trim()
+      .lines()
+      .last()
+      .unwrap_or("command failed")
+      .to_string();
+    return Err(format!("Failed to apply bridge on {}: {}", target.host, detail));
+  }
+  info!(
+    "Applying {} on {}; rollback in {}s unless confirmed",
+    plan.bridge, target.host, rollback_secs
+  );
+
+  // The pooled connection may not survive the interface moving under it
+  std::thread::sleep(Duration::from_secs(5));
+  pool.close(target);
+
+  let confirm = format!(
+    "sudo -n systemctl stop {unit}.timer\n\
+     ip -o addr show dev {bridge} | awk '{{ print $4 }}'\n",
+    unit = ROLLBACK_UNIT,
+    bridge = shell_quote(&plan.bridge),
+  );
+  // Leave a margin so we never confirm after the rollback has started
+  let deadline = Instant::now() + Duration::from_secs(rollback_secs - 15);
+  let mut last_error = String::new();
+  while Instant::now() < deadline {
+    match ssh::run_script(&workspace, &pool, target, &confirm, READ_TIMEOUT) {
+      Ok(out) if out.status == Some(0) => {
+        info!("Bridge {} on {} confirmed", plan.bridge, target.host);
+        journal::record(
+          app,
+          Action::Netplan {
+            target: target.clone(),
+            path: plan.path.clone(),
+            backup: backup.clone(),
+          },
+        );
+        return Ok(BridgeApplied {
+          bridge: plan.bridge.clone(),
+          addresses: out
+            .stdout
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .map(str::to_string)
+            .collect(),
+          backup,
+        });
+      }
+      Ok(out) => {
+        last_error = out
+          .stderr
+          .trim()
+          .lines()
+          .last()
+          .unwrap_or("confirmation failed")
+          .to_string()
+      }
+      Err(e) => last_error = e,
+    }
+    pool.close(target);
+    std::thread::sleep(Duration::from_secs(3));
+  }
+  Err(format!(
+    "{} did not come back after the bridge change ({}); the previous configuration will be restored automatically",
+    target.host, last_error
+  ))
+}
+
+/// Apply a plan from `plan_bridge`, rolling back automatically if the node
+/// can't be reached afterwards.
+#[tauri::command]
+pub async fn apply_bridge(
+  app: AppHandle,
+  host: SshTarget,
+  plan: BridgePlan,
+  options: Option<ApplyBridgeOptions>,
+) -> Result<BridgeApplied, String> {
+  host.validate()?;
+  if plan.host != host.host {
+    return Err(format!("This plan was made for {}, not {}", plan.host, host.host));
+  }
+  if !is_netplan_path(&plan.path) {
+    return Err(format!("Not a netplan file: {}", plan.path));
+  }
+  if !validation::is_valid_label(&plan.bridge) {
+    return Err(format!("Invalid bridge name: {}", plan.bridge));
+  }
+  serde_yaml::from_str::<Value>(&plan.proposed)
+    .map_err(|e| format!("Proposed configuration is not valid YAML: {}", e))?;
+  let rollback_secs = options
+    .unwrap_or_default()
+    .rollback_secs
+    .unwrap_or(DEFAULT_ROLLBACK_SECS)
+    .clamp(MIN_ROLLBACK_SECS, MAX_ROLLBACK_SECS);
+  if dry_run::is_enabled(&app) {
+    info!("Dry run: not applying {} on {}", plan.bridge, host.host);
+    return Ok(BridgeApplied {
+      backup: format!("{}{}", plan.path, BACKUP_SUFFIX),
+      bridge: plan.bridge,
+      addresses: plan.addresses,
+    });
+  }
+  tauri::async_runtime::spawn_blocking(move || apply(&app, &host, &plan, rollback_secs))
+    .await
+    .map_err(|e| e.to_string())?
+}
@@ -0,0 +1,461 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! NVIDIA driver and container toolkit state of GPU nodes.
+//!
+//! The playbooks either leave an existing driver alone, upgrade it, or
+//! install one, and install `nvidia-container-toolkit` where it's missing.
+//! Which path a node takes depends on what's there now: `lspci` finds the
+//! cards even without a driver, `nvidia-smi` gives the driver version, CUDA
+//! version and compute capability, and dpkg tells a packaged driver from a
+//! `.run` install. The policy matches the backend's GPU detection: driver
+//! 580 or newer, and Volta (compute capability 7.0) or newer cards only.
+//!
+//! Jetson boards and DGX systems are the exception. A Jetson's GPU sits on
+//! the SoC rather than PCI and its driver comes with the JetPack release
+//! it was flashed with, and DGX OS ships and upgrades its own driver, so
+//! the report's `platform` says so and the wizard leaves those drivers to
+//! the platform. It also gives the container runtime mode the node needs:
+//! CSV mounts on JetPack before 6, CDI from JetPack 6 on.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+use super::inventory::{lspci_fields, sections, split_id};
+use super::ssh::{self, SshPool, SshTarget};
+use crate::workspace::Workspace;
+
+const DETECT_TIMEOUT: Duration = Duration::from_secs(30);
+const MIN_DRIVER_MAJOR: u32 = 580;
+const MIN_COMPUTE_CAP: (u32, u32) = (7, 0);
+
+const SCRIPT: &str = r#"
+export LC_ALL=C
+echo '@@gpus'
+lspci -D -mm -nn 2>/dev/null | grep -E '\[(0300|0302|0380)\]' | grep '\[10de\]'
+echo '@@smi'
+nvidia-smi --query-gpu=pci.bus_id,name,driver_version,compute_cap --format=csv,noheader 2>&1
+echo "exit=$?"
+echo '@@facts'
+echo "cuda=$(nvidia-smi 2>/dev/null | sed -n 's/.*CUDA Version: *\([0-9.]*\).*/\1/p' | head -1)"
+echo "kmod=$(head -1 /proc/driver/nvidia/version 2>/dev/null)"
+echo "nouveau=$([ -d /sys/module/nouveau ] && echo yes || echo no)"
+echo "secure_boot=$(mokutil --sb-state 2>/dev/null | head -1)"
+echo "ctk=$(nvidia-ctk --version 2>/dev/null | head -1)"
+echo "toolkit_pkg=$(dpkg-query -W -f='${Version}' nvidia-container-toolkit 2>/dev/null)"
+echo "driver_pkg=$(dpkg-query -W -f='${db:Status-Abbrev} ${Package}\n' 'nvidia-driver-*' 2>/dev/null | awk '$1 == "ii" { print $2; exit }')"
+echo "runfile=$([ -x /usr/bin/nvidia-uninstall ] && echo yes || echo no)"
+echo "arch=$(uname -m)"
+echo "dt_model=$(tr -d '\0' 2>/dev/null < /proc/device-tree/model)"
+echo "tegra=$(tr '\0' ' ' 2>/dev/null < /proc/device-tree/compatible | grep -o 'nvidia,tegra[0-9a-z]*' | head -1)"
+echo "l4t=$(head -1 /etc/nv_tegra_release 2>/dev/null)"
+echo "dgx=$(sed -n 's/^DGX_SWBUILD_VERSION="\{0,1\}\([^"]*\)"\{0,1\}$/\1/p' /etc/dgx-release 2>/dev/null | tail -1)"
+echo "dgx_name=$(sed -n 's/^DGX_NAME="\{0,1\}\([^"]*\)"\{0,1\}$/\1/p' /etc/dgx-release 2>/dev/null | head -1)"
+echo "product=$(cat /sys/class/dmi/id/product_name 2>/dev/null)"
+echo '@@end'
+"#;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NvidiaGpu {
+  pub pci_slot: String,
+  pub name: String,
+  /// e.g. `8.6`; only known once the driver is loaded
+  pub compute_cap: Option<String>,
+  /// False for pre-Volta cards; `None` when the compute capability is unknown
+  pub supported: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DriverStatus {
+  Compatible,
+  Old,
+  Missing,
+  UnsupportedGpu,
+  Unknown,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DriverAction {
+  Install,
+  Upgrade,
+  /// Leave the node out of GPU scheduling; no driver fixes the card
+  Exclude,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DriverSource {
+  Package,
+  Runfile,
+  /// Part of the JetPack or DGX OS image
+  Platform,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlatformKind {
+  /// A PC or server with discrete cards
+  #[default]
+  Generic,
+  /// Tegra SoC with an integrated GPU, driver shipped with JetPack (L4T)
+  Jetson,
+  /// DGX OS, which ships and upgrades its own driver
+  Dgx,
+}
+
+/// Mode `nvidia-container-runtime` runs in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuntimeMode {
+  #[default]
+  Auto,
+  /// Mounts the files listed in `/etc/nvidia-container-runtime/host-files-for-container.d`,
+  /// as JetPack before 6 needs
+  Csv,
+  /// CDI specs from `nvidia-ctk cdi generate`
+  Cdi,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct NvidiaPlatform {
+  pub kind: PlatformKind,
+  /// Device-tree model, DGX name or DMI product name
+  pub model: Option<String>,
+  /// e.g. `R36.3.0`, on Jetson
+  pub l4t_release: Option<String>,
+  pub dgx_os_version: Option<String>,
+  /// The playbooks may install or upgrade the driver
+  pub manage_driver: bool,
+  pub runtime_mode: RuntimeMode,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct NvidiaReport {
+  pub host: String,
+  pub error: Option<String>,
+  pub arch: Option<String>,
+  pub platform: NvidiaPlatform,
+  pub gpus: Vec<NvidiaGpu>,
+  pub driver_installed: bool,
+  pub driver_version: Option<String>,
+  /// How the current driver was installed
+  pub driver_source: Option<DriverSource>,
+  /// Highest CUDA version the driver supports
+  pub cuda_version: Option<String>,
+  pub driver_status: Option<DriverStatus>,
+  pub action_required: Option<DriverAction>,
+  pub min_driver_major: u32,
+  pub container_toolkit_installed: bool,
+  pub container_toolkit_version: Option<String>,
+  /// `None` when `mokutil` isn't available
+  pub secure_boot: Option<bool>,
+  pub nouveau_loaded: bool,
+  pub warnings: Vec<String>,
+}
+
+/// `8.6` -> (8, 6)
+fn parse_cap(cap: &str) -> Option<(u32, u32)> {
+  let (major, minor) = cap.trim().split_once('.')?;
+  Some((major.parse().ok()?, minor.parse().ok()?))
+}
+
+/// `580.95.05` from `NVRM version: NVIDIA UNIX Open Kernel Module for x86_64  580.95.05  Release Build ...`
+fn version_token(line: &str) -> Option<&str> {
+  line
+    .split_whitespace()
+    .find(|t| t.contains('.') && t.split('.').all(|p| !p.is_empty() && p.chars().all(|c| c.is_ascii_digit())))
+}
+
+/// `R36.3.0` from `# R36 (release), REVISION: 3.0, GCID: ..., BOARD: generic, ...`
+fn l4t_release(line: &str) -> Option<String> {
+  let major = line.trim_start_matches(['#', ' ']).split_whitespace().next()?;
+  let revision = line
+    .split(',')
+    .find_map(|f| f.trim().strip_prefix("REVISION:"))
+    .map(str::trim)?;
+  Some(format!("{}.{}", major, revision))
+}
+
+/// Tell a Jetson or DGX from the device tree, `/etc/nv_tegra_release`,
+/// `/etc/dgx-release` and the DMI product name, and pick its defaults.
+fn fingerprint(fact: impl Fn(&str) -> Option<String>) -> NvidiaPlatform {
+  let l4t = fact("l4t");
+  if fact("tegra").is_some() || l4t.is_some() {
+    let l4t_release = l4t.as_deref().and_then(l4t_release);
+    // JetPack 6 (L4T R36) moved the runtime to CDI
+    let major = l4t_release
+      .as_deref()
+      .and_then(|r| r.trim_start_matches('R').split('.').next()?.parse::<u32>().ok());
+    return NvidiaPlatform {
+      kind: PlatformKind::Jetson,
+      model: fact("dt_model"),
+      l4t_release,
+      dgx_os_version: None,
+      manage_driver: false,
+      runtime_mode: if major.is_some_and(|m| m >= 36) {
+        RuntimeMode::Cdi
+      } else {
+        RuntimeMode::Csv
+      },
+    };
+  }
+  let product = fact("product");
+  let dgx_os_version = fact("dgx");
+  if dgx_os_version.is_some() || product.as_deref().is_some_and(|p| p.contains("DGX")) {
+    return NvidiaPlatform {
+      kind: PlatformKind::Dgx,
+      model: fact("dgx_name").or(product),
+      // Without DGX OS the box is a plain Ubuntu server as far as the driver goes
+      manage_driver: dgx_os_version.is_none(),
+      dgx_os_version,
+      l4t_release: None,
+      runtime_mode: RuntimeMode::Auto,
+    };
+  }
+  NvidiaPlatform {
+    model: product,
+    manage_driver: true,
+    ..Default::default()
+  }
+}
+
+/// lspci's `0000:01:00.0` and nvidia-smi's `00000000:01:00.0` name the same
+/// device; compare on bus, device and function.
+fn same_slot(a: &str, b: &str) -> bool {
+  let tail = |s: &str| s.rsplitn(3, ':').take(2).collect::<Vec<_>>().join(":").to_ascii_lowercase();
+  tail(a) == tail(b)
+}
+
+fn parse(host: &str, output: &str) -> NvidiaReport {
+  let sections = sections(output);
+  let empty = Vec::new();
+  let section = |name: &str| sections.get(name).unwrap_or(&empty);
+  let facts: HashMap<&str, &str> = section("facts").iter().filter_map(|l| l.split_once('=')).collect();
+  let fact = |key: &str| facts.get(key).map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+
+  let mut report = NvidiaReport {
+    host: host.to_string(),
+    arch: fact("arch"),
+    platform: fingerprint(fact),
+    min_driver_major: MIN_DRIVER_MAJOR,
+    nouveau_loaded: fact("nouveau").as_deref() == Some("yes"),
+    secure_boot: fact("secure_boot").and_then(|s| {
+      if s.contains("enabled") {
+        Some(true)
+      } else if s.contains("disabled") {
+        Some(false)
+      } else {
+        None
+      }
+    }),
+    ..Default::default()
+  };
+
+  let smi = section("smi");
+  let smi_ok = smi.iter().any(|l| l.trim() == "exit=0");
+  // (bus id, name, driver version, compute capability)
+  let smi_rows: Vec<Vec<&str>> = if smi_ok {
+    smi
+      .iter()
+      .filter(|l| !l.starts_with("exit="))
+      .map(|l| l.split(',').map(str::trim).collect::<Vec<_>>())
+      .filter(|f| f.len() >= 3)
+      .collect()
+  } else {
+    Vec::new()
+  };
+
+  for line in section("gpus").iter().filter(|l| !l.trim().is_empty()) {
+    let (slot, fields) = lspci_fields(line);
+    let (model, _) = split_id(fields.get(2).map(String::as_str).unwrap_or(""));
+    let row = smi_rows.iter().find(|r| same_slot(r[0], &slot));
+    let compute_cap = row
+      .and_then(|r| r.get(3))
+      .map(|c| c.to_string())
+      .filter(|c| parse_cap(c).is_some());
+    report.gpus.push(NvidiaGpu {
+      supported: compute_cap.as_deref().and_then(parse_cap).map(|cap| cap >= MIN_COMPUTE_CAP),
+      name: row.map(|r| r[1].to_string()).unwrap_or_else(|| model.to_string()),
+      pci_slot: slot,
+      compute_cap,
+    });
+  }
+
+  report.driver_version = smi_rows
+    .first()
+    .map(|r| r[2].to_string())
+    .or_else(|| fact("kmod").as_deref().and_then(version_token).map(str::to_string));
+  report.driver_installed = smi_ok || report.driver_version.is_some();
+  report.cuda_version = fact("cuda");
+  report.driver_source = if fact("driver_pkg").is_some() {
+    Some(DriverSource::Package)
+  } else if fact("runfile").as_deref() == Some("yes") {
+    Some(DriverSource::Runfile)
+  } else {
+    None
+  };
+  report.container_toolkit_version = fact("toolkit_pkg").or_else(|| {
+    fact("ctk")
+      .as_deref()
+      .and_then(|l| l.split_whitespace().last())
+      .map(str::to_string)
+  });
+  report.container_toolkit_installed = report.container_toolkit_version.is_some();
+
+  // The integrated GPU isn't on PCI, and the driver is whatever the
+  // JetPack release on the board ships; only a reflash changes it
+  if report.platform.kind == PlatformKind::Jetson {
+    report.driver_installed = report.platform.l4t_release.is_some();
+    report.driver_source = Some(DriverSource::Platform);
+    report.driver_status = Some(if report.driver_installed {
+      DriverStatus::Compatible
+    } else {
+      DriverStatus::Unknown
+    });
+    if !report.driver_installed {
+      report
+        .warnings
+        .push("No L4T release found; flash the board with JetPack before adding it".to_string());
+    }
+    if !report.container_toolkit_installed {
+      report
+        .warnings
+        .push("nvidia-container-toolkit is not installed; install it with the nvidia-jetpack packages".to_string());
+    }
+    return report;
+  }
+
+  if report.gpus.is_empty() {
+    if report.container_toolkit_installed {
+      report
+        .warnings
+        .push("nvidia-container-toolkit is installed but no NVIDIA GPU was found".to_string());
+    }
+    return report;
+  }
+
+  let (status, action) = if report.gpus.iter().any(|g| g.supported == Some(false)) {
+    (DriverStatus::UnsupportedGpu, Some(DriverAction::Exclude))
+  } else {
+    match &report.driver_version {
+      None => (DriverStatus::Missing, Some(DriverAction::Install)),
+      Some(version) => match version.split('.').next().and_then(|m| m.parse::<u32>().ok()) {
+        Some(major) if major >= MIN_DRIVER_MAJOR => (DriverStatus::Compatible, None),
+        Some(_) => (DriverStatus::Old, Some(DriverAction::Upgrade)),
+        None => (DriverStatus::Unknown, None),
+      },
+    }
+  };
+
+  if let Some(gpu) = report.gpus.iter().find(|g| g.supported == Some(false)) {
+    report.warnings.push(format!(
+      "{} (compute capability {}) predates Volta and is not supported",
+      gpu.name,
+      gpu.compute_cap.as_deref().unwrap_or("unknown")
+    ));
+  }
+  if !smi_ok && report.driver_version.is_some() {
+    let detail = smi.iter().find(|l| !l.starts_with("exit=")).copied().unwrap_or("").trim();
+    if detail.contains("mismatch") {
+      report
+        .warnings
+        .push("The loaded kernel module doesn't match the driver libraries; reboot the node".to_string());
+    } else {
+      report
+        .warnings
+        .push(format!("The driver module is loaded but nvidia-smi fails: {}", detail));
+    }
+  }
+  if report.nouveau_loaded {
+    report
+      .warnings
+      .push("nouveau is loaded; it must be blacklisted and the node rebooted before the NVIDIA driver can load".to_string());
+  }
+  if report.secure_boot == Some(true) && matches!(action, Some(DriverAction::Install | DriverAction::Upgrade)) {
+    report.warnings.push(
+      "Secure Boot is enabled; a driver built from the .run installer won't load unless its module is signed with an enrolled key"
+        .to_string(),
+    );
+  }
+  if report.platform.kind == PlatformKind::Dgx && !report.platform.manage_driver {
+    report.driver_source = Some(DriverSource::Platform);
+    if matches!(action, Some(DriverAction::Install | DriverAction::Upgrade)) {
+      report.warnings.push(
+        "DGX OS manages this node's driver; install or upgrade it through the DGX OS repositories rather than the playbooks"
+          .to_string(),
+      );
+    }
+  } else if matches!(report.driver_source, Some(DriverSource::Package)) && matches!(action, Some(DriverAction::Upgrade)) {
+    report.warnings.push(
+      "The current driver came from an Ubuntu package; remove it before upgrading so the two don't conflict".to_string(),
+    );
+  }
+  if !report.container_toolkit_installed {
+    report
+      .warnings
+      .push("nvidia-container-toolkit is not installed; the playbooks will install it".to_string());
+  }
+
+  report.driver_status = Some(status);
+  report.action_required = action;
+  report
+}
+
+fn detect(workspace: &Workspace, pool: &SshPool, target: &SshTarget) -> NvidiaReport {
+  let failed = |error: String| NvidiaReport {
+    host: target.host.clone(),
+    error: Some(error),
+    min_driver_major: MIN_DRIVER_MAJOR,
+    ..Default::default()
+  };
+  match ssh::run_script(workspace, pool, target, SCRIPT, DETECT_TIMEOUT) {
+    Ok(output) if output.stdout.contains("@@end") => parse(&target.host, &output.stdout),
+    Ok(output) => failed(format!(
+      "NVIDIA detection did not complete on {}: {}",
+      target.host,
+      output.stderr.trim()
+    )),
+    Err(e) => failed(e),
+  }
+}
+
+/// Report the NVIDIA driver, CUDA and container toolkit state of each node
+/// and which driver path the playbooks should take.
+#[tauri::command]
+pub async fn detect_nvidia_stack(app: AppHandle, hosts: Vec<SshTarget>) -> Result<Vec<NvidiaReport>, String> {
+  for target in &hosts {
+    target.validate()?;
+  }
+  tauri::async_runtime::spawn_blocking(move || {
+    let workspace = app.state::<Workspace>();
+    let workspace: &Workspace = &workspace;
+    let pool = app.state::<SshPool>();
+    let pool: &SshPool = &pool;
+    std::thread::scope(|scope| {
+      let handles: Vec<_> = hosts
+        .iter()
+        .map(|target| scope.spawn(move || detect(workspace, pool, target)))
+        .collect();
+      handles
+        .into_iter()
+        .zip(&hosts)
+        .map(|(handle, target)| {
+          handle.join().unwrap_or_else(|_| NvidiaReport {
+            host: target.host.clone(),
+            error: Some("NVIDIA detection panicked".to_string()),
+            min_driver_major: MIN_DRIVER_MAJOR,
+            ..Default::default()
+          })
+        })
+        .collect()
+    })
+  })
+  .await
+  .map_err(|e| e.to_string())
+}
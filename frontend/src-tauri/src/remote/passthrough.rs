@@ -0,0 +1,278 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Whether a node's GPUs can be passed through to VMs.
+//!
+//! Passthrough needs three things: the IOMMU switched on (firmware plus, on
+//! Intel, `intel_iommu=on` on older kernels), the `vfio-pci` driver, and
+//! each GPU in an IOMMU group of its own. A group is the unit the host hands
+//! over, so a GPU that shares one with, say, the SATA controller can't be
+//! given away without it. The GPU's other functions (its HDMI audio) and PCI
+//! bridges may share the group; anything else blocks passthrough.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+use super::inventory::{lspci_fields, sections, split_id};
+use super::ssh::{self, SshPool, SshTarget};
+use crate::workspace::Workspace;
+
+const CHECK_TIMEOUT: Duration = Duration::from_secs(30);
+const PCI_BRIDGE_CLASS: &str = "0x0604";
+const VFIO_MODULES: &[&str] = &["vfio", "vfio_pci", "vfio_iommu_type1"];
+
+const SCRIPT: &str = r#"
+export LC_ALL=C
+echo '@@cmdline'
+cat /proc/cmdline
+echo '@@cpu'
+grep -m1 '^vendor_id' /proc/cpuinfo | cut -d: -f2
+echo '@@iommu'
+ls /sys/class/iommu 2>/dev/null
+echo '@@modules'
+for m in vfio vfio_pci vfio_iommu_type1; do
+  if [ -d "/sys/module/$m" ]; then echo "$m loaded"
+  elif modinfo -n "$m" >/dev/null 2>&1; then echo "$m available"
+  else echo "$m missing"; fi
+done
+echo '@@groups'
+for d in /sys/kernel/iommu_groups/*/devices/*; do
+  [ -e "$d" ] || continue
+  g=${d#/sys/kernel/iommu_groups/}
+  echo "${g%%/*} ${d##*/} $(cat "$d/class" 2>/dev/null)"
+done
+echo '@@gpus'
+lspci -D -mm -nn 2>/dev/null | grep -E '\[(0300|0302|0380)\]'
+echo '@@drivers'
+for d in /sys/bus/pci/devices/*; do
+  [ -e "$d/driver" ] && echo "${d##*/} $(basename "$(readlink "$d/driver")")"
+done
+echo '@@end'
+"#;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GpuPassthrough {
+  /// Full PCI address, e.g. `0000:01:00.0`
+  pub pci_slot: String,
+  pub vendor: String,
+  pub model: String,
+  /// Host driver currently bound, e.g. `nvidia` or `vfio-pci`
+  pub driver: Option<String>,
+  pub iommu_group: Option<u32>,
+  /// Devices in the same group that would have to go along with it
+  pub shared_with: Vec<String>,
+  pub passthrough_ready: bool,
+  pub problems: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PassthroughReport {
+  pub host: String,
+  pub error: Option<String>,
+  /// `intel`, `amd` or the raw `vendor_id`
+  pub cpu_vendor: Option<String>,
+  pub iommu_enabled: bool,
+  pub iommu_groups: usize,
+  pub vfio_available: bool,
+  pub vfio_loaded: bool,
+  /// Kernel parameters needed before passthrough can work
+  pub missing_flags: Vec<String>,
+  /// Parameters that aren't required but are advisable, e.g. `iommu=pt`
+  pub recommended_flags: Vec<String>,
+  /// Node-level problems, beyond the per-GPU ones
+  pub problems: Vec<String>,
+  pub gpus: Vec<GpuPassthrough>,
+}
+
+/// `0000:01:00.1` -> `0000:01:00`, the part shared by a device's functions.
+fn slot_device(slot: &str) -> &str {
+  slot.rsplit_once('.').map(|(device, _)| device).unwrap_or(slot)
+}
+
+fn parse(host: &str, output: &str) -> PassthroughReport {
+  let sections = sections(output);
+  let empty = Vec::new();
+  let section = |name: &str| sections.get(name).unwrap_or(&empty);
+
+  let cmdline: Vec<&str> = section("cmdline")
+    .iter()
+    .flat_map(|l| l.split_whitespace())
+    .collect();
+  let has_flag = |flag: &str| cmdline.contains(&flag);
+  let cpu_vendor = section("cpu").first().map(|v| match v.trim() {
+    "GenuineIntel" => "intel".to_string(),
+    "AuthenticAMD" => "amd".to_string(),
+    other => other.to_string(),
+  });
+
+  // (group, slot, class)
+  let members: Vec<(u32, &str, &str)> = section("groups")
+    .iter()
+    .filter_map(|l| {
+      let mut parts = l.split_whitespace();
+      let group = parts.next()?.parse().ok()?;
+      Some((group, parts.next()?, parts.next().unwrap_or("")))
+    })
+    .collect();
+  let groups: HashMap<&str, u32> = members.iter().map(|(g, slot, _)| (*slot, *g)).collect();
+  let iommu_groups = {
+    let mut ids: Vec<u32> = members.iter().map(|(g, _, _)| *g).collect();
+    ids.sort_unstable();
+    ids.dedup();
+    ids.len()
+  };
+  let iommu_enabled = iommu_groups > 0 || section("iommu").iter().any(|l| !l.trim().is_empty());
+
+  let modules: HashMap<&str, &str> = section("modules")
+    .iter()
+    .filter_map(|l| l.split_once(' '))
+    .collect();
+  let vfio_loaded = VFIO_MODULES.iter().all(|m| modules.get(m) == Some(&"loaded"));
+  let vfio_available = VFIO_MODULES
+    .iter()
+    .all(|m| matches!(modules.get(m), Some(&"loaded") | Some(&"available")));
+  let drivers: HashMap<&str, &str> = section("drivers")
+    .iter()
+    .filter_map(|l| l.split_once(' '))
+    .collect();
+
+  let mut report = PassthroughReport {
+    host: host.to_string(),
+    cpu_vendor,
+    iommu_enabled,
+    iommu_groups,
+    vfio_available,
+    vfio_loaded,
+    ..Default::default()
+  };
+
+  if !iommu_enabled {
+    match report.cpu_vendor.as_deref() {
+      Some("intel") if !has_flag("intel_iommu=on") => report.missing_flags.push("intel_iommu=on".to_string()),
+      Some("intel") => report
+        .problems
+        .push("intel_iommu=on is set but no IOMMU is active; enable VT-d in the firmware".to_string()),
+      // AMD-Vi is on by default once the firmware exposes it
+      Some("amd") => report
+        .problems
+        .push("No IOMMU is active; enable IOMMU / AMD-Vi in the firmware".to_string()),
+      _ => report.problems.push("No IOMMU is active".to_string()),
+    }
+  }
+  if !has_flag("iommu=pt") {
+    // Without it the host's own devices pay for DMA remapping too
+    report.recommended_flags.push("iommu=pt".to_string());
+  }
+  if !vfio_available {
+    report
+      .problems
+      .push("vfio-pci is not available in this kernel; install the linux-modules-extra package".to_string());
+  }
+
+  for line in section("gpus").iter().filter(|l| !l.trim().is_empty()) {
+    let (slot, fields) = lspci_fields(line);
+    let (vendor_name, vendor_id) = split_id(fields.get(1).map(String::as_str).unwrap_or(""));
+    let (model, _) = split_id(fields.get(2).map(String::as_str).unwrap_or(""));
+    let vendor = match vendor_id {
+      "10de" => "nvidia".to_string(),
+      "1002" => "amd".to_string(),
+      "8086" => "intel".to_string(),
+      _ => vendor_name.to_string(),
+    };
+    let iommu_group = groups.get(slot.as_str()).copied();
+    let shared_with: Vec<String> = iommu_group
+      .map(|group| {
+        members
+          .iter()
+          .filter(|(g, other, class)| {
+            *g == group && slot_device(other) != slot_device(&slot) && !class.starts_with(PCI_BRIDGE_CLASS)
+          })
+          .map(|(_, other, _)| other.to_string())
+          .collect()
+      })
+      .unwrap_or_default();
+
+    let mut problems = Vec::new();
+    if iommu_enabled && iommu_group.is_none() {
+      problems.push("Not in any IOMMU group".to_string());
+    }
+    if !shared_with.is_empty() {
+      problems.push(format!(
+        "Shares IOMMU group {} with {}; move the card to another slot",
+        iommu_group.unwrap_or_default(),
+        shared_with.join(", ")
+      ));
+    }
+    // The host's boot display can't be handed over while it's in use
+    if vendor == "intel" && slot_device(&slot).ends_with(":00:02") {
+      problems.push("Integrated graphics; only discrete GPUs are passed through".to_string());
+    }
+
+    report.gpus.push(GpuPassthrough {
+      passthrough_ready: iommu_enabled && vfio_available && problems.is_empty(),
+      driver: drivers.get(slot.as_str()).map(|d| d.to_string()),
+      pci_slot: slot,
+      vendor,
+      model: model.to_string(),
+      iommu_group,
+      shared_with,
+      problems,
+    });
+  }
+  report
+}
+
+fn check(workspace: &Workspace, pool: &SshPool, target: &SshTarget) -> PassthroughReport {
+  let failed = |error: String| PassthroughReport {
+    host: target.host.clone(),
+    error: Some(error),
+    ..Default::default()
+  };
+  match ssh::run_script(workspace, pool, target, SCRIPT, CHECK_TIMEOUT) {
+    Ok(output) if output.stdout.contains("@@end") => parse(&target.host, &output.stdout),
+    Ok(output) => failed(format!(
+      "Passthrough check did not complete on {}: {}",
+      target.host,
+      output.stderr.trim()
+    )),
+    Err(e) => failed(e),
+  }
+}
+
+/// Report, per node, whether each GPU can be passed through to a VM and
+/// which kernel parameters are missing.
+#[tauri::command]
+pub async fn check_gpu_passthrough(app: AppHandle, hosts: Vec<SshTarget>) -> Result<Vec<PassthroughReport>, String> {
+  for target in &hosts {
+    target.validate()?;
+  }
+  tauri::async_runtime::spawn_blocking(move || {
+    let workspace = app.state::<Workspace>();
+    let workspace: &Workspace = &workspace;
+    let pool = app.state::<SshPool>();
+    let pool: &SshPool = &pool;
+    std::thread::scope(|scope| {
+      let handles: Vec<_> = hosts
+        .iter()
+        .map(|target| scope.spawn(move || check(workspace, pool, target)))
+        .collect();
+      handles
+        .into_iter()
+        .zip(&hosts)
+        .map(|(handle, target)| {
+          handle.join().unwrap_or_else(|_| PassthroughReport {
+            host: target.host.clone(),
+            error: Some("Passthrough check panicked".to_string()),
+            ..Default::default()
+          })
+        })
+        .collect()
+    })
+  })
+  .await
+  .map_err(|e| e.to_string())
+}
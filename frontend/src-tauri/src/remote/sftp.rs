@@ -0,0 +1,797 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! File transfer to and from nodes.
+//!
+//! A small SFTP (protocol version 3) client speaking to the node's `sftp`
+//! subsystem over the pooled SSH connection, so transfers need neither `scp`
+//! nor a second handshake. Only what transfers need is implemented: open,
+//! read, write, stat, rename, remove and setstat.
+//!
+//! Data goes to a `.part` file next to the destination and is renamed into
+//! place once complete, so a half-transferred bootstrap script is never
+//! mistaken for the real one. With `resume`, an existing `.part` file is
+//! continued from its current size if the chunk before that matches the
+//! source, and started over if the source changed since. Several chunk
+//! requests are kept in flight to hide round-trip latency; progress is
+//! emitted as `file-transfer-progress` events at most a few times per
+//! second.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, Read, Seek, SeekFrom, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tracing::info;
+
+use super::ssh::{self, SshPool, SshTarget};
+use crate::{audit, cancel};
+use crate::workspace::Workspace;
+
+pub const PROGRESS_EVENT: &str = "file-transfer-progress";
+
+const CHUNK: u32 = 32 * 1024;
+/// Outstanding read/write requests per transfer
+const WINDOW: usize = 16;
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+const PART_SUFFIX: &str = ".part";
+
+// Packet types
+const FXP_INIT: u8 = 1;
+const FXP_VERSION: u8 = 2;
+const FXP_OPEN: u8 = 3;
+const FXP_CLOSE: u8 = 4;
+const FXP_READ: u8 = 5;
+const FXP_WRITE: u8 = 6;
+const FXP_SETSTAT: u8 = 9;
+const FXP_REMOVE: u8 = 13;
+const FXP_STAT: u8 = 17;
+const FXP_RENAME: u8 = 18;
+const FXP_STATUS: u8 = 101;
+const FXP_HANDLE: u8 = 102;
+const FXP_DATA: u8 = 103;
+const FXP_ATTRS: u8 = 105;
+const FXP_EXTENDED: u8 = 200;
+
+// Open flags
+const FXF_READ: u32 = 0x01;
+const FXF_WRITE: u32 = 0x02;
+const FXF_CREAT: u32 = 0x08;
+const FXF_TRUNC: u32 = 0x10;
+
+// Attribute flags
+const ATTR_SIZE: u32 = 0x01;
+const ATTR_UIDGID: u32 = 0x02;
+const ATTR_PERMISSIONS: u32 = 0x04;
+const ATTR_ACMODTIME: u32 = 0x08;
+const ATTR_EXTENDED: u32 = 0x8000_0000;
+
+// Status codes
+const FX_OK: u32 = 0;
+const FX_EOF: u32 = 1;
+const FX_NO_SUCH_FILE: u32 = 2;
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+  Upload,
+  Download,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "phase", rename_all = "snake_case")]
+pub enum TransferProgress {
+  Transferring {
+    transfer_id: String,
+    direction: Direction,
+    bytes_done: u64,
+    bytes_total: u64,
+  },
+  Done {
+    transfer_id: String,
+    direction: Direction,
+    bytes_total: u64,
+    /// Bytes that were already in place from an earlier attempt
+    resumed_from: u64,
+    elapsed_secs: u64,
+  },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TransferOptions {
+  /// Event tag; lets the UI subscribe before invoking and cancel
+  pub transfer_id: String,
+  #[serde(default)]
+  pub resume: bool,
+  /// Permissions for an uploaded file, e.g. `0o755` for a script
+  pub mode: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TransferResult {
+  pub transfer_id: String,
+  pub bytes_total: u64,
+  pub resumed_from: u64,
+}
+
+#[derive(Default)]
+pub struct Transfers {
+  running: Mutex<HashMap<String, Arc<AtomicBool>>>,
+}
+
+/// Cursor over a received packet payload.
+struct Reader<'a> {
+  buf: &'a [u8],
+  pos: usize,
+}
+
+impl<'a> Reader<'a> {
+  fn new(buf: &'a [u8]) -> Self {
+    Self { buf, pos: 0 }
+  }
+
+  fn bytes(&mut self, len: usize) -> Result<&'a [u8], String> {
+    let end = self.pos.checked_add(len).filter(|&end| end <= self.buf.len());
+    let end = end.ok_or("Truncated SFTP packet")?;
+    let out = &self.buf[self.pos..end];
+    self.pos = end;
+    Ok(out)
+  }
+
+  fn u32(&mut self) -> Result<u32, String> {
+    let b = self.bytes(4)?;
+    Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+  }
+
+  fn u64(&mut self) -> Result<u64, String> {
+    Ok((u64::from(self.u32()?) << 32) | u64::from(self.u32()?))
+  }
+
+  fn string(&mut self) -> Result<&'a [u8], String> {
+    let len = self.u32()? as usize;
+    self.bytes(len)
+  }
+
+  fn is_empty(&self) -> bool {
+    self.pos >= self.buf.len()
+  }
+
+  /// The size field of an ATTRS structure, skipping the rest.
+  fn attrs_size(&mut self) -> Result<Option<u64>, String> {
+    let flags = self.u32()?;
+    let size = if flags & ATTR_SIZE != 0 { Some(self.u64()?) } else { None };
+    if flags & ATTR_UIDGID != 0 {
+      self.bytes(8)?;
+    }
+    if flags & ATTR_PERMISSIONS != 0 {
+      self.bytes(4)?;
+    }
+    if flags & ATTR_ACMODTIME != 0 {
+      self.bytes(8)?;
+    }
+    if flags & ATTR_EXTENDED != 0 {
+      for _ in 0..self.u32()? {
+        self.string()?;
+        self.string()?;
+      }
+    }
+    Ok(size)
+  }
+}
+
+fn put_u32(out: &mut Vec<u8>, value: u32) {
+  out.extend_from_slice(&value.to_be_bytes());
+}
+
+fn put_u64(out: &mut Vec<u8>, value: u64) {
+  out.extend_from_slice(&value.to_be_bytes());
+}
+
+fn put_string(out: &mut Vec<u8>, value: &[u8]) {
+  put_u32(out, value.len() as u32);
+  out.extend_from_slice(value);
+}
+
+struct Reply {
+  kind: u8,
+  id: u32,
+  body: Vec<u8>,
+}
+
+impl Reply {
+  fn reader(&self) -> Reader<'_> {
+    Reader::new(&self.body)
+  }
+
+  /// `(code, message)` of a STATUS reply.
+  fn status(&self) -> Result<(u32, String), String> {
+    let mut r = self.reader();
+    let code = r.u32()?;
+    let message = if r.is_empty() { String::new() } else { String::from_utf8_lossy(r.string()?).to_string() };
+    Ok((code, message))
+  }
+
+  fn expect_ok(&self, what: &str) -> Result<(), String> {
+    if self.kind != FXP_STATUS {
+      return Err(format!("{}: unexpected SFTP reply type {}", what, self.kind));
+    }
+    match self.status()? {
+      (FX_OK, _) => Ok(()),
+      (code, message) => Err(format!("{}: {} (SFTP status {})", what, message, code)),
+    }
+  }
+}
+
+struct Sftp {
+  child: Child,
+  stdin: ChildStdin,
+  stdout: BufReader<ChildStdout>,
+  next_id: u32,
+  posix_rename: bool,
+}
+
+impl Sftp {
+  fn connect(workspace: &Workspace, pool: &SshPool, target: &SshTarget) -> Result<Self, String> {
+    let mut child = ssh::subsystem(workspace, pool, target, "sftp")?
+      .stdin(Stdio::piped())
+      .stdout(Stdio::piped())
+      .stderr(Stdio::null())
+      .spawn()
+      .map_err(|e| format!("Failed to run ssh: {}", e))?;
+    let stdin = child.stdin.take().ok_or("ssh stdin unavailable")?;
+    let stdout = BufReader::new(child.stdout.take().ok_or("ssh stdout unavailable")?);
+    let mut sftp = Self {
+      child,
+      stdin,
+      stdout,
+      next_id: 0,
+      posix_rename: false,
+    };
+
+    let mut init = Vec::new();
+    put_u32(&mut init, 3);
+    sftp.send(FXP_INIT, &init)?;
+    let (kind, body) = sftp.read_packet()?;
+    if kind != FXP_VERSION {
+      return Err(format!("{} did not start an SFTP session", target.host));
+    }
+    let mut r = Reader::new(&body);
+    let version = r.u32()?;
+    if version < 3 {
+      return Err(format!("{} speaks SFTP version {}, need 3", target.host, version));
+    }
+    while !r.is_empty() {
+      let name = r.string()?;
+      r.string()?;
+      if name == b"posix-rename@openssh.com" {
+        sftp.posix_rename = true;
+      }
+    }
+    Ok(sftp)
+  }
+
+  fn send(&mut self, kind: u8, payload: &[u8]) -> Result<(), String> {
+    let mut packet = Vec::with_capacity(payload.len() + 5);
+    put_u32(&mut packet, payload.len() as u32 + 1);
+    packet.push(kind);
+    packet.extend_from_slice(payload);
+    self
+      .stdin
+      .write_all(&packet)
+      .map_err(|e| format!("SFTP connection lost: {}", e))
+  }
+
+  fn read_packet(&mut self) -> Result<(u8, Vec<u8>), String> {
+    let mut header = [0u8; 5];
+    self
+      .stdout
+      .read_exact(&mut header)
+      .map_err(|e| format!("SFTP connection lost: {}", e))?;
+    let len = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+    if len == 0 || len > 4 * 1024 * 1024 {
+      return Err(format!("Invalid SFTP packet length {}", len));
+    }
+    let mut body = vec![0u8; len - 1];
+    self
+      .stdout
+      .read_exact(&mut body)
+      .map_err(|e| format!("SFTP connection lost: {}", e))?;
+    Ok((header[4], body))
+  }
+
+  /// Send a request whose payload follows the id; returns the id.
+  fn request(&mut self, kind: u8, build: impl FnOnce(&mut Vec<u8>)) -> Result<u32, String> {
+    self.next_id = self.next_id.wrapping_add(1);
+    let id = self.next_id;
+    let mut payload = Vec::new();
+    put_u32(&mut payload, id);
+    build(&mut payload);
+    self.send(kind, &payload)?;
+    Ok(id)
+  }
+
+  fn recv(&mut self) -> Result<Reply, String> {
+    let (kind, body) = self.read_packet()?;
+    let mut r = Reader::new(&body);
+    let id = r.u32()?;
+    Ok(Reply {
+      kind,
+      id,
+      body: body[4..].to_vec(),
+    })
+  }
+
+  /// Send a request and wait for its reply, for the one-at-a-time calls.
+  fn call(&mut self, kind: u8, build: impl FnOnce(&mut Vec<u8>)) -> Result<Reply, String> {
+    let id = self.request(kind, build)?;
+    let reply = self.recv()?;
+    if reply.id != id {
+      return Err(format!("SFTP reply for request {} while waiting for {}", reply.id, id));
+    }
+    Ok(reply)
+  }
+
+  /// Size of `path`, or `None` if it doesn't exist.
+  fn stat(&mut self, path: &str) -> Result<Option<u64>, String> {
+    let reply = self.call(FXP_STAT, |p| put_string(p, path.as_bytes()))?;
+    match reply.kind {
+      FXP_ATTRS => Ok(Some(reply.reader().attrs_size()?.unwrap_or(0))),
+      FXP_STATUS => match reply.status()? {
+        (FX_NO_SUCH_FILE, _) => Ok(None),
+        (code, message) => Err(format!("Cannot stat {}: {} (SFTP status {})", path, message, code)),
+      },
+      kind => Err(format!("Cannot stat {}: unexpected SFTP reply type {}", path, kind)),
+    }
+  }
+
+  fn open(&mut self, path: &str, flags: u32, mode: Option<u32>) -> Result<Vec<u8>, String> {
+    let reply = self.call(FXP_OPEN, |p| {
+      put_string(p, path.as_bytes());
+      put_u32(p, flags);
+      match mode {
+        Some(mode) => {
+          put_u32(p, ATTR_PERMISSIONS);
+          put_u32(p, mode);
+        }
+        None => put_u32(p, 0),
+      }
+    })?;
+    match reply.kind {
+      FXP_HANDLE => Ok(reply.reader().string()?.to_vec()),
+      FXP_STATUS => {
+        let (code, message) = reply.status()?;
+        Err(format!("Cannot open {}: {} (SFTP status {})", path, message, code))
+      }
+      kind => Err(format!("Cannot open {}: unexpected SFTP reply type {}", path, kind)),
+    }
+  }
+
+  /// Up to `len` bytes of an open file from `at`; fewer at its end.
+  fn read_at(&mut self, handle: &[u8], at: u64, len: u32) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    while (out.len() as u32) < len {
+      let pos = at + out.len() as u64;
+      let want = len - out.len() as u32;
+      let reply = self.call(FXP_READ, |p| {
+        put_string(p, handle);
+        put_u64(p, pos);
+        put_u32(p, want);
+      })?;
+      match reply.kind {
+        FXP_DATA => {
+          let data = reply.reader().string()?;
+          if data.is_empty() {
+            break;
+          }
+          out.extend_from_slice(data);
+        }
+        FXP_STATUS => match reply.status()? {
+          (FX_EOF, _) => break,
+          (code, message) => return Err(format!("Reading remote file: {} (SFTP status {})", message, code)),
+        },
+        kind => return Err(format!("Reading remote file: unexpected SFTP reply type {}", kind)),
+      }
+    }
+    Ok(out)
+  }
+
+  fn close(&mut self, handle: &[u8]) -> Result<(), String> {
+    self
+      .call(FXP_CLOSE, |p| put_string(p, handle))?
+      .expect_ok("Closing remote file")
+  }
+
+  fn set_mode(&mut self, path: &str, mode: u32) -> Result<(), String> {
+    self
+      .call(FXP_SETSTAT, |p| {
+        put_string(p, path.as_bytes());
+        put_u32(p, ATTR_PERMISSIONS);
+        put_u32(p, mode);
+      })?
+      .expect_ok(&format!("Setting permissions on {}", path))
+  }
+
+  /// Move `from` over `to`, replacing it.
+  fn replace(&mut self, from: &str, to: &str) -> Result<(), String> {
+    let what = format!("Renaming {} to {}", from, to);
+    if self.posix_rename {
+      return self
+        .call(FXP_EXTENDED, |p| {
+          put_string(p, b"posix-rename@openssh.com");
+          put_string(p, from.as_bytes());
+          put_string(p, to.as_bytes());
+        })?
+        .expect_ok(&what);
+    }
+    // Plain v3 RENAME refuses to overwrite
+    if self.stat(to)?.is_some() {
+      self
+        .call(FXP_REMOVE, |p| put_string(p, to.as_bytes()))?
+        .expect_ok(&format!("Removing {}", to))?;
+    }
+    self
+      .call(FXP_RENAME, |p| {
+        put_string(p, from.as_bytes());
+        put_string(p, to.as_bytes());
+      })?
+      .expect_ok(&what)
+  }
+}
+
+impl Drop for Sftp {
+  fn drop(&mut self) {
+    let _ = self.child.kill();
+    let _ = self.child.wait();
+  }
+}
+
+struct Progress<'a> {
+  app: &'a AppHandle,
+  transfer_id: &'a str,
+  direction: Direction,
+  bytes_total: u64,
+  last: Instant,
+}
+
+impl Progress<'_> {
+  fn report(&mut self, bytes_done: u64) {
+    if self.last.elapsed() < PROGRESS_INTERVAL && bytes_done < self.bytes_total {
+      return;
+    }
+    self.last = Instant::now();
+    let _ = self.app.emit(
+      PROGRESS_EVENT,
+      TransferProgress::Transferring {
+        transfer_id: self.transfer_id.to_string(),
+        direction: self.direction,
+        bytes_done,
+        bytes_total: self.bytes_total,
+      },
+    );
+  }
+}
+
+fn check_cancel(cancel: &AtomicBool) -> Result<(), String> {
+  if cancel.load(Ordering::SeqCst) {
+    Err("Transfer cancelled".to_string())
+  } else {
+    Ok(())
+  }
+}
+
+/// The chunk before `offset`, which both ends must agree on to resume.
+fn resume_check_range(offset: u64) -> (u64, u32) {
+  let len = offset.min(u64::from(CHUNK));
+  (offset - len, len as u32)
+}
+
+fn read_local_at(file: &mut File, at: u64, len: u32) -> std::io::Result<Vec<u8>> {
+  let mut buf = vec![0u8; len as usize];
+  file.seek(SeekFrom::Start(at))?;
+  file.read_exact(&mut buf)?;
+  Ok(buf)
+}
+
+fn upload(
+  sftp: &mut Sftp,
+  local: &str,
+  remote: &str,
+  options: &TransferOptions,
+  progress: &mut Progress,
+  cancel: &AtomicBool,
+) -> Result<u64, String> {
+  let mut file = File::open(local).map_err(|e| format!("Failed to open {}: {}", local, e))?;
+  let total = progress.bytes_total;
+  let part = format!("{}{}", remote, PART_SUFFIX);
+
+  let mut offset = if options.resume {
+    sftp.stat(&part)?.filter(|&size| size <= total).unwrap_or(0)
+  } else {
+    0
+  };
+  if offset > 0 {
+    let (at, len) = resume_check_range(offset);
+    let part_handle = sftp.open(&part, FXF_READ, None)?;
+    let theirs = sftp.read_at(&part_handle, at, len);
+    sftp.close(&part_handle)?;
+    let ours = read_local_at(&mut file, at, len).map_err(|e| format!("Failed to read {}: {}", local, e))?;
+    if theirs? != ours {
+      info!("{} changed since {} was written; starting over", local, part);
+      offset = 0;
+    }
+  }
+  let flags = FXF_WRITE | FXF_CREAT | if offset == 0 { FXF_TRUNC } else { 0 };
+  let handle = sftp.open(&part, flags, Some(0o600))?;
+  file
+    .seek(SeekFrom::Start(offset))
+    .map_err(|e| format!("Failed to read {}: {}", local, e))?;
+
+  let mut sent = offset;
+  let mut done = offset;
+  let mut in_flight: VecDeque<(u32, u64)> = VecDeque::new();
+  let mut buf = vec![0u8; CHUNK as usize];
+  while done < total {
+    check_cancel(cancel)?;
+    while in_flight.len() < WINDOW && sent < total {
+      let len = (total - sent).min(u64::from(CHUNK)) as usize;
+      file
+        .read_exact(&mut buf[..len])
+        .map_err(|e| format!("Failed to read {}: {}", local, e))?;
+      let id = sftp.request(FXP_WRITE, |p| {
+        put_string(p, &handle);
+        put_u64(p, sent);
+        put_string(p, &buf[..len]);
+      })?;
+      in_flight.push_back((id, len as u64));
+      sent += len as u64;
+    }
+
+    let reply = sftp.recv()?;
+    let at = in_flight
+      .iter()
+      .position(|(id, _)| *id == reply.id)
+      .ok_or_else(|| format!("Unexpected SFTP reply {}", reply.id))?;
+    let (_, len) = in_flight.remove(at).unwrap_or_default();
+    reply.expect_ok(&format!("Writing {}", part))?;
+    done += len;
+    progress.report(done);
+  }
+
+  sftp.close(&handle)?;
+  sftp.replace(&part, remote)?;
+  if let Some(mode) = options.mode {
+    sftp.set_mode(remote, mode & 0o7777)?;
+  }
+  Ok(offset)
+}
+
+fn download(
+  sftp: &mut Sftp,
+  remote: &str,
+  local: &str,
+  options: &TransferOptions,
+  progress: &mut Progress,
+  cancel: &AtomicBool,
+) -> Result<u64, String> {
+  let total = progress.bytes_total;
+  let part = format!("{}{}", local, PART_SUFFIX);
+
+  let handle = sftp.open(remote, FXF_READ, None)?;
+  let existing = std::fs::metadata(&part).map(|m| m.len()).unwrap_or(0);
+  let mut offset = if options.resume && existing <= total { existing } else { 0 };
+  if offset > 0 {
+    let (at, len) = resume_check_range(offset);
+    let ours = File::open(&part)
+      .and_then(|mut f| read_local_at(&mut f, at, len))
+      .map_err(|e| format!("Failed to read {}: {}", part, e))?;
+    if sftp.read_at(&handle, at, len)? != ours {
+      info!("{} changed since {} was written; starting over", remote, part);
+      offset = 0;
+    }
+  }
+  let mut file = OpenOptions::new()
+    .write(true)
+    .create(true)
+    .truncate(false)
+    .open(&part)
+    .map_err(|e| format!("Failed to open {}: {}", part, e))?;
+  file
+    .set_len(offset)
+    .map_err(|e| format!("Failed to write {}: {}", part, e))?;
+
+  let mut next = offset;
+  let mut done = offset;
+  let mut eof = false;
+  // Ranges still to request, after short reads
+  let mut pending: VecDeque<(u64, u32)> = VecDeque::new();
+  let mut in_flight: HashMap<u32, (u64, u32)> = HashMap::new();
+  loop {
+    check_cancel(cancel)?;
+    while in_flight.len() < WINDOW && !eof {
+      let (at, len) = match pending.pop_front() {
+        Some(range) => range,
+        None if next < total => {
+          let len = (total - next).min(u64::from(CHUNK)) as u32;
+          next += u64::from(len);
+          (next - u64::from(len), len)
+        }
+        None => break,
+      };
+      let id = sftp.request(FXP_READ, |p| {
+        put_string(p, &handle);
+        put_u64(p, at);
+        put_u32(p, len);
+      })?;
+      in_flight.insert(id, (at, len));
+    }
+    if in_flight.is_empty() {
+      break;
+    }
+
+    let reply = sftp.recv()?;
+    let (at, len) = in_flight
+      .remove(&reply.id)
+      .ok_or_else(|| format!("Unexpected SFTP reply {}", reply.id))?;
+    match reply.kind {
+      FXP_DATA => {
+        let data = reply.reader().string()?;
+        file
+          .seek(SeekFrom::Start(at))
+          .and_then(|_| file.write_all(data))
+          .map_err(|e| format!("Failed to write {}: {}", part, e))?;
+        done += data.len() as u64;
+        if (data.len() as u32) < len {
+          pending.push_back((at + data.len() as u64, len - data.len() as u32));
+        }
+        progress.report(done);
+      }
+      FXP_STATUS => match reply.status()? {
+        // The file shrank since we stat'ed it
+        (FX_EOF, _) => eof = true,
+        (code, message) => return Err(format!("Reading {}: {} (SFTP status {})", remote, message, code)),
+      },
+      kind => return Err(format!("Reading {}: unexpected SFTP reply type {}", remote, kind)),
+    }
+  }
+  sftp.close(&handle)?;
+
+  if done != total {
+    return Err(format!("{} changed during the transfer ({} of {} bytes read)", remote, done, total));
+  }
+  file
+    .sync_all()
+    .map_err(|e| format!("Failed to write {}: {}", part, e))?;
+  drop(file);
+  std::fs::rename(&part, local).map_err(|e| format!("Failed to move {} into place: {}", part, e))?;
+  Ok(offset)
+}
+
+fn transfer(
+  app: &AppHandle,
+  target: &SshTarget,
+  direction: Direction,
+  source: &str,
+  destination: &str,
+  options: TransferOptions,
+) -> Result<TransferResult, String> {
+  let transfers = app.state::<Transfers>();
+  let cancel = Arc::new(AtomicBool::new(false));
+  {
+    let mut running = transfers.running.lock().map_err(|e| e.to_string())?;
+    if running.contains_key(&options.transfer_id) {
+      return Err(format!("A transfer with id {} is already running", options.transfer_id));
+    }
+    running.insert(options.transfer_id.clone(), cancel.clone());
+  }
+  let _operation = cancel::watch(app, &format!("transfer to or from {}", target.host), cancel.clone());
+
+  let started = Instant::now();
+  let result = (|| {
+    let mut sftp = Sftp::connect(&app.state::<Workspace>(), &app.state::<SshPool>(), target)?;
+    let bytes_total = match direction {
+      Direction::Upload => std::fs::metadata(source)
+        .map_err(|e| format!("Failed to read {}: {}", source, e))?
+        .len(),
+      Direction::Download => sftp
+        .stat(source)?
+        .ok_or_else(|| format!("{} does not exist on {}", source, target.host))?,
+    };
+    let mut progress = Progress {
+      app,
+      transfer_id: &options.transfer_id,
+      direction,
+      bytes_total,
+      last: Instant::now() - PROGRESS_INTERVAL,
+    };
+    progress.report(0);
+
+    let resumed_from = match direction {
+      Direction::Upload => {
+        audit::file_write(&target.host, &target.user, destination, bytes_total, options.mode);
+        upload(&mut sftp, source, destination, &options, &mut progress, &cancel)?
+      }
+      Direction::Download => download(&mut sftp, source, destination, &options, &mut progress, &cancel)?,
+    };
+    info!(
+      "{:?} of {} ({} bytes) to/from {} finished",
+      direction, source, bytes_total, target.host
+    );
+    let _ = app.emit(
+      PROGRESS_EVENT,
+      TransferProgress::Done {
+        transfer_id: options.transfer_id.clone(),
+        direction,
+        bytes_total,
+        resumed_from,
+        elapsed_secs: started.elapsed().as_secs(),
+      },
+    );
+    Ok(TransferResult {
+      transfer_id: options.transfer_id.clone(),
+      bytes_total,
+      resumed_from,
+    })
+  })();
+
+  if let Ok(mut running) = transfers.running.lock() {
+    running.remove(&options.transfer_id);
+  }
+  result
+}
+
+fn check_remote_path(path: &str) -> Result<(), String> {
+  if path.is_empty() || path.contains('\0') {
+    return Err(format!("Invalid remote path: {:?}", path));
+  }
+  Ok(())
+}
+
+#[tauri::command]
+pub async fn upload_file(
+  app: AppHandle,
+  host: SshTarget,
+  local_path: String,
+  remote_path: String,
+  options: TransferOptions,
+) -> Result<TransferResult, String> {
+  host.validate()?;
+  check_remote_path(&remote_path)?;
+  tauri::async_runtime::spawn_blocking(move || {
+    transfer(&app, &host, Direction::Upload, &local_path, &remote_path, options)
+  })
+  .await
+  .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn download_file(
+  app: AppHandle,
+  host: SshTarget,
+  remote_path: String,
+  local_path: String,
+  options: TransferOptions,
+) -> Result<TransferResult, String> {
+  host.validate()?;
+  check_remote_path(&remote_path)?;
+  tauri::async_runtime::spawn_blocking(move || {
+    transfer(&app, &host, Direction::Download, &remote_path, &local_path, options)
+  })
+  .await
+  .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub fn cancel_transfer(transfers: State<'_, Transfers>, transfer_id: String) -> Result<(), String> {
+  let running = transfers.running.lock().map_err(|e| e.to_string())?;
+  running
+    .get(&transfer_id)
+    .ok_or_else(|| format!("No transfer with id {} is running", transfer_id))?
+    .store(true, Ordering::SeqCst);
+  Ok(())
+}
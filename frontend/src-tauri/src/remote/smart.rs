@@ -0,0 +1,318 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! SMART health of the disks that will hold cluster data.
+//!
+//! LXD, the k8s snap and container images all live under `/var`, so the
+//! disks behind `/` and `/var` and below are read, plus any disk the
+//! user picked for the LXD pool. `smartctl --json` reports both SATA/SAS
+//! attributes and the NVMe health log; a drive the firmware calls failing,
+//! with pending or uncorrectable sectors, past its rated endurance or out
+//! of spare blocks fails the check, and reallocated sectors, media errors
+//! or heavy wear warn. Virtual disks and most USB bridges have no SMART
+//! data and are reported as such rather than as healthy.
+//!
+//! It needs `smartmontools` and passwordless sudo on the node; it only
+//! reads.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+use super::inventory::sections;
+use super::ssh::{self, SshPool, SshTarget};
+use crate::preflight::Outcome;
+use crate::workspace::Workspace;
+
+const SCAN_TIMEOUT: Duration = Duration::from_secs(60);
+/// Rated endurance used, in percent, from which a drive warns
+const WARN_WEAR_PERCENT: u64 = 80;
+/// Mount points whose disks hold cluster data
+const DATA_MOUNTS: &[&str] = &["/", "/var", "/var/lib", "/var/snap"];
+/// ATA attributes whose normalized value is the life left, in percent:
+/// Samsung's wear leveling count, SSD life left, Intel's media wearout
+/// indicator and Crucial's lifetime remaining
+const ATA_LIFE_LEFT: &[u64] = &[177, 231, 233, 202];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DiskHealth {
+  /// `sda`, `nvme0n1`
+  pub name: String,
+  pub model: Option<String>,
+  pub serial: Option<String>,
+  /// `ata`, `nvme`, `scsi` or `sat`
+  pub protocol: Option<String>,
+  /// What the drive's own self-assessment says; `None` without SMART
+  pub passed: Option<bool>,
+  pub power_on_hours: Option<u64>,
+  pub temperature_c: Option<u64>,
+  pub reallocated_sectors: Option<u64>,
+  pub pending_sectors: Option<u64>,
+  pub uncorrectable_sectors: Option<u64>,
+  /// Rated endurance used, in percent; over 100 past it
+  pub wear_percent: Option<u64>,
+  /// NVMe only
+  pub media_errors: Option<u64>,
+  pub available_spare_percent: Option<u64>,
+  pub outcome: Outcome,
+  pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeDiskHealth {
+  pub host: String,
+  pub disks: Vec<DiskHealth>,
+  pub outcome: Outcome,
+  pub message: String,
+}
+
+/// Script reading SMART data for the data disks and `extra` ones, which
+/// must be plain device names.
+pub fn scan_script(extra: &[String]) -> String {
+  let mounts = DATA_MOUNTS.join(" ");
+  format!(
+    r#"
+export LC_ALL=C
+disks=$(for m in {mounts}; do
+  src=$(findmnt -n -o SOURCE --target "$m" 2>/dev/null | sed 's/\[.*//') || continue
+  lsblk -s -n -r -o NAME,TYPE "$src" 2>/dev/null
+done | awk '$2 == "disk" {{ print $1 }}')
+command -v smartctl > /dev/null 2>&1 || echo '@@no-smartctl'
+for d in $(printf '%s\n' $disks {extra} | sort -u); do
+  echo "@@disk $d"
+  sudo -n smartctl --json=c -a "/dev/$d" 2>/dev/null
+done
+echo '@@end'
+"#,
+    mounts = mounts,
+    extra = extra.join(" ")
+  )
+}
+
+fn ata_attribute(json: &Value, id: u64) -> Option<&Value> {
+  json["ata_smart_attributes"]["table"]
+    .as_array()?
+    .iter()
+    .find(|a| a["id"].as_u64() == Some(id))
+}
+
+fn ata_raw(json: &Value, id: u64) -> Option<u64> {
+  ata_attribute(json, id)?["raw"]["value"].as_u64()
+}
+
+fn text(v: &Value) -> Option<String> {
+  v.as_str().map(|s| s.trim().to_string()).filter(|s| !s.is_empty())
+}
+
+fn disk_health(name: &str, lines: &[&str]) -> DiskHealth {
+  let json = serde_json::from_str::<Value>(&lines.join("\n")).unwrap_or(Value::Null);
+  let nvme = &json["nvme_smart_health_information_log"];
+  let wear_percent = nvme["percentage_used"].as_u64().or_else(|| {
+    ATA_LIFE_LEFT
+      .iter()
+      .find_map(|id| ata_attribute(&json, *id)?["value"].as_u64())
+      .map(|left| 100u64.saturating_sub(left))
+  });
+  let mut disk = DiskHealth {
+    name: name.to_string(),
+    model: text(&json["model_name"]),
+    serial: text(&json["serial_number"]),
+    protocol: text(&json["device"]["protocol"]).map(|p| p.to_lowercase()),
+    passed: json["smart_status"]["passed"].as_bool(),
+    power_on_hours: json["power_on_time"]["hours"].as_u64(),
+    temperature_c: json["temperature"]["current"].as_u64(),
+    reallocated_sectors: ata_raw(&json, 5),
+    pending_sectors: ata_raw(&json, 197),
+    uncorrectable_sectors: ata_raw(&json, 198),
+    wear_percent,
+    media_errors: nvme["media_errors"].as_u64(),
+    available_spare_percent: nvme["available_spare"].as_u64(),
+    outcome: Outcome::Pass,
+    message: String::new(),
+  };
+  let spare_threshold = nvme["available_spare_threshold"].as_u64();
+  let critical_warning = nvme["critical_warning"].as_u64().unwrap_or(0);
+
+  let mut failing = Vec::new();
+  let mut worn = Vec::new();
+  if disk.passed == Some(false) {
+    failing.push("the drive reports itself as failing".to_string());
+  }
+  if critical_warning != 0 {
+    failing.push(format!("NVMe critical warning {:#04x}", critical_warning));
+  }
+  for (count, what) in [
+    (disk.pending_sectors, "sectors pending reallocation"),
+    (disk.uncorrectable_sectors, "uncorrectable sectors"),
+  ] {
+    if let Some(n) = count.filter(|n| *n > 0) {
+      failing.push(format!("{} {}", n, what));
+    }
+  }
+  if let (Some(spare), Some(threshold)) = (disk.available_spare_percent, spare_threshold) {
+    if spare < threshold {
+      failing.push(format!(
+        "{}% spare blocks left, below the {}% threshold",
+        spare, threshold
+      ));
+    }
+  }
+  match disk.wear_percent {
+    Some(wear) if wear >= 100 => failing.push(format!("{}% of its rated endurance used", wear)),
+    Some(wear) if wear >= WARN_WEAR_PERCENT => worn.push(format!("{}% of its rated endurance used", wear)),
+    _ => {}
+  }
+  if let Some(n) = disk.reallocated_sectors.filter(|n| *n > 0) {
+    worn.push(format!("{} reallocated sectors", n));
+  }
+  if let Some(n) = disk.media_errors.filter(|n| *n > 0) {
+    worn.push(format!("{} media errors", n));
+  }
+
+  let label = disk.model.clone().unwrap_or_else(|| name.to_string());
+  (disk.outcome, disk.message) = if !failing.is_empty() {
+    (
+      Outcome::Fail,
+      format!(
+        "{} ({}): {}",
+        name,
+        label,
+        failing.into_iter().chain(worn).collect::<Vec<_>>().join(", ")
+      ),
+    )
+  } else if !worn.is_empty() {
+    (Outcome::Warn, format!("{} ({}): {}", name, label, worn.join(", ")))
+  } else if disk.passed.is_none() {
+    (Outcome::Skipped, format!("{} reports no SMART data", name))
+  } else {
+    (Outcome::Pass, format!("{} ({}) is healthy", name, label))
+  };
+  disk
+}
+
+/// The disks in the output of `scan_script`.
+pub fn parse(output: &str) -> Result<Vec<DiskHealth>, String> {
+  let sections = sections(output);
+  if sections.contains_key("no-smartctl") {
+    return Err("smartmontools is not installed".to_string());
+  }
+  let mut disks: Vec<DiskHealth> = sections
+    .iter()
+    .filter_map(|(name, lines)| Some(disk_health(name.strip_prefix("disk ")?, lines)))
+    .collect();
+  disks.sort_by(|a, b| a.name.cmp(&b.name));
+  Ok(disks)
+}
+
+/// The worst disk decides; disks without SMART data only matter when
+/// none has any.
+pub fn verdict(disks: &[DiskHealth]) -> (Outcome, String) {
+  if disks.is_empty() {
+    return (Outcome::Warn, "Found no disks behind the data mounts".to_string());
+  }
+  let problems: Vec<&DiskHealth> = disks
+    .iter()
+    .filter(|d| matches!(d.outcome, Outcome::Fail | Outcome::Warn))
+    .collect();
+  if !problems.is_empty() {
+    let outcome = if problems.iter().any(|d| d.outcome == Outcome::Fail) {
+      Outcome::Fail
+    } else {
+      Outcome::Warn
+    };
+    let messages: Vec<&str> = problems.iter().map(|d| d.message.as_str()).collect();
+    return (outcome, messages.join("; "));
+  }
+  if disks.iter().all(|d| d.outcome == Outcome::Skipped) {
+    return (
+      Outcome::Warn,
+      "No SMART data for the data disks; a virtual disk, a USB bridge or sudo not allowed".to_string(),
+    );
+  }
+  let names: Vec<&str> = disks
+    .iter()
+    .filter(|d| d.outcome == Outcome::Pass)
+    .map(|d| d.name.as_str())
+    .collect();
+  (Outcome::Pass, format!("{} healthy", names.join(", ")))
+}
+
+fn check(workspace: &Workspace, pool: &SshPool, target: &SshTarget, extra: &[String]) -> NodeDiskHealth {
+  let result = ssh::run_script(workspace, pool, target, &scan_script(extra), SCAN_TIMEOUT).and_then(|output| {
+    if !output.stdout.contains("@@end") {
+      return Err(format!("Scan did not complete: {}", output.stderr.trim()));
+    }
+    parse(&output.stdout)
+  });
+  match result {
+    Ok(disks) => {
+      let (outcome, message) = verdict(&disks);
+      NodeDiskHealth {
+        host: target.host.clone(),
+        disks,
+        outcome,
+        message,
+      }
+    }
+    Err(e) => NodeDiskHealth {
+      host: target.host.clone(),
+      disks: Vec::new(),
+      outcome: Outcome::Skipped,
+      message: format!("Skipped: {}", e),
+    },
+  }
+}
+
+/// Read SMART health of each node's data disks and of `disks`, the ones
+/// picked for the LXD pool, by name.
+#[tauri::command]
+pub async fn check_disk_health(
+  app: AppHandle,
+  hosts: Vec<SshTarget>,
+  disks: Option<Vec<String>>,
+) -> Result<Vec<NodeDiskHealth>, String> {
+  for target in &hosts {
+    target.validate()?;
+  }
+  let extra: Vec<String> = disks
+    .unwrap_or_default()
+    .iter()
+    .map(|d| d.trim().trim_start_matches("/dev/").to_string())
+    .collect();
+  if let Some(bad) = extra
+    .iter()
+    .find(|d| d.is_empty() || !d.chars().all(|c| c.is_ascii_alphanumeric()))
+  {
+    return Err(format!("Invalid disk name: {}", bad));
+  }
+  tauri::async_runtime::spawn_blocking(move || {
+    let workspace = app.state::<Workspace>();
+    let workspace: &Workspace = &workspace;
+    let pool = app.state::<SshPool>();
+    let pool: &SshPool = &pool;
+    let extra = &extra;
+    std::thread::scope(|scope| {
+      let handles: Vec<_> = hosts
+        .iter()
+        .map(|target| scope.spawn(move || check(workspace, pool, target, extra)))
+        .collect();
+      handles
+        .into_iter()
+        .zip(&hosts)
+        .map(|(handle, target)| {
+          handle.join().unwrap_or_else(|_| NodeDiskHealth {
+            host: target.host.clone(),
+            disks: Vec::new(),
+            outcome: Outcome::Skipped,
+            message: "Skipped: disk health check panicked".to_string(),
+          })
+        })
+        .collect()
+    })
+  })
+  .await
+  .map_err(|e| e.to_string())
+}
@@ -0,0 +1,714 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Running commands on nodes through the system `ssh`.
+//!
+//! Authentication is key-only (`BatchMode=yes`): by the time the wizard
+//! talks to nodes from here, SSH setup has installed the cluster key, or
+//! the user picked a key their ssh-agent holds (see [`super::agent`]). Host
+//! keys are checked strictly against the ones the user confirmed (see
+//! [`super::host_keys`]), written to a `known_hosts` file in the run
+//! workspace, so the user's own `~/.ssh/known_hosts` is never modified and
+//! a node with an unconfirmed or changed key is refused.
+//!
+//! Connections are pooled with OpenSSH multiplexing. The first command for
+//! a host starts a background master (`ssh -M -N -f`) with a control socket
+//! in a private directory with a random name under `$XDG_RUNTIME_DIR` or
+//! `/tmp` (socket paths are limited to ~100 bytes, too short for the app
+//! data dir on macOS); later commands reuse it, so preflight checks that
+//! run dozens of small commands don't pay for a handshake each. Masters time out on their own after `ControlPersist`
+//! and are closed explicitly when the app exits. A node with a `jump_host`
+//! is reached through it by the master's `ProxyCommand`, so commands, file
+//! transfers and log streams to it all go through the bastion.
+//!
+//! `run_remote` streams each output line as a `remote-output` event tagged
+//! with a run id the caller picks, so the UI can subscribe before invoking.
+//! Commands can run under `sudo`, either passwordless (`sudo -n`) or with a
+//! password fed on stdin, never on the command line. Besides the overall
+//! timeout, a command can be given a stall limit: if it prints nothing for
+//! that long it's killed as stuck (apt waiting on a lock, a host that went
+//! away), and the result keeps the last lines it did print.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use super::agent;
+use crate::{audit, cancel, redact};
+use crate::platform::find_program;
+use crate::validation;
+use crate::workspace::{create_private_dir, Workspace};
+
+pub const OUTPUT_EVENT: &str = "remote-output";
+
+/// Runs the script fed to its stdin
+const SCRIPT_SHELL: &str = "bash -s";
+const CONNECT_TIMEOUT_SECS: u32 = 10;
+const CONTROL_PERSIST_SECS: u32 = 300;
+const DEFAULT_RUN_TIMEOUT_SECS: u64 = 300;
+const MAX_RUN_TIMEOUT_SECS: u64 = 4 * 3600;
+/// Lines of output kept for the result of a command that didn't finish
+const TAIL_LINES: usize = 20;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SshTarget {
+  pub host: String,
+  pub user: String,
+  pub port: Option<u16>,
+  /// Private key to use instead of the user's default keys
+  pub identity_file: Option<String>,
+  /// Fingerprint of the ssh-agent identity to use instead, see `agent`
+  pub agent_key: Option<String>,
+  /// `[user@]host[:port]` of the bastion the node is only reachable
+  /// through; the user defaults to the node's
+  pub jump_host: Option<String>,
+}
+
+impl SshTarget {
+  pub fn validate(&self) -> Result<(), String> {
+    if !validation::is_valid_host(&self.host) {
+      return Err(format!("Invalid host: {}", self.host));
+    }
+    if !validation::is_valid_user(&self.user) {
+      return Err(format!("Invalid SSH user: {}", self.user));
+    }
+    if self.identity_file.is_some() && self.agent_key.is_some() {
+      return Err("Choose either a key file or an ssh-agent key, not both".to_string());
+    }
+    self.jump_target()?;
+    Ok(())
+  }
+
+  /// The jump host as a target of its own, authenticating like the node.
+  pub fn jump_target(&self) -> Result<Option<SshTarget>, String> {
+    let Some(jump) = self.jump_host.as_deref().filter(|j| !j.is_empty()) else {
+      return Ok(None);
+    };
+    let invalid = || format!("Invalid jump host: {}", jump);
+    let (user, address) = match jump.split_once('@') {
+      Some((user, address)) => (user.to_string(), address),
+      None => (self.user.clone(), jump),
+    };
+    let (host, port) = match address.strip_prefix('[') {
+      Some(bracketed) => {
+        let (host, rest) = bracketed.split_once(']').ok_or_else(invalid)?;
+        let port = match rest.strip_prefix(':') {
+          Some(port) => Some(port.parse::<u16>().map_err(|_| invalid())?),
+          None if rest.is_empty() => None,
+          None => return Err(invalid()),
+        };
+        (host, port)
+      }
+      None => match address.split_once(':') {
+        Some((host, port)) => (host, Some(port.parse::<u16>().map_err(|_| invalid())?)),
+        None => (address, None),
+      },
+    };
+    if !validation::is_valid_user(&user) || !validation::is_valid_host(host) || host == self.host {
+      return Err(invalid());
+    }
+    Ok(Some(SshTarget {
+      host: host.to_string(),
+      user,
+      port,
+      identity_file: self.identity_file.clone(),
+      agent_key: self.agent_key.clone(),
+      jump_host: None,
+    }))
+  }
+
+  fn port(&self) -> u16 {
+    self.port.unwrap_or(22)
+  }
+
+  fn destination(&self) -> String {
+    format!("{}@{}", self.user, self.host)
+  }
+}
+
+/// Per (destination, port) lock, held while checking or starting its master
+type MasterLocks = HashMap<(String, u16), Arc<Mutex<()>>>;
+
+/// Control sockets of the master connections opened this session.
+#[derive(Default)]
+pub struct SshPool {
+  control_dir: Mutex<Option<PathBuf>>,
+  masters: Mutex<MasterLocks>,
+  running: Mutex<HashMap<String, Arc<AtomicBool>>>,
+  next_run: AtomicU64,
+}
+
+impl SshPool {
+  fn control_dir(&self) -> Result<PathBuf, String> {
+    let mut dir = self.control_dir.lock().map_err(|e| e.to_string())?;
+    if let Some(dir) = dir.as_ref() {
+      return Ok(dir.clone());
+    }
+    let name = crate::backend::random_hex(8)?;
+    let base = std::env::var_os("XDG_RUNTIME_DIR")
+      .map(PathBuf::from)
+      .filter(|d| d.is_dir())
+      .unwrap_or_else(|| PathBuf::from("/tmp"));
+    let path = base.join(format!("thinkube-ssh-{}", name));
+    // Never one that already exists, which another user could have made
+    let mut builder = std::fs::DirBuilder::new();
+    #[cfg(unix)]
+    {
+      use std::os::unix::fs::DirBuilderExt;
+      builder.mode(0o700);
+    }
+    builder
+      .create(&path)
+      .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+    create_private_dir(&path)?;
+    *dir = Some(path.clone());
+    Ok(path)
+  }
+
+  /// Kill the remote command running as `run_id`; false if there's none.
+  pub fn cancel(&self, run_id: &str) -> bool {
+    let Some(cancel) = self.running.lock().ok().and_then(|r| r.get(run_id).cloned()) else {
+      return false;
+    };
+    cancel.store(true, Ordering::SeqCst);
+    true
+  }
+
+  /// Ask every master we started to exit and remove the socket directory.
+  pub fn close_all(&self) {
+    let Some(dir) = self.control_dir.lock().ok().and_then(|mut d| d.take()) else {
+      return;
+    };
+    let masters: Vec<_> = self
+      .masters
+      .lock()
+      .map(|mut m| m.drain().map(|(key, _)| key).collect())
+      .unwrap_or_default();
+    if let Some(ssh) = find_program("ssh", &[]) {
+      for (destination, port) in masters {
+        exit_master(&ssh, &dir, &destination, port);
+      }
+    }
+    let _ = std::fs::remove_dir_all(dir);
+  }
+
+  /// Drop the master for `target`, e.g. after its network was reconfigured
+  /// and the connection may be dead; the next command opens a fresh one.
+  pub fn close(&self, target: &SshTarget) {
+    let Some(dir) = self.control_dir.lock().ok().and_then(|d| d.clone()) else {
+      return;
+    };
+    let key = (target.destination(), target.port());
+    let Some(lock) = self.masters.lock().ok().and_then(|m| m.get(&key).cloned()) else {
+      return;
+    };
+    let _guard = lock.lock();
+    if let Some(ssh) = find_program("ssh", &[]) {
+      exit_master(&ssh, &dir, &key.0, key.1);
+    }
+  }
+}
+
+fn exit_master(ssh: &Path, control_dir: &Path, destination: &str, port: u16) {
+  let _ = Command::new(ssh)
+    .args(["-p", &port.to_string()])
+    .arg("-o")
+    .arg(format!("ControlPath={}", control_path(control_dir).display()))
+    .args(["-O", "exit", destination])
+    .stdin(Stdio::null())
+    .stdout(Stdio::null())
+    .stderr(Stdio::null())
+    .status();
+}
+
+fn control_path(dir: &Path) -> PathBuf {
+  // %C hashes local host, remote host, port and user into a fixed-length name
+  dir.join("%C")
+}
+
+fn base_command(ssh: &Path, workspace: &Workspace, control_dir: &Path, target: &SshTarget) -> Result<Command, String> {
+  let known_hosts = workspace.resolve("ssh/known_hosts")?;
+  let mut cmd = Command::new(ssh);
+  cmd
+    .args(["-p", &target.port().to_string()])
+    .args(["-o", "BatchMode=yes"])
+    .args(["-o", &format!("ConnectTimeout={}", CONNECT_TIMEOUT_SECS)])
+    .args(["-o", "ServerAliveInterval=15"])
+    .args(["-o", "ServerAliveCountMax=3"])
+    .args(["-o", "StrictHostKeyChecking=yes"])
+    .arg("-o")
+    .arg(format!("UserKnownHostsFile={}", known_hosts.display()))
+    .arg("-o")
+    .arg(format!("ControlPath={}", control_path(control_dir).display()));
+  cmd.args(identity_args(workspace, target)?);
+  // A ProxyCommand rather than ProxyJump, which would check the jump host's
+  // key against the user's known_hosts and might prompt for it
+  if let Some(jump) = target.jump_target()? {
+    let mut proxy = vec![
+      ssh.display().to_string(),
+      "-p".to_string(),
+      jump.port().to_string(),
+      "-o".to_string(),
+      "BatchMode=yes".to_string(),
+      "-o".to_string(),
+      format!("ConnectTimeout={}", CONNECT_TIMEOUT_SECS),
+      "-o".to_string(),
+      "StrictHostKeyChecking=yes".to_string(),
+      "-o".to_string(),
+      format!("UserKnownHostsFile={}", known_hosts.display()),
+    ];
+    proxy.extend(identity_args(workspace, &jump)?);
+    proxy.extend(["-W".to_string(), "%h:%p".to_string(), jump.destination()]);
+    let proxy: Vec<String> = proxy.iter().map(|arg| shell_quote(arg)).collect();
+    cmd.arg("-o").arg(format!("ProxyCommand={}", proxy.join(" ")));
+  }
+  Ok(cmd)
+}
+
+/// How `target` authenticates: an ssh-agent identity, a key file, or the
+/// user's default keys.
+fn identity_args(workspace: &Workspace, target: &SshTarget) -> Result<Vec<String>, String> {
+  if let Some(fingerprint) = &target.agent_key {
+    let (socket, public_key) = agent::identity_file(workspace, fingerprint)?;
+    return Ok(vec![
+      "-o".to_string(),
+      format!("IdentityAgent={}", socket.display()),
+      "-o".to_string(),
+      "IdentitiesOnly=yes".to_string(),
+      "-i".to_string(),
+      public_key.display().to_string(),
+    ]);
+  }
+  Ok(match &target.identity_file {
+    Some(identity) => vec![
+      "-o".to_string(),
+      "IdentitiesOnly=yes".to_string(),
+      "-i".to_string(),
+      identity.clone(),
+    ],
+    None => Vec::new(),
+  })
+}
+
+/// Make sure a master connection to `target` is up, starting one if needed.
+fn ensure_master(ssh: &Path, workspace: &Workspace, pool: &SshPool, target: &SshTarget) -> Result<PathBuf, String> {
+  let control_dir = pool.control_dir()?;
+  let lock = pool
+    .masters
+    .lock()
+    .map_err(|e| e.to_string())?
+    .entry((target.destination(), target.port()))
+    .or_default()
+    .clone();
+  // Parallel callers for the same host wait here rather than racing two masters
+  let _guard = lock.lock().map_err(|e| e.to_string())?;
+
+  let alive = base_command(ssh, workspace, &control_dir, target)?
+    .args(["-O", "check", &target.destination()])
+    .stdin(Stdio::null())
+    .stdout(Stdio::null())
+    .stderr(Stdio::null())
+    .status()
+    .map(|s| s.success())
+    .unwrap_or(false);
+  if alive {
+    return Ok(control_dir);
+  }
+
+  // -f backgrounds the master after authenticating; it keeps whatever
+  // stderr it was given open, so errors go to a log file instead of a pipe
+  let log = workspace.resolve(&format!("ssh/{}-{}.log", target.host.replace(':', "_"), target.port()))?;
+  let status = base_command(ssh, workspace, &control_dir, target)?
+    .args(["-M", "-N", "-f"])
+    .args(["-o", "ControlMaster=yes"])
+    .args(["-o", &format!("ControlPersist={}", CONTROL_PERSIST_SECS)])
+    .arg("-E")
+    .arg(&log)
+    .arg(target.destination())
+    .stdin(Stdio::null())
+    .stdout(Stdio::null())
+    .stderr(Stdio::null())
+    .status()
+    .map_err(|e| format!("Failed to run ssh: {}", e))?;
+  if !status.success() {
+    let contents = std::fs::read_to_string(&log).unwrap_or_default();
+    if contents.contains("REMOTE HOST IDENTIFICATION HAS CHANGED") {
+      return Err(format!(
+        "The host key of {} has changed since it was trusted; refusing to connect. If the node was reinstalled, \
+         forget its old key and confirm the new one",
+        target.host
+      ));
+    }
+    if contents.contains("Host key verification failed") {
+      return Err(format!(
+        "The host key of {} has not been confirmed yet; check its fingerprint before connecting",
+        target.host
+      ));
+    }
+    let detail = contents.trim().lines().last().unwrap_or("connection failed");
+    return Err(format!("SSH to {} failed: {}", target.destination(), detail));
+  }
+  Ok(control_dir)
+}
+
+/// `ssh` with its options set to go through the pooled connection, missing
+/// only the destination and what to run there.
+fn pooled(workspace: &Workspace, pool: &SshPool, target: &SshTarget) -> Result<Command, String> {
+  target.validate()?;
+  let ssh = find_program("ssh", &[]).ok_or("ssh is not installed")?;
+  let control_dir = ensure_master(&ssh, workspace, pool, target)?;
+  let mut cmd = base_command(&ssh, workspace, &control_dir, target)?;
+  cmd.args(["-o", "ControlMaster=no"]);
+  Ok(cmd)
+}
+
+/// An `ssh` command that runs `remote_command` over the pooled connection.
+pub fn command(
+  workspace: &Workspace,
+  pool: &SshPool,
+  target: &SshTarget,
+  remote_command: &str,
+) -> Result<Command, String> {
+  let mut cmd = pooled(workspace, pool, target)?;
+  // `run_script` records the script itself
+  if remote_command != SCRIPT_SHELL {
+    audit::remote_command(&target.host, &target.user, remote_command);
+  }
+  cmd.arg(target.destination()).arg(remote_command);
+  Ok(cmd)
+}
+
+/// An `ssh` command for a login shell on a terminal, over the pooled
+/// connection.
+pub fn interactive(workspace: &Workspace, pool: &SshPool, target: &SshTarget) -> Result<Command, String> {
+  let mut cmd = pooled(workspace, pool, target)?;
+  audit::remote_shell(&target.host, &target.user);
+  cmd.arg("-tt").arg(target.destination());
+  Ok(cmd)
+}
+
+/// An `ssh` command that starts the `name` subsystem (e.g. `sftp`) over the
+/// pooled connection.
+pub fn subsystem(workspace: &Workspace, pool: &SshPool, target: &SshTarget, name: &str) -> Result<Command, String> {
+  let mut cmd = pooled(workspace, pool, target)?;
+  cmd.arg("-s").arg(target.destination()).arg(name);
+  Ok(cmd)
+}
+
+/// Single-quote `value` for the remote POSIX shell.
+pub fn shell_quote(value: &str) -> String {
+  format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// Wait for `child`, killing it when `timeout` passes or `stop` says so.
+/// `Ok(None)` means it was stopped.
+fn wait<F: Fn() -> bool>(child: &mut Child, timeout: Duration, stop: F) -> Result<Option<i32>, String> {
+  let deadline = Instant::now() + timeout;
+  loop {
+    match child.try_wait() {
+      Ok(Some(status)) => return Ok(Some(status.code().unwrap_or(-1))),
+      Ok(None) if Instant::now() < deadline && !stop() => {
+        std::thread::sleep(Duration::from_millis(100))
+      }
+      Ok(None) => {
+        let _ = child.kill();
+        let _ = child.wait();
+        return Ok(None);
+      }
+      Err(e) => return Err(format!("Failed to wait for ssh: {}", e)),
+    }
+  }
+}
+
+pub struct ScriptOutput {
+  pub status: Option<i32>,
+  pub stdout: String,
+  pub stderr: String,
+}
+
+/// Feed `script` to `bash -s` on the target and collect its output, killing
+/// ssh if it runs past `timeout`.
+pub fn run_script(
+  workspace: &Workspace,
+  pool: &SshPool,
+  target: &SshTarget,
+  script: &str,
+  timeout: Duration,
+) -> Result<ScriptOutput, String> {
+  audit::remote_command(&target.host, &target.user, script);
+  run_with_input(workspace, pool, target, SCRIPT_SHELL, script, timeout)
+}
+
+/// Run `remote_command` with `input` on its stdin and collect its output,
+/// like [`run_script`].
+pub fn run_with_input(
+  workspace: &Workspace,
+  pool: &SshPool,
+  target: &SshTarget,
+  remote_command: &str,
+  input: &str,
+  timeout: Duration,
+) -> Result<ScriptOutput, String> {
+  let mut child = command(workspace, pool, target, remote_command)?
+    .stdin(Stdio::piped())
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    .spawn()
+    .map_err(|e| format!("Failed to run ssh: {}", e))?;
+
+  if let Some(mut stdin) = child.stdin.take() {
+    let _ = stdin.write_all(input.as_bytes());
+  }
+  let mut stdout = child.stdout.take().ok_or("ssh stdout unavailable")?;
+  let mut stderr = child.stderr.take().ok_or("ssh stderr unavailable")?;
+  let out_reader = std::thread::spawn(move || {
+    let mut buf = String::new();
+    let _ = stdout.read_to_string(&mut buf);
+    buf
+  });
+  let err_reader = std::thread::spawn(move || {
+    let mut buf = String::new();
+    let _ = stderr.read_to_string(&mut buf);
+    buf
+  });
+
+  let status = wait(&mut child, timeout, || false)?
+    .ok_or_else(|| format!("{} did not finish within {}s", target.host, timeout.as_secs()))?;
+  let stdout = out_reader.join().unwrap_or_default();
+  let stderr = err_reader.join().unwrap_or_default();
+  // ssh itself exits with 255 when the connection fails
+  if status == 255 {
+    let detail = stderr.trim().lines().last().unwrap_or("connection failed");
+    return Err(format!("SSH to {} failed: {}", target.destination(), detail));
+  }
+  Ok(ScriptOutput {
+    status: Some(status),
+    stdout,
+    stderr,
+  })
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RunOptions {
+  /// Event tag; generated when omitted
+  pub run_id: Option<String>,
+  #[serde(default)]
+  pub sudo: bool,
+  /// Fed to `sudo -S` on stdin; without it sudo must be passwordless
+  pub sudo_password: Option<String>,
+  pub timeout_secs: Option<u64>,
+  /// Kill the command once it has printed nothing for this long
+  pub stall_secs: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Stream {
+  Stdout,
+  Stderr,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RemoteOutput {
+  pub run_id: String,
+  pub host: String,
+  pub stream: Stream,
+  pub line: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RemoteResult {
+  pub run_id: String,
+  pub host: String,
+  /// `None` when the command was killed
+  pub exit_code: Option<i32>,
+  pub timed_out: bool,
+  /// Killed after printing nothing for `stall_secs`
+  pub stalled: bool,
+  pub cancelled: bool,
+  pub duration_ms: u64,
+  /// The last lines printed, when the command was killed
+  pub tail: Vec<String>,
+}
+
+/// When a command last printed anything, and what.
+struct Activity {
+  last: Instant,
+  tail: VecDeque<String>,
+}
+
+impl Activity {
+  fn new() -> Arc<Mutex<Self>> {
+    Arc::new(Mutex::new(Activity {
+      last: Instant::now(),
+      tail: VecDeque::new(),
+    }))
+  }
+}
+
+fn spawn_forwarder<R: Read + Send + 'static>(
+  app: AppHandle,
+  source: R,
+  run_id: String,
+  host: String,
+  stream: Stream,
+  activity: Arc<Mutex<Activity>>,
+) -> std::thread::JoinHandle<()> {
+  std::thread::spawn(move || {
+    for chunk in BufReader::new(source).split(b'\n') {
+      let Ok(bytes) = chunk else { break };
+      let line = String::from_utf8_lossy(&bytes).trim_end_matches('\r').to_string();
+      let line = crate::redact::redact(&line).into_owned();
+      if let Ok(mut activity) = activity.lock() {
+        activity.last = Instant::now();
+        if activity.tail.len() == TAIL_LINES {
+          activity.tail.pop_front();
+        }
+        activity.tail.push_back(line.clone());
+      }
+      let _ = app.emit(
+        OUTPUT_EVENT,
+        RemoteOutput {
+          run_id: run_id.clone(),
+          host: host.clone(),
+          stream,
+          line,
+        },
+      );
+    }
+  })
+}
+
+/// `run_remote`'s body, for callers running commands as part of their own
+/// work. The output streams under `options.run_id` all the same.
+pub fn run(app: &AppHandle, target: &SshTarget, remote_command: &str, options: RunOptions) -> Result<RemoteResult, String> {
+  let pool = app.state::<SshPool>();
+  let workspace = app.state::<Workspace>();
+  let run_id = options
+    .run_id
+    .unwrap_or_else(|| format!("remote-{}", pool.next_run.fetch_add(1, Ordering::SeqCst) + 1));
+  let timeout = Duration::from_secs(
+    options
+      .timeout_secs
+      .unwrap_or(DEFAULT_RUN_TIMEOUT_SECS)
+      .clamp(1, MAX_RUN_TIMEOUT_SECS),
+  );
+  let stall = options.stall_secs.map(|secs| Duration::from_secs(secs.max(1)));
+
+  // sudo can echo it back, and the output goes to the UI and the log
+  if let Some(password) = &options.sudo_password {
+    redact::register(password);
+  }
+  let remote_command = match (options.sudo, &options.sudo_password) {
+    (false, _) => remote_command.to_string(),
+    (true, None) => format!("sudo -n -- sh -c {}", shell_quote(remote_command)),
+    (true, Some(_)) => format!("sudo -S -p '' -- sh -c {}", shell_quote(remote_command)),
+  };
+
+  let cancel = Arc::new(AtomicBool::new(false));
+  {
+    let mut running = pool.running.lock().map_err(|e| e.to_string())?;
+    if running.contains_key(&run_id) {
+      return Err(format!("A remote command with id {} is already running", run_id));
+    }
+    running.insert(run_id.clone(), cancel.clone());
+  }
+  let _operation = cancel::watch(app, &format!("remote command on {}", target.host), cancel.clone());
+
+  let started = Instant::now();
+  let result = (|| {
+    let mut child = command(&workspace, &pool, target, &remote_command)?
+      .stdin(Stdio::piped())
+      .stdout(Stdio::piped())
+      .stderr(Stdio::piped())
+      .spawn()
+      .map_err(|e| format!("Failed to run ssh: {}", e))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+      if let (true, Some(password)) = (options.sudo, &options.sudo_password) {
+        let _ = stdin.write_all(format!("{}\n", password).as_bytes());
+      }
+    }
+    let activity = Activity::new();
+    let readers = [
+      child.stdout.take().map(|out| {
+        let (run_id, host) = (run_id.clone(), target.host.clone());
+        spawn_forwarder(app.clone(), out, run_id, host, Stream::Stdout, activity.clone())
+      }),
+      child.stderr.take().map(|err| {
+        let (run_id, host) = (run_id.clone(), target.host.clone());
+        spawn_forwarder(app.clone(), err, run_id, host, Stream::Stderr, activity.clone())
+      }),
+    ];
+
+    let is_stalled = || match (stall, activity.lock()) {
+      (Some(stall), Ok(activity)) => activity.last.elapsed() >= stall,
+      _ => false,
+    };
+    let exit_code = wait(&mut child, timeout, || cancel.load(Ordering::SeqCst) || is_stalled())?;
+    for reader in readers.into_iter().flatten() {
+      let _ = reader.join();
+    }
+    let cancelled = exit_code.is_none() && cancel.load(Ordering::SeqCst);
+    let stalled = exit_code.is_none() && !cancelled && is_stalled();
+    let tail = match activity.lock() {
+      Ok(activity) if exit_code.is_none() => activity.tail.iter().cloned().collect(),
+      _ => Vec::new(),
+    };
+    Ok(RemoteResult {
+      run_id: run_id.clone(),
+      host: target.host.clone(),
+      exit_code,
+      timed_out: exit_code.is_none() && !cancelled && !stalled,
+      stalled,
+      cancelled,
+      duration_ms: started.elapsed().as_millis() as u64,
+      tail,
+    })
+  })();
+
+  if let Ok(mut running) = pool.running.lock() {
+    running.remove(&run_id);
+  }
+  result
+}
+
+/// Run a shell command on a node, streaming its output as `remote-output`
+/// events. Resolves when the command exits, times out or is cancelled.
+#[tauri::command]
+pub async fn run_remote(
+  app: AppHandle,
+  host: SshTarget,
+  command: String,
+  options: Option<RunOptions>,
+) -> Result<RemoteResult, String> {
+  host.validate()?;
+  if command.trim().is_empty() {
+    return Err("No command given".to_string());
+  }
+  tauri::async_runtime::spawn_blocking(move || run(&app, &host, &command, options.unwrap_or_default()))
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub fn cancel_remote(pool: State<'_, SshPool>, run_id: String) -> Result<(), String> {
+  if !pool.cancel(&run_id) {
+    return Err(format!("No remote command with id {} is running", run_id));
+  }
+  Ok(())
+}
+
+pub fn shutdown(app: &AppHandle) {
+  if let Some(pool) = app.try_state::<SshPool>() {
+    pool.close_all();
+  }
+}
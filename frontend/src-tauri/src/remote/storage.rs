@@ -0,0 +1,237 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Which LXD storage drivers a node can use, and which one it should.
+//!
+//! The LXD snap ships its own `zfs`, `lvm` and `btrfs` tools, so what
+//! really decides support is the kernel: the `zfs`, `dm_thin_pool` and
+//! `btrfs` modules. Host tools are reported too since the playbooks use
+//! them for checks. Existing pools and volume groups matter most: a node
+//! that already has a healthy zpool or a volume group with free space
+//! should get its LXD pool there rather than in a new loop file.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+use super::disks::{parse_disks, parse_vgs, parse_zpools};
+use super::inventory::sections;
+use super::ssh::{self, SshPool, SshTarget};
+use crate::workspace::Workspace;
+
+const DETECT_TIMEOUT: Duration = Duration::from_secs(30);
+/// Free space in a volume group worth putting a pool in
+const MIN_VG_FREE_BYTES: u64 = 20 * 1024 * 1024 * 1024;
+
+const SCRIPT: &str = r#"
+export LC_ALL=C
+echo '@@tools'
+for t in zfs zpool lvm btrfs; do
+  echo "$t $(command -v $t >/dev/null 2>&1 && echo yes || echo no)"
+done
+echo '@@modules'
+for m in zfs dm_thin_pool btrfs; do
+  if [ -d "/sys/module/$m" ]; then echo "$m loaded"
+  elif modinfo -n "$m" >/dev/null 2>&1; then echo "$m available"
+  else echo "$m missing"; fi
+done
+echo '@@lsblk'
+lsblk -J -b -o NAME,PATH,SIZE,TYPE,FSTYPE,MOUNTPOINT,LABEL,MODEL,ROTA 2>/dev/null
+echo '@@vgs'
+sudo -n vgs --reportformat json --units b --nosuffix -o vg_name,vg_size,vg_free 2>/dev/null
+echo '@@zpool'
+zpool list -Hp -o name,size,free,health 2>/dev/null
+echo '@@btrfs'
+findmnt -t btrfs -n -o TARGET 2>/dev/null
+echo '@@lxd'
+snap list lxd 2>/dev/null | awk 'NR == 2 { print $2 }'
+echo '@@end'
+"#;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BackendSupport {
+  /// LXD storage driver name: `zfs`, `lvm`, `btrfs` or `dir`
+  pub driver: String,
+  /// The kernel can run it; the LXD snap brings the userspace tools
+  pub supported: bool,
+  pub kernel_module: bool,
+  pub host_tools: bool,
+  /// Existing pools, volume groups or btrfs mounts
+  pub existing: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StorageBackends {
+  pub host: String,
+  pub error: Option<String>,
+  pub lxd_version: Option<String>,
+  pub backends: Vec<BackendSupport>,
+  /// Disks with no partitions or signatures
+  pub blank_disks: Vec<String>,
+  pub recommended: Option<String>,
+  pub reason: Option<String>,
+}
+
+fn parse(host: &str, output: &str) -> StorageBackends {
+  let sections = sections(output);
+  let empty = Vec::new();
+  let section = |name: &str| sections.get(name).unwrap_or(&empty);
+  let pairs = |name: &str| -> HashMap<String, String> {
+    section(name)
+      .iter()
+      .filter_map(|l| l.split_once(' '))
+      .map(|(k, v)| (k.to_string(), v.trim().to_string()))
+      .collect()
+  };
+  let tools = pairs("tools");
+  let modules = pairs("modules");
+  let has_tool = |t: &str| tools.get(t).map(String::as_str) == Some("yes");
+  let has_module = |m: &str| matches!(modules.get(m).map(String::as_str), Some("loaded" | "available"));
+
+  let zpools: Vec<_> = parse_zpools(section("zpool"))
+    .into_iter()
+    .filter(|p| p.health == "ONLINE")
+    .collect();
+  let vgs = parse_vgs(section("vgs"));
+  let btrfs_mounts: Vec<String> = section("btrfs")
+    .iter()
+    .map(|l| l.trim().to_string())
+    .filter(|l| !l.is_empty())
+    .collect();
+  let blank_disks: Vec<String> = parse_disks(section("lsblk"))
+    .into_iter()
+    .filter(|d| d.is_blank())
+    .map(|d| d.path)
+    .collect();
+
+  let backends = vec![
+    BackendSupport {
+      driver: "zfs".to_string(),
+      supported: has_module("zfs"),
+      kernel_module: has_module("zfs"),
+      host_tools: has_tool("zfs") && has_tool("zpool"),
+      existing: zpools.iter().map(|p| p.name.clone()).collect(),
+    },
+    BackendSupport {
+      driver: "lvm".to_string(),
+      supported: has_module("dm_thin_pool"),
+      kernel_module: has_module("dm_thin_pool"),
+      host_tools: has_tool("lvm"),
+      existing: vgs.iter().map(|vg| vg.name.clone()).collect(),
+    },
+    BackendSupport {
+      driver: "btrfs".to_string(),
+      supported: has_module("btrfs"),
+      kernel_module: has_module("btrfs"),
+      host_tools: has_tool("btrfs"),
+      existing: btrfs_mounts,
+    },
+    BackendSupport {
+      driver: "dir".to_string(),
+      supported: true,
+      kernel_module: true,
+      host_tools: true,
+      existing: Vec::new(),
+    },
+  ];
+  let supported = |driver: &str| backends.iter().any(|b| b.driver == driver && b.supported);
+
+  let roomy_vg = vgs
+    .iter()
+    .filter(|vg| vg.free_bytes >= MIN_VG_FREE_BYTES)
+    .max_by_key(|vg| vg.free_bytes);
+  let (recommended, reason) = if let Some(pool) = zpools.first().filter(|_| supported("zfs")) {
+    ("zfs", format!("Use the existing ZFS pool {}", pool.name))
+  } else if let Some(vg) = roomy_vg.filter(|_| supported("lvm")) {
+    (
+      "lvm",
+      format!(
+        "Volume group {} has {} GiB free for a thin pool",
+        vg.name,
+        vg.free_bytes / (1024 * 1024 * 1024)
+      ),
+    )
+  } else if supported("zfs") {
+    let on = blank_disks.first().map(String::as_str).unwrap_or("a loop file");
+    ("zfs", format!("ZFS gives snapshots and compression; create the pool on {}", on))
+  } else if supported("btrfs") {
+    (
+      "btrfs",
+      "The kernel has no ZFS module; btrfs still gives copy-on-write snapshots".to_string(),
+    )
+  } else if supported("lvm") {
+    ("lvm", "LVM thin provisioning is the best driver this kernel supports".to_string())
+  } else {
+    (
+      "dir",
+      "No copy-on-write driver is available; containers will be full copies".to_string(),
+    )
+  };
+
+  StorageBackends {
+    host: host.to_string(),
+    error: None,
+    lxd_version: section("lxd")
+      .first()
+      .map(|v| v.trim().to_string())
+      .filter(|v| !v.is_empty()),
+    backends,
+    blank_disks,
+    recommended: Some(recommended.to_string()),
+    reason: Some(reason),
+  }
+}
+
+fn detect(workspace: &Workspace, pool: &SshPool, target: &SshTarget) -> StorageBackends {
+  let failed = |error: String| StorageBackends {
+    host: target.host.clone(),
+    error: Some(error),
+    ..Default::default()
+  };
+  match ssh::run_script(workspace, pool, target, SCRIPT, DETECT_TIMEOUT) {
+    Ok(output) if output.stdout.contains("@@end") => parse(&target.host, &output.stdout),
+    Ok(output) => failed(format!(
+      "Storage detection did not complete on {}: {}",
+      target.host,
+      output.stderr.trim()
+    )),
+    Err(e) => failed(e),
+  }
+}
+
+/// Report the storage drivers each node supports and recommend one for its
+/// LXD pool.
+#[tauri::command]
+pub async fn detect_storage_backends(app: AppHandle, hosts: Vec<SshTarget>) -> Result<Vec<StorageBackends>, String> {
+  for target in &hosts {
+    target.validate()?;
+  }
+  tauri::async_runtime::spawn_blocking(move || {
+    let workspace = app.state::<Workspace>();
+    let workspace: &Workspace = &workspace;
+    let pool = app.state::<SshPool>();
+    let pool: &SshPool = &pool;
+    std::thread::scope(|scope| {
+      let handles: Vec<_> = hosts
+        .iter()
+        .map(|target| scope.spawn(move || detect(workspace, pool, target)))
+        .collect();
+      handles
+        .into_iter()
+        .zip(&hosts)
+        .map(|(handle, target)| {
+          handle.join().unwrap_or_else(|_| StorageBackends {
+            host: target.host.clone(),
+            error: Some("Storage detection panicked".to_string()),
+            ..Default::default()
+          })
+        })
+        .collect()
+    })
+  })
+  .await
+  .map_err(|e| e.to_string())
+}
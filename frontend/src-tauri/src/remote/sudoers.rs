@@ -0,0 +1,330 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Passwordless sudo for the install user.
+//!
+//! Ansible becomes root with `sudo -n` on every node (and on the installer
+//! host for the local plays), so the install user needs a `NOPASSWD` rule.
+//! `preview_passwordless_sudo` says where that rule would go and what it
+//! would say, and whether sudo already works without a password anyway;
+//! `setup_passwordless_sudo` takes the sudo password once and installs it
+//! as a drop-in in `/etc/sudoers.d`. The drop-in is written to a temporary
+//! name `sudo` ignores, checked with `visudo`, then moved into place, and
+//! removed again should the whole configuration stop parsing, so a bad rule
+//! can never lock anyone out of sudo. It isn't journaled: a rollback needs
+//! passwordless sudo itself.
+
+use serde::Serialize;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tracing::info;
+
+use super::ssh::{self, shell_quote, ScriptOutput, SshPool, SshTarget};
+use crate::preflight::Outcome;
+use crate::workspace::Workspace;
+use crate::{audit, dry_run, redact, validation};
+
+const CHECK_TIMEOUT: Duration = Duration::from_secs(30);
+const INSTALL_TIMEOUT: Duration = Duration::from_secs(60);
+const LOCALHOST: &str = "localhost";
+
+const CHECK_SCRIPT: &str = r#"
+export LC_ALL=C
+if sudo -n true 2>/dev/null; then echo passwordless; fi
+echo '@@end'
+"#;
+
+/// Run as root; `{path}` and `{contents}` are shell quoted.
+const INSTALL_SCRIPT: &str = r#"
+set -e
+export LC_ALL=C
+PATH=/usr/sbin:/usr/bin:/sbin:/bin:$PATH
+umask 077
+if ! grep -Eq '^[#@]includedir[[:space:]]+(/private)?/etc/sudoers\.d' /etc/sudoers; then
+  echo '/etc/sudoers does not include /etc/sudoers.d' >&2
+  exit 3
+fi
+# sudo skips names with a dot, so the rule only counts once it is moved
+tmp=$(mktemp /etc/sudoers.d/.thinkube.XXXXXX)
+trap 'rm -f "$tmp"' EXIT
+printf '%s' {contents} > "$tmp"
+visudo -cqf "$tmp"
+chown 0:0 "$tmp"
+chmod 0440 "$tmp"
+mv -f "$tmp" {path}
+if ! visudo -cq; then
+  rm -f {path}
+  echo 'The sudoers configuration did not parse with the rule added' >&2
+  exit 4
+fi
+echo installed
+"#;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SudoersPlan {
+  /// The node, or `localhost` for the installer host
+  pub host: String,
+  pub user: String,
+  pub path: String,
+  pub contents: String,
+  /// sudo already works without a password, so nothing would change
+  pub passwordless: bool,
+  /// Why the node couldn't be checked
+  pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeSudoers {
+  pub host: String,
+  pub outcome: Outcome,
+  pub message: String,
+}
+
+#[derive(Clone, Copy)]
+enum Machine<'a> {
+  Local,
+  Node(&'a SshTarget),
+}
+
+impl Machine<'_> {
+  fn host(&self) -> &str {
+    match self {
+      Machine::Local => LOCALHOST,
+      Machine::Node(target) => &target.host,
+    }
+  }
+}
+
+/// The drop-in for `user`; sudo ignores files with a dot in their name.
+pub fn drop_in(user: &str) -> (String, String) {
+  (
+    format!("/etc/sudoers.d/thinkube-{}", user.replace('.', "_")),
+    format!(
+      "# Added by the Thinkube installer so Ansible can become root\n{} ALL=(ALL:ALL) NOPASSWD: ALL\n",
+      user
+    ),
+  )
+}
+
+pub fn run_local(program: &str, args: &[&str], input: &str) -> Result<ScriptOutput, String> {
+  if program == "sudo" {
+    audit::local_sudo(args);
+  }
+  let mut child = Command::new(program)
+    .args(args)
+    .stdin(Stdio::piped())
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    .spawn()
+    .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+  if let Some(mut stdin) = child.stdin.take() {
+    let _ = stdin.write_all(input.as_bytes());
+  }
+  let output = child.wait_with_output().map_err(|e| e.to_string())?;
+  Ok(ScriptOutput {
+    status: output.status.code(),
+    stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+    stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+  })
+}
+
+fn local_user() -> Result<String, String> {
+  if !cfg!(unix) {
+    return Err("sudo is not available on this platform".to_string());
+  }
+  let output = run_local("id", &["-un"], "")?;
+  let user = output.stdout.trim().to_string();
+  if !validation::is_valid_user(&user) {
+    return Err(format!("Invalid local user: {}", user));
+  }
+  Ok(user)
+}
+
+fn user(machine: Machine) -> Result<String, String> {
+  match machine {
+    Machine::Local => local_user(),
+    Machine::Node(target) => Ok(target.user.clone()),
+  }
+}
+
+fn is_passwordless(workspace: &Workspace, pool: &SshPool, machine: Machine) -> Result<bool, String> {
+  let output = match machine {
+    Machine::Local => run_local("sh", &["-s"], CHECK_SCRIPT)?,
+    Machine::Node(target) => ssh::run_script(workspace, pool, target, CHECK_SCRIPT, CHECK_TIMEOUT)?,
+  };
+  if !output.stdout.contains("@@end") {
+    return Err(format!("Check did not complete: {}", output.stderr.trim()));
+  }
+  Ok(output.stdout.lines().any(|l| l.trim() == "passwordless"))
+}
+
+fn plan(workspace: &Workspace, pool: &SshPool, machine: Machine) -> SudoersPlan {
+  let user = user(machine);
+  let (path, contents) = drop_in(user.as_deref().unwrap_or_default());
+  let passwordless = user
+    .as_ref()
+    .map_err(Clone::clone)
+    .and_then(|_| is_passwordless(workspace, pool, machine));
+  SudoersPlan {
+    host: machine.host().to_string(),
+    user: user.unwrap_or_default(),
+    path,
+    contents,
+    passwordless: passwordless.as_ref().is_ok_and(|p| *p),
+    error: passwordless.err(),
+  }
+}
+
+fn install(workspace: &Workspace, pool: &SshPool, machine: Machine, plan: &SudoersPlan, password: &str) -> NodeSudoers {
+  let result = |outcome, message| NodeSudoers {
+    host: plan.host.clone(),
+    outcome,
+    message,
+  };
+  if let Some(e) = &plan.error {
+    return result(Outcome::Skipped, format!("Skipped: {}", e));
+  }
+  if plan.passwordless {
+    return result(Outcome::Pass, "sudo already works without a password".to_string());
+  }
+
+  let script = INSTALL_SCRIPT
+    .replace("{path}", &shell_quote(&plan.path))
+    .replace("{contents}", &shell_quote(&plan.contents));
+  let input = format!("{}\n", password);
+  let output = match machine {
+    Machine::Local => run_local("sudo", &["-S", "-p", "", "--", "sh", "-c", &script], &input),
+    Machine::Node(target) => ssh::run_with_input(
+      workspace,
+      pool,
+      target,
+      &format!("sudo -S -p '' -- sh -c {}", shell_quote(&script)),
+      &input,
+      INSTALL_TIMEOUT,
+    ),
+  };
+  let output = match output {
+    Ok(output) => output,
+    Err(e) => return result(Outcome::Fail, e),
+  };
+  if output.status != Some(0) || !output.stdout.lines().any(|l| l.trim() == "installed") {
+    let stderr = output.stderr.trim();
+    let message = if stderr.contains("incorrect password") || stderr.contains("Sorry, try again") {
+      format!("The sudo password for {} was not accepted", plan.user)
+    } else if stderr.contains("not in the sudoers file") {
+      format!("{} is not allowed to use sudo", plan.user)
+    } else {
+      format!(
+        "Failed to install {}: {}",
+        plan.path,
+        stderr.lines().last().unwrap_or("command failed")
+      )
+    };
+    return result(Outcome::Fail, message);
+  }
+
+  match is_passwordless(workspace, pool, machine) {
+    Ok(true) => {
+      info!("Installed {} on {}", plan.path, plan.host);
+      result(Outcome::Pass, format!("Installed {}", plan.path))
+    }
+    Ok(false) => result(
+      Outcome::Warn,
+      format!(
+        "Installed {} but sudo still asks for a password; a later rule may override it",
+        plan.path
+      ),
+    ),
+    Err(e) => result(
+      Outcome::Warn,
+      format!("Installed {} but could not check it: {}", plan.path, e),
+    ),
+  }
+}
+
+fn machines(hosts: &[SshTarget], include_local: bool) -> Vec<Machine<'_>> {
+  include_local
+    .then_some(Machine::Local)
+    .into_iter()
+    .chain(hosts.iter().map(Machine::Node))
+    .collect()
+}
+
+/// What `setup_passwordless_sudo` would install on each node, and on the
+/// installer host with `include_local`.
+#[tauri::command]
+pub async fn preview_passwordless_sudo(
+  app: AppHandle,
+  hosts: Vec<SshTarget>,
+  include_local: bool,
+) -> Result<Vec<SudoersPlan>, String> {
+  for target in &hosts {
+    target.validate()?;
+  }
+  tauri::async_runtime::spawn_blocking(move || {
+    let workspace = app.state::<Workspace>();
+    let workspace: &Workspace = &workspace;
+    let pool = app.state::<SshPool>();
+    let pool: &SshPool = &pool;
+
+    std::thread::scope(|scope| {
+      let handles: Vec<_> = machines(&hosts, include_local)
+        .into_iter()
+        .map(|machine| scope.spawn(move || plan(workspace, pool, machine)))
+        .collect();
+      handles.into_iter().filter_map(|h| h.join().ok()).collect()
+    })
+  })
+  .await
+  .map_err(|e| e.to_string())
+}
+
+/// Let the install user run sudo without a password on each node, and on
+/// the installer host with `include_local`, using `sudo_password` once.
+#[tauri::command]
+pub async fn setup_passwordless_sudo(
+  app: AppHandle,
+  hosts: Vec<SshTarget>,
+  include_local: bool,
+  sudo_password: String,
+) -> Result<Vec<NodeSudoers>, String> {
+  for target in &hosts {
+    target.validate()?;
+  }
+  redact::register(&sudo_password);
+  let dry_run = dry_run::is_enabled(&app);
+  tauri::async_runtime::spawn_blocking(move || {
+    let workspace = app.state::<Workspace>();
+    let workspace: &Workspace = &workspace;
+    let pool = app.state::<SshPool>();
+    let pool: &SshPool = &pool;
+    let password = sudo_password.as_str();
+
+    std::thread::scope(|scope| {
+      let handles: Vec<_> = machines(&hosts, include_local)
+        .into_iter()
+        .map(|machine| {
+          scope.spawn(move || {
+            let plan = plan(workspace, pool, machine);
+            if dry_run && plan.error.is_none() && !plan.passwordless {
+              info!("Dry run: not installing {} on {}", plan.path, plan.host);
+              return NodeSudoers {
+                host: plan.host,
+                outcome: Outcome::Skipped,
+                message: format!("Dry run: would install {}", plan.path),
+              };
+            }
+            install(workspace, pool, machine, &plan, password)
+          })
+        })
+        .collect();
+      handles.into_iter().filter_map(|h| h.join().ok()).collect()
+    })
+  })
+  .await
+  .map_err(|e| e.to_string())
+}
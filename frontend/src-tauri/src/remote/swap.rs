@@ -0,0 +1,297 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Swap on the nodes.
+//!
+//! With its default configuration kubelet refuses to start while any swap
+//! is active, so the cluster playbooks fail on a node that still has the
+//! installer's swap file or a zram device. `check_swap` lists what is
+//! active, what `/etc/fstab` turns on at boot and the zram services;
+//! `disable_swap` turns all of it off and keeps it off across reboots,
+//! with a backup of `/etc/fstab`, journaled so a rollback turns it back on.
+
+use serde::Serialize;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tracing::info;
+
+use super::inventory::sections;
+use super::ssh::{self, shell_quote, SshPool, SshTarget};
+use crate::dry_run;
+use crate::journal::{self, Action};
+use crate::preflight::Outcome;
+use crate::validation;
+use crate::workspace::Workspace;
+
+const SCAN_TIMEOUT: Duration = Duration::from_secs(30);
+/// `swapoff` moves swapped pages back into memory, which takes a while
+const DISABLE_TIMEOUT: Duration = Duration::from_secs(300);
+pub const FSTAB_BACKUP: &str = "/etc/fstab.thinkube-bak";
+
+pub const SCAN_SCRIPT: &str = r#"
+export LC_ALL=C
+echo '@@swaps'
+tail -n +2 /proc/swaps 2>/dev/null
+echo '@@fstab'
+awk '$1 !~ /^#/ && $3 == "swap" { print $1 }' /etc/fstab 2>/dev/null
+echo '@@units'
+systemctl list-units --type=service --state=active --no-legend --plain 2>/dev/null \
+  | awk '{ print $1 }' | grep -E '^(zram-config|zramswap|systemd-zram-setup@.*)\.service$'
+echo '@@end'
+"#;
+
+const DISABLE_SCRIPT: &str = r#"
+set -e
+export LC_ALL=C
+for unit in $(systemctl list-units --type=service --state=active --no-legend --plain 2>/dev/null \
+  | awk '{ print $1 }' | grep -E '^(zram-config|zramswap|systemd-zram-setup@.*)\.service$'); do
+  sudo -n systemctl stop "$unit"
+  sudo -n systemctl mask "$unit" >/dev/null 2>&1
+  echo "unit $unit"
+done
+sudo -n swapoff -a
+if grep -qE '^[^#]\S*\s+\S+\s+swap\s' /etc/fstab; then
+  [ -f /etc/fstab.thinkube-bak ] || sudo -n cp -p /etc/fstab /etc/fstab.thinkube-bak
+  sudo -n sed -i -E 's/^([^#]\S*\s+\S+\s+swap\s.*)$/# \1 # disabled by thinkube/' /etc/fstab
+  sudo -n systemctl daemon-reload
+  echo fstab
+fi
+"#;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SwapDevice {
+  pub name: String,
+  /// `file`, `partition` or `zram`
+  pub kind: String,
+  pub size_bytes: u64,
+  pub used_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SwapStatus {
+  pub active: Vec<SwapDevice>,
+  /// Swap entries `/etc/fstab` turns on at boot
+  pub fstab_entries: Vec<String>,
+  /// Running services that set up zram swap
+  pub zram_units: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeSwap {
+  pub host: String,
+  pub status: Option<SwapStatus>,
+  pub outcome: Outcome,
+  pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DisabledSwap {
+  /// zram services stopped and masked
+  pub units: Vec<String>,
+  /// Whether `/etc/fstab` entries were commented out
+  pub fstab: bool,
+}
+
+impl SwapStatus {
+  pub fn verdict(&self) -> (Outcome, String) {
+    if !self.active.is_empty() {
+      let total: u64 = self.active.iter().map(|d| d.size_bytes).sum();
+      let names: Vec<&str> = self.active.iter().map(|d| d.name.as_str()).collect();
+      (
+        Outcome::Fail,
+        format!(
+          "{} MiB of swap active on {}; kubelet won't start until it is disabled",
+          total / (1024 * 1024),
+          names.join(", ")
+        ),
+      )
+    } else if !self.fstab_entries.is_empty() || !self.zram_units.is_empty() {
+      let sources: Vec<&str> = self
+        .fstab_entries
+        .iter()
+        .chain(&self.zram_units)
+        .map(String::as_str)
+        .collect();
+      (
+        Outcome::Warn,
+        format!("Swap is off but comes back at boot from {}", sources.join(", ")),
+      )
+    } else {
+      (Outcome::Pass, "Swap is off".to_string())
+    }
+  }
+}
+
+/// Script turning swap back on after `disable_swap`. `units` must be
+/// validated service names.
+pub fn restore_script(units: &[String], fstab: bool) -> String {
+  let mut script = String::from("set -e\n");
+  if fstab {
+    script.push_str(&format!(
+      "if [ -f {backup} ]; then sudo -n mv {backup} /etc/fstab; sudo -n systemctl daemon-reload; fi\n",
+      backup = FSTAB_BACKUP
+    ));
+  }
+  for unit in units {
+    script.push_str(&format!(
+      "sudo -n systemctl unmask {unit} >/dev/null 2>&1\nsudo -n systemctl start {unit} || true\n",
+      unit = shell_quote(unit)
+    ));
+  }
+  script.push_str("sudo -n swapon -a\n");
+  script
+}
+
+pub fn parse(output: &str) -> SwapStatus {
+  let sections = sections(output);
+  let empty = Vec::new();
+  let section = |name: &str| {
+    sections
+      .get(name)
+      .unwrap_or(&empty)
+      .iter()
+      .map(|l| l.trim())
+      .filter(|l| !l.is_empty())
+      .collect::<Vec<_>>()
+  };
+
+  let active = section("swaps")
+    .into_iter()
+    .filter_map(|line| {
+      let fields: Vec<&str> = line.split_whitespace().collect();
+      let [name, kind, size, used, ..] = fields[..] else {
+        return None;
+      };
+      let kib = |v: &str| v.parse::<u64>().unwrap_or_default() * 1024;
+      Some(SwapDevice {
+        name: name.to_string(),
+        kind: if name.starts_with("/dev/zram") {
+          "zram".to_string()
+        } else {
+          kind.to_string()
+        },
+        size_bytes: kib(size),
+        used_bytes: kib(used),
+      })
+    })
+    .collect();
+  SwapStatus {
+    active,
+    fstab_entries: section("fstab").into_iter().map(str::to_string).collect(),
+    zram_units: section("units").into_iter().map(str::to_string).collect(),
+  }
+}
+
+/// Report active and boot-time swap on each node.
+#[tauri::command]
+pub async fn check_swap(app: AppHandle, hosts: Vec<SshTarget>) -> Result<Vec<NodeSwap>, String> {
+  for target in &hosts {
+    target.validate()?;
+  }
+  tauri::async_runtime::spawn_blocking(move || {
+    let workspace = app.state::<Workspace>();
+    let workspace: &Workspace = &workspace;
+    let pool = app.state::<SshPool>();
+    let pool: &SshPool = &pool;
+
+    std::thread::scope(|scope| {
+      let handles: Vec<_> = hosts
+        .iter()
+        .map(|target| {
+          scope.spawn(move || {
+            let output = ssh::run_script(workspace, pool, target, SCAN_SCRIPT, SCAN_TIMEOUT)?;
+            if !output.stdout.contains("@@end") {
+              return Err(format!("Scan did not complete: {}", output.stderr.trim()));
+            }
+            Ok(parse(&output.stdout))
+          })
+        })
+        .collect();
+      handles
+        .into_iter()
+        .zip(&hosts)
+        .map(|(handle, target)| {
+          let result = handle.join().unwrap_or_else(|_| Err("Scan panicked".to_string()));
+          match result {
+            Ok(status) => {
+              let (outcome, message) = status.verdict();
+              NodeSwap {
+                host: target.host.clone(),
+                status: Some(status),
+                outcome,
+                message,
+              }
+            }
+            Err(e) => NodeSwap {
+              host: target.host.clone(),
+              status: None,
+              outcome: Outcome::Skipped,
+              message: format!("Skipped: {}", e),
+            },
+          }
+        })
+        .collect()
+    })
+  })
+  .await
+  .map_err(|e| e.to_string())
+}
+
+/// Turn swap off on the node and keep it off across reboots.
+#[tauri::command]
+pub async fn disable_swap(app: AppHandle, host: SshTarget) -> Result<DisabledSwap, String> {
+  host.validate()?;
+  let dry_run = dry_run::is_enabled(&app);
+  tauri::async_runtime::spawn_blocking(move || {
+    let workspace = app.state::<Workspace>();
+    let pool = app.state::<SshPool>();
+    if dry_run {
+      let output = ssh::run_script(&workspace, &pool, &host, SCAN_SCRIPT, SCAN_TIMEOUT)?;
+      let status = parse(&output.stdout);
+      info!("Dry run: not disabling swap on {}", host.host);
+      return Ok(DisabledSwap {
+        units: status.zram_units,
+        fstab: !status.fstab_entries.is_empty(),
+      });
+    }
+
+    let output = ssh::run_script(&workspace, &pool, &host, DISABLE_SCRIPT, DISABLE_TIMEOUT)?;
+    let disabled = DisabledSwap {
+      units: output
+        .stdout
+        .lines()
+        .filter_map(|l| l.trim().strip_prefix("unit "))
+        .filter(|u| validation::is_valid_unit(u))
+        .map(str::to_string)
+        .collect(),
+      fstab: output.stdout.lines().any(|l| l.trim() == "fstab"),
+    };
+    // Whatever was turned off before a failure still needs turning back on
+    if !disabled.units.is_empty() || disabled.fstab {
+      journal::record(
+        &app,
+        Action::Swap {
+          target: host.clone(),
+          units: disabled.units.clone(),
+          fstab: disabled.fstab,
+        },
+      );
+    }
+    if output.status != Some(0) {
+      let detail = output
+        .stderr
+        .trim()
+        .lines()
+        .last()
+        .unwrap_or("command failed")
+        .to_string();
+      return Err(format!("Failed to disable swap on {}: {}", host.host, detail));
+    }
+    info!("Disabled swap on {}", host.host);
+    Ok(disabled)
+  })
+  .await
+  .map_err(|e| e.to_string())?
+}
@@ -0,0 +1,442 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Hugepages and kernel tuning for GPU and AI nodes.
+//!
+//! Model servers map their weights from many files at once, and some
+//! inference stacks want their buffers in hugepages; a stock Ubuntu kernel
+//! allows too few of either. Each `WorkloadProfile` lists the sysctls it
+//! needs at least and the memory it wants reserved in hugepages.
+//! `check_kernel_tuning` reports the current hugepage allocation,
+//! transparent hugepage mode and those sysctls on each node against the
+//! profile. `apply_kernel_tuning` raises whatever falls short, now and in
+//! `/etc/sysctl.d` for later boots; values already above the minimum are
+//! left alone. The previous values are journaled, so a rollback puts them
+//! back. The kernel may reserve fewer hugepages than asked when memory is
+//! fragmented, so the result says how many it got.
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tracing::info;
+
+use super::inventory::sections;
+use super::ssh::{self, SshPool, SshTarget};
+use crate::dry_run;
+use crate::journal::{self, Action};
+use crate::preflight::Outcome;
+use crate::workspace::Workspace;
+
+const SCAN_TIMEOUT: Duration = Duration::from_secs(30);
+/// Reserving hugepages compacts memory first, which takes a while
+const APPLY_TIMEOUT: Duration = Duration::from_secs(120);
+pub const SYSCTL_FILE: &str = "/etc/sysctl.d/90-thinkube-tuning.conf";
+const HUGEPAGES: &str = "vm.nr_hugepages";
+/// More than this share of memory in hugepages starves everything else
+const MAX_HUGEPAGE_SHARE: u64 = 4;
+
+/// Every sysctl a profile may set, so journaled keys can be checked
+pub const TUNABLES: &[&str] = &[
+  HUGEPAGES,
+  "fs.inotify.max_user_instances",
+  "fs.inotify.max_user_watches",
+  "vm.max_map_count",
+  "net.core.somaxconn",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkloadProfile {
+  /// Nodes without AI workloads
+  General,
+  /// Model serving
+  Inference,
+  /// Fine-tuning and training
+  Training,
+}
+
+impl WorkloadProfile {
+  /// Minimum value of each sysctl
+  fn sysctls(self) -> &'static [(&'static str, u64)] {
+    match self {
+      WorkloadProfile::General => &[
+        ("fs.inotify.max_user_instances", 8192),
+        ("fs.inotify.max_user_watches", 524288),
+      ],
+      WorkloadProfile::Inference => &[
+        ("fs.inotify.max_user_instances", 8192),
+        ("fs.inotify.max_user_watches", 524288),
+        ("vm.max_map_count", 262144),
+        ("net.core.somaxconn", 4096),
+      ],
+      WorkloadProfile::Training => &[
+        ("fs.inotify.max_user_instances", 8192),
+        ("fs.inotify.max_user_watches", 524288),
+        ("vm.max_map_count", 1048576),
+        ("net.core.somaxconn", 4096),
+      ],
+    }
+  }
+
+  /// Memory to reserve in hugepages
+  fn hugepage_mib(self) -> u64 {
+    match self {
+      WorkloadProfile::General => 0,
+      WorkloadProfile::Inference => 2048,
+      WorkloadProfile::Training => 8192,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SysctlValue {
+  pub key: String,
+  /// `None` when the kernel doesn't have it
+  pub value: Option<u64>,
+  pub minimum: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TuningStatus {
+  pub hugepages_total: u64,
+  pub hugepages_free: u64,
+  pub hugepage_size_kb: u64,
+  pub hugepages_wanted: u64,
+  /// `always`, `madvise` or `never`
+  pub transparent_hugepages: Option<String>,
+  pub memory_bytes: u64,
+  pub sysctls: Vec<SysctlValue>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeTuning {
+  pub host: String,
+  pub status: Option<TuningStatus>,
+  pub outcome: Outcome,
+  pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AppliedTuning {
+  /// `key=value` of each sysctl raised
+  pub changed: Vec<String>,
+  /// Hugepages reserved afterwards
+  pub hugepages_total: u64,
+  pub hugepages_wanted: u64,
+}
+
+fn scan_script(profile: WorkloadProfile) -> String {
+  let mut script = String::from(
+    "export LC_ALL=C\n\
+     echo '@@meminfo'\n\
+     grep -E '^(MemTotal|HugePages_Total|HugePages_Free|Hugepagesize):' /proc/meminfo\n\
+     echo '@@thp'\n\
+     cat /sys/kernel/mm/transparent_hugepage/enabled 2>/dev/null\n\
+     echo '@@sysctl'\n",
+  );
+  for (key, _) in profile.sysctls() {
+    script.push_str(&format!("echo \"{k} $(sysctl -n {k} 2>/dev/null)\"\n", k = key));
+  }
+  script.push_str("echo '@@end'\n");
+  script
+}
+
+fn parse(profile: WorkloadProfile, output: &str) -> TuningStatus {
+  let sections = sections(output);
+  let empty = Vec::new();
+  let meminfo = |name: &str| {
+    sections
+      .get("meminfo")
+      .unwrap_or(&empty)
+      .iter()
+      .find_map(|l| l.strip_prefix(name)?.strip_prefix(':'))
+      .and_then(|v| v.split_whitespace().next()?.parse::<u64>().ok())
+      .unwrap_or(0)
+  };
+  // `always [madvise] never`: the bracketed one is in effect
+  let transparent_hugepages = sections.get("thp").and_then(|lines| {
+    let line = lines.first()?;
+    let start = line.find('[')?;
+    let end = line[start..].find(']')?;
+    Some(line[start + 1..start + end].to_string())
+  });
+  let sysctls = profile
+    .sysctls()
+    .iter()
+    .map(|(key, minimum)| SysctlValue {
+      key: key.to_string(),
+      value: sections.get("sysctl").unwrap_or(&empty).iter().find_map(|l| {
+        let (name, value) = l.trim().split_once(' ')?;
+        (name == *key).then(|| value.trim().parse().ok())?
+      }),
+      minimum: *minimum,
+    })
+    .collect();
+  // 2 MiB on x86, but 512 MiB on arm64 kernels with 64k pages
+  let hugepage_size_kb = match meminfo("Hugepagesize") {
+    0 => 2048,
+    size => size,
+  };
+  TuningStatus {
+    hugepages_total: meminfo("HugePages_Total"),
+    hugepages_free: meminfo("HugePages_Free"),
+    hugepage_size_kb,
+    hugepages_wanted: (profile.hugepage_mib() * 1024 + hugepage_size_kb - 1) / hugepage_size_kb,
+    transparent_hugepages,
+    memory_bytes: meminfo("MemTotal") * 1024,
+    sysctls,
+  }
+}
+
+impl TuningStatus {
+  fn hugepage_bytes(&self, pages: u64) -> u64 {
+    pages * self.hugepage_size_kb * 1024
+  }
+
+  pub fn verdict(&self) -> (Outcome, String) {
+    let wanted_bytes = self.hugepage_bytes(self.hugepages_wanted);
+    if self.memory_bytes > 0 && wanted_bytes > self.memory_bytes / MAX_HUGEPAGE_SHARE {
+      return (
+        Outcome::Fail,
+        format!(
+          "{} MiB of hugepages would take more than a quarter of the node's {} MiB of memory",
+          wanted_bytes / (1024 * 1024),
+          self.memory_bytes / (1024 * 1024)
+        ),
+      );
+    }
+    let mut short = Vec::new();
+    if self.hugepages_total < self.hugepages_wanted {
+      short.push(format!(
+        "{} hugepages reserved, {} wanted",
+        self.hugepages_total, self.hugepages_wanted
+      ));
+    }
+    for sysctl in &self.sysctls {
+      match sysctl.value {
+        None => short.push(format!("{} is missing", sysctl.key)),
+        Some(value) if value < sysctl.minimum => short.push(format!(
+          "{} is {}, at least {} wanted",
+          sysctl.key, value, sysctl.minimum
+        )),
+        Some(_) => {}
+      }
+    }
+    if short.is_empty() {
+      (
+        Outcome::Pass,
+        format!("{} hugepages reserved and the kernel is tuned", self.hugepages_total),
+      )
+    } else {
+      (Outcome::Warn, short.join("; "))
+    }
+  }
+
+  /// `(key, current, wanted)` of each sysctl to raise
+  fn shortfalls(&self) -> Vec<(String, u64, u64)> {
+    let mut raise: Vec<(String, u64, u64)> = self
+      .sysctls
+      .iter()
+      .filter_map(|s| {
+        let value = s.value?;
+        (value < s.minimum).then(|| (s.key.clone(), value, s.minimum))
+      })
+      .collect();
+    if self.hugepages_total < self.hugepages_wanted {
+      raise.push((HUGEPAGES.to_string(), self.hugepages_total, self.hugepages_wanted));
+    }
+    raise
+  }
+}
+
+/// Script raising each `(key, _, wanted)` now and at boot; the keys come
+/// from a profile, so they need no quoting.
+fn apply_script(raise: &[(String, u64, u64)]) -> String {
+  let mut script = format!(
+    "set -e\n[ -f {file} ] || echo '# Written by the Thinkube installer' | sudo -n tee {file} >/dev/null\n",
+    file = SYSCTL_FILE
+  );
+  for (key, _, wanted) in raise {
+    script.push_str(&format!(
+      "sudo -n sysctl -q -w {k}={v}\n\
+       echo 'set {k}'\n\
+       sudo -n sed -i '/^{k} = /d' {file}\n\
+       echo '{k} = {v}' | sudo -n tee -a {file} >/dev/null\n",
+      k = key,
+      v = wanted,
+      file = SYSCTL_FILE
+    ));
+  }
+  script.push_str("awk '/^HugePages_Total:/ { print \"hugepages \" $2 }' /proc/meminfo\n");
+  script
+}
+
+/// Script putting `previous` values back after `apply_kernel_tuning`.
+/// The keys must be among `TUNABLES`.
+pub fn restore_script(previous: &[(String, u64)]) -> String {
+  let mut script = String::from("set -e\n");
+  for (key, value) in previous {
+    script.push_str(&format!(
+      "sudo -n sysctl -q -w {k}={v}\n\
+       [ ! -f {file} ] || sudo -n sed -i '/^{k} = /d' {file}\n",
+      k = key,
+      v = value,
+      file = SYSCTL_FILE
+    ));
+  }
+  // Only the header left
+  script.push_str(&format!(
+    "if [ -f {file} ] && ! grep -qv '^#' {file}; then sudo -n rm -f {file}; fi\n",
+    file = SYSCTL_FILE
+  ));
+  script
+}
+
+fn scan(
+  workspace: &Workspace,
+  pool: &SshPool,
+  target: &SshTarget,
+  profile: WorkloadProfile,
+) -> Result<TuningStatus, String> {
+  let output = ssh::run_script(workspace, pool, target, &scan_script(profile), SCAN_TIMEOUT)?;
+  if !output.stdout.contains("@@end") {
+    return Err(format!("Scan did not complete: {}", output.stderr.trim()));
+  }
+  Ok(parse(profile, &output.stdout))
+}
+
+/// Report hugepages and the profile's sysctls on each node.
+#[tauri::command]
+pub async fn check_kernel_tuning(
+  app: AppHandle,
+  hosts: Vec<SshTarget>,
+  profile: WorkloadProfile,
+) -> Result<Vec<NodeTuning>, String> {
+  for target in &hosts {
+    target.validate()?;
+  }
+  tauri::async_runtime::spawn_blocking(move || {
+    let workspace = app.state::<Workspace>();
+    let workspace: &Workspace = &workspace;
+    let pool = app.state::<SshPool>();
+    let pool: &SshPool = &pool;
+
+    std::thread::scope(|scope| {
+      let handles: Vec<_> = hosts
+        .iter()
+        .map(|target| scope.spawn(move || scan(workspace, pool, target, profile)))
+        .collect();
+      handles
+        .into_iter()
+        .zip(&hosts)
+        .map(
+          |(handle, target)| match handle.join().unwrap_or_else(|_| Err("Scan panicked".to_string())) {
+            Ok(status) => {
+              let (outcome, message) = status.verdict();
+              NodeTuning {
+                host: target.host.clone(),
+                status: Some(status),
+                outcome,
+                message,
+              }
+            }
+            Err(e) => NodeTuning {
+              host: target.host.clone(),
+              status: None,
+              outcome: Outcome::Skipped,
+              message: format!("Skipped: {}", e),
+            },
+          },
+        )
+        .collect()
+    })
+  })
+  .await
+  .map_err(|e| e.to_string())
+}
+
+/// Raise the hugepages and sysctls the profile wants on the node.
+#[tauri::command]
+pub async fn apply_kernel_tuning(
+  app: AppHandle,
+  host: SshTarget,
+  profile: WorkloadProfile,
+) -> Result<AppliedTuning, String> {
+  host.validate()?;
+  let dry_run = dry_run::is_enabled(&app);
+  tauri::async_runtime::spawn_blocking(move || {
+    let workspace = app.state::<Workspace>();
+    let pool = app.state::<SshPool>();
+    let status = scan(&workspace, &pool, &host, profile)?;
+    if let (Outcome::Fail, message) = status.verdict() {
+      return Err(format!("Not tuning {}: {}", host.host, message));
+    }
+    let raise = status.shortfalls();
+    if dry_run || raise.is_empty() {
+      if dry_run {
+        info!("Dry run: not tuning the kernel on {}", host.host);
+      }
+      return Ok(AppliedTuning {
+        changed: raise
+          .iter()
+          .map(|(key, _, wanted)| format!("{}={}", key, wanted))
+          .collect(),
+        hugepages_total: status.hugepages_total,
+        hugepages_wanted: status.hugepages_wanted,
+      });
+    }
+
+    let output = ssh::run_script(&workspace, &pool, &host, &apply_script(&raise), APPLY_TIMEOUT)?;
+    let set: Vec<&str> = output
+      .stdout
+      .lines()
+      .filter_map(|l| l.trim().strip_prefix("set "))
+      .collect();
+    let changed: Vec<&(String, u64, u64)> = raise.iter().filter(|(key, ..)| set.contains(&key.as_str())).collect();
+    // Whatever was raised before a failure still needs putting back
+    if !changed.is_empty() {
+      journal::record(
+        &app,
+        Action::KernelTuning {
+          target: host.clone(),
+          previous: changed
+            .iter()
+            .map(|(key, previous, _)| (key.clone(), *previous))
+            .collect(),
+        },
+      );
+    }
+    if output.status != Some(0) {
+      let detail = output
+        .stderr
+        .trim()
+        .lines()
+        .last()
+        .unwrap_or("command failed")
+        .to_string();
+      return Err(format!("Failed to tune the kernel on {}: {}", host.host, detail));
+    }
+    let hugepages_total = output
+      .stdout
+      .lines()
+      .find_map(|l| l.trim().strip_prefix("hugepages "))
+      .and_then(|v| v.parse().ok())
+      .unwrap_or(status.hugepages_total);
+    info!(
+      "Tuned the kernel on {}: {} hugepages of {} wanted",
+      host.host, hugepages_total, status.hugepages_wanted
+    );
+    Ok(AppliedTuning {
+      changed: changed
+        .iter()
+        .map(|(key, _, wanted)| format!("{}={}", key, wanted))
+        .collect(),
+      hugepages_total,
+      hugepages_wanted: status.hugepages_wanted,
+    })
+  })
+  .await
+  .map_err(|e| e.to_string())?
+}
@@ -0,0 +1,319 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Whether the VMs planned for each node fit on it.
+//!
+//! LXD starts VMs whose combined memory exceeds the host and lets the OOM
+//! killer sort it out halfway through the install, and a thin pool that
+//! fills up takes every VM on it down at once. `plan_vm_resources` reads
+//! each node's CPUs, memory and the free space where LXD keeps its storage,
+//! keeps some of each for the host itself, and compares the rest times the
+//! overcommit ratio with what the planned VMs add up to. vCPUs overcommit
+//! well, so their ratio defaults to 4; memory and disk default to 1. A
+//! resource over its limit fails the node, so provisioning can be held
+//! back; one that only fits thanks to overcommit, or leaves little spare,
+//! is a warning.
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+use super::inventory::sections;
+use super::ssh::{self, SshPool, SshTarget};
+use crate::preflight::Outcome;
+use crate::validation;
+use crate::workspace::Workspace;
+
+const DETECT_TIMEOUT: Duration = Duration::from_secs(30);
+/// Kept for the host's own services, snapd and LXD
+pub const HOST_CPUS: u64 = 1;
+pub const HOST_MEMORY_MIB: u64 = 2048;
+pub const HOST_DISK_GIB: u64 = 10;
+/// Share of a limit past which a resource is reported as tight
+const TIGHT: f64 = 0.9;
+
+/// Free space is measured where LXD keeps its pools: the snap's data dir
+/// when it's installed, /var (where it will be) otherwise.
+const SCRIPT: &str = r#"
+export LC_ALL=C
+echo '@@capacity'
+echo "cpus=$(nproc 2>/dev/null)"
+awk '/^MemTotal:/ { print "mem_kb=" $2 }' /proc/meminfo
+dir=/var/snap/lxd/common/lxd
+[ -d "$dir" ] || dir=/var
+echo "disk_path=$dir"
+echo "disk_free=$(df -P -B1 "$dir" 2>/dev/null | awk 'NR == 2 { print $4 }')"
+echo '@@end'
+"#;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PlannedVm {
+  pub name: String,
+  pub vcpus: u32,
+  pub memory_mib: u64,
+  pub disk_gib: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct VmHost {
+  #[serde(flatten)]
+  pub target: SshTarget,
+  pub vms: Vec<PlannedVm>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OvercommitRatios {
+  pub cpu: f64,
+  pub memory: f64,
+  pub disk: f64,
+}
+
+impl Default for OvercommitRatios {
+  fn default() -> Self {
+    Self {
+      cpu: 4.0,
+      memory: 1.0,
+      disk: 1.0,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HostCapacity {
+  pub cpus: u32,
+  pub memory_mib: u64,
+  pub disk_free_gib: u64,
+  /// Where the free space was measured
+  pub disk_path: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ResourceUse {
+  /// `vcpus`, `memory` or `disk`
+  pub resource: String,
+  pub unit: String,
+  pub requested: u64,
+  /// What the host has, before anything is kept for it
+  pub capacity: u64,
+  /// What the VMs may add up to: what's left for them times the ratio
+  pub limit: u64,
+  pub outcome: Outcome,
+  pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HostPlan {
+  pub host: String,
+  pub vms: Vec<String>,
+  pub capacity: Option<HostCapacity>,
+  pub resources: Vec<ResourceUse>,
+  pub outcome: Outcome,
+  pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VmPlanReport {
+  pub hosts: Vec<HostPlan>,
+  pub ratios: OvercommitRatios,
+  /// Every host was checked and none is over a limit; provisioning may
+  /// go ahead
+  pub ok: bool,
+}
+
+fn parse(output: &str) -> Option<HostCapacity> {
+  let sections = sections(output);
+  let lines = sections.get("capacity")?;
+  let fact = |key: &str| {
+    lines
+      .iter()
+      .find_map(|l| l.trim().strip_prefix(key)?.strip_prefix('='))
+      .map(str::trim)
+      .filter(|v| !v.is_empty())
+  };
+  let number = |key: &str| fact(key).and_then(|v| v.parse::<u64>().ok());
+  Some(HostCapacity {
+    cpus: number("cpus")? as u32,
+    memory_mib: number("mem_kb")? / 1024,
+    disk_free_gib: number("disk_free").unwrap_or(0) / (1024 * 1024 * 1024),
+    disk_path: fact("disk_path").unwrap_or("/var").to_string(),
+  })
+}
+
+fn check(resource: &str, unit: &str, requested: u64, capacity: u64, reserved: u64, ratio: f64) -> ResourceUse {
+  let available = capacity.saturating_sub(reserved);
+  let limit = (available as f64 * ratio).floor() as u64;
+  // `12 vCPUs`, `8192 MiB of memory`
+  let amount = |value: u64| match resource {
+    "vcpus" => format!("{} {}", value, unit),
+    _ => format!("{} {} of {}", value, unit, resource),
+  };
+  let (outcome, message) = if requested > limit {
+    (
+      Outcome::Fail,
+      format!(
+        "{} requested, but at most {} {} fit ({} on the host, {} kept for it, overcommit {}×)",
+        amount(requested),
+        limit,
+        unit,
+        capacity,
+        reserved,
+        ratio
+      ),
+    )
+  } else if requested > available {
+    (
+      Outcome::Warn,
+      format!(
+        "{} requested on {} available, overcommitted {:.1}×",
+        amount(requested),
+        available,
+        requested as f64 / available.max(1) as f64
+      ),
+    )
+  } else if requested as f64 > limit as f64 * TIGHT {
+    (
+      Outcome::Warn,
+      format!(
+        "{} requested, leaving {} {} spare",
+        amount(requested),
+        limit - requested,
+        unit
+      ),
+    )
+  } else {
+    (
+      Outcome::Pass,
+      format!("{} requested of {} {}", amount(requested), limit, unit),
+    )
+  };
+  ResourceUse {
+    resource: resource.to_string(),
+    unit: unit.to_string(),
+    requested,
+    capacity,
+    limit,
+    outcome,
+    message,
+  }
+}
+
+fn plan(host: &VmHost, capacity: &HostCapacity, ratios: &OvercommitRatios) -> Vec<ResourceUse> {
+  let vcpus = host.vms.iter().map(|vm| u64::from(vm.vcpus)).sum();
+  let memory = host.vms.iter().map(|vm| vm.memory_mib).sum();
+  let disk = host.vms.iter().map(|vm| vm.disk_gib).sum();
+  vec![
+    check("vcpus", "vCPUs", vcpus, u64::from(capacity.cpus), HOST_CPUS, ratios.cpu),
+    check(
+      "memory",
+      "MiB",
+      memory,
+      capacity.memory_mib,
+      HOST_MEMORY_MIB,
+      ratios.memory,
+    ),
+    check("disk", "GiB", disk, capacity.disk_free_gib, HOST_DISK_GIB, ratios.disk),
+  ]
+}
+
+fn summary(resources: &[ResourceUse]) -> (Outcome, String) {
+  for outcome in [Outcome::Fail, Outcome::Warn] {
+    let messages: Vec<&str> = resources
+      .iter()
+      .filter(|r| r.outcome == outcome)
+      .map(|r| r.message.as_str())
+      .collect();
+    if !messages.is_empty() {
+      return (outcome, messages.join("; "));
+    }
+  }
+  (Outcome::Pass, "The planned VMs fit".to_string())
+}
+
+/// Sum the VMs planned for each host and compare them with what the host
+/// has, allowing the given overcommit `ratios`.
+#[tauri::command]
+pub async fn plan_vm_resources(
+  app: AppHandle,
+  hosts: Vec<VmHost>,
+  ratios: Option<OvercommitRatios>,
+) -> Result<VmPlanReport, String> {
+  let ratios = ratios.unwrap_or_default();
+  for ratio in [ratios.cpu, ratios.memory, ratios.disk] {
+    if !(ratio.is_finite() && (0.1..=16.0).contains(&ratio)) {
+      return Err(format!("Invalid overcommit ratio: {}", ratio));
+    }
+  }
+  for host in &hosts {
+    host.target.validate()?;
+    for vm in &host.vms {
+      if !validation::is_valid_label(&vm.name) {
+        return Err(format!("Invalid VM name: {}", vm.name));
+      }
+      if vm.vcpus == 0 || vm.memory_mib == 0 {
+        return Err(format!("VM {} needs at least one vCPU and some memory", vm.name));
+      }
+    }
+  }
+  tauri::async_runtime::spawn_blocking(move || {
+    let workspace = app.state::<Workspace>();
+    let workspace: &Workspace = &workspace;
+    let pool = app.state::<SshPool>();
+    let pool: &SshPool = &pool;
+
+    let plans: Vec<HostPlan> = std::thread::scope(|scope| {
+      let handles: Vec<_> = hosts
+        .iter()
+        .map(|host| {
+          scope.spawn(move || {
+            let output = ssh::run_script(workspace, pool, &host.target, SCRIPT, DETECT_TIMEOUT)?;
+            if !output.stdout.contains("@@end") {
+              return Err(format!("Detection did not complete: {}", output.stderr.trim()));
+            }
+            parse(&output.stdout).ok_or_else(|| "Could not read the CPU and memory".to_string())
+          })
+        })
+        .collect();
+      handles
+        .into_iter()
+        .zip(&hosts)
+        .map(|(handle, host)| {
+          let result = handle.join().unwrap_or_else(|_| Err("Detection panicked".to_string()));
+          let vms = host.vms.iter().map(|vm| vm.name.clone()).collect();
+          match result {
+            Ok(capacity) => {
+              let resources = plan(host, &capacity, &ratios);
+              let (outcome, message) = summary(&resources);
+              HostPlan {
+                host: host.target.host.clone(),
+                vms,
+                capacity: Some(capacity),
+                resources,
+                outcome,
+                message,
+              }
+            }
+            Err(e) => HostPlan {
+              host: host.target.host.clone(),
+              vms,
+              capacity: None,
+              resources: Vec::new(),
+              outcome: Outcome::Skipped,
+              message: format!("Skipped: {}", e),
+            },
+          }
+        })
+        .collect()
+    });
+
+    VmPlanReport {
+      ok: plans.iter().all(|p| matches!(p.outcome, Outcome::Pass | Outcome::Warn)),
+      hosts: plans,
+      ratios,
+    }
+  })
+  .await
+  .map_err(|e| e.to_string())
+}
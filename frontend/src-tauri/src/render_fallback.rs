@@ -0,0 +1,119 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Relaunching with safer WebKitGTK settings after a blank window.
+//!
+//! The session profile doesn't cover every driver, so each launch leaves a
+//! probe file with the fallback level it is trying, and the main window
+//! calls `webview_rendered` once it has painted a frame. That removes the
+//! probe and saves the level as `webview_fallback` in settings.json. If the
+//! window hasn't painted [`RENDER_TIMEOUT`] after it was shown, the shell
+//! restarts itself one level further down
+//! [`FALLBACKS`](crate::platform::session::FALLBACKS); a launch that
+//! crashed outright leaves its probe behind, and the next one moves down a
+//! level the same way. Linux only: WebKit on macOS and WebView2 on Windows
+//! don't need it.
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tracing::{error, info, warn};
+
+use crate::platform::session::FALLBACKS;
+use crate::settings::{Settings, SettingsStore};
+
+/// How long the main window has to paint once it's shown
+const RENDER_TIMEOUT: Duration = Duration::from_secs(20);
+const PROBE_FILE: &str = "render-probe";
+/// Same as tauri.conf.json's, which isn't readable before Tauri starts
+const IDENTIFIER: &str = "org.thinkube.installer";
+
+static LEVEL: AtomicUsize = AtomicUsize::new(0);
+static RENDERED: AtomicBool = AtomicBool::new(false);
+static ARMED: AtomicBool = AtomicBool::new(false);
+
+/// Tauri's `app_config_dir` on Linux, where settings.json lives.
+fn config_dir() -> Option<PathBuf> {
+  let base = std::env::var_os("XDG_CONFIG_HOME")
+    .filter(|d| !d.is_empty())
+    .map(PathBuf::from)
+    .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+  Some(base.join(IDENTIFIER))
+}
+
+/// The fallback level for this launch: one past the last one tried when
+/// that launch never rendered, else the one that last worked.
+pub fn launch_level() -> usize {
+  if std::env::consts::OS != "linux" {
+    return 0;
+  }
+  let Some(dir) = config_dir() else {
+    return 0;
+  };
+  let probe = dir.join(PROBE_FILE);
+  let level = match std::fs::read_to_string(&probe)
+    .ok()
+    .and_then(|l| l.trim().parse::<usize>().ok())
+  {
+    Some(failed) => (failed + 1).min(FALLBACKS.len()),
+    None => std::fs::read_to_string(dir.join("settings.json"))
+      .ok()
+      .and_then(|s| serde_json::from_str::<Settings>(&s).ok())
+      .and_then(|s| s.webview_fallback)
+      .unwrap_or(0)
+      .min(FALLBACKS.len()),
+  };
+  let _ = std::fs::create_dir_all(&dir).and_then(|_| std::fs::write(&probe, level.to_string()));
+  LEVEL.store(level, Ordering::SeqCst);
+  level
+}
+
+/// Start the render watchdog once the main window is shown.
+pub fn arm(app: &AppHandle) {
+  if std::env::consts::OS != "linux" || ARMED.swap(true, Ordering::SeqCst) {
+    return;
+  }
+  let app = app.clone();
+  std::thread::spawn(move || {
+    std::thread::sleep(RENDER_TIMEOUT);
+    if RENDERED.load(Ordering::SeqCst) {
+      return;
+    }
+    let level = LEVEL.load(Ordering::SeqCst);
+    if level >= FALLBACKS.len() {
+      error!(
+        "The main window hasn't rendered after {} s even with every renderer fallback",
+        RENDER_TIMEOUT.as_secs()
+      );
+      return;
+    }
+    warn!(
+      "The main window hasn't rendered after {} s; restarting with renderer fallback {}",
+      RENDER_TIMEOUT.as_secs(),
+      level + 1
+    );
+    // The probe still names this level, so the next launch moves past it
+    app.restart();
+  });
+}
+
+/// Called by the main window once it has painted a frame.
+#[tauri::command]
+pub fn webview_rendered(app: AppHandle) -> Result<(), String> {
+  if RENDERED.swap(true, Ordering::SeqCst) || std::env::consts::OS != "linux" {
+    return Ok(());
+  }
+  let level = LEVEL.load(Ordering::SeqCst);
+  if let Some(dir) = config_dir() {
+    let _ = std::fs::remove_file(dir.join(PROBE_FILE));
+  }
+  let settings = app.state::<SettingsStore>();
+  if settings.get().webview_fallback.unwrap_or(0) != level {
+    info!("Webview rendered with renderer fallback {}; remembering it", level);
+    settings.update(|s| s.webview_fallback = Some(level))?;
+  }
+  Ok(())
+}
@@ -0,0 +1,410 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! A human-readable record of the installation.
+//!
+//! The wizard records every step it runs (playbooks, node setup) with
+//! `record_step`: when it started, how long it took, how it ended and the
+//! handful of outputs worth keeping. Steps passed over with `skip_step` are
+//! recorded as skipped, with the reason given. The transcript is kept in
+//! `transcript.json` in the profile's data dir so it survives a reboot in
+//! the middle of an install. `export_report` renders it as Markdown or HTML
+//! into `<profile data dir>/reports/`, with secrets masked.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, State};
+use time::OffsetDateTime;
+use tracing::{info, warn};
+
+use crate::eta;
+use crate::migrations::Versioned;
+use crate::profiles;
+use crate::redact::redact;
+use crate::telemetry::Outcome;
+use crate::workspace::write_private_file;
+
+/// Upper bound on recorded steps so a looping caller can't grow the file.
+const MAX_STEPS: usize = 1000;
+/// Longest output value kept per key.
+const MAX_OUTPUT_LEN: usize = 4000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepRecord {
+  /// Identifier such as the playbook name
+  pub step: String,
+  pub title: String,
+  /// Milliseconds since the Unix epoch
+  pub started_at: u64,
+  pub duration_ms: u64,
+  pub outcome: Outcome,
+  pub message: Option<String>,
+  /// Key outputs, e.g. task counts or the failing task
+  #[serde(default)]
+  pub outputs: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+  Markdown,
+  Html,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportedReport {
+  pub path: String,
+  pub content: String,
+}
+
+pub struct Transcript {
+  path: PathBuf,
+  steps: Mutex<Vec<StepRecord>>,
+}
+
+impl Transcript {
+  /// Load the transcript of the current installation, if any.
+  pub fn load(app: &AppHandle) -> Result<Self, String> {
+    let path = profiles::data_dir(app)?.join("transcript.json");
+
+    let steps = match std::fs::read_to_string(&path) {
+      Ok(contents) => serde_json::from_str::<Versioned<_>>(&contents).map(|v| v.items).unwrap_or_else(|e| {
+        warn!("Ignoring invalid {}: {}", path.display(), e);
+        Vec::new()
+      }),
+      Err(_) => Vec::new(),
+    };
+
+    Ok(Self {
+      path,
+      steps: Mutex::new(steps),
+    })
+  }
+
+  pub fn steps(&self) -> Vec<StepRecord> {
+    self.steps.lock().map(|s| s.clone()).unwrap_or_default()
+  }
+
+  pub fn record(&self, mut step: StepRecord) -> Result<(), String> {
+    // Stored as they'll be shown, so the file on disk is masked too
+    step.message = step.message.map(|m| redact(&m).into_owned());
+    for value in step.outputs.values_mut() {
+      if value.len() > MAX_OUTPUT_LEN {
+        let mut end = MAX_OUTPUT_LEN;
+        while !value.is_char_boundary(end) {
+          end -= 1;
+        }
+        value.truncate(end);
+        value.push('…');
+      }
+      *value = redact(value).into_owned();
+    }
+
+    let mut steps = self.steps.lock().map_err(|e| e.to_string())?;
+    if steps.len() >= MAX_STEPS {
+      return Err(format!("The transcript already holds {} steps", MAX_STEPS));
+    }
+    info!("Transcript: {} {:?}", step.step, step.outcome);
+    steps.push(step);
+    self.save(&steps)
+  }
+
+  fn clear(&self) -> Result<(), String> {
+    let mut steps = self.steps.lock().map_err(|e| e.to_string())?;
+    steps.clear();
+    match std::fs::remove_file(&self.path) {
+      Ok(()) => Ok(()),
+      Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+      Err(e) => Err(format!("Failed to remove {}: {}", self.path.display(), e)),
+    }
+  }
+
+  fn save(&self, steps: &[StepRecord]) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(&Versioned::current(steps)).map_err(|e| e.to_string())?;
+    let tmp = self.path.with_extension("json.tmp");
+    write_private_file(&tmp, json.as_bytes())?;
+    std::fs::rename(&tmp, &self.path).map_err(|e| format!("Failed to write {}: {}", self.path.display(), e))
+  }
+}
+
+pub fn format_time(ms: u64) -> String {
+  let Ok(at) = OffsetDateTime::from_unix_timestamp((ms / 1000) as i64) else {
+    return ms.to_string();
+  };
+  format!(
+    "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+    at.year(),
+    u8::from(at.month()),
+    at.day(),
+    at.hour(),
+    at.minute(),
+    at.second()
+  )
+}
+
+fn format_duration(ms: u64) -> String {
+  let secs = ms / 1000;
+  match secs {
+    0..=59 => format!("{:.1}s", ms as f64 / 1000.0),
+    60..=3599 => format!("{}m {}s", secs / 60, secs % 60),
+    _ => format!("{}h {}m", secs / 3600, secs % 3600 / 60),
+  }
+}
+
+fn outcome_label(outcome: Outcome) -> &'static str {
+  match outcome {
+    Outcome::Success => "Succeeded",
+    Outcome::Failure => "Failed",
+    Outcome::Skipped => "Skipped",
+    Outcome::Cancelled => "Cancelled",
+  }
+}
+
+/// The figures at the top of the report.
+struct Summary {
+  version: String,
+  os: String,
+  generated: String,
+  started: Option<String>,
+  total_ms: u64,
+  counts: Vec<(&'static str, usize)>,
+}
+
+fn summarize(app: &AppHandle, steps: &[StepRecord]) -> Summary {
+  let now = SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|d| d.as_millis() as u64)
+    .unwrap_or_default();
+  let os = crate::platform::os_release::compatibility().os;
+  let outcomes = [Outcome::Success, Outcome::Failure, Outcome::Skipped, Outcome::Cancelled];
+  Summary {
+    version: app.package_info().version.to_string(),
+    os: format!("{} ({})", os.pretty_name, std::env::consts::ARCH),
+    generated: format_time(now),
+    started: steps.iter().map(|s| s.started_at).min().map(format_time),
+    total_ms: steps.iter().map(|s| s.duration_ms).sum(),
+    counts: outcomes
+      .iter()
+      .map(|o| (outcome_label(*o), steps.iter().filter(|s| s.outcome == *o).count()))
+      .filter(|(_, n)| *n > 0)
+      .collect(),
+  }
+}
+
+/// Table cells can't hold newlines or unescaped pipes.
+fn md_cell(text: &str) -> String {
+  text.replace('|', "\\|").replace('\n', " ")
+}
+
+fn render_markdown(summary: &Summary, steps: &[StepRecord]) -> String {
+  let mut out = String::new();
+  let _ = writeln!(out, "# Thinkube installation report\n");
+  let _ = writeln!(out, "- Installer version: {}", summary.version);
+  let _ = writeln!(out, "- Host: {}", summary.os);
+  if let Some(started) = &summary.started {
+    let _ = writeln!(out, "- Started: {}", started);
+  }
+  let _ = writeln!(out, "- Generated: {}", summary.generated);
+  let _ = writeln!(out, "- Time in steps: {}", format_duration(summary.total_ms));
+  let counts: Vec<String> = summary
+    .counts
+    .iter()
+    .map(|(l, n)| format!("{} {}", n, l.to_lowercase()))
+    .collect();
+  let _ = writeln!(out, "- Steps: {} ({})\n", steps.len(), counts.join(", "));
+
+  let _ = writeln!(out, "## Steps\n");
+  let _ = writeln!(out, "| # | Step | Started | Duration | Outcome |");
+  let _ = writeln!(out, "|---|------|---------|----------|---------|");
+  for (i, step) in steps.iter().enumerate() {
+    let _ = writeln!(
+      out,
+      "| {} | {} | {} | {} | {} |",
+      i + 1,
+      md_cell(&step.title),
+      format_time(step.started_at),
+      format_duration(step.duration_ms),
+      outcome_label(step.outcome)
+    );
+  }
+
+  let detailed: Vec<(usize, &StepRecord)> = steps
+    .iter()
+    .enumerate()
+    .filter(|(_, s)| s.message.is_some() || !s.outputs.is_empty())
+    .collect();
+  if !detailed.is_empty() {
+    let _ = writeln!(out, "\n## Details");
+  }
+  for (i, step) in detailed {
+    let _ = writeln!(out, "\n### {}. {} (`{}`)\n", i + 1, step.title, step.step);
+    if let Some(message) = &step.message {
+      let _ = writeln!(out, "{}\n", message);
+    }
+    for (key, value) in &step.outputs {
+      if value.contains('\n') {
+        let _ = writeln!(out, "**{}**:\n\n```\n{}\n```\n", key, value);
+      } else {
+        let _ = writeln!(out, "- **{}**: {}", key, value);
+      }
+    }
+  }
+  out
+}
+
+pub fn html_escape(text: &str) -> String {
+  text
+    .replace('&', "&amp;")
+    .replace('<', "&lt;")
+    .replace('>', "&gt;")
+    .replace('"', "&quot;")
+}
+
+fn render_html(summary: &Summary, steps: &[StepRecord]) -> String {
+  let mut out = String::new();
+  out.push_str(
+    "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Thinkube installation report</title>\n<style>\n\
+     body { font-family: sans-serif; max-width: 60rem; margin: 2rem auto; }\n\
+     table { border-collapse: collapse; width: 100%; }\n\
+     th, td { border: 1px solid #ccc; padding: 0.3rem 0.6rem; text-align: left; }\n\
+     .succeeded { color: #1a7f37; } .failed { color: #cf222e; } .skipped, .cancelled { color: #9a6700; }\n\
+     pre { background: #f6f8fa; padding: 0.6rem; overflow-x: auto; }\n\
+     </style>\n</head>\n<body>\n<h1>Thinkube installation report</h1>\n<ul>\n",
+  );
+  let _ = writeln!(out, "<li>Installer version: {}</li>", html_escape(&summary.version));
+  let _ = writeln!(out, "<li>Host: {}</li>", html_escape(&summary.os));
+  if let Some(started) = &summary.started {
+    let _ = writeln!(out, "<li>Started: {}</li>", started);
+  }
+  let _ = writeln!(out, "<li>Generated: {}</li>", summary.generated);
+  let _ = writeln!(out, "<li>Time in steps: {}</li>", format_duration(summary.total_ms));
+  let counts: Vec<String> = summary
+    .counts
+    .iter()
+    .map(|(l, n)| format!("{} {}", n, l.to_lowercase()))
+    .collect();
+  let _ = writeln!(out, "<li>Steps: {} ({})</li>\n</ul>", steps.len(), counts.join(", "));
+
+  out.push_str(
+    "<h2>Steps</h2>\n<table>\n<tr><th>#</th><th>Step</th><th>Started</th><th>Duration</th><th>Outcome</th></tr>\n",
+  );
+  for (i, step) in steps.iter().enumerate() {
+    let label = outcome_label(step.outcome);
+    let _ = writeln!(
+      out,
+      "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td class=\"{}\">{}</td></tr>",
+      i + 1,
+      html_escape(&step.title),
+      format_time(step.started_at),
+      format_duration(step.duration_ms),
+      label.to_lowercase(),
+      label
+    );
+  }
+  out.push_str("</table>\n");
+
+  let mut details_heading = false;
+  for (i, step) in steps.iter().enumerate() {
+    if step.message.is_none() && step.outputs.is_empty() {
+      continue;
+    }
+    if !details_heading {
+      out.push_str("<h2>Details</h2>\n");
+      details_heading = true;
+    }
+    let _ = writeln!(
+      out,
+      "<h3>{}. {} (<code>{}</code>)</h3>",
+      i + 1,
+      html_escape(&step.title),
+      html_escape(&step.step)
+    );
+    if let Some(message) = &step.message {
+      let _ = writeln!(out, "<p>{}</p>", html_escape(message));
+    }
+    for (key, value) in &step.outputs {
+      if value.contains('\n') {
+        let _ = writeln!(
+          out,
+          "<p><strong>{}</strong>:</p>\n<pre>{}</pre>",
+          html_escape(key),
+          html_escape(value)
+        );
+      } else {
+        let _ = writeln!(
+          out,
+          "<p><strong>{}</strong>: {}</p>",
+          html_escape(key),
+          html_escape(value)
+        );
+      }
+    }
+  }
+  out.push_str("</body>\n</html>\n");
+  out
+}
+
+/// Record a finished step of the installation.
+#[tauri::command]
+pub fn record_step(app: AppHandle, transcript: State<'_, Transcript>, mut record: StepRecord) -> Result<(), String> {
+  if record.step.trim().is_empty() {
+    return Err("A step id is required".to_string());
+  }
+  record.message = record.message.filter(|m| !m.trim().is_empty());
+  let (step, duration_ms, outcome) = (record.step.clone(), record.duration_ms, record.outcome);
+  transcript.record(record)?;
+  eta::step_recorded(&app, &step, duration_ms, outcome);
+  Ok(())
+}
+
+#[tauri::command]
+pub fn get_transcript(transcript: State<'_, Transcript>) -> Vec<StepRecord> {
+  transcript.steps()
+}
+
+/// Forget the recorded steps, e.g. when a new installation starts.
+#[tauri::command]
+pub fn clear_transcript(transcript: State<'_, Transcript>) -> Result<(), String> {
+  transcript.clear()
+}
+
+/// Render the transcript and save it under `<profile data dir>/reports/`.
+#[tauri::command]
+pub fn export_report(
+  app: AppHandle,
+  transcript: State<'_, Transcript>,
+  format: ReportFormat,
+) -> Result<ExportedReport, String> {
+  let steps = transcript.steps();
+  if steps.is_empty() {
+    return Err("No installation steps have been recorded yet".to_string());
+  }
+  let summary = summarize(&app, &steps);
+  let (content, extension) = match format {
+    ReportFormat::Markdown => (render_markdown(&summary, &steps), "md"),
+    ReportFormat::Html => (render_html(&summary, &steps), "html"),
+  };
+  // Already masked when recorded; secrets registered since then are caught here
+  let content = redact(&content).into_owned();
+
+  let dir = profiles::data_dir(&app)?.join("reports");
+  std::fs::create_dir_all(&dir).map_err(|e| format!("Cannot create {}: {}", dir.display(), e))?;
+  let stamp = SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|d| d.as_secs())
+    .unwrap_or_default();
+  let path = dir.join(format!("installation-{}.{}", stamp, extension));
+  write_private_file(&path, content.as_bytes())?;
+  info!("Installation report written to {}", path.display());
+
+  Ok(ExportedReport {
+    path: path.display().to_string(),
+    content,
+  })
+}
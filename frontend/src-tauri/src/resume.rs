@@ -0,0 +1,255 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Picking the install back up after a reboot.
+//!
+//! Before asking for a reboot the wizard calls `schedule_resume` with the
+//! step to come back to and whatever state it needs. That is written to
+//! `resume.json` in the profile's config dir and a login item is registered
+//! that starts the installer again: an XDG autostart entry on Linux, a
+//! launch agent on macOS. The login item is one-shot: it is removed as soon
+//! as the installer starts, whether or not the wizard goes on to resume, so
+//! a run that is abandoned never keeps launching the app.
+//!
+//! Secrets must not go into the saved state; they stay in the keyring.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager, State};
+use tracing::{error, info, warn};
+
+use crate::migrations::STATE_VERSION;
+use crate::profiles;
+use crate::workspace::{write_private_file, Workspace};
+
+/// Saved state older than this is discarded rather than resumed
+const MAX_AGE_SECS: u64 = 7 * 24 * 60 * 60;
+
+const ENTRY_NAME: &str = "org.thinkube.installer.resume";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResumeState {
+  /// State format version, see `migrations`
+  #[serde(default)]
+  pub version: u32,
+  /// Wizard step to open after the reboot
+  pub step: String,
+  /// Why the reboot was needed, shown when resuming
+  pub reason: Option<String>,
+  /// Opaque wizard state saved by the frontend
+  #[serde(default)]
+  pub state: Value,
+  /// Run whose workspace holds the files generated before the reboot
+  pub run_id: String,
+  pub workspace: String,
+  pub saved_at: u64,
+}
+
+fn state_path(app: &AppHandle) -> Result<PathBuf, String> {
+  profiles::config_dir(app).map(|dir| dir.join("resume.json"))
+}
+
+fn now() -> u64 {
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|d| d.as_secs())
+    .unwrap_or_default()
+}
+
+/// The binary the login item should start. Inside an AppImage the running
+/// executable lives on a temporary mount, so prefer the image itself.
+fn launcher() -> Result<PathBuf, String> {
+  if let Some(appimage) = std::env::var_os("APPIMAGE").filter(|p| !p.is_empty()) {
+    return Ok(PathBuf::from(appimage));
+  }
+  std::env::current_exe().map_err(|e| format!("Cannot locate the installer executable: {}", e))
+}
+
+#[cfg(target_os = "linux")]
+fn entry_path(app: &AppHandle) -> Result<PathBuf, String> {
+  let config = match std::env::var_os("XDG_CONFIG_HOME").filter(|p| !p.is_empty()) {
+    Some(dir) => PathBuf::from(dir),
+    None => app
+      .path()
+      .home_dir()
+      .map_err(|e| format!("Cannot resolve home directory: {}", e))?
+      .join(".config"),
+  };
+  Ok(config.join("autostart").join(format!("{}.desktop", ENTRY_NAME)))
+}
+
+#[cfg(target_os = "linux")]
+fn entry_contents(exe: &std::path::Path) -> String {
+  // Desktop entry Exec values quote with double quotes and escape \ " ` $
+  let mut quoted = String::new();
+  for c in exe.display().to_string().chars() {
+    if matches!(c, '"' | '`' | '$' | '\\') {
+      quoted.push('\\');
+    }
+    quoted.push(c);
+  }
+  format!(
+    "[Desktop Entry]\n\
+     Type=Application\n\
+     Name=Thinkube Installer\n\
+     Comment=Resume the Thinkube installation after a reboot\n\
+     Exec=\"{}\"\n\
+     NoDisplay=true\n\
+     X-GNOME-Autostart-enabled=true\n",
+    quoted
+  )
+}
+
+#[cfg(target_os = "macos")]
+fn entry_path(app: &AppHandle) -> Result<PathBuf, String> {
+  Ok(
+    app
+      .path()
+      .home_dir()
+      .map_err(|e| format!("Cannot resolve home directory: {}", e))?
+      .join("Library/LaunchAgents")
+      .join(format!("{}.plist", ENTRY_NAME)),
+  )
+}
+
+#[cfg(target_os = "macos")]
+fn entry_contents(exe: &std::path::Path) -> String {
+  let exe = exe
+    .display()
+    .to_string()
+    .replace('&', "&amp;")
+    .replace('<', "&lt;")
+    .replace('>', "&gt;");
+  format!(
+    r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+  <key>Label</key>
+  <string>{}</string>
+  <key>ProgramArguments</key>
+  <array>
+    <string>{}</string>
+  </array>
+  <key>RunAtLoad</key>
+  <true/>
+  <key>LaunchOnlyOnce</key>
+  <true/>
+</dict>
+</plist>
+"#,
+    ENTRY_NAME, exe
+  )
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn entry_path(_app: &AppHandle) -> Result<PathBuf, String> {
+  Err("Resuming after a reboot is not supported on this platform".to_string())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn entry_contents(_exe: &std::path::Path) -> String {
+  String::new()
+}
+
+fn remove_entry(app: &AppHandle) {
+  let Ok(path) = entry_path(app) else {
+    return;
+  };
+  match std::fs::remove_file(&path) {
+    Ok(()) => info!("Removed resume login item {}", path.display()),
+    Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+    Err(e) => error!("Failed to remove {}: {}", path.display(), e),
+  }
+}
+
+fn remove_state(app: &AppHandle) -> Result<(), String> {
+  let path = state_path(app)?;
+  match std::fs::remove_file(&path) {
+    Ok(()) => Ok(()),
+    Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+    Err(e) => Err(format!("Failed to remove {}: {}", path.display(), e)),
+  }
+}
+
+fn load(app: &AppHandle) -> Option<ResumeState> {
+  let path = state_path(app).ok()?;
+  let contents = std::fs::read_to_string(&path).ok()?;
+  match serde_json::from_str::<ResumeState>(&contents) {
+    Ok(state) if now().saturating_sub(state.saved_at) <= MAX_AGE_SECS => Some(state),
+    Ok(_) => {
+      info!("Discarding resume state older than {} days", MAX_AGE_SECS / 86400);
+      let _ = remove_state(app);
+      None
+    }
+    Err(e) => {
+      warn!("Ignoring invalid {}: {}", path.display(), e);
+      let _ = remove_state(app);
+      None
+    }
+  }
+}
+
+/// Consume the login item that started this launch, if any. The saved state
+/// stays until the wizard resumes or discards it.
+pub fn setup(app: &AppHandle) {
+  remove_entry(app);
+  if let Some(state) = load(app) {
+    info!("Pending resume at step {} (run {})", state.step, state.run_id);
+  }
+}
+
+/// Save the wizard state and start the installer at the next login.
+#[tauri::command]
+pub fn schedule_resume(
+  app: AppHandle,
+  workspace: State<'_, Workspace>,
+  step: String,
+  reason: Option<String>,
+  state: Option<Value>,
+) -> Result<ResumeState, String> {
+  let step = step.trim().to_string();
+  if step.is_empty() {
+    return Err("A step to resume at is required".to_string());
+  }
+  let resume = ResumeState {
+    version: STATE_VERSION,
+    step,
+    reason: reason.map(|r| r.trim().to_string()).filter(|r| !r.is_empty()),
+    state: state.unwrap_or(Value::Null),
+    run_id: workspace.run_id().to_string(),
+    workspace: workspace.root().display().to_string(),
+    saved_at: now(),
+  };
+
+  let entry = entry_path(&app)?;
+  let path = state_path(&app)?;
+  let json = serde_json::to_string_pretty(&resume).map_err(|e| e.to_string())?;
+  let tmp = path.with_extension("json.tmp");
+  write_private_file(&tmp, json.as_bytes())?;
+  std::fs::rename(&tmp, &path).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+
+  if let Err(e) = write_private_file(&entry, entry_contents(&launcher()?).as_bytes()) {
+    let _ = remove_state(&app);
+    return Err(e);
+  }
+  info!("Will resume at step {} after reboot ({})", resume.step, entry.display());
+  Ok(resume)
+}
+
+/// State saved before a reboot, for the wizard to offer resuming.
+#[tauri::command]
+pub fn get_resume_state(app: AppHandle) -> Option<ResumeState> {
+  load(&app)
+}
+
+/// Forget the saved state, after resuming or when the user declines.
+#[tauri::command]
+pub fn clear_resume_state(app: AppHandle) -> Result<(), String> {
+  remove_entry(&app);
+  remove_state(&app)
+}
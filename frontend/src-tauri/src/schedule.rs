@@ -0,0 +1,354 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Starting the installation later, at a time the user picks.
+//!
+//! `schedule_install` records when to start, the nodes to check first and
+//! the wizard state to start from in `schedule.json` in the profile's
+//! config dir, so the plan survives restarting the installer. The app has
+//! to be running at that time, so until then closing the main window only
+//! hides it to the tray. At the planned time the shell checks the power
+//! source and runs the preflight suite again; if every node still passes it
+//! brings the window back and emits a `scheduled-install` event with the
+//! `starting` phase, and the wizard deploys from the saved state. Anything
+//! that fails instead cancels the start and says why, in the event and as
+//! a desktop notification. `cancel_scheduled_install` stops it at any
+//! point before the wizard takes over, including while preflight runs.
+//!
+//! Like the resume state, the saved wizard state must not hold secrets.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tracing::{info, warn};
+
+use crate::desktop::notify;
+use crate::migrations::STATE_VERSION;
+use crate::platform::power;
+use crate::preflight::{self, PreflightOptions, PreflightReport};
+use crate::remote::ssh::SshTarget;
+use crate::workspace::write_private_file;
+use crate::{i18n, profiles, windows};
+
+pub const EVENT: &str = "scheduled-install";
+
+/// How far ahead a start may be planned
+const MAX_AHEAD_SECS: u64 = 7 * 24 * 60 * 60;
+/// A start missed by more than this (the installer wasn't running) is
+/// dropped rather than started late
+const GRACE_SECS: u64 = 30 * 60;
+/// The timer re-reads the clock this often, so a suspend doesn't throw it off
+const TICK: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledInstall {
+  /// State format version, see `migrations`
+  #[serde(default)]
+  pub version: u32,
+  /// Unix time to start at
+  pub start_at: u64,
+  /// Nodes the preflight suite checks before starting
+  pub hosts: Vec<SshTarget>,
+  /// Opaque wizard state saved by the frontend
+  #[serde(default)]
+  pub state: Value,
+  pub scheduled_at: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "phase", rename_all = "snake_case")]
+pub enum ScheduleEvent {
+  Scheduled {
+    schedule: ScheduledInstall,
+  },
+  /// The planned time came; preflight is running
+  Checking {
+    start_at: u64,
+  },
+  /// Preflight passed; the wizard should deploy from `state`
+  Starting {
+    state: Value,
+    report: PreflightReport,
+  },
+  Blocked {
+    message: String,
+    report: Option<PreflightReport>,
+  },
+  Cancelled,
+}
+
+#[derive(Default)]
+pub struct Scheduler {
+  planned: Mutex<Option<ScheduledInstall>>,
+  /// Bumped whenever the plan changes, so the timer of an older one stops
+  generation: AtomicU64,
+}
+
+impl Scheduler {
+  pub fn pending(&self) -> Option<ScheduledInstall> {
+    self.planned.lock().ok().and_then(|p| p.clone())
+  }
+
+  fn replace(&self, plan: Option<ScheduledInstall>) -> u64 {
+    let mut planned = self.planned.lock().unwrap_or_else(|e| e.into_inner());
+    *planned = plan;
+    self.generation.fetch_add(1, Ordering::SeqCst) + 1
+  }
+
+  /// Clear the plan if it's still the one `generation` was armed for.
+  fn finish(&self, generation: u64) -> bool {
+    let Ok(mut planned) = self.planned.lock() else {
+      return false;
+    };
+    if self
+      .generation
+      .compare_exchange(generation, generation + 1, Ordering::SeqCst, Ordering::SeqCst)
+      .is_err()
+    {
+      return false;
+    }
+    *planned = None;
+    true
+  }
+
+  fn is_current(&self, generation: u64) -> bool {
+    self.generation.load(Ordering::SeqCst) == generation
+  }
+}
+
+fn state_path(app: &AppHandle) -> Result<PathBuf, String> {
+  profiles::config_dir(app).map(|dir| dir.join("schedule.json"))
+}
+
+fn now() -> u64 {
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|d| d.as_secs())
+    .unwrap_or_default()
+}
+
+fn save(app: &AppHandle, plan: &ScheduledInstall) -> Result<(), String> {
+  let path = state_path(app)?;
+  let json = serde_json::to_string_pretty(plan).map_err(|e| e.to_string())?;
+  let tmp = path.with_extension("json.tmp");
+  write_private_file(&tmp, json.as_bytes())?;
+  std::fs::rename(&tmp, &path).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+fn remove_state(app: &AppHandle) -> Result<(), String> {
+  let path = state_path(app)?;
+  match std::fs::remove_file(&path) {
+    Ok(()) => Ok(()),
+    Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+    Err(e) => Err(format!("Failed to remove {}: {}", path.display(), e)),
+  }
+}
+
+fn load(app: &AppHandle) -> Option<ScheduledInstall> {
+  let path = state_path(app).ok()?;
+  let contents = std::fs::read_to_string(&path).ok()?;
+  match serde_json::from_str::<ScheduledInstall>(&contents) {
+    Ok(plan) if now() <= plan.start_at + GRACE_SECS => Some(plan),
+    Ok(plan) => {
+      warn!(
+        "Dropping the installation scheduled for {}; the installer wasn't running then",
+        plan.start_at
+      );
+      let _ = remove_state(app);
+      None
+    }
+    Err(e) => {
+      warn!("Ignoring invalid {}: {}", path.display(), e);
+      let _ = remove_state(app);
+      None
+    }
+  }
+}
+
+fn set_tooltip(app: &AppHandle, scheduled: bool) {
+  if let Some(tray) = app.tray_by_id("main") {
+    let tooltip = if scheduled {
+      i18n::t("tray-tooltip-scheduled")
+    } else {
+      "Thinkube Installer".to_string()
+    };
+    let _ = tray.set_tooltip(Some(tooltip));
+  }
+}
+
+fn block(app: &AppHandle, generation: u64, message: String, report: Option<PreflightReport>) {
+  if !app.state::<Scheduler>().finish(generation) {
+    return;
+  }
+  warn!("Not starting the scheduled installation: {}", message);
+  let _ = remove_state(app);
+  set_tooltip(app, false);
+  notify::send(app, &i18n::t("schedule-blocked-title"), &message);
+  let _ = app.emit(EVENT, ScheduleEvent::Blocked { message, report });
+}
+
+fn fire(app: &AppHandle, generation: u64, plan: ScheduledInstall) {
+  info!("Scheduled start reached; checking {} nodes first", plan.hosts.len());
+  let _ = app.emit(
+    EVENT,
+    ScheduleEvent::Checking {
+      start_at: plan.start_at,
+    },
+  );
+
+  let power = power::get_power_status(app.state());
+  if power.block {
+    let message = power.message.unwrap_or_else(|| i18n::t("power-battery-blocked"));
+    return block(app, generation, message, None);
+  }
+
+  let options = PreflightOptions {
+    run_id: Some(format!("scheduled-{}", plan.start_at)),
+    ..Default::default()
+  };
+  let report = match preflight::run(app, plan.hosts, options) {
+    Ok(report) => report,
+    Err(e) => {
+      let message = i18n::t_args("schedule-preflight-error", &[("error", &e)]);
+      return block(app, generation, message, None);
+    }
+  };
+  if !app.state::<Scheduler>().is_current(generation) {
+    info!("The scheduled installation was cancelled during preflight");
+    return;
+  }
+  if !report.ok {
+    let count = (report.summary.fail + report.summary.skipped).to_string();
+    let message = i18n::t_args("schedule-preflight-failed", &[("count", &count)]);
+    return block(app, generation, message, Some(report));
+  }
+
+  if !app.state::<Scheduler>().finish(generation) {
+    return;
+  }
+  info!("Preflight passed; starting the scheduled installation");
+  let _ = remove_state(app);
+  set_tooltip(app, false);
+  if let Err(e) = windows::show_main(app) {
+    warn!("Failed to show the main window: {}", e);
+  }
+  let nodes = report.nodes.len().to_string();
+  notify::send(
+    app,
+    &i18n::t("schedule-started-title"),
+    &i18n::t_args("schedule-started-body", &[("nodes", &nodes)]),
+  );
+  let _ = app.emit(
+    EVENT,
+    ScheduleEvent::Starting {
+      state: plan.state,
+      report,
+    },
+  );
+}
+
+/// Wait for the planned time on a thread of its own, then start.
+fn arm(app: &AppHandle, generation: u64) {
+  let app = app.clone();
+  std::thread::spawn(move || loop {
+    let scheduler = app.state::<Scheduler>();
+    if !scheduler.is_current(generation) {
+      return;
+    }
+    let Some(plan) = scheduler.pending() else {
+      return;
+    };
+    let now = now();
+    if now >= plan.start_at {
+      return fire(&app, generation, plan);
+    }
+    std::thread::sleep(TICK.min(Duration::from_secs(plan.start_at - now)));
+  });
+}
+
+/// Pick up a start planned before the installer was last closed.
+pub fn setup(app: &AppHandle) {
+  let Some(plan) = load(app) else {
+    return;
+  };
+  info!(
+    "Installation scheduled for {} on {} nodes",
+    plan.start_at,
+    plan.hosts.len()
+  );
+  let generation = app.state::<Scheduler>().replace(Some(plan));
+  set_tooltip(app, true);
+  arm(app, generation);
+}
+
+/// Start the installation at `start_at` (Unix time) if `hosts` still pass
+/// preflight then. Replaces any start planned before.
+#[tauri::command]
+pub fn schedule_install(
+  app: AppHandle,
+  scheduler: State<'_, Scheduler>,
+  start_at: u64,
+  hosts: Vec<SshTarget>,
+  state: Option<Value>,
+) -> Result<ScheduledInstall, String> {
+  if hosts.is_empty() {
+    return Err("No nodes to check before starting".to_string());
+  }
+  for target in &hosts {
+    target.validate()?;
+  }
+  let now = now();
+  if start_at <= now {
+    return Err("The start time has to be in the future".to_string());
+  }
+  if start_at - now > MAX_AHEAD_SECS {
+    return Err(format!(
+      "Installations can be scheduled at most {} days ahead",
+      MAX_AHEAD_SECS / 86400
+    ));
+  }
+
+  let plan = ScheduledInstall {
+    version: STATE_VERSION,
+    start_at,
+    hosts,
+    state: state.unwrap_or(Value::Null),
+    scheduled_at: now,
+  };
+  save(&app, &plan)?;
+  info!(
+    "Installation scheduled in {} minutes on {} nodes",
+    (start_at - now) / 60,
+    plan.hosts.len()
+  );
+  let generation = scheduler.replace(Some(plan.clone()));
+  set_tooltip(&app, true);
+  arm(&app, generation);
+  let _ = app.emit(EVENT, ScheduleEvent::Scheduled { schedule: plan.clone() });
+  Ok(plan)
+}
+
+#[tauri::command]
+pub fn get_scheduled_install(scheduler: State<'_, Scheduler>) -> Option<ScheduledInstall> {
+  scheduler.pending()
+}
+
+/// Drop the planned start. Does nothing once the wizard has been told to
+/// start.
+#[tauri::command]
+pub fn cancel_scheduled_install(app: AppHandle, scheduler: State<'_, Scheduler>) -> Result<(), String> {
+  if scheduler.pending().is_none() {
+    return Ok(());
+  }
+  scheduler.replace(None);
+  info!("Scheduled installation cancelled");
+  set_tooltip(&app, false);
+  let _ = app.emit(EVENT, ScheduleEvent::Cancelled);
+  remove_state(&app)
+}
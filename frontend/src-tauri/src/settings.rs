@@ -0,0 +1,129 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Persistent shell settings (`settings.json` in the app config dir).
+//!
+//! Every field is optional / defaulted so settings written by an older or
+//! newer installer still load; unknown fields are dropped on the next save.
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+use tracing::warn;
+
+use crate::migrations::STATE_VERSION;
+use crate::platform::regional::KeyboardLayout;
+use crate::windows::WindowGeometry;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+  /// State format version, see `migrations`
+  pub version: u32,
+  /// UI language chosen in the wizard (BCP 47 tag)
+  pub locale: Option<String>,
+  /// Anonymous telemetry consent; `None` until the user has been asked
+  pub telemetry_consent: Option<bool>,
+  /// How often to try starting the backend before giving up
+  pub backend_start_attempts: Option<u32>,
+  /// Seconds before a copied secret is cleared from the clipboard
+  pub clipboard_clear_secs: Option<u64>,
+  /// Verbosity of the shell, the backend and playbook runs
+  pub log_level: Option<String>,
+  /// Memory limit for the backend and its children; 0 for none, three
+  /// quarters of RAM when unset
+  pub backend_memory_limit_mib: Option<u64>,
+  /// CPU limit for the backend, 100 per core
+  pub backend_cpu_percent: Option<u32>,
+  /// Maximum number of backend processes and threads
+  pub backend_max_tasks: Option<u32>,
+  /// Consecutive failed health checks before the backend counts as hung;
+  /// 0 disables the watchdog
+  pub backend_watchdog_failures: Option<u32>,
+  /// Log files kept per log, the current one included
+  pub log_keep_files: Option<usize>,
+  /// Days rotated logs and playbook logs are kept
+  pub log_keep_days: Option<u64>,
+  /// Size at which a log file is rotated
+  pub log_max_file_mib: Option<u64>,
+  /// Refuse to start a deployment on battery power instead of warning
+  pub block_on_battery: Option<bool>,
+  /// WebKitGTK renderer fallbacks that last gave a working window (Linux)
+  pub webview_fallback: Option<usize>,
+  /// Desktop notifications for finished phases and failures; on unless false
+  pub desktop_notifications: Option<bool>,
+  /// Main window placement when it was last closed
+  pub main_window: Option<WindowGeometry>,
+  /// Webview zoom factor; 1.0 when unset
+  pub zoom: Option<f64>,
+  /// Domain of the cluster being installed, whose links may be opened
+  pub cluster_domain: Option<String>,
+  /// Installation profile to load; the default profile when unset
+  pub profile: Option<String>,
+  /// Tag, branch or commit the playbooks checkout is pinned to
+  pub playbooks_ref: Option<String>,
+  /// Unpacked offline bundle used instead of the network
+  pub offline_bundle: Option<String>,
+  /// Keyboard layout for provisioned nodes; this machine's when unset
+  pub keyboard_layout: Option<KeyboardLayout>,
+}
+
+pub struct SettingsStore {
+  path: PathBuf,
+  data: Mutex<Settings>,
+}
+
+impl SettingsStore {
+  /// Load settings from disk, falling back to defaults if the file is
+  /// missing or unreadable.
+  pub fn load(app: &AppHandle) -> Result<Self, String> {
+    let path = app
+      .path()
+      .app_config_dir()
+      .map_err(|e| format!("Cannot resolve app config directory: {}", e))?
+      .join("settings.json");
+
+    let data = match std::fs::read_to_string(&path) {
+      Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+        warn!("Ignoring invalid {}: {}", path.display(), e);
+        Settings::default()
+      }),
+      Err(_) => Settings::default(),
+    };
+
+    Ok(Self {
+      path,
+      data: Mutex::new(data),
+    })
+  }
+
+  pub fn get(&self) -> Settings {
+    self.data.lock().map(|s| s.clone()).unwrap_or_default()
+  }
+
+  /// Apply `change` and write the result to disk.
+  pub fn update<F: FnOnce(&mut Settings)>(&self, change: F) -> Result<(), String> {
+    let mut data = self.data.lock().map_err(|e| e.to_string())?;
+    change(&mut data);
+    data.version = STATE_VERSION;
+    self.save(&data)
+  }
+
+  fn save(&self, data: &Settings) -> Result<(), String> {
+    if let Some(dir) = self.path.parent() {
+      std::fs::create_dir_all(dir)
+        .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    }
+    let json = serde_json::to_string_pretty(data).map_err(|e| e.to_string())?;
+
+    // Write to a sibling file and rename so a crash never leaves a
+    // truncated settings file behind.
+    let tmp = self.path.with_extension("json.tmp");
+    std::fs::write(&tmp, json).map_err(|e| format!("Failed to write {}: {}", tmp.display(), e))?;
+    std::fs::rename(&tmp, &self.path)
+      .map_err(|e| format!("Failed to write {}: {}", self.path.display(), e))
+  }
+}
@@ -0,0 +1,193 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Logging for the Rust shell.
+//!
+//! Everything the shell logs goes through `tracing`: to stdout as before, to
+//! `shell.log` in the app log directory (so packaged builds, which have no
+//! terminal, keep a record, and crash reports pick up its tail), and into a
+//! ring buffer the UI reads with `get_shell_logs`, masked by
+//! [`crate::redact`] on the way to each. Startup phases run inside spans, so
+//! each line says which phase and attempt it came from. The level follows
+//! [`crate::log_level`].
+//!
+//! Backend output is logged with the `backend` target. It goes to
+//! `backend.log` instead and has its own buffer (`get_backend_log`). Both
+//! files rotate as [`crate::log_files`] says.
+
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::io::IsTerminal;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager, State};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::filter::{filter_fn, LevelFilter};
+use tracing_subscriber::fmt::format::DefaultFields;
+use tracing_subscriber::fmt::FormattedFields;
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::{fmt, reload, Layer, Registry};
+
+use crate::log_files::{self, Retention, RotatingFile};
+use crate::redact::{redact, Redacting};
+
+/// Target of the lines echoed from the backend's stdout/stderr.
+pub const BACKEND_TARGET: &str = "backend";
+
+/// Lines kept for the UI.
+const BUFFER_LINES: usize = 5000;
+
+static LEVEL: OnceLock<reload::Handle<LevelFilter, Registry>> = OnceLock::new();
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ShellLogLine {
+  /// Milliseconds since the Unix epoch
+  pub timestamp: u64,
+  pub level: String,
+  pub target: String,
+  /// Enclosing spans, outermost first, e.g. `attempt{attempt=2}`
+  pub spans: Vec<String>,
+  pub message: String,
+}
+
+#[derive(Clone, Default)]
+pub struct ShellLog(Arc<Mutex<VecDeque<ShellLogLine>>>);
+
+impl ShellLog {
+  fn push(&self, line: ShellLogLine) {
+    if let Ok(mut buffer) = self.0.lock() {
+      if buffer.len() == BUFFER_LINES {
+        buffer.pop_front();
+      }
+      buffer.push_back(line);
+    }
+  }
+}
+
+/// The message of an event, followed by its other fields.
+#[derive(Default)]
+struct MessageVisitor(String);
+
+impl Visit for MessageVisitor {
+  fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+    if field.name() == "message" {
+      let fields = std::mem::take(&mut self.0);
+      let _ = write!(self.0, "{:?}{}", value, fields);
+    } else {
+      let _ = write!(self.0, " {}={:?}", field.name(), value);
+    }
+  }
+}
+
+impl<S> Layer<S> for ShellLog
+where
+  S: Subscriber + for<'a> LookupSpan<'a>,
+{
+  fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+    let metadata = event.metadata();
+    if metadata.target() == BACKEND_TARGET {
+      return;
+    }
+    let mut message = MessageVisitor::default();
+    event.record(&mut message);
+    // The fmt layers already keep the formatted fields of every span
+    let spans = ctx
+      .event_scope(event)
+      .map(|scope| {
+        scope
+          .from_root()
+          .map(|span| match span.extensions().get::<FormattedFields<DefaultFields>>() {
+            Some(fields) if !fields.is_empty() => format!("{}{{{}}}", span.name(), fields),
+            _ => span.name().to_string(),
+          })
+          .collect()
+      })
+      .unwrap_or_default();
+
+    self.push(ShellLogLine {
+      timestamp: SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default(),
+      level: metadata.level().to_string(),
+      target: metadata.target().to_string(),
+      spans,
+      message: redact(&message.0).into_owned(),
+    });
+  }
+}
+
+fn log_files(app: &AppHandle) -> Result<(RotatingFile, RotatingFile), String> {
+  let dir = log_files::app_log_dir(app)?;
+  let retention = Retention::configured(app);
+  Ok((
+    RotatingFile::open(&dir, "shell", retention)?,
+    RotatingFile::open(&dir, "backend", retention)?,
+  ))
+}
+
+/// Install the subscriber. Lines logged before this are lost, so it comes
+/// first in setup, right after the settings it takes the retention from.
+pub fn init(app: &AppHandle) {
+  let buffer = ShellLog::default();
+  app.manage(buffer.clone());
+  let (filter, handle) = reload::Layer::new(LevelFilter::INFO);
+  let (files, file_error) = match log_files(app) {
+    Ok((shell, backend)) => (
+      Some(
+        fmt::layer()
+          .with_ansi(false)
+          .with_writer(Mutex::new(Redacting(shell)))
+          .with_filter(filter_fn(|metadata| metadata.target() != BACKEND_TARGET))
+          .and_then(
+            fmt::layer()
+              .with_ansi(false)
+              .with_writer(Mutex::new(Redacting(backend)))
+              .with_filter(filter_fn(|metadata| metadata.target() == BACKEND_TARGET)),
+          ),
+      ),
+      None,
+    ),
+    Err(e) => (None, Some(e)),
+  };
+  let subscriber = Registry::default()
+    .with(filter)
+    .with(
+      fmt::layer()
+        .with_ansi(std::io::stdout().is_terminal())
+        .with_writer(|| Redacting(std::io::stdout())),
+    )
+    .with(files)
+    .with(buffer);
+
+  if let Err(e) = tracing::subscriber::set_global_default(subscriber) {
+    eprintln!("Failed to install the shell logger: {}", e);
+    return;
+  }
+  let _ = LEVEL.set(handle);
+  if let Some(e) = file_error {
+    tracing::warn!("{}; logging to stdout only", e);
+  }
+}
+
+/// Change which levels are logged.
+pub fn set_level(level: LevelFilter) {
+  if let Some(handle) = LEVEL.get() {
+    let _ = handle.modify(|filter| *filter = level);
+  }
+}
+
+/// The most recent `limit` lines (all of them by default), oldest first.
+#[tauri::command]
+pub fn get_shell_logs(log: State<'_, ShellLog>, limit: Option<usize>) -> Vec<ShellLogLine> {
+  let Ok(buffer) = log.0.lock() else {
+    return Vec::new();
+  };
+  let skip = limit.map(|limit| buffer.len().saturating_sub(limit)).unwrap_or(0);
+  buffer.iter().skip(skip).cloned().collect()
+}
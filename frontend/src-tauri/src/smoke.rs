@@ -0,0 +1,314 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Checking the cluster works once the playbooks have finished.
+//!
+//! `run_smoke_tests` resolves the service names and a random name under
+//! the domain (which only resolves if the wildcard record is in place),
+//! then probes Keycloak, Harbor, Thinkube Control, code-server and the
+//! ingress wildcard over HTTPS, trusting the public roots plus the cluster
+//! CA: the one passed in, or else the bootstrap CA from the run workspace.
+//! The API server is checked at `/livez` against the Kubernetes CA, read
+//! from the control plane over SSH when one is given. Any HTTP answer short
+//! of a server error counts, since most of these want a login first; what
+//! fails is a name that doesn't resolve, a certificate that isn't trusted
+//! or a service that doesn't answer. The checks run in parallel, each one
+//! emitted as a `smoke-test` event as it finishes.
+
+use serde::{Deserialize, Serialize};
+use std::net::ToSocketAddrs;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager};
+use tracing::{info, warn};
+
+use crate::certs;
+use crate::net::tls;
+use crate::preflight::{Outcome, Verdict};
+use crate::remote::ssh::{self, SshPool, SshTarget};
+use crate::validation;
+use crate::workspace::Workspace;
+
+pub const EVENT: &str = "smoke-test";
+
+const TIMEOUT: Duration = Duration::from_secs(15);
+const API_SERVER_PORT: u16 = 6443;
+/// Where kubeadm and the k8s snap keep the cluster CA
+const KUBE_CA_SCRIPT: &str =
+  "cat /etc/kubernetes/pki/ca.crt 2>/dev/null || cat /var/snap/k8s/common/etc/kubernetes/pki/ca.crt";
+
+/// `(check id, subdomain, path)` of Keycloak, Harbor, Thinkube Control and
+/// code-server
+const SERVICES: &[(&str, &str, &str)] = &[
+  ("keycloak", "auth", "/realms/master"),
+  ("harbor", "registry", "/api/v2.0/ping"),
+  ("control", "control", "/"),
+  ("code", "code", "/"),
+];
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct SmokeOptions {
+  /// Event tag; generated when omitted
+  pub run_id: Option<String>,
+  /// PEM of the CA the ingress certificates chain to, if not a public one
+  pub ca_pem: Option<String>,
+  /// Node to read the Kubernetes CA from; the API server is checked there
+  pub control_plane: Option<SshTarget>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SmokeCheck {
+  pub id: String,
+  /// What was probed: a name or a URL
+  pub target: String,
+  pub outcome: Outcome,
+  pub message: String,
+  pub duration_ms: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SmokeSummary {
+  pub pass: usize,
+  pub warn: usize,
+  pub fail: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SmokeReport {
+  pub run_id: String,
+  pub domain: String,
+  pub checks: Vec<SmokeCheck>,
+  pub summary: SmokeSummary,
+  /// No check failed
+  pub ok: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SmokeEvent {
+  pub run_id: String,
+  #[serde(flatten)]
+  pub check: SmokeCheck,
+}
+
+enum Probe {
+  Dns { id: String, name: String },
+  Https { id: String, url: String, ping: bool },
+  ApiServer { url: String },
+}
+
+/// An HTTPS client trusting `roots`, without following redirects.
+pub fn agent(roots: rustls::RootCertStore) -> Result<ureq::Agent, String> {
+  let config = rustls::ClientConfig::builder_with_provider(tls::provider())
+    .with_safe_default_protocol_versions()
+    .map_err(|e| e.to_string())?
+    .with_root_certificates(roots)
+    .with_no_client_auth();
+  Ok(
+    ureq::AgentBuilder::new()
+      .timeout(TIMEOUT)
+      .redirects(0)
+      .tls_config(Arc::new(config))
+      .build(),
+  )
+}
+
+fn resolve(name: &str) -> Verdict {
+  match (name, 443).to_socket_addrs() {
+    Ok(addrs) => {
+      let mut ips: Vec<String> = addrs.map(|a| a.ip().to_string()).collect();
+      ips.sort();
+      ips.dedup();
+      Verdict::pass(format!("Resolves to {}", ips.join(", ")))
+    }
+    Err(e) => Verdict::fail(format!("Doesn't resolve: {}", e)),
+  }
+}
+
+fn describe(error: ureq::Transport) -> String {
+  let detail = error.to_string();
+  match error.kind() {
+    ureq::ErrorKind::Dns => "The name doesn't resolve".to_string(),
+    _ if detail.contains("certificate") || detail.contains("UnknownIssuer") => {
+      format!("The certificate isn't trusted: {}", detail)
+    }
+    _ => format!("No answer: {}", detail),
+  }
+}
+
+fn https(agent: &ureq::Agent, url: &str, ping: bool) -> Verdict {
+  match agent.get(url).call() {
+    Ok(response) if ping && response.status() != 200 => {
+      Verdict::warn(format!("Answered HTTP {} instead of a pong", response.status()))
+    }
+    Ok(response) => Verdict::pass(format!("HTTP {}", response.status())),
+    Err(ureq::Error::Status(code, _)) if code >= 500 => Verdict::fail(format!("HTTP {}", code)),
+    Err(ureq::Error::Status(code, _)) if ping => Verdict::warn(format!("Answered HTTP {} instead of a pong", code)),
+    Err(ureq::Error::Status(code, _)) => Verdict::pass(format!("HTTP {}", code)),
+    Err(ureq::Error::Transport(transport)) => Verdict::fail(describe(transport)),
+  }
+}
+
+fn api_server(agent: Option<&ureq::Agent>, url: &str, ca_error: Option<&str>) -> Verdict {
+  let Some(agent) = agent else {
+    return Verdict::fail(format!(
+      "Couldn't read the Kubernetes CA: {}",
+      ca_error.unwrap_or("no control plane given")
+    ));
+  };
+  match agent.get(url).call() {
+    Ok(response) => Verdict::pass(format!("Live (HTTP {})", response.status())),
+    Err(ureq::Error::Status(code @ (401 | 403), _)) => {
+      Verdict::warn(format!("Answers, but refuses anonymous health checks (HTTP {})", code))
+    }
+    Err(ureq::Error::Status(code, _)) => Verdict::fail(format!("Not live (HTTP {})", code)),
+    Err(ureq::Error::Transport(transport)) => Verdict::fail(describe(transport)),
+  }
+}
+
+fn kube_ca(app: &AppHandle, target: &SshTarget) -> Result<String, String> {
+  let output = ssh::run_script(
+    &app.state::<Workspace>(),
+    &app.state::<SshPool>(),
+    target,
+    KUBE_CA_SCRIPT,
+    TIMEOUT,
+  )?;
+  if output.status != Some(0) || !output.stdout.contains("BEGIN CERTIFICATE") {
+    return Err(format!("No cluster CA found on {}", target.host));
+  }
+  Ok(output.stdout)
+}
+
+fn run(app: &AppHandle, domain: &str, options: SmokeOptions) -> Result<SmokeReport, String> {
+  let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+  let run_id = options
+    .run_id
+    .clone()
+    .unwrap_or_else(|| format!("smoke-{}", now.as_millis()));
+
+  let ca_pem = options.ca_pem.clone().filter(|pem| !pem.trim().is_empty()).or_else(|| {
+    let path = app.state::<Workspace>().resolve("certs/ca.crt").ok()?;
+    std::fs::read_to_string(path).ok()
+  });
+  let ingress = agent(certs::roots(ca_pem.as_deref())?)?;
+  let (kube, kube_error) = match &options.control_plane {
+    Some(target) => match kube_ca(app, target).and_then(|pem| agent(certs::roots(Some(&pem))?)) {
+      Ok(agent) => (Some(agent), None),
+      Err(e) => (None, Some(e)),
+    },
+    None => (None, None),
+  };
+
+  // Nothing is registered under it, so only the wildcard can answer
+  let wildcard = format!("smoke-{:x}.{}", now.as_millis(), domain);
+  let mut probes = vec![Probe::Dns {
+    id: "dns-wildcard".to_string(),
+    name: wildcard.clone(),
+  }];
+  for (id, subdomain, _) in SERVICES {
+    probes.push(Probe::Dns {
+      id: format!("dns-{}", id),
+      name: format!("{}.{}", subdomain, domain),
+    });
+  }
+  for (id, subdomain, path) in SERVICES {
+    probes.push(Probe::Https {
+      id: id.to_string(),
+      url: format!("https://{}.{}{}", subdomain, domain, path),
+      ping: *id == "harbor",
+    });
+  }
+  probes.push(Probe::Https {
+    id: "ingress-wildcard".to_string(),
+    url: format!("https://{}/", wildcard),
+    ping: false,
+  });
+  if let Some(target) = &options.control_plane {
+    let host = if target.host.contains(':') {
+      format!("[{}]", target.host)
+    } else {
+      target.host.clone()
+    };
+    probes.push(Probe::ApiServer {
+      url: format!("https://{}:{}/livez", host, API_SERVER_PORT),
+    });
+  }
+  info!("Running {} smoke tests against {}", probes.len(), domain);
+
+  let checks: Vec<SmokeCheck> = std::thread::scope(|scope| {
+    let handles: Vec<_> = probes
+      .iter()
+      .map(|probe| {
+        let (ingress, kube, kube_error, run_id) = (&ingress, kube.as_ref(), kube_error.as_deref(), &run_id);
+        scope.spawn(move || {
+          let started = Instant::now();
+          let (id, target, verdict) = match probe {
+            Probe::Dns { id, name } => (id.clone(), name.clone(), resolve(name)),
+            Probe::Https { id, url, ping } => (id.clone(), url.clone(), https(ingress, url, *ping)),
+            Probe::ApiServer { url } => ("api-server".to_string(), url.clone(), api_server(kube, url, kube_error)),
+          };
+          let check = SmokeCheck {
+            id,
+            target,
+            outcome: verdict.outcome,
+            message: verdict.message,
+            duration_ms: started.elapsed().as_millis() as u64,
+          };
+          let _ = app.emit(
+            EVENT,
+            SmokeEvent {
+              run_id: run_id.clone(),
+              check: check.clone(),
+            },
+          );
+          check
+        })
+      })
+      .collect();
+    handles.into_iter().filter_map(|h| h.join().ok()).collect()
+  });
+
+  let mut summary = SmokeSummary::default();
+  for check in &checks {
+    match check.outcome {
+      Outcome::Pass => summary.pass += 1,
+      Outcome::Warn => summary.warn += 1,
+      Outcome::Fail | Outcome::Skipped => summary.fail += 1,
+    }
+  }
+  if summary.fail > 0 {
+    warn!("{} of {} smoke tests failed", summary.fail, checks.len());
+  } else {
+    info!("All {} smoke tests passed", checks.len());
+  }
+  Ok(SmokeReport {
+    run_id,
+    domain: domain.to_string(),
+    ok: summary.fail == 0,
+    checks,
+    summary,
+  })
+}
+
+/// Probe the installed cluster's endpoints under `domain` and report what
+/// works.
+#[tauri::command]
+pub async fn run_smoke_tests(
+  app: AppHandle,
+  domain: String,
+  options: Option<SmokeOptions>,
+) -> Result<SmokeReport, String> {
+  let domain = domain.trim().trim_end_matches('.').to_ascii_lowercase();
+  if !validation::is_valid_domain(&domain) {
+    return Err(format!("Invalid domain: {}", domain));
+  }
+  let options = options.unwrap_or_default();
+  if let Some(target) = &options.control_plane {
+    target.validate()?;
+  }
+  tauri::async_runtime::spawn_blocking(move || run(&app, &domain, options))
+    .await
+    .map_err(|e| e.to_string())?
+}
@@ -0,0 +1,308 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Snapshots of the environment an install ran in.
+//!
+//! `capture_environment` records what can make one run behave differently
+//! from another: the environment variables the shell, backend and Ansible
+//! read, the versions of the tools the playbooks call, the OS, the shell
+//! settings, the wizard's resolved configuration (secrets dropped) and the
+//! branch and commit of the thinkube checkout. It is written as versioned
+//! JSON to `snapshots/` in the run workspace, which is kept when a run
+//! fails; `compare_environments` lists what differs between two of them.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+use tracing::info;
+
+use crate::config::{self, ConfigDocument, ExportRequest};
+use crate::platform::{self, os_release};
+use crate::redact::{self, MASK};
+use crate::settings::{Settings, SettingsStore};
+use crate::workspace::{write_private_file, Workspace};
+
+/// Value of the `format` field identifying snapshot files.
+pub const FORMAT: &str = "thinkube-environment-snapshot";
+
+/// Snapshot version written by this installer.
+pub const CURRENT_VERSION: u32 = 1;
+
+/// Variables recorded whole, and prefixes of families recorded as a group.
+const ENV_NAMES: &[&str] = &[
+  "PATH",
+  "HOME",
+  "SHELL",
+  "LANG",
+  "LC_ALL",
+  "TZ",
+  "HTTP_PROXY",
+  "HTTPS_PROXY",
+  "NO_PROXY",
+  "http_proxy",
+  "https_proxy",
+  "no_proxy",
+  "SSH_AUTH_SOCK",
+  "KUBECONFIG",
+  "VIRTUAL_ENV",
+  "PYTHONPATH",
+  "TERMINAL",
+];
+const ENV_PREFIXES: &[&str] = &["THINKUBE_", "TK_", "ANSIBLE_"];
+
+/// Tools the install calls, with the argument that makes each print its version.
+const TOOLS: &[(&str, &str)] = &[
+  ("python3", "--version"),
+  ("ansible", "--version"),
+  ("ansible-playbook", "--version"),
+  ("git", "--version"),
+  ("ssh", "-V"),
+  ("kubectl", "version --client"),
+  ("helm", "version --short"),
+];
+
+/// Where the backend keeps its thinkube checkout.
+const CHECKOUT: &str = "thinkube";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitState {
+  pub path: String,
+  pub branch: Option<String>,
+  pub commit: Option<String>,
+  pub remote_url: Option<String>,
+  /// Uncommitted changes in the checkout
+  pub dirty: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnvironmentSnapshot {
+  pub format: String,
+  pub version: u32,
+  pub installer_version: String,
+  /// Seconds since the Unix epoch
+  pub captured_at: u64,
+  pub run_id: String,
+  pub os: Value,
+  pub arch: String,
+  pub env: BTreeMap<String, String>,
+  /// Values baked in at build time, in effect unless `env` overrides them
+  pub build_defaults: BTreeMap<String, String>,
+  /// First line of each tool's version output; `None` when not installed
+  pub tools: BTreeMap<String, Option<String>>,
+  pub settings: Settings,
+  pub config: Option<ConfigDocument>,
+  pub git: Option<GitState>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SavedSnapshot {
+  pub path: String,
+  pub snapshot: EnvironmentSnapshot,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SnapshotDifference {
+  /// Dotted path of the value, e.g. `tools.ansible`
+  pub field: String,
+  pub before: Option<Value>,
+  pub after: Option<Value>,
+}
+
+fn is_secret_name(name: &str) -> bool {
+  let name = name.to_ascii_uppercase();
+  ["TOKEN", "PASSWORD", "SECRET", "KEY"]
+    .iter()
+    .any(|marker| name.contains(marker))
+}
+
+fn environment() -> BTreeMap<String, String> {
+  std::env::vars()
+    .filter(|(name, _)| ENV_NAMES.contains(&name.as_str()) || ENV_PREFIXES.iter().any(|p| name.starts_with(p)))
+    .map(|(name, value)| {
+      let value = if is_secret_name(&name) {
+        MASK.to_string()
+      } else {
+        redact::redact(&value).into_owned()
+      };
+      (name, value)
+    })
+    .collect()
+}
+
+fn build_defaults() -> BTreeMap<String, String> {
+  [
+    ("THINKUBE_BRANCH", option_env!("THINKUBE_BUILD_BRANCH")),
+    ("THINKUBE_REPO_URL", option_env!("THINKUBE_BUILD_REPO_URL")),
+    ("THINKUBE_METADATA_REPO", option_env!("THINKUBE_BUILD_METADATA_REPO")),
+  ]
+  .into_iter()
+  .filter_map(|(name, value)| {
+    value
+      .filter(|v| !v.is_empty())
+      .map(|v| (name.to_string(), v.to_string()))
+  })
+  .collect()
+}
+
+fn first_line(output: std::process::Output) -> Option<String> {
+  // `ssh -V` prints to stderr
+  let text = if output.stdout.is_empty() {
+    output.stderr
+  } else {
+    output.stdout
+  };
+  String::from_utf8_lossy(&text)
+    .lines()
+    .next()
+    .map(|line| line.trim().to_string())
+    .filter(|line| !line.is_empty())
+}
+
+fn tool_versions(venv_bin: Option<&Path>) -> BTreeMap<String, Option<String>> {
+  TOOLS
+    .iter()
+    .map(|(name, args)| {
+      // The backend's venv comes first: it is what the playbooks run with
+      let program = venv_bin
+        .map(|dir| dir.join(name))
+        .filter(|p| p.is_file())
+        .or_else(|| platform::find_program(name, &[]));
+      let version = program.and_then(|program| {
+        Command::new(program)
+          .args(args.split_whitespace())
+          .output()
+          .ok()
+          .and_then(first_line)
+      });
+      (name.to_string(), version)
+    })
+    .collect()
+}
+
+fn git(dir: &Path, args: &[&str]) -> Option<String> {
+  let output = Command::new("git").arg("-C").arg(dir).args(args).output().ok()?;
+  if !output.status.success() {
+    return None;
+  }
+  Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn git_state() -> Option<GitState> {
+  let dir = PathBuf::from(std::env::var_os("HOME")?).join(CHECKOUT);
+  let commit = git(&dir, &["rev-parse", "HEAD"])?;
+  Some(GitState {
+    path: dir.display().to_string(),
+    branch: git(&dir, &["rev-parse", "--abbrev-ref", "HEAD"]).filter(|b| b != "HEAD"),
+    commit: Some(commit),
+    remote_url: git(&dir, &["remote", "get-url", "origin"]).map(|url| redact::redact(&url).into_owned()),
+    dirty: git(&dir, &["status", "--porcelain"]).is_some_and(|status| !status.is_empty()),
+  })
+}
+
+fn capture(app: &AppHandle, request: Option<ExportRequest>) -> EnvironmentSnapshot {
+  let workspace = app.state::<Workspace>();
+  EnvironmentSnapshot {
+    format: FORMAT.to_string(),
+    version: CURRENT_VERSION,
+    installer_version: app.package_info().version.to_string(),
+    captured_at: SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .map(|d| d.as_secs())
+      .unwrap_or_default(),
+    run_id: workspace.run_id().to_string(),
+    os: serde_json::to_value(os_release::detect()).unwrap_or_default(),
+    arch: std::env::consts::ARCH.to_string(),
+    env: environment(),
+    build_defaults: build_defaults(),
+    tools: tool_versions(crate::backend::venv_bin(app).as_deref()),
+    settings: app.try_state::<SettingsStore>().map(|s| s.get()).unwrap_or_default(),
+    config: request.map(|request| config::build_document(app, request, false)),
+    git: git_state(),
+  }
+}
+
+/// `value` flattened to dotted paths.
+fn flatten(prefix: &str, value: &Value, out: &mut BTreeMap<String, Value>) {
+  match value {
+    Value::Object(map) if !map.is_empty() => {
+      for (key, value) in map {
+        let path = if prefix.is_empty() {
+          key.clone()
+        } else {
+          format!("{}.{}", prefix, key)
+        };
+        flatten(&path, value, out);
+      }
+    }
+    _ => {
+      out.insert(prefix.to_string(), value.clone());
+    }
+  }
+}
+
+fn read_snapshot(path: &str) -> Result<Value, String> {
+  let text = std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+  let value: Value = serde_json::from_str(&text).map_err(|e| format!("{} is not valid JSON: {}", path, e))?;
+  if value["format"] != FORMAT {
+    return Err(format!("{} is not an environment snapshot", path));
+  }
+  if value["version"]
+    .as_u64()
+    .map_or(true, |v| v > u64::from(CURRENT_VERSION))
+  {
+    return Err(format!("{} was written by a newer installer", path));
+  }
+  Ok(value)
+}
+
+/// Capture the environment into the run workspace. `config` is the wizard's
+/// state, as for `export_config`; its secrets are left out.
+#[tauri::command]
+pub async fn capture_environment(app: AppHandle, config: Option<ExportRequest>) -> Result<SavedSnapshot, String> {
+  tauri::async_runtime::spawn_blocking(move || {
+    let snapshot = capture(&app, config);
+    let path = app
+      .state::<Workspace>()
+      .resolve(&format!("snapshots/environment-{}.json", snapshot.captured_at))?;
+    let json = serde_json::to_string_pretty(&snapshot).map_err(|e| e.to_string())?;
+    write_private_file(&path, json.as_bytes())?;
+    info!("Captured environment snapshot to {}", path.display());
+    Ok(SavedSnapshot {
+      path: path.display().to_string(),
+      snapshot,
+    })
+  })
+  .await
+  .map_err(|e| e.to_string())?
+}
+
+/// Every value that differs between two snapshot files, ignoring when and
+/// in which run they were taken.
+#[tauri::command]
+pub fn compare_environments(before: String, after: String) -> Result<Vec<SnapshotDifference>, String> {
+  let (mut a, mut b) = (BTreeMap::new(), BTreeMap::new());
+  flatten("", &read_snapshot(&before)?, &mut a);
+  flatten("", &read_snapshot(&after)?, &mut b);
+
+  let mut fields: Vec<&String> = a.keys().chain(b.keys()).collect();
+  fields.sort();
+  fields.dedup();
+  Ok(
+    fields
+      .into_iter()
+      .filter(|field| !matches!(field.as_str(), "captured_at" | "run_id" | "config.exported_at"))
+      .filter(|field| a.get(*field) != b.get(*field))
+      .map(|field| SnapshotDifference {
+        field: field.clone(),
+        before: a.get(field).cloned(),
+        after: b.get(field).cloned(),
+      })
+      .collect(),
+  )
+}
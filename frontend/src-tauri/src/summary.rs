@@ -0,0 +1,287 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! The post-install summary handed to the team running the cluster.
+//!
+//! Unlike the installation report, which lists every step, this is a
+//! single page about the result: the service endpoints, where the
+//! kubeconfig, inventory and secrets live, the node table and what to do
+//! next. The wizard passes the nodes it placed; the rest comes from the
+//! shell's own state (cluster domain, run workspace, transcript). HTML is
+//! saved wherever the user picks; PDF goes through the system print
+//! dialog's "Save as PDF", since there's no PDF renderer in the shell.
+
+use serde::Deserialize;
+use std::fmt::Write as _;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::webview::PageLoadEvent;
+use tauri::{AppHandle, Manager, State, Url, WebviewUrl, WebviewWindowBuilder};
+use tauri_plugin_dialog::DialogExt;
+use tracing::{info, warn};
+
+use crate::i18n;
+use crate::keyring;
+use crate::profiles;
+use crate::redact::redact;
+use crate::report::{format_time, html_escape, Transcript};
+use crate::settings::SettingsStore;
+use crate::telemetry::Outcome;
+use crate::uninstall::kubeconfig_path;
+use crate::windows::MAIN_WINDOW;
+use crate::workspace::{write_private_file, Workspace};
+
+const SUMMARY_WINDOW: &str = "summary";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SummaryFormat {
+  Html,
+  Pdf,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SummaryNode {
+  pub hostname: String,
+  pub ip: String,
+  pub role: String,
+  pub gpu: Option<String>,
+}
+
+/// What only the wizard knows about the cluster.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct SummaryRequest {
+  pub cluster_name: Option<String>,
+  /// Falls back to the domain saved with `set_cluster_domain`
+  pub domain: Option<String>,
+  pub admin_username: Option<String>,
+  pub overlay_provider: Option<String>,
+  pub nodes: Vec<SummaryNode>,
+}
+
+/// Services every installation exposes, by subdomain.
+const ENDPOINTS: &[(&str, &str, &str)] = &[
+  ("control", "Thinkube Control", "Platform dashboard and single sign-on"),
+  ("code", "Code Server", "Browser IDE with the cluster's tools"),
+];
+
+fn section(out: &mut String, title: &str) {
+  let _ = writeln!(out, "<h2>{}</h2>", html_escape(title));
+}
+
+fn render(app: &AppHandle, request: &SummaryRequest, domain: Option<&str>) -> String {
+  let steps = app.state::<Transcript>().steps();
+  let workspace = app.state::<Workspace>();
+  let now = SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|d| d.as_millis() as u64)
+    .unwrap_or_default();
+  let name = request
+    .cluster_name
+    .as_deref()
+    .filter(|n| !n.is_empty())
+    .unwrap_or("Thinkube");
+
+  let mut out = String::new();
+  let _ = write!(
+    out,
+    "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{} cluster summary</title>\n<style>\n\
+     body {{ font-family: sans-serif; max-width: 52rem; margin: 2rem auto; color: #1f2328; }}\n\
+     h1 {{ margin-bottom: 0.2rem; }} .subtitle {{ color: #656d76; margin-top: 0; }}\n\
+     table {{ border-collapse: collapse; width: 100%; }}\n\
+     th, td {{ border: 1px solid #d0d7de; padding: 0.35rem 0.6rem; text-align: left; }}\n\
+     th {{ background: #f6f8fa; }} code {{ background: #f6f8fa; padding: 0 0.2rem; }}\n\
+     .failed {{ color: #cf222e; }}\n\
+     @media print {{ body {{ margin: 0; }} a {{ color: inherit; }} }}\n\
+     </style>\n</head>\n<body>\n<h1>{} cluster summary</h1>\n",
+    html_escape(name),
+    html_escape(name)
+  );
+  let _ = writeln!(
+    out,
+    "<p class=\"subtitle\">Installed with Thinkube Installer {} · {}</p>",
+    html_escape(&app.package_info().version.to_string()),
+    format_time(now)
+  );
+
+  section(&mut out, "Endpoints");
+  match domain {
+    Some(domain) => {
+      out.push_str("<table>\n<tr><th>Service</th><th>URL</th><th>Purpose</th></tr>\n");
+      for (sub, service, purpose) in ENDPOINTS {
+        let url = format!("https://{}.{}", sub, domain);
+        let _ = writeln!(
+          out,
+          "<tr><td>{}</td><td><a href=\"{}\">{}</a></td><td>{}</td></tr>",
+          service,
+          html_escape(&url),
+          html_escape(&url),
+          purpose
+        );
+      }
+      out.push_str("</table>\n");
+    }
+    None => out.push_str("<p>No cluster domain was configured.</p>\n"),
+  }
+  if let Some(user) = request.admin_username.as_deref().filter(|u| !u.is_empty()) {
+    let _ = writeln!(
+      out,
+      "<p>Sign in as <code>{}</code>; the password is the one set during installation.</p>",
+      html_escape(user)
+    );
+  }
+
+  section(&mut out, "Credentials and files");
+  out.push_str("<ul>\n");
+  if let Some(path) = kubeconfig_path(app) {
+    let _ = writeln!(
+      out,
+      "<li>Kubeconfig: <code>{}</code></li>",
+      html_escape(&path.display().to_string())
+    );
+  }
+  let _ = writeln!(
+    out,
+    "<li>Installer workspace (inventory, generated certificates): <code>{}</code></li>",
+    html_escape(&workspace.root().display().to_string())
+  );
+  let _ = writeln!(
+    out,
+    "<li>API tokens and passwords: the OS keyring of the installing user, service <code>{}</code></li>",
+    keyring::SERVICE
+  );
+  out.push_str("</ul>\n<p>None of the secrets themselves are included in this document.</p>\n");
+
+  section(&mut out, "Nodes");
+  if request.nodes.is_empty() {
+    out.push_str("<p>No nodes were reported.</p>\n");
+  } else {
+    out.push_str("<table>\n<tr><th>Host</th><th>IP address</th><th>Role</th><th>GPU</th></tr>\n");
+    for node in &request.nodes {
+      let _ = writeln!(
+        out,
+        "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+        html_escape(&node.hostname),
+        html_escape(&node.ip),
+        html_escape(&node.role),
+        html_escape(node.gpu.as_deref().unwrap_or("—"))
+      );
+    }
+    out.push_str("</table>\n");
+  }
+  if let Some(overlay) = request.overlay_provider.as_deref().filter(|o| !o.is_empty()) {
+    let _ = writeln!(out, "<p>Remote access overlay: {}</p>", html_escape(overlay));
+  }
+
+  let failed: Vec<&str> = steps
+    .iter()
+    .filter(|s| s.outcome == Outcome::Failure)
+    .map(|s| s.title.as_str())
+    .collect();
+  if !steps.is_empty() {
+    section(&mut out, "Installation");
+    let _ = writeln!(
+      out,
+      "<p>{} steps recorded, {} succeeded.</p>",
+      steps.len(),
+      steps.iter().filter(|s| s.outcome == Outcome::Success).count()
+    );
+    if !failed.is_empty() {
+      let _ = writeln!(
+        out,
+        "<p class=\"failed\">Failed along the way (retried or skipped): {}</p>",
+        html_escape(&failed.join(", "))
+      );
+    }
+  }
+
+  section(&mut out, "Next steps");
+  out.push_str("<ol>\n");
+  if let Some(domain) = domain {
+    let _ = writeln!(
+      out,
+      "<li>Open <a href=\"https://control.{d}\">https://control.{d}</a> and sign in.</li>",
+      d = html_escape(domain)
+    );
+  }
+  out.push_str(
+    "<li>Check the cluster from the terminal: <code>kubectl get nodes</code> with the kubeconfig above.</li>\n\
+     <li>Back up the kubeconfig and the installer workspace somewhere only administrators can read.</li>\n\
+     <li>Give team members their own accounts in Thinkube Control instead of sharing the admin login.</li>\n\
+     </ol>\n</body>\n</html>\n",
+  );
+  out
+}
+
+/// Open the summary in its own window and start printing it, which is
+/// where "Save as PDF" lives.
+fn print(app: &AppHandle, path: &std::path::Path) -> Result<(), String> {
+  let url = Url::from_file_path(path).map_err(|_| format!("Cannot open {}", path.display()))?;
+  if let Some(window) = app.get_webview_window(SUMMARY_WINDOW) {
+    let _ = window.close();
+  }
+  WebviewWindowBuilder::new(app, SUMMARY_WINDOW, WebviewUrl::External(url))
+    .title(i18n::t("summary-window-title"))
+    .inner_size(900.0, 800.0)
+    .on_page_load(|window, payload| {
+      if payload.event() == PageLoadEvent::Finished {
+        if let Err(e) = window.print() {
+          warn!("Failed to print the summary: {}", e);
+        }
+      }
+    })
+    .build()
+    .map(|_| ())
+    .map_err(|e| e.to_string())
+}
+
+/// Render the post-install summary. HTML asks where to save it and returns
+/// the path, or `None` when cancelled; PDF opens the print dialog on a copy
+/// under `<profile data dir>/reports/`.
+#[tauri::command]
+pub async fn export_summary(
+  app: AppHandle,
+  settings: State<'_, SettingsStore>,
+  format: SummaryFormat,
+  summary: SummaryRequest,
+) -> Result<Option<String>, String> {
+  let domain = summary
+    .domain
+    .clone()
+    .filter(|d| !d.trim().is_empty())
+    .or_else(|| settings.get().cluster_domain);
+  let content = redact(&render(&app, &summary, domain.as_deref())).into_owned();
+  let stem = format!("{}-summary", summary.cluster_name.as_deref().unwrap_or("thinkube"));
+
+  if format == SummaryFormat::Pdf {
+    let dir = profiles::data_dir(&app)?.join("reports");
+    let path = dir.join(format!("{}.html", stem));
+    write_private_file(&path, content.as_bytes())?;
+    print(&app, &path)?;
+    info!("Printing the cluster summary from {}", path.display());
+    return Ok(Some(path.display().to_string()));
+  }
+
+  tauri::async_runtime::spawn_blocking(move || {
+    let mut dialog = app
+      .dialog()
+      .file()
+      .set_title(i18n::t("summary-save-title"))
+      .set_file_name(format!("{}.html", stem))
+      .add_filter("HTML", &["html", "htm"]);
+    if let Some(window) = app.get_webview_window(MAIN_WINDOW) {
+      dialog = dialog.set_parent(&window);
+    }
+    let Some(chosen) = dialog.blocking_save_file() else {
+      return Ok(None);
+    };
+    let path = chosen.into_path().map_err(|e| e.to_string())?;
+    write_private_file(&path, content.as_bytes())?;
+    info!("Cluster summary written to {}", path.display());
+    Ok(Some(path.display().to_string()))
+  })
+  .await
+  .map_err(|e| e.to_string())?
+}
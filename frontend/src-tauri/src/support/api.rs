@@ -0,0 +1,152 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! The read-only diagnostics API a support session exposes.
+//!
+//! It listens on a random loopback port that only the tunnel forwards to,
+//! answers `GET` alone, and wants the session's access code as a bearer
+//! token or `code` query parameter. `/status` is the installer, OS and
+//! backend state, `/transcript` the recorded steps and `/logs?limit=` the
+//! shell's recent log lines. Every answer goes through the redaction
+//! filter once more, and every request is logged, so the user can see
+//! what was looked at.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tracing::{info, warn};
+
+use crate::backend::{self, Backend, BackendStatus};
+use crate::install_guard::InstallGuard;
+use crate::platform::os_release::{self, OsInfo};
+use crate::report::Transcript;
+use crate::shell_log::{self, ShellLog};
+use crate::{dry_run, profiles, redact};
+
+const MAX_HEADER_BYTES: usize = 8192;
+const DEFAULT_LOG_LINES: usize = 500;
+
+#[derive(Debug, Clone, Serialize)]
+struct Status {
+  installer_version: String,
+  os: OsInfo,
+  profile: String,
+  backend: BackendStatus,
+  running_step: Option<String>,
+  dry_run: bool,
+}
+
+pub fn serve(app: AppHandle, listener: TcpListener, code: String, stop: Arc<AtomicBool>) {
+  if let Err(e) = listener.set_nonblocking(true) {
+    warn!("Support API: {}", e);
+    return;
+  }
+  std::thread::spawn(move || {
+    while !stop.load(Ordering::SeqCst) {
+      match listener.accept() {
+        Ok((stream, _)) => {
+          let (app, code) = (app.clone(), code.clone());
+          std::thread::spawn(move || {
+            if let Err(e) = handle(&app, stream, &code) {
+              warn!("Support API: {}", e);
+            }
+          });
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => std::thread::sleep(Duration::from_millis(100)),
+        Err(e) => {
+          warn!("Support API accept failed: {}", e);
+          std::thread::sleep(Duration::from_millis(500));
+        }
+      }
+    }
+  });
+}
+
+fn respond(stream: &mut TcpStream, code: u16, reason: &str, body: &str) -> std::io::Result<()> {
+  write!(
+    stream,
+    "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+    code,
+    reason,
+    body.len(),
+    body
+  )
+}
+
+fn query<'a>(target: &'a str, name: &str) -> Option<&'a str> {
+  target
+    .split_once('?')?
+    .1
+    .split('&')
+    .find_map(|pair| pair.strip_prefix(name)?.strip_prefix('='))
+}
+
+fn status(app: &AppHandle) -> Status {
+  Status {
+    installer_version: app.package_info().version.to_string(),
+    os: os_release::detect(),
+    profile: profiles::active(app),
+    backend: backend::get_backend_status(app.state::<Backend>()),
+    running_step: app.state::<InstallGuard>().running_step(),
+    dry_run: dry_run::is_enabled(app),
+  }
+}
+
+fn handle(app: &AppHandle, mut stream: TcpStream, code: &str) -> std::io::Result<()> {
+  stream.set_nonblocking(false)?;
+  stream.set_read_timeout(Some(Duration::from_secs(10)))?;
+  let mut reader = BufReader::new(stream.try_clone()?);
+  let mut request_line = String::new();
+  reader.read_line(&mut request_line)?;
+  let mut bearer = None;
+  let mut header_bytes = 0;
+  loop {
+    let mut line = String::new();
+    let n = reader.read_line(&mut line)?;
+    header_bytes += n;
+    if n == 0 || line == "\r\n" || line == "\n" || header_bytes > MAX_HEADER_BYTES {
+      break;
+    }
+    if let Some((name, value)) = line.split_once(':') {
+      if name.trim().eq_ignore_ascii_case("authorization") {
+        bearer = value.trim().strip_prefix("Bearer ").map(|t| t.trim().to_string());
+      }
+    }
+  }
+
+  let mut parts = request_line.split_whitespace();
+  let method = parts.next().unwrap_or("");
+  let target = parts.next().unwrap_or("");
+  let path = target.split('?').next().unwrap_or("");
+  if method != "GET" {
+    return respond(&mut stream, 405, "Method Not Allowed", r#"{"detail":"Read only"}"#);
+  }
+  let presented = bearer.as_deref().or_else(|| query(target, "code")).unwrap_or("");
+  // Comparing digests so the time taken says nothing about the code
+  let digest = |text: &str| ring::digest::digest(&ring::digest::SHA256, text.as_bytes());
+  if digest(presented).as_ref() != digest(code).as_ref() {
+    return respond(&mut stream, 401, "Unauthorized", r#"{"detail":"Wrong access code"}"#);
+  }
+  info!("Support session: GET {}", path);
+
+  let body = match path {
+    "/status" => serde_json::to_value(status(app)),
+    "/transcript" => serde_json::to_value(app.state::<Transcript>().steps()),
+    "/logs" => {
+      let limit = query(target, "limit")
+        .and_then(|l| l.parse().ok())
+        .unwrap_or(DEFAULT_LOG_LINES);
+      serde_json::to_value(shell_log::get_shell_logs(app.state::<ShellLog>(), Some(limit)))
+    }
+    _ => return respond(&mut stream, 404, "Not Found", r#"{"detail":"Not found"}"#),
+  }
+  .unwrap_or(Value::Null);
+  respond(&mut stream, 200, "OK", &redact::redact(&body.to_string()))
+}
@@ -0,0 +1,305 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Letting a support engineer look at a stuck installation.
+//!
+//! Nothing here runs unless the user asks: `open_support_tunnel` needs
+//! their explicit consent. It starts the read-only diagnostics API in `api`
+//! on a random loopback port and opens a reverse SSH tunnel to the support
+//! endpoint that forwards a port there to it, and to nothing else. No shell
+//! or other port is reachable through it. The engineer also needs the
+//! access code shown to the user, who reads it out. The endpoint's host key
+//! must have been confirmed like a node's, since it is checked strictly.
+//!
+//! A session lasts at most `MAX_MINUTES` and ends on its own when its time
+//! is up or the tunnel drops. While it is open the tray tooltip says so and
+//! a `support-tunnel` event carries the session for the banner, with `null`
+//! once it is over; `close_support_tunnel` ends it at once, and so does
+//! quitting the installer.
+
+pub mod api;
+
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader};
+use std::net::TcpListener;
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tracing::info;
+
+use crate::backend::random_hex;
+use crate::platform::find_program;
+use crate::remote::ssh::SshTarget;
+use crate::workspace::Workspace;
+use crate::{i18n, redact};
+
+pub const EVENT: &str = "support-tunnel";
+
+const DEFAULT_MINUTES: u64 = 30;
+const MAX_MINUTES: u64 = 120;
+/// How long ssh gets to connect and have the endpoint allocate a port
+const OPEN_TIMEOUT: Duration = Duration::from_secs(20);
+const TICK: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SupportRequest {
+  /// The support endpoint; only a key file, if any, may be given
+  pub endpoint: SshTarget,
+  /// Defaults to `DEFAULT_MINUTES`
+  pub minutes: Option<u64>,
+  /// The user agreed to share the diagnostics
+  #[serde(default)]
+  pub consent: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SupportSession {
+  /// `user@host` of the support endpoint
+  pub endpoint: String,
+  /// Port on the endpoint forwarded to the diagnostics API
+  pub remote_port: u16,
+  /// Code the engineer must present, shown to the user to read out
+  pub access_code: String,
+  /// Unix times
+  pub started_at: u64,
+  pub expires_at: u64,
+}
+
+struct Session {
+  info: SupportSession,
+  child: Child,
+  stop: Arc<AtomicBool>,
+}
+
+/// The open support session, if any.
+#[derive(Default)]
+pub struct SupportTunnel(Mutex<Option<Session>>);
+
+impl SupportTunnel {
+  fn take(&self) -> Option<Session> {
+    self.0.lock().unwrap_or_else(|e| e.into_inner()).take()
+  }
+}
+
+fn now() -> u64 {
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|d| d.as_secs())
+    .unwrap_or_default()
+}
+
+fn set_tooltip(app: &AppHandle, open: bool) {
+  if let Some(tray) = app.tray_by_id("main") {
+    let tooltip = if open {
+      i18n::t("tray-tooltip-support")
+    } else {
+      "Thinkube Installer".to_string()
+    };
+    let _ = tray.set_tooltip(Some(tooltip));
+  }
+}
+
+fn end(app: &AppHandle, session: Session, reason: &str) {
+  let Session { info, mut child, stop } = session;
+  stop.store(true, Ordering::SeqCst);
+  let _ = child.kill();
+  let _ = child.wait();
+  info!("Support session with {} ended: {}", info.endpoint, reason);
+  set_tooltip(app, false);
+  let _ = app.emit(EVENT, None::<SupportSession>);
+}
+
+/// Start ssh and wait for the endpoint to say which port it forwards.
+fn connect(workspace: &Workspace, endpoint: &SshTarget, local_port: u16) -> Result<(Child, u16), String> {
+  let ssh = find_program("ssh", &[]).ok_or("ssh is not installed")?;
+  let known_hosts = workspace.resolve("ssh/known_hosts")?;
+  let mut cmd = Command::new(ssh);
+  cmd
+    .args(["-N", "-T"])
+    .args(["-p", &endpoint.port.unwrap_or(22).to_string()])
+    .args(["-o", "BatchMode=yes"])
+    .args(["-o", "ExitOnForwardFailure=yes"])
+    .args(["-o", "ServerAliveInterval=15"])
+    .args(["-o", "ServerAliveCountMax=3"])
+    .args(["-o", "ControlPath=none"])
+    .args(["-o", "StrictHostKeyChecking=yes"])
+    .arg("-o")
+    .arg(format!("UserKnownHostsFile={}", known_hosts.display()))
+    .arg("-R")
+    .arg(format!("0:127.0.0.1:{}", local_port));
+  if let Some(identity) = &endpoint.identity_file {
+    cmd.args(["-o", "IdentitiesOnly=yes", "-i", identity]);
+  }
+  let mut child = cmd
+    .arg(format!("{}@{}", endpoint.user, endpoint.host))
+    .stdin(Stdio::null())
+    .stdout(Stdio::null())
+    .stderr(Stdio::piped())
+    .spawn()
+    .map_err(|e| format!("Failed to run ssh: {}", e))?;
+
+  // Keep reading stderr for the life of the tunnel, so ssh never blocks on it
+  let stderr = child.stderr.take().ok_or("ssh has no stderr")?;
+  let (tx, rx) = mpsc::channel();
+  std::thread::spawn(move || {
+    for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+      let _ = tx.send(line);
+    }
+  });
+
+  let deadline = Instant::now() + OPEN_TIMEOUT;
+  let mut errors = Vec::new();
+  let failure = loop {
+    let left = deadline.saturating_duration_since(Instant::now());
+    if left.is_zero() {
+      break "Timed out waiting for the support endpoint".to_string();
+    }
+    match rx.recv_timeout(left) {
+      Ok(line) => {
+        if let Some(port) = line
+          .strip_prefix("Allocated port ")
+          .and_then(|rest| rest.split_whitespace().next())
+          .and_then(|port| port.parse().ok())
+        {
+          return Ok((child, port));
+        }
+        if line.contains("Host key verification failed") {
+          break format!(
+            "The host key of {} isn't confirmed yet; confirm it before opening a support session",
+            endpoint.host
+          );
+        }
+        errors.push(line);
+      }
+      // ssh exited without allocating a port
+      Err(mpsc::RecvTimeoutError::Disconnected) => {
+        break match errors.last() {
+          Some(line) => format!("Could not open the support tunnel: {}", line),
+          None => "Could not open the support tunnel".to_string(),
+        };
+      }
+      Err(mpsc::RecvTimeoutError::Timeout) => {}
+    }
+  };
+  let _ = child.kill();
+  let _ = child.wait();
+  Err(failure)
+}
+
+/// End the session when its time is up or ssh exits.
+fn watch(app: AppHandle, stop: Arc<AtomicBool>, expires_at: u64) {
+  std::thread::spawn(move || loop {
+    std::thread::sleep(TICK);
+    if stop.load(Ordering::SeqCst) {
+      return;
+    }
+    let tunnel = app.state::<SupportTunnel>();
+    let mut guard = tunnel.0.lock().unwrap_or_else(|e| e.into_inner());
+    let Some(session) = guard.as_mut().filter(|s| Arc::ptr_eq(&s.stop, &stop)) else {
+      return;
+    };
+    let reason = if !matches!(session.child.try_wait(), Ok(None)) {
+      "the tunnel dropped"
+    } else if now() >= expires_at {
+      "its time was up"
+    } else {
+      continue;
+    };
+    if let Some(session) = guard.take() {
+      drop(guard);
+      end(&app, session, reason);
+    }
+    return;
+  });
+}
+
+#[tauri::command]
+pub async fn open_support_tunnel(app: AppHandle, request: SupportRequest) -> Result<SupportSession, String> {
+  if !request.consent {
+    return Err("A support session needs your consent".to_string());
+  }
+  let endpoint = request.endpoint;
+  endpoint.validate()?;
+  if endpoint.jump_host.is_some() || endpoint.agent_key.is_some() {
+    return Err("The support endpoint must be reached directly, with a key file or the default keys".to_string());
+  }
+  let minutes = request.minutes.unwrap_or(DEFAULT_MINUTES);
+  if !(1..=MAX_MINUTES).contains(&minutes) {
+    return Err(format!("A support session lasts 1 to {} minutes", MAX_MINUTES));
+  }
+  if app
+    .state::<SupportTunnel>()
+    .0
+    .lock()
+    .map_err(|e| e.to_string())?
+    .is_some()
+  {
+    return Err("A support session is already open".to_string());
+  }
+
+  let listener = TcpListener::bind("127.0.0.1:0").map_err(|e| format!("Failed to start the diagnostics API: {}", e))?;
+  let local_port = listener.local_addr().map_err(|e| e.to_string())?.port();
+  let access_code = random_hex(16)?;
+  redact::register(&access_code);
+
+  let handle = app.clone();
+  let target = endpoint.clone();
+  let (child, remote_port) =
+    tauri::async_runtime::spawn_blocking(move || connect(&handle.state::<Workspace>(), &target, local_port))
+      .await
+      .map_err(|e| e.to_string())??;
+
+  let stop = Arc::new(AtomicBool::new(false));
+  api::serve(app.clone(), listener, access_code.clone(), stop.clone());
+  let started_at = now();
+  let info = SupportSession {
+    endpoint: format!("{}@{}", endpoint.user, endpoint.host),
+    remote_port,
+    access_code,
+    started_at,
+    expires_at: started_at + minutes * 60,
+  };
+  let session = Session {
+    info: info.clone(),
+    child,
+    stop: stop.clone(),
+  };
+  let tunnel = app.state::<SupportTunnel>();
+  let previous = tunnel.0.lock().map_err(|e| e.to_string())?.replace(session);
+  if let Some(previous) = previous {
+    // Opened twice at the same time; keep the newer one
+    end(&app, previous, "replaced");
+  }
+  watch(app.clone(), stop, info.expires_at);
+  info!(
+    "Support session with {} open for {} minutes on remote port {}",
+    info.endpoint, minutes, remote_port
+  );
+  set_tooltip(&app, true);
+  let _ = app.emit(EVENT, Some(&info));
+  Ok(info)
+}
+
+#[tauri::command]
+pub fn close_support_tunnel(app: AppHandle, tunnel: State<'_, SupportTunnel>) {
+  if let Some(session) = tunnel.take() {
+    end(&app, session, "closed by the user");
+  }
+}
+
+#[tauri::command]
+pub fn get_support_tunnel(tunnel: State<'_, SupportTunnel>) -> Option<SupportSession> {
+  tunnel.0.lock().ok()?.as_ref().map(|s| s.info.clone())
+}
+
+/// End any open session; called when the installer quits.
+pub fn shutdown(app: &AppHandle) {
+  if let Some(session) = app.state::<SupportTunnel>().take() {
+    end(app, session, "the installer quit");
+  }
+}
@@ -0,0 +1,324 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Ordering and running the nodes of a dependency graph.
+//!
+//! Nothing here knows what a task does: [`Graph::new`] checks the ids and
+//! edges and rejects cycles, and [`execute`] runs each node through a
+//! callback once all of its dependencies have succeeded, up to `parallel`
+//! at a time. When a node fails, everything that depends on it, directly
+//! or not, is blocked, while branches that don't keep going. A run can
+//! start from the statuses a previous one ended with, so only what's left
+//! runs again.
+
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Condvar, Mutex};
+use std::time::Instant;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+  Pending,
+  Running,
+  Succeeded,
+  Failed,
+  /// A dependency failed or was cancelled
+  Blocked,
+  /// Passed over on purpose; counts as done for what depends on it
+  Skipped,
+  Cancelled,
+}
+
+impl Status {
+  /// Nodes that depend on this one may run.
+  pub fn satisfies(self) -> bool {
+    matches!(self, Status::Succeeded | Status::Skipped)
+  }
+}
+
+/// A node's outcome, as passed to the `on_status` callback.
+#[derive(Debug, Clone)]
+pub struct Update {
+  pub index: usize,
+  pub status: Status,
+  pub message: Option<String>,
+  pub duration_ms: u64,
+}
+
+pub struct Graph {
+  /// Indices of the nodes each node depends on
+  deps: Vec<Vec<usize>>,
+  /// Indices of the nodes that depend on each node
+  dependents: Vec<Vec<usize>>,
+}
+
+impl Graph {
+  /// `nodes` are `(id, ids it depends on)`; ids must be unique.
+  pub fn new(nodes: &[(&str, &[String])]) -> Result<Self, String> {
+    let mut index = HashMap::new();
+    for (i, (id, _)) in nodes.iter().enumerate() {
+      if index.insert(*id, i).is_some() {
+        return Err(format!("Task {} is defined twice", id));
+      }
+    }
+    let mut deps = vec![Vec::new(); nodes.len()];
+    let mut dependents = vec![Vec::new(); nodes.len()];
+    for (i, (id, depends_on)) in nodes.iter().enumerate() {
+      for dep in depends_on.iter() {
+        let &j = index
+          .get(dep.as_str())
+          .ok_or_else(|| format!("Task {} depends on unknown task {}", id, dep))?;
+        if j == i {
+          return Err(format!("Task {} depends on itself", id));
+        }
+        if !deps[i].contains(&j) {
+          deps[i].push(j);
+          dependents[j].push(i);
+        }
+      }
+    }
+    let graph = Graph { deps, dependents };
+
+    // Kahn's algorithm; whatever never becomes ready is on or behind a cycle
+    let mut waiting: Vec<usize> = graph.deps.iter().map(Vec::len).collect();
+    let mut ready: VecDeque<usize> = (0..nodes.len()).filter(|&i| waiting[i] == 0).collect();
+    let mut ordered = 0;
+    while let Some(i) = ready.pop_front() {
+      ordered += 1;
+      for &d in &graph.dependents[i] {
+        waiting[d] -= 1;
+        if waiting[d] == 0 {
+          ready.push_back(d);
+        }
+      }
+    }
+    if ordered < nodes.len() {
+      let stuck: Vec<&str> = (0..nodes.len())
+        .filter(|&i| waiting[i] > 0)
+        .map(|i| nodes[i].0)
+        .collect();
+      return Err(format!(
+        "The task dependencies form a cycle through {}",
+        stuck.join(", ")
+      ));
+    }
+    Ok(graph)
+  }
+
+  fn len(&self) -> usize {
+    self.deps.len()
+  }
+
+  /// Everything that depends on `index`, directly or not.
+  pub fn downstream(&self, index: usize) -> Vec<usize> {
+    let mut seen = vec![false; self.len()];
+    let mut stack = vec![index];
+    while let Some(i) = stack.pop() {
+      for &d in &self.dependents[i] {
+        if !seen[d] {
+          seen[d] = true;
+          stack.push(d);
+        }
+      }
+    }
+    (0..self.len()).filter(|&i| seen[i]).collect()
+  }
+
+  /// `nodes` and everything downstream of any of them.
+  pub fn affected(&self, nodes: &[usize]) -> Vec<bool> {
+    let mut affected = vec![false; self.len()];
+    for &i in nodes {
+      affected[i] = true;
+      for d in self.downstream(i) {
+        affected[d] = true;
+      }
+    }
+    affected
+  }
+}
+
+struct Progress {
+  status: Vec<Status>,
+  /// Dependencies of each node that aren't done yet
+  waiting: Vec<usize>,
+  ready: VecDeque<usize>,
+  running: usize,
+}
+
+impl Progress {
+  fn finished(&self) -> bool {
+    self.ready.is_empty() && self.running == 0
+  }
+}
+
+/// Run the `Pending` nodes of `graph` with `run`, which returns a message
+/// on success and an error on failure, reporting each change of status
+/// through `on_status`. `initial` is every node's status going in (all
+/// `Pending` for a fresh run); nodes in any other state aren't run again.
+/// Once `cancel` is set nothing new starts, and a node that fails after
+/// that counts as cancelled; the callback is expected to notice the flag
+/// too. The statuses are returned in node order.
+pub fn execute<R, S>(
+  graph: &Graph,
+  initial: Vec<Status>,
+  parallel: usize,
+  cancel: &AtomicBool,
+  run: R,
+  on_status: S,
+) -> Vec<Status>
+where
+  R: Fn(usize) -> Result<Option<String>, String> + Sync,
+  S: Fn(Update) + Sync,
+{
+  let waiting: Vec<usize> = graph
+    .deps
+    .iter()
+    .map(|deps| deps.iter().filter(|&&d| !initial[d].satisfies()).count())
+    .collect();
+  let ready = (0..graph.len())
+    .filter(|&i| initial[i] == Status::Pending && waiting[i] == 0)
+    .collect();
+  let state = Mutex::new(Progress {
+    status: initial,
+    waiting,
+    ready,
+    running: 0,
+  });
+  let wake = Condvar::new();
+
+  // Block everything downstream of a node that didn't succeed
+  let block_dependents = |progress: &mut Progress, from: usize| {
+    let mut stack = vec![from];
+    while let Some(i) = stack.pop() {
+      for &d in &graph.dependents[i] {
+        if progress.status[d] == Status::Pending {
+          progress.status[d] = Status::Blocked;
+          on_status(Update {
+            index: d,
+            status: Status::Blocked,
+            message: None,
+            duration_ms: 0,
+          });
+          stack.push(d);
+        }
+      }
+    }
+  };
+
+  // Nodes left waiting on one that failed last time can't run either
+  if let Ok(mut progress) = state.lock() {
+    for i in 0..graph.len() {
+      if !matches!(progress.status[i], Status::Pending | Status::Running) && !progress.status[i].satisfies() {
+        block_dependents(&mut progress, i);
+      }
+    }
+  }
+
+  let worker = || loop {
+    let index = {
+      let Ok(mut progress) = state.lock() else {
+        return;
+      };
+      loop {
+        if progress.finished() {
+          wake.notify_all();
+          return;
+        }
+        if let Some(i) = progress.ready.pop_front() {
+          if cancel.load(Ordering::SeqCst) {
+            progress.status[i] = Status::Cancelled;
+            on_status(Update {
+              index: i,
+              status: Status::Cancelled,
+              message: None,
+              duration_ms: 0,
+            });
+            block_dependents(&mut progress, i);
+            continue;
+          }
+          progress.status[i] = Status::Running;
+          progress.running += 1;
+          break i;
+        }
+        progress = match wake.wait(progress) {
+          Ok(progress) => progress,
+          Err(_) => return,
+        };
+      }
+    };
+
+    on_status(Update {
+      index,
+      status: Status::Running,
+      message: None,
+      duration_ms: 0,
+    });
+    let started = Instant::now();
+    let result = run(index);
+    let duration_ms = started.elapsed().as_millis() as u64;
+
+    let Ok(mut progress) = state.lock() else {
+      return;
+    };
+    progress.running -= 1;
+    let (status, message) = match result {
+      Ok(message) => (Status::Succeeded, message),
+      // Most likely killed by the cancellation rather than failing on its own
+      Err(e) if cancel.load(Ordering::SeqCst) => (Status::Cancelled, Some(e)),
+      Err(e) => (Status::Failed, Some(e)),
+    };
+    progress.status[index] = status;
+    on_status(Update {
+      index,
+      status,
+      message,
+      duration_ms,
+    });
+    if status.satisfies() {
+      for &d in &graph.dependents[index] {
+        progress.waiting[d] -= 1;
+        if progress.waiting[d] == 0 && progress.status[d] == Status::Pending {
+          progress.ready.push_back(d);
+        }
+      }
+    } else {
+      block_dependents(&mut progress, index);
+    }
+    wake.notify_all();
+  };
+
+  std::thread::scope(|scope| {
+    for _ in 0..parallel.clamp(1, graph.len().max(1)) {
+      scope.spawn(worker);
+    }
+  });
+
+  state.into_inner().map(|progress| progress.status).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::Graph;
+
+  fn graph(nodes: &[(&str, &[&str])]) -> Graph {
+    let owned: Vec<(&str, Vec<String>)> = nodes
+      .iter()
+      .map(|(id, deps)| (*id, deps.iter().map(|d| d.to_string()).collect()))
+      .collect();
+    let edges: Vec<(&str, &[String])> = owned.iter().map(|(id, deps)| (*id, deps.as_slice())).collect();
+    Graph::new(&edges).unwrap()
+  }
+
+  #[test]
+  fn affected_follows_every_path_down() {
+    // a -> b -> d, a -> c, e on its own
+    let g = graph(&[("a", &[]), ("b", &["a"]), ("c", &["a"]), ("d", &["b"]), ("e", &[])]);
+    assert_eq!(g.affected(&[1]), [false, true, false, true, false]);
+    assert_eq!(g.affected(&[0]), [true, true, true, true, false]);
+    assert_eq!(g.affected(&[]), [false; 5]);
+  }
+}
@@ -0,0 +1,113 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Remembering which steps already succeeded, and with what inputs.
+//!
+//! When a command task succeeds, a marker with its id and a hash of its
+//! inputs (the command, the node, sudo and whatever the wizard passes as
+//! `inputs`) goes into `completed.json` in the profile's data dir, next to
+//! the journal. A later run skips a task whose marker still matches, so
+//! re-running an install after a fix picks up where it went wrong instead
+//! of starting over. Changing anything that goes into the hash runs it
+//! again, as does `force`, and so does everything downstream of a task
+//! that runs again, whatever its own marker says. A task that fails loses
+//! its marker.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::AppHandle;
+use tracing::warn;
+
+use super::Task;
+use crate::migrations::Versioned;
+use crate::profiles;
+use crate::workspace::write_private_file;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Marker {
+  pub id: String,
+  /// SHA-256 of the task's action and inputs
+  pub input_hash: String,
+  pub run_id: String,
+  pub completed_at: u64,
+}
+
+pub struct Completed {
+  path: PathBuf,
+  markers: Mutex<HashMap<String, Marker>>,
+}
+
+/// What a task's outcome depends on, hashed.
+pub fn input_hash(task: &Task) -> String {
+  let inputs = serde_json::to_vec(&(&task.action, &task.inputs)).unwrap_or_default();
+  let digest = ring::digest::digest(&ring::digest::SHA256, &inputs);
+  digest.as_ref().iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+impl Completed {
+  /// Load the markers left by earlier runs, if any.
+  pub fn load(app: &AppHandle) -> Result<Self, String> {
+    let path = profiles::data_dir(app)?.join("completed.json");
+
+    let markers: Vec<Marker> = match std::fs::read_to_string(&path) {
+      Ok(contents) => serde_json::from_str::<Versioned<_>>(&contents)
+        .map(|v| v.items)
+        .unwrap_or_else(|e| {
+          warn!("Ignoring invalid {}: {}", path.display(), e);
+          Vec::new()
+        }),
+      Err(_) => Vec::new(),
+    };
+
+    Ok(Self {
+      path,
+      markers: Mutex::new(markers.into_iter().map(|m| (m.id.clone(), m)).collect()),
+    })
+  }
+
+  /// The marker of `task`, if it succeeded before with the same inputs.
+  pub fn matching(&self, task: &Task) -> Option<Marker> {
+    let markers = self.markers.lock().ok()?;
+    let marker = markers.get(&task.id)?;
+    (marker.input_hash == input_hash(task)).then(|| marker.clone())
+  }
+
+  pub fn record(&self, run_id: &str, task: &Task) -> Result<(), String> {
+    let mut markers = self.markers.lock().map_err(|e| e.to_string())?;
+    markers.insert(
+      task.id.clone(),
+      Marker {
+        id: task.id.clone(),
+        input_hash: input_hash(task),
+        run_id: run_id.to_string(),
+        completed_at: SystemTime::now()
+          .duration_since(UNIX_EPOCH)
+          .map(|d| d.as_secs())
+          .unwrap_or_default(),
+      },
+    );
+    self.save(&markers)
+  }
+
+  pub fn remove(&self, id: &str) -> Result<(), String> {
+    let mut markers = self.markers.lock().map_err(|e| e.to_string())?;
+    if markers.remove(id).is_none() {
+      return Ok(());
+    }
+    self.save(&markers)
+  }
+
+  fn save(&self, markers: &HashMap<String, Marker>) -> Result<(), String> {
+    let mut items: Vec<&Marker> = markers.values().collect();
+    items.sort_by(|a, b| a.id.cmp(&b.id));
+    let json = serde_json::to_string_pretty(&Versioned::current(items)).map_err(|e| e.to_string())?;
+    let tmp = self.path.with_extension("json.tmp");
+    write_private_file(&tmp, json.as_bytes())?;
+    std::fs::rename(&tmp, &self.path).map_err(|e| format!("Failed to write {}: {}", self.path.display(), e))
+  }
+}
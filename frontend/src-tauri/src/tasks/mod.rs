@@ -0,0 +1,574 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Running install steps in dependency order.
+//!
+//! `run_task_graph` takes install steps as tasks that name the tasks they
+//! depend on (the bridge before the VMs, the VMs before Kubernetes) and
+//! runs them with [`dag::execute`]: a task starts as soon as everything it
+//! depends on has succeeded, independent branches run in parallel up to
+//! `max_parallel`, and a failure blocks what depends on it without stopping
+//! the other branches. A task is either a command on a node, run over the
+//! pooled SSH connection with its output streamed as `remote-output` under
+//! `<run id>/<task id>`, or a milestone that only groups others. Every
+//! status change is emitted as a `task-graph` event for the progress graph.
+//! `cancel_task_graph` kills the running commands and starts nothing new.
+//!
+//! So a stuck step can't hold up the install forever, each command has a
+//! timeout and can have a stall limit, set per task or for the whole graph:
+//! past either it's killed and the task fails with the last lines it
+//! printed in its message.
+//!
+//! The last graph to finish is kept, so a failed step doesn't mean
+//! starting over: `retry_step` runs it again and `skip_step` passes over
+//! it, recording the skip and its reason in the transcript, and either way
+//! the steps it blocked run while everything that already succeeded is
+//! left alone. Across runs, [`markers`] lets a command that already
+//! succeeded with the same inputs be skipped.
+//!
+//! In a dry run no command is run: each is reported as skipped with what
+//! it would have run, and no marker is recorded.
+
+mod dag;
+pub mod markers;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tracing::{info, warn};
+
+use self::markers::Completed;
+use crate::cancel;
+use crate::dry_run;
+use crate::remote::ssh::{self, RunOptions, SshPool, SshTarget};
+use crate::report::{StepRecord, Transcript};
+use crate::telemetry::Outcome;
+
+pub use dag::Status;
+
+pub const EVENT: &str = "task-graph";
+
+const DEFAULT_PARALLEL: usize = 4;
+const MAX_PARALLEL: usize = 32;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Action {
+  Remote {
+    host: SshTarget,
+    command: String,
+    #[serde(default)]
+    sudo: bool,
+    /// Defaults to the graph's `timeout_secs`
+    timeout_secs: Option<u64>,
+    /// Kill the command after this long without output; defaults to the
+    /// graph's `stall_secs`
+    stall_secs: Option<u64>,
+  },
+  /// Does nothing; lets other tasks depend on a group at once
+  Milestone,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Task {
+  pub id: String,
+  pub title: Option<String>,
+  #[serde(default)]
+  pub depends_on: Vec<String>,
+  pub action: Action,
+  /// Anything else the step depends on, e.g. the config it applies; a
+  /// change runs it again even after it succeeded
+  #[serde(default)]
+  pub inputs: Value,
+  /// Run it even if it already succeeded with the same inputs
+  #[serde(default)]
+  pub force: bool,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct GraphOptions {
+  /// Event tag; generated when omitted
+  pub run_id: Option<String>,
+  pub max_parallel: Option<usize>,
+  /// For tasks with `sudo`; without it sudo must be passwordless
+  pub sudo_password: Option<String>,
+  /// For tasks that don't set their own
+  pub timeout_secs: Option<u64>,
+  pub stall_secs: Option<u64>,
+}
+
+/// A task as the progress graph draws it.
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskNode {
+  pub id: String,
+  pub title: String,
+  pub depends_on: Vec<String>,
+  pub host: Option<String>,
+  /// `pending` unless a retry starts from an earlier run
+  pub status: Status,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskResult {
+  pub id: String,
+  pub status: Status,
+  pub message: Option<String>,
+  pub duration_ms: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Summary {
+  pub succeeded: usize,
+  pub failed: usize,
+  pub blocked: usize,
+  pub skipped: usize,
+  pub cancelled: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GraphReport {
+  pub run_id: String,
+  /// In the order the tasks were given
+  pub tasks: Vec<TaskResult>,
+  pub summary: Summary,
+  /// Every task succeeded or was skipped
+  pub ok: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "phase", rename_all = "snake_case")]
+pub enum TaskGraphEvent {
+  Started {
+    run_id: String,
+    tasks: Vec<TaskNode>,
+  },
+  Task {
+    run_id: String,
+    #[serde(flatten)]
+    result: TaskResult,
+  },
+  Finished {
+    run_id: String,
+    summary: Summary,
+  },
+}
+
+struct Running {
+  cancel: Arc<AtomicBool>,
+  task_ids: Vec<String>,
+}
+
+/// What's kept of a finished graph, so its steps can be retried or skipped.
+#[derive(Clone)]
+struct GraphRun {
+  run_id: String,
+  tasks: Vec<Task>,
+  options: GraphOptions,
+  results: Vec<TaskResult>,
+}
+
+#[derive(Default)]
+pub struct TaskGraphs {
+  /// Graphs being run, by run id
+  running: Mutex<HashMap<String, Running>>,
+  /// The most recent graph to finish
+  last: Mutex<Option<GraphRun>>,
+}
+
+fn remote_run_id(run_id: &str, task: &str) -> String {
+  format!("{}/{}", run_id, task)
+}
+
+fn validate(tasks: &[Task]) -> Result<(), String> {
+  if tasks.is_empty() {
+    return Err("No tasks to run".to_string());
+  }
+  for task in tasks {
+    if task.id.is_empty()
+      || !task
+        .id
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+    {
+      return Err(format!("Invalid task id: {}", task.id));
+    }
+    if let Action::Remote { host, command, .. } = &task.action {
+      host.validate()?;
+      if command.trim().is_empty() {
+        return Err(format!("Task {} has no command", task.id));
+      }
+    }
+  }
+  Ok(())
+}
+
+fn run_task(app: &AppHandle, run_id: &str, task: &Task, graph: &GraphOptions) -> Result<Option<String>, String> {
+  let Action::Remote {
+    host,
+    command,
+    sudo,
+    timeout_secs,
+    stall_secs,
+  } = &task.action
+  else {
+    return Ok(None);
+  };
+  let options = RunOptions {
+    run_id: Some(remote_run_id(run_id, &task.id)),
+    sudo: *sudo,
+    sudo_password: graph.sudo_password.clone().filter(|_| *sudo),
+    timeout_secs: timeout_secs.or(graph.timeout_secs),
+    stall_secs: stall_secs.or(graph.stall_secs),
+  };
+  let result = ssh::run(app, host, command, options)?;
+  let error = match result.exit_code {
+    Some(0) => return Ok(None),
+    Some(code) => return Err(format!("Exited with status {} on {}", code, host.host)),
+    None if result.cancelled => return Err("Cancelled".to_string()),
+    None if result.stalled => format!(
+      "No output for {}s on {}, so it was stopped as stuck",
+      stall_secs.or(graph.stall_secs).unwrap_or_default(),
+      host.host
+    ),
+    None => format!("Timed out after {}s on {}", result.duration_ms / 1000, host.host),
+  };
+  if result.tail.is_empty() {
+    return Err(error);
+  }
+  Err(format!("{}. Last output:\n{}", error, result.tail.join("\n")))
+}
+
+/// What a dry run reports for a command task instead of running it.
+fn dry_run_result(task: &Task) -> Option<TaskResult> {
+  let Action::Remote { host, command, sudo, .. } = &task.action else {
+    return None;
+  };
+  info!("Dry run: not running task {} on {}", task.id, host.host);
+  Some(TaskResult {
+    id: task.id.clone(),
+    status: Status::Skipped,
+    message: Some(format!(
+      "Dry run: would run {}`{}` on {}",
+      if *sudo { "with sudo " } else { "" },
+      command.trim(),
+      host.host
+    )),
+    duration_ms: 0,
+  })
+}
+
+fn graph_of(tasks: &[Task]) -> Result<dag::Graph, String> {
+  let edges: Vec<(&str, &[String])> = tasks.iter().map(|t| (t.id.as_str(), t.depends_on.as_slice())).collect();
+  dag::Graph::new(&edges)
+}
+
+/// Run whatever is `Pending` in `results` and keep the outcome as the last
+/// graph.
+fn execute(
+  app: &AppHandle,
+  run_id: String,
+  tasks: Vec<Task>,
+  options: GraphOptions,
+  mut results: Vec<TaskResult>,
+) -> Result<GraphReport, String> {
+  let graph = graph_of(&tasks)?;
+  let dry_run = dry_run::is_enabled(app);
+  if dry_run {
+    for (task, result) in tasks.iter().zip(results.iter_mut()) {
+      if result.status == Status::Pending {
+        if let Some(skipped) = dry_run_result(task) {
+          *result = skipped;
+        }
+      }
+    }
+  }
+  let parallel = options.max_parallel.unwrap_or(DEFAULT_PARALLEL).clamp(1, MAX_PARALLEL);
+  let graphs = app.state::<TaskGraphs>();
+
+  let cancel = Arc::new(AtomicBool::new(false));
+  {
+    let mut running = graphs.running.lock().map_err(|e| e.to_string())?;
+    if running.contains_key(&run_id) {
+      return Err(format!("A task graph with id {} is already running", run_id));
+    }
+    running.insert(
+      run_id.clone(),
+      Running {
+        cancel: cancel.clone(),
+        task_ids: tasks.iter().map(|t| t.id.clone()).collect(),
+      },
+    );
+  }
+  let _operation = cancel::watch(app, &format!("task graph {}", run_id), cancel.clone());
+
+  let _ = app.emit(
+    EVENT,
+    TaskGraphEvent::Started {
+      run_id: run_id.clone(),
+      tasks: tasks
+        .iter()
+        .zip(&results)
+        .map(|(t, result)| TaskNode {
+          id: t.id.clone(),
+          title: t.title.clone().unwrap_or_else(|| t.id.clone()),
+          depends_on: t.depends_on.clone(),
+          host: match &t.action {
+            Action::Remote { host, .. } => Some(host.host.clone()),
+            Action::Milestone => None,
+          },
+          status: result.status,
+        })
+        .collect(),
+    },
+  );
+  let pending = results.iter().filter(|r| r.status == Status::Pending).count();
+  info!("Running {} tasks as {}, {} at a time", pending, run_id, parallel);
+
+  let initial = results.iter().map(|r| r.status).collect();
+  let results = Mutex::new(results);
+  dag::execute(
+    &graph,
+    initial,
+    parallel,
+    &cancel,
+    |index| run_task(app, &run_id, &tasks[index], &options),
+    |update| {
+      let result = TaskResult {
+        id: tasks[update.index].id.clone(),
+        status: update.status,
+        message: update.message,
+        duration_ms: update.duration_ms,
+      };
+      if result.status == Status::Failed {
+        warn!(
+          "Task {} failed: {}",
+          result.id,
+          result.message.as_deref().unwrap_or_default()
+        );
+      }
+      let task = &tasks[update.index];
+      let completed = app.try_state::<Completed>().filter(|_| !dry_run);
+      if let (Action::Remote { .. }, Some(completed)) = (&task.action, completed) {
+        let saved = match result.status {
+          Status::Succeeded => completed.record(&run_id, task),
+          Status::Failed => completed.remove(&task.id),
+          _ => Ok(()),
+        };
+        if let Err(e) = saved {
+          warn!("Failed to update the completed steps: {}", e);
+        }
+      }
+      if let Ok(mut results) = results.lock() {
+        results[update.index] = result.clone();
+      }
+      let _ = app.emit(
+        EVENT,
+        TaskGraphEvent::Task {
+          run_id: run_id.clone(),
+          result,
+        },
+      );
+    },
+  );
+
+  if let Ok(mut running) = graphs.running.lock() {
+    running.remove(&run_id);
+  }
+  let results = results.into_inner().map_err(|e| e.to_string())?;
+  let mut summary = Summary::default();
+  for result in &results {
+    match result.status {
+      Status::Succeeded => summary.succeeded += 1,
+      Status::Failed => summary.failed += 1,
+      Status::Blocked => summary.blocked += 1,
+      Status::Skipped => summary.skipped += 1,
+      Status::Cancelled => summary.cancelled += 1,
+      Status::Pending | Status::Running => {}
+    }
+  }
+  let _ = app.emit(
+    EVENT,
+    TaskGraphEvent::Finished {
+      run_id: run_id.clone(),
+      summary: summary.clone(),
+    },
+  );
+  let report = GraphReport {
+    run_id: run_id.clone(),
+    ok: results.iter().all(|r| r.status.satisfies()),
+    tasks: results.clone(),
+    summary,
+  };
+  if let Ok(mut last) = graphs.last.lock() {
+    *last = Some(GraphRun {
+      run_id,
+      tasks,
+      options,
+      results,
+    });
+  }
+  Ok(report)
+}
+
+fn run(app: &AppHandle, tasks: Vec<Task>, options: GraphOptions) -> Result<GraphReport, String> {
+  validate(&tasks)?;
+  let run_id = options.run_id.clone().unwrap_or_else(|| {
+    let now = std::time::SystemTime::now()
+      .duration_since(std::time::UNIX_EPOCH)
+      .unwrap_or_default();
+    format!("tasks-{}", now.as_millis())
+  });
+  let completed = app.state::<Completed>();
+  let mut markers: Vec<_> = tasks
+    .iter()
+    .map(|t| completed.matching(t).filter(|_| !t.force))
+    .collect();
+  // A task that runs again may change what the ones after it depend on,
+  // so their markers no longer say they're done
+  let rerun: Vec<usize> = (0..tasks.len()).filter(|&i| markers[i].is_none()).collect();
+  for (marker, affected) in markers.iter_mut().zip(graph_of(&tasks)?.affected(&rerun)) {
+    if affected {
+      *marker = None;
+    }
+  }
+  let results: Vec<TaskResult> = tasks
+    .iter()
+    .zip(markers)
+    .map(|(t, marker)| match marker {
+      Some(marker) => TaskResult {
+        id: t.id.clone(),
+        status: Status::Skipped,
+        message: Some(format!("Already done in {} with the same inputs", marker.run_id)),
+        duration_ms: 0,
+      },
+      None => TaskResult {
+        id: t.id.clone(),
+        status: Status::Pending,
+        message: None,
+        duration_ms: 0,
+      },
+    })
+    .collect();
+  let done = results.iter().filter(|r| r.status == Status::Skipped).count();
+  if done > 0 {
+    info!("Skipping {} tasks already done with the same inputs", done);
+  }
+  execute(app, run_id, tasks, options, results)
+}
+
+/// Run the last graph again from its failed step `id`, either retrying it
+/// or, with a `skip` reason, passing over it. Either way the steps it
+/// blocked run too; nothing else that already finished does.
+fn resume(app: &AppHandle, id: &str, skip: Option<String>) -> Result<GraphReport, String> {
+  let mut run = app
+    .state::<TaskGraphs>()
+    .last
+    .lock()
+    .map_err(|e| e.to_string())?
+    .clone()
+    .ok_or("No task graph has run yet")?;
+  let index = run
+    .tasks
+    .iter()
+    .position(|t| t.id == id)
+    .ok_or_else(|| format!("The last task graph has no step {}", id))?;
+  if !matches!(run.results[index].status, Status::Failed | Status::Cancelled) {
+    return Err(format!("Step {} didn't fail, so there's nothing to retry or skip", id));
+  }
+  let graph = graph_of(&run.tasks)?;
+
+  let result = &mut run.results[index];
+  match skip {
+    Some(reason) => {
+      info!("Skipping step {}: {}", id, reason);
+      let transcript = app.state::<Transcript>();
+      transcript.record(StepRecord {
+        step: id.to_string(),
+        title: run.tasks[index].title.clone().unwrap_or_else(|| id.to_string()),
+        started_at: std::time::SystemTime::now()
+          .duration_since(std::time::UNIX_EPOCH)
+          .map(|d| d.as_millis() as u64)
+          .unwrap_or_default(),
+        duration_ms: 0,
+        outcome: Outcome::Skipped,
+        message: Some(reason.clone()),
+        outputs: result
+          .message
+          .iter()
+          .map(|error| ("error".to_string(), error.clone()))
+          .collect(),
+      })?;
+      result.status = Status::Skipped;
+      result.message = Some(format!("Skipped: {}", reason));
+    }
+    None => {
+      info!("Retrying step {}", id);
+      result.status = Status::Pending;
+      result.message = None;
+    }
+  }
+  for d in graph.downstream(index) {
+    if matches!(run.results[d].status, Status::Blocked | Status::Cancelled) {
+      run.results[d].status = Status::Pending;
+      run.results[d].message = None;
+    }
+  }
+  execute(app, run.run_id, run.tasks, run.options, run.results)
+}
+
+/// Run `tasks` in dependency order and return every task's outcome.
+#[tauri::command]
+pub async fn run_task_graph(
+  app: AppHandle,
+  tasks: Vec<Task>,
+  options: Option<GraphOptions>,
+) -> Result<GraphReport, String> {
+  tauri::async_runtime::spawn_blocking(move || run(&app, tasks, options.unwrap_or_default()))
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub fn cancel_task_graph(
+  graphs: State<'_, TaskGraphs>,
+  pool: State<'_, SshPool>,
+  run_id: String,
+) -> Result<(), String> {
+  let running = graphs.running.lock().map_err(|e| e.to_string())?;
+  let graph = running
+    .get(&run_id)
+    .ok_or_else(|| format!("No task graph with id {} is running", run_id))?;
+  info!("Cancelling task graph {}", run_id);
+  graph.cancel.store(true, Ordering::SeqCst);
+  for task in &graph.task_ids {
+    pool.cancel(&remote_run_id(&run_id, task));
+  }
+  Ok(())
+}
+
+/// Run a failed step of the last task graph again, along with the steps it
+/// blocked.
+#[tauri::command]
+pub async fn retry_step(app: AppHandle, id: String) -> Result<GraphReport, String> {
+  tauri::async_runtime::spawn_blocking(move || resume(&app, &id, None))
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Pass over a failed step of the last task graph and run the steps that
+/// were waiting on it. The skip and its reason go into the report.
+#[tauri::command]
+pub async fn skip_step(app: AppHandle, id: String, reason: String) -> Result<GraphReport, String> {
+  let reason = reason.trim().to_string();
+  if reason.is_empty() {
+    return Err("A reason for skipping the step is required".to_string());
+  }
+  tauri::async_runtime::spawn_blocking(move || resume(&app, &id, Some(reason)))
+    .await
+    .map_err(|e| e.to_string())?
+}
@@ -0,0 +1,209 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Opt-in anonymous telemetry for install outcomes.
+//!
+//! Only step identifiers, durations, outcomes and a coarse failure category
+//! are recorded — never hostnames, addresses, usernames or command output.
+//! Nothing is sent unless the user explicitly consented, and
+//! `preview_telemetry` returns the exact payload `submit_telemetry` would
+//! post so the user can inspect it first.
+//!
+//! The collection endpoint is baked in at build time through
+//! `THINKUBE_BUILD_TELEMETRY_URL`; builds without it never send anything.
+
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+
+use crate::settings::SettingsStore;
+
+/// Upper bound on buffered events so a runaway caller can't grow memory.
+const MAX_EVENTS: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+  Success,
+  Failure,
+  Skipped,
+  Cancelled,
+}
+
+/// Coarse failure buckets; free-form error text is deliberately not accepted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureCategory {
+  Network,
+  Ssh,
+  Permission,
+  Timeout,
+  Dependency,
+  Playbook,
+  Disk,
+  Unknown,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StepEvent {
+  pub step: String,
+  pub duration_ms: u64,
+  pub outcome: Outcome,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub failure_category: Option<FailureCategory>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Payload {
+  pub schema: u32,
+  pub installer_version: String,
+  pub os: String,
+  pub os_version: String,
+  pub arch: String,
+  pub events: Vec<StepEvent>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TelemetryStatus {
+  /// `None` until the user has answered the consent prompt
+  pub consent: Option<bool>,
+  /// Whether this build has a collection endpoint at all
+  pub endpoint_configured: bool,
+  pub pending_events: usize,
+}
+
+#[derive(Default)]
+pub struct Telemetry {
+  events: Mutex<Vec<StepEvent>>,
+}
+
+fn endpoint() -> Option<&'static str> {
+  option_env!("THINKUBE_BUILD_TELEMETRY_URL").filter(|url| !url.is_empty())
+}
+
+/// Step ids are internal identifiers like `k8s.install-cni`; reject anything
+/// that could smuggle user data (spaces, slashes, overly long strings).
+fn valid_step_id(step: &str) -> bool {
+  !step.is_empty()
+    && step.len() <= 64
+    && step
+      .chars()
+      .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '-' | '_' | '.'))
+}
+
+impl Telemetry {
+  fn payload(&self, app: &AppHandle) -> Payload {
+    let os = crate::platform::os_release::compatibility().os;
+    Payload {
+      schema: 1,
+      installer_version: app.package_info().version.to_string(),
+      os: os.id,
+      os_version: os.version_id,
+      arch: std::env::consts::ARCH.to_string(),
+      events: self.events.lock().map(|e| e.clone()).unwrap_or_default(),
+    }
+  }
+}
+
+fn consented(settings: &SettingsStore) -> bool {
+  settings.get().telemetry_consent == Some(true)
+}
+
+#[tauri::command]
+pub fn get_telemetry_status(telemetry: State<'_, Telemetry>, settings: State<'_, SettingsStore>) -> TelemetryStatus {
+  TelemetryStatus {
+    consent: settings.get().telemetry_consent,
+    endpoint_configured: endpoint().is_some(),
+    pending_events: telemetry.events.lock().map(|e| e.len()).unwrap_or(0),
+  }
+}
+
+#[tauri::command]
+pub fn set_telemetry_consent(
+  telemetry: State<'_, Telemetry>,
+  settings: State<'_, SettingsStore>,
+  enabled: bool,
+) -> Result<(), String> {
+  settings.update(|s| s.telemetry_consent = Some(enabled))?;
+  if !enabled {
+    // Withdrawing consent also drops anything collected so far
+    if let Ok(mut events) = telemetry.events.lock() {
+      events.clear();
+    }
+  }
+  Ok(())
+}
+
+/// Record the outcome of an install step. A no-op without consent.
+#[tauri::command]
+pub fn record_step_outcome(
+  telemetry: State<'_, Telemetry>,
+  settings: State<'_, SettingsStore>,
+  step: String,
+  duration_ms: u64,
+  outcome: Outcome,
+  failure_category: Option<FailureCategory>,
+) -> Result<(), String> {
+  if !consented(&settings) {
+    return Ok(());
+  }
+  if !valid_step_id(&step) {
+    return Err(format!("Invalid telemetry step id: {:?}", step));
+  }
+
+  let mut events = telemetry.events.lock().map_err(|e| e.to_string())?;
+  if events.len() < MAX_EVENTS {
+    events.push(StepEvent {
+      step,
+      duration_ms,
+      outcome,
+      failure_category: if outcome == Outcome::Failure {
+        Some(failure_category.unwrap_or(FailureCategory::Unknown))
+      } else {
+        None
+      },
+    });
+  }
+  Ok(())
+}
+
+/// Exactly what `submit_telemetry` would send right now.
+#[tauri::command]
+pub fn preview_telemetry(app: AppHandle, telemetry: State<'_, Telemetry>) -> Payload {
+  telemetry.payload(&app)
+}
+
+/// Send buffered events and clear them. Returns the number of events sent.
+#[tauri::command]
+pub async fn submit_telemetry(app: AppHandle) -> Result<usize, String> {
+  if !consented(&app.state::<SettingsStore>()) {
+    return Ok(0);
+  }
+  let Some(url) = endpoint() else { return Ok(0) };
+
+  let telemetry = app.state::<Telemetry>();
+  let payload = telemetry.payload(&app);
+  let sent = payload.events.len();
+  if sent == 0 {
+    return Ok(0);
+  }
+
+  tauri::async_runtime::spawn_blocking(move || {
+    ureq::post(url)
+      .timeout(Duration::from_secs(10))
+      .send_json(&payload)
+      .map(|_| ())
+      .map_err(|e| format!("Failed to submit telemetry: {}", e))
+  })
+  .await
+  .map_err(|e| e.to_string())??;
+
+  if let Ok(mut events) = telemetry.events.lock() {
+    let sent_now = sent.min(events.len());
+    events.drain(..sent_now);
+  }
+  Ok(sent)
+}
@@ -0,0 +1,256 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Cloudflare API token check.
+//!
+//! cert-manager and external-dns need to read the cluster's zone and edit
+//! its DNS records. A token that only has account-level scopes, or DNS:Read,
+//! passes a naive "can I list zones" check and then fails the first ACME
+//! DNS-01 challenge. We verify the token itself, find the zone for the
+//! domain (walking up to the registered domain for subdomains), and compare
+//! the permissions Cloudflare reports for the token on that zone against
+//! what the deployment needs. The same zone lookup backs the TXT records
+//! created for domain verification.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::time::Duration;
+use tracing::info;
+
+use crate::validation;
+
+const API: &str = "https://api.cloudflare.com/client/v4";
+
+/// Zone permissions the deployment relies on, with the names the Cloudflare
+/// dashboard uses for them when creating a token.
+const REQUIRED: &[(&str, &str)] = &[
+  ("#zone:read", "Zone → Zone → Read"),
+  ("#dns_records:read", "Zone → DNS → Read"),
+  ("#dns_records:edit", "Zone → DNS → Edit"),
+];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CloudflareTokenReport {
+  /// Active and holds every required permission on the zone
+  pub valid: bool,
+  /// `active`, `disabled` or `expired`; `None` if Cloudflare rejected the token
+  pub token_status: Option<String>,
+  pub expires_on: Option<String>,
+  pub zone_id: Option<String>,
+  pub zone_name: Option<String>,
+  /// Required permissions the token holds on the zone
+  pub granted: Vec<String>,
+  /// Required permissions the token lacks, in dashboard terms
+  pub missing: Vec<String>,
+  pub message: String,
+}
+
+impl CloudflareTokenReport {
+  fn rejected(token_status: Option<String>, message: String) -> Self {
+    Self {
+      valid: false,
+      token_status,
+      expires_on: None,
+      zone_id: None,
+      zone_name: None,
+      granted: Vec::new(),
+      missing: Vec::new(),
+      message,
+    }
+  }
+}
+
+enum Reply {
+  Ok(Value),
+  Denied(u16, String),
+}
+
+fn get(agent: &ureq::Agent, token: &str, path: &str) -> Result<Reply, String> {
+  match agent
+    .get(&format!("{}{}", API, path))
+    .set("Authorization", &format!("Bearer {}", token))
+    .call()
+  {
+    Ok(response) => response
+      .into_json::<Value>()
+      .map(Reply::Ok)
+      .map_err(|e| format!("Unexpected Cloudflare response: {}", e)),
+    Err(ureq::Error::Status(code, response)) if code == 401 || code == 403 => {
+      let body = response.into_json::<Value>().unwrap_or(Value::Null);
+      Ok(Reply::Denied(code, first_error(&body)))
+    }
+    Err(ureq::Error::Status(code, response)) => {
+      let body = response.into_json::<Value>().unwrap_or(Value::Null);
+      Err(format!("Cloudflare API error (HTTP {}): {}", code, first_error(&body)))
+    }
+    Err(ureq::Error::Transport(transport)) => Err(format!("Cannot reach the Cloudflare API: {}", transport)),
+  }
+}
+
+fn first_error(body: &Value) -> String {
+  body["errors"][0]["message"].as_str().unwrap_or("unknown error").to_string()
+}
+
+/// `example.com` for `lab.example.com`, and so on: every candidate zone
+/// name from most to least specific, stopping at two labels.
+fn zone_candidates(domain: &str) -> Vec<&str> {
+  let mut candidates = vec![domain];
+  let mut rest = domain;
+  while let Some((_, parent)) = rest.split_once('.') {
+    if !parent.contains('.') {
+      break;
+    }
+    candidates.push(parent);
+    rest = parent;
+  }
+  candidates
+}
+
+/// The zone `domain` belongs to, as far as the token can see.
+fn find_zone(agent: &ureq::Agent, token: &str, domain: &str) -> Result<Option<Value>, String> {
+  for candidate in zone_candidates(domain) {
+    match get(agent, token, &format!("/zones?name={}", candidate))? {
+      Reply::Ok(body) => {
+        if let Some(found) = body["result"].as_array().and_then(|zones| zones.first()) {
+          return Ok(Some(found.clone()));
+        }
+      }
+      Reply::Denied(code, message) => info!("Zone lookup for {} denied (HTTP {}): {}", candidate, code, message),
+    }
+  }
+  Ok(None)
+}
+
+fn agent() -> ureq::Agent {
+  ureq::AgentBuilder::new().timeout(Duration::from_secs(15)).build()
+}
+
+fn write_error(error: ureq::Error) -> String {
+  match error {
+    ureq::Error::Status(code, response) => {
+      let body = response.into_json::<Value>().unwrap_or(Value::Null);
+      format!("Cloudflare API error (HTTP {}): {}", code, first_error(&body))
+    }
+    ureq::Error::Transport(transport) => format!("Cannot reach the Cloudflare API: {}", transport),
+  }
+}
+
+/// A DNS record created through the API, to delete again later.
+#[derive(Debug, Clone, Serialize)]
+pub struct CreatedRecord {
+  pub zone_id: String,
+  pub record_id: String,
+}
+
+/// Create a TXT record `name` with `content` in the zone of `domain`.
+pub fn create_txt_record(token: &str, domain: &str, name: &str, content: &str) -> Result<CreatedRecord, String> {
+  let agent = agent();
+  let zone = find_zone(&agent, token, domain)?.ok_or_else(|| format!("No zone for '{}' is visible to this token", domain))?;
+  let zone_id = zone["id"].as_str().ok_or("Cloudflare returned a zone without an id")?.to_string();
+  let body: Value = agent
+    .post(&format!("{}/zones/{}/dns_records", API, zone_id))
+    .set("Authorization", &format!("Bearer {}", token))
+    .send_json(serde_json::json!({ "type": "TXT", "name": name, "content": content, "ttl": 60 }))
+    .map_err(write_error)?
+    .into_json()
+    .map_err(|e| format!("Unexpected Cloudflare response: {}", e))?;
+  let record_id = body["result"]["id"]
+    .as_str()
+    .ok_or("Cloudflare returned a record without an id")?
+    .to_string();
+  info!("Created TXT record {} in zone {}", name, zone["name"].as_str().unwrap_or(domain));
+  Ok(CreatedRecord { zone_id, record_id })
+}
+
+pub fn delete_record(token: &str, record: &CreatedRecord) -> Result<(), String> {
+  agent()
+    .delete(&format!("{}/zones/{}/dns_records/{}", API, record.zone_id, record.record_id))
+    .set("Authorization", &format!("Bearer {}", token))
+    .call()
+    .map(|_| ())
+    .map_err(write_error)
+}
+
+fn check(token: &str, domain: &str) -> Result<CloudflareTokenReport, String> {
+  let agent = agent();
+
+  let verify = match get(&agent, token, "/user/tokens/verify")? {
+    Reply::Ok(body) => body["result"].clone(),
+    Reply::Denied(_, message) => {
+      return Ok(CloudflareTokenReport::rejected(
+        None,
+        format!("Cloudflare rejected the token: {}", message),
+      ))
+    }
+  };
+  let status = verify["status"].as_str().unwrap_or("unknown").to_string();
+  let expires_on = verify["expires_on"].as_str().map(str::to_string);
+  if status != "active" {
+    let mut report = CloudflareTokenReport::rejected(Some(status.clone()), format!("Token is {}", status));
+    report.expires_on = expires_on;
+    return Ok(report);
+  }
+
+  let Some(zone) = find_zone(&agent, token, domain)? else {
+    return Ok(CloudflareTokenReport {
+      valid: false,
+      token_status: Some(status),
+      expires_on,
+      zone_id: None,
+      zone_name: None,
+      granted: Vec::new(),
+      missing: REQUIRED.iter().map(|(_, label)| label.to_string()).collect(),
+      message: format!(
+        "No zone for '{}' is visible to this token. Either the domain isn't in this Cloudflare account \
+         or the token isn't scoped to its zone.",
+        domain
+      ),
+    });
+  };
+
+  let held: Vec<&str> = zone["permissions"]
+    .as_array()
+    .map(|perms| perms.iter().filter_map(Value::as_str).collect())
+    .unwrap_or_default();
+  let (granted, missing): (Vec<_>, Vec<_>) = REQUIRED.iter().partition(|(perm, _)| held.contains(perm));
+  let zone_name = zone["name"].as_str().unwrap_or(domain).to_string();
+
+  let message = if missing.is_empty() {
+    format!("Token can manage DNS records for {}", zone_name)
+  } else {
+    format!(
+      "Token is missing {} on zone {}",
+      missing.iter().map(|(_, label)| *label).collect::<Vec<_>>().join(", "),
+      zone_name
+    )
+  };
+
+  Ok(CloudflareTokenReport {
+    valid: missing.is_empty(),
+    token_status: Some(status),
+    expires_on,
+    zone_id: zone["id"].as_str().map(str::to_string),
+    zone_name: Some(zone_name),
+    granted: granted.iter().map(|(_, label)| label.to_string()).collect(),
+    missing: missing.iter().map(|(_, label)| label.to_string()).collect(),
+    message,
+  })
+}
+
+#[tauri::command]
+pub async fn validate_cloudflare_token(token: String, domain: String) -> Result<CloudflareTokenReport, String> {
+  let token = token.trim().to_string();
+  let domain = domain.trim().trim_end_matches('.').to_ascii_lowercase();
+  if token.is_empty() {
+    return Err("No API token provided".to_string());
+  }
+  crate::redact::register(&token);
+  if !validation::is_valid_domain(&domain) {
+    return Err(format!("Invalid domain: {}", domain));
+  }
+  tauri::async_runtime::spawn_blocking(move || check(&token, &domain))
+    .await
+    .map_err(|e| e.to_string())?
+}
@@ -0,0 +1,308 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! GitHub token and SSH access check.
+//!
+//! The platform deployment creates repositories, registers Actions runners
+//! and pushes over SSH. Each of those fails differently when a credential is
+//! incomplete, so the check reports them separately: does the token
+//! authenticate, does it carry the classic scopes we need, can it act in the
+//! target organization, and does `ssh git@github.com` authenticate with the
+//! user's key. Fine-grained tokens don't report scopes; for those the scope
+//! check is left undecided rather than failed.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tracing::info;
+
+use crate::platform::find_program;
+use crate::validation;
+use crate::workspace::{write_private_file, Workspace};
+
+const API: &str = "https://api.github.com";
+
+/// Classic PAT scopes the deployment needs, each with the broader scopes
+/// that imply it.
+const REQUIRED_SCOPES: &[(&str, &[&str])] = &[
+  ("repo", &[]),
+  ("workflow", &[]),
+  ("write:packages", &[]),
+  ("read:org", &["write:org", "admin:org"]),
+  ("write:discussion", &[]),
+];
+
+/// GitHub's published host key, so the check neither trusts on first use
+/// nor touches the user's `known_hosts`.
+const KNOWN_HOSTS: &str = "github.com ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIOMqqnkVzrm0SdG6UOoqKLsabgH5C9okWi0dh2l9GKJl\n\
+[ssh.github.com]:443 ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIOMqqnkVzrm0SdG6UOoqKLsabgH5C9okWi0dh2l9GKJl\n";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GithubAccessReport {
+  /// Every check that ran passed
+  pub valid: bool,
+
+  pub auth_ok: bool,
+  pub username: Option<String>,
+  pub auth_error: Option<String>,
+
+  /// `None` when the token doesn't report scopes (fine-grained tokens)
+  pub scopes_ok: Option<bool>,
+  pub scopes: Vec<String>,
+  pub missing_scopes: Vec<String>,
+
+  /// `None` when no organization was given
+  pub org_ok: Option<bool>,
+  /// `admin` or `member`
+  pub org_role: Option<String>,
+  pub org_error: Option<String>,
+
+  pub ssh_ok: bool,
+  /// Account the SSH key belongs to, from GitHub's greeting
+  pub ssh_username: Option<String>,
+  /// `github.com:22`, or `ssh.github.com:443` when port 22 is blocked
+  pub ssh_endpoint: Option<String>,
+  pub ssh_error: Option<String>,
+}
+
+fn request(agent: &ureq::Agent, token: &str, path: &str) -> ureq::Request {
+  agent
+    .get(&format!("{}{}", API, path))
+    .set("Authorization", &format!("Bearer {}", token))
+    .set("Accept", "application/vnd.github+json")
+    .set("X-GitHub-Api-Version", "2022-11-28")
+}
+
+fn describe(error: ureq::Error) -> String {
+  match error {
+    ureq::Error::Status(401, _) => "Invalid or expired GitHub token".to_string(),
+    ureq::Error::Status(code, response) => {
+      let message = response
+        .into_json::<Value>()
+        .ok()
+        .and_then(|body| body["message"].as_str().map(str::to_string))
+        .unwrap_or_default();
+      format!("GitHub API error (HTTP {}): {}", code, message)
+    }
+    ureq::Error::Transport(transport) => format!("Cannot reach the GitHub API: {}", transport),
+  }
+}
+
+fn missing_scopes(granted: &[String]) -> Vec<String> {
+  REQUIRED_SCOPES
+    .iter()
+    .filter(|(scope, implied_by)| {
+      !granted
+        .iter()
+        .any(|g| g == scope || implied_by.contains(&g.as_str()))
+    })
+    .map(|(scope, _)| scope.to_string())
+    .collect()
+}
+
+/// Role in `org`, or an explanation of why the token can't act there.
+fn org_membership(agent: &ureq::Agent, token: &str, org: &str) -> Result<String, String> {
+  match request(agent, token, &format!("/user/memberships/orgs/{}", org)).call() {
+    Ok(response) => {
+      let body: Value = response.into_json().map_err(|e| e.to_string())?;
+      match body["state"].as_str() {
+        Some("active") => Ok(body["role"].as_str().unwrap_or("member").to_string()),
+        Some(state) => Err(format!("Membership in {} is {}; accept the invitation first", org, state)),
+        None => Err(format!("Unexpected membership response for {}", org)),
+      }
+    }
+    Err(ureq::Error::Status(404, _)) => Err(format!(
+      "Token owner is not a member of {}, or the organization denies this token",
+      org
+    )),
+    Err(ureq::Error::Status(403, response)) => {
+      let message = response
+        .into_json::<Value>()
+        .ok()
+        .and_then(|body| body["message"].as_str().map(str::to_string))
+        .unwrap_or_default();
+      Err(format!("{} blocked the token: {}", org, message))
+    }
+    Err(e) => Err(describe(e)),
+  }
+}
+
+enum SshOutcome {
+  Authenticated(String),
+  Rejected(String),
+  Unreachable(String),
+}
+
+fn ssh_attempt(ssh: &std::path::Path, known_hosts: &std::path::Path, host: &str, port: u16) -> SshOutcome {
+  let output = Command::new(ssh)
+    .arg("-T")
+    .args(["-p", &port.to_string()])
+    .args(["-o", "BatchMode=yes"])
+    .args(["-o", "ConnectTimeout=10"])
+    .args(["-o", "StrictHostKeyChecking=yes"])
+    .args(["-o", "HostKeyAlgorithms=ssh-ed25519"])
+    .arg("-o")
+    .arg(format!("UserKnownHostsFile={}", known_hosts.display()))
+    .arg(format!("git@{}", host))
+    .stdin(Stdio::null())
+    .output();
+  let output = match output {
+    Ok(output) => output,
+    Err(e) => return SshOutcome::Unreachable(format!("Failed to run ssh: {}", e)),
+  };
+
+  // GitHub refuses a shell, so success is exit status 1 plus the greeting
+  let stderr = String::from_utf8_lossy(&output.stderr);
+  if let Some(rest) = stderr.split("Hi ").nth(1) {
+    if let Some((user, _)) = rest.split_once('!') {
+      if stderr.contains("successfully authenticated") {
+        return SshOutcome::Authenticated(user.to_string());
+      }
+    }
+  }
+  let detail = stderr.trim().lines().last().unwrap_or("ssh failed").to_string();
+  if stderr.contains("Permission denied") {
+    SshOutcome::Rejected(detail)
+  } else {
+    SshOutcome::Unreachable(detail)
+  }
+}
+
+fn check_ssh(workspace: &Workspace, report: &mut GithubAccessReport) {
+  let Some(ssh) = find_program("ssh", &[]) else {
+    report.ssh_error = Some("ssh is not installed".to_string());
+    return;
+  };
+  let known_hosts = match workspace
+    .resolve("ssh/github_known_hosts")
+    .and_then(|path| write_private_file(&path, KNOWN_HOSTS.as_bytes()).map(|_| path))
+  {
+    Ok(path) => path,
+    Err(e) => {
+      report.ssh_error = Some(e);
+      return;
+    }
+  };
+
+  let mut error = None;
+  for (host, port) in [("github.com", 22), ("ssh.github.com", 443)] {
+    match ssh_attempt(&ssh, &known_hosts, host, port) {
+      SshOutcome::Authenticated(user) => {
+        report.ssh_ok = true;
+        report.ssh_username = Some(user);
+        report.ssh_endpoint = Some(format!("{}:{}", host, port));
+        report.ssh_error = None;
+        return;
+      }
+      SshOutcome::Rejected(detail) => {
+        report.ssh_endpoint = Some(format!("{}:{}", host, port));
+        report.ssh_error = Some(format!(
+          "GitHub rejected the SSH key ({}). Add your public key at https://github.com/settings/keys",
+          detail
+        ));
+        return;
+      }
+      SshOutcome::Unreachable(detail) => {
+        info!("SSH to {}:{} failed: {}", host, port, detail);
+        error = Some(format!("Cannot reach GitHub over SSH: {}", detail));
+      }
+    }
+  }
+  report.ssh_error = error;
+}
+
+fn check(workspace: &Workspace, token: &str, org: Option<&str>) -> GithubAccessReport {
+  let agent = ureq::AgentBuilder::new().timeout(Duration::from_secs(10)).build();
+  let mut report = GithubAccessReport {
+    valid: false,
+    auth_ok: false,
+    username: None,
+    auth_error: None,
+    scopes_ok: None,
+    scopes: Vec::new(),
+    missing_scopes: Vec::new(),
+    org_ok: None,
+    org_role: None,
+    org_error: None,
+    ssh_ok: false,
+    ssh_username: None,
+    ssh_endpoint: None,
+    ssh_error: None,
+  };
+
+  match request(&agent, token, "/user").call() {
+    Ok(response) => {
+      report.auth_ok = true;
+      if let Some(header) = response.header("X-OAuth-Scopes") {
+        report.scopes = header
+          .split(',')
+          .map(|s| s.trim().to_string())
+          .filter(|s| !s.is_empty())
+          .collect();
+        report.missing_scopes = missing_scopes(&report.scopes);
+        report.scopes_ok = Some(report.missing_scopes.is_empty());
+      }
+      report.username = response
+        .into_json::<Value>()
+        .ok()
+        .and_then(|body| body["login"].as_str().map(str::to_string));
+    }
+    Err(e) => report.auth_error = Some(describe(e)),
+  }
+
+  if let Some(org) = org {
+    if !report.auth_ok {
+      report.org_error = Some("Skipped: the token did not authenticate".to_string());
+      report.org_ok = Some(false);
+    } else if report.username.as_deref().map(|u| u.eq_ignore_ascii_case(org)) == Some(true) {
+      // Repositories go to the user's own account
+      report.org_ok = Some(true);
+      report.org_role = Some("admin".to_string());
+    } else {
+      match org_membership(&agent, token, org) {
+        Ok(role) => {
+          report.org_ok = Some(true);
+          report.org_role = Some(role);
+        }
+        Err(e) => {
+          report.org_ok = Some(false);
+          report.org_error = Some(e);
+        }
+      }
+    }
+  }
+
+  check_ssh(workspace, &mut report);
+
+  report.valid = report.auth_ok && report.scopes_ok != Some(false) && report.org_ok != Some(false) && report.ssh_ok;
+  report
+}
+
+/// Validate a GitHub token (and its access to `org`, if given) and SSH
+/// access to github.com with the user's default keys.
+#[tauri::command]
+pub async fn validate_github_access(
+  app: AppHandle,
+  token: String,
+  org: Option<String>,
+) -> Result<GithubAccessReport, String> {
+  let token = token.trim().to_string();
+  if token.is_empty() {
+    return Err("GitHub token is required".to_string());
+  }
+  crate::redact::register(&token);
+  let org = org.map(|o| o.trim().to_string()).filter(|o| !o.is_empty());
+  if let Some(org) = &org {
+    if !validation::is_valid_label(org) {
+      return Err(format!("Invalid GitHub organization: {}", org));
+    }
+  }
+
+  tauri::async_runtime::spawn_blocking(move || check(&app.state::<Workspace>(), &token, org.as_deref()))
+    .await
+    .map_err(|e| e.to_string())
+}
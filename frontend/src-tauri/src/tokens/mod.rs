@@ -0,0 +1,9 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Checking third-party API tokens before the deployment depends on them.
+
+pub mod cloudflare;
+pub mod github;
@@ -0,0 +1,227 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! A suggested cluster layout for the hardware that was found.
+//!
+//! `suggest_topology` proposes what the role assignment page would
+//! otherwise leave to the user, following the same rules the wizard
+//! enforces. There is one control plane, on bare metal with at least 4
+//! cores and 8 GB of memory. It goes on a CPU-only node when there is one,
+//! so the GPUs are left to AI workloads, and otherwise on the one with the
+//! most memory. Every other node that may be a worker becomes one,
+//! flagged as a GPU worker when it has GPUs. Storage replicas go on the
+//! nodes with the most disk: three when three nodes have room for them,
+//! else the single largest. Each worker also gets the largest LXD VM it
+//! could hold while leaving the host what `plan_vm_resources` keeps for
+//! it. Every choice comes with the reason for it, and the wizard applies
+//! the result as a starting point the user can change.
+
+use serde::{Deserialize, Serialize};
+
+use crate::remote::vmplan::{HOST_CPUS, HOST_DISK_GIB, HOST_MEMORY_MIB};
+
+const MIN_CONTROL_PLANE_CORES: u32 = 4;
+const MIN_CONTROL_PLANE_MEMORY_GB: f64 = 8.0;
+const MIN_WORKER_CORES: u32 = 2;
+/// Disk a node needs to be worth a storage replica
+const MIN_STORAGE_DISK_GB: f64 = 200.0;
+const STORAGE_REPLICAS: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+  ControlPlane,
+  Worker,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct NodeHardware {
+  pub host: String,
+  pub hostname: String,
+  pub cpu_cores: u32,
+  pub memory_gb: f64,
+  pub disk_gb: f64,
+  #[serde(default)]
+  pub gpu_count: u32,
+  /// From `detect_boards`; any role when not set
+  pub allowed_roles: Option<Vec<Role>>,
+}
+
+impl NodeHardware {
+  fn allows(&self, role: Role) -> bool {
+    self.allowed_roles.as_ref().map_or(true, |roles| roles.contains(&role))
+  }
+
+  fn can_be_control_plane(&self) -> bool {
+    self.cpu_cores >= MIN_CONTROL_PLANE_CORES
+      && self.memory_gb >= MIN_CONTROL_PLANE_MEMORY_GB
+      && self.allows(Role::ControlPlane)
+  }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VmSize {
+  pub vcpus: u32,
+  pub memory_mib: u64,
+  pub disk_gib: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeSuggestion {
+  pub host: String,
+  pub hostname: String,
+  /// `None` when the node can't take any role
+  pub role: Option<Role>,
+  pub gpu_worker: bool,
+  pub storage: bool,
+  /// The largest VM the node could hold, for workers
+  pub vm: Option<VmSize>,
+  pub reasons: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TopologySuggestion {
+  pub nodes: Vec<NodeSuggestion>,
+  pub warnings: Vec<String>,
+}
+
+fn vm_size(node: &NodeHardware) -> Option<VmSize> {
+  let vcpus = u64::from(node.cpu_cores).checked_sub(HOST_CPUS).filter(|v| *v > 0)?;
+  let memory_mib = ((node.memory_gb * 1024.0) as u64)
+    .checked_sub(HOST_MEMORY_MIB)
+    .filter(|m| *m >= 1024)?;
+  let disk_gib = (node.disk_gb as u64).checked_sub(HOST_DISK_GIB).filter(|d| *d > 0)?;
+  Some(VmSize {
+    vcpus: vcpus as u32,
+    // Whole GiB, as LXD limits are usually given
+    memory_mib: memory_mib / 1024 * 1024,
+    disk_gib,
+  })
+}
+
+fn suggest(nodes: &[NodeHardware]) -> TopologySuggestion {
+  let mut warnings = Vec::new();
+  let control_plane = nodes
+    .iter()
+    .enumerate()
+    .filter(|(_, n)| n.can_be_control_plane())
+    // CPU-only first when there is a choice, then the most memory and cores
+    .max_by(|(_, a), (_, b)| {
+      (a.gpu_count == 0, a.memory_gb, a.cpu_cores)
+        .partial_cmp(&(b.gpu_count == 0, b.memory_gb, b.cpu_cores))
+        .unwrap_or(std::cmp::Ordering::Equal)
+    })
+    .map(|(i, _)| i);
+  if control_plane.is_none() {
+    warnings.push(format!(
+      "No node can run the control plane; it needs at least {} cores and {} GB of memory",
+      MIN_CONTROL_PLANE_CORES, MIN_CONTROL_PLANE_MEMORY_GB
+    ));
+  }
+
+  let mut suggestions: Vec<NodeSuggestion> = nodes
+    .iter()
+    .enumerate()
+    .map(|(i, node)| {
+      let mut reasons = Vec::new();
+      let role = if Some(i) == control_plane {
+        // CPU-only nodes are picked first, so a GPU node here means none
+        // of them qualified
+        reasons.push(if node.gpu_count == 0 && nodes.iter().any(|n| n.gpu_count > 0) {
+          "Runs the control plane on a CPU-only node, leaving the GPUs to workloads".to_string()
+        } else if node.gpu_count > 0 && nodes.len() > 1 {
+          "Runs the control plane; no CPU-only node is large enough, so it shares this node's GPUs".to_string()
+        } else {
+          format!(
+            "Runs the control plane: {} cores and {:.0} GB of memory",
+            node.cpu_cores, node.memory_gb
+          )
+        });
+        Some(Role::ControlPlane)
+      } else if !node.allows(Role::Worker) {
+        reasons.push("This board can't run a worker".to_string());
+        None
+      } else if node.cpu_cores < MIN_WORKER_CORES {
+        reasons.push(format!(
+          "Has {} cores; a worker needs at least {}",
+          node.cpu_cores, MIN_WORKER_CORES
+        ));
+        None
+      } else {
+        reasons.push("Runs workloads as a worker".to_string());
+        Some(Role::Worker)
+      };
+      let gpu_worker = role.is_some() && node.gpu_count > 0;
+      if gpu_worker {
+        reasons.push(format!(
+          "Schedules GPU workloads on its {} GPU{}",
+          node.gpu_count,
+          if node.gpu_count == 1 { "" } else { "s" }
+        ));
+      }
+      NodeSuggestion {
+        host: node.host.clone(),
+        hostname: node.hostname.clone(),
+        vm: (role == Some(Role::Worker)).then(|| vm_size(node)).flatten(),
+        role,
+        gpu_worker,
+        storage: false,
+        reasons,
+      }
+    })
+    .collect();
+
+  let mut by_disk: Vec<usize> = (0..nodes.len())
+    .filter(|&i| suggestions[i].role.is_some() && nodes[i].disk_gb >= MIN_STORAGE_DISK_GB)
+    .collect();
+  by_disk.sort_by(|&a, &b| {
+    nodes[b]
+      .disk_gb
+      .partial_cmp(&nodes[a].disk_gb)
+      .unwrap_or(std::cmp::Ordering::Equal)
+  });
+  let replicas = match by_disk.len() {
+    n if n >= STORAGE_REPLICAS => STORAGE_REPLICAS,
+    0 => {
+      warnings.push(format!(
+        "No node has {:.0} GB of disk for storage replicas",
+        MIN_STORAGE_DISK_GB
+      ));
+      0
+    }
+    n => {
+      if nodes.len() >= STORAGE_REPLICAS {
+        warnings.push(format!(
+          "Only {} node{} with {:.0} GB of disk; storage will have a single replica",
+          n,
+          if n == 1 { "" } else { "s" },
+          MIN_STORAGE_DISK_GB
+        ));
+      }
+      1
+    }
+  };
+  for &i in by_disk.iter().take(replicas) {
+    suggestions[i].storage = true;
+    suggestions[i].reasons.push(format!(
+      "Holds a storage replica on its {:.0} GB of disk",
+      nodes[i].disk_gb
+    ));
+  }
+
+  TopologySuggestion {
+    nodes: suggestions,
+    warnings,
+  }
+}
+
+/// Propose roles, GPU workers, storage replicas and VM sizes for `nodes`.
+#[tauri::command]
+pub fn suggest_topology(nodes: Vec<NodeHardware>) -> Result<TopologySuggestion, String> {
+  if nodes.is_empty() {
+    return Err("No nodes to plan".to_string());
+  }
+  Ok(suggest(&nodes))
+}
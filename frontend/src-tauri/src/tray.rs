@@ -0,0 +1,53 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! System tray icon and menu.
+
+use tauri::menu::{Menu, MenuItem, PredefinedMenuItem};
+use tauri::tray::TrayIconBuilder;
+use tauri::AppHandle;
+use tracing::warn;
+
+use crate::{i18n, windows};
+
+const SHOW_INSTALLER: &str = "show-installer";
+const SHOW_LOGS: &str = "show-logs";
+const QUIT: &str = "quit";
+
+pub fn create(app: &AppHandle) -> tauri::Result<()> {
+  let menu = Menu::with_items(
+    app,
+    &[
+      &MenuItem::with_id(app, SHOW_INSTALLER, i18n::t("tray-show-installer"), true, None::<&str>)?,
+      &MenuItem::with_id(app, SHOW_LOGS, i18n::t("tray-show-logs"), true, None::<&str>)?,
+      &PredefinedMenuItem::separator(app)?,
+      &MenuItem::with_id(app, QUIT, i18n::t("tray-quit"), true, None::<&str>)?,
+    ],
+  )?;
+
+  let mut builder = TrayIconBuilder::with_id("main")
+    .menu(&menu)
+    .tooltip("Thinkube Installer")
+    .on_menu_event(|app, event| {
+      let result = match event.id.as_ref() {
+        SHOW_INSTALLER => windows::show_main(app),
+        SHOW_LOGS => windows::show_logs(app),
+        QUIT => {
+          windows::save_main_geometry(app);
+          app.exit(0);
+          Ok(())
+        }
+        _ => Ok(()),
+      };
+      if let Err(e) = result {
+        warn!("Tray action {} failed: {}", event.id.as_ref(), e);
+      }
+    });
+  if let Some(icon) = app.default_window_icon() {
+    builder = builder.icon(icon.clone());
+  }
+  builder.build(app)?;
+  Ok(())
+}
@@ -0,0 +1,478 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Trusting the cluster CA on this machine.
+//!
+//! The dashboards' certificates chain to the CA the installer generated,
+//! which no browser knows, so each of them warns until the user trusts it.
+//! `trust_cluster_ca` adds it, only with the user's consent, to the system
+//! trust store (the distro's ca-certificates anchors, or the System
+//! keychain on macOS) using the sudo password once, and optionally to the
+//! NSS databases Chrome and Firefox keep per user on Linux, which don't
+//! read the system store. It takes a CA certificate and nothing else; the
+//! CA key stays in the workspace.
+//!
+//! Every addition is recorded in `~/.thinkube-installer/trusted-ca.json`.
+//! `untrust_cluster_ca` removes what is recorded, and so does the
+//! uninstall, which has no password to give and relies on `sudo -n` for
+//! the system store.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tauri::{AppHandle, Manager};
+use tracing::{info, warn};
+
+use crate::certs::{parse_pem, to_pem};
+use crate::platform::find_program;
+use crate::preflight::Outcome;
+use crate::remote::ssh::{shell_quote, ScriptOutput};
+use crate::remote::sudoers::run_local;
+use crate::workspace::{write_private_file, Workspace};
+use crate::{dry_run, redact};
+
+const MACOS_KEYCHAIN: &str = "/Library/Keychains/System.keychain";
+/// ca-certificates anchor directories, and the command that rebuilds the
+/// bundle from them: Debian and Ubuntu, Fedora and RHEL, Arch, openSUSE
+const ANCHOR_DIRS: &[(&str, &str)] = &[
+  ("/usr/local/share/ca-certificates", "update-ca-certificates"),
+  ("/etc/pki/ca-trust/source/anchors", "update-ca-trust extract"),
+  ("/etc/ca-certificates/trust-source/anchors", "trust extract-compat"),
+  ("/etc/pki/trust/anchors", "update-ca-certificates"),
+];
+/// NSS databases below the home directory: Chrome and Chromium, then the
+/// deb and snap builds of Firefox, one per profile
+const NSS_DIRS: &[&str] = &[".pki/nssdb", "snap/chromium/current/.pki/nssdb"];
+const FIREFOX_DIRS: &[&str] = &[".mozilla/firefox", "snap/firefox/common/.mozilla/firefox"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Store {
+  System,
+  Nss,
+}
+
+/// A CA this installer added to a trust store.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrustedCa {
+  pub store: Store,
+  /// Anchor file, keychain or NSS database directory
+  pub location: String,
+  /// NSS nickname, or SHA-1 in the keychain; empty for anchor files
+  pub name: String,
+  /// SHA-256 of the certificate, in hex
+  pub fingerprint: String,
+  pub subject: String,
+}
+
+impl TrustedCa {
+  pub fn id(&self) -> String {
+    if self.name.is_empty() {
+      self.location.clone()
+    } else {
+      format!("{}#{}", self.location, self.name)
+    }
+  }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TrustRequest {
+  /// PEM of the CA; the bootstrap CA in the run workspace when omitted
+  pub ca_pem: Option<String>,
+  /// Also add it to the browsers' NSS databases (Linux)
+  #[serde(default)]
+  pub nss: bool,
+  /// For the system store; `sudo -n` is tried without one
+  pub sudo_password: Option<String>,
+  /// The user agreed to trust the CA for every site
+  #[serde(default)]
+  pub consent: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TrustResult {
+  pub store: Store,
+  pub location: String,
+  pub outcome: Outcome,
+  pub message: String,
+}
+
+struct Ca {
+  pem: String,
+  sha256: String,
+  sha1: String,
+  subject: String,
+}
+
+fn hex(bytes: &[u8]) -> String {
+  bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn load_ca(workspace: &Workspace, pem: Option<&str>) -> Result<Ca, String> {
+  let pem = match pem {
+    Some(pem) => pem.trim().to_string(),
+    None => {
+      let path = workspace.resolve("certs/ca.crt")?;
+      std::fs::read_to_string(&path)
+        .map_err(|_| "No cluster CA in the workspace; generate the certificates first".to_string())?
+        .trim()
+        .to_string()
+    }
+  };
+  let chain = parse_pem(pem.as_bytes())?;
+  // Only certificates are parsed; any other block, such as a key pasted
+  // along with the CA, is dropped by writing out what was parsed
+  if chain.len() != 1 {
+    return Err("Expected a single CA certificate".to_string());
+  }
+  let (_, cert) = x509_parser::parse_x509_certificate(&chain[0]).map_err(|e| format!("Invalid certificate: {}", e))?;
+  if !cert.is_ca() {
+    return Err(format!("{} is not a CA certificate", cert.subject()));
+  }
+  Ok(Ca {
+    sha256: hex(ring::digest::digest(&ring::digest::SHA256, &chain[0]).as_ref()),
+    sha1: hex(ring::digest::digest(&ring::digest::SHA1_FOR_LEGACY_USE_ONLY, &chain[0]).as_ref()),
+    subject: cert.subject().to_string(),
+    pem: to_pem(&chain[0]),
+  })
+}
+
+fn records_path() -> Option<PathBuf> {
+  std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".thinkube-installer").join("trusted-ca.json"))
+}
+
+/// CAs recorded by earlier `trust_cluster_ca` calls.
+pub fn recorded() -> Vec<TrustedCa> {
+  records_path()
+    .and_then(|path| std::fs::read_to_string(path).ok())
+    .and_then(|text| serde_json::from_str(&text).ok())
+    .unwrap_or_default()
+}
+
+fn save(entries: &[TrustedCa]) -> Result<(), String> {
+  let path = records_path().ok_or("Cannot locate the home directory")?;
+  if entries.is_empty() {
+    return match std::fs::remove_file(&path) {
+      Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(format!("Failed to remove {}: {}", path.display(), e)),
+      _ => Ok(()),
+    };
+  }
+  let json = serde_json::to_vec_pretty(entries).map_err(|e| e.to_string())?;
+  write_private_file(&path, &json)
+}
+
+fn remember(entry: TrustedCa) -> Result<(), String> {
+  let mut entries = recorded();
+  entries.retain(|e| e.id() != entry.id());
+  entries.push(entry);
+  save(&entries)
+}
+
+pub fn forget(entry: &TrustedCa) -> Result<(), String> {
+  let mut entries = recorded();
+  entries.retain(|e| e.id() != entry.id());
+  save(&entries)
+}
+
+/// Run `script` as root, with the password or else `sudo -n`.
+fn as_root(script: &str, password: Option<&str>) -> Result<(), String> {
+  let output: ScriptOutput = match password {
+    Some(password) => run_local(
+      "sudo",
+      &["-S", "-p", "", "--", "sh", "-c", script],
+      &format!("{}\n", password),
+    )?,
+    None => run_local("sudo", &["-n", "--", "sh", "-c", script], "")?,
+  };
+  if output.status == Some(0) {
+    return Ok(());
+  }
+  let stderr = output.stderr.trim();
+  Err(
+    if stderr.contains("incorrect password") || stderr.contains("Sorry, try again") {
+      "The sudo password was not accepted".to_string()
+    } else if stderr.contains("a password is required") {
+      "sudo needs a password".to_string()
+    } else {
+      stderr.lines().last().unwrap_or("command failed").to_string()
+    },
+  )
+}
+
+fn anchor_dir() -> Option<(&'static str, &'static str)> {
+  ANCHOR_DIRS.iter().copied().find(|(dir, _)| Path::new(dir).is_dir())
+}
+
+fn refresh_command(anchor: &str) -> &'static str {
+  ANCHOR_DIRS
+    .iter()
+    .find(|(dir, _)| Path::new(anchor).parent() == Some(Path::new(dir)))
+    .map_or("update-ca-certificates", |(_, refresh)| refresh)
+}
+
+/// The CA as a file certutil and `security` can read.
+fn ca_file(workspace: &Workspace, ca: &Ca) -> Result<PathBuf, String> {
+  let path = workspace.resolve("certs/trusted-ca.crt")?;
+  write_private_file(&path, format!("{}\n", ca.pem).as_bytes())?;
+  Ok(path)
+}
+
+fn add_to_system(workspace: &Workspace, ca: &Ca, password: Option<&str>) -> Result<TrustedCa, String> {
+  if cfg!(target_os = "macos") {
+    let file = ca_file(workspace, ca)?;
+    let script = format!(
+      "security add-trusted-cert -d -r trustRoot -k {} {}",
+      MACOS_KEYCHAIN,
+      shell_quote(&file.display().to_string())
+    );
+    as_root(&script, password)?;
+    return Ok(TrustedCa {
+      store: Store::System,
+      location: MACOS_KEYCHAIN.to_string(),
+      name: ca.sha1.clone(),
+      fingerprint: ca.sha256.clone(),
+      subject: ca.subject.clone(),
+    });
+  }
+  let (dir, refresh) = anchor_dir().ok_or("Found no ca-certificates anchors directory on this machine")?;
+  // update-ca-certificates only picks up `.crt` files
+  let path = format!("{}/thinkube-{}.crt", dir, &ca.sha256[..16]);
+  let script = format!(
+    "set -e\nPATH=/usr/sbin:/usr/bin:/sbin:/bin:$PATH\numask 022\nprintf '%s\\n' {} > {}\n{} > /dev/null\n",
+    shell_quote(&ca.pem),
+    shell_quote(&path),
+    refresh
+  );
+  as_root(&script, password)?;
+  Ok(TrustedCa {
+    store: Store::System,
+    location: path,
+    name: String::new(),
+    fingerprint: ca.sha256.clone(),
+    subject: ca.subject.clone(),
+  })
+}
+
+/// NSS databases of the user's browsers that exist already.
+fn nss_databases() -> Vec<PathBuf> {
+  let Some(home) = std::env::var_os("HOME").map(PathBuf::from) else {
+    return Vec::new();
+  };
+  let mut dirs: Vec<PathBuf> = NSS_DIRS.iter().map(|d| home.join(d)).collect();
+  for firefox in FIREFOX_DIRS {
+    if let Ok(entries) = std::fs::read_dir(home.join(firefox)) {
+      dirs.extend(entries.flatten().map(|e| e.path()));
+    }
+  }
+  dirs.retain(|d| d.join("cert9.db").is_file());
+  dirs
+}
+
+fn certutil(args: &[&str]) -> Result<(), String> {
+  let program = find_program("certutil", &[]).ok_or("certutil is not installed (libnss3-tools)")?;
+  let output = Command::new(&program)
+    .args(args)
+    .output()
+    .map_err(|e| format!("Failed to run certutil: {}", e))?;
+  if output.status.success() {
+    return Ok(());
+  }
+  let stderr = String::from_utf8_lossy(&output.stderr);
+  Err(stderr.trim().lines().last().unwrap_or("certutil failed").to_string())
+}
+
+fn add_to_nss(dir: &Path, file: &Path, ca: &Ca) -> Result<TrustedCa, String> {
+  let nickname = format!("thinkube-{}", &ca.sha256[..16]);
+  let database = format!("sql:{}", dir.display());
+  let file = file.display().to_string();
+  certutil(&["-d", &database, "-A", "-t", "C,,", "-n", &nickname, "-i", &file])?;
+  Ok(TrustedCa {
+    store: Store::Nss,
+    location: dir.display().to_string(),
+    name: nickname,
+    fingerprint: ca.sha256.clone(),
+    subject: ca.subject.clone(),
+  })
+}
+
+/// Take a recorded CA out of its store again.
+pub fn remove(entry: &TrustedCa, password: Option<&str>) -> Result<(), String> {
+  match entry.store {
+    Store::System if entry.location == MACOS_KEYCHAIN => {
+      if entry.name.is_empty() || !entry.name.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("Invalid certificate hash: {}", entry.name));
+      }
+      as_root(
+        &format!("security delete-certificate -t -Z {} {}", entry.name, MACOS_KEYCHAIN),
+        password,
+      )
+    }
+    Store::System => {
+      let file = Path::new(&entry.location);
+      let in_anchors = ANCHOR_DIRS.iter().any(|(dir, _)| file.parent() == Some(Path::new(dir)));
+      let ours = file
+        .file_name()
+        .and_then(|n| n.to_str())
+        .is_some_and(|n| n.starts_with("thinkube-") && n.ends_with(".crt"));
+      if !in_anchors || !ours {
+        return Err(format!("Not an anchor this installer added: {}", entry.location));
+      }
+      if !file.exists() {
+        return Ok(());
+      }
+      let script = format!(
+        "set -e\nPATH=/usr/sbin:/usr/bin:/sbin:/bin:$PATH\nrm -f {}\n{} > /dev/null\n",
+        shell_quote(&entry.location),
+        refresh_command(&entry.location)
+      );
+      as_root(&script, password)
+    }
+    Store::Nss => {
+      if !Path::new(&entry.location).join("cert9.db").is_file() {
+        return Ok(());
+      }
+      certutil(&["-d", &format!("sql:{}", entry.location), "-D", "-n", &entry.name])
+    }
+  }
+}
+
+fn result(store: Store, location: &str, outcome: Outcome, message: String) -> TrustResult {
+  TrustResult {
+    store,
+    location: location.to_string(),
+    outcome,
+    message,
+  }
+}
+
+fn trust(app: &AppHandle, request: &TrustRequest) -> Result<Vec<TrustResult>, String> {
+  let workspace = app.state::<Workspace>();
+  let ca = load_ca(&workspace, request.ca_pem.as_deref())?;
+  let password = request.sudo_password.as_deref();
+  let nss = if request.nss && cfg!(target_os = "linux") {
+    nss_databases()
+  } else {
+    Vec::new()
+  };
+
+  if dry_run::is_enabled(app) {
+    let system = if cfg!(target_os = "macos") {
+      MACOS_KEYCHAIN
+    } else {
+      anchor_dir().map_or("the system trust store", |(dir, _)| dir)
+    };
+    let mut results = vec![result(
+      Store::System,
+      system,
+      Outcome::Skipped,
+      format!("Dry run: would trust {} in {}", ca.subject, system),
+    )];
+    for dir in &nss {
+      let location = dir.display().to_string();
+      let message = format!("Dry run: would trust {} in {}", ca.subject, location);
+      results.push(result(Store::Nss, &location, Outcome::Skipped, message));
+    }
+    info!("Dry run: not trusting {}", ca.subject);
+    return Ok(results);
+  }
+
+  let mut results = Vec::new();
+  let mut added = Vec::new();
+  match add_to_system(&workspace, &ca, password) {
+    Ok(entry) => {
+      results.push(result(
+        Store::System,
+        &entry.location,
+        Outcome::Pass,
+        format!("Trusted {} system-wide", ca.subject),
+      ));
+      added.push(entry);
+    }
+    Err(e) => results.push(result(Store::System, "", Outcome::Fail, e)),
+  }
+  if request.nss {
+    if nss.is_empty() {
+      results.push(result(
+        Store::Nss,
+        "",
+        Outcome::Skipped,
+        "Found no Chrome or Firefox certificate database".to_string(),
+      ));
+    }
+    let file = ca_file(&workspace, &ca)?;
+    for dir in &nss {
+      let location = dir.display().to_string();
+      match add_to_nss(dir, &file, &ca) {
+        Ok(entry) => {
+          results.push(result(
+            Store::Nss,
+            &location,
+            Outcome::Pass,
+            format!("Trusted in {}", location),
+          ));
+          added.push(entry);
+        }
+        Err(e) => results.push(result(Store::Nss, &location, Outcome::Fail, e)),
+      }
+    }
+  }
+  for entry in added {
+    info!("Trusted {} in {}", entry.subject, entry.location);
+    if let Err(e) = remember(entry) {
+      warn!("Failed to record a trusted CA: {}", e);
+    }
+  }
+  Ok(results)
+}
+
+/// Add the cluster CA to this machine's trust store, and to the browsers'
+/// with `nss`; needs the user's consent.
+#[tauri::command]
+pub async fn trust_cluster_ca(app: AppHandle, request: TrustRequest) -> Result<Vec<TrustResult>, String> {
+  if !request.consent {
+    return Err("Trusting the cluster CA needs your consent".to_string());
+  }
+  if let Some(password) = &request.sudo_password {
+    redact::register(password);
+  }
+  tauri::async_runtime::spawn_blocking(move || trust(&app, &request))
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Remove every CA `trust_cluster_ca` added.
+#[tauri::command]
+pub async fn untrust_cluster_ca(app: AppHandle, sudo_password: Option<String>) -> Result<Vec<TrustResult>, String> {
+  if let Some(password) = &sudo_password {
+    redact::register(password);
+  }
+  let dry_run = dry_run::is_enabled(&app);
+  tauri::async_runtime::spawn_blocking(move || {
+    recorded()
+      .into_iter()
+      .map(|entry| {
+        if dry_run {
+          let message = format!("Dry run: would remove {} from {}", entry.subject, entry.location);
+          return result(entry.store, &entry.location, Outcome::Skipped, message);
+        }
+        match remove(&entry, sudo_password.as_deref()).and_then(|()| forget(&entry)) {
+          Ok(()) => {
+            info!("Removed {} from {}", entry.subject, entry.location);
+            let message = format!("Removed {}", entry.subject);
+            result(entry.store, &entry.location, Outcome::Pass, message)
+          }
+          Err(e) => result(entry.store, &entry.location, Outcome::Fail, e),
+        }
+      })
+      .collect()
+  })
+  .await
+  .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_trusted_cluster_ca() -> Vec<TrustedCa> {
+  recorded()
+}
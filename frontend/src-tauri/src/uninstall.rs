@@ -0,0 +1,536 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Removing what a previous installation left on this machine and the nodes.
+//!
+//! `scan_installation` lists everything found, grouped in categories the
+//! user can pick from; `uninstall` scans again and removes the items in the
+//! chosen categories, emitting an `uninstall-progress` event per item. Items
+//! go in an order that keeps the nodes reachable: instances before the
+//! snaps that run them, netplan restored before the SSH keys the installer
+//! added are dropped, and those last.
+//!
+//! Port forwards the installer asked the router for (with the
+//! `port-mapping` feature) go first, and the cluster CA comes out of this
+//! machine's trust stores before the state directory, while their records
+//! are still there.
+//!
+//! Nodes are dedicated to Thinkube, so every LXD instance on them is listed.
+//! Snaps are limited to the Kubernetes distributions the playbooks install;
+//! LXD itself ships with Ubuntu and stays.
+
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+use tracing::{error, info};
+
+use crate::{dry_run, trust};
+use crate::remote::inventory::sections;
+use crate::remote::netplan;
+use crate::remote::ssh::{self, shell_quote, SshPool, SshTarget};
+use crate::workspace::Workspace;
+
+pub const PROGRESS_EVENT: &str = "uninstall-progress";
+
+const SCAN_TIMEOUT: Duration = Duration::from_secs(30);
+const REMOVE_TIMEOUT: Duration = Duration::from_secs(300);
+const KNOWN_SNAPS: &[&str] = &["k8s", "microk8s"];
+/// Comment on the cluster key the SSH setup playbook authorizes
+const SSH_KEY_MARKER: &str = "thinkube_cluster";
+
+const SCAN_SCRIPT: &str = r#"
+export LC_ALL=C
+echo '@@instances'
+lxc list --format csv -c n,t 2>/dev/null
+echo '@@snaps'
+snap list 2>/dev/null | awk 'NR > 1 { print $1 }'
+echo '@@netplan'
+ls -1 /etc/netplan/ 2>/dev/null | grep '\.thinkube-bak$'
+echo '@@ssh_keys'
+grep -c 'thinkube_cluster' ~/.ssh/authorized_keys 2>/dev/null
+echo '@@state'
+[ -d ~/.thinkube-installer ] && echo "$HOME/.thinkube-installer"
+echo '@@end'
+"#;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Category {
+  /// Port forwards added on the router
+  #[cfg(feature = "port-mapping")]
+  PortMappings,
+  /// LXD containers and VMs on the nodes
+  Instances,
+  /// Kubernetes snaps on the nodes
+  Snaps,
+  /// Bridged netplan configurations, restored from their backups
+  Bridges,
+  /// Contexts for Thinkube clusters in `~/.kube/config`
+  Kubeconfig,
+  /// The cluster CA in this machine's trust stores
+  TrustedCa,
+  /// Installer state on this machine and the nodes
+  StateDirs,
+  /// The cluster key in the nodes' `authorized_keys`
+  SshKeys,
+}
+
+impl Category {
+  fn key(self) -> &'static str {
+    match self {
+      #[cfg(feature = "port-mapping")]
+      Category::PortMappings => "port_mappings",
+      Category::Instances => "instances",
+      Category::Snaps => "snaps",
+      Category::Bridges => "bridges",
+      Category::Kubeconfig => "kubeconfig",
+      Category::TrustedCa => "trusted_ca",
+      Category::StateDirs => "state_dirs",
+      Category::SshKeys => "ssh_keys",
+    }
+  }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UninstallItem {
+  pub id: String,
+  pub category: Category,
+  /// `None` for items on this machine
+  pub host: Option<String>,
+  pub name: String,
+  pub detail: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HostError {
+  pub host: String,
+  pub error: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct UninstallScan {
+  pub items: Vec<UninstallItem>,
+  pub errors: Vec<HostError>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UninstallResult {
+  pub item: UninstallItem,
+  pub success: bool,
+  pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UninstallProgress {
+  pub index: usize,
+  pub total: usize,
+  pub result: UninstallResult,
+}
+
+fn item(category: Category, host: Option<&str>, name: &str, detail: Option<String>) -> UninstallItem {
+  UninstallItem {
+    id: format!("{}:{}:{}", category.key(), host.unwrap_or("local"), name),
+    category,
+    host: host.map(str::to_string),
+    name: name.to_string(),
+    detail,
+  }
+}
+
+fn parse_node(host: &str, output: &str) -> Vec<UninstallItem> {
+  let sections = sections(output);
+  let empty = Vec::new();
+  let section = |name: &str| {
+    sections
+      .get(name)
+      .unwrap_or(&empty)
+      .iter()
+      .map(|l| l.trim())
+      .filter(|l| !l.is_empty())
+      .collect::<Vec<_>>()
+  };
+  let mut items = Vec::new();
+
+  for line in section("instances") {
+    let (name, kind) = line.split_once(',').unwrap_or((line, ""));
+    let detail = (!kind.is_empty()).then(|| kind.to_string());
+    items.push(item(Category::Instances, Some(host), name, detail));
+  }
+  for name in section("snaps").into_iter().filter(|s| KNOWN_SNAPS.contains(s)) {
+    items.push(item(Category::Snaps, Some(host), name, None));
+  }
+  for file in section("netplan") {
+    let backup = format!("/etc/netplan/{}", file);
+    let detail = backup
+      .strip_suffix(netplan::BACKUP_SUFFIX)
+      .map(|p| format!("Restore {}", p));
+    items.push(item(Category::Bridges, Some(host), &backup, detail));
+  }
+  if let Some(count) = section("ssh_keys")
+    .first()
+    .and_then(|c| c.parse::<u32>().ok())
+    .filter(|c| *c > 0)
+  {
+    items.push(item(
+      Category::SshKeys,
+      Some(host),
+      SSH_KEY_MARKER,
+      Some(format!("{} key(s) in ~/.ssh/authorized_keys", count)),
+    ));
+  }
+  for dir in section("state") {
+    items.push(item(Category::StateDirs, Some(host), dir, None));
+  }
+  items
+}
+
+pub fn kubeconfig_path(app: &AppHandle) -> Option<PathBuf> {
+  if let Some(first) = std::env::var_os("KUBECONFIG")
+    .and_then(|v| std::env::split_paths(&v).next())
+    .filter(|p| !p.as_os_str().is_empty())
+  {
+    return Some(first);
+  }
+  app.path().home_dir().ok().map(|home| home.join(".kube").join("config"))
+}
+
+/// Every file kubectl merges its config from: each `KUBECONFIG` entry, or
+/// `~/.kube/config` without it.
+fn kubeconfig_paths(app: &AppHandle) -> Vec<PathBuf> {
+  let paths: Vec<PathBuf> = std::env::var_os("KUBECONFIG")
+    .map(|v| std::env::split_paths(&v).filter(|p| !p.as_os_str().is_empty()).collect())
+    .unwrap_or_default();
+  if paths.is_empty() {
+    return kubeconfig_path(app).into_iter().collect();
+  }
+  paths
+}
+
+fn named<'a>(config: &'a Value, list: &str) -> impl Iterator<Item = &'a Value> {
+  config[list].as_sequence().into_iter().flatten()
+}
+
+/// Contexts whose own name or cluster name mentions Thinkube.
+fn thinkube_contexts(config: &Value) -> Vec<(String, String)> {
+  named(config, "contexts")
+    .filter_map(|c| {
+      let name = c["name"].as_str()?;
+      let cluster = c["context"]["cluster"].as_str().unwrap_or_default();
+      (name.contains("thinkube") || cluster.contains("thinkube")).then(|| (name.to_string(), cluster.to_string()))
+    })
+    .collect()
+}
+
+fn scan_local(app: &AppHandle, workspace: &Workspace) -> Vec<UninstallItem> {
+  let mut items = Vec::new();
+
+  #[cfg(feature = "port-mapping")]
+  for mapping in crate::net::portmap::recorded() {
+    let detail = Some(format!(
+      "To {}:{} via {}",
+      mapping.internal_ip, mapping.internal_port, mapping.gateway
+    ));
+    items.push(item(
+      Category::PortMappings,
+      None,
+      &mapping.external_port.to_string(),
+      detail,
+    ));
+  }
+
+  for path in kubeconfig_paths(app) {
+    let config = std::fs::read_to_string(&path)
+      .ok()
+      .and_then(|text| serde_yaml::from_str::<Value>(&text).ok());
+    if let Some(config) = config {
+      for (context, cluster) in thinkube_contexts(&config) {
+        let detail = Some(format!("Cluster {} in {}", cluster, path.display()));
+        items.push(item(Category::Kubeconfig, None, &context, detail));
+      }
+    }
+  }
+
+  for entry in trust::recorded() {
+    let detail = Some(format!("{} in {}", entry.subject, entry.location));
+    items.push(item(Category::TrustedCa, None, &entry.id(), detail));
+  }
+
+  let mut dirs = Vec::new();
+  if let Ok(home) = app.path().home_dir() {
+    dirs.push(home.join(".thinkube-installer"));
+  }
+  // Repository clones the backend makes for running playbooks
+  if let Ok(entries) = std::fs::read_dir(std::env::temp_dir()) {
+    dirs.extend(
+      entries
+        .flatten()
+        .filter(|e| e.file_name().to_string_lossy().starts_with("thinkube-installer-"))
+        .map(|e| e.path()),
+    );
+  }
+  // Workspaces kept from earlier runs; the current one is still in use
+  if let Some(runs) = workspace.root().parent() {
+    if let Ok(entries) = std::fs::read_dir(runs) {
+      dirs.extend(entries.flatten().map(|e| e.path()).filter(|p| p != workspace.root()));
+    }
+  }
+  for dir in dirs.into_iter().filter(|d| d.is_dir()) {
+    items.push(item(Category::StateDirs, None, &dir.display().to_string(), None));
+  }
+  items
+}
+
+fn scan(app: &AppHandle, hosts: &[SshTarget]) -> UninstallScan {
+  let workspace = app.state::<Workspace>();
+  let workspace: &Workspace = &workspace;
+  let pool = app.state::<SshPool>();
+  let pool: &SshPool = &pool;
+
+  let results: Vec<Result<Vec<UninstallItem>, String>> = std::thread::scope(|scope| {
+    let handles: Vec<_> = hosts
+      .iter()
+      .map(|target| {
+        scope.spawn(move || {
+          let output = ssh::run_script(workspace, pool, target, SCAN_SCRIPT, SCAN_TIMEOUT)?;
+          if !output.stdout.contains("@@end") {
+            return Err(format!("Scan did not complete: {}", output.stderr.trim()));
+          }
+          Ok(parse_node(&target.host, &output.stdout))
+        })
+      })
+      .collect();
+    handles
+      .into_iter()
+      .map(|h| h.join().unwrap_or_else(|_| Err("Scan panicked".to_string())))
+      .collect()
+  });
+
+  let mut scan = UninstallScan {
+    items: scan_local(app, workspace),
+    errors: Vec::new(),
+  };
+  for (target, result) in hosts.iter().zip(results) {
+    match result {
+      Ok(items) => scan.items.extend(items),
+      Err(error) => scan.errors.push(HostError {
+        host: target.host.clone(),
+        error,
+      }),
+    }
+  }
+  scan.items.sort_by_key(|i| i.category);
+  scan
+}
+
+/// Drop `context` from the kubeconfig at `path`. The file is copied to
+/// `.thinkube-bak` before its first edit of the run, as noted in
+/// `backed_up`, so that copy is what it was before the uninstall.
+fn remove_kubeconfig_context(path: &Path, context: &str, backed_up: &mut Vec<PathBuf>) -> Result<(), String> {
+  let text = std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+  let mut config: Value =
+    serde_yaml::from_str(&text).map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+
+  let Some(entry) = named(&config, "contexts")
+    .find(|c| c["name"].as_str() == Some(context))
+    .cloned()
+  else {
+    return Ok(());
+  };
+  let cluster = entry["context"]["cluster"].as_str().map(str::to_string);
+  let user = entry["context"]["user"].as_str().map(str::to_string);
+
+  let retain = |config: &mut Value, list: &str, name: &str| {
+    if let Some(seq) = config.get_mut(list).and_then(Value::as_sequence_mut) {
+      seq.retain(|v| v["name"].as_str() != Some(name));
+    }
+  };
+  retain(&mut config, "contexts", context);
+  // Clusters and users may be shared with contexts we keep
+  let still_used =
+    |config: &Value, key: &str, name: &str| named(config, "contexts").any(|c| c["context"][key].as_str() == Some(name));
+  if let Some(cluster) = cluster.filter(|c| !still_used(&config, "cluster", c)) {
+    retain(&mut config, "clusters", &cluster);
+  }
+  if let Some(user) = user.filter(|u| !still_used(&config, "user", u)) {
+    retain(&mut config, "users", &user);
+  }
+  if config["current-context"].as_str() == Some(context) {
+    if let Some(map) = config.as_mapping_mut() {
+      map.insert(Value::from("current-context"), Value::from(""));
+    }
+  }
+
+  if !backed_up.iter().any(|p| p == path) {
+    let backup = path.with_extension("thinkube-bak");
+    std::fs::copy(path, &backup).map_err(|e| format!("Failed to back up {}: {}", path.display(), e))?;
+    backed_up.push(path.to_path_buf());
+  }
+  let yaml = serde_yaml::to_string(&config).map_err(|e| e.to_string())?;
+  crate::workspace::write_private_file(path, yaml.as_bytes())
+}
+
+fn remove_local(app: &AppHandle, item: &UninstallItem, backed_up: &mut Vec<PathBuf>) -> Result<(), String> {
+  match item.category {
+    // From each file that has it, as kubectl merges them all
+    Category::Kubeconfig => kubeconfig_paths(app)
+      .iter()
+      .filter(|path| path.is_file())
+      .try_for_each(|path| remove_kubeconfig_context(path, &item.name, backed_up)),
+    Category::StateDirs => {
+      let dir = Path::new(&item.name);
+      if !dir.exists() {
+        return Ok(());
+      }
+      std::fs::remove_dir_all(dir).map_err(|e| format!("Failed to remove {}: {}", dir.display(), e))
+    }
+    Category::TrustedCa => {
+      let Some(entry) = trust::recorded().into_iter().find(|e| e.id() == item.name) else {
+        return Ok(());
+      };
+      trust::remove(&entry, None)?;
+      trust::forget(&entry)
+    }
+    #[cfg(feature = "port-mapping")]
+    Category::PortMappings => {
+      let recorded = crate::net::portmap::recorded();
+      let Some(mapping) = recorded.iter().find(|m| m.external_port.to_string() == item.name) else {
+        return Ok(());
+      };
+      crate::net::portmap::remove(mapping)?;
+      crate::net::portmap::forget(mapping)
+    }
+    other => Err(format!("{} items only exist on nodes", other.key())),
+  }
+}
+
+fn remove_script(item: &UninstallItem) -> Result<String, String> {
+  let name = shell_quote(&item.name);
+  Ok(match item.category {
+    Category::Instances => format!(
+      "set -e\nif lxc info {name} >/dev/null 2>&1; then lxc delete --force {name}; fi\n",
+      name = name
+    ),
+    Category::Snaps => format!(
+      "set -e\nif snap list {name} >/dev/null 2>&1; then sudo -n snap remove --purge {name}; fi\n",
+      name = name
+    ),
+    Category::Bridges => {
+      let path = item
+        .name
+        .strip_suffix(netplan::BACKUP_SUFFIX)
+        .filter(|p| netplan::is_netplan_path(p))
+        .ok_or_else(|| format!("Not a netplan backup: {}", item.name))?;
+      format!("{}sudo -n rm -f {}\n", netplan::restore_script(path, &item.name), name)
+    }
+    Category::SshKeys => format!(
+      "set -e\n[ -f ~/.ssh/authorized_keys ] && sed -i '/{}/d' ~/.ssh/authorized_keys\n",
+      SSH_KEY_MARKER
+    ),
+    Category::StateDirs => format!("rm -rf -- {}\n", name),
+    Category::Kubeconfig | Category::TrustedCa => {
+      return Err(format!("{} items only exist on this machine", item.category.key()))
+    }
+    #[cfg(feature = "port-mapping")]
+    Category::PortMappings => return Err("Port mappings only exist on this machine's router".to_string()),
+  })
+}
+
+fn remove_remote(
+  workspace: &Workspace,
+  pool: &SshPool,
+  target: &SshTarget,
+  item: &UninstallItem,
+) -> Result<(), String> {
+  let output = ssh::run_script(workspace, pool, target, &remove_script(item)?, REMOVE_TIMEOUT)?;
+  if output.status != Some(0) {
+    return Err(
+      output
+        .stderr
+        .trim()
+        .lines()
+        .last()
+        .unwrap_or("command failed")
+        .to_string(),
+    );
+  }
+  if matches!(item.category, Category::Bridges | Category::SshKeys) {
+    pool.close(target);
+  }
+  Ok(())
+}
+
+/// Find what a previous installation left behind.
+#[tauri::command]
+pub async fn scan_installation(app: AppHandle, hosts: Vec<SshTarget>) -> Result<UninstallScan, String> {
+  for target in &hosts {
+    target.validate()?;
+  }
+  tauri::async_runtime::spawn_blocking(move || scan(&app, &hosts))
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Remove everything found in `categories`, reporting each item as it goes.
+#[tauri::command]
+pub async fn uninstall(
+  app: AppHandle,
+  hosts: Vec<SshTarget>,
+  categories: Vec<Category>,
+) -> Result<Vec<UninstallResult>, String> {
+  for target in &hosts {
+    target.validate()?;
+  }
+  if categories.is_empty() {
+    return Err("Select at least one category to remove".to_string());
+  }
+  tauri::async_runtime::spawn_blocking(move || {
+    let items: Vec<UninstallItem> = scan(&app, &hosts)
+      .items
+      .into_iter()
+      .filter(|i| categories.contains(&i.category))
+      .collect();
+    let workspace = app.state::<Workspace>();
+    let pool = app.state::<SshPool>();
+
+    let total = items.len();
+    let mut results = Vec::new();
+    let dry_run = dry_run::is_enabled(&app);
+    let mut backed_up = Vec::new();
+    for (index, item) in items.into_iter().enumerate() {
+      let outcome = match &item.host {
+        _ if dry_run => Ok(()),
+        Some(host) => match hosts.iter().find(|t| &t.host == host) {
+          Some(target) => remove_remote(&workspace, &pool, target, &item),
+          None => Err(format!("Unknown host {}", host)),
+        },
+        None => remove_local(&app, &item, &mut backed_up),
+      };
+      match &outcome {
+        Ok(()) if dry_run => info!("Dry run: would remove {}", item.id),
+        Ok(()) => info!("Removed {}", item.id),
+        Err(e) => error!("Failed to remove {}: {}", item.id, e),
+      }
+      let result = UninstallResult {
+        item,
+        success: outcome.is_ok(),
+        error: outcome.err(),
+      };
+      let _ = app.emit(
+        PROGRESS_EVENT,
+        UninstallProgress {
+          index,
+          total,
+          result: result.clone(),
+        },
+      );
+      results.push(result);
+    }
+    results
+  })
+  .await
+  .map_err(|e| e.to_string())
+}
@@ -0,0 +1,93 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Validators for identifiers that arrive from outside the wizard (deep
+//! links, imported files) or end up in inventories and playbook variables.
+
+/// A DNS domain name per RFC 1123: dot-separated labels of 1-63 letters,
+/// digits and hyphens, not starting or ending with a hyphen, 253 characters
+/// total, at least two labels, and a non-numeric TLD.
+pub fn is_valid_domain(domain: &str) -> bool {
+  let domain = domain.strip_suffix('.').unwrap_or(domain);
+  if domain.is_empty() || domain.len() > 253 {
+    return false;
+  }
+  let labels: Vec<&str> = domain.split('.').collect();
+  if labels.len() < 2 || !labels.iter().all(|l| is_valid_label(l)) {
+    return false;
+  }
+  labels
+    .last()
+    .map(|tld| !tld.chars().all(|c| c.is_ascii_digit()))
+    .unwrap_or(false)
+}
+
+/// A single DNS label (also the rule for a hostname without domain).
+pub fn is_valid_label(label: &str) -> bool {
+  !label.is_empty()
+    && label.len() <= 63
+    && !label.starts_with('-')
+    && !label.ends_with('-')
+    && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+/// A git branch, tag or commit name, following the rules of
+/// `git check-ref-format` that matter for a value we pass to `git checkout`.
+pub fn is_valid_git_ref(git_ref: &str) -> bool {
+  !git_ref.is_empty()
+    && git_ref.len() <= 255
+    && !git_ref.starts_with('-')
+    && !git_ref.starts_with('/')
+    && !git_ref.ends_with('/')
+    && !git_ref.ends_with('.')
+    && !git_ref.ends_with(".lock")
+    && !git_ref.contains("..")
+    && !git_ref.contains("//")
+    && !git_ref.contains("@{")
+    && git_ref
+      .chars()
+      .all(|c| !c.is_ascii_control() && !c.is_whitespace() && !"~^:?*[\\".contains(c))
+}
+
+/// Something we can connect to: an IPv4/IPv6 address, a bare hostname or a
+/// fully qualified domain name.
+pub fn is_valid_host(host: &str) -> bool {
+  host.parse::<std::net::IpAddr>().is_ok() || is_valid_label(host) || is_valid_domain(host)
+}
+
+/// A login name, as SSH and sudoers take it unquoted.
+pub fn is_valid_user(user: &str) -> bool {
+  !user.is_empty()
+    && !user.starts_with('-')
+    && user
+      .chars()
+      .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+}
+
+/// A network interface name, as the kernel accepts it.
+pub fn is_valid_interface(interface: &str) -> bool {
+  !interface.is_empty()
+    && interface.len() <= 15
+    && !interface.starts_with('-')
+    && interface
+      .chars()
+      .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+}
+
+/// A systemd service, socket or timer unit name, including template
+/// instances (`systemd-zram-setup@zram0.service`).
+pub fn is_valid_unit(unit: &str) -> bool {
+  let Some(name) = [".service", ".socket", ".timer"]
+    .iter()
+    .find_map(|suffix| unit.strip_suffix(suffix))
+  else {
+    return false;
+  };
+  !name.is_empty()
+    && !unit.starts_with('-')
+    && unit
+      .chars()
+      .all(|c| c.is_ascii_alphanumeric() || matches!(c, '@' | '.' | '_' | '-'))
+}
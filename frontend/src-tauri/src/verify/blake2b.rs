@@ -0,0 +1,196 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! BLAKE2b-512 (RFC 7693), which minisign signatures are made over.
+
+const IV: [u64; 8] = [
+  0x6a09e667f3bcc908,
+  0xbb67ae8584caa73b,
+  0x3c6ef372fe94f82b,
+  0xa54ff53a5f1d36f1,
+  0x510e527fade682d1,
+  0x9b05688c2b3e6c1f,
+  0x1f83d9abfb41bd6b,
+  0x5be0cd19137e2179,
+];
+
+const SIGMA: [[usize; 16]; 10] = [
+  [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+  [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+  [11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
+  [7, 9, 3, 1, 13, 12, 11, 14, 2, 6, 5, 10, 4, 0, 15, 8],
+  [9, 0, 5, 7, 2, 4, 10, 15, 14, 1, 11, 12, 6, 8, 3, 13],
+  [2, 12, 6, 10, 0, 11, 8, 3, 4, 13, 7, 5, 15, 14, 1, 9],
+  [12, 5, 1, 15, 14, 13, 4, 10, 0, 7, 6, 3, 9, 2, 8, 11],
+  [13, 11, 7, 14, 12, 1, 3, 9, 5, 0, 15, 4, 8, 6, 2, 10],
+  [6, 15, 14, 9, 11, 3, 0, 8, 12, 2, 13, 7, 1, 4, 10, 5],
+  [10, 2, 8, 4, 7, 6, 1, 5, 15, 11, 9, 14, 3, 12, 13, 0],
+];
+
+fn g(v: &mut [u64; 16], a: usize, b: usize, c: usize, d: usize, x: u64, y: u64) {
+  v[a] = v[a].wrapping_add(v[b]).wrapping_add(x);
+  v[d] = (v[d] ^ v[a]).rotate_right(32);
+  v[c] = v[c].wrapping_add(v[d]);
+  v[b] = (v[b] ^ v[c]).rotate_right(24);
+  v[a] = v[a].wrapping_add(v[b]).wrapping_add(y);
+  v[d] = (v[d] ^ v[a]).rotate_right(16);
+  v[c] = v[c].wrapping_add(v[d]);
+  v[b] = (v[b] ^ v[c]).rotate_right(63);
+}
+
+/// Incremental BLAKE2b-512, which minisign hashes files with.
+pub struct Blake2b {
+  h: [u64; 8],
+  buf: [u8; 128],
+  len: usize,
+  total: u128,
+}
+
+impl Default for Blake2b {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl Blake2b {
+  pub fn new() -> Self {
+    let mut h = IV;
+    // No key, 64-byte digest
+    h[0] ^= 0x0101_0040;
+    Blake2b {
+      h,
+      buf: [0; 128],
+      len: 0,
+      total: 0,
+    }
+  }
+
+  fn compress(&mut self, last: bool) {
+    let mut m = [0u64; 16];
+    for (i, word) in m.iter_mut().enumerate() {
+      let mut bytes = [0u8; 8];
+      bytes.copy_from_slice(&self.buf[i * 8..i * 8 + 8]);
+      *word = u64::from_le_bytes(bytes);
+    }
+    let mut v = [0u64; 16];
+    v[..8].copy_from_slice(&self.h);
+    v[8..].copy_from_slice(&IV);
+    v[12] ^= self.total as u64;
+    v[13] ^= (self.total >> 64) as u64;
+    if last {
+      v[14] = !v[14];
+    }
+    for round in 0..12 {
+      let s = &SIGMA[round % 10];
+      g(&mut v, 0, 4, 8, 12, m[s[0]], m[s[1]]);
+      g(&mut v, 1, 5, 9, 13, m[s[2]], m[s[3]]);
+      g(&mut v, 2, 6, 10, 14, m[s[4]], m[s[5]]);
+      g(&mut v, 3, 7, 11, 15, m[s[6]], m[s[7]]);
+      g(&mut v, 0, 5, 10, 15, m[s[8]], m[s[9]]);
+      g(&mut v, 1, 6, 11, 12, m[s[10]], m[s[11]]);
+      g(&mut v, 2, 7, 8, 13, m[s[12]], m[s[13]]);
+      g(&mut v, 3, 4, 9, 14, m[s[14]], m[s[15]]);
+    }
+    for i in 0..8 {
+      self.h[i] ^= v[i] ^ v[i + 8];
+    }
+  }
+
+  pub fn update(&mut self, mut data: &[u8]) {
+    while !data.is_empty() {
+      // The last block is compressed differently, so a full buffer waits
+      // until more data shows it wasn't the last
+      if self.len == 128 {
+        self.total += 128;
+        self.compress(false);
+        self.len = 0;
+      }
+      let n = (128 - self.len).min(data.len());
+      self.buf[self.len..self.len + n].copy_from_slice(&data[..n]);
+      self.len += n;
+      data = &data[n..];
+    }
+  }
+
+  pub fn finalize(mut self) -> [u8; 64] {
+    self.total += self.len as u128;
+    self.buf[self.len..].fill(0);
+    self.compress(true);
+    let mut out = [0u8; 64];
+    for (i, word) in self.h.iter().enumerate() {
+      out[i * 8..i * 8 + 8].copy_from_slice(&word.to_le_bytes());
+    }
+    out
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::Blake2b;
+
+  fn hex(data: &[u8]) -> String {
+    let mut hash = Blake2b::new();
+    hash.update(data);
+    hash.finalize().iter().map(|b| format!("{:02x}", b)).collect()
+  }
+
+  fn counting(len: usize) -> Vec<u8> {
+    (0..len).map(|i| i as u8).collect()
+  }
+
+  /// RFC 7693, appendix A
+  #[test]
+  fn abc() {
+    assert_eq!(
+      hex(b"abc"),
+      "ba80a53f981c4d0d6a2797b69f12f6e94c212f14685ac4b74b12bb6fdbffa2d1\
+       7d87c5392aab792dc252d5de4533cc9518d38aa8dbf1925ab92386edd4009923"
+    );
+  }
+
+  #[test]
+  fn empty() {
+    assert_eq!(
+      hex(b""),
+      "786a02f742015903c6c6fd852552d272912f4740e15847618a86e217f71f5419\
+       d25e1031afee585313896444934eb04b903a685b1448b755d56f701afe9be2ce"
+    );
+  }
+
+  /// A full block is only compressed as the last one when nothing follows
+  #[test]
+  fn block_boundary() {
+    assert_eq!(
+      hex(&counting(127)),
+      "b6292669ccd38d5f01caae96ba272c76a879a45743afa0725d83b9ebb26665b7\
+       31f1848c52f11972b6644f554c064fa90780dbbbf3a89d4fc31f67df3e5857ef"
+    );
+    assert_eq!(
+      hex(&counting(128)),
+      "2319e3789c47e2daa5fe807f61bec2a1a6537fa03f19ff32e87eecbfd64b7e0e\
+       8ccff439ac333b040f19b0c4ddd11a61e24ac1fe0f10a039806c5dcc0da3d115"
+    );
+    assert_eq!(
+      hex(&counting(129)),
+      "f59711d44a031d5f97a9413c065d1e614c417ede998590325f49bad2fd444d3e\
+       4418be19aec4e11449ac1a57207898bc57d76a1bcf3566292c20c683a5c4648f"
+    );
+  }
+
+  #[test]
+  fn split_updates() {
+    let data = counting(256);
+    for split in [1, 64, 127, 128, 129, 255] {
+      let mut hash = Blake2b::new();
+      hash.update(&data[..split]);
+      hash.update(&data[split..]);
+      assert_eq!(hash.finalize(), {
+        let mut whole = Blake2b::new();
+        whole.update(&data);
+        whole.finalize()
+      });
+    }
+  }
+}
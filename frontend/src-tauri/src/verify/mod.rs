@@ -0,0 +1,281 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Signature checks for anything the installer downloads and runs.
+//!
+//! Artifacts are signed with minisign, the same format the Tauri updater
+//! uses, and each one comes with a detached `<file>.minisig`. The public
+//! keys that may sign them are baked in at build time through
+//! `THINKUBE_BUILD_SIGNING_KEYS` (minisign public keys, separated by
+//! whitespace or commas); a build without any refuses every artifact
+//! rather than trusting whatever it is handed. Both the prehashed (`ED`,
+//! minisign's default) and legacy (`Ed`) signatures are accepted, and the
+//! trusted comment's global signature is checked too, so the comment a
+//! release was signed with can't be swapped. The comment's `file:` must
+//! name the artifact, as `minisign -S` writes it, so one signed file can't
+//! be passed off as another.
+//!
+//! Minisign rather than GPG or sigstore: it needs no keyring or network,
+//! the updater already depends on its keys, and verifying it takes an
+//! Ed25519 check `ring` provides. The same keys vouch for the rest of
+//! what an install pulls in: offline bundles carry a `.minisig` like
+//! downloads, the playbooks commit must carry an SSH signature from one
+//! of them (see [`allowed_signers`]), and pip only installs from
+//! hash-pinned locks that verify (see [`signed_lock`]). A development
+//! build without keys can't check those and warns instead of refusing,
+//! or it couldn't install at all.
+
+mod blake2b;
+
+use base64::Engine;
+use ring::signature::{UnparsedPublicKey, ED25519};
+use serde::Serialize;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use blake2b::Blake2b;
+
+pub const SIGNATURE_EXTENSION: &str = "minisig";
+/// Legacy signatures sign the whole file, which is read into memory
+const MAX_LEGACY_SIZE: u64 = 64 * 1024 * 1024;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VerifiedArtifact {
+  pub path: String,
+  /// Id of the key that signed it, as minisign prints it
+  pub key_id: String,
+  pub trusted_comment: String,
+  pub sha256: String,
+}
+
+struct PublicKey {
+  id: [u8; 8],
+  key: [u8; 32],
+}
+
+fn decode(text: &str) -> Result<Vec<u8>, String> {
+  base64::engine::general_purpose::STANDARD
+    .decode(text.trim())
+    .map_err(|e| format!("Invalid base64: {}", e))
+}
+
+fn hex(bytes: &[u8]) -> String {
+  bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// The way minisign prints key ids: the little-endian id as hex.
+fn key_id(id: &[u8; 8]) -> String {
+  format!("{:016X}", u64::from_le_bytes(*id))
+}
+
+/// `Ed` + key id + Ed25519 key, as minisign writes it.
+fn parse_public_key(text: &str) -> Result<PublicKey, String> {
+  let bytes = decode(text)?;
+  if bytes.len() != 42 || &bytes[..2] != b"Ed" {
+    return Err("Not a minisign public key".to_string());
+  }
+  let mut id = [0u8; 8];
+  let mut key = [0u8; 32];
+  id.copy_from_slice(&bytes[2..10]);
+  key.copy_from_slice(&bytes[10..]);
+  Ok(PublicKey { id, key })
+}
+
+/// Whether this build was made with any signing keys.
+pub fn has_keys() -> bool {
+  !trusted_keys().is_empty()
+}
+
+fn trusted_keys() -> Vec<PublicKey> {
+  option_env!("THINKUBE_BUILD_SIGNING_KEYS")
+    .unwrap_or_default()
+    .split([',', ' ', '\n', '\t'])
+    .filter(|k| !k.trim().is_empty())
+    .filter_map(|k| parse_public_key(k).ok())
+    .collect()
+}
+
+struct Signature {
+  prehashed: bool,
+  key_id: [u8; 8],
+  signature: Vec<u8>,
+  trusted_comment: String,
+  global_signature: Vec<u8>,
+}
+
+fn parse_signature(text: &str) -> Result<Signature, String> {
+  let mut lines = text.lines();
+  let malformed = || "Malformed signature file".to_string();
+  let _untrusted = lines
+    .next()
+    .filter(|l| l.starts_with("untrusted comment:"))
+    .ok_or_else(malformed)?;
+  let blob = decode(lines.next().ok_or_else(malformed)?)?;
+  let trusted_comment = lines
+    .next()
+    .and_then(|l| l.strip_prefix("trusted comment: "))
+    .ok_or_else(malformed)?
+    .to_string();
+  let global_signature = decode(lines.next().ok_or_else(malformed)?)?;
+  if blob.len() != 74 || global_signature.len() != 64 {
+    return Err(malformed());
+  }
+  let prehashed = match &blob[..2] {
+    b"ED" => true,
+    b"Ed" => false,
+    _ => return Err("Unsupported signature algorithm".to_string()),
+  };
+  let mut key_id = [0u8; 8];
+  key_id.copy_from_slice(&blob[2..10]);
+  Ok(Signature {
+    prehashed,
+    key_id,
+    signature: blob[10..].to_vec(),
+    trusted_comment,
+    global_signature,
+  })
+}
+
+/// The file's BLAKE2b-512 (for prehashed signatures) and SHA-256, in one
+/// pass.
+fn digests(path: &Path) -> Result<([u8; 64], String), String> {
+  let mut file = std::fs::File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+  let mut blake = Blake2b::new();
+  let mut sha = ring::digest::Context::new(&ring::digest::SHA256);
+  let mut buf = [0u8; 64 * 1024];
+  loop {
+    let n = file
+      .read(&mut buf)
+      .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    if n == 0 {
+      break;
+    }
+    blake.update(&buf[..n]);
+    sha.update(&buf[..n]);
+  }
+  Ok((blake.finalize(), hex(sha.finish().as_ref())))
+}
+
+pub fn signature_path(path: &Path) -> PathBuf {
+  let mut name = path.as_os_str().to_owned();
+  name.push(".");
+  name.push(SIGNATURE_EXTENSION);
+  PathBuf::from(name)
+}
+
+/// The `file:` field of a trusted comment.
+fn signed_file_name(trusted_comment: &str) -> Option<&str> {
+  trusted_comment.split('\t').find_map(|field| field.strip_prefix("file:"))
+}
+
+/// Check `path` against its `.minisig` and the embedded keys. Any error
+/// means the file must not be used.
+pub fn verify(path: &Path) -> Result<VerifiedArtifact, String> {
+  let name = path
+    .file_name()
+    .ok_or_else(|| format!("{} is not a file", path.display()))?
+    .to_string_lossy()
+    .into_owned();
+  verify_as(path, &name)
+}
+
+/// [`verify`] for a file that was signed as `name`, e.g. a download still
+/// under its `.part` name.
+pub fn verify_as(path: &Path, name: &str) -> Result<VerifiedArtifact, String> {
+  let sig_path = signature_path(path);
+  let text = std::fs::read_to_string(&sig_path)
+    .map_err(|e| format!("{} is not signed ({}: {})", path.display(), sig_path.display(), e))?;
+  let signature = parse_signature(&text)?;
+  let keys = trusted_keys();
+  if keys.is_empty() {
+    return Err("This build has no signing keys, so it can't verify downloads".to_string());
+  }
+  let key = keys.iter().find(|k| k.id == signature.key_id).ok_or_else(|| {
+    format!(
+      "{} is signed with an unknown key ({})",
+      path.display(),
+      key_id(&signature.key_id)
+    )
+  })?;
+  let key = UnparsedPublicKey::new(&ED25519, key.key);
+
+  let (blake, sha256) = digests(path)?;
+  let signed = if signature.prehashed {
+    key.verify(&blake, &signature.signature)
+  } else {
+    let size = std::fs::metadata(path).map(|m| m.len()).unwrap_or(u64::MAX);
+    if size > MAX_LEGACY_SIZE {
+      return Err(format!("{} is too large for a legacy signature", path.display()));
+    }
+    let contents = std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    key.verify(&contents, &signature.signature)
+  };
+  signed.map_err(|_| {
+    format!(
+      "{} doesn't match its signature; it may have been tampered with",
+      path.display()
+    )
+  })?;
+
+  let mut global = signature.signature.clone();
+  global.extend_from_slice(signature.trusted_comment.as_bytes());
+  key
+    .verify(&global, &signature.global_signature)
+    .map_err(|_| format!("The trusted comment of {} was tampered with", sig_path.display()))?;
+  match signed_file_name(&signature.trusted_comment) {
+    Some(signed) if signed == name => {}
+    Some(signed) => return Err(format!("{} was signed as {}, not {}", path.display(), signed, name)),
+    None => return Err(format!("The signature of {} doesn't say which file it is for", path.display())),
+  }
+
+  Ok(VerifiedArtifact {
+    path: path.display().to_string(),
+    key_id: key_id(&signature.key_id),
+    trusted_comment: signature.trusted_comment,
+    sha256,
+  })
+}
+
+/// The hash-pinned requirements at `path`, once their `.minisig` checks
+/// out, for `pip install --require-hashes`. `None` when this build has no
+/// keys to check them with, and callers fall back to unpinned installs.
+pub fn signed_lock(path: &Path) -> Result<Option<PathBuf>, String> {
+  if !has_keys() {
+    return Ok(None);
+  }
+  verify(path)?;
+  Ok(Some(path.to_path_buf()))
+}
+
+/// The signing keys as a git `gpg.ssh.allowedSignersFile`, so
+/// `git verify-commit` accepts commits signed with them. An Ed25519
+/// minisign key is the same key an `ssh-ed25519` line carries. `None`
+/// without keys.
+pub fn allowed_signers() -> Option<String> {
+  let keys = trusted_keys();
+  if keys.is_empty() {
+    return None;
+  }
+  let lines = keys.iter().map(|key| {
+    let mut blob = Vec::new();
+    for field in [&b"ssh-ed25519"[..], &key.key[..]] {
+      blob.extend_from_slice(&(field.len() as u32).to_be_bytes());
+      blob.extend_from_slice(field);
+    }
+    format!(
+      "thinkube-release namespaces=\"git\" ssh-ed25519 {}\n",
+      base64::engine::general_purpose::STANDARD.encode(blob)
+    )
+  });
+  Some(lines.collect())
+}
+
+/// Verify a file already on disk against its `.minisig`.
+#[tauri::command]
+pub async fn verify_artifact(path: String) -> Result<VerifiedArtifact, String> {
+  tauri::async_runtime::spawn_blocking(move || verify(Path::new(&path)))
+    .await
+    .map_err(|e| e.to_string())?
+}
@@ -0,0 +1,323 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Secondary windows created and owned by the Rust shell.
+//!
+//! The splash window is shown while the backend boots and closed once the
+//! main window is ready. The log viewer is created lazily the first time
+//! it's opened and then only hidden when the user closes it, so its
+//! scrollback survives; it's destroyed together with the main window. The
+//! main window's size, position and monitor are saved in settings.json
+//! when it closes and restored the next time it's shown, on the primary
+//! monitor if the saved one is gone. A zoom set with `set_zoom` applies to
+//! every window and is restored too. Until startup reveals the main window
+//! nothing else (the tray, a notification, a second launch) can show it,
+//! so it never appears half set up behind the splash.
+
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::{
+  AppHandle, Manager, Monitor, PhysicalPosition, PhysicalSize, State, WebviewUrl, WebviewWindowBuilder, WindowEvent,
+};
+use tracing::{info, warn};
+
+use crate::i18n;
+use crate::settings::SettingsStore;
+
+pub const MAIN_WINDOW: &str = "main";
+pub const LOGS_WINDOW: &str = "logs";
+pub const SPLASH_WINDOW: &str = "splash";
+
+/// Show the splash window. It's a static page so it paints immediately.
+pub fn show_splash(app: &AppHandle) -> tauri::Result<()> {
+  if app.get_webview_window(SPLASH_WINDOW).is_some() {
+    return Ok(());
+  }
+  WebviewWindowBuilder::new(app, SPLASH_WINDOW, WebviewUrl::App("splash.html".into()))
+    .title("Thinkube Installer")
+    .inner_size(420.0, 280.0)
+    .resizable(false)
+    .decorations(false)
+    .center()
+    .build()?;
+  Ok(())
+}
+
+/// Update the status line on the splash window, if it's still open.
+pub fn set_splash_status(app: &AppHandle, status: &str) {
+  if let Some(window) = app.get_webview_window(SPLASH_WINDOW) {
+    let text = serde_json::to_string(status).unwrap_or_default();
+    let _ = window.eval(format!(
+      "(function(){{var el=document.getElementById('status');if(el)el.textContent={};}})()",
+      text
+    ));
+  }
+}
+
+pub fn close_splash(app: &AppHandle) {
+  if let Some(window) = app.get_webview_window(SPLASH_WINDOW) {
+    let _ = window.destroy();
+  }
+}
+
+/// Show the log viewer, creating it on first use.
+pub fn show_logs(app: &AppHandle) -> tauri::Result<()> {
+  if let Some(window) = app.get_webview_window(LOGS_WINDOW) {
+    window.show()?;
+    window.unminimize()?;
+    return window.set_focus();
+  }
+
+  let window = WebviewWindowBuilder::new(app, LOGS_WINDOW, WebviewUrl::App("logs".into()))
+    .title(i18n::t("logs-window-title"))
+    .inner_size(1000.0, 700.0)
+    .min_inner_size(600.0, 400.0)
+    .build()?;
+
+  restore_zoom(app, LOGS_WINDOW);
+  let handle = window.clone();
+  window.on_window_event(move |event| {
+    if let WindowEvent::CloseRequested { api, .. } = event {
+      // Keep the webview (and its scrollback) around; just hide it
+      api.prevent_close();
+      let _ = handle.hide();
+    }
+  });
+  Ok(())
+}
+
+pub fn hide_logs(app: &AppHandle) -> tauri::Result<()> {
+  match app.get_webview_window(LOGS_WINDOW) {
+    Some(window) => window.hide(),
+    None => Ok(()),
+  }
+}
+
+/// Destroy secondary windows so the app can exit when the main window closes.
+pub fn close_secondary(app: &AppHandle) {
+  close_splash(app);
+  if let Some(window) = app.get_webview_window(LOGS_WINDOW) {
+    let _ = window.destroy();
+  }
+}
+
+/// Main window placement, in physical pixels.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WindowGeometry {
+  pub x: i32,
+  pub y: i32,
+  pub width: u32,
+  pub height: u32,
+  pub maximized: bool,
+  /// Name of the monitor the window was on
+  pub monitor: Option<String>,
+}
+
+/// Smallest part of the title bar that must stay on a monitor
+const MIN_VISIBLE: i32 = 100;
+const MIN_ZOOM: f64 = 0.5;
+const MAX_ZOOM: f64 = 3.0;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MonitorInfo {
+  pub name: Option<String>,
+  pub x: i32,
+  pub y: i32,
+  pub width: u32,
+  pub height: u32,
+  pub scale_factor: f64,
+  /// The main window is on this monitor
+  pub current: bool,
+  pub primary: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DisplayInfo {
+  pub monitors: Vec<MonitorInfo>,
+  /// Scale factor of the monitor the main window is on
+  pub window_scale_factor: f64,
+  /// Webview zoom set with `set_zoom`, 1.0 by default
+  pub zoom: f64,
+}
+
+fn on_monitor(monitor: &Monitor, x: i32, y: i32, width: u32) -> bool {
+  let pos = monitor.position();
+  let size = monitor.size();
+  let right = pos.x + size.width as i32;
+  let bottom = pos.y + size.height as i32;
+  x + width as i32 - MIN_VISIBLE > pos.x && x + MIN_VISIBLE < right && y >= pos.y && y + MIN_VISIBLE < bottom
+}
+
+/// Remember where the main window is, e.g. before it closes.
+pub fn save_main_geometry(app: &AppHandle) {
+  let Some(window) = app.get_webview_window(MAIN_WINDOW) else {
+    return;
+  };
+  let maximized = window.is_maximized().unwrap_or(false);
+  let (Ok(position), Ok(size)) = (window.outer_position(), window.inner_size()) else {
+    return;
+  };
+  if size.width == 0 || size.height == 0 {
+    // Minimized; keep the last real placement
+    return;
+  }
+  let geometry = WindowGeometry {
+    x: position.x,
+    y: position.y,
+    width: size.width,
+    height: size.height,
+    maximized,
+    monitor: window.current_monitor().ok().flatten().and_then(|m| m.name().cloned()),
+  };
+  if let Some(settings) = app.try_state::<SettingsStore>() {
+    if let Err(e) = settings.update(|s| s.main_window = Some(geometry)) {
+      warn!("Failed to save the window placement: {}", e);
+    }
+  }
+}
+
+/// Put the main window back where it was last closed, or center it.
+pub fn restore_main_geometry(app: &AppHandle) {
+  let Some(window) = app.get_webview_window(MAIN_WINDOW) else {
+    return;
+  };
+  let saved = app.try_state::<SettingsStore>().and_then(|s| s.get().main_window);
+  let monitors = window.available_monitors().unwrap_or_default();
+  let Some(geometry) = saved else {
+    let _ = window.center();
+    return;
+  };
+  // The saved monitor by name, else any monitor the saved spot is still on
+  let monitor = monitors
+    .iter()
+    .find(|m| geometry.monitor.is_some() && m.name() == geometry.monitor.as_ref())
+    .filter(|m| on_monitor(m, geometry.x, geometry.y, geometry.width))
+    .or_else(|| {
+      monitors
+        .iter()
+        .find(|m| on_monitor(m, geometry.x, geometry.y, geometry.width))
+    });
+  match monitor {
+    Some(_) => {
+      let _ = window.set_size(PhysicalSize::new(geometry.width, geometry.height));
+      let _ = window.set_position(PhysicalPosition::new(geometry.x, geometry.y));
+    }
+    None => {
+      info!(
+        "Monitor {} is gone; centering the window on the primary monitor",
+        geometry.monitor.as_deref().unwrap_or("of the last session")
+      );
+      let primary = window.primary_monitor().ok().flatten();
+      let (width, height) = primary
+        .as_ref()
+        .map(|m| (m.size().width, m.size().height))
+        .unwrap_or((geometry.width, geometry.height));
+      let _ = window.set_size(PhysicalSize::new(geometry.width.min(width), geometry.height.min(height)));
+      let _ = window.center();
+    }
+  }
+  if geometry.maximized {
+    let _ = window.maximize();
+  }
+}
+
+/// Apply the saved zoom to a newly created window.
+pub fn restore_zoom(app: &AppHandle, label: &str) {
+  let Some(zoom) = app.try_state::<SettingsStore>().and_then(|s| s.get().zoom) else {
+    return;
+  };
+  if let Some(window) = app.get_webview_window(label) {
+    let _ = window.set_zoom(zoom.clamp(MIN_ZOOM, MAX_ZOOM));
+  }
+}
+
+/// Set once startup has swapped the splash for the main window
+static MAIN_REVEALED: AtomicBool = AtomicBool::new(false);
+
+/// Whether startup is done and the main window may be shown.
+pub fn main_revealed() -> bool {
+  MAIN_REVEALED.load(Ordering::SeqCst)
+}
+
+/// Show the main window for the first time, once startup is done.
+pub fn reveal_main(app: &AppHandle) -> tauri::Result<()> {
+  MAIN_REVEALED.store(true, Ordering::SeqCst);
+  show_main(app)
+}
+
+/// Bring the main window forward; does nothing while startup is still on
+/// the splash, as the window is shown when that ends anyway.
+pub fn show_main(app: &AppHandle) -> tauri::Result<()> {
+  if !main_revealed() {
+    return Ok(());
+  }
+  match app.get_webview_window(MAIN_WINDOW) {
+    Some(window) => {
+      window.show()?;
+      window.unminimize()?;
+      window.set_focus()
+    }
+    None => Ok(()),
+  }
+}
+
+#[tauri::command]
+pub fn open_logs_window(app: AppHandle) -> Result<(), String> {
+  show_logs(&app).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn hide_logs_window(app: AppHandle) -> Result<(), String> {
+  hide_logs(&app).map_err(|e| e.to_string())
+}
+
+/// Each monitor's geometry and scale factor, and which one has the window.
+#[tauri::command]
+pub fn get_display_info(app: AppHandle, settings: State<'_, SettingsStore>) -> Result<DisplayInfo, String> {
+  let window = app
+    .get_webview_window(MAIN_WINDOW)
+    .ok_or("The main window is not open")?;
+  let current = window.current_monitor().map_err(|e| e.to_string())?;
+  let primary = window.primary_monitor().map_err(|e| e.to_string())?;
+  let same = |a: &Option<Monitor>, b: &Monitor| {
+    a.as_ref()
+      .is_some_and(|a| a.name() == b.name() && a.position() == b.position())
+  };
+  let monitors = window
+    .available_monitors()
+    .map_err(|e| e.to_string())?
+    .into_iter()
+    .map(|m| MonitorInfo {
+      name: m.name().cloned(),
+      x: m.position().x,
+      y: m.position().y,
+      width: m.size().width,
+      height: m.size().height,
+      scale_factor: m.scale_factor(),
+      current: same(&current, &m),
+      primary: same(&primary, &m),
+    })
+    .collect();
+  Ok(DisplayInfo {
+    monitors,
+    window_scale_factor: window.scale_factor().map_err(|e| e.to_string())?,
+    zoom: settings.get().zoom.unwrap_or(1.0),
+  })
+}
+
+/// Zoom every window by `factor` (0.5 to 3) and remember it.
+#[tauri::command]
+pub fn set_zoom(app: AppHandle, settings: State<'_, SettingsStore>, factor: f64) -> Result<f64, String> {
+  if !factor.is_finite() {
+    return Err(format!("Invalid zoom factor: {}", factor));
+  }
+  let factor = factor.clamp(MIN_ZOOM, MAX_ZOOM);
+  for window in app.webview_windows().values() {
+    window.set_zoom(factor).map_err(|e| e.to_string())?;
+  }
+  settings.update(|s| s.zoom = Some(factor).filter(|&f| f != 1.0))?;
+  info!("Zoom set to {}", factor);
+  Ok(factor)
+}
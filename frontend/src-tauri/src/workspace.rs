@@ -0,0 +1,202 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Per-run work directory for generated artifacts.
+//!
+//! Inventories, rendered cloud-init files and temporary keys are written
+//! below `<profile cache dir>/runs/<run id>/`, which is created with mode 0700 so
+//! nothing generated during a run is readable by other local users. The
+//! directory is removed when the run finishes successfully and kept for
+//! inspection otherwise.
+
+use serde::Serialize;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, State};
+use tracing::info;
+
+use crate::journal::Journal;
+use crate::profiles;
+
+pub struct Workspace {
+  run_id: String,
+  root: PathBuf,
+  finished: Mutex<bool>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkspaceInfo {
+  pub run_id: String,
+  pub path: String,
+}
+
+impl Workspace {
+  /// Create the work directory for a new run below the app cache dir.
+  pub fn create(app: &AppHandle) -> Result<Self, String> {
+    let base = profiles::cache_dir(app)?.join("runs");
+    let run_id = new_run_id();
+    let root = base.join(&run_id);
+
+    create_private_dir(&base)?;
+    create_private_dir(&root)?;
+
+    Ok(Self {
+      run_id,
+      root,
+      finished: Mutex::new(false),
+    })
+  }
+
+  pub fn run_id(&self) -> &str {
+    &self.run_id
+  }
+
+  pub fn root(&self) -> &Path {
+    &self.root
+  }
+
+  /// Resolve a relative path inside the workspace, creating its parent
+  /// directories. Absolute paths and `..` components are rejected so callers
+  /// can't escape the run directory.
+  pub fn resolve(&self, relative: &str) -> Result<PathBuf, String> {
+    let relative = Path::new(relative);
+    if relative.as_os_str().is_empty() {
+      return Err("Workspace path must not be empty".to_string());
+    }
+    if relative
+      .components()
+      .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+    {
+      return Err(format!(
+        "Workspace path must be relative and stay inside the run directory: {}",
+        relative.display()
+      ));
+    }
+
+    let path = self.root.join(relative);
+    if let Some(parent) = path.parent() {
+      let mut dir = self.root.clone();
+      for component in parent.strip_prefix(&self.root).unwrap_or(Path::new("")).components() {
+        dir.push(component);
+        create_private_dir(&dir)?;
+      }
+    }
+    Ok(path)
+  }
+
+  /// Mark the run as finished. Successful runs have their directory removed;
+  /// failed runs keep it so generated files can be inspected.
+  pub fn finish(&self, success: bool) -> Result<(), String> {
+    let mut finished = self.finished.lock().map_err(|e| e.to_string())?;
+    if *finished {
+      return Ok(());
+    }
+    *finished = true;
+
+    if success && self.root.exists() {
+      std::fs::remove_dir_all(&self.root)
+        .map_err(|e| format!("Failed to remove {}: {}", self.root.display(), e))?;
+      info!("Removed run workspace {}", self.root.display());
+    } else {
+      info!("Keeping run workspace {}", self.root.display());
+    }
+    Ok(())
+  }
+}
+
+fn new_run_id() -> String {
+  let now = SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .unwrap_or_default();
+  format!(
+    "{}-{:05x}{:04x}",
+    now.as_secs(),
+    now.subsec_micros(),
+    std::process::id() & 0xffff
+  )
+}
+
+/// Create a directory (if missing) and restrict it to the current user.
+pub fn create_private_dir(path: &Path) -> Result<(), String> {
+  let mut builder = std::fs::DirBuilder::new();
+  builder.recursive(true);
+  #[cfg(unix)]
+  {
+    use std::os::unix::fs::DirBuilderExt;
+    builder.mode(0o700);
+  }
+  builder
+    .create(path)
+    .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+
+  // DirBuilder's mode is subject to the umask and doesn't apply to
+  // directories that already existed, so set it explicitly.
+  #[cfg(unix)]
+  {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o700))
+      .map_err(|e| format!("Failed to set permissions on {}: {}", path.display(), e))?;
+  }
+  Ok(())
+}
+
+/// Write `contents` to `path`, readable and writable by the current user only.
+pub fn write_private_file(path: &Path, contents: &[u8]) -> Result<(), String> {
+  use std::io::Write;
+
+  if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+    std::fs::create_dir_all(parent)
+      .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+  }
+
+  let mut options = std::fs::OpenOptions::new();
+  options.write(true).create(true).truncate(true);
+  #[cfg(unix)]
+  {
+    use std::os::unix::fs::OpenOptionsExt;
+    options.mode(0o600);
+  }
+  let mut file = options
+    .open(path)
+    .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+
+  // mode() only applies when the file is created; tighten existing files too
+  #[cfg(unix)]
+  {
+    use std::os::unix::fs::PermissionsExt;
+    file
+      .set_permissions(std::fs::Permissions::from_mode(0o600))
+      .map_err(|e| format!("Failed to set permissions on {}: {}", path.display(), e))?;
+  }
+
+  file
+    .write_all(contents)
+    .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+#[tauri::command]
+pub fn get_workspace(workspace: State<'_, Workspace>) -> WorkspaceInfo {
+  WorkspaceInfo {
+    run_id: workspace.run_id().to_string(),
+    path: workspace.root().display().to_string(),
+  }
+}
+
+#[tauri::command]
+pub fn resolve_workspace_path(workspace: State<'_, Workspace>, relative: String) -> Result<String, String> {
+  workspace
+    .resolve(&relative)
+    .map(|path| path.display().to_string())
+}
+
+/// A successful run also has nothing left to roll back.
+#[tauri::command]
+pub fn finish_run(workspace: State<'_, Workspace>, journal: State<'_, Journal>, success: bool) -> Result<(), String> {
+  if success {
+    journal.clear()?;
+  }
+  workspace.finish(success)
+}
@@ -13,6 +13,8 @@ import { TkLabel } from "thinkube-style/components/forms-inputs"
 import { Check, Loader2, Copy, Info, XCircle } from "lucide-react"
 import { getAnsibleLogClassName, getAnsibleLogPrefix } from "@/lib/ansible-log-utils"
 import { useCopyToClipboard } from "@/lib/use-copy-to-clipboard"
+import { withApiToken } from "@/utils/axios"
+import { invoke } from "@tauri-apps/api/core"
 
 interface PlaybookExecutorProps {
   title: string
@@ -146,6 +148,10 @@ ${logOutput.map(log => log.message).join('\n')}`
       setTaskSummary({ total: 0, ok: 0, changed: 0, skipped: 0, failed: 0 })
       seenTasksRef.current = new Set()
       startTimeRef.current = Date.now()
+      // The shell asks before closing the window while this runs
+      invoke('set_install_running', { running: true, step: title }).catch((error) =>
+        console.error('Failed to report running step:', error)
+      )
       lastOutputAtRef.current = Date.now()
       setNow(Date.now())
 
@@ -156,22 +162,22 @@ ${logOutput.map(log => log.message).join('\n')}`
     const connectWebSocket = async (params: any) => {
       const encodedPlaybookName = encodeURIComponent(playbookName)
 
-      // In Tauri, we need to connect directly to localhost:8000
+      // In Tauri, we need to connect directly to 127.0.0.1:8000
       const isTauri = typeof window !== 'undefined' && window.location.protocol === 'tauri:'
       const wsBase =
         isTauri ||
         (typeof window !== 'undefined' &&
           window.location.protocol === 'http:' &&
           window.location.hostname === 'localhost')
-          ? 'ws://localhost:8000'
+          ? 'ws://127.0.0.1:8000'
           : typeof window !== 'undefined'
           ? `${window.location.protocol === 'https:' ? 'wss:' : 'ws:'}//${window.location.host}`
-          : 'ws://localhost:8000'
+          : 'ws://127.0.0.1:8000'
 
       const wsUrl = `${wsBase}/ws/playbook/${encodedPlaybookName}`
 
       try {
-        const ws = new WebSocket(wsUrl)
+        const ws = new WebSocket(await withApiToken(wsUrl))
         websocketRef.current = ws
 
         // Store params in closure to ensure they're available in onopen
@@ -460,10 +466,39 @@ ${logOutput.map(log => log.message).join('\n')}`
       }
     }
 
+    // Add the run to the installation transcript for the exported report
+    const recordStep = (result: any) => {
+      const failedLines = logOutputRef.current
+        .filter(log => log.type === 'failed' || log.type === 'error')
+        .map(log => log.message)
+      const outputs: Record<string, string> = {
+        tasks: `${taskSummary.total} total, ${taskSummary.ok} ok, ${taskSummary.changed} changed, ${taskSummary.skipped} skipped, ${taskSummary.failed} failed`,
+      }
+      if (currentTask) outputs.last_task = currentTask
+      if (failedLines.length > 0) outputs.errors = failedLines.slice(-20).join('\n')
+      const outcome =
+        result.status === 'success' ? 'success' : result.status === 'cancelled' ? 'cancelled' : 'failure'
+      invoke('record_step', {
+        record: {
+          step: playbookName,
+          title,
+          started_at: Math.round(startTimeRef.current || Date.now()),
+          duration_ms: Math.round((result.duration ?? 0) * 1000),
+          outcome,
+          message: result.message ?? null,
+          outputs,
+        },
+      }).catch((error) => console.error('Failed to record step:', error))
+    }
+
     const completeExecution = (result: any) => {
       setIsExecuting(false)
+      invoke('set_install_running', { running: false, step: title }).catch((error) =>
+        console.error('Failed to report finished step:', error)
+      )
       websocketRef.current?.close()
       websocketRef.current = null
+      recordStep(result)
 
       // Call onComplete prop if provided
       // Use logOutputRef instead of logOutput state to get synchronous access to latest logs
@@ -491,6 +526,7 @@ ${logOutput.map(log => log.message).join('\n')}`
         duration,
         logs: logOutputRef.current.map(log => log.message).join('\n')
       }
+      recordStep(result)
       if (onComplete) {
         onComplete(result)
       }
@@ -0,0 +1,89 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+import { useState, useEffect } from "react"
+import { invoke } from "@tauri-apps/api/core"
+import { listen } from "@tauri-apps/api/event"
+import { TkAlert, TkAlertDescription } from "thinkube-style/components/feedback"
+import { TkButton } from "thinkube-style/components/buttons-badges"
+import { AlertCircle, Loader2 } from "lucide-react"
+
+// Shape of the `backend-status` event emitted by the Rust shell
+type BackendStatus =
+  | { phase: "starting" | "installing_dependencies" | "waiting_for_health" | "ready" }
+  | { phase: "retrying"; attempt: number; max_attempts: number; error: string; retry_in_ms: number }
+  | { phase: "unresponsive"; failures: number }
+  | { phase: "failed"; error: string }
+
+const PHASE_TEXT: Record<string, string> = {
+  starting: "Starting backend…",
+  installing_dependencies: "Installing dependencies… (first run only)",
+  waiting_for_health: "Waiting for backend…",
+}
+
+export default function BackendStatusBanner() {
+  const [status, setStatus] = useState<BackendStatus | null>(null)
+
+  useEffect(() => {
+    let unlisten: (() => void) | undefined
+
+    invoke<BackendStatus>("get_backend_status")
+      .then((initial) => setStatus(initial))
+      .catch((error) => console.error("Failed to load backend status:", error))
+
+    listen<BackendStatus>("backend-status", (event) => {
+      setStatus(event.payload)
+    }).then((fn) => {
+      unlisten = fn
+    })
+
+    return () => unlisten?.()
+  }, [])
+
+  if (!status || status.phase === "ready") {
+    return null
+  }
+
+  if (status.phase === "failed") {
+    return (
+      <TkAlert variant="destructive" className="m-4">
+        <AlertCircle className="h-4 w-4" />
+        <TkAlertDescription>{status.error}</TkAlertDescription>
+      </TkAlert>
+    )
+  }
+
+  if (status.phase === "unresponsive") {
+    return (
+      <TkAlert variant="destructive" className="m-4">
+        <AlertCircle className="h-4 w-4" />
+        <TkAlertDescription className="flex items-center justify-between gap-4">
+          <span>The backend is not responding. It may be stuck on a playbook step.</span>
+          <TkButton
+            size="sm"
+            intent="secondary"
+            onClick={() =>
+              invoke("restart_backend").catch((error) => console.error("Failed to restart backend:", error))
+            }
+          >
+            Restart backend
+          </TkButton>
+        </TkAlertDescription>
+      </TkAlert>
+    )
+  }
+
+  const text =
+    status.phase === "retrying"
+      ? `Backend did not start (${status.error}), retrying (attempt ${status.attempt + 1} of ${status.max_attempts})…`
+      : PHASE_TEXT[status.phase]
+
+  return (
+    <TkAlert className="m-4 bg-info/10 text-info border-info/20">
+      <Loader2 className="h-4 w-4 animate-spin" />
+      <TkAlertDescription>{text}</TkAlertDescription>
+    </TkAlert>
+  )
+}
@@ -0,0 +1,91 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+import { useState, useEffect, useCallback } from "react"
+import { invoke } from "@tauri-apps/api/core"
+import { TkAlert, TkAlertDescription } from "thinkube-style/components/feedback"
+import { TkButton } from "thinkube-style/components/buttons-badges"
+import { AlertCircle, CheckCircle2, Loader2 } from "lucide-react"
+
+// Shape returned by the `check_connectivity` command
+type ConnectivityReport = {
+  status: "online" | "slow" | "captive_portal" | "tls_intercepted" | "no_dns" | "offline"
+  message: string
+  latency_ms: number | null
+  portal_url: string | null
+  tls_issuer: string | null
+}
+
+export default function ConnectivityStatus() {
+  const [report, setReport] = useState<ConnectivityReport | null>(null)
+  const [checking, setChecking] = useState(false)
+
+  const check = useCallback(async () => {
+    setChecking(true)
+    try {
+      setReport(await invoke<ConnectivityReport>("check_connectivity"))
+    } catch (error) {
+      console.error("Failed to check connectivity:", error)
+    } finally {
+      setChecking(false)
+    }
+  }, [])
+
+  useEffect(() => {
+    check()
+  }, [check])
+
+  if (checking && !report) {
+    return (
+      <TkAlert className="bg-info/10 text-info border-info/20">
+        <Loader2 className="h-4 w-4 animate-spin" />
+        <TkAlertDescription>Checking internet connectivity…</TkAlertDescription>
+      </TkAlert>
+    )
+  }
+
+  if (!report) {
+    return null
+  }
+
+  if (report.status === "online") {
+    return (
+      <TkAlert className="bg-success/10 text-success border-success/20">
+        <CheckCircle2 className="h-4 w-4" />
+        <TkAlertDescription>
+          {report.message}
+          {report.latency_ms !== null && ` (${report.latency_ms} ms)`}
+        </TkAlertDescription>
+      </TkAlert>
+    )
+  }
+
+  const severe = report.status !== "slow"
+  return (
+    <TkAlert
+      className={
+        severe
+          ? "bg-destructive/10 text-destructive border-destructive/20"
+          : "bg-warning/10 text-warning border-warning/20"
+      }
+    >
+      <AlertCircle className="h-4 w-4" />
+      <TkAlertDescription>
+        <div className="flex items-center justify-between gap-4">
+          <div>
+            <div>{report.message}</div>
+            {report.portal_url && (
+              <div className="text-sm opacity-80 break-all">Login page: {report.portal_url}</div>
+            )}
+          </div>
+          <TkButton size="sm" intent="secondary" onClick={check} disabled={checking}>
+            {checking && <Loader2 className="h-4 w-4 animate-spin" />}
+            Check again
+          </TkButton>
+        </div>
+      </TkAlertDescription>
+    </TkAlert>
+  )
+}
@@ -0,0 +1,116 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+import { useState, useEffect } from "react"
+import { invoke } from "@tauri-apps/api/core"
+import { listen } from "@tauri-apps/api/event"
+import { TkButton } from "thinkube-style/components/buttons-badges"
+import { CheckCircle2, Loader2, ShieldCheck } from "lucide-react"
+
+// Shapes returned by the `*_domain_challenge` commands
+type DomainChallenge = {
+  domain: string
+  record_name: string
+  value: string
+  created_at: number
+  published: boolean
+}
+
+type VerificationRound = {
+  domain: string
+  attempt: number
+  verified: boolean
+  answers: { resolver: string; found: boolean; values: string[]; error: string | null }[]
+  elapsed_secs: number
+}
+
+interface DomainVerificationProps {
+  domain: string
+  // When given, the TXT record is created through Cloudflare
+  cloudflareToken?: string
+  onVerified?: () => void
+}
+
+export default function DomainVerification({ domain, cloudflareToken, onVerified }: DomainVerificationProps) {
+  const [challenge, setChallenge] = useState<DomainChallenge | null>(null)
+  const [round, setRound] = useState<VerificationRound | null>(null)
+  const [running, setRunning] = useState(false)
+  const [error, setError] = useState<string | null>(null)
+
+  useEffect(() => {
+    let unlisten: (() => void) | undefined
+    listen<VerificationRound>("domain-verification", (event) => {
+      if (event.payload.domain === domain) setRound(event.payload)
+    }).then((fn) => {
+      unlisten = fn
+    })
+    return () => unlisten?.()
+  }, [domain])
+
+  // A different domain needs a new challenge
+  useEffect(() => {
+    setChallenge(null)
+    setRound(null)
+    setError(null)
+  }, [domain])
+
+  const verify = async () => {
+    setRunning(true)
+    setError(null)
+    try {
+      let current = await invoke<DomainChallenge>("create_domain_challenge", { domain })
+      if (cloudflareToken && !current.published) {
+        current = await invoke<DomainChallenge>("publish_domain_challenge", { domain, cloudflareToken })
+      }
+      setChallenge(current)
+      const result = await invoke<VerificationRound>("verify_domain_challenge", { domain })
+      setRound(result)
+      if (result.verified) {
+        onVerified?.()
+        await invoke("clear_domain_challenge", { domain, cloudflareToken: cloudflareToken || null })
+      } else {
+        setError("None of the resolvers returned the TXT record yet. DNS changes can take a few minutes; try again.")
+      }
+    } catch (e: any) {
+      setError(typeof e === "string" ? e : "Domain verification failed")
+    } finally {
+      setRunning(false)
+    }
+  }
+
+  if (round?.verified) {
+    return (
+      <p className="text-xs text-success flex items-center gap-1">
+        <CheckCircle2 className="h-3 w-3" /> You control {domain}
+      </p>
+    )
+  }
+
+  return (
+    <div className="space-y-2">
+      <TkButton intent="secondary" size="sm" className="gap-2" onClick={verify} disabled={running || !domain}>
+        {running ? <Loader2 className="h-4 w-4 animate-spin" /> : <ShieldCheck className="h-4 w-4" />}
+        {running ? "Verifying domain…" : "Verify domain ownership"}
+      </TkButton>
+
+      {challenge && !challenge.published && (
+        <div className="text-xs space-y-1">
+          <p>Create this TXT record with your DNS provider:</p>
+          <p className="font-mono break-all">
+            {challenge.record_name} TXT "{challenge.value}"
+          </p>
+        </div>
+      )}
+
+      {running && round && (
+        <p className="text-xs text-muted-foreground">
+          Waiting for DNS (attempt {round.attempt}, {round.elapsed_secs}s)…
+        </p>
+      )}
+
+      {error && <p className="text-xs text-destructive">{error}</p>}
+    </div>
+  )
+}
@@ -0,0 +1,121 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+import { useState, useEffect } from "react"
+import { invoke } from "@tauri-apps/api/core"
+import { TkAlert, TkAlertDescription } from "thinkube-style/components/feedback"
+import { TkButton } from "thinkube-style/components/buttons-badges"
+import { AlertCircle, CheckCircle2, Loader2, ShieldAlert, ShieldQuestion } from "lucide-react"
+
+type KeyStatus = "trusted" | "unknown" | "changed"
+
+// Shape returned by the `scan_host_key` command
+type HostKeyScan = {
+  host: string
+  port: number
+  status: KeyStatus
+  keys: { key_type: string; fingerprint: string }[]
+  trusted: { key_type: string; fingerprint: string }[]
+}
+
+// Asks the user to confirm each node's host key before anything connects to it
+export default function HostKeys({ hosts }: { hosts: string[] }) {
+  const [scans, setScans] = useState<Record<string, HostKeyScan>>({})
+  const [errors, setErrors] = useState<Record<string, string>>({})
+  const [busy, setBusy] = useState<string | null>(null)
+
+  const scan = async (host: string) => {
+    try {
+      const result = await invoke<HostKeyScan>("scan_host_key", { host })
+      setScans((current) => ({ ...current, [host]: result }))
+      setErrors(({ [host]: _, ...rest }) => rest)
+    } catch (e) {
+      setErrors((current) => ({ ...current, [host]: String(e) }))
+    }
+  }
+
+  useEffect(() => {
+    hosts.forEach(scan)
+  }, [hosts.join(",")])
+
+  const act = async (host: string, command: "trust_host_key" | "forget_host_key") => {
+    setBusy(host)
+    try {
+      await invoke(
+        command,
+        command === "trust_host_key"
+          ? { host, fingerprints: scans[host].keys.map((key) => key.fingerprint) }
+          : { host }
+      )
+      await scan(host)
+    } catch (e) {
+      setErrors((current) => ({ ...current, [host]: String(e) }))
+    } finally {
+      setBusy(null)
+    }
+  }
+
+  return (
+    <div className="space-y-3">
+      <p className="text-sm text-muted-foreground">
+        Compare each fingerprint with the output of <span className="font-mono">ssh-keygen -lf</span> on the node's
+        console before trusting it.
+      </p>
+      {hosts.map((host) => {
+        const result = scans[host]
+        return (
+          <div key={host} className="rounded-md border px-3 py-2">
+            <div className="flex items-center gap-3">
+              {!result ? (
+                <Loader2 className="h-4 w-4 animate-spin" />
+              ) : result.status === "trusted" ? (
+                <CheckCircle2 className="h-4 w-4 text-success" />
+              ) : result.status === "changed" ? (
+                <ShieldAlert className="h-4 w-4 text-destructive" />
+              ) : (
+                <ShieldQuestion className="h-4 w-4 text-warning" />
+              )}
+              <div className="flex-1 font-medium">{host}</div>
+              {result?.status === "unknown" && (
+                <TkButton intent="secondary" size="sm" onClick={() => act(host, "trust_host_key")} disabled={busy !== null}>
+                  Trust
+                </TkButton>
+              )}
+              {result?.status === "changed" && (
+                <TkButton intent="ghost" size="sm" onClick={() => act(host, "forget_host_key")} disabled={busy !== null}>
+                  Forget old key
+                </TkButton>
+              )}
+            </div>
+            {result && result.status !== "trusted" && (
+              <div className="mt-2 space-y-1 font-mono text-xs text-muted-foreground">
+                {result.keys.map((key) => (
+                  <div key={key.fingerprint}>
+                    {key.key_type} {key.fingerprint}
+                  </div>
+                ))}
+              </div>
+            )}
+            {result?.status === "changed" && (
+              <TkAlert className="mt-2 bg-destructive/10 text-destructive border-destructive/20">
+                <ShieldAlert className="h-4 w-4" />
+                <TkAlertDescription>
+                  This node's key is not the one trusted before ({result.trusted.map((k) => k.fingerprint).join(", ")}).
+                  Someone may be intercepting the connection. Forget the old key only if you reinstalled the node.
+                </TkAlertDescription>
+              </TkAlert>
+            )}
+            {errors[host] && (
+              <TkAlert className="mt-2 bg-destructive/10 text-destructive border-destructive/20">
+                <AlertCircle className="h-4 w-4" />
+                <TkAlertDescription>{errors[host]}</TkAlertDescription>
+              </TkAlert>
+            )}
+          </div>
+        )
+      })}
+    </div>
+  )
+}
@@ -0,0 +1,145 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+import { useState } from "react"
+import { invoke } from "@tauri-apps/api/core"
+import { TkCard, TkCardContent, TkCardHeader, TkCardTitle } from "thinkube-style/components/cards-data"
+import { TkButton } from "thinkube-style/components/buttons-badges"
+import { TkInput, TkLabel } from "thinkube-style/components/forms-inputs"
+import { AlertCircle, AlertTriangle, CheckCircle2, Loader2, MinusCircle, Network } from "lucide-react"
+
+type Outcome = "pass" | "warn" | "fail" | "skipped"
+
+// Shape returned by the `check_ipv6_readiness` command
+type Ipv6Readiness = {
+  nodes: { host: string; address: string | null; outcome: Outcome; message: string }[]
+  cidrs: { name: string; cidr: string; valid: boolean; problems: string[]; warnings: string[] }[]
+  dual_stack: boolean
+}
+
+// Read by the inventory generator
+export type Ipv6Selection = {
+  dual_stack: boolean
+  pod_cidr: string
+  service_cidr: string
+  addresses: Record<string, string>
+}
+
+const OUTCOME_ICONS: Record<Outcome, JSX.Element> = {
+  pass: <CheckCircle2 className="h-4 w-4 text-success" />,
+  warn: <AlertTriangle className="h-4 w-4 text-warning" />,
+  fail: <AlertCircle className="h-4 w-4 text-destructive" />,
+  skipped: <MinusCircle className="h-4 w-4 text-muted-foreground" />,
+}
+
+export default function Ipv6Readiness() {
+  const saved: Ipv6Selection | null = JSON.parse(sessionStorage.getItem("ipv6Readiness") || "null")
+  const [podCidr, setPodCidr] = useState(saved?.pod_cidr || "fd01::/108")
+  const [serviceCidr, setServiceCidr] = useState(saved?.service_cidr || "fd98::/108")
+  const [report, setReport] = useState<Ipv6Readiness | null>(null)
+  const [checking, setChecking] = useState(false)
+  const [error, setError] = useState<string | null>(null)
+
+  const check = async () => {
+    setChecking(true)
+    setError(null)
+    try {
+      const servers = JSON.parse(sessionStorage.getItem("discoveredServers") || "[]")
+      const result = await invoke<Ipv6Readiness>("check_ipv6_readiness", {
+        hosts: servers.map((server: any) => ({
+          host: server.ip,
+          user: server.username,
+          port: null,
+          identity_file: null,
+        })),
+        plan: { pod_cidr: podCidr, service_cidr: serviceCidr },
+      })
+      setReport(result)
+      const selection: Ipv6Selection = {
+        dual_stack: result.dual_stack,
+        pod_cidr: podCidr.trim(),
+        service_cidr: serviceCidr.trim(),
+        addresses: Object.fromEntries(
+          result.nodes.filter((node) => node.address).map((node) => [node.host, node.address as string])
+        ),
+      }
+      sessionStorage.setItem("ipv6Readiness", JSON.stringify(selection))
+    } catch (e: any) {
+      setError(String(e))
+    } finally {
+      setChecking(false)
+    }
+  }
+
+  return (
+    <TkCard className="mb-6">
+      <TkCardHeader>
+        <TkCardTitle>IPv6 / Dual-Stack</TkCardTitle>
+      </TkCardHeader>
+      <TkCardContent className="space-y-4">
+        <p className="text-sm text-muted-foreground">
+          The cluster is set up dual-stack when every node has IPv6 connectivity and both IPv6 ranges are valid.
+          Otherwise it stays IPv4 only.
+        </p>
+        <div className="grid grid-cols-1 md:grid-cols-2 gap-4">
+          <div className="space-y-2">
+            <TkLabel htmlFor="podCidrV6">Pod CIDR (IPv6)</TkLabel>
+            <TkInput id="podCidrV6" className="font-mono" value={podCidr} onChange={(e) => setPodCidr(e.target.value)} />
+          </div>
+          <div className="space-y-2">
+            <TkLabel htmlFor="serviceCidrV6">Service CIDR (IPv6)</TkLabel>
+            <TkInput
+              id="serviceCidrV6"
+              className="font-mono"
+              value={serviceCidr}
+              onChange={(e) => setServiceCidr(e.target.value)}
+            />
+          </div>
+        </div>
+        <TkButton intent="secondary" className="gap-2" onClick={check} disabled={checking}>
+          {checking ? <Loader2 className="h-4 w-4 animate-spin" /> : <Network className="h-4 w-4" />}
+          {checking ? "Checking…" : "Check IPv6 readiness"}
+        </TkButton>
+
+        {error && <p className="text-sm text-destructive">{error}</p>}
+
+        {report && (
+          <div className="space-y-2">
+            {report.nodes.map((node) => (
+              <div key={node.host} className="flex items-start gap-3 rounded-md border px-3 py-2 text-sm">
+                {OUTCOME_ICONS[node.outcome]}
+                <div className="flex-1">
+                  <div className="font-medium">
+                    {node.host}
+                    {node.address && <span className="ml-2 font-mono text-muted-foreground">{node.address}</span>}
+                  </div>
+                  <div className="text-muted-foreground">{node.message}</div>
+                </div>
+              </div>
+            ))}
+            {report.cidrs.map((cidr) => (
+              <div key={cidr.name} className="rounded-md border px-3 py-2 text-sm">
+                <div className="flex items-center gap-3">
+                  {OUTCOME_ICONS[!cidr.valid ? "fail" : cidr.warnings.length ? "warn" : "pass"]}
+                  <span className="font-mono">{cidr.cidr}</span>
+                </div>
+                {[...cidr.problems, ...cidr.warnings].map((problem) => (
+                  <div key={problem} className="ml-7 text-muted-foreground">
+                    {problem}
+                  </div>
+                ))}
+              </div>
+            ))}
+            <p className="text-sm font-medium">
+              {report.dual_stack
+                ? "The cluster will be installed dual-stack."
+                : "The cluster will be installed IPv4 only."}
+            </p>
+          </div>
+        )}
+      </TkCardContent>
+    </TkCard>
+  )
+}
@@ -0,0 +1,114 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+import { useState, useEffect } from "react"
+import { invoke } from "@tauri-apps/api/core"
+import { TkButton } from "thinkube-style/components/buttons-badges"
+import { TkInput, TkLabel } from "thinkube-style/components/forms-inputs"
+import { Keyboard } from "lucide-react"
+
+// Shape returned by `get_regional_defaults` and `set_keyboard_layout`
+type KeyboardLayout = {
+  layout: string
+  variant: string
+  model: string
+}
+
+type RegionalDefaults = {
+  timezone: string
+  locale: string
+  keyboard: KeyboardLayout
+  keyboard_source: string
+}
+
+// Read by the inventory generator
+function remember(keyboard: KeyboardLayout) {
+  sessionStorage.setItem("keyboardLayout", JSON.stringify(keyboard))
+}
+
+// Keyboard layout the nodes' consoles get; this machine's unless overridden
+export default function KeyboardLayoutSetting() {
+  const [defaults, setDefaults] = useState<RegionalDefaults | null>(null)
+  const [layout, setLayout] = useState("")
+  const [variant, setVariant] = useState("")
+  const [saving, setSaving] = useState(false)
+  const [error, setError] = useState<string | null>(null)
+
+  const apply = (result: RegionalDefaults) => {
+    setDefaults(result)
+    setLayout(result.keyboard.layout)
+    setVariant(result.keyboard.variant)
+    remember(result.keyboard)
+  }
+
+  useEffect(() => {
+    invoke<RegionalDefaults>("get_regional_defaults")
+      .then(apply)
+      .catch((error) => console.error("Failed to detect the keyboard layout:", error))
+  }, [])
+
+  const save = async (keyboard: KeyboardLayout | null) => {
+    setSaving(true)
+    setError(null)
+    try {
+      apply(await invoke<RegionalDefaults>("set_keyboard_layout", { keyboard }))
+    } catch (e) {
+      setError(String(e))
+    } finally {
+      setSaving(false)
+    }
+  }
+
+  if (!defaults) {
+    return null
+  }
+
+  const overridden = defaults.keyboard_source === "override"
+  const changed = layout !== defaults.keyboard.layout || variant !== defaults.keyboard.variant
+
+  return (
+    <div className="space-y-2">
+      <TkLabel htmlFor="keyboardLayout" className="flex items-center gap-2">
+        <Keyboard className="h-4 w-4" /> Node Keyboard Layout
+      </TkLabel>
+      <div className="flex items-center gap-2">
+        <TkInput
+          id="keyboardLayout"
+          placeholder="us"
+          className="w-32 font-mono"
+          value={layout}
+          onChange={(e: React.ChangeEvent<HTMLInputElement>) => setLayout(e.target.value)}
+        />
+        <TkInput
+          placeholder="variant"
+          className="w-32 font-mono"
+          value={variant}
+          onChange={(e: React.ChangeEvent<HTMLInputElement>) => setVariant(e.target.value)}
+        />
+        <TkButton
+          size="sm"
+          intent="secondary"
+          disabled={!layout || !changed || saving}
+          onClick={() => save({ layout, variant, model: defaults.keyboard.model })}
+        >
+          Use this layout
+        </TkButton>
+        {overridden && (
+          <TkButton size="sm" intent="ghost" disabled={saving} onClick={() => save(null)}>
+            Use this machine's
+          </TkButton>
+        )}
+      </div>
+      <p className="text-xs text-muted-foreground">
+        {overridden
+          ? "Chosen by you for the consoles of new nodes and VMs"
+          : defaults.keyboard_source === "default"
+            ? "Could not detect this machine's layout; new nodes and VMs get this one"
+            : "Detected from this machine; new nodes and VMs get the same"}
+      </p>
+      {error && <p className="text-xs text-destructive">{error}</p>}
+    </div>
+  )
+}
@@ -0,0 +1,71 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+import { useState } from "react"
+import { invoke } from "@tauri-apps/api/core"
+import { TkButton } from "thinkube-style/components/buttons-badges"
+import { AlertTriangle, CheckCircle2, Loader2, Radar } from "lucide-react"
+
+// Shape returned by the `check_lb_range` command
+type LbRangeReport = {
+  start: string
+  end: string
+  probed: number
+  occupied: { address: string; mac: string | null }[]
+  free: boolean
+}
+
+export default function LbRangeCheck({ start, end }: { start: string; end: string }) {
+  const [report, setReport] = useState<LbRangeReport | null>(null)
+  const [checking, setChecking] = useState(false)
+  const [error, setError] = useState<string | null>(null)
+
+  const check = async () => {
+    setChecking(true)
+    setError(null)
+    try {
+      setReport(await invoke<LbRangeReport>("check_lb_range", { start, end }))
+    } catch (e: any) {
+      setError(String(e))
+    } finally {
+      setChecking(false)
+    }
+  }
+
+  // A report for another range says nothing about this one
+  const current = report && report.start === start && report.end === end ? report : null
+
+  return (
+    <div className="mt-4 space-y-2">
+      <TkButton intent="secondary" className="gap-2" onClick={check} disabled={checking}>
+        {checking ? <Loader2 className="h-4 w-4 animate-spin" /> : <Radar className="h-4 w-4" />}
+        {checking ? "Probing…" : "Check that the range is unused"}
+      </TkButton>
+
+      {error && <p className="text-sm text-destructive">{error}</p>}
+
+      {current &&
+        (current.free ? (
+          <p className="flex items-center gap-2 text-sm">
+            <CheckCircle2 className="h-4 w-4 text-success" />
+            Nothing answered on any of the {current.probed} addresses.
+          </p>
+        ) : (
+          <div className="rounded-md border px-3 py-2 text-sm">
+            <div className="flex items-center gap-2 font-medium">
+              <AlertTriangle className="h-4 w-4 text-warning" />
+              {current.occupied.length} of {current.probed} addresses are already in use
+            </div>
+            {current.occupied.map((entry) => (
+              <div key={entry.address} className="ml-6 font-mono text-muted-foreground">
+                {entry.address}
+                {entry.mac && <span className="ml-2">{entry.mac}</span>}
+              </div>
+            ))}
+          </div>
+        ))}
+    </div>
+  )
+}
@@ -0,0 +1,129 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+import { useState, useEffect } from "react"
+import { invoke } from "@tauri-apps/api/core"
+import { TkCard, TkCardContent, TkCardHeader, TkCardTitle } from "thinkube-style/components/cards-data"
+import { TkButton } from "thinkube-style/components/buttons-badges"
+import {
+  TkLabel,
+  TkSelect,
+  TkSelectContent,
+  TkSelectItem,
+  TkSelectTrigger,
+  TkSelectValue,
+} from "thinkube-style/components/forms-inputs"
+import { Gauge, Loader2 } from "lucide-react"
+
+type MirrorKind = "apt" | "snap" | "registry"
+
+// Shape returned by the `rank_mirrors` command
+type MirrorResult = {
+  kind: MirrorKind
+  url: string
+  reachable: boolean
+  latency_ms: number | null
+  throughput_kib_s: number | null
+  error: string | null
+}
+
+type MirrorRanking = Record<MirrorKind, MirrorResult[]>
+
+// Read by the inventory generator
+export type MirrorSelection = Partial<Record<MirrorKind, string>>
+
+const KIND_LABELS: Record<MirrorKind, string> = {
+  apt: "Ubuntu packages (apt)",
+  snap: "Snap store",
+  registry: "Container registry",
+}
+
+const describe = (result: MirrorResult) => {
+  if (!result.reachable) return `unreachable${result.error ? ` (${result.error})` : ""}`
+  const parts = [`${result.latency_ms} ms`]
+  if (result.throughput_kib_s !== null) {
+    parts.push(`${(result.throughput_kib_s / 1024).toFixed(1)} MiB/s`)
+  }
+  return parts.join(", ")
+}
+
+export default function MirrorSelection() {
+  const [ranking, setRanking] = useState<MirrorRanking | null>(null)
+  const [selection, setSelection] = useState<MirrorSelection>(() =>
+    JSON.parse(sessionStorage.getItem("mirrorSelection") || "{}")
+  )
+  const [measuring, setMeasuring] = useState(false)
+  const [error, setError] = useState<string | null>(null)
+
+  useEffect(() => {
+    sessionStorage.setItem("mirrorSelection", JSON.stringify(selection))
+  }, [selection])
+
+  const measure = async () => {
+    setMeasuring(true)
+    setError(null)
+    try {
+      const result = await invoke<MirrorRanking>("rank_mirrors")
+      setRanking(result)
+      // Default each kind to the fastest reachable mirror
+      setSelection((current) => {
+        const next = { ...current }
+        for (const kind of Object.keys(KIND_LABELS) as MirrorKind[]) {
+          const fastest = result[kind].find((m) => m.reachable)
+          if (!next[kind] && fastest) next[kind] = fastest.url
+        }
+        return next
+      })
+    } catch (e: any) {
+      setError(String(e))
+    } finally {
+      setMeasuring(false)
+    }
+  }
+
+  return (
+    <TkCard className="mb-6">
+      <TkCardHeader>
+        <TkCardTitle>Download Mirrors</TkCardTitle>
+      </TkCardHeader>
+      <TkCardContent className="space-y-4">
+        <div className="flex items-center justify-between gap-4">
+          <p className="text-sm text-muted-foreground">
+            Measure the package and image mirrors from this network and use the fastest ones for the install.
+          </p>
+          <TkButton intent="secondary" className="gap-2" onClick={measure} disabled={measuring}>
+            {measuring ? <Loader2 className="h-4 w-4 animate-spin" /> : <Gauge className="h-4 w-4" />}
+            {measuring ? "Measuring…" : ranking ? "Measure again" : "Measure mirrors"}
+          </TkButton>
+        </div>
+
+        {error && <p className="text-sm text-destructive">{error}</p>}
+
+        {ranking &&
+          (Object.keys(KIND_LABELS) as MirrorKind[]).map((kind) => (
+            <div key={kind} className="space-y-1">
+              <TkLabel>{KIND_LABELS[kind]}</TkLabel>
+              <TkSelect
+                value={selection[kind] || ""}
+                onValueChange={(url: string) => setSelection((current) => ({ ...current, [kind]: url }))}
+              >
+                <TkSelectTrigger className="w-full">
+                  <TkSelectValue placeholder="Default" />
+                </TkSelectTrigger>
+                <TkSelectContent>
+                  {ranking[kind].map((mirror) => (
+                    <TkSelectItem key={mirror.url} value={mirror.url} disabled={!mirror.reachable}>
+                      <span className="font-mono">{mirror.url}</span>
+                      <span className="text-muted-foreground"> — {describe(mirror)}</span>
+                    </TkSelectItem>
+                  ))}
+                </TkSelectContent>
+              </TkSelect>
+            </div>
+          ))}
+      </TkCardContent>
+    </TkCard>
+  )
+}
@@ -0,0 +1,125 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+import { useState, useEffect } from "react"
+import { invoke } from "@tauri-apps/api/core"
+import { listen } from "@tauri-apps/api/event"
+import { TkAlert, TkAlertDescription } from "thinkube-style/components/feedback"
+import { TkButton } from "thinkube-style/components/buttons-badges"
+import { AlertCircle, HardDriveDownload, Loader2, PackageOpen } from "lucide-react"
+
+// Shape returned by the `get_offline_bundle` command
+type BundleManifest = {
+  created_at: number
+  installer_version: string
+  arch: string
+  playbooks_commit: string
+  snaps: string[]
+  images: string[]
+  files: { path: string; size: number; sha256: string }[]
+}
+
+// Shape returned by the `import_offline_bundle` command
+type ImportedBundle = {
+  path: string
+  manifest: BundleManifest
+}
+
+// Prepare an air-gapped install on a connected machine, or use one here
+export default function OfflineBundle() {
+  const [manifest, setManifest] = useState<BundleManifest | null>(null)
+  const [busy, setBusy] = useState<string | null>(null)
+  const [message, setMessage] = useState<string | null>(null)
+  const [error, setError] = useState<string | null>(null)
+
+  useEffect(() => {
+    let unlisten: (() => void) | undefined
+    invoke<BundleManifest | null>("get_offline_bundle").then(setManifest).catch(() => {})
+    listen<string>("offline-bundle-progress", (event) => {
+      setBusy(event.payload)
+    }).then((fn) => {
+      unlisten = fn
+    })
+    return () => unlisten?.()
+  }, [])
+
+  const run = async (action: () => Promise<void>) => {
+    setError(null)
+    setMessage(null)
+    setBusy("starting")
+    try {
+      await action()
+    } catch (e) {
+      setError(String(e))
+    } finally {
+      setBusy(null)
+    }
+  }
+
+  const create = () =>
+    run(async () => {
+      const path = await invoke<string | null>("create_offline_bundle")
+      if (path) setMessage(`Offline bundle saved to ${path}`)
+    })
+
+  const importBundle = () =>
+    run(async () => {
+      const imported = await invoke<ImportedBundle | null>("import_offline_bundle")
+      if (imported) {
+        setManifest(imported.manifest)
+        setMessage(`Using the offline bundle from installer ${imported.manifest.installer_version}`)
+      }
+    })
+
+  const clear = () =>
+    run(async () => {
+      await invoke("clear_offline_bundle")
+      setManifest(null)
+    })
+
+  return (
+    <div className="space-y-3">
+      {manifest && (
+        <TkAlert className="bg-info/10 text-info border-info/20">
+          <PackageOpen className="h-4 w-4" />
+          <TkAlertDescription>
+            <div className="flex items-center justify-between gap-4">
+              <div>
+                Installing from an offline bundle ({manifest.arch}, {manifest.files.length} files, playbooks{" "}
+                {manifest.playbooks_commit.slice(0, 12)}) instead of the network.
+              </div>
+              <TkButton intent="secondary" size="sm" onClick={clear} disabled={busy !== null}>
+                Use the network
+              </TkButton>
+            </div>
+          </TkAlertDescription>
+        </TkAlert>
+      )}
+
+      {error && (
+        <TkAlert className="bg-destructive/10 text-destructive border-destructive/20">
+          <AlertCircle className="h-4 w-4" />
+          <TkAlertDescription>{error}</TkAlertDescription>
+        </TkAlert>
+      )}
+
+      {message && <p className="text-sm text-muted-foreground">{message}</p>}
+
+      <div className="flex flex-wrap justify-center gap-2">
+        <TkButton intent="secondary" size="sm" className="gap-2" onClick={create} disabled={busy !== null}>
+          {busy ? <Loader2 className="h-4 w-4 animate-spin" /> : <HardDriveDownload className="h-4 w-4" />}
+          Create offline bundle
+        </TkButton>
+        <TkButton intent="secondary" size="sm" className="gap-2" onClick={importBundle} disabled={busy !== null}>
+          <PackageOpen className="h-4 w-4" />
+          Import offline bundle
+        </TkButton>
+      </div>
+      {busy && busy !== "starting" && (
+        <p className="text-sm text-muted-foreground text-center">Working on {busy}…</p>
+      )}
+    </div>
+  )
+}
@@ -0,0 +1,101 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+import { useState, useEffect } from "react"
+import { invoke } from "@tauri-apps/api/core"
+import { listen } from "@tauri-apps/api/event"
+import { TkAlert, TkAlertDescription } from "thinkube-style/components/feedback"
+import { TkButton } from "thinkube-style/components/buttons-badges"
+import { AlertCircle, CheckCircle2 } from "lucide-react"
+
+// Shape returned by the `get_platform_compatibility` command
+export type PlatformCompatibility = {
+  installer_version: string
+  platform_version: string | null
+  branch: string
+  manifest_url: string | null
+  verdict: "compatible" | "untested" | "unsupported" | "unknown"
+  message: string
+}
+
+type Props = {
+  onChange?: (compatibility: PlatformCompatibility) => void
+}
+
+export default function PlatformCompatibilityStatus({ onChange }: Props) {
+  const [compat, setCompat] = useState<PlatformCompatibility | null>(null)
+  const [checking, setChecking] = useState(false)
+
+  const update = (result: PlatformCompatibility) => {
+    setCompat(result)
+    onChange?.(result)
+  }
+
+  const recheck = async () => {
+    setChecking(true)
+    try {
+      update(await invoke<PlatformCompatibility>("get_platform_compatibility", { refresh: true }))
+    } catch (error) {
+      console.error("Failed to check platform compatibility:", error)
+    } finally {
+      setChecking(false)
+    }
+  }
+
+  useEffect(() => {
+    let unlisten: (() => void) | undefined
+    invoke<PlatformCompatibility>("get_platform_compatibility")
+      .then(update)
+      .catch((error) => console.error("Failed to check platform compatibility:", error))
+    // The startup check may still be running when the page mounts
+    listen<PlatformCompatibility>("platform-compatibility", (event) => {
+      update(event.payload)
+    }).then((fn) => {
+      unlisten = fn
+    })
+    return () => unlisten?.()
+  }, [])
+
+  if (!compat) {
+    return null
+  }
+
+  const platform = compat.platform_version ? ` (platform ${compat.platform_version}, ${compat.branch})` : ""
+
+  if (compat.verdict === "compatible") {
+    return (
+      <TkAlert className="bg-success/10 text-success border-success/20">
+        <CheckCircle2 className="h-4 w-4" />
+        <TkAlertDescription>
+          {compat.message}
+          {platform}
+        </TkAlertDescription>
+      </TkAlert>
+    )
+  }
+
+  return (
+    <TkAlert
+      className={
+        compat.verdict === "unsupported"
+          ? "bg-destructive/10 text-destructive border-destructive/20"
+          : "bg-warning/10 text-warning border-warning/20"
+      }
+    >
+      <AlertCircle className="h-4 w-4" />
+      <TkAlertDescription>
+        <div className="flex items-center justify-between gap-4">
+          <div>
+            {compat.message}
+            {platform}
+          </div>
+          <TkButton intent="secondary" size="sm" onClick={recheck} disabled={checking}>
+            Check again
+          </TkButton>
+        </div>
+      </TkAlertDescription>
+    </TkAlert>
+  )
+}
@@ -0,0 +1,88 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+import { useState, useEffect } from "react"
+import { invoke } from "@tauri-apps/api/core"
+import { TkButton } from "thinkube-style/components/buttons-badges"
+import { AlertCircle, CheckCircle2, Globe, Loader2, MinusCircle } from "lucide-react"
+
+// Shape returned by the `check_public_exposure` command
+type Outcome = "pass" | "warn" | "fail" | "skipped"
+
+type Finding = {
+  outcome: Outcome
+  message: string
+}
+
+type ExposureReport = {
+  public_ip: Finding & { ip: string | null }
+  dns: (Finding & { domain: string; addresses: string[] }) | null
+  ports: (Finding & { port: number })[]
+}
+
+const outcomeClass: Record<Outcome, string> = {
+  pass: "text-success",
+  warn: "text-warning",
+  fail: "text-destructive",
+  skipped: "text-muted-foreground",
+}
+
+function FindingLine({ finding }: { finding: Finding }) {
+  const Icon =
+    finding.outcome === "pass" ? CheckCircle2 : finding.outcome === "skipped" ? MinusCircle : AlertCircle
+  return (
+    <p className={`text-xs flex items-start gap-1 ${outcomeClass[finding.outcome]}`}>
+      <Icon className="h-3 w-3 mt-0.5 shrink-0" /> {finding.message}
+    </p>
+  )
+}
+
+interface PublicExposureProps {
+  domain: string
+}
+
+export default function PublicExposure({ domain }: PublicExposureProps) {
+  const [report, setReport] = useState<ExposureReport | null>(null)
+  const [checking, setChecking] = useState(false)
+  const [error, setError] = useState<string | null>(null)
+
+  useEffect(() => {
+    setReport(null)
+    setError(null)
+  }, [domain])
+
+  const check = async () => {
+    setChecking(true)
+    setError(null)
+    try {
+      setReport(await invoke<ExposureReport>("check_public_exposure", { domain: domain || null }))
+    } catch (e: any) {
+      setError(typeof e === "string" ? e : "Failed to check public exposure")
+    } finally {
+      setChecking(false)
+    }
+  }
+
+  return (
+    <div className="space-y-2">
+      <TkButton intent="secondary" size="sm" className="gap-2" onClick={check} disabled={checking}>
+        {checking ? <Loader2 className="h-4 w-4 animate-spin" /> : <Globe className="h-4 w-4" />}
+        {checking ? "Checking public access…" : "Check public IP and port forwarding"}
+      </TkButton>
+
+      {report && (
+        <div className="space-y-1">
+          <FindingLine finding={report.public_ip} />
+          {report.dns && <FindingLine finding={report.dns} />}
+          {report.ports.map((port) => (
+            <FindingLine key={port.port} finding={port} />
+          ))}
+        </div>
+      )}
+
+      {error && <p className="text-xs text-destructive">{error}</p>}
+    </div>
+  )
+}
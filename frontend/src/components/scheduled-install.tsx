@@ -0,0 +1,193 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+import { useState, useEffect } from "react"
+import { useNavigate } from "react-router-dom"
+import { invoke } from "@tauri-apps/api/core"
+import { listen } from "@tauri-apps/api/event"
+import { TkAlert, TkAlertDescription, tkToast } from "thinkube-style/components/feedback"
+import { TkButton } from "thinkube-style/components/buttons-badges"
+import { AlertCircle, Clock, Loader2 } from "lucide-react"
+
+// Shape returned by the `schedule_install` command
+type ScheduledInstall = {
+  start_at: number
+  hosts: { host: string; user: string }[]
+  state: Record<string, string> | null
+  scheduled_at: number
+}
+
+// Shape of the `scheduled-install` event emitted by the Rust shell
+type ScheduleEvent =
+  | { phase: "scheduled"; schedule: ScheduledInstall }
+  | { phase: "checking"; start_at: number }
+  | { phase: "starting"; state: Record<string, string> | null }
+  | { phase: "blocked"; message: string }
+  | { phase: "cancelled" }
+
+// Kept out of schedule.json; they're still in this session when the start
+// comes, unless the installer was restarted in between
+const SECRET_KEYS = [
+  "sudoPassword",
+  "cloudflareToken",
+  "githubToken",
+  "hfToken",
+  "tailscaleApiToken",
+  "tailscaleAuthKey",
+  "tailscaleOauthClientSecret",
+  "zerotierApiToken",
+]
+
+function wizardState(): Record<string, string> {
+  const state: Record<string, string> = {}
+  for (let i = 0; i < sessionStorage.length; i++) {
+    const key = sessionStorage.key(i)
+    if (!key || SECRET_KEYS.includes(key)) continue
+    state[key] = sessionStorage.getItem(key) || ""
+  }
+  if (state.discoveredServers) {
+    const servers = JSON.parse(state.discoveredServers)
+    state.discoveredServers = JSON.stringify(servers.map(({ password, ...server }: any) => server))
+  }
+  return state
+}
+
+function formatStart(startAt: number) {
+  return new Date(startAt * 1000).toLocaleString(undefined, { dateStyle: "medium", timeStyle: "short" })
+}
+
+// `datetime-local` value for tomorrow at 02:00
+function defaultStart() {
+  const date = new Date()
+  date.setDate(date.getDate() + 1)
+  date.setHours(2, 0, 0, 0)
+  const pad = (n: number) => String(n).padStart(2, "0")
+  return `${date.getFullYear()}-${pad(date.getMonth() + 1)}-${pad(date.getDate())}T${pad(date.getHours())}:${pad(date.getMinutes())}`
+}
+
+// Pick a start time on the review page, or show and cancel the planned one
+export default function ScheduledInstallPicker({ disabled }: { disabled?: boolean }) {
+  const [schedule, setSchedule] = useState<ScheduledInstall | null>(null)
+  const [startAt, setStartAt] = useState(defaultStart)
+  const [busy, setBusy] = useState(false)
+  const [error, setError] = useState<string | null>(null)
+
+  useEffect(() => {
+    let unlisten: (() => void) | undefined
+    invoke<ScheduledInstall | null>("get_scheduled_install").then(setSchedule).catch(() => {})
+    listen<ScheduleEvent>("scheduled-install", (event) => {
+      if (event.payload.phase === "scheduled") setSchedule(event.payload.schedule)
+      if (event.payload.phase === "cancelled" || event.payload.phase === "blocked") setSchedule(null)
+    }).then((fn) => {
+      unlisten = fn
+    })
+    return () => unlisten?.()
+  }, [])
+
+  const scheduleInstall = async () => {
+    setError(null)
+    setBusy(true)
+    try {
+      const servers = JSON.parse(sessionStorage.getItem("discoveredServers") || "[]")
+      const planned = await invoke<ScheduledInstall>("schedule_install", {
+        startAt: Math.floor(new Date(startAt).getTime() / 1000),
+        hosts: servers.map((server: any) => ({
+          host: server.ip,
+          user: server.username,
+          port: null,
+          identity_file: null,
+        })),
+        state: wizardState(),
+      })
+      setSchedule(planned)
+      tkToast.success(`The installation will start at ${formatStart(planned.start_at)}`)
+    } catch (e) {
+      setError(String(e))
+    } finally {
+      setBusy(false)
+    }
+  }
+
+  const cancel = async () => {
+    setError(null)
+    try {
+      await invoke("cancel_scheduled_install")
+      setSchedule(null)
+    } catch (e) {
+      setError(String(e))
+    }
+  }
+
+  return (
+    <div className="space-y-3">
+      {schedule ? (
+        <TkAlert className="bg-info/10 text-info border-info/20">
+          <Clock className="h-4 w-4" />
+          <TkAlertDescription>
+            <div className="flex items-center justify-between gap-4">
+              <div>
+                The installation starts at {formatStart(schedule.start_at)} if the preflight checks still pass on all{" "}
+                {schedule.hosts.length} nodes. Leave the installer running; closing the window keeps it in the tray.
+              </div>
+              <TkButton intent="secondary" size="sm" onClick={cancel}>
+                Cancel
+              </TkButton>
+            </div>
+          </TkAlertDescription>
+        </TkAlert>
+      ) : (
+        <div className="flex flex-wrap items-center gap-2">
+          <label htmlFor="scheduled-start" className="text-sm text-muted-foreground">
+            Start later, at
+          </label>
+          <input
+            id="scheduled-start"
+            type="datetime-local"
+            className="rounded-md border border-input bg-background px-2 py-1 text-sm"
+            value={startAt}
+            onChange={(e) => setStartAt(e.target.value)}
+          />
+          <TkButton intent="secondary" size="sm" className="gap-2" onClick={scheduleInstall} disabled={disabled || busy}>
+            {busy ? <Loader2 className="h-4 w-4 animate-spin" /> : <Clock className="h-4 w-4" />}
+            Schedule
+          </TkButton>
+        </div>
+      )}
+
+      {error && (
+        <TkAlert className="bg-destructive/10 text-destructive border-destructive/20">
+          <AlertCircle className="h-4 w-4" />
+          <TkAlertDescription>{error}</TkAlertDescription>
+        </TkAlert>
+      )}
+    </div>
+  )
+}
+
+// Mounted once: deploys when the shell says a scheduled start passed preflight
+export function ScheduledInstallListener() {
+  const navigate = useNavigate()
+
+  useEffect(() => {
+    let unlisten: (() => void) | undefined
+    listen<ScheduleEvent>("scheduled-install", (event) => {
+      const payload = event.payload
+      if (payload.phase === "starting") {
+        // What this session already has wins; it may hold newer edits
+        for (const [key, value] of Object.entries(payload.state || {})) {
+          if (sessionStorage.getItem(key) === null) sessionStorage.setItem(key, value)
+        }
+        navigate("/deploy")
+      } else if (payload.phase === "blocked") {
+        tkToast.error(payload.message)
+      }
+    }).then((fn) => {
+      unlisten = fn
+    })
+    return () => unlisten?.()
+  }, [navigate])
+
+  return null
+}
@@ -0,0 +1,128 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+import { useState, useEffect } from "react"
+import { invoke } from "@tauri-apps/api/core"
+import { listen } from "@tauri-apps/api/event"
+import { TkAlert, TkAlertDescription } from "thinkube-style/components/feedback"
+import { TkButton } from "thinkube-style/components/buttons-badges"
+import { Activity, AlertCircle, Loader2, RefreshCw } from "lucide-react"
+
+// Shape returned by the `get_service_health` command
+type HealthSnapshot = {
+  checked_at: number
+  results: { kind: "service" | "node"; name: string; up: boolean; message: string; latency_ms: number }[]
+  healthy: boolean
+}
+
+type Target = { host: string; user: string }
+
+// Keeps watching the cluster's services and nodes after the install
+export default function ServiceHealth({ domain, nodes }: { domain: string; nodes: Target[] }) {
+  const [watching, setWatching] = useState(false)
+  const [snapshot, setSnapshot] = useState<HealthSnapshot | null>(null)
+  const [busy, setBusy] = useState(false)
+  const [error, setError] = useState<string | null>(null)
+
+  useEffect(() => {
+    let unlisten: (() => void) | undefined
+    invoke<object | null>("get_health_config")
+      .then((config) => setWatching(config !== null))
+      .catch(() => {})
+    invoke<HealthSnapshot | null>("get_service_health").then(setSnapshot).catch(() => {})
+    listen<HealthSnapshot>("service-health", (event) => setSnapshot(event.payload)).then((fn) => {
+      unlisten = fn
+    })
+    return () => unlisten?.()
+  }, [])
+
+  const toggle = async () => {
+    setError(null)
+    setBusy(true)
+    try {
+      await invoke("configure_health_probes", {
+        config: watching
+          ? null
+          : {
+              services: [
+                { name: "Keycloak", url: `https://auth.${domain}/realms/master` },
+                { name: "Harbor", url: `https://registry.${domain}/api/v2.0/ping` },
+                { name: "Thinkube Control", url: `https://control.${domain}/` },
+                { name: "Code Server", url: `https://code.${domain}/` },
+              ],
+              nodes: nodes.map((node) => ({ ...node, port: null, identity_file: null })),
+              interval_secs: null,
+              ca_pem: null,
+            },
+      })
+      setWatching(!watching)
+      if (watching) setSnapshot(null)
+    } catch (e) {
+      setError(String(e))
+    } finally {
+      setBusy(false)
+    }
+  }
+
+  const refresh = async () => {
+    setError(null)
+    setBusy(true)
+    try {
+      setSnapshot(await invoke<HealthSnapshot | null>("get_service_health", { refresh: true }))
+    } catch (e) {
+      setError(String(e))
+    } finally {
+      setBusy(false)
+    }
+  }
+
+  return (
+    <div className="space-y-4">
+      <div className="flex items-center justify-between gap-4">
+        <p className="text-sm text-muted-foreground">
+          {watching
+            ? "The installer checks these every minute while it runs and notifies you when one goes down."
+            : "Keep the installer as a status dashboard for your cluster."}
+        </p>
+        <div className="flex gap-2">
+          {watching && (
+            <TkButton intent="ghost" size="sm" onClick={refresh} disabled={busy}>
+              <RefreshCw className="h-4 w-4" />
+            </TkButton>
+          )}
+          <TkButton intent="secondary" size="sm" className="gap-2" onClick={toggle} disabled={busy}>
+            {busy ? <Loader2 className="h-4 w-4 animate-spin" /> : <Activity className="h-4 w-4" />}
+            {watching ? "Stop watching" : "Keep watching"}
+          </TkButton>
+        </div>
+      </div>
+
+      {snapshot && (
+        <div className="divide-y rounded-md border">
+          {snapshot.results.map((result) => (
+            <div key={`${result.kind}-${result.name}`} className="flex items-center gap-3 px-3 py-2">
+              <span className={`h-2 w-2 rounded-full ${result.up ? "bg-success" : "bg-destructive"}`} />
+              <div className="flex-1 font-medium">{result.name}</div>
+              <div className="text-sm text-muted-foreground">
+                {result.message}
+                {result.up && ` · ${result.latency_ms} ms`}
+              </div>
+            </div>
+          ))}
+          <div className="px-3 py-2 text-xs text-muted-foreground">
+            Last checked {new Date(snapshot.checked_at * 1000).toLocaleTimeString()}
+          </div>
+        </div>
+      )}
+
+      {error && (
+        <TkAlert className="bg-destructive/10 text-destructive border-destructive/20">
+          <AlertCircle className="h-4 w-4" />
+          <TkAlertDescription>{error}</TkAlertDescription>
+        </TkAlert>
+      )}
+    </div>
+  )
+}
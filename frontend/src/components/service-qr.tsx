@@ -0,0 +1,49 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+import { useState } from "react"
+import { invoke } from "@tauri-apps/api/core"
+import { TkButton } from "thinkube-style/components/buttons-badges"
+import { QrCode } from "lucide-react"
+
+// Shape returned by the Rust `generate_qr` command
+interface QrImage {
+  mime_type: string
+  data: string
+  data_url: string
+}
+
+// Toggleable QR code for opening a service URL from a phone
+export default function ServiceQr({ url }: { url: string }) {
+  const [image, setImage] = useState<string | null>(null)
+  const [error, setError] = useState<string | null>(null)
+
+  const toggle = async () => {
+    if (image) {
+      setImage(null)
+      return
+    }
+    try {
+      const qr = await invoke<QrImage>("generate_qr", { url })
+      setImage(qr.data_url)
+      setError(null)
+    } catch (err) {
+      setError(String(err))
+    }
+  }
+
+  return (
+    <div className="mt-2">
+      <TkButton intent="ghost" size="sm" onClick={toggle}>
+        <QrCode className="w-4 h-4 mr-1" />
+        {image ? "Hide QR code" : "Show QR code"}
+      </TkButton>
+      {image && (
+        <img src={image} alt={`QR code for ${url}`} className="mt-2 w-40 h-40 bg-white p-2 rounded" />
+      )}
+      {error && <p className="text-sm text-destructive mt-1">{error}</p>}
+    </div>
+  )
+}
@@ -0,0 +1,154 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+import { useState, useEffect, useRef } from "react"
+import { invoke } from "@tauri-apps/api/core"
+import { listen } from "@tauri-apps/api/event"
+import { TkAlert, TkAlertDescription } from "thinkube-style/components/feedback"
+import { TkButton } from "thinkube-style/components/buttons-badges"
+import { AlertCircle, AlertTriangle, CheckCircle2, Loader2, RefreshCw, XCircle } from "lucide-react"
+
+type Outcome = "pass" | "warn" | "fail" | "skipped"
+
+// Shape returned by the `run_smoke_tests` command
+type SmokeCheck = {
+  id: string
+  target: string
+  outcome: Outcome
+  message: string
+  duration_ms: number
+}
+
+type SmokeReport = {
+  run_id: string
+  domain: string
+  checks: SmokeCheck[]
+  summary: { pass: number; warn: number; fail: number }
+  ok: boolean
+}
+
+type SmokeEvent = SmokeCheck & { run_id: string }
+
+const LABELS: Record<string, string> = {
+  "dns-wildcard": "Wildcard DNS record",
+  "dns-keycloak": "Keycloak DNS",
+  "dns-harbor": "Harbor DNS",
+  "dns-control": "Thinkube Control DNS",
+  "dns-code": "Code Server DNS",
+  keycloak: "Keycloak",
+  harbor: "Harbor",
+  control: "Thinkube Control",
+  code: "Code Server",
+  "ingress-wildcard": "Ingress wildcard",
+  "api-server": "Kubernetes API server",
+}
+
+function OutcomeIcon({ outcome }: { outcome: Outcome }) {
+  if (outcome === "pass") return <CheckCircle2 className="h-4 w-4 text-success" />
+  if (outcome === "warn") return <AlertTriangle className="h-4 w-4 text-warning" />
+  return <XCircle className="h-4 w-4 text-destructive" />
+}
+
+// Checks the installed cluster's endpoints once, then on demand
+export default function SmokeTests({
+  domain,
+  controlPlane,
+}: {
+  domain: string
+  controlPlane?: { host: string; user: string }
+}) {
+  const [checks, setChecks] = useState<SmokeCheck[]>([])
+  const [report, setReport] = useState<SmokeReport | null>(null)
+  const [running, setRunning] = useState(false)
+  const [error, setError] = useState<string | null>(null)
+  const runId = useRef("")
+
+  const run = async () => {
+    runId.current = `smoke-${Date.now()}`
+    setChecks([])
+    setReport(null)
+    setError(null)
+    setRunning(true)
+    try {
+      const result = await invoke<SmokeReport>("run_smoke_tests", {
+        domain,
+        options: {
+          run_id: runId.current,
+          control_plane: controlPlane ? { ...controlPlane, port: null, identity_file: null } : null,
+        },
+      })
+      setReport(result)
+      setChecks(result.checks)
+    } catch (e) {
+      setError(String(e))
+    } finally {
+      setRunning(false)
+    }
+  }
+
+  useEffect(() => {
+    let unlisten: (() => void) | undefined
+    listen<SmokeEvent>("smoke-test", (event) => {
+      if (event.payload.run_id !== runId.current) return
+      setChecks((current) => [...current.filter((c) => c.id !== event.payload.id), event.payload])
+    }).then((fn) => {
+      unlisten = fn
+    })
+    run()
+    return () => unlisten?.()
+  }, [domain])
+
+  return (
+    <div className="space-y-4">
+      <div className="flex items-center justify-between gap-4">
+        <p className="text-sm text-muted-foreground">
+          {running
+            ? "Checking DNS and the service endpoints over HTTPS..."
+            : report
+              ? `${report.summary.pass} passed, ${report.summary.warn} with warnings, ${report.summary.fail} failed`
+              : "Check DNS and the service endpoints over HTTPS."}
+        </p>
+        <TkButton intent="secondary" size="sm" className="gap-2" onClick={run} disabled={running}>
+          {running ? <Loader2 className="h-4 w-4 animate-spin" /> : <RefreshCw className="h-4 w-4" />}
+          Run again
+        </TkButton>
+      </div>
+
+      {checks.length > 0 && (
+        <div className="divide-y rounded-md border">
+          {checks.map((check) => (
+            <div key={check.id} className="flex items-start gap-3 px-3 py-2">
+              <div className="mt-0.5">
+                <OutcomeIcon outcome={check.outcome} />
+              </div>
+              <div className="min-w-0 flex-1">
+                <div className="font-medium">{LABELS[check.id] || check.id}</div>
+                <div className="truncate text-xs text-muted-foreground">{check.target}</div>
+              </div>
+              <div className="max-w-[50%] text-right text-sm text-muted-foreground">{check.message}</div>
+            </div>
+          ))}
+        </div>
+      )}
+
+      {report && !report.ok && (
+        <TkAlert className="bg-warning/10 text-warning border-warning/20">
+          <AlertTriangle className="h-4 w-4" />
+          <TkAlertDescription>
+            Some services aren't reachable yet. They can take a few minutes to come up after the playbooks finish;
+            run the checks again before troubleshooting.
+          </TkAlertDescription>
+        </TkAlert>
+      )}
+
+      {error && (
+        <TkAlert className="bg-destructive/10 text-destructive border-destructive/20">
+          <AlertCircle className="h-4 w-4" />
+          <TkAlertDescription>{error}</TkAlertDescription>
+        </TkAlert>
+      )}
+    </div>
+  )
+}
@@ -0,0 +1,146 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+import { useState, useEffect } from "react"
+import { invoke } from "@tauri-apps/api/core"
+import { listen } from "@tauri-apps/api/event"
+import { TkAlert, TkAlertDescription } from "thinkube-style/components/feedback"
+import { TkButton } from "thinkube-style/components/buttons-badges"
+import { TkCheckbox, TkInput, TkLabel } from "thinkube-style/components/forms-inputs"
+import { LifeBuoy } from "lucide-react"
+
+// Shape of the `support-tunnel` event emitted by the Rust shell; null once
+// the session is over
+type SupportSession = {
+  endpoint: string
+  remote_port: number
+  access_code: string
+  started_at: number
+  expires_at: number
+}
+
+function formatTime(unix: number) {
+  return new Date(unix * 1000).toLocaleTimeString(undefined, { timeStyle: "short" })
+}
+
+function useSupportSession() {
+  const [session, setSession] = useState<SupportSession | null>(null)
+
+  useEffect(() => {
+    let unlisten: (() => void) | undefined
+
+    invoke<SupportSession | null>("get_support_tunnel")
+      .then(setSession)
+      .catch((error) => console.error("Failed to load support session:", error))
+
+    listen<SupportSession | null>("support-tunnel", (event) => {
+      setSession(event.payload)
+    }).then((fn) => {
+      unlisten = fn
+    })
+
+    return () => unlisten?.()
+  }, [])
+
+  return session
+}
+
+// Shown on every page while a support session is open
+export function SupportTunnelBanner() {
+  const session = useSupportSession()
+
+  if (!session) {
+    return null
+  }
+
+  return (
+    <TkAlert className="m-4 bg-warning/10 text-warning border-warning/20">
+      <LifeBuoy className="h-4 w-4" />
+      <TkAlertDescription className="flex items-center justify-between gap-4">
+        <span>
+          Support session open with {session.endpoint} until {formatTime(session.expires_at)}. Access
+          code: <span className="font-mono">{session.access_code}</span>
+        </span>
+        <TkButton
+          size="sm"
+          intent="secondary"
+          onClick={() =>
+            invoke("close_support_tunnel").catch((error) => console.error("Failed to end support session:", error))
+          }
+        >
+          End session
+        </TkButton>
+      </TkAlertDescription>
+    </TkAlert>
+  )
+}
+
+// Asks for the endpoint and consent before opening a session
+export function SupportTunnelForm() {
+  const session = useSupportSession()
+  const [endpoint, setEndpoint] = useState("")
+  const [minutes, setMinutes] = useState("30")
+  const [consent, setConsent] = useState(false)
+  const [opening, setOpening] = useState(false)
+  const [error, setError] = useState<string | null>(null)
+
+  if (session) {
+    return null
+  }
+
+  const open = async () => {
+    const [user, address] = endpoint.includes("@") ? endpoint.split("@", 2) : ["support", endpoint]
+    const [host, port] = address.split(":", 2)
+    setOpening(true)
+    setError(null)
+    try {
+      await invoke("open_support_tunnel", {
+        request: {
+          endpoint: { host, user, port: port ? Number(port) : null },
+          minutes: Number(minutes),
+          consent,
+        },
+      })
+    } catch (e) {
+      setError(String(e))
+    } finally {
+      setOpening(false)
+    }
+  }
+
+  return (
+    <div className="flex flex-col gap-2 rounded-md border p-3">
+      <div className="flex items-center gap-2">
+        <TkInput
+          placeholder="user@support-host[:port]"
+          className="font-mono"
+          value={endpoint}
+          onChange={(e) => setEndpoint(e.target.value)}
+        />
+        <TkInput
+          type="number"
+          min={1}
+          max={120}
+          className="w-24"
+          value={minutes}
+          onChange={(e) => setMinutes(e.target.value)}
+        />
+        <span className="text-sm text-muted-foreground">minutes</span>
+      </div>
+      <div className="flex items-center gap-2">
+        <TkCheckbox id="support-consent" checked={consent} onCheckedChange={(checked) => setConsent(checked === true)} />
+        <TkLabel htmlFor="support-consent">
+          Let support read the installer status, step transcript and logs (secrets are redacted) for this long
+        </TkLabel>
+      </div>
+      <div className="flex items-center gap-2">
+        <TkButton size="sm" disabled={!consent || !endpoint || opening} onClick={open}>
+          Open support session
+        </TkButton>
+        {error && <span className="text-sm text-destructive">{error}</span>}
+      </div>
+    </div>
+  )
+}
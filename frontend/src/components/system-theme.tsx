@@ -0,0 +1,40 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+import { useEffect } from "react"
+import { invoke } from "@tauri-apps/api/core"
+import { listen } from "@tauri-apps/api/event"
+
+// Shape returned by the `get_system_theme` command
+type SystemTheme = "light" | "dark"
+
+// Follows the desktop's dark/light mode unless a theme was picked explicitly
+export default function SystemThemeSync() {
+  useEffect(() => {
+    let unlisten: (() => void) | undefined
+
+    const apply = (theme: SystemTheme) => {
+      const stored = localStorage.getItem("theme")
+      if (stored && stored !== "system") return
+      const root = window.document.documentElement
+      root.classList.remove("light", "dark")
+      root.classList.add(theme)
+    }
+
+    invoke<SystemTheme>("get_system_theme")
+      .then(apply)
+      .catch((error) => console.error("Failed to load system theme:", error))
+
+    listen<SystemTheme>("system-theme-changed", (event) => {
+      apply(event.payload)
+    }).then((fn) => {
+      unlisten = fn
+    })
+
+    return () => unlisten?.()
+  }, [])
+
+  return null
+}
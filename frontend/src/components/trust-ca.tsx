@@ -0,0 +1,126 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+import { useState, useEffect } from "react"
+import { invoke } from "@tauri-apps/api/core"
+import { TkButton } from "thinkube-style/components/buttons-badges"
+import { TkCheckbox, TkLabel } from "thinkube-style/components/forms-inputs"
+import { AlertCircle, CheckCircle2, Loader2, MinusCircle, ShieldCheck } from "lucide-react"
+
+type Outcome = "pass" | "warn" | "fail" | "skipped"
+
+// Shapes returned by the trust commands
+type TrustResult = {
+  store: "system" | "nss"
+  location: string
+  outcome: Outcome
+  message: string
+}
+
+type TrustedCa = {
+  store: "system" | "nss"
+  location: string
+  subject: string
+}
+
+const outcomeClass: Record<Outcome, string> = {
+  pass: "text-success",
+  warn: "text-warning",
+  fail: "text-destructive",
+  skipped: "text-muted-foreground",
+}
+
+// Adds the cluster CA to this machine's trust stores so the dashboards
+// open without certificate warnings
+export default function TrustCa() {
+  const [trusted, setTrusted] = useState<TrustedCa[]>([])
+  const [consent, setConsent] = useState(false)
+  const [nss, setNss] = useState(true)
+  const [busy, setBusy] = useState(false)
+  const [results, setResults] = useState<TrustResult[]>([])
+  const [error, setError] = useState<string | null>(null)
+
+  const refresh = () =>
+    invoke<TrustedCa[]>("get_trusted_cluster_ca")
+      .then(setTrusted)
+      .catch((error) => console.error("Failed to load trusted CAs:", error))
+
+  useEffect(() => {
+    refresh()
+  }, [])
+
+  const run = async (command: string, args: Record<string, unknown>) => {
+    setBusy(true)
+    setError(null)
+    try {
+      setResults(await invoke<TrustResult[]>(command, args))
+    } catch (e) {
+      setError(String(e))
+    } finally {
+      setBusy(false)
+      refresh()
+    }
+  }
+
+  const sudoPassword = sessionStorage.getItem("sudoPassword") || null
+
+  return (
+    <div className="space-y-3">
+      <p className="text-sm text-muted-foreground">
+        The dashboards use certificates from the cluster CA, which browsers don't know yet. Trusting it on
+        this machine removes the certificate warnings.
+      </p>
+      {trusted.length > 0 ? (
+        <div className="space-y-1">
+          {trusted.map((ca) => (
+            <p key={`${ca.store}:${ca.location}`} className="text-xs flex items-center gap-1 text-success">
+              <ShieldCheck className="h-3 w-3" /> {ca.subject} trusted in {ca.location}
+            </p>
+          ))}
+          <TkButton
+            size="sm"
+            intent="secondary"
+            disabled={busy}
+            onClick={() => run("untrust_cluster_ca", { sudoPassword })}
+          >
+            Stop trusting the cluster CA
+          </TkButton>
+        </div>
+      ) : (
+        <>
+          <div className="flex items-center gap-2">
+            <TkCheckbox id="trust-consent" checked={consent} onCheckedChange={(checked) => setConsent(checked === true)} />
+            <TkLabel htmlFor="trust-consent">
+              Trust certificates from the cluster CA for every site on this machine
+            </TkLabel>
+          </div>
+          <div className="flex items-center gap-2">
+            <TkCheckbox id="trust-nss" checked={nss} onCheckedChange={(checked) => setNss(checked === true)} />
+            <TkLabel htmlFor="trust-nss">Also in Chrome and Firefox (Linux)</TkLabel>
+          </div>
+          <TkButton
+            size="sm"
+            className="gap-2"
+            disabled={!consent || busy}
+            onClick={() => run("trust_cluster_ca", { request: { nss, consent, sudo_password: sudoPassword } })}
+          >
+            {busy && <Loader2 className="h-4 w-4 animate-spin" />}
+            Trust the cluster CA
+          </TkButton>
+        </>
+      )}
+      {results.map((result, index) => {
+        const Icon =
+          result.outcome === "pass" ? CheckCircle2 : result.outcome === "skipped" ? MinusCircle : AlertCircle
+        return (
+          <p key={index} className={`text-xs flex items-start gap-1 ${outcomeClass[result.outcome]}`}>
+            <Icon className="h-3 w-3 mt-0.5 shrink-0" /> {result.message}
+          </p>
+        )
+      })}
+      {error && <p className="text-xs text-destructive">{error}</p>}
+    </div>
+  )
+}
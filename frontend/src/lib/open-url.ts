@@ -0,0 +1,26 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+import { invoke } from "@tauri-apps/api/core"
+
+// Open a documentation or service link in the user's browser. The shell only
+// opens https links to known hosts and the cluster's own domain.
+export async function openUrl(url: string) {
+  try {
+    await invoke("open_url", { url })
+  } catch (error) {
+    console.error("Failed to open link:", error)
+  }
+}
+
+// Send every external link click through openUrl instead of the webview
+export function routeExternalLinks() {
+  document.addEventListener("click", (event) => {
+    const link = (event.target as Element | null)?.closest?.("a[href]") as HTMLAnchorElement | null
+    if (!link || !/^https?:/i.test(link.href) || link.origin === window.location.origin) return
+    event.preventDefault()
+    openUrl(link.href)
+  })
+}
@@ -0,0 +1,19 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+import { invoke } from "@tauri-apps/api/core"
+
+// Have the Rust shell mask these values in every log and streamed output.
+// Call before the value is first sent to the backend.
+export async function registerSecrets(...values: (string | undefined | null)[]) {
+  for (const value of values) {
+    if (!value) continue
+    try {
+      await invoke("register_secret", { value })
+    } catch (error) {
+      console.error("Failed to register secret for redaction:", error)
+    }
+  }
+}
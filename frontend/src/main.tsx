@@ -9,6 +9,7 @@ import { BrowserRouter, Routes, Route, Navigate } from 'react-router-dom';
 import { TkAppHeader } from 'thinkube-style/components/utilities';
 import { TkThemeProvider } from 'thinkube-style/components/theme';
 import { TkToaster } from 'thinkube-style/components/feedback';
+import { invoke } from '@tauri-apps/api/core';
 import 'thinkube-style/styles.css';
 import './index.css';
 
@@ -30,11 +31,20 @@ import CompletePage from './pages/complete';
 import OverlaySetupPage from './pages/overlay-setup';
 import OverlayCredentialsPage from './pages/overlay-credentials';
 import TailscaleOperatorSetupPage from './pages/tailscale-operator-setup';
+import LogsPage from './pages/logs';
+import BackendStatusBanner from './components/backend-status';
+import SystemThemeSync from './components/system-theme';
+import { ScheduledInstallListener } from './components/scheduled-install';
+import { SupportTunnelBanner } from './components/support-tunnel';
+import { routeExternalLinks } from './lib/open-url';
 
 function App() {
   return (
     <div className="min-h-screen bg-background flex flex-col">
       <TkAppHeader title="Thinkube Installer" />
+      <BackendStatusBanner />
+      <SupportTunnelBanner />
+      <ScheduledInstallListener />
 
       <main className="flex-1">
         <Routes>
@@ -56,6 +66,7 @@ function App() {
           <Route path="/deploy" element={<DeployPage />} />
           <Route path="/installation" element={<InstallationPage />} />
           <Route path="/complete" element={<CompletePage />} />
+          <Route path="/logs" element={<LogsPage />} />
         </Routes>
       </main>
 
@@ -68,6 +79,8 @@ function App() {
   );
 }
 
+routeExternalLinks();
+
 ReactDOM.createRoot(document.getElementById('root')!).render(
   <React.StrictMode>
     <TkThemeProvider>
@@ -75,6 +88,15 @@ ReactDOM.createRoot(document.getElementById('root')!).render(
         <App />
       </BrowserRouter>
       <TkToaster />
+      <SystemThemeSync />
     </TkThemeProvider>
   </React.StrictMode>
 );
+
+// Two frames in, something has been painted; the shell relaunches with safer
+// renderer settings if this never arrives
+requestAnimationFrame(() =>
+  requestAnimationFrame(() => {
+    invoke('webview_rendered').catch((error) => console.error('Failed to report render:', error));
+  })
+);
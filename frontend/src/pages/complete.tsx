@@ -5,6 +5,7 @@
 
 import { useState, useEffect, useMemo } from "react"
 import { useNavigate } from "react-router-dom"
+import { invoke } from "@tauri-apps/api/core"
 import { TkCard, TkCardContent, TkCardHeader, TkCardTitle } from "thinkube-style/components/cards-data"
 import { TkAlert, TkAlertDescription, tkToast } from "thinkube-style/components/feedback"
 import { TkButton } from "thinkube-style/components/buttons-badges"
@@ -23,6 +24,10 @@ import {
   EyeOff,
   Copy
 } from "lucide-react"
+import ServiceQr from "@/components/service-qr"
+import SmokeTests from "@/components/smoke-tests"
+import ServiceHealth from "@/components/service-health"
+import TrustCa from "@/components/trust-ca"
 
 interface DeploymentData {
   domainName: string
@@ -121,6 +126,16 @@ export default function Complete() {
     }
   }
 
+  // Secrets go through the Rust shell, which clears the clipboard again
+  const copySecret = async (value: string) => {
+    try {
+      const secs = await invoke<number>("copy_secret", { value })
+      tkToast.success(`Copied; the clipboard will be cleared in ${secs}s`)
+    } catch (err) {
+      tkToast.error(`Failed to copy: ${err}`)
+    }
+  }
+
   const downloadLogs = () => {
     try {
       window.location.href = "/api/logs/download"
@@ -129,6 +144,59 @@ export default function Complete() {
     }
   }
 
+  // Written by the Rust shell with 0600 permissions wherever the user picks
+  const exportFile = async (kind: "kubeconfig" | "credentials", contents?: string) => {
+    try {
+      const path = await invoke<string | null>("export_file", {
+        kind,
+        contents,
+        fileName: kind === "kubeconfig" ? `${deploymentData.clusterName || "thinkube"}-kubeconfig.yaml` : undefined,
+      })
+      if (path) tkToast.success(`Saved to ${path}`)
+    } catch (err) {
+      tkToast.error(`Failed to save: ${err}`)
+    }
+  }
+
+  const exportCredentials = () =>
+    exportFile(
+      "credentials",
+      [
+        `Domain: ${deploymentData.domainName}`,
+        `Thinkube Control: https://control.${deploymentData.domainName}`,
+        `Code Server: https://code.${deploymentData.domainName}`,
+        `Username: ${deploymentData.adminUsername}`,
+        `Password: ${deploymentData.adminPassword}`,
+        `Control plane: ${deploymentData.systemUsername}@${deploymentData.controlPlaneIP}`,
+        "",
+      ].join("\n"),
+    )
+
+  // Rendered by the Rust shell; "pdf" opens the print dialog instead
+  const exportSummary = async (format: "html" | "pdf") => {
+    const clusterNodes = JSON.parse(sessionStorage.getItem("clusterNodes") || "[]")
+    try {
+      const path = await invoke<string | null>("export_summary", {
+        format,
+        summary: {
+          cluster_name: deploymentData.clusterName || null,
+          domain: deploymentData.domainName,
+          admin_username: deploymentData.adminUsername,
+          overlay_provider: deploymentData.overlayProvider || null,
+          nodes: clusterNodes.map((n: any) => ({
+            hostname: n.hostname,
+            ip: n.ip,
+            role: n.role,
+            gpu: n.hasGPU && n.gpuInfo ? `${n.gpuInfo.gpu_count}× ${n.gpuInfo.gpu_model}` : null,
+          })),
+        },
+      })
+      if (path && format === "html") tkToast.success(`Saved to ${path}`)
+    } catch (err) {
+      tkToast.error(`Failed to export the summary: ${err}`)
+    }
+  }
+
   const closeInstaller = () => {
     // This is a Tauri desktop application
     if (typeof window !== 'undefined' && (window as any).__TAURI__) {
@@ -188,6 +256,7 @@ export default function Complete() {
               <p className="text-sm text-muted-foreground mt-1">
                 Central management dashboard for your Thinkube platform
               </p>
+              <ServiceQr url={`https://control.${deploymentData.domainName}`} />
             </div>
           </div>
 
@@ -208,6 +277,7 @@ export default function Complete() {
               <p className="text-sm text-muted-foreground mt-1">
                 VS Code in the browser with CI/CD integration
               </p>
+              <ServiceQr url={`https://code.${deploymentData.domainName}`} />
             </div>
           </div>
 
@@ -228,6 +298,40 @@ export default function Complete() {
         </TkCardContent>
       </TkCard>
 
+      {/* Post-install checks */}
+      <TkCard className="mb-6">
+        <TkCardHeader>
+          <TkCardTitle>Service Checks</TkCardTitle>
+        </TkCardHeader>
+        <TkCardContent>
+          <SmokeTests
+            domain={deploymentData.domainName}
+            controlPlane={{ host: deploymentData.controlPlaneIP, user: deploymentData.systemUsername }}
+          />
+        </TkCardContent>
+      </TkCard>
+
+      <TkCard className="mb-6">
+        <TkCardHeader>
+          <TkCardTitle>Certificate Trust</TkCardTitle>
+        </TkCardHeader>
+        <TkCardContent>
+          <TrustCa />
+        </TkCardContent>
+      </TkCard>
+
+      <TkCard className="mb-6">
+        <TkCardHeader>
+          <TkCardTitle>Cluster Status</TkCardTitle>
+        </TkCardHeader>
+        <TkCardContent>
+          <ServiceHealth
+            domain={deploymentData.domainName}
+            nodes={[{ host: deploymentData.controlPlaneIP, user: deploymentData.systemUsername }]}
+          />
+        </TkCardContent>
+      </TkCard>
+
       {/* Tailscale-specific reachability summary */}
       {deploymentData.overlayProvider === "tailscale" && (
         <TkCard className="mb-6">
@@ -356,7 +460,7 @@ export default function Complete() {
                   <TkButton
                     intent="ghost"
                     size="sm"
-                    onClick={() => copyToClipboard(deploymentData.adminPassword)}
+                    onClick={() => copySecret(deploymentData.adminPassword)}
                   >
                     <Copy className="w-4 h-4" />
                   </TkButton>
@@ -404,6 +508,42 @@ export default function Complete() {
               <Download className="w-5 h-5" />
               Download Logs
             </TkButton>
+
+            <TkButton
+              intent="secondary"
+              className="gap-2"
+              onClick={() => exportFile("kubeconfig")}
+            >
+              <Download className="w-5 h-5" />
+              Save kubeconfig
+            </TkButton>
+
+            <TkButton
+              intent="secondary"
+              className="gap-2"
+              onClick={exportCredentials}
+            >
+              <Download className="w-5 h-5" />
+              Save credentials
+            </TkButton>
+
+            <TkButton
+              intent="secondary"
+              className="gap-2"
+              onClick={() => exportSummary("html")}
+            >
+              <Download className="w-5 h-5" />
+              Save summary
+            </TkButton>
+
+            <TkButton
+              intent="secondary"
+              className="gap-2"
+              onClick={() => exportSummary("pdf")}
+            >
+              <Download className="w-5 h-5" />
+              Print summary / PDF
+            </TkButton>
           </div>
         </TkCardContent>
       </TkCard>
@@ -22,8 +22,34 @@ import {
 } from "lucide-react"
 import { cn } from "@/lib/utils"
 import axios from "@/utils/axios"
+import { registerSecrets } from "@/lib/secrets"
+import { invoke } from "@tauri-apps/api/core"
+import DomainVerification from "@/components/domain-verification"
+import PublicExposure from "@/components/public-exposure"
+import KeyboardLayoutSetting from "@/components/keyboard-layout"
 
 // TypeScript Interfaces
+interface CloudflareTokenReport {
+  valid: boolean
+  token_status: string | null
+  zone_name: string | null
+  missing: string[]
+  message: string
+}
+
+interface GithubAccessReport {
+  valid: boolean
+  auth_ok: boolean
+  username: string | null
+  auth_error: string | null
+  scopes_ok: boolean | null
+  missing_scopes: string[]
+  org_ok: boolean | null
+  org_error: string | null
+  ssh_ok: boolean
+  ssh_error: string | null
+}
+
 interface ConfigData {
   clusterName: string
   domainName: string
@@ -173,19 +199,21 @@ export default function Configuration() {
     setErrors(prev => ({ ...prev, cloudflareToken: '' }))
 
     try {
-      const response = await axios.post('/api/verify-cloudflare', {
+      const report = await invoke<CloudflareTokenReport>('validate_cloudflare_token', {
         token: config.cloudflareToken,
         domain: config.domainName
       })
 
-      if (response.data.valid) {
+      if (report.valid) {
         setCloudflareVerified(true)
         setErrors(prev => ({ ...prev, cloudflareToken: '' }))
         return true
       } else {
         setErrors(prev => ({
           ...prev,
-          cloudflareToken: response.data.message || 'Token does not have access to this domain'
+          cloudflareToken: report.missing.length > 0 && report.zone_name
+            ? `${report.message}. Edit the token in the Cloudflare dashboard and add: ${report.missing.join(', ')}`
+            : report.message
         }))
         setCloudflareVerified(false)
         return false
@@ -193,7 +221,7 @@ export default function Configuration() {
     } catch (error: any) {
       setErrors(prev => ({
         ...prev,
-        cloudflareToken: error.response?.data?.detail || 'Failed to verify Cloudflare token'
+        cloudflareToken: typeof error === 'string' ? error : 'Failed to verify Cloudflare token'
       }))
       setCloudflareVerified(false)
       return false
@@ -214,18 +242,25 @@ export default function Configuration() {
     setErrors(prev => ({ ...prev, githubToken: '' }))
 
     try {
-      const response = await axios.post('/api/verify-github', {
-        token: config.githubToken
+      const report = await invoke<GithubAccessReport>('validate_github_access', {
+        token: config.githubToken,
+        org: config.githubOrg || null
       })
 
-      if (response.data.valid) {
+      if (report.valid) {
         setGithubVerified(true)
         setErrors(prev => ({ ...prev, githubToken: '' }))
         return true
       } else {
+        const problems = [
+          report.auth_error,
+          report.missing_scopes.length > 0 && `Token missing required scopes: ${report.missing_scopes.join(', ')}`,
+          report.org_error,
+          report.ssh_error,
+        ].filter(Boolean)
         setErrors(prev => ({
           ...prev,
-          githubToken: response.data.message || 'Invalid GitHub token'
+          githubToken: problems.join('. ') || 'Invalid GitHub token'
         }))
         setGithubVerified(false)
         return false
@@ -233,7 +268,7 @@ export default function Configuration() {
     } catch (error: any) {
       setErrors(prev => ({
         ...prev,
-        githubToken: error.response?.data?.detail || 'Failed to verify GitHub token'
+        githubToken: typeof error === 'string' ? error : 'Failed to verify GitHub token'
       }))
       setGithubVerified(false)
       return false
@@ -352,8 +387,12 @@ export default function Configuration() {
       JSON.stringify({ ...existingLocal, ...configToSave }),
     )
 
+    registerSecrets(config.cloudflareToken, config.githubToken, config.hfToken)
     sessionStorage.setItem('cloudflareToken', config.cloudflareToken)
     sessionStorage.setItem('domainName', config.domainName)
+    invoke('set_cluster_domain', { domain: config.domainName }).catch((error) =>
+      console.error('Failed to save the cluster domain:', error),
+    )
     sessionStorage.setItem('clusterName', config.clusterName)
 
     if (config.githubToken) {
@@ -472,9 +511,17 @@ export default function Configuration() {
                   <p className="text-xs text-destructive">{errors.cloudflareToken}</p>
                 )}
                 {cloudflareVerified && (
-                  <p className="text-xs text-success">✓ Token has access to {config.domainName}</p>
+                  <>
+                    <p className="text-xs text-success">✓ Token has access to {config.domainName}</p>
+                    <DomainVerification domain={config.domainName} cloudflareToken={config.cloudflareToken} />
+                    <PublicExposure domain={config.domainName} />
+                  </>
                 )}
               </div>
+
+              <div className="md:col-span-2">
+                <KeyboardLayoutSetting />
+              </div>
             </div>
           </TkCardContent>
         </TkCard>
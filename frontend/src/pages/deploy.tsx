@@ -5,6 +5,8 @@
 
 import { useReducer, useEffect, useRef, useState } from "react"
 import { useNavigate } from "react-router-dom"
+import { invoke } from "@tauri-apps/api/core"
+import { listen } from "@tauri-apps/api/event"
 import { TkCard, TkCardContent } from "thinkube-style/components/cards-data"
 import { TkButton } from "thinkube-style/components/buttons-badges"
 import { TkAlert, TkAlertDescription, tkToast } from "thinkube-style/components/feedback"
@@ -21,6 +23,14 @@ interface Playbook {
   extraVars?: Record<string, any>
 }
 
+// Shape returned by the `estimate_install` command and `install-eta` events
+interface InstallEta {
+  completed: number
+  total: number
+  remaining_ms: number
+  finish_at: number
+}
+
 interface PlaybookLog {
   status: 'success' | 'failed' | 'running'
   logs: string
@@ -135,6 +145,16 @@ function deployReducer(state: DeployState, action: DeployAction): DeployState {
   }
 }
 
+const PHASE_NAMES: Record<string, string> = {
+  initial: "Initial setup",
+  kubernetes: "Kubernetes and core services"
+}
+
+// Desktop notification when the installer isn't focused; see `notify_install_event`
+const notify = (event: Record<string, unknown>) => {
+  invoke("notify_install_event", { event }).catch((error) => console.error("Failed to notify:", error))
+}
+
 const initialState: DeployState = {
   queue: [],
   currentIndex: 0,
@@ -152,6 +172,7 @@ export default function Deploy() {
   const [state, dispatch] = useReducer(deployReducer, initialState)
   const executorRef = useRef<any>(null)
   const currentLogsRef = useRef<string>('')
+  const [eta, setEta] = useState<InstallEta | null>(null)
 
   // Build playbook queue
   const buildQueue = async (): Promise<Playbook[]> => {
@@ -515,7 +536,11 @@ export default function Deploy() {
         setTimeout(() => {
           dispatch({ type: 'RESTORE_AFTER_ROLLBACK' })
         }, 100)
+        notify({ kind: 'failed', step: currentPlaybook.title, message: result.message || null })
       } else {
+        if (result.status !== 'cancelled') {
+          notify({ kind: 'failed', step: currentPlaybook.title, message: result.message || null })
+        }
         dispatch({
           type: 'PLAYBOOK_FAILED',
           playbookId: currentPlaybook.id,
@@ -538,14 +563,23 @@ export default function Deploy() {
         setTimeout(() => {
           dispatch({ type: 'RESTORE_AFTER_ROLLBACK' })
         }, 100)
+        const last = state.currentIndex + 1 >= state.queue.length
+        if (last) {
+          notify({ kind: 'input_required', prompt: 'Rollback finished. Open the installer and click Retry to continue.' })
+        }
       } else {
         // Auto-continue to next playbook for normal deployment
         const nextIndex = state.currentIndex + 1
         if (nextIndex < state.queue.length) {
+          const nextPhase = state.queue[nextIndex].phase
+          if (nextPhase !== currentPlaybook.phase) {
+            notify({ kind: 'phase_complete', phase: PHASE_NAMES[currentPlaybook.phase] || currentPlaybook.phase })
+          }
           setTimeout(() => {
             dispatch({ type: 'START_PLAYBOOK', index: nextIndex })
           }, 100)
         } else {
+          notify({ kind: 'install_complete' })
           dispatch({ type: 'COMPLETE' })
         }
       }
@@ -687,6 +721,18 @@ export default function Deploy() {
       const queue = await buildQueue()
       dispatch({ type: 'INIT_QUEUE', queue })
 
+      const downloads = JSON.parse(sessionStorage.getItem('downloadEstimate') || 'null')
+      invoke<InstallEta>('estimate_install', {
+        plan: {
+          steps: queue.map(p => ({ step: p.name, title: p.title })),
+          download_bytes: downloads?.total_bytes ?? null,
+          throughput_kib_s: downloads?.throughput_kib_s ?? null,
+          disk_write_mib_s: null,
+        },
+      })
+        .then(setEta)
+        .catch((error) => console.error('Failed to estimate the install time:', error))
+
       // Always start fresh — no resume from previous sessions
       setTimeout(() => {
         dispatch({ type: 'START_PLAYBOOK', index: 0 })
@@ -704,6 +750,33 @@ export default function Deploy() {
     }
   }, [state.currentIndex, state.status])
 
+  // Refined as each step is recorded
+  useEffect(() => {
+    let unlisten: (() => void) | undefined
+    listen<InstallEta>('install-eta', (event) => {
+      setEta(event.payload)
+    }).then((fn) => {
+      unlisten = fn
+    })
+    return () => unlisten?.()
+  }, [])
+
+  // Mirror overall progress on the Dock/taskbar icon; rollbacks don't count
+  useEffect(() => {
+    if (state.queue.length === 0 || state.queue[0].phase === 'rollback') return
+    invoke('set_install_progress', {
+      state: state.status,
+      completed: state.status === 'complete' ? state.queue.length : state.currentIndex,
+      total: state.queue.length
+    }).catch((error) => console.error('Failed to report progress:', error))
+  }, [state.currentIndex, state.status, state.queue])
+
+  useEffect(() => {
+    return () => {
+      invoke('set_install_progress', { state: 'idle', completed: 0, total: 0 }).catch(() => {})
+    }
+  }, [])
+
   // Derive subway steps - use playbook titles + final "Complete" step
   const playbookTitles = [...state.queue.map(p => p.title), 'Deployment Complete']
   const currentPlaybookNumber = state.status === 'complete'
@@ -770,6 +843,12 @@ ${log.logs}`
             current={currentPlaybookNumber}
             isRunning={state.status === 'running'}
           />
+          {eta && state.status === 'running' && (
+            <p className="mt-4 text-sm text-muted-foreground">
+              About {Math.max(1, Math.round(eta.remaining_ms / 60000))} min left, done around{' '}
+              {new Date(eta.finish_at).toLocaleTimeString([], { hour: '2-digit', minute: '2-digit' })}
+            </p>
+          )}
         </TkCardContent>
       </TkCard>
 
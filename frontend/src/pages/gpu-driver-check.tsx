@@ -18,6 +18,7 @@ import {
   TkSelectValue
 } from "thinkube-style/components/forms-inputs"
 import { CheckCircle2, XCircle, Loader2, AlertCircle } from "lucide-react"
+import { invoke } from "@tauri-apps/api/core"
 import axios from "@/utils/axios"
 
 interface Node {
@@ -34,6 +35,23 @@ interface Node {
   gpu_supported?: boolean
 }
 
+// Platform part of the report returned by the `detect_nvidia_stack` command
+interface NvidiaPlatform {
+  kind: "generic" | "jetson" | "dgx"
+  model?: string
+  l4t_release?: string
+  dgx_os_version?: string
+  manage_driver: boolean
+  runtime_mode: "auto" | "csv" | "cdi"
+}
+
+interface NvidiaReport {
+  host: string
+  error?: string
+  platform: NvidiaPlatform
+  driver_installed: boolean
+}
+
 interface Summary {
   ready: number
   needs_install: number
@@ -57,6 +75,7 @@ export default function GpuDriverCheck() {
     error: 0
   })
   const [decisions, setDecisions] = useState<Record<string, string>>({})
+  const [platforms, setPlatforms] = useState<Record<string, NvidiaReport>>({})
 
   useEffect(() => {
     detectGpuDrivers()
@@ -96,10 +115,23 @@ export default function GpuDriverCheck() {
         ssh_key: server.ssh_key
       }))
 
-      // Call detection API
-      const response = await axios.post("/api/gpu/detect-drivers", {
-        nodes: nodeList
-      })
+      // Call detection API; the shell fingerprints Jetson and DGX nodes alongside
+      const [response, reports] = await Promise.all([
+        axios.post("/api/gpu/detect-drivers", {
+          nodes: nodeList
+        }),
+        invoke<NvidiaReport[]>("detect_nvidia_stack", {
+          hosts: discoveredServers.map((server: any) => ({
+            host: server.ip,
+            user: server.username,
+            port: null,
+            identity_file: null
+          }))
+        }).catch(() => [] as NvidiaReport[])
+      ])
+      const platformByIp: Record<string, NvidiaReport> = {}
+      reports.filter((r) => !r.error).forEach((r) => (platformByIp[r.host] = r))
+      setPlatforms(platformByIp)
 
       // Ensure response has expected structure
       if (!response.data || !response.data.nodes) {
@@ -123,7 +155,9 @@ export default function GpuDriverCheck() {
         const initialDecisions: Record<string, string> = {}
         response.data.nodes.forEach((node: Node) => {
           if (node.action_required === "install" || node.action_required === "upgrade") {
-            initialDecisions[node.ip] = ""
+            // JetPack and DGX OS own the driver; only a manual upgrade or exclusion applies
+            const managed = platformByIp[node.ip]?.platform.manage_driver === false
+            initialDecisions[node.ip] = !managed ? "" : node.action_required === "upgrade" ? "abort" : "exclude"
           }
         })
         setDecisions(initialDecisions)
@@ -219,8 +253,13 @@ export default function GpuDriverCheck() {
         let gpu_enabled = true
         let driver_preinstalled = false
         let reason = null
+        const platform = platforms[node.ip]?.platform
 
-        if (!node.gpu_detected) {
+        if (platform?.kind === "jetson" && platforms[node.ip].driver_installed) {
+          // The integrated GPU isn't on PCI, so the backend reports no GPU
+          gpu_enabled = true
+          driver_preinstalled = true
+        } else if (!node.gpu_detected) {
           gpu_enabled = false
           reason = "No GPU detected"
         } else if (node.driver_status === "unsupported_gpu") {
@@ -246,6 +285,8 @@ export default function GpuDriverCheck() {
           driver_preinstalled,
           driver_version: node.driver_version,
           needs_driver_install: decisions[node.ip] === "install",
+          platform: platform?.kind ?? "generic",
+          container_runtime_mode: platform?.runtime_mode ?? "auto",
           reason
         }
       })
@@ -366,6 +407,16 @@ export default function GpuDriverCheck() {
                     <td className="py-2 px-4">
                       <div className="font-bold">{node.hostname}</div>
                       <div className="text-sm text-muted-foreground">{node.ip}</div>
+                      {platforms[node.ip] && platforms[node.ip].platform.kind !== "generic" && (
+                        <div className="text-xs text-muted-foreground">
+                          {platforms[node.ip].platform.model ||
+                            (platforms[node.ip].platform.kind === "jetson" ? "Jetson" : "DGX")}
+                          {platforms[node.ip].platform.l4t_release &&
+                            ` · L4T ${platforms[node.ip].platform.l4t_release}`}
+                          {platforms[node.ip].platform.dgx_os_version &&
+                            ` · DGX OS ${platforms[node.ip].platform.dgx_os_version}`}
+                        </div>
+                      )}
                     </td>
                     <td className="py-2 px-4">
                       {node.gpu_detected ? (
@@ -461,9 +512,11 @@ export default function GpuDriverCheck() {
                             <TkSelectValue placeholder="-- Choose action --" />
                           </TkSelectTrigger>
                           <TkSelectContent>
-                            <TkSelectItem value="install">
-                              Install drivers automatically
-                            </TkSelectItem>
+                            {platforms[node.ip]?.platform.manage_driver !== false && (
+                              <TkSelectItem value="install">
+                                Install drivers automatically
+                              </TkSelectItem>
+                            )}
                             <TkSelectItem value="exclude">
                               Exclude from GPU (CPU-only)
                             </TkSelectItem>
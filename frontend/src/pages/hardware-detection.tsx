@@ -27,6 +27,16 @@ import {
 } from "lucide-react"
 import axios from "@/utils/axios"
 
+// One entry per GPU nvidia-smi reports
+interface GpuDetail {
+  index: number
+  name: string
+  memory_mib?: number | null
+  unified_memory?: boolean
+  compute_cap?: string | null
+  mig_mode?: "enabled" | "disabled" | null
+}
+
 interface Hardware {
   cpu_cores: number
   memory_gb: number
@@ -38,6 +48,9 @@ interface Hardware {
   driver_status?: "compatible" | "old" | "missing" | "unsupported_gpu" | "unknown"
   compute_cap?: string
   gpu_supported?: boolean
+  gpus?: GpuDetail[]
+  gpu_memory_mib?: number
+  feasible_workloads?: string[]
   architecture?: string
   lvm_expandable?: boolean
   lvm_free_gb?: number
@@ -59,6 +72,22 @@ interface Server {
   error?: string | null
 }
 
+// Labels for the backend's GPU_WORKLOADS, smallest first
+const WORKLOAD_LABELS: Record<string, string> = {
+  embeddings: "Embeddings and rerankers",
+  "llm-small": "Small LLMs (up to 8B, quantized)",
+  "image-generation": "Image generation",
+  "llm-medium": "LLMs up to 8B at full precision",
+  "fine-tuning": "LoRA fine-tuning",
+  "llm-large": "LLMs up to 70B, quantized",
+}
+
+function formatVram(gpu: GpuDetail) {
+  if (!gpu.memory_mib) return "VRAM unknown"
+  const gb = Math.round(gpu.memory_mib / 1024)
+  return gpu.unified_memory ? `${gb} GB shared` : `${gb} GB`
+}
+
 export default function HardwareDetection() {
   const navigate = useNavigate()
 
@@ -82,6 +111,12 @@ export default function HardwareDetection() {
     )
   }, [servers])
 
+  // Workloads at least one node has a GPU for
+  const feasibleWorkloads = useMemo(() => {
+    const feasible = new Set(servers.flatMap((server) => server.hardware?.feasible_workloads || []))
+    return Object.keys(WORKLOAD_LABELS).filter((id) => feasible.has(id))
+  }, [servers])
+
   const hasDetectionErrors = useMemo(() => {
     return servers.some((server) => server.error)
   }, [servers])
@@ -246,6 +281,8 @@ export default function HardwareDetection() {
         interface: s.network?.interface
       }))
     sessionStorage.setItem("serverNetworkInfo", JSON.stringify(networkInfo))
+    // Pre-selects the AI workloads later steps offer
+    sessionStorage.setItem("feasibleWorkloads", JSON.stringify(feasibleWorkloads))
 
     navigate("/role-assignment")
   }
@@ -339,6 +376,19 @@ export default function HardwareDetection() {
                   </div>
                 )}
 
+                {/* Per-GPU memory, compute capability and MIG mode */}
+                {server.hardware?.gpus && server.hardware.gpus.length > 0 && (
+                  <div className="mt-4 flex flex-wrap gap-2">
+                    {server.hardware.gpus.map((gpu) => (
+                      <TkBadge key={gpu.index} appearance="outlined">
+                        GPU {gpu.index}: {gpu.name} · {formatVram(gpu)}
+                        {gpu.compute_cap && ` · CC ${gpu.compute_cap}`}
+                        {gpu.mig_mode === "enabled" && " · MIG"}
+                      </TkBadge>
+                    ))}
+                  </div>
+                )}
+
                 {/* LVM Expansion Notice */}
                 {server.hardware?.lvm_expandable && (
                   <TkAlert className="mt-4 bg-warning/10 text-warning border-warning/20">
@@ -396,6 +446,16 @@ export default function HardwareDetection() {
                 </li>
               )}
             </ul>
+            {feasibleWorkloads.length > 0 && (
+              <>
+                <h3 className="font-semibold mt-4 mb-2">AI Workloads the GPUs Can Run</h3>
+                <ul className="space-y-1 text-sm text-muted-foreground">
+                  {feasibleWorkloads.map((id) => (
+                    <li key={id}>{WORKLOAD_LABELS[id]}</li>
+                  ))}
+                </ul>
+              </>
+            )}
           </TkCardContent>
         </TkCard>
       )}
@@ -14,6 +14,7 @@ import { TkLabel } from "thinkube-style/components/forms-inputs"
 import { TkPageWrapper } from "thinkube-style/components/utilities"
 import { ChevronRight } from "lucide-react"
 import { getAnsibleLogClassName, getAnsibleLogPrefix } from "@/lib/ansible-log-utils"
+import { withApiToken } from "@/utils/axios"
 
 interface InstallationStatus {
   phase: 'idle' | 'starting' | 'running' | 'completed' | 'failed'
@@ -73,35 +74,35 @@ export default function Installation() {
   }
 
   const connectWebSocket = () => {
-    // In Tauri, we need to connect directly to localhost:8000
+    // In Tauri, we need to connect directly to 127.0.0.1:8000
     // Tauri v2 uses tauri: protocol
     const isTauri = typeof window !== 'undefined' && window.location.protocol === 'tauri:'
 
     // Determine WebSocket base URL
     let wsBase: string
     if (isTauri) {
-      // Tauri app - always connect to localhost:8000
-      wsBase = 'ws://localhost:8000'
+      // Tauri app - always connect to 127.0.0.1:8000
+      wsBase = 'ws://127.0.0.1:8000'
     } else if (
       typeof window !== 'undefined' &&
       window.location.hostname === 'localhost' &&
       window.location.port === '5173'
     ) {
       // Development mode (Vite dev server)
-      wsBase = 'ws://localhost:8000'
+      wsBase = 'ws://127.0.0.1:8000'
     } else {
       // Production web deployment
       wsBase = typeof window !== 'undefined'
         ? `${window.location.protocol === 'https:' ? 'wss:' : 'ws:'}//${window.location.host}`
-        : 'ws://localhost:8000'
+        : 'ws://127.0.0.1:8000'
     }
 
     // Try /ws first, then /api/ws
     let wsUrl = `${wsBase}/ws`
     let retryWithApi = true
 
-    const createConnection = (url: string) => {
-      const ws = new WebSocket(url)
+    const createConnection = async (url: string) => {
+      const ws = new WebSocket(await withApiToken(url))
       wsRef.current = ws
 
       ws.onopen = () => {
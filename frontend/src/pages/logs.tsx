@@ -0,0 +1,114 @@
+/*
+ * Copyright 2025 Alejandro Martínez Corriá and the Thinkube contributors
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+import { useState, useEffect, useRef } from "react"
+import { invoke } from "@tauri-apps/api/core"
+import { listen } from "@tauri-apps/api/event"
+import { TkCheckbox, TkLabel } from "thinkube-style/components/forms-inputs"
+import { SupportTunnelForm } from "../components/support-tunnel"
+
+// Shape of the `backend-log` event emitted by the Rust shell
+interface BackendLogLine {
+  stream: "stdout" | "stderr"
+  line: string
+  timestamp: number
+}
+
+// Returned by `get_shell_logs`: the Rust shell's own log
+interface ShellLogLine {
+  timestamp: number
+  level: "ERROR" | "WARN" | "INFO" | "DEBUG" | "TRACE"
+  target: string
+  spans: string[]
+  message: string
+}
+
+const MAX_LINES = 5000
+const SHELL_LOG_POLL_MS = 2000
+
+export default function Logs() {
+  const [lines, setLines] = useState<BackendLogLine[]>([])
+  const [shellLines, setShellLines] = useState<ShellLogLine[]>([])
+  const [showShell, setShowShell] = useState(false)
+  const [autoScroll, setAutoScroll] = useState(true)
+  const containerRef = useRef<HTMLDivElement>(null)
+
+  useEffect(() => {
+    let unlisten: (() => void) | undefined
+
+    // Backfill what was logged before this window opened, then follow
+    invoke<BackendLogLine[]>("get_backend_log")
+      .then((initial) => setLines(initial))
+      .catch((error) => console.error("Failed to load backend log:", error))
+
+    listen<BackendLogLine>("backend-log", (event) => {
+      setLines((prev) => [...prev, event.payload].slice(-MAX_LINES))
+    }).then((fn) => {
+      unlisten = fn
+    })
+
+    return () => unlisten?.()
+  }, [])
+
+  // The shell log has no event; poll it while it is shown
+  useEffect(() => {
+    if (!showShell) return
+    const load = () =>
+      invoke<ShellLogLine[]>("get_shell_logs", { limit: MAX_LINES })
+        .then(setShellLines)
+        .catch((error) => console.error("Failed to load shell log:", error))
+    load()
+    const timer = setInterval(load, SHELL_LOG_POLL_MS)
+    return () => clearInterval(timer)
+  }, [showShell])
+
+  useEffect(() => {
+    if (autoScroll && containerRef.current) {
+      containerRef.current.scrollTop = containerRef.current.scrollHeight
+    }
+  }, [lines, shellLines, showShell, autoScroll])
+
+  return (
+    <div className="flex flex-col h-[calc(100vh-8rem)] p-4 gap-2">
+      <SupportTunnelForm />
+      <div className="flex items-center gap-2">
+        <TkCheckbox
+          id="auto-scroll"
+          checked={autoScroll}
+          onCheckedChange={(checked) => setAutoScroll(checked === true)}
+        />
+        <TkLabel htmlFor="auto-scroll">Auto-scroll</TkLabel>
+        <TkCheckbox
+          id="show-shell"
+          className="ml-4"
+          checked={showShell}
+          onCheckedChange={(checked) => setShowShell(checked === true)}
+        />
+        <TkLabel htmlFor="show-shell">Installer shell log</TkLabel>
+      </div>
+      <div
+        ref={containerRef}
+        className="flex-1 overflow-auto rounded-md bg-muted p-3 font-mono text-xs"
+      >
+        {showShell && shellLines.map((entry, index) => (
+          <div
+            key={index}
+            className={entry.level === "ERROR" || entry.level === "WARN" ? "text-destructive whitespace-pre-wrap" : "whitespace-pre-wrap"}
+          >
+            {entry.level} {entry.spans.length > 0 && `${entry.spans.join(":")}: `}{entry.message}
+          </div>
+        ))}
+        {!showShell && lines.map((entry, index) => (
+          <div
+            key={index}
+            className={entry.stream === "stderr" ? "text-destructive whitespace-pre-wrap" : "whitespace-pre-wrap"}
+          >
+            {entry.line}
+          </div>
+        ))}
+      </div>
+    </div>
+  )
+}
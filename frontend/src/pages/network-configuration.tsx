@@ -34,6 +34,9 @@ import {
 } from "lucide-react";
 import { cn } from "@/lib/utils";
 import axios from "@/utils/axios";
+import MirrorSelection from "@/components/mirror-selection";
+import Ipv6Readiness from "@/components/ipv6-readiness";
+import LbRangeCheck from "@/components/lb-range-check";
 
 // TypeScript Interfaces
 interface NetworkConfig {
@@ -1110,6 +1113,10 @@ networkConfig.overlayCIDR
                       </li>
                     </ul>
                   </div>
+                  <LbRangeCheck
+                    start={`${getNetworkBase(networkConfig.overlayCIDR)}.${networkConfig.lbStartOctet}`}
+                    end={`${getNetworkBase(networkConfig.overlayCIDR)}.${networkConfig.lbEndOctet}`}
+                  />
                 </div>
               )}
           </TkCardContent>
@@ -1149,6 +1156,10 @@ networkConfig.overlayCIDR
         </TkCardContent>
       </TkCard>
 
+      <MirrorSelection />
+
+      <Ipv6Readiness />
+
       {/* Baremetal Servers */}
       <TkCard className="mb-6">
         <TkCardHeader>
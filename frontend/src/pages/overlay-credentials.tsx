@@ -28,6 +28,7 @@ import {
 } from "lucide-react"
 import { cn } from "@/lib/utils"
 import axios from "@/utils/axios"
+import { registerSecrets } from "@/lib/secrets"
 
 type OverlayProvider = "zerotier" | "tailscale"
 
@@ -219,6 +220,7 @@ export default function OverlayCredentials() {
         zerotierNetworkId: zt.networkId,
         zerotierApiToken: zt.apiToken,
       })
+      registerSecrets(zt.apiToken)
       sessionStorage.setItem("zerotierApiToken", zt.apiToken)
       sessionStorage.setItem("zerotierNetworkId", zt.networkId)
     } else {
@@ -229,6 +231,7 @@ export default function OverlayCredentials() {
         tailscaleAuthKey: ts.authKey,
         tailscaleApiToken: ts.apiToken,
       })
+      registerSecrets(ts.authKey, ts.apiToken)
       sessionStorage.setItem("tailscaleAuthKey", ts.authKey)
       sessionStorage.setItem("tailscaleApiToken", ts.apiToken)
     }
@@ -11,6 +11,7 @@ import { TkButton } from "thinkube-style/components/buttons-badges"
 import { TkBadge } from "thinkube-style/components/buttons-badges"
 import { TkPageWrapper } from "thinkube-style/components/utilities"
 import { CheckCircle2, XCircle, Info, Loader2, ChevronLeft, ChevronRight, AlertCircle } from "lucide-react"
+import { invoke } from "@tauri-apps/api/core"
 import axios from "@/utils/axios"
 
 interface Requirement {
@@ -22,11 +23,21 @@ interface Requirement {
   action?: 'install'
 }
 
+// Shape returned by the `get_host_environment` command
+interface HostEnvironment {
+  kind: 'bare_metal' | 'vm' | 'container' | 'wsl'
+  platform: string | null
+  kvm: boolean
+  can_be_node: boolean
+  message: string
+}
+
 export default function Requirements() {
   const navigate = useNavigate()
   const [requirements, setRequirements] = useState<Requirement[]>([])
   const [isLoading, setIsLoading] = useState(true)
   const [error, setError] = useState('')
+  const [environment, setEnvironment] = useState<HostEnvironment | null>(null)
 
   const systemRequirements = useMemo(() => {
     return requirements.filter(req => req.category === 'system')
@@ -52,6 +63,12 @@ export default function Requirements() {
     return hardRequirementsMet && hasToolsToInstall
   }, [hardRequirementsMet, hasToolsToInstall])
 
+  useEffect(() => {
+    invoke<HostEnvironment>('get_host_environment')
+      .then(setEnvironment)
+      .catch(() => setEnvironment(null))
+  }, [])
+
   useEffect(() => {
     const checkRequirements = async () => {
       const minLoadTime = new Promise(resolve => setTimeout(resolve, 500))
@@ -93,6 +110,20 @@ export default function Requirements() {
         </TkAlert>
       )}
 
+      {/* Only worth a note when this machine isn't bare metal with KVM */}
+      {environment && (environment.kind !== 'bare_metal' || !environment.kvm) && (
+        <TkAlert
+          className={
+            environment.can_be_node
+              ? "bg-warning/10 text-warning border-warning/20 mb-6"
+              : "bg-info/10 text-info border-info/20 mb-6"
+          }
+        >
+          {environment.can_be_node ? <AlertCircle className="h-4 w-4" /> : <Info className="h-4 w-4" />}
+          <TkAlertDescription>{environment.message}</TkAlertDescription>
+        </TkAlert>
+      )}
+
       {isLoading ? (
         <TkCard className="mb-6">
           <TkCardContent className="py-8">
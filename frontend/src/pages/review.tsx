@@ -7,7 +7,7 @@ import { useState, useEffect, useMemo } from "react"
 import { useNavigate } from "react-router-dom"
 import { TkCard, TkCardContent, TkCardHeader, TkCardTitle } from "thinkube-style/components/cards-data"
 import { TkButton } from "thinkube-style/components/buttons-badges"
-import { tkToast } from "thinkube-style/components/feedback"
+import { TkAlert, TkAlertDescription, tkToast } from "thinkube-style/components/feedback"
 import { TkBadge } from "thinkube-style/components/buttons-badges"
 import { TkPageWrapper } from "thinkube-style/components/utilities"
 import {
@@ -18,7 +18,9 @@ import {
   TkDialogHeader,
   TkDialogTitle
 } from "thinkube-style/components/modals-overlays"
-import { ChevronLeft, ChevronRight, Copy, Download, Eye } from "lucide-react"
+import { AlertCircle, BatteryWarning, ChevronLeft, ChevronRight, Copy, Download, Eye } from "lucide-react"
+import { invoke } from "@tauri-apps/api/core"
+import ScheduledInstallPicker from "@/components/scheduled-install"
 
 interface Node {
   id: string
@@ -41,6 +43,43 @@ interface Node {
   gpus?: any[]
 }
 
+// Shape returned by the `get_power_status` command
+interface PowerStatus {
+  source: "ac" | "battery" | "unknown"
+  has_battery: boolean
+  charge_percent: number | null
+  block: boolean
+  message: string | null
+}
+
+// Shape returned by the `validate_config` command
+interface ConfigValidation {
+  valid: boolean
+  errors: Array<{ path: string; code: string; message: string }>
+}
+
+// Shape returned by the `estimate_downloads` command
+interface DownloadEstimate {
+  items: Array<{
+    name: string
+    scope: "once" | "per_node" | "per_gpu_node"
+    count: number
+    bytes: number
+    source: "snap_store" | "github" | "head" | "estimate"
+  }>
+  total_bytes: number
+  throughput_kib_s: number | null
+  eta_secs: number | null
+}
+
+const formatBytes = (bytes: number) =>
+  bytes >= 1024 ** 3 ? `${(bytes / 1024 ** 3).toFixed(1)} GiB` : `${Math.round(bytes / 1024 ** 2)} MiB`
+
+const formatDuration = (secs: number) =>
+  secs >= 3600
+    ? `${Math.floor(secs / 3600)} h ${Math.round((secs % 3600) / 60)} min`
+    : `${Math.max(1, Math.round(secs / 60))} min`
+
 interface Config {
   clusterName?: string
   domainName?: string
@@ -56,6 +95,55 @@ export default function Review() {
   const [gpuAssignments, setGpuAssignments] = useState<Record<string, string>>({})
   const [generatedInventory, setGeneratedInventory] = useState("")
   const [inventoryModalOpen, setInventoryModalOpen] = useState(false)
+  const [power, setPower] = useState<PowerStatus | null>(null)
+  const [configErrors, setConfigErrors] = useState<ConfigValidation["errors"]>([])
+  const [downloads, setDownloads] = useState<DownloadEstimate | null>(null)
+
+  useEffect(() => {
+    const checkPower = () =>
+      invoke<PowerStatus>("get_power_status")
+        .then(setPower)
+        .catch(() => setPower(null))
+    checkPower()
+    // Follow the user plugging the charger in while on this page
+    const interval = setInterval(checkPower, 10000)
+    return () => clearInterval(interval)
+  }, [])
+
+  // Catch what the playbooks would only fail on halfway through
+  useEffect(() => {
+    if (allNodes.length === 0) return
+    invoke<ConfigValidation>("validate_config", {
+      json: {
+        config,
+        network: JSON.parse(sessionStorage.getItem("networkConfiguration") || "null"),
+        nodes: JSON.parse(sessionStorage.getItem("clusterNodes") || "[]").map((node: any) => ({
+          ...node,
+          hasGPU: allNodes.find((n) => n.hostname === node.hostname)?.hasGPU ?? node.hasGPU,
+        })),
+        gpu_assignments: gpuAssignments,
+        deployment_type: deploymentType,
+      },
+    })
+      .then((report) => setConfigErrors(report.errors))
+      .catch((error) => console.error("Failed to validate the configuration:", error))
+  }, [config, allNodes, gpuAssignments, deploymentType])
+
+  // What the install will download, before committing to it
+  useEffect(() => {
+    if (allNodes.length === 0) return
+    const mirrors = JSON.parse(sessionStorage.getItem("mirrorSelection") || "{}")
+    invoke<DownloadEstimate>("estimate_downloads", {
+      config: { nodes: allNodes },
+      mirror: mirrors.apt ?? null,
+    })
+      .then((estimate) => {
+        setDownloads(estimate)
+        // The deploy page's time estimate uses it
+        sessionStorage.setItem("downloadEstimate", JSON.stringify(estimate))
+      })
+      .catch((error) => console.error("Failed to estimate the downloads:", error))
+  }, [allNodes])
 
   const hasGPUs = useMemo(() => {
     return Object.keys(gpuAssignments).some(
@@ -209,7 +297,13 @@ export default function Review() {
     setInventoryModalOpen(true)
   }
 
-  const startDeployment = () => {
+  const startDeployment = async () => {
+    const status = await invoke<PowerStatus>("get_power_status").catch(() => null)
+    setPower(status)
+    if (status?.block) {
+      tkToast.error(status.message || "Plug this machine in to start the deployment")
+      return
+    }
     navigate("/deploy")
   }
 
@@ -366,6 +460,70 @@ export default function Review() {
         </TkCard>
       )}
 
+      {downloads && (
+        <TkCard className="mb-6">
+          <TkCardHeader>
+            <TkCardTitle>Downloads</TkCardTitle>
+          </TkCardHeader>
+          <TkCardContent className="space-y-3">
+            <p className="text-muted-foreground">
+              About {formatBytes(downloads.total_bytes)}
+              {downloads.eta_secs !== null && downloads.throughput_kib_s !== null
+                ? `, roughly ${formatDuration(downloads.eta_secs)} at the current ${(downloads.throughput_kib_s / 1024).toFixed(1)} MiB/s`
+                : "; the bandwidth couldn't be measured"}
+              .
+            </p>
+            <ul className="text-sm space-y-1">
+              {downloads.items.map((item) => (
+                <li key={item.name} className="flex justify-between gap-4">
+                  <span>
+                    {item.name}
+                    {item.count > 1 && ` × ${item.count}`}
+                  </span>
+                  <span className="text-muted-foreground">
+                    {item.source === "estimate" ? "~" : ""}
+                    {formatBytes(item.bytes)}
+                  </span>
+                </li>
+              ))}
+            </ul>
+          </TkCardContent>
+        </TkCard>
+      )}
+
+      {configErrors.length > 0 && (
+        <TkAlert className="bg-destructive/10 text-destructive border-destructive/20 mb-6">
+          <AlertCircle className="h-4 w-4" />
+          <TkAlertDescription>
+            <p className="font-medium mb-1">Fix these before deploying:</p>
+            <ul className="text-sm space-y-1">
+              {configErrors.map((error, index) => (
+                <li key={index}>
+                  <code className="text-xs">{error.path}</code> {error.message}
+                </li>
+              ))}
+            </ul>
+          </TkAlertDescription>
+        </TkAlert>
+      )}
+
+      {power?.message && (
+        <TkAlert
+          className={
+            power.block
+              ? "bg-destructive/10 text-destructive border-destructive/20 mb-6"
+              : "bg-warning/10 text-warning border-warning/20 mb-6"
+          }
+        >
+          <BatteryWarning className="h-4 w-4" />
+          <TkAlertDescription>{power.message}</TkAlertDescription>
+        </TkAlert>
+      )}
+
+      <div className="mb-6">
+        <ScheduledInstallPicker disabled={power?.block || configErrors.length > 0} />
+      </div>
+
       {/* Actions */}
       <div className="flex justify-between">
         <TkButton
@@ -377,7 +535,7 @@ export default function Review() {
           Back to Network Configuration
         </TkButton>
 
-        <TkButton className="gap-2" onClick={startDeployment}>
+        <TkButton className="gap-2" onClick={startDeployment} disabled={power?.block || configErrors.length > 0}>
           Start Deployment
           <ChevronRight className="w-5 h-5" />
         </TkButton>
@@ -18,6 +18,43 @@ import {
   TkSelectValue,
 } from "thinkube-style/components/forms-inputs"
 import { AlertTriangle, ChevronLeft, ChevronRight } from "lucide-react"
+import { invoke } from "@tauri-apps/api/core"
+
+// Shape returned by the `detect_boards` command
+interface BoardReport {
+  host: string
+  error?: string
+  raspberry_pi: boolean
+  model?: string
+  memory_mb: number
+  root_storage?: "sd_card" | "emmc" | "usb" | "nvme" | "other"
+  allowed_roles: Array<"control_plane" | "worker">
+  warnings: string[]
+}
+
+// Shape returned by the `list_topology_presets` command
+interface TopologyPreset {
+  id: string
+  name: string
+  description: string
+  min_nodes: number
+  max_nodes?: number
+  gpu: "any" | "required_on_control_plane" | "prefer_workers"
+  config: Record<string, unknown>
+  source: "builtin" | "user"
+}
+
+// Shape returned by the `suggest_topology` command
+interface TopologySuggestion {
+  nodes: Array<{
+    host: string
+    role: "control_plane" | "worker" | null
+    gpu_worker: boolean
+    storage: boolean
+    reasons: string[]
+  }>
+  warnings: string[]
+}
 
 interface NodeData {
   id: string
@@ -37,12 +74,17 @@ interface NodeData {
   role: string
   host?: string
   gpu?: any
+  board?: BoardReport
 }
 
 export default function RoleAssignment() {
   const navigate = useNavigate()
   const [allNodes, setAllNodes] = useState<NodeData[]>([])
   const [validationErrors, setValidationErrors] = useState<string[]>([])
+  const [presets, setPresets] = useState<TopologyPreset[]>([])
+  const [presetId, setPresetId] = useState(sessionStorage.getItem('topologyPreset') || '')
+  const [presetWarnings, setPresetWarnings] = useState<string[]>([])
+  const [suggestion, setSuggestion] = useState<TopologySuggestion | null>(null)
 
   const baremetalNodes = useMemo(() => {
     return allNodes.filter(n => n.type === 'baremetal')
@@ -60,8 +102,12 @@ export default function RoleAssignment() {
     return validationErrors.length === 0 && controlPlaneNodes.length > 0
   }, [validationErrors, controlPlaneNodes])
 
+  const allowsRole = (node: NodeData, role: "control_plane" | "worker") => {
+    return !node.board || node.board.allowed_roles.includes(role)
+  }
+
   const canBeControlPlane = (node: NodeData) => {
-    return node.cpu >= 4 && node.memory >= 8
+    return node.cpu >= 4 && node.memory >= 8 && allowsRole(node, 'control_plane')
   }
 
   const getNodeGPUStatus = (node: NodeData) => {
@@ -72,6 +118,66 @@ export default function RoleAssignment() {
     return `${totalGPUs} GPU${totalGPUs > 1 ? 's' : ''}`
   }
 
+  // Assign roles the way the preset describes and prefill its settings
+  const applyPreset = (id: string) => {
+    const preset = presets.find(p => p.id === id)
+    setPresetId(id)
+    // Its reasons are about the suggested roles, not the preset's
+    setSuggestion(null)
+    if (!preset) {
+      sessionStorage.removeItem('topologyPreset')
+      setPresetWarnings([])
+      return
+    }
+    sessionStorage.setItem('topologyPreset', preset.id)
+
+    const warnings: string[] = []
+    const nodes = allNodes.map(n => ({ ...n, role: '' }))
+    const candidates = nodes.filter(canBeControlPlane)
+    const controlPlane =
+      preset.gpu === 'prefer_workers'
+        ? candidates.find(n => !n.hasGPU) || candidates[0]
+        : preset.gpu === 'required_on_control_plane'
+          ? candidates.find(n => n.hasGPU)
+          : candidates[0]
+    if (controlPlane) {
+      controlPlane.role = 'control_plane'
+    } else {
+      warnings.push(
+        preset.gpu === 'required_on_control_plane'
+          ? 'No server with a GPU can run the control plane'
+          : 'No server can run the control plane'
+      )
+    }
+    if (preset.gpu === 'prefer_workers' && controlPlane?.hasGPU) {
+      warnings.push('Every eligible control plane has a GPU; one of them will not be a dedicated GPU worker')
+    }
+
+    const maxWorkers = preset.max_nodes ? preset.max_nodes - 1 : Infinity
+    let workers = 0
+    nodes.forEach(node => {
+      if (!node.role && workers < maxWorkers && allowsRole(node, 'worker')) {
+        node.role = 'worker'
+        workers++
+      }
+    })
+    if (nodes.length < preset.min_nodes) {
+      warnings.push(`${preset.name} needs at least ${preset.min_nodes} servers; ${nodes.length} were found`)
+    }
+    if (preset.max_nodes && nodes.length > preset.max_nodes) {
+      warnings.push(`${preset.name} uses ${preset.max_nodes} servers; go back and deselect the others`)
+    }
+    setAllNodes(nodes)
+    setPresetWarnings(warnings)
+
+    // Only fills settings the user hasn't chosen yet
+    const existing = JSON.parse(localStorage.getItem('thinkube-config') || '{}')
+    const prefill = Object.fromEntries(
+      Object.entries(preset.config).filter(([key]) => existing[key] === undefined || existing[key] === '')
+    )
+    localStorage.setItem('thinkube-config', JSON.stringify({ ...existing, ...prefill }))
+  }
+
   const validateRoles = () => {
     const errors: string[] = []
 
@@ -139,25 +245,76 @@ export default function RoleAssignment() {
 
     setAllNodes(baremetalList)
 
-    const eligibleForCP = baremetalList.filter((n: NodeData) => canBeControlPlane(n))
+    const suggestRoles = (nodes: NodeData[]) => {
+      const eligibleForCP = nodes.filter((n: NodeData) => canBeControlPlane(n))
 
-    if (eligibleForCP.length > 0) {
-      eligibleForCP[0].role = 'control_plane'
+      if (eligibleForCP.length > 0) {
+        eligibleForCP[0].role = 'control_plane'
+      }
+
+      nodes.forEach((node: NodeData) => {
+        if (!node.role && node.hostname !== 'dns' && (node.type === 'baremetal' || node.cpu >= 2) && allowsRole(node, 'worker')) {
+          node.role = 'worker'
+        }
+      })
+
+      setAllNodes([...nodes])
     }
 
-    baremetalList.forEach((node: NodeData) => {
-      if (!node.role && node.hostname !== 'dns' && (node.type === 'baremetal' || node.cpu >= 2)) {
-        node.role = 'worker'
-      }
+    // Raspberry Pis can't carry the control plane, and small ones not even a worker
+    const discoveredServers = JSON.parse(sessionStorage.getItem('discoveredServers') || '[]')
+    invoke<BoardReport[]>('detect_boards', {
+      hosts: baremetalList.map((n: NodeData) => ({
+        host: n.ip,
+        user: discoveredServers.find((s: any) => s.ip === n.ip)?.username || '',
+        port: null,
+        identity_file: null
+      }))
     })
-
-    setAllNodes(baremetalList)
+      .then((reports) => {
+        baremetalList.forEach((node: NodeData) => {
+          node.board = reports.find((r) => r.host === node.ip && !r.error)
+        })
+      })
+      .catch((error) => console.error('Failed to detect boards:', error))
+      .finally(() => {
+        // The planner knows the storage and GPU rules; the local fallback
+        // only places the control plane
+        invoke<TopologySuggestion>('suggest_topology', {
+          nodes: baremetalList.map((n: NodeData) => ({
+            host: n.ip,
+            hostname: n.hostname,
+            cpu_cores: n.cpu,
+            memory_gb: n.memory,
+            disk_gb: n.disk,
+            gpu_count: n.hasGPU ? n.gpuInfo?.gpu_count || 1 : 0,
+            allowed_roles: n.board?.allowed_roles ?? null,
+          })),
+        })
+          .then((result) => {
+            baremetalList.forEach((node: NodeData) => {
+              node.role = result.nodes.find((s) => s.host === node.ip)?.role || ''
+            })
+            setAllNodes([...baremetalList])
+            setSuggestion(result)
+          })
+          .catch((error) => {
+            console.error('Failed to suggest a topology:', error)
+            suggestRoles(baremetalList)
+          })
+      })
   }, [])
 
   useEffect(() => {
     validateRoles()
   }, [allNodes])
 
+  useEffect(() => {
+    invoke<{ presets: TopologyPreset[] }>('list_topology_presets')
+      .then((list) => setPresets(list.presets))
+      .catch((error) => console.error('Failed to list topology presets:', error))
+  }, [])
+
   return (
     <TkPageWrapper title="Kubernetes Role Assignment">
       <TkCard className="mb-6">
@@ -190,9 +347,41 @@ export default function RoleAssignment() {
         </TkCardContent>
       </TkCard>
 
+      {presets.length > 0 && allNodes.length > 0 && (
+        <TkCard className="mb-6">
+          <TkCardContent className="pt-6">
+            <h2 className="text-xl font-semibold mb-4">Topology Preset</h2>
+            <TkSelect value={presetId || "none"} onValueChange={(value: string) => applyPreset(value === "none" ? "" : value)}>
+              <TkSelectTrigger className="w-full md:w-[360px]">
+                <TkSelectValue placeholder="Assign roles myself" />
+              </TkSelectTrigger>
+              <TkSelectContent>
+                <TkSelectItem value="none">Assign roles myself</TkSelectItem>
+                {presets.map((preset) => (
+                  <TkSelectItem key={preset.id} value={preset.id}>
+                    {preset.name}{preset.source === "user" ? " (custom)" : ""}
+                  </TkSelectItem>
+                ))}
+              </TkSelectContent>
+            </TkSelect>
+            {presets.find(p => p.id === presetId)?.description && (
+              <p className="text-sm text-muted-foreground mt-2">
+                {presets.find(p => p.id === presetId)?.description}
+              </p>
+            )}
+            {presetWarnings.map((warning, index) => (
+              <p key={index} className="text-sm text-warning mt-1">{warning}</p>
+            ))}
+          </TkCardContent>
+        </TkCard>
+      )}
+
       <TkCard className="mb-6">
         <TkCardContent className="pt-6">
           <h2 className="text-xl font-semibold mb-4">Assign Roles to Nodes</h2>
+          {suggestion?.warnings.map((warning, index) => (
+            <p key={index} className="text-sm text-warning mb-2">{warning}</p>
+          ))}
 
           <div className="space-y-4">
             {baremetalNodes.length > 0 && (
@@ -215,6 +404,17 @@ export default function RoleAssignment() {
                               </TkBadge>
                             )}
                           </p>
+                          {node.board?.raspberry_pi && (
+                            <p className="text-xs text-muted-foreground">{node.board.model}</p>
+                          )}
+                          {node.board?.warnings.map((warning, index) => (
+                            <p key={index} className="text-xs text-warning">{warning}</p>
+                          ))}
+                          {suggestion?.nodes
+                            .find((s) => s.host === node.ip)
+                            ?.reasons.map((reason, index) => (
+                              <p key={index} className="text-xs text-muted-foreground">{reason}</p>
+                            ))}
                         </div>
                       </div>
 
@@ -242,7 +442,9 @@ export default function RoleAssignment() {
                           </TkSelectTrigger>
                           <TkSelectContent>
                             <TkSelectItem value="none">No Role</TkSelectItem>
-                            <TkSelectItem value="worker">Worker</TkSelectItem>
+                            <TkSelectItem value="worker" disabled={!allowsRole(node, 'worker')}>
+                              Worker
+                            </TkSelectItem>
                             <TkSelectItem
                               value="control_plane"
                               disabled={!canBeControlPlane(node) || (controlPlaneNodes.length > 0 && node.role !== 'control_plane')}
@@ -17,6 +17,7 @@ import {
   RefreshCw
 } from "lucide-react"
 import { PlaybookExecutorStream } from "@/components/PlaybookExecutorStream"
+import HostKeys from "@/components/host-keys"
 
 interface Server {
   hostname: string
@@ -214,6 +215,16 @@ export default function SSHSetup() {
         </TkCardContent>
       </TkCard>
 
+      {/* Host keys */}
+      <TkCard className="mb-6">
+        <TkCardHeader>
+          <TkCardTitle>Host Keys</TkCardTitle>
+        </TkCardHeader>
+        <TkCardContent>
+          <HostKeys hosts={servers.map((server) => server.ip)} />
+        </TkCardContent>
+      </TkCard>
+
       {/* Discovered Servers */}
       <TkCard className="mb-6">
         <TkCardHeader>
@@ -13,6 +13,7 @@ import { TkLabel } from "thinkube-style/components/forms-inputs"
 import { TkPageWrapper } from "thinkube-style/components/utilities"
 import { Info, ChevronLeft, Eye, EyeOff, Loader2 } from "lucide-react"
 import axios from "@/utils/axios"
+import { registerSecrets } from "@/lib/secrets"
 
 export default function SudoPassword() {
   const navigate = useNavigate()
@@ -45,6 +46,7 @@ export default function SudoPassword() {
     setError("")
 
     try {
+      await registerSecrets(sudoPassword)
       // Verify the sudo password
       const response = await axios.post("/api/verify-sudo", {
         password: sudoPassword
@@ -3,14 +3,20 @@
  * SPDX-License-Identifier: Apache-2.0
  */
 
+import { useState } from "react"
 import { useNavigate } from "react-router-dom"
 import { TkCard, TkCardContent, TkCardFooter, TkCardHeader } from "thinkube-style/components/cards-data"
 import { TkAlert, TkAlertDescription } from "thinkube-style/components/feedback"
 import { TkButton } from "thinkube-style/components/buttons-badges"
 import { Info, CheckCircle2, ChevronRight } from "lucide-react"
+import ConnectivityStatus from "@/components/connectivity-status"
+import PlatformCompatibilityStatus from "@/components/platform-compatibility"
+import OfflineBundle from "@/components/offline-bundle"
 
 export default function Welcome() {
   const navigate = useNavigate()
+  // An installer the platform release doesn't support can't start
+  const [blocked, setBlocked] = useState(false)
 
   return (
     <div className="max-w-7xl mx-auto px-6 py-8 flex items-center justify-center h-full">
@@ -40,6 +46,10 @@ export default function Welcome() {
               </TkAlertDescription>
             </TkAlert>
 
+            <ConnectivityStatus />
+
+            <PlatformCompatibilityStatus onChange={(c) => setBlocked(c.verdict === "unsupported")} />
+
             <div className="space-y-3 text-left">
               <div className="flex items-center gap-3">
                 <CheckCircle2 className="h-6 w-6 text-success flex-shrink-0" />
@@ -54,6 +64,8 @@ export default function Welcome() {
                 <span className="font-medium">Automated deployment</span>
               </div>
             </div>
+
+            <OfflineBundle />
           </TkCardContent>
 
           <TkCardFooter className="justify-center">
@@ -61,6 +73,7 @@ export default function Welcome() {
               size="lg"
               className="gap-2"
               onClick={() => navigate('/requirements')}
+              disabled={blocked}
             >
               Get Started
               <ChevronRight className="h-5 w-5" />
@@ -4,12 +4,31 @@
  */
 
 import axios from 'axios'
+import { invoke } from '@tauri-apps/api/core'
+
+// Shared secret the backend requires on every call, from the Rust shell.
+// Outside Tauri (backend started by hand for development) there is none.
+let apiTokenPromise = null
+
+export const getApiToken = () => {
+  if (!apiTokenPromise) {
+    apiTokenPromise = invoke('get_api_token').catch(() => '')
+  }
+  return apiTokenPromise
+}
+
+// WebSockets can't send headers from the browser, so the token goes in the URL
+export const withApiToken = async (url) => {
+  const token = await getApiToken()
+  if (!token) return url
+  return `${url}${url.includes('?') ? '&' : '?'}token=${encodeURIComponent(token)}`
+}
 
 // Configure axios defaults
 // Tauri v2 uses tauri: protocol, not window.__TAURI__
 const isTauri = window.location.protocol === 'tauri:'
 const baseURL = isTauri || (window.location.protocol === 'http:' && window.location.hostname === 'localhost')
-  ? 'http://localhost:8000'
+  ? 'http://127.0.0.1:8000'
   : ''
 
 console.log('=== AXIOS CONFIG DEBUG ===')
@@ -31,7 +50,11 @@ const axiosInstance = axios.create({
 
 // Request interceptor to ensure /api prefix
 axiosInstance.interceptors.request.use(
-  (config) => {
+  async (config) => {
+    const token = await getApiToken()
+    if (token) {
+      config.headers.Authorization = `Bearer ${token}`
+    }
     // Ensure all requests have /api prefix
     if (config.url && !config.url.startsWith('/api')) {
       config.url = `/api${config.url}`
@@ -4,6 +4,25 @@
  */
 
 import axios from 'axios'
+import { invoke } from '@tauri-apps/api/core'
+
+// Shared secret the backend requires on every call, from the Rust shell.
+// Outside Tauri (backend started by hand for development) there is none.
+let apiTokenPromise: Promise<string> | null = null
+
+export const getApiToken = (): Promise<string> => {
+  if (!apiTokenPromise) {
+    apiTokenPromise = invoke<string>('get_api_token').catch(() => '')
+  }
+  return apiTokenPromise
+}
+
+// WebSockets can't send headers from the browser, so the token goes in the URL
+export const withApiToken = async (url: string): Promise<string> => {
+  const token = await getApiToken()
+  if (!token) return url
+  return `${url}${url.includes('?') ? '&' : '?'}token=${encodeURIComponent(token)}`
+}
 
 // Configure axios defaults
 // Only run on client side
@@ -16,7 +35,7 @@ const getBaseURL = () => {
   const isTauri = window.location.protocol === 'tauri:' || window.location.protocol === 'asset:'
   const isLocalhost = window.location.protocol === 'http:' && window.location.hostname === 'localhost'
 
-  const baseURL = isTauri || isLocalhost ? 'http://localhost:8000' : ''
+  const baseURL = isTauri || isLocalhost ? 'http://127.0.0.1:8000' : ''
 
   console.log('=== AXIOS CONFIG DEBUG ===')
   console.log('window.location.protocol:', window.location.protocol)
@@ -39,7 +58,11 @@ const axiosInstance = axios.create({
 
 // Request interceptor to ensure /api prefix
 axiosInstance.interceptors.request.use(
-  (config) => {
+  async (config) => {
+    const token = await getApiToken()
+    if (token) {
+      config.headers.Authorization = `Bearer ${token}`
+    }
     // Ensure all requests have /api prefix
     if (config.url && !config.url.startsWith('/api')) {
       config.url = `/api${config.url}`
@@ -243,6 +243,37 @@ export function generateDynamicInventory() {
   }
   inventory.all.vars.container_build_platforms = platformMap[buildArchitecture]
 
+  // Mirrors measured and picked on the network configuration page; the
+  // public snap store and Docker Hub are the defaults, so only others are set
+  const mirrorSelection = JSON.parse(sessionStorage.getItem('mirrorSelection') || '{}')
+  if (mirrorSelection.apt) {
+    inventory.all.vars.apt_mirror = mirrorSelection.apt
+  }
+  if (mirrorSelection.snap && mirrorSelection.snap !== 'https://api.snapcraft.io') {
+    inventory.all.vars.snap_store_proxy = mirrorSelection.snap
+  }
+  if (mirrorSelection.registry && mirrorSelection.registry !== 'https://registry-1.docker.io') {
+    inventory.all.vars.registry_mirror = mirrorSelection.registry
+  }
+
+  // Keyboard layout for the nodes' consoles, detected on this machine or
+  // chosen on the configuration page
+  const keyboardLayout = JSON.parse(sessionStorage.getItem('keyboardLayout') || 'null')
+  if (keyboardLayout?.layout) {
+    inventory.all.vars.keyboard_layout = keyboardLayout.layout
+    inventory.all.vars.keyboard_variant = keyboardLayout.variant || ''
+    inventory.all.vars.keyboard_model = keyboardLayout.model || 'pc105'
+  }
+
+  // Dual-stack settings, only once the IPv6 readiness check passed on
+  // every node and for both planned CIDRs
+  const ipv6Readiness = JSON.parse(sessionStorage.getItem('ipv6Readiness') || 'null')
+  if (ipv6Readiness?.dual_stack) {
+    inventory.all.vars.dual_stack = true
+    inventory.all.vars.pod_cidr_v6 = ipv6Readiness.pod_cidr
+    inventory.all.vars.service_cidr_v6 = ipv6Readiness.service_cidr
+  }
+
   // Add baremetal servers from network configuration
   const discoveredServers = JSON.parse(sessionStorage.getItem('discoveredServers') || '[]')
 
@@ -288,6 +319,17 @@ export function generateDynamicInventory() {
     if (discoveredServer?.is_local) {
       serverDef.ansible_connection = 'local'
     }
+
+    const ipv6Address = ipv6Readiness?.dual_stack && ipv6Readiness.addresses?.[server.ip]
+    if (ipv6Address) {
+      serverDef.ipv6_address = ipv6Address
+    }
+
+    // Nodes only reachable through a bastion, as `[user@]host[:port]`
+    const jumpHost = discoveredServer?.jump_host
+    if (jumpHost && !discoveredServer?.is_local) {
+      serverDef.ansible_ssh_common_args = `-o ProxyJump=${jumpHost}`
+    }
     // REMOVED: Do not assume first server is local - this causes issues
     // when the installer runs on a different machine
     
@@ -43,6 +43,10 @@ export function generateMinimalInventory() {
       ansible_user: currentUser,
       display_hostname: server.hostname || server.host || server.name || hostname
     }
+    // Nodes only reachable through a bastion, as `[user@]host[:port]`
+    if (server.jump_host) {
+      inventory.all.children.baremetal.hosts[hostname].ansible_ssh_common_args = `-o ProxyJump=${server.jump_host}`
+    }
   })
   
   return inventory
@@ -75,6 +79,9 @@ export function minimalInventoryToYAML(inventory) {
     yaml += `        ${hostname}:\n`
     yaml += `          ansible_host: ${hostConfig.ansible_host}\n`
     yaml += `          ansible_user: ${hostConfig.ansible_user}\n`
+    if (hostConfig.ansible_ssh_common_args) {
+      yaml += `          ansible_ssh_common_args: "${hostConfig.ansible_ssh_common_args}"\n`
+    }
   }
   
   return yaml